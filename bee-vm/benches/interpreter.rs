@@ -0,0 +1,253 @@
+use bee_vm::opcodes::arithmetic_ops::add::add;
+use bee_vm::opcodes::crypto_ops::check_sig::op_checksig_with_context;
+use bee_vm::opcodes::crypto_ops::ripe_md_160::hash_160;
+use bee_vm::opcodes::crypto_ops::sha_256::sha_256;
+use bee_vm::opcodes::stack_ops::dup::dup;
+use bee_vm::opcodes::stack_ops::pick::pick;
+use bee_vm::opcodes::stack_ops::roll::roll;
+use bee_vm::processor::input::split_tokens;
+use bee_vm::script::decode::bytes_to_tokens;
+use bee_vm::script::encode::{push_data, tokens_to_bytes};
+use bee_vm::stack::executor::execute_code_with_stack;
+use bee_vm::stack::Stack;
+use bee_vm::transaction::context::ExecutionContext;
+use bee_vm::transaction::sigcache::signature_cache;
+use bee_vm::transaction::sighash::SigVersion;
+use bee_vm::transaction::verify::verify_input;
+use bee_vm::transaction::{Transaction, TxOut};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::ecdsa::{Signature, SigningKey};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+// Unsigned BIP143 native P2WPKH example, its inputs are given the benchmarked outputs.
+const TX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+fn signing_key(seed: u8) -> SigningKey {
+    SigningKey::from_slice(&[seed; 32]).unwrap()
+}
+
+fn public_key(key: &SigningKey) -> Vec<u8> {
+    key.verifying_key().to_sec1_bytes().to_vec()
+}
+
+fn output(script_pubkey: Vec<u8>) -> TxOut {
+    TxOut {
+        value: 100_000,
+        script_pubkey,
+    }
+}
+
+/// Legacy DER signature of input 0 spending `script_pubkey`, with SIGHASH_ALL appended.
+fn sign(key: &SigningKey, tx: &Transaction, script_pubkey: &[u8]) -> Vec<u8> {
+    let context = ExecutionContext::new(
+        tx.clone(),
+        0,
+        output(script_pubkey.to_vec()),
+        SigVersion::Base,
+    )
+    .unwrap();
+    let hash = context
+        .signature_hash(script_pubkey, 0x01, u32::MAX)
+        .unwrap();
+    let signature: Signature = key.sign_prehash(&hash).unwrap();
+    let mut signature = signature.to_der().as_bytes().to_vec();
+    signature.push(0x01);
+    signature
+}
+
+/// Transaction whose input 0 spends `script_pubkey` with a scriptSig pushing `items`, and the
+/// outputs spent by its inputs.
+fn spend(script_pubkey: Vec<u8>, items: Vec<Vec<u8>>) -> (Transaction, Vec<TxOut>) {
+    let mut tx = Transaction::from_hex(TX).unwrap();
+    for item in items {
+        push_data(&mut tx.inputs[0].script_sig, &item).unwrap();
+    }
+    let prevouts = vec![output(script_pubkey), output(vec![0x51])];
+    (tx, prevouts)
+}
+
+fn p2pkh() -> (Transaction, Vec<TxOut>) {
+    let key = signing_key(1);
+    let hash = Ripemd160::digest(Sha256::digest(public_key(&key)));
+    let script_pubkey = tokens_to_bytes(&split_tokens(&format!(
+        "OP_DUP OP_HASH160 {} OP_EQUALVERIFY OP_CHECKSIG",
+        hex::encode(hash)
+    )))
+    .unwrap();
+    let tx = Transaction::from_hex(TX).unwrap();
+    let signature = sign(&key, &tx, &script_pubkey);
+    spend(script_pubkey, vec![signature, public_key(&key)])
+}
+
+/// Bare 3-of-5 multisig signed by the first, third and fifth keys.
+fn multisig() -> (Transaction, Vec<TxOut>) {
+    let keys: Vec<SigningKey> = (1..=5).map(signing_key).collect();
+    let public_keys: Vec<String> = keys
+        .iter()
+        .map(|key| hex::encode(public_key(key)))
+        .collect();
+    let script_pubkey = tokens_to_bytes(&split_tokens(&format!(
+        "3 {} 5 OP_CHECKMULTISIG",
+        public_keys.join(" ")
+    )))
+    .unwrap();
+    let tx = Transaction::from_hex(TX).unwrap();
+    let mut items = vec![vec![]];
+    for key in [&keys[0], &keys[2], &keys[4]] {
+        items.push(sign(key, &tx, &script_pubkey));
+    }
+    spend(script_pubkey, items)
+}
+
+fn verify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verify");
+    for (name, (tx, prevouts)) in [("p2pkh", p2pkh()), ("multisig_3_of_5", multisig())] {
+        verify_input(&tx, 0, &prevouts).unwrap();
+        // Every signature verified again, then found in the signature cache.
+        group.bench_function(format!("{}/cold", name), |b| {
+            b.iter_batched(
+                || signature_cache().clear(),
+                |_| verify_input(black_box(&tx), 0, &prevouts).unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+        group.bench_function(format!("{}/cached", name), |b| {
+            b.iter(|| verify_input(black_box(&tx), 0, &prevouts).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Picks and rolls the bottom half of a stack of `depth` items, once per item.
+fn shuffle_script(depth: usize) -> String {
+    let items: Vec<String> = (1..=depth).map(|i| i.to_string()).collect();
+    let n = depth / 2;
+    format!(
+        "{} {}",
+        items.join(" "),
+        format!("{n} OP_PICK OP_DROP {n} OP_ROLL ").repeat(depth)
+    )
+}
+
+/// `ops` additions and subtractions on a counter.
+fn arithmetic_script(ops: usize) -> String {
+    format!("0 {}", "3 OP_ADD 1 OP_SUB ".repeat(ops / 2))
+}
+
+/// Chains the five hash opcodes `rounds` times.
+fn hash_script(rounds: usize) -> String {
+    format!(
+        "68656c6c6f {}",
+        "OP_SHA256 OP_HASH160 OP_HASH256 OP_RIPEMD160 OP_SHA1 ".repeat(rounds)
+    )
+}
+
+/// BitVM style 32 bit additions on 4 bit limbs : each limb is added with the carry, reduced
+/// in a conditional and moved to the alt stack.
+fn limb_addition_script(rounds: usize) -> String {
+    let limb = "OP_ADD OP_ADD OP_DUP 16 OP_GREATERTHANOREQUAL \
+                OP_IF 16 OP_SUB 1 OP_ELSE 0 OP_ENDIF OP_SWAP OP_TOALTSTACK";
+    let mut script = String::new();
+    for round in 0..rounds {
+        script.push_str(" 0");
+        for i in 0..8 {
+            script.push_str(&format!(
+                " {} {} {}",
+                (round + i) % 16,
+                (round * i) % 16,
+                limb
+            ));
+        }
+        script.push_str(&" OP_FROMALTSTACK OP_DROP".repeat(8));
+        script.push_str(" OP_DROP");
+    }
+    script
+}
+
+fn executor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("executor");
+    let scripts = [
+        ("pick_roll", shuffle_script(200)),
+        ("arithmetic", arithmetic_script(1000)),
+        ("hash", hash_script(100)),
+        ("bitvm_limb_addition", limb_addition_script(100)),
+    ];
+    for (name, script) in scripts {
+        let script = split_tokens(&script);
+        execute_code_with_stack(&script, Stack::new()).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new(name, script.len()),
+            &script,
+            |b, script| {
+                b.iter(|| execute_code_with_stack(black_box(script), Stack::new()).unwrap())
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Runs one opcode function on a fresh copy of `stack`.
+fn bench_opcode<F>(c: &mut Criterion, name: &str, stack: Stack, opcode: F)
+where
+    F: Fn(&mut Stack),
+{
+    c.bench_function(&format!("opcode/{}", name), |b| {
+        b.iter_batched(
+            || stack.clone(),
+            |mut stack| opcode(&mut stack),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn opcodes(c: &mut Criterion) {
+    let items = |script: &str| {
+        let (stack, _) = execute_code_with_stack(&split_tokens(script), Stack::new()).unwrap();
+        stack
+    };
+    let deep = (1..=100)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    bench_opcode(c, "OP_ADD", items("1000 2000"), |stack| add(stack).unwrap());
+    bench_opcode(c, "OP_DUP", items("68656c6c6f"), |stack| {
+        dup(stack, 1).unwrap()
+    });
+    bench_opcode(c, "OP_PICK", items(&format!("{} 50", deep)), |stack| {
+        pick(stack).unwrap()
+    });
+    bench_opcode(c, "OP_ROLL", items(&format!("{} 50", deep)), |stack| {
+        roll(stack).unwrap()
+    });
+    bench_opcode(c, "OP_SHA256", items("68656c6c6f"), |stack| {
+        sha_256(stack).unwrap()
+    });
+    bench_opcode(c, "OP_HASH160", items("68656c6c6f"), |stack| {
+        hash_160(stack).unwrap()
+    });
+
+    let (tx, prevouts) = p2pkh();
+    let context =
+        ExecutionContext::new(tx.clone(), 0, prevouts[0].clone(), SigVersion::Base).unwrap();
+    let script_code = bytes_to_tokens(&prevouts[0].script_pubkey).unwrap();
+    let (signature, key) = {
+        let script_sig = bytes_to_tokens(&tx.inputs[0].script_sig).unwrap();
+        (script_sig[0].clone(), script_sig[1].clone())
+    };
+    bench_opcode(
+        c,
+        "OP_CHECKSIG",
+        items(&format!("{} {}", signature, key)),
+        |stack| {
+            // Clearing the one entry cache costs nothing next to the verification.
+            signature_cache().clear();
+            op_checksig_with_context(stack, &script_code, u32::MAX, &context).unwrap()
+        },
+    );
+}
+
+criterion_group!(benches, verify, executor, opcodes);
+criterion_main!(benches);
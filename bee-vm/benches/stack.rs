@@ -0,0 +1,60 @@
+use bee_vm::processor::input::split_tokens;
+use bee_vm::stack::executor::execute_code_with_stack;
+use bee_vm::stack::Stack;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Stack depths of the large script benchmarks.
+const DEPTHS: [usize; 3] = [10, 100, 1000];
+
+fn push_pop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stack/push_pop");
+    for depth in DEPTHS {
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, &depth| {
+            b.iter(|| {
+                let mut stack = Stack::new();
+                for i in 0..depth {
+                    stack.push_to_top(i.to_string());
+                }
+                while let Some(item) = stack.pop_from_top() {
+                    black_box(item);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Fills the stack then duplicates and drops its top item once per stack item.
+fn dup_drop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stack/dup_drop");
+    for depth in DEPTHS {
+        let script = split_tokens(&format!(
+            "{}{}",
+            "1 ".repeat(depth),
+            "OP_DUP OP_DROP ".repeat(depth)
+        ));
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &script, |b, script| {
+            b.iter(|| execute_code_with_stack(black_box(script), Stack::new()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Fills the stack then rolls its bottom item to the top once per stack item.
+fn roll(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stack/roll");
+    for depth in DEPTHS {
+        let script = split_tokens(&format!(
+            "{}{}",
+            "1 ".repeat(depth),
+            format!("{} OP_ROLL ", depth - 1).repeat(depth)
+        ));
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &script, |b, script| {
+            b.iter(|| execute_code_with_stack(black_box(script), Stack::new()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, push_pop, dup_drop, roll);
+criterion_main!(benches);
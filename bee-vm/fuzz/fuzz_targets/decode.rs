@@ -0,0 +1,17 @@
+#![no_main]
+
+use bee_vm::script::analyze::analyze;
+use bee_vm::script::decode::bytes_to_tokens;
+use bee_vm::script::encode::tokens_to_bytes;
+use bee_vm::stack::symbolic::execute_symbolic;
+use libfuzzer_sys::fuzz_target;
+
+// Raw script bytes, as found in transactions : decoding and the static analyses.
+fuzz_target!(|script: &[u8]| {
+    let Ok(tokens) = bytes_to_tokens(script) else {
+        return;
+    };
+    let _ = tokens_to_bytes(&tokens);
+    let _ = analyze(script, None);
+    let _ = execute_symbolic(script);
+});
@@ -0,0 +1,30 @@
+#![no_main]
+
+use bee_vm::script::generate::ArbitraryScript;
+use bee_vm::stack::executor::{execute_code_with_context, execute_code_with_stack};
+use bee_vm::stack::Stack;
+use bee_vm::transaction::context::ExecutionContext;
+use bee_vm::transaction::sighash::SigVersion;
+use bee_vm::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use libfuzzer_sys::fuzz_target;
+
+// Well-formed scripts from a fuzzed initial stack, alone and spending a segwit input.
+fuzz_target!(|input: (Vec<Vec<u8>>, ArbitraryScript, u32, u32)| {
+    let (witness, script, sequence, lock_time) = input;
+    let stack = Stack::from_data(&witness);
+    let _ = execute_code_with_stack(&script.0, stack.clone());
+
+    let tx = Transaction {
+        version: 2,
+        inputs: vec![TxIn::new(OutPoint::default(), sequence)],
+        outputs: vec![],
+        lock_time,
+    };
+    let spent_output = TxOut {
+        value: 0,
+        script_pubkey: vec![],
+    };
+    if let Ok(context) = ExecutionContext::new(tx, 0, spent_output, SigVersion::WitnessV0) {
+        let _ = execute_code_with_context(&script.0, stack, &context);
+    }
+});
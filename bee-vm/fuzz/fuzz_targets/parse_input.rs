@@ -0,0 +1,16 @@
+#![no_main]
+
+use bee_vm::processor::input::parse_input;
+use bee_vm::script::analyze::analyze_tokens;
+use bee_vm::stack::executor::execute_code_with_stack;
+use bee_vm::stack::Stack;
+use libfuzzer_sys::fuzz_target;
+
+// Scripts as typed on the command line : any text token reaches the interpreter.
+fuzz_target!(|input: &str| {
+    let Ok(tokens) = parse_input(input.to_string()) else {
+        return;
+    };
+    let _ = analyze_tokens(&tokens, Some(0));
+    let _ = execute_code_with_stack(&tokens, Stack::new());
+});
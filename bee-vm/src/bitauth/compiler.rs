@@ -0,0 +1,309 @@
+use crate::bitauth::keys::TestKeys;
+use crate::bitauth::template::{AuthenticationTemplate, VariableType};
+use crate::errors::BitauthError;
+use crate::opcodes::opcode::Opcode;
+use crate::opcodes::utils::script_num_to_bytes;
+use crate::script::encode::tokens_to_bytes;
+use k256::ecdsa::signature::Signer;
+
+/// A single parsed element of a Bitauth (CashAssembly) script.
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Opcode(String),
+    Push(Vec<Item>),
+    Hex(Vec<u8>),
+    Number(i64),
+    Text(String),
+    Identifier(String),
+}
+
+/// Compiles the scripts of a template into Bee VM tokens.
+pub struct Compiler<'a> {
+    template: &'a AuthenticationTemplate,
+    keys: &'a TestKeys,
+}
+
+impl<'a> Compiler<'a> {
+    pub fn new(template: &'a AuthenticationTemplate, keys: &'a TestKeys) -> Self {
+        Compiler { template, keys }
+    }
+
+    /// Compiles the script with the given id into the token list executed by Bee VM.
+    pub fn compile(&self, script_id: &str) -> Result<Vec<String>, BitauthError> {
+        self.compile_with_trail(script_id, &mut vec![])
+    }
+
+    fn compile_with_trail(
+        &self,
+        script_id: &str,
+        trail: &mut Vec<String>,
+    ) -> Result<Vec<String>, BitauthError> {
+        if trail.iter().any(|id| id == script_id) {
+            return Err(BitauthError::CircularReference(script_id.to_string()));
+        }
+        let script = self
+            .template
+            .scripts
+            .get(script_id)
+            .ok_or(BitauthError::ScriptNotFound(script_id.to_string()))?;
+
+        trail.push(script_id.to_string());
+        let items = parse_items(&strip_comments(&script.script))?;
+        let mut tokens = vec![];
+        for item in items {
+            match item {
+                Item::Opcode(name) => tokens.push(name),
+                Item::Push(inner) => tokens.push(self.push_token(&inner, trail)?),
+                Item::Identifier(id) if self.template.scripts.contains_key(&id) => {
+                    tokens.extend(self.compile_with_trail(&id, trail)?)
+                }
+                other => {
+                    return Err(BitauthError::UnsupportedOperation(format!(
+                        "raw bytecode outside of a push : {:?}",
+                        other
+                    )))
+                }
+            }
+        }
+        trail.pop();
+
+        Ok(tokens)
+    }
+
+    /// A push of a single number stays a number, everything else is pushed as hex.
+    fn push_token(&self, items: &[Item], trail: &mut Vec<String>) -> Result<String, BitauthError> {
+        if let [Item::Number(n)] = items {
+            return Ok(n.to_string());
+        }
+
+        let mut bytes = vec![];
+        for item in items {
+            bytes.extend(self.item_bytes(item, trail)?);
+        }
+
+        if bytes.is_empty() {
+            Ok("0".to_string())
+        } else {
+            Ok(hex::encode(bytes))
+        }
+    }
+
+    fn item_bytes(&self, item: &Item, trail: &mut Vec<String>) -> Result<Vec<u8>, BitauthError> {
+        match item {
+            Item::Opcode(name) => Ok(vec![
+                Opcode::from_name(name)
+                    .ok_or(BitauthError::UnknownIdentifier(name.to_string()))?
+                    .0,
+            ]),
+            Item::Push(inner) => Ok(tokens_to_bytes(&[self.push_token(inner, trail)?])?),
+            Item::Hex(bytes) => Ok(bytes.clone()),
+            Item::Number(n) => Ok(script_num_to_bytes(*n)),
+            Item::Text(text) => Ok(text.as_bytes().to_vec()),
+            Item::Identifier(id) => {
+                if self.template.scripts.contains_key(id) {
+                    return Ok(tokens_to_bytes(&self.compile_with_trail(id, trail)?)?);
+                }
+                self.resolve_variable(id, trail)
+            }
+        }
+    }
+
+    /// Resolves `variable.operation[.parameter]` references.
+    fn resolve_variable(
+        &self,
+        identifier: &str,
+        trail: &mut Vec<String>,
+    ) -> Result<Vec<u8>, BitauthError> {
+        let mut parts = identifier.splitn(3, '.');
+        let variable_id = parts.next().unwrap_or_default();
+        let operation = parts.next();
+        let parameter = parts.next();
+
+        let variable = self
+            .template
+            .find_variable(variable_id)
+            .ok_or(BitauthError::UnknownIdentifier(identifier.to_string()))?;
+
+        match variable.variable_type {
+            VariableType::AddressData | VariableType::WalletData => {
+                self.keys.data(variable_id).cloned().ok_or_else(|| {
+                    BitauthError::UnsupportedOperation(format!(
+                        "no value provided for {}",
+                        variable_id
+                    ))
+                })
+            }
+            VariableType::Key | VariableType::HdKey => {
+                let key = self.keys.key(variable_id);
+                match (operation, parameter) {
+                    (Some("public_key"), None) => Ok(key.verifying_key().to_sec1_bytes().to_vec()),
+                    (Some("data_signature" | "ecdsa_data_signature"), Some(script_id)) => {
+                        let message = tokens_to_bytes(&self.compile_with_trail(script_id, trail)?)?;
+                        let signature: k256::ecdsa::Signature = key.sign(&message);
+                        Ok(signature.to_der().as_bytes().to_vec())
+                    }
+                    (Some("schnorr_data_signature"), Some(script_id)) => {
+                        let message = tokens_to_bytes(&self.compile_with_trail(script_id, trail)?)?;
+                        let schnorr_key = k256::schnorr::SigningKey::from_bytes(&key.to_bytes())
+                            .map_err(|_| {
+                                BitauthError::UnsupportedOperation(identifier.to_string())
+                            })?;
+                        let signature: k256::schnorr::Signature = schnorr_key.sign(&message);
+                        Ok(signature.to_bytes().to_vec())
+                    }
+                    _ => Err(BitauthError::UnsupportedOperation(identifier.to_string())),
+                }
+            }
+        }
+    }
+}
+
+/// Removes `// line` and `/* block */` comments.
+fn strip_comments(script: &str) -> String {
+    let mut result = String::new();
+    let mut chars = script.chars().peekable();
+    let mut quote: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            result.push(c);
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('\'' | '"', _) => {
+                quote = Some(c);
+                result.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        result.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                result.push(' ');
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+fn parse_items(script: &str) -> Result<Vec<Item>, BitauthError> {
+    let chars: Vec<char> = script.chars().collect();
+    let mut position = 0;
+    let items = parse_sequence(&chars, &mut position, false)?;
+    Ok(items)
+}
+
+fn parse_sequence(
+    chars: &[char],
+    position: &mut usize,
+    in_push: bool,
+) -> Result<Vec<Item>, BitauthError> {
+    let mut items = vec![];
+
+    while *position < chars.len() {
+        let c = chars[*position];
+        if c.is_whitespace() {
+            *position += 1;
+        } else if c == '<' {
+            *position += 1;
+            items.push(Item::Push(parse_sequence(chars, position, true)?));
+        } else if c == '>' {
+            if !in_push {
+                return Err(BitauthError::UnbalancedPush("unexpected '>'".to_string()));
+            }
+            *position += 1;
+            return Ok(items);
+        } else if c == '\'' || c == '"' {
+            let start = *position + 1;
+            let end = chars[start..].iter().position(|&ch| ch == c).ok_or(
+                BitauthError::InvalidLiteral("unterminated string".to_string()),
+            )?;
+            items.push(Item::Text(chars[start..start + end].iter().collect()));
+            *position = start + end + 1;
+        } else {
+            let start = *position;
+            while *position < chars.len()
+                && !chars[*position].is_whitespace()
+                && !matches!(chars[*position], '<' | '>' | '\'' | '"')
+            {
+                *position += 1;
+            }
+            let word: String = chars[start..*position].iter().collect();
+            items.push(parse_word(&word)?);
+        }
+    }
+
+    if in_push {
+        return Err(BitauthError::UnbalancedPush("missing '>'".to_string()));
+    }
+    Ok(items)
+}
+
+fn parse_word(word: &str) -> Result<Item, BitauthError> {
+    if word.starts_with("OP_") {
+        return Ok(Item::Opcode(word.to_string()));
+    }
+    if let Some(hex_part) = word.strip_prefix("0x") {
+        if !hex_part.len().is_multiple_of(2) {
+            return Err(BitauthError::InvalidLiteral(word.to_string()));
+        }
+        return hex::decode(hex_part)
+            .map(Item::Hex)
+            .map_err(|_| BitauthError::InvalidLiteral(word.to_string()));
+    }
+    let digits = word.strip_prefix('-').unwrap_or(word);
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        return word
+            .parse::<i64>()
+            .map(Item::Number)
+            .map_err(|_| BitauthError::InvalidLiteral(word.to_string()));
+    }
+    Ok(Item::Identifier(word.to_string()))
+}
+
+#[cfg(test)]
+mod test_compiler {
+    use crate::bitauth::compiler::{parse_items, strip_comments, Item};
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_strip_comments() {
+        let script = "// comment\nOP_1 /* block\n comment */ OP_2 <'// not a comment'>";
+        assert_eq!(strip_comments(script), "\nOP_1   OP_2 <'// not a comment'>");
+    }
+
+    #[rstest]
+    #[case("OP_DUP", vec![Item::Opcode("OP_DUP".to_string())])]
+    #[case("<0xabcd>", vec![Item::Push(vec![Item::Hex(vec![0xab, 0xcd])])])]
+    #[case("<-5>", vec![Item::Push(vec![Item::Number(-5)])])]
+    #[case("<key.public_key>", vec![Item::Push(vec![Item::Identifier("key.public_key".to_string())])])]
+    #[case("<<1>>", vec![Item::Push(vec![Item::Push(vec![Item::Number(1)])])])]
+    fn test_parse_items(#[case] script: &str, #[case] expected: Vec<Item>) {
+        assert_eq!(parse_items(script).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("<0x123>")]
+    #[case("<1")]
+    #[case("1>")]
+    fn test_parse_items_errors(#[case] script: &str) {
+        assert!(parse_items(script).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use k256::ecdsa::SigningKey;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Keys and data used to resolve template variables.
+///
+/// Key variables that were not set explicitly get a test key derived from the variable id, so
+/// compiling the same template twice always produces the same scripts.
+#[derive(Debug, Clone, Default)]
+pub struct TestKeys {
+    keys: BTreeMap<String, SigningKey>,
+    data: BTreeMap<String, Vec<u8>>,
+}
+
+impl TestKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the given key for the `Key` / `HdKey` variable with this id.
+    pub fn with_key(mut self, variable_id: &str, key: SigningKey) -> Self {
+        self.keys.insert(variable_id.to_string(), key);
+        self
+    }
+
+    /// Sets the value of an `AddressData` / `WalletData` variable.
+    pub fn with_data(mut self, variable_id: &str, data: Vec<u8>) -> Self {
+        self.data.insert(variable_id.to_string(), data);
+        self
+    }
+
+    /// Returns the key for the variable, deriving a deterministic test key if none was set.
+    pub fn key(&self, variable_id: &str) -> SigningKey {
+        if let Some(key) = self.keys.get(variable_id) {
+            return key.clone();
+        }
+
+        // Hash the id (and a counter) until we land on a valid secret key.
+        let mut counter: u32 = 0;
+        loop {
+            let mut hasher = Sha256::new();
+            hasher.update(b"bee-vm/bitauth/test-key/");
+            hasher.update(variable_id.as_bytes());
+            hasher.update(counter.to_le_bytes());
+            if let Ok(key) = SigningKey::from_slice(&hasher.finalize()) {
+                return key;
+            }
+            counter += 1;
+        }
+    }
+
+    pub fn data(&self, variable_id: &str) -> Option<&Vec<u8>> {
+        self.data.get(variable_id)
+    }
+}
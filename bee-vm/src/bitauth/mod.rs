@@ -0,0 +1,278 @@
+// ==================================================
+// Bitauth IDE templates (import / export)
+// ==================================================
+
+pub mod compiler;
+pub mod keys;
+pub mod template;
+
+use crate::bitauth::compiler::Compiler;
+use crate::bitauth::keys::TestKeys;
+use crate::bitauth::template::{AuthenticationTemplate, Entity, TemplateScript, TEMPLATE_SCHEMA};
+use crate::errors::BitauthError;
+use crate::opcodes::utils::cast_to_bool;
+use crate::stack::executor::execute_code_with_stack;
+use crate::stack::Stack;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Entity id used for the scripts exported from Bee VM.
+pub const EXPORT_ENTITY: &str = "bee_vm";
+
+/// Result of compiling and running a single template script in Bee VM.
+#[derive(Debug)]
+pub struct ScriptEvaluation {
+    pub script_id: String,
+    pub tokens: Vec<String>,
+    pub result: Result<(Stack, Stack), String>,
+}
+
+impl ScriptEvaluation {
+    /// True when the script ran without errors and left a true element (non-zero, not negative
+    /// zero) on top of the stack.
+    pub fn succeeded(&self) -> bool {
+        match &self.result {
+            Ok((main_stack, _)) => main_stack.peek(0).is_some_and(|top| cast_to_bool(top)),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Reads a template from a `.json` file.
+pub fn load_template(path: impl AsRef<Path>) -> Result<AuthenticationTemplate, BitauthError> {
+    let content =
+        fs::read_to_string(path).map_err(|e| BitauthError::TemplateRead(e.to_string()))?;
+    parse_template(&content)
+}
+
+pub fn parse_template(json: &str) -> Result<AuthenticationTemplate, BitauthError> {
+    serde_json::from_str(json).map_err(|e| BitauthError::TemplateParse(e.to_string()))
+}
+
+pub fn template_to_json(template: &AuthenticationTemplate) -> Result<String, BitauthError> {
+    serde_json::to_string_pretty(template).map_err(|e| BitauthError::TemplateParse(e.to_string()))
+}
+
+/// Compiles every script of the template and runs it in Bee VM.
+///
+/// Unlocking scripts (scripts with `unlocks` set) are run followed by the locking script they
+/// unlock. Compilation errors are reported per script and do not stop the other scripts.
+pub fn evaluate_template(
+    template: &AuthenticationTemplate,
+    keys: &TestKeys,
+) -> Vec<ScriptEvaluation> {
+    let compiler = Compiler::new(template, keys);
+
+    template
+        .scripts
+        .iter()
+        .map(|(script_id, script)| {
+            let compiled = compiler.compile(script_id).and_then(|mut tokens| {
+                if let Some(locking_id) = &script.unlocks {
+                    tokens.extend(compiler.compile(locking_id)?);
+                }
+                Ok(tokens)
+            });
+
+            match compiled {
+                Ok(tokens) => ScriptEvaluation {
+                    script_id: script_id.clone(),
+                    result: execute_code_with_stack(&tokens, Stack::new())
+                        .map_err(|e| e.to_string()),
+                    tokens,
+                },
+                Err(e) => ScriptEvaluation {
+                    script_id: script_id.clone(),
+                    tokens: vec![],
+                    result: Err(e.to_string()),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Builds a template containing the given Bee VM scripts, owned by a single entity.
+pub fn export_template(name: &str, scripts: &[(&str, Vec<String>)]) -> AuthenticationTemplate {
+    let template_scripts: BTreeMap<String, TemplateScript> = scripts
+        .iter()
+        .map(|(id, tokens)| {
+            (
+                id.to_string(),
+                TemplateScript {
+                    name: id.to_string(),
+                    script: tokens_to_cash_assembly(tokens),
+                    unlocks: None,
+                    extra: BTreeMap::new(),
+                },
+            )
+        })
+        .collect();
+
+    let entity = Entity {
+        name: "Bee VM".to_string(),
+        description: "Scripts exported from Bee VM.".to_string(),
+        scripts: template_scripts.keys().cloned().collect(),
+        variables: BTreeMap::new(),
+        extra: BTreeMap::new(),
+    };
+
+    AuthenticationTemplate {
+        schema: Some(TEMPLATE_SCHEMA.to_string()),
+        name: name.to_string(),
+        description: String::new(),
+        entities: BTreeMap::from([(EXPORT_ENTITY.to_string(), entity)]),
+        scripts: template_scripts,
+        scenarios: None,
+        supported: vec!["BTC_2017_08".to_string()],
+        version: 0,
+        extra: BTreeMap::new(),
+    }
+}
+
+/// Converts Bee VM tokens into the Bitauth script syntax, one instruction per line.
+pub fn tokens_to_cash_assembly(tokens: &[String]) -> String {
+    tokens
+        .iter()
+        .map(|token| {
+            let hex_part = token.strip_prefix("0x").unwrap_or(token);
+            let digits = token.strip_prefix('-').unwrap_or(token);
+            if token.starts_with("OP_") {
+                token.to_string()
+            } else if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+                format!("<{}>", token)
+            } else if hex_part.len().is_multiple_of(2)
+                && hex_part.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                format!("<0x{}>", hex_part)
+            } else if token.contains('\'') {
+                format!("<\"{}\">", token)
+            } else {
+                format!("<'{}'>", token)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test_bitauth {
+    use crate::bitauth::compiler::Compiler;
+    use crate::bitauth::keys::TestKeys;
+    use crate::bitauth::template::VariableType;
+    use crate::bitauth::{
+        evaluate_template, export_template, load_template, parse_template, ScriptEvaluation,
+    };
+    use crate::errors::{BitauthError, OpCodeErrors};
+    use crate::stack::Stack;
+    use rstest::rstest;
+
+    const TEMPLATE: &str = r#"{
+        "name": "Test",
+        "entities": {
+            "owner": {
+                "name": "Owner",
+                "scripts": ["lock", "unlock"],
+                "variables": { "key": { "type": "Key" } }
+            }
+        },
+        "scripts": {
+            "lock": { "script": "OP_DUP OP_SIZE <33> OP_EQUALVERIFY <key.public_key> OP_EQUAL" },
+            "unlock": { "script": "// the owner's key\n<key.public_key>", "unlocks": "lock" },
+            "math": { "script": "<2> <3> OP_ADD <5> OP_EQUAL" },
+            "sig": { "script": "<key.schnorr_data_signature.math> <key.data_signature.math>" }
+        },
+        "supported": ["BTC_2017_08"],
+        "version": 0
+    }"#;
+
+    #[rstest]
+    fn test_load_repo_template() -> color_eyre::Result<()> {
+        let template = load_template(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../bit_auth/btc_script_study.wallet-template.json"
+        ))?;
+        let variable = template.find_variable("owner_hdkey").unwrap();
+        assert_eq!(variable.variable_type, VariableType::HdKey);
+        assert_eq!(template.scripts.len(), 2);
+
+        // `<0x123>` is an odd length hex literal : neither script compiles.
+        let invalid_literal = BitauthError::InvalidLiteral("0x123".to_string()).to_string();
+        for evaluation in evaluate_template(&template, &TestKeys::new()) {
+            assert_eq!(evaluation.result.unwrap_err(), invalid_literal);
+        }
+
+        // With an even literal, `main` compiles but stops at OP_CHECKDATASIG, a Bitcoin Cash
+        // opcode.
+        let mut template = template;
+        template.scripts.get_mut("message").unwrap().script = "<0x0123>".to_string();
+        let evaluations = evaluate_template(&template, &TestKeys::new());
+        let find = |id: &str| evaluations.iter().find(|e| e.script_id == id).unwrap();
+        assert!(find("main").tokens.contains(&"OP_CHECKDATASIG".to_string()));
+        assert_eq!(
+            find("main").result.as_ref().unwrap_err(),
+            &OpCodeErrors::UnknownOpcode.to_string()
+        );
+        assert!(find("message").succeeded());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_evaluate_template() -> color_eyre::Result<()> {
+        let template = parse_template(TEMPLATE)?;
+        let evaluations = evaluate_template(&template, &TestKeys::new());
+
+        let find = |id: &str| evaluations.iter().find(|e| e.script_id == id).unwrap();
+        assert!(find("unlock").succeeded());
+        assert!(find("math").succeeded());
+        assert!(!find("lock").succeeded());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("01", true)]
+    #[case("02", true)]
+    #[case("0001", true)]
+    #[case("81", true)]
+    #[case("", false)]
+    #[case("0000", false)]
+    #[case("0080", false)]
+    fn test_succeeded_uses_script_truthiness(#[case] top: &str, #[case] expected: bool) {
+        let evaluation = ScriptEvaluation {
+            script_id: "main".to_string(),
+            tokens: vec![],
+            result: Ok((Stack::from_data(&[hex::decode(top).unwrap()]), Stack::new())),
+        };
+        assert_eq!(evaluation.succeeded(), expected);
+    }
+
+    #[rstest]
+    fn test_signatures_are_deterministic() -> color_eyre::Result<()> {
+        let template = parse_template(TEMPLATE)?;
+        let keys = TestKeys::new();
+        let first = Compiler::new(&template, &keys).compile("sig")?;
+        let second = Compiler::new(&template, &keys).compile("sig")?;
+        assert_eq!(first, second);
+        // 64 byte schnorr signature followed by a DER encoded ecdsa signature
+        assert_eq!(first[0].len(), 128);
+        assert!(first[1].starts_with("30"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_export_round_trip() -> color_eyre::Result<()> {
+        let tokens: Vec<String> = ["2", "abcd", "OP_SIZE", "OP_NIP", "OP_ADD", "4", "OP_EQUAL"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let template = export_template("Exported", &[("main", tokens.clone())]);
+        let json = crate::bitauth::template_to_json(&template)?;
+        let imported = parse_template(&json)?;
+        assert_eq!(imported, template);
+
+        let keys = TestKeys::new();
+        assert_eq!(Compiler::new(&imported, &keys).compile("main")?, tokens);
+        assert!(evaluate_template(&imported, &keys)[0].succeeded());
+        Ok(())
+    }
+}
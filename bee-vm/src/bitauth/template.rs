@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub const TEMPLATE_SCHEMA: &str = "https://ide.bitauth.com/authentication-template-v0.schema.json";
+
+/// Bitauth IDE authentication template (v0).
+///
+/// Only the parts Bee VM understands are typed, everything else is kept in `extra` so that a
+/// template can be loaded and written back without losing information.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticationTemplate {
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default)]
+    pub entities: BTreeMap<String, Entity>,
+    #[serde(default)]
+    pub scripts: BTreeMap<String, TemplateScript>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenarios: Option<Value>,
+    #[serde(default)]
+    pub supported: Vec<String>,
+    #[serde(default)]
+    pub version: u32,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entity {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default)]
+    pub scripts: Vec<String>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, Variable>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Variable {
+    #[serde(rename = "type")]
+    pub variable_type: VariableType,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VariableType {
+    Key,
+    HdKey,
+    AddressData,
+    WalletData,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateScript {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub script: String,
+    /// Id of the locking script this (unlocking) script is meant to satisfy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unlocks: Option<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+impl AuthenticationTemplate {
+    /// Finds a variable definition by id across all the entities of the template.
+    pub fn find_variable(&self, id: &str) -> Option<&Variable> {
+        self.entities
+            .values()
+            .find_map(|entity| entity.variables.get(id))
+    }
+}
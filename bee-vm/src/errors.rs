@@ -32,6 +32,16 @@ pub enum OpCodeErrors {
     InvalidSignature,
     #[error("Error creating message")]
     MessageCreationError,
+    #[error("Lock time check failed : {0}")]
+    UnsatisfiedLocktime(String),
+    #[error("{0} needs a transaction context")]
+    MissingContext(String),
+    #[error("More than {0} items on the stack and alt stack")]
+    StackOverflow(usize),
+    #[error("More than {0} non push opcodes in the script")]
+    OpCount(usize),
+    #[error("Position {position} is out of range of a stack of {length} items")]
+    PositionOutOfRange { position: usize, length: usize },
 }
 
 #[derive(Error, Debug)]
@@ -39,3 +49,154 @@ pub enum InputParsingError {
     #[error("Not able to parse the inputs provided to the vm.")]
     InputParsingErrorAtRun,
 }
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ScriptError {
+    #[error("Unknown opcode in script : {0}")]
+    UnknownOpcode(String),
+    #[error("Push data of {0} bytes is too large")]
+    PushDataTooLarge(usize),
+    #[error("Push at byte {0} runs past the end of the script")]
+    TruncatedPush(usize),
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum AnalysisError {
+    #[error("{opcode} at byte {position} needs {needed} stack items, only {available} are there")]
+    StackUnderflow {
+        position: usize,
+        opcode: String,
+        needed: usize,
+        available: usize,
+    },
+    #[error("OP_FROMALTSTACK at byte {0} with an empty alt stack")]
+    AltStackUnderflow(usize),
+    #[error("{1} at byte {0} without an OP_IF / OP_NOTIF")]
+    UnmatchedConditional(usize, String),
+    #[error("Conditional opened at byte {0} is never closed by an OP_ENDIF")]
+    UnterminatedConditional(usize),
+    #[error(
+        "Branches joined at byte {position} leave different stack heights ({first} and {second})"
+    )]
+    UnbalancedBranches {
+        position: usize,
+        first: isize,
+        second: isize,
+    },
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SymbolicError {
+    #[error("{0} at byte {1} needs a constant operand")]
+    NonConstantOperand(String, usize),
+    #[error("{0} is not supported in symbolic execution")]
+    Unsupported(String),
+    #[error("Unbalanced conditional at byte {0}")]
+    UnbalancedConditional(usize),
+    #[error("More than {0} paths through the script")]
+    TooManyPaths(usize),
+    #[error("Expression of more than {0} nodes")]
+    ExpressionTooLarge(usize),
+    #[error("Error : {0}")]
+    Script(#[from] ScriptError),
+}
+
+#[derive(Error, Debug)]
+pub enum SolverError {
+    #[error("No spending path can be satisfied :\n{}", .0.join("\n"))]
+    Unsatisfiable(Vec<String>),
+    #[error("Error : {0}")]
+    Symbolic(#[from] SymbolicError),
+}
+
+#[derive(Error, Debug)]
+pub enum BitauthError {
+    #[error("Not able to read the template : {0}")]
+    TemplateRead(String),
+    #[error("Not able to parse the template : {0}")]
+    TemplateParse(String),
+    #[error("Script not found in template : {0}")]
+    ScriptNotFound(String),
+    #[error("Unknown variable or script reference : {0}")]
+    UnknownIdentifier(String),
+    #[error("Unsupported variable operation : {0}")]
+    UnsupportedOperation(String),
+    #[error("Invalid literal : {0}")]
+    InvalidLiteral(String),
+    #[error("Unbalanced push brackets in script : {0}")]
+    UnbalancedPush(String),
+    #[error("Circular script reference : {0}")]
+    CircularReference(String),
+    #[error("Error : {0}")]
+    Script(#[from] ScriptError),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TransactionError {
+    #[error("Transaction data ends before byte {0}")]
+    Truncated(usize),
+    #[error("{0} trailing bytes after the transaction")]
+    TrailingBytes(usize),
+    #[error("Invalid segwit flag : {0}")]
+    InvalidSegwitFlag(u8),
+    #[error("Segwit marker and flag without any witness")]
+    SuperfluousWitness,
+    #[error("Non canonical compact size at byte {0}")]
+    NonCanonicalCompactSize(usize),
+    #[error("Invalid hex : {0}")]
+    InvalidHex(String),
+    #[error("Input index {0} is out of range")]
+    InputIndexOutOfRange(usize),
+    #[error("Spent output of input {0} is missing")]
+    MissingPrevout(usize),
+    #[error("Invalid sighash type : {0:#x}")]
+    InvalidSighashType(u32),
+    #[error("SIGHASH_SINGLE without an output at index {0}")]
+    SingleWithoutOutput(usize),
+    #[error("Invalid signature encoding : {0}")]
+    InvalidSignature(String),
+    #[error("Error : {0}")]
+    Script(#[from] ScriptError),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum VerifyError {
+    #[error("Script evaluated to false")]
+    EvalFalse,
+    #[error("Script execution failed : {0}")]
+    Execution(String),
+    #[error("scriptSig is not push only")]
+    SigPushOnly,
+    #[error("Witness program spent with a non empty scriptSig")]
+    WitnessMalleated,
+    #[error("Nested witness program spent with more than the redeem script push in scriptSig")]
+    WitnessMalleatedP2SH,
+    #[error("Witness given for a non witness output")]
+    WitnessUnexpected,
+    #[error("Witness is empty")]
+    WitnessProgramWitnessEmpty,
+    #[error("Witness does not match the witness program")]
+    WitnessProgramMismatch,
+    #[error("Invalid witness v0 program length : {0}")]
+    WitnessProgramWrongLength(usize),
+    #[error("Witness script must leave exactly one element on the stack")]
+    CleanStack,
+    #[error("Script of {0} bytes is larger than the 10000 bytes limit")]
+    ScriptSize(usize),
+    #[error("Element of {0} bytes is larger than the 520 bytes limit")]
+    PushSize(usize),
+    #[error("More than 1000 items on the stack")]
+    StackSize,
+    #[error("{0} is disabled")]
+    DisabledOpcode(String),
+    #[error("{0} is not a valid opcode")]
+    BadOpcode(String),
+    #[error("Invalid taproot control block size : {0}")]
+    TaprootWrongControlSize(usize),
+    #[error("Invalid Schnorr signature")]
+    SchnorrSig,
+    #[error("Error : {0}")]
+    Transaction(#[from] TransactionError),
+    #[error("Error : {0}")]
+    Script(#[from] ScriptError),
+}
@@ -6,3 +6,9 @@ pub mod processor;
 pub mod errors;
 pub mod stack;
 pub mod utils;
+// Module for script serialization
+pub mod script;
+// Module for Bitauth IDE template import / export
+pub mod bitauth;
+// Module for transactions and the signature hashes checked by OP_CHECKSIG
+pub mod transaction;
@@ -6,12 +6,22 @@ pub mod processor;
 pub mod errors;
 pub mod stack;
 pub mod utils;
+// Module for script serialization
+pub mod script;
+// Module for Bitauth IDE template import / export
+pub mod bitauth;
+// Module for transactions and the signature hashes checked by OP_CHECKSIG
+pub mod transaction;
 
 use std::env;
 
 use processor::input::parse_input;
 
+use script::analyze::analyze_tokens;
+use script::asm::pretty_print_tokens;
+
 use stack::executor::execute_code;
+use stack::symbolic::execute_symbolic_tokens;
 
 use colored::Colorize;
 
@@ -51,6 +61,31 @@ fn main() -> color_eyre::Result<()> {
 
     let res = parse_input(args[1].clone())?;
 
+    if let Ok(listing) = pretty_print_tokens(&res) {
+        log::info!("SCRIPT :\n{}", listing);
+    }
+
+    // `--symbolic` lists the spending paths of the script instead of running it, as JSON
+    // with `--json`.
+    if args.iter().any(|arg| arg == "--symbolic") {
+        let result = execute_symbolic_tokens(&res)?;
+        match args.iter().any(|arg| arg == "--json") {
+            true => println!("{}", serde_json::to_string_pretty(&result)?),
+            false => println!("{}", result),
+        }
+        return Ok(());
+    }
+
+    // Scripts run from an empty stack, reading below it is an underflow.
+    if let Ok(analysis) = analyze_tokens(&res, Some(0)) {
+        for error in &analysis.errors {
+            log::warn!("{}", error);
+        }
+        if let Some(items) = analysis.final_items {
+            log::info!("Stack items at the end (static analysis) : {}", items);
+        }
+    }
+
     execute_code(res.clone()).expect("Error in executing the instructions");
 
     Ok(())
@@ -14,7 +14,7 @@ pub fn abs(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
         None => return Err(OpCodeErrors::MissingValue("abs : value 1".to_string())),
     })?;
 
-    vm_state.push_to_top(item_1.abs().to_string());
+    vm_state.push_to_top((item_1 as i64).abs().to_string());
 
     Ok(())
 }
@@ -10,7 +10,8 @@ use crate::stack::Stack;
 /// [  b  ]
 /// [  a  ]
 ///
-/// a is added to b.
+/// a is added to b. The sum may not fit in 4 bytes, it is then only rejected when used as a
+/// number again.
 pub fn add(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
     let item_1 = string_to_i32(match &vm_state.pop_from_top() {
         Some(val) => val,
@@ -22,7 +23,7 @@ pub fn add(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
         None => return Err(OpCodeErrors::MissingValue("add : value 2".to_string())),
     })?;
 
-    vm_state.push_to_top((item_1 + item_2).to_string());
+    vm_state.push_to_top((item_1 as i64 + item_2 as i64).to_string());
 
     Ok(())
 }
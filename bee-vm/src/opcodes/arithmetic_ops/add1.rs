@@ -13,7 +13,7 @@ pub fn add_1(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
         Some(val) => val,
         None => return Err(OpCodeErrors::MissingValue("add_1 : value 1".to_string())),
     })?;
-    vm_state.push_to_top((item_1 + 1).to_string());
+    vm_state.push_to_top((item_1 as i64 + 1).to_string());
 
     Ok(())
 }
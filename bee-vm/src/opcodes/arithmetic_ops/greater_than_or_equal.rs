@@ -29,7 +29,7 @@ pub fn greater_than_or_equal(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
         }
     })?;
 
-    if item_1 >= item_2 {
+    if item_2 >= item_1 {
         vm_state.push_to_top("1".to_string());
     } else {
         vm_state.push_to_top("0".to_string());
@@ -45,8 +45,8 @@ mod test_opcode_greater_than_or_equal {
     use rstest::rstest;
 
     #[rstest]
-    #[case(vec!["5".to_string(), "1".to_string()], vec!["0".to_string()])]
-    #[case(vec!["0".to_string(), "1".to_string()], vec!["1".to_string()])]
+    #[case(vec!["5".to_string(), "1".to_string()], vec!["1".to_string()])]
+    #[case(vec!["0".to_string(), "1".to_string()], vec!["0".to_string()])]
     #[case(vec!["7".to_string(), "7".to_string()], vec!["1".to_string()])]
     fn test_greater_than_or_equal(
         #[case] initial: Vec<String>,
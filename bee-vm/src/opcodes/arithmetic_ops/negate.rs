@@ -15,7 +15,7 @@ pub fn negate(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
         None => return Err(OpCodeErrors::MissingValue("negate: value 1".to_string())),
     };
 
-    vm_state.push_to_top((0 - item_1).to_string());
+    vm_state.push_to_top((-(item_1 as i64)).to_string());
 
     Ok(())
 }
@@ -1,4 +1,5 @@
 use crate::errors::OpCodeErrors;
+use crate::opcodes::utils::string_to_i32;
 use crate::stack::Stack;
 
 /// **OP_NOT**
@@ -8,19 +9,15 @@ use crate::stack::Stack;
 /// [ 0x00 ]
 /// => [ 0x01 ]
 pub fn not(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    let value = vm_state.pop_from_top();
-
-    match value {
-        Some(val) => {
-            if val == "1" {
-                vm_state.push_to_top("0".to_string());
-            } else if val == "0" {
-                vm_state.push_to_top("1".to_string());
-            } else {
-                vm_state.push_to_top("0".to_string());
-            }
-        }
+    let item_1 = string_to_i32(match &vm_state.pop_from_top() {
+        Some(val) => val,
         None => return Err(OpCodeErrors::MissingValue("not : value 1".to_string())),
+    })?;
+
+    if item_1 == 0 {
+        vm_state.push_to_top("1".to_string());
+    } else {
+        vm_state.push_to_top("0".to_string());
     }
 
     Ok(())
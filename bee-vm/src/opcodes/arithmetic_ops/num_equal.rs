@@ -20,7 +20,7 @@ pub fn num_equal(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
             ))
         }
     })?;
-    let item_2 = string_to_i32(match &vm_state.pop() {
+    let item_2 = string_to_i32(match &vm_state.pop_from_top() {
         Some(val) => val,
         None => {
             return Err(OpCodeErrors::MissingValue(
@@ -48,6 +48,7 @@ mod test_opcode_num_equal {
     #[case(vec!["0".to_string(), "0".to_string()], vec!["1".to_string()])]
     #[case(vec!["1".to_string(), "0".to_string()], vec!["0".to_string()])]
     #[case(vec!["5".to_string(), "0".to_string()], vec!["0".to_string()])]
+    #[case(vec!["7".to_string(), "3".to_string(), "3".to_string()], vec!["7".to_string(), "1".to_string()])]
     fn test_num_equal(
         #[case] initial: Vec<String>,
         #[case] expected: Vec<String>,
@@ -21,7 +21,7 @@ pub fn sub(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
         None => return Err(OpCodeErrors::MissingValue("sub : value 2".to_string())),
     })?;
 
-    vm_state.push_to_top((item_1 - item_2).to_string());
+    vm_state.push_to_top((item_2 as i64 - item_1 as i64).to_string());
 
     Ok(())
 }
@@ -33,8 +33,8 @@ mod test_opcode_sub {
     use rstest::rstest;
 
     #[rstest]
-    #[case(vec!["1".to_string(), "3".to_string()], vec!["2".to_string()])]
-    #[case(vec!["-1".to_string(), "-3".to_string()], vec!["-2".to_string()])]
+    #[case(vec!["1".to_string(), "3".to_string()], vec!["-2".to_string()])]
+    #[case(vec!["-1".to_string(), "-3".to_string()], vec!["2".to_string()])]
     fn test_sub(
         #[case] initial: Vec<String>,
         #[case] expected: Vec<String>,
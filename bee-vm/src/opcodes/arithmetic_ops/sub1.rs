@@ -13,13 +13,7 @@ pub fn sub_1(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
         Some(val) => val,
         None => return Err(OpCodeErrors::MissingValue("sub_1 : value 1".to_string())),
     })?;
-    let item_2 = string_to_i32(match &vm_state.pop_from_top() {
-        Some(val) => val,
-        None => return Err(OpCodeErrors::MissingValue("sub_1 : value 2".to_string())),
-    })?;
-
-    vm_state.push_to_top(item_2.to_string());
-    vm_state.push_to_top((item_1 - item_2).to_string());
+    vm_state.push_to_top((item_1 as i64 - 1).to_string());
 
     Ok(())
 }
@@ -1,15 +1,15 @@
 use crate::errors::OpCodeErrors;
-use crate::opcodes::utils::string_to_i32;
+use crate::opcodes::utils::cast_to_bool;
 use crate::stack::Stack;
 
 /// Marks transaction as invalid if top stack_ops value is not true. The top stack_ops value is removed.
 pub fn verify(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    let item = string_to_i32(match &vm_state.pop_from_top() {
-        Some(val) => val,
+    let item = match &vm_state.pop_from_top() {
+        Some(val) => cast_to_bool(val),
         None => return Err(OpCodeErrors::MissingValue("verify : value 1".to_string())),
-    })?;
+    };
 
-    if item != 1 {
+    if !item {
         return Err(OpCodeErrors::OpVerifyFailed);
     }
 
@@ -25,7 +25,10 @@ mod test_opcode_verify {
 
     #[rstest]
     #[case(vec!["1".to_string()], vec![], false)]
-    #[case(vec!["45550".to_string()], vec![], true)]
+    #[case(vec!["45550".to_string()], vec![], false)]
+    #[case(vec!["abcd".to_string()], vec![], false)]
+    #[case(vec!["0".to_string()], vec![], true)]
+    #[case(vec!["0x0080".to_string()], vec![], true)]
     fn test_verify(
         #[case] initial: Vec<String>,
         #[case] expected: Vec<String>,
@@ -1,25 +1,52 @@
 use crate::errors::OpCodeErrors;
+use crate::opcodes::utils::cast_to_bool;
+use crate::script::encode::element_to_bytes;
 use crate::stack::Stack;
 
 pub struct ControlFlow {
     if_stack: Vec<bool>,
+    minimal_if: bool,
 }
 
 impl ControlFlow {
     pub fn new() -> Self {
         ControlFlow {
             if_stack: Vec::new(),
+            minimal_if: false,
+        }
+    }
+
+    /// Control flow of tapscript, where the OP_IF / OP_NOTIF argument must be empty or
+    /// exactly 0x01 (BIP342 makes MINIMALIF a consensus rule).
+    pub fn tapscript() -> Self {
+        ControlFlow {
+            if_stack: Vec::new(),
+            minimal_if: true,
         }
     }
 
     pub fn op_if(&mut self, stack: &mut Stack) -> Result<(), OpCodeErrors> {
+        self.push_branch(stack, false)
+    }
+
+    /// OP_NOTIF executes its branch when the condition is false.
+    pub fn op_notif(&mut self, stack: &mut Stack) -> Result<(), OpCodeErrors> {
+        self.push_branch(stack, true)
+    }
+
+    fn push_branch(&mut self, stack: &mut Stack, negate: bool) -> Result<(), OpCodeErrors> {
         if self.should_execute() {
             let condition = match stack.pop_from_top() {
                 Some(val) => val,
                 None => return Err(OpCodeErrors::StackEmpty),
             };
-            let execute = condition != "0" && condition.to_lowercase() != "false";
-            self.if_stack.push(execute);
+            if self.minimal_if && !matches!(element_to_bytes(&condition).as_slice(), [] | [1]) {
+                return Err(OpCodeErrors::InvalidValue(
+                    "OP_IF / OP_NOTIF argument must be empty or 0x01 in tapscript".to_string(),
+                ));
+            }
+            let execute = cast_to_bool(&condition) && condition.to_lowercase() != "false";
+            self.if_stack.push(execute != negate);
         } else {
             self.if_stack.push(false);
         }
@@ -47,8 +74,21 @@ impl ControlFlow {
         }
     }
 
+    /// Checks that every OP_IF / OP_NOTIF was closed once the script is over.
+    pub fn finish(&self) -> Result<(), OpCodeErrors> {
+        if self.if_stack.is_empty() {
+            Ok(())
+        } else {
+            Err(OpCodeErrors::UnbalancedControlFlow(
+                "OP_IF without OP_ENDIF".to_string(),
+            ))
+        }
+    }
+
+    /// A branch only runs when every enclosing branch runs, an OP_ELSE nested in a skipped
+    /// branch must not turn execution back on.
     pub fn should_execute(&self) -> bool {
-        self.if_stack.is_empty() || *self.if_stack.last().unwrap()
+        self.if_stack.iter().all(|execute| *execute)
     }
 }
 
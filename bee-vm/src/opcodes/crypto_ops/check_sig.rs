@@ -1,13 +1,17 @@
 use crate::errors::OpCodeErrors;
 use crate::opcodes::crypto_ops::hash_script;
+use crate::script::decode::script_num_from_bytes;
+use crate::script::encode::{element_to_bytes, tokens_to_bytes};
 use crate::stack::Stack;
+use crate::transaction::context::ExecutionContext;
+use crate::transaction::sighash::SigVersion;
 use k256::ecdsa::signature::Verifier;
 use k256::ecdsa::Signature;
 use k256::ecdsa::VerifyingKey;
 
 pub fn op_checksig(stack: &mut Stack, script: &[String]) -> Result<(), OpCodeErrors> {
     // Check if we have enough elements on the stack
-    if stack.elements.len() < 2 {
+    if stack.len() < 2 {
         return Err(OpCodeErrors::MissingValues(
             "At least 2 values needed for this operation".to_string(),
         ));
@@ -17,11 +21,8 @@ pub fn op_checksig(stack: &mut Stack, script: &[String]) -> Result<(), OpCodeErr
     let signature_hex = stack.pop_from_top().unwrap();
     let public_key_str = stack.pop_from_top().unwrap();
 
-    // Parse the public key/verifying key
-    let public_key: VerifyingKey = VerifyingKey::from_sec1_bytes(
-        &hex::decode(public_key_str).map_err(|_| OpCodeErrors::InvalidPublicKey)?,
-    )
-    .map_err(|_| OpCodeErrors::InvalidPublicKey)?;
+    let public_key = hex::decode(public_key_str).map_err(|_| OpCodeErrors::InvalidPublicKey)?;
+    let signature = hex::decode(&signature_hex).map_err(|_| OpCodeErrors::InvalidSignature)?;
 
     // Create a copy of the script for modification
     let mut script_for_hash = script.to_vec();
@@ -32,12 +33,12 @@ pub fn op_checksig(stack: &mut Stack, script: &[String]) -> Result<(), OpCodeErr
     // Hash the modified script
     let script_hash = hash_script(script_for_hash)?; // same as message
 
-    let signature: Signature = Signature::from_slice(
-        &hex::decode(signature_hex).map_err(|_| OpCodeErrors::InvalidSignature)?,
-    )
-    .map_err(|_| OpCodeErrors::InvalidSignature)?;
-
-    // Verify the signature
+    // The script hash is not a transaction signature hash, the shared signature cache is left
+    // out so that its entries can't stand for each other.
+    let public_key =
+        VerifyingKey::from_sec1_bytes(&public_key).map_err(|_| OpCodeErrors::InvalidPublicKey)?;
+    let signature =
+        Signature::from_slice(&signature).map_err(|_| OpCodeErrors::InvalidSignature)?;
     let result = public_key
         .verify(script_hash.as_slice(), &signature)
         .is_ok();
@@ -48,6 +49,120 @@ pub fn op_checksig(stack: &mut Stack, script: &[String]) -> Result<(), OpCodeErr
     Ok(())
 }
 
+/// OP_CHECKSIG against a real transaction input, with the Bitcoin stack order `<sig> <pubkey>`.
+///
+/// `script` holds the tokens after the last executed OP_CODESEPARATOR (legacy / segwit v0
+/// script code) and `code_separator_position` its opcode position (tapscript).
+pub fn op_checksig_with_context(
+    stack: &mut Stack,
+    script: &[String],
+    code_separator_position: u32,
+    context: &ExecutionContext,
+) -> Result<(), OpCodeErrors> {
+    if stack.len() < 2 {
+        return Err(OpCodeErrors::MissingValues(
+            "At least 2 values needed for this operation".to_string(),
+        ));
+    }
+
+    let public_key = element_to_bytes(&stack.pop_from_top().unwrap());
+    let signature = element_to_bytes(&stack.pop_from_top().unwrap());
+
+    let result = match context.sig_version {
+        SigVersion::Base | SigVersion::WitnessV0 => {
+            let script_code = script_code(script, &[&signature], context.sig_version)?;
+            context
+                .check_ecdsa_signature(&signature, &public_key, &script_code)
+                .map_err(|_| OpCodeErrors::InvalidSignature)?
+        }
+        SigVersion::Taproot => context
+            .check_schnorr_signature(&signature, &public_key, code_separator_position)
+            .map_err(|_| OpCodeErrors::InvalidSignature)?,
+        SigVersion::Tapscript => {
+            check_tapscript_signature(&signature, &public_key, code_separator_position, context)?
+        }
+    };
+
+    stack.push_to_top(if result { "1" } else { "0" }.to_string());
+
+    Ok(())
+}
+
+/// **OP_CHECKSIGADD** (BIP342), with the stack order `<sig> <n> <pubkey>`.
+///
+/// Pushes `n + 1` when the signature is valid and `n` when it is empty. Only tapscript has it.
+pub fn op_checksigadd_with_context(
+    stack: &mut Stack,
+    code_separator_position: u32,
+    context: &ExecutionContext,
+) -> Result<(), OpCodeErrors> {
+    if context.sig_version != SigVersion::Tapscript {
+        return Err(OpCodeErrors::UnknownOpcode);
+    }
+    if stack.len() < 3 {
+        return Err(OpCodeErrors::MissingValues(
+            "At least 3 values needed for this operation".to_string(),
+        ));
+    }
+
+    let public_key = element_to_bytes(&stack.pop_from_top().unwrap());
+    let n = element_to_bytes(&stack.pop_from_top().unwrap());
+    let signature = element_to_bytes(&stack.pop_from_top().unwrap());
+    if n.len() > 4 {
+        return Err(OpCodeErrors::NumberNotInRange);
+    }
+
+    let result =
+        check_tapscript_signature(&signature, &public_key, code_separator_position, context)?;
+    let n = script_num_from_bytes(&n) + result as i64;
+    stack.push_to_top(n.to_string());
+
+    Ok(())
+}
+
+/// BIP342 signature check: an empty signature fails, any other failing signature is an error.
+pub fn check_tapscript_signature(
+    signature: &[u8],
+    public_key: &[u8],
+    code_separator_position: u32,
+    context: &ExecutionContext,
+) -> Result<bool, OpCodeErrors> {
+    if public_key.is_empty() {
+        return Err(OpCodeErrors::InvalidPublicKey);
+    }
+    if signature.is_empty() {
+        return Ok(false);
+    }
+    // Unknown public key types are reserved for soft forks and always succeed.
+    if public_key.len() != 32 {
+        return Ok(true);
+    }
+    match context.check_schnorr_signature(signature, public_key, code_separator_position) {
+        Ok(true) => Ok(true),
+        _ => Err(OpCodeErrors::InvalidSignature),
+    }
+}
+
+/// Serialized script code signed by legacy and segwit v0 signatures.
+///
+/// Legacy signatures can not sign themselves, so their pushes are removed first (FindAndDelete).
+pub fn script_code(
+    script: &[String],
+    signatures: &[&[u8]],
+    sig_version: SigVersion,
+) -> Result<Vec<u8>, OpCodeErrors> {
+    let mut tokens = script.to_vec();
+    if sig_version == SigVersion::Base {
+        tokens.retain(|token| {
+            token.starts_with("OP_")
+                || !signatures
+                    .iter()
+                    .any(|sig| !sig.is_empty() && element_to_bytes(token) == *sig)
+        });
+    }
+    tokens_to_bytes(&tokens).map_err(|e| OpCodeErrors::InvalidValue(e.to_string()))
+}
+
 #[cfg(test)]
 mod check_sigs_test {
     use crate::opcodes::crypto_ops::hash_script;
@@ -101,7 +216,7 @@ mod check_sigs_test {
         let (final_stack, _) = execute_code(script)?;
 
         // Check the result - should be valid
-        assert_eq!(final_stack.read_ele_from_top(0).unwrap(), "1");
+        assert_eq!(final_stack.peek(0).unwrap(), "1");
 
         Ok(())
     }
@@ -119,7 +234,7 @@ mod check_sigs_test {
         script.push("OP_CHECKSIG".to_string());
 
         let (final_stack, _) = execute_code(script)?;
-        assert_eq!(final_stack.read_ele_from_top(0).unwrap(), "0");
+        assert_eq!(final_stack.peek(0).unwrap(), "0");
 
         Ok(())
     }
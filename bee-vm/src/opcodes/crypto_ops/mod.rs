@@ -31,3 +31,39 @@ pub fn hash_script(script: Vec<String>) -> Result<[u8; 32], OpCodeErrors> {
 
     Ok(result)
 }
+
+/// Checks the strict DER encoding rules of BIP66 on a signature with its sighash byte appended.
+pub fn is_valid_signature_encoding(sig: &[u8]) -> bool {
+    // 0x30 [total-length] 0x02 [R-length] [R] 0x02 [S-length] [S] [sighash]
+    if sig.len() < 9 || sig.len() > 73 {
+        return false;
+    }
+    if sig[0] != 0x30 || sig[1] as usize != sig.len() - 3 {
+        return false;
+    }
+
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != sig.len() {
+        return false;
+    }
+
+    // R and S must be positive integers without unnecessary leading zeros.
+    if sig[2] != 0x02 || len_r == 0 || sig[4] & 0x80 != 0 {
+        return false;
+    }
+    if len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0 {
+        return false;
+    }
+    if sig[len_r + 4] != 0x02 || len_s == 0 || sig[len_r + 6] & 0x80 != 0 {
+        return false;
+    }
+    if len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0 {
+        return false;
+    }
+
+    true
+}
@@ -1,13 +1,18 @@
 use crate::errors::OpCodeErrors;
+use crate::opcodes::crypto_ops::check_sig::script_code;
 use crate::opcodes::crypto_ops::hash_script;
+use crate::opcodes::utils::string_to_i32;
+use crate::script::encode::element_to_bytes;
 use crate::stack::Stack;
+use crate::transaction::context::ExecutionContext;
+use crate::transaction::sighash::SigVersion;
 use k256::ecdsa::signature::Verifier;
 use k256::ecdsa::Signature;
 use k256::ecdsa::VerifyingKey;
 
 pub fn op_checkmultisig(stack: &mut Stack, script: &[String]) -> Result<(), OpCodeErrors> {
     // Ensure minimum stack size (n_pubkeys + pubkeys + m_sigs + sigs + dummy)
-    if stack.elements.len() < 4 {
+    if stack.len() < 4 {
         return Err(OpCodeErrors::MissingValues(
             "At least 4 values needed for this operation".to_string(),
         ));
@@ -21,7 +26,7 @@ pub fn op_checkmultisig(stack: &mut Stack, script: &[String]) -> Result<(), OpCo
         .map_err(|_| OpCodeErrors::InvalidValue("Invalid number of public keys".to_string()))?;
 
     // Validate stack has enough elements for public keys
-    if stack.elements.len() < n_pubkeys + 2 {
+    if stack.len() < n_pubkeys + 2 {
         return Err(OpCodeErrors::MissingValues(
             "Not enough values for the specified number of public keys".to_string(),
         ));
@@ -31,10 +36,9 @@ pub fn op_checkmultisig(stack: &mut Stack, script: &[String]) -> Result<(), OpCo
     let mut pubkeys = Vec::with_capacity(n_pubkeys);
     for _ in 0..n_pubkeys {
         let pubkey_str = stack.pop_from_top().unwrap();
-        let public_key: VerifyingKey = VerifyingKey::from_sec1_bytes(
-            &hex::decode(pubkey_str).map_err(|_| OpCodeErrors::InvalidPublicKey)?,
-        )
-        .map_err(|_| OpCodeErrors::InvalidPublicKey)?;
+        let pubkey_bytes = hex::decode(pubkey_str).map_err(|_| OpCodeErrors::InvalidPublicKey)?;
+        let public_key: VerifyingKey = VerifyingKey::from_sec1_bytes(&pubkey_bytes)
+            .map_err(|_| OpCodeErrors::InvalidPublicKey)?;
         pubkeys.push(public_key);
     }
 
@@ -55,13 +59,14 @@ pub fn op_checkmultisig(stack: &mut Stack, script: &[String]) -> Result<(), OpCo
     }
 
     // Check stack size for signatures
-    if stack.elements.len() < required_sigs + 1 {
+    if stack.len() < required_sigs + 1 {
         return Err(OpCodeErrors::MissingValues(
             "Not enough values for the specified number of signatures".to_string(),
         ));
     }
 
-    // Pop and parse signatures with their hash types
+    // Pop and parse signatures once, the verification loop below tries each of them on
+    // several keys
     let mut signatures = Vec::with_capacity(required_sigs);
     for _ in 0..required_sigs {
         let sig_hex = stack.pop_from_top().unwrap();
@@ -69,7 +74,7 @@ pub fn op_checkmultisig(stack: &mut Stack, script: &[String]) -> Result<(), OpCo
             &hex::decode(sig_hex).map_err(|_| OpCodeErrors::InvalidSignature)?,
         )
         .map_err(|_| OpCodeErrors::InvalidSignature)?;
-        signatures.push(hex::encode(signature.to_bytes()));
+        signatures.push(signature);
     }
 
     // Pop the dummy value (Bitcoin consensus bug feature)
@@ -79,8 +84,13 @@ pub fn op_checkmultisig(stack: &mut Stack, script: &[String]) -> Result<(), OpCo
     let mut script_for_hash = script.to_vec();
 
     // Remove all signatures from the script before hashing
-    for sig in &signatures {
-        script_for_hash.retain(|op| !op.contains(&sig.to_string()));
+    let signature_bytes: Vec<Vec<u8>> = signatures
+        .iter()
+        .map(|sig| sig.to_bytes().to_vec())
+        .collect();
+    for sig in &signature_bytes {
+        let sig_hex = hex::encode(sig);
+        script_for_hash.retain(|op| !op.contains(&sig_hex));
     }
     let script_hash = hash_script(script_for_hash)?;
 
@@ -88,20 +98,13 @@ pub fn op_checkmultisig(stack: &mut Stack, script: &[String]) -> Result<(), OpCo
     // Verify signatures
     // ========================
 
+    // Like OP_CHECKSIG without a transaction, the shared signature cache is left out : it
+    // holds transaction signature hashes, not script hashes.
     let mut valid_sigs = 0;
 
     'sig_loop: for sig in signatures.iter() {
-        for pub_key in pubkeys.iter().take(n_pubkeys) {
-            if pub_key
-                .verify(
-                    script_hash.as_slice(),
-                    &Signature::from_slice(
-                        &hex::decode(sig).map_err(|_| OpCodeErrors::InvalidSignature)?,
-                    )
-                    .map_err(|_| OpCodeErrors::InvalidSignature)?,
-                )
-                .is_ok()
-            {
+        for pub_key in pubkeys.iter() {
+            if pub_key.verify(script_hash.as_slice(), sig).is_ok() {
                 valid_sigs += 1;
                 continue 'sig_loop;
             }
@@ -118,10 +121,99 @@ pub fn op_checkmultisig(stack: &mut Stack, script: &[String]) -> Result<(), OpCo
     Ok(())
 }
 
+/// Maximum number of public keys of OP_CHECKMULTISIG.
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
+/// OP_CHECKMULTISIG against a real transaction input, following Bitcoin Core.
+///
+/// Stack (top first) : `n <pubkey_n> .. <pubkey_1> m <sig_m> .. <sig_1> <dummy>`. Signatures
+/// have to be in the same order as their public keys, and it is disabled in tapscript.
+pub fn op_checkmultisig_with_context(
+    stack: &mut Stack,
+    script: &[String],
+    context: &ExecutionContext,
+) -> Result<(), OpCodeErrors> {
+    if matches!(
+        context.sig_version,
+        SigVersion::Taproot | SigVersion::Tapscript
+    ) {
+        return Err(OpCodeErrors::InvalidValue(
+            "OP_CHECKMULTISIG is disabled in tapscript".to_string(),
+        ));
+    }
+
+    let n_pubkeys = pop_count(stack, MAX_PUBKEYS_PER_MULTISIG)?;
+    if stack.len() < n_pubkeys + 1 {
+        return Err(OpCodeErrors::MissingValues(
+            "Not enough values for the specified number of public keys".to_string(),
+        ));
+    }
+    let mut pubkeys: Vec<Vec<u8>> = (0..n_pubkeys)
+        .map(|_| element_to_bytes(&stack.pop_from_top().unwrap()))
+        .collect();
+    pubkeys.reverse();
+
+    let required_sigs = pop_count(stack, n_pubkeys)?;
+    // The signatures and the dummy element.
+    if stack.len() < required_sigs + 1 {
+        return Err(OpCodeErrors::MissingValues(
+            "Not enough values for the specified number of signatures".to_string(),
+        ));
+    }
+    let mut signatures: Vec<Vec<u8>> = (0..required_sigs)
+        .map(|_| element_to_bytes(&stack.pop_from_top().unwrap()))
+        .collect();
+    signatures.reverse();
+    // The dummy element has to be empty (NULLDUMMY, BIP 147).
+    if !element_to_bytes(&stack.pop_from_top().unwrap()).is_empty() {
+        return Err(OpCodeErrors::InvalidValue(
+            "OP_CHECKMULTISIG dummy element is not empty".to_string(),
+        ));
+    }
+
+    let signature_refs: Vec<&[u8]> = signatures.iter().map(|sig| sig.as_slice()).collect();
+    let script_code = script_code(script, &signature_refs, context.sig_version)?;
+
+    // Each public key is tried once, in order, until every signature found its key.
+    let mut success = true;
+    let mut sig_index = 0;
+    let mut key_index = 0;
+    while success && sig_index < signatures.len() {
+        let valid = context
+            .check_ecdsa_signature(&signatures[sig_index], &pubkeys[key_index], &script_code)
+            .map_err(|_| OpCodeErrors::InvalidSignature)?;
+        if valid {
+            sig_index += 1;
+        }
+        key_index += 1;
+        if signatures.len() - sig_index > pubkeys.len() - key_index {
+            success = false;
+        }
+    }
+
+    stack.push_to_top(if success { "1" } else { "0" }.to_string());
+
+    Ok(())
+}
+
+/// Pops a key or signature count in the range `0..=max`.
+fn pop_count(stack: &mut Stack, max: usize) -> Result<usize, OpCodeErrors> {
+    let element = stack.pop_from_top().ok_or(OpCodeErrors::StackEmpty)?;
+    string_to_i32(&element)
+        .ok()
+        .and_then(|count| usize::try_from(count).ok())
+        .filter(|count| *count <= max)
+        .ok_or(OpCodeErrors::InvalidValue(format!(
+            "Invalid multisig count : {}",
+            element
+        )))
+}
+
 #[cfg(test)]
 mod check_multisig_test {
     use crate::opcodes::crypto_ops::hash_script;
     use crate::stack::executor::execute_code;
+    use crate::transaction::sigcache::signature_cache;
     use hex;
     use k256::ecdsa::signature::Signer;
     use k256::ecdsa::Signature;
@@ -183,7 +275,7 @@ mod check_multisig_test {
         script.push("OP_CHECKMULTISIG".to_string());
 
         let (final_stack, _) = execute_code(script)?;
-        assert_eq!(final_stack.read_ele_from_top(0).unwrap(), "1");
+        assert_eq!(final_stack.peek(0).unwrap(), "1");
         Ok(())
     }
 
@@ -215,7 +307,7 @@ mod check_multisig_test {
         script.push("OP_CHECKMULTISIG".to_string());
 
         let (final_stack, _) = execute_code(script)?;
-        assert_eq!(final_stack.read_ele_from_top(0).unwrap(), "0");
+        assert_eq!(final_stack.peek(0).unwrap(), "0");
         Ok(())
     }
 
@@ -258,7 +350,7 @@ mod check_multisig_test {
         script.push("OP_CHECKMULTISIG".to_string());
 
         let (final_stack, _) = execute_code(script)?;
-        assert_eq!(final_stack.read_ele_from_top(0).unwrap(), "1");
+        assert_eq!(final_stack.peek(0).unwrap(), "1");
         Ok(())
     }
 
@@ -282,4 +374,37 @@ mod check_multisig_test {
         assert!(result.is_err());
         Ok(())
     }
+
+    #[rstest]
+    fn test_script_hashes_stay_out_of_the_signature_cache() -> color_eyre::Result<()> {
+        let secret_key = SigningKey::random(&mut OsRng);
+        let public_key = secret_key.verifying_key().to_sec1_bytes();
+        let multisig = |signature: &Signature| {
+            vec![
+                "0".to_string(),
+                hex::encode(signature.to_bytes()),
+                "1".to_string(),
+                hex::encode(&public_key),
+                "1".to_string(),
+                "OP_CHECKMULTISIG".to_string(),
+            ]
+        };
+        let mut signed_script = multisig(&secret_key.sign(&[0; 32]));
+        signed_script.remove(1);
+        let script_hash = hash_script(signed_script)?;
+
+        // A transaction signature hash equal to the script hash does not validate the
+        // signature of another message.
+        let other_signature: Signature = secret_key.sign(&[1; 32]);
+        signature_cache().insert(&script_hash, &public_key, &other_signature.to_bytes());
+        let (final_stack, _) = execute_code(multisig(&other_signature))?;
+        assert_eq!(final_stack.peek(0).unwrap(), "0");
+
+        // And a valid signature of the script hash is not recorded.
+        let signature: Signature = secret_key.sign(&script_hash);
+        let (final_stack, _) = execute_code(multisig(&signature))?;
+        assert_eq!(final_stack.peek(0).unwrap(), "1");
+        assert!(!signature_cache().contains(&script_hash, &public_key, &signature.to_bytes()));
+        Ok(())
+    }
 }
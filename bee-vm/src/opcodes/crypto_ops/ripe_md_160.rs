@@ -1,10 +1,12 @@
 use crate::errors::OpCodeErrors;
+use crate::script::decode::data_to_token;
+use crate::script::encode::element_to_bytes;
 use crate::stack::Stack;
 use ripemd::{Digest, Ripemd160};
 use sha2::Sha256;
 
 pub fn ripe_md_160(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 1 {
+    if vm_state.is_empty() {
         return Err(OpCodeErrors::MissingValue(
             "Need at least one value for ripe md 160".to_string(),
         ));
@@ -13,9 +15,9 @@ pub fn ripe_md_160(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
     let top_element = vm_state.pop_from_top().unwrap();
 
     let mut hasher = Ripemd160::new();
-    hasher.update(top_element);
+    hasher.update(element_to_bytes(&top_element));
     let hasher_result = hasher.finalize();
-    let hash = hex::encode(hasher_result);
+    let hash = data_to_token(&hasher_result);
 
     vm_state.push_to_top(hash);
 
@@ -23,7 +25,7 @@ pub fn ripe_md_160(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
 }
 
 pub fn hash_160(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 1 {
+    if vm_state.is_empty() {
         return Err(OpCodeErrors::MissingValue(
             "Need at least one value for ripe md 160".to_string(),
         ));
@@ -32,14 +34,14 @@ pub fn hash_160(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
     let top_element = vm_state.pop_from_top().unwrap();
 
     // sha 256
-    let hash = Sha256::digest(top_element);
+    let hash = Sha256::digest(element_to_bytes(&top_element));
 
     // ripe md 160
     let mut hasher = Ripemd160::new();
     hasher.update(hash);
     let hasher_result = hasher.finalize();
 
-    vm_state.push_to_top(hex::encode(hasher_result));
+    vm_state.push_to_top(data_to_token(&hasher_result));
     Ok(())
 }
 
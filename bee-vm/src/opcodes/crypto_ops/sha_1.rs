@@ -1,9 +1,11 @@
 use crate::errors::OpCodeErrors;
+use crate::script::decode::data_to_token;
+use crate::script::encode::element_to_bytes;
 use crate::stack::Stack;
 use sha1::{Digest, Sha1};
 
 pub fn sha_1(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 1 {
+    if vm_state.is_empty() {
         return Err(OpCodeErrors::MissingValue(
             "Need at least one value for sha1".to_string(),
         ));
@@ -12,9 +14,9 @@ pub fn sha_1(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
     let top_element = vm_state.pop_from_top().unwrap();
 
     let mut hasher = Sha1::new();
-    hasher.update(top_element);
+    hasher.update(element_to_bytes(&top_element));
     let hasher_result = hasher.finalize();
-    let hash = hex::encode(hasher_result);
+    let hash = data_to_token(&hasher_result);
 
     vm_state.push_to_top(hash);
 
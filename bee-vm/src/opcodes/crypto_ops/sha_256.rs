@@ -1,34 +1,36 @@
 use crate::errors::OpCodeErrors;
+use crate::script::decode::data_to_token;
+use crate::script::encode::element_to_bytes;
 use crate::stack::Stack;
 use sha2::{Digest, Sha256};
 
 pub fn sha_256(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 1 {
+    if vm_state.is_empty() {
         return Err(OpCodeErrors::MissingValue(
             "Need at least one value for sha256".to_string(),
         ));
     }
 
     let top_element = vm_state.pop_from_top().unwrap();
-    let hash = Sha256::digest(top_element);
+    let hash = Sha256::digest(element_to_bytes(&top_element));
 
-    vm_state.push(hex::encode(hash));
+    vm_state.push_to_top(data_to_token(&hash));
 
     Ok(())
 }
 
 pub fn hash_256(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 1 {
+    if vm_state.is_empty() {
         return Err(OpCodeErrors::MissingValue(
             "Need at least one value for sha256".to_string(),
         ));
     }
 
     let top_element = vm_state.pop_from_top().unwrap();
-    let hash = Sha256::digest(top_element);
+    let hash = Sha256::digest(element_to_bytes(&top_element));
     let hash_final = Sha256::digest(hash);
 
-    vm_state.push(hex::encode(hash_final));
+    vm_state.push_to_top(data_to_token(&hash_final));
 
     Ok(())
 }
@@ -42,6 +44,7 @@ mod test_opcode_sha_256 {
 
     #[rstest]
     #[case(vec!["hello".to_string()], vec!["2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_string()])]
+    #[case(vec!["1".to_string(), "hello".to_string()], vec!["1".to_string(), "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_string()])]
     fn test_sha_256(
         #[case] initial: Vec<String>,
         #[case] expected: Vec<String>,
@@ -55,6 +58,7 @@ mod test_opcode_sha_256 {
 
     #[rstest]
     #[case(vec!["hello".to_string()], vec!["9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50".to_string()])]
+    #[case(vec!["1".to_string(), "hello".to_string()], vec!["1".to_string(), "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50".to_string()])]
     fn test_hash_256(
         #[case] initial: Vec<String>,
         #[case] expected: Vec<String>,
@@ -0,0 +1,188 @@
+use crate::errors::OpCodeErrors;
+use crate::script::decode::script_num_from_bytes;
+use crate::script::encode::element_to_bytes;
+use crate::stack::Stack;
+use crate::transaction::context::ExecutionContext;
+
+/// Lock times below this value are block heights, the others unix timestamps.
+pub const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+/// Sequence bit disabling the relative lock time (BIP68).
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: i64 = 1 << 31;
+/// Sequence bit making the relative lock time a multiple of 512 seconds (BIP68).
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: i64 = 1 << 22;
+/// Bits of the sequence holding the relative lock time (BIP68).
+pub const SEQUENCE_LOCKTIME_MASK: i64 = 0x0000ffff;
+
+/// **OP_CHECKLOCKTIMEVERIFY** (BIP65)
+///
+/// Fails unless the transaction lock time has reached the top stack element, which stays on
+/// the stack. Both must be heights or both timestamps, and the input must not be final.
+pub fn op_checklocktimeverify(
+    stack: &mut Stack,
+    context: &ExecutionContext,
+) -> Result<(), OpCodeErrors> {
+    let lock_time = read_locktime(stack, "OP_CHECKLOCKTIMEVERIFY")?;
+    let tx_lock_time = context.tx.lock_time as i64;
+
+    if (lock_time < LOCKTIME_THRESHOLD) != (tx_lock_time < LOCKTIME_THRESHOLD) {
+        return Err(OpCodeErrors::UnsatisfiedLocktime(format!(
+            "lock time {} and transaction lock time {} are not of the same type",
+            lock_time, tx_lock_time
+        )));
+    }
+    if lock_time > tx_lock_time {
+        return Err(OpCodeErrors::UnsatisfiedLocktime(format!(
+            "transaction lock time {} is before {}",
+            tx_lock_time, lock_time
+        )));
+    }
+    if context.tx.inputs[context.input_index].sequence == u32::MAX {
+        return Err(OpCodeErrors::UnsatisfiedLocktime(
+            "the input sequence is final, the lock time is not enforced".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// **OP_CHECKSEQUENCEVERIFY** (BIP112)
+///
+/// Fails unless the input sequence encodes a relative lock time of at least the top stack
+/// element, which stays on the stack. Elements with the disable flag behave like OP_NOP.
+pub fn op_checksequenceverify(
+    stack: &mut Stack,
+    context: &ExecutionContext,
+) -> Result<(), OpCodeErrors> {
+    let sequence = read_locktime(stack, "OP_CHECKSEQUENCEVERIFY")?;
+    if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+        return Ok(());
+    }
+
+    if (context.tx.version as u32) < 2 {
+        return Err(OpCodeErrors::UnsatisfiedLocktime(format!(
+            "relative lock times need a version 2 transaction, found version {}",
+            context.tx.version
+        )));
+    }
+    let tx_sequence = context.tx.inputs[context.input_index].sequence as i64;
+    if tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+        return Err(OpCodeErrors::UnsatisfiedLocktime(format!(
+            "the input sequence {:#010x} disables relative lock times",
+            tx_sequence
+        )));
+    }
+
+    let mask = SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+    let (required, actual) = (sequence & mask, tx_sequence & mask);
+    if (required & SEQUENCE_LOCKTIME_TYPE_FLAG) != (actual & SEQUENCE_LOCKTIME_TYPE_FLAG) {
+        return Err(OpCodeErrors::UnsatisfiedLocktime(format!(
+            "relative lock time {:#010x} and input sequence {:#010x} are not of the same type",
+            required, actual
+        )));
+    }
+    if required > actual {
+        return Err(OpCodeErrors::UnsatisfiedLocktime(format!(
+            "input sequence {:#010x} is below the relative lock time {:#010x}",
+            actual, required
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reads the top stack element as a lock time, a script number of up to 5 bytes.
+fn read_locktime(stack: &Stack, opcode: &str) -> Result<i64, OpCodeErrors> {
+    let element = match stack.peek(0) {
+        Some(element) => element_to_bytes(element),
+        None => return Err(OpCodeErrors::MissingValue(format!("{} : value 1", opcode))),
+    };
+    if element.len() > 5 {
+        return Err(OpCodeErrors::NumberNotInRange);
+    }
+
+    let lock_time = script_num_from_bytes(&element);
+    if lock_time < 0 {
+        return Err(OpCodeErrors::UnsatisfiedLocktime(format!(
+            "negative lock time {}",
+            lock_time
+        )));
+    }
+    Ok(lock_time)
+}
+
+#[cfg(test)]
+mod test_locktime {
+    use crate::errors::OpCodeErrors;
+    use crate::opcodes::locktime::{op_checklocktimeverify, op_checksequenceverify};
+    use crate::stack::Stack;
+    use crate::transaction::context::ExecutionContext;
+    use crate::transaction::sighash::SigVersion;
+    use crate::transaction::{OutPoint, Transaction, TxIn, TxOut};
+    use rstest::rstest;
+
+    fn context(version: i32, sequence: u32, lock_time: u32) -> ExecutionContext {
+        let tx = Transaction {
+            version,
+            inputs: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: [0x11; 32],
+                    vout: 0,
+                },
+                script_sig: vec![],
+                sequence,
+                witness: vec![],
+            }],
+            outputs: vec![],
+            lock_time,
+        };
+        let spent_output = TxOut {
+            value: 1000,
+            script_pubkey: vec![],
+        };
+        ExecutionContext::new(tx, 0, spent_output, SigVersion::WitnessV0).unwrap()
+    }
+
+    #[rstest]
+    #[case("100", 0xfffffffe, 100, true)]
+    #[case("100", 0xfffffffe, 150, true)]
+    #[case("100", 0xfffffffe, 99, false)]
+    #[case("100", 0xffffffff, 150, false)]
+    #[case("100", 0xfffffffe, 500_000_001, false)]
+    #[case("0x0065cd1d", 0xfffffffe, 500_000_001, true)]
+    #[case("-1", 0xfffffffe, 150, false)]
+    fn test_checklocktimeverify(
+        #[case] element: &str,
+        #[case] sequence: u32,
+        #[case] lock_time: u32,
+        #[case] valid: bool,
+    ) {
+        let mut stack = Stack::stack_from(vec![element.to_string()]);
+        let res = op_checklocktimeverify(&mut stack, &context(2, sequence, lock_time));
+        assert_eq!(res.is_ok(), valid, "{:?}", res);
+        if let Err(error) = res {
+            assert!(matches!(error, OpCodeErrors::UnsatisfiedLocktime(_)));
+        }
+        assert_eq!(stack.items(), [element.to_string()]);
+    }
+
+    #[rstest]
+    #[case("144", 2, 144, true)]
+    #[case("144", 2, 200, true)]
+    #[case("144", 2, 143, false)]
+    #[case("144", 1, 200, false)]
+    #[case("144", 2, 0x80000000 | 200, false)]
+    #[case("144", 2, 0x00400000 | 200, false)]
+    #[case("4194305", 2, 0x00400002, true)]
+    #[case("0x0000008000", 1, 0, true)]
+    fn test_checksequenceverify(
+        #[case] element: &str,
+        #[case] version: i32,
+        #[case] sequence: u32,
+        #[case] valid: bool,
+    ) {
+        let mut stack = Stack::stack_from(vec![element.to_string()]);
+        let res = op_checksequenceverify(&mut stack, &context(version, sequence, 0));
+        assert_eq!(res.is_ok(), valid, "{:?}", res);
+        assert_eq!(stack.items(), [element.to_string()]);
+    }
+}
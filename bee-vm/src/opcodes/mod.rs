@@ -6,6 +6,7 @@ pub mod new_num;
 pub mod op_equal;
 pub mod op_reserved;
 pub mod op_return;
+pub mod opcode;
 pub mod utils;
 pub mod zero_not_equal;
 
@@ -14,6 +15,11 @@ pub mod zero_not_equal;
 // ==================================================
 pub mod control_flow;
 
+// ==================================================
+// Lock Time Ops (CHECKLOCKTIMEVERIFY, CHECKSEQUENCEVERIFY)
+// ==================================================
+pub mod locktime;
+
 // ==================================================
 // Arithmetic Ops
 // ==================================================
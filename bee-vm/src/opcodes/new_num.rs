@@ -4,7 +4,7 @@ use crate::stack::Stack;
 
 /// To input an element into the stack_ops
 pub fn new_num(vm_state: &mut Stack, input: String) -> Result<(), OpCodeErrors> {
-    let input_type = check_string_type(&input);
+    let input_type = check_string_type(&input)?;
 
     match input_type {
         StringType::STRING(val) => {
@@ -2,7 +2,7 @@ use crate::errors::OpCodeErrors;
 use crate::stack::Stack;
 
 pub fn op_equal(stack: &mut Stack) -> Result<(), OpCodeErrors> {
-    if stack.length < 2 {
+    if stack.len() < 2 {
         return Err(OpCodeErrors::MissingValues(
             "Stack length is invalid".to_string(),
         ));
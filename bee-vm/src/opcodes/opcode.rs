@@ -0,0 +1,354 @@
+/// A single script opcode, identified by its byte value.
+///
+/// Bee VM executes scripts as a list of string tokens, this type is the bridge between the
+/// token names (`OP_DUP`) and the serialized byte form (`0x76`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Opcode(pub u8);
+
+/// Number of main stack items an opcode consumes and produces when it runs.
+///
+/// Items an opcode only reads (OP_CHECKLOCKTIMEVERIFY) count as popped and pushed back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackEffect {
+    pub pops: usize,
+    pub pushes: usize,
+}
+
+const fn effect(pops: usize, pushes: usize) -> Option<StackEffect> {
+    Some(StackEffect { pops, pushes })
+}
+
+/// Byte values and canonical names of every opcode.
+///
+/// Data pushes (0x01 - 0x4b) are not named opcodes, they are handled by the script encoder.
+const OPCODE_NAMES: &[(u8, &str)] = &[
+    // constants
+    (0x00, "OP_0"),
+    (0x4c, "OP_PUSHDATA1"),
+    (0x4d, "OP_PUSHDATA2"),
+    (0x4e, "OP_PUSHDATA4"),
+    (0x4f, "OP_1NEGATE"),
+    (0x50, "OP_RESERVED"),
+    (0x51, "OP_1"),
+    (0x52, "OP_2"),
+    (0x53, "OP_3"),
+    (0x54, "OP_4"),
+    (0x55, "OP_5"),
+    (0x56, "OP_6"),
+    (0x57, "OP_7"),
+    (0x58, "OP_8"),
+    (0x59, "OP_9"),
+    (0x5a, "OP_10"),
+    (0x5b, "OP_11"),
+    (0x5c, "OP_12"),
+    (0x5d, "OP_13"),
+    (0x5e, "OP_14"),
+    (0x5f, "OP_15"),
+    (0x60, "OP_16"),
+    // flow control
+    (0x61, "OP_NOP"),
+    (0x62, "OP_VER"),
+    (0x63, "OP_IF"),
+    (0x64, "OP_NOTIF"),
+    (0x65, "OP_VERIF"),
+    (0x66, "OP_VERNOTIF"),
+    (0x67, "OP_ELSE"),
+    (0x68, "OP_ENDIF"),
+    (0x69, "OP_VERIFY"),
+    (0x6a, "OP_RETURN"),
+    // stack ops
+    (0x6b, "OP_TOALTSTACK"),
+    (0x6c, "OP_FROMALTSTACK"),
+    (0x6d, "OP_2DROP"),
+    (0x6e, "OP_2DUP"),
+    (0x6f, "OP_3DUP"),
+    (0x70, "OP_2OVER"),
+    (0x71, "OP_2ROT"),
+    (0x72, "OP_2SWAP"),
+    (0x73, "OP_IFDUP"),
+    (0x74, "OP_DEPTH"),
+    (0x75, "OP_DROP"),
+    (0x76, "OP_DUP"),
+    (0x77, "OP_NIP"),
+    (0x78, "OP_OVER"),
+    (0x79, "OP_PICK"),
+    (0x7a, "OP_ROLL"),
+    (0x7b, "OP_ROT"),
+    (0x7c, "OP_SWAP"),
+    (0x7d, "OP_TUCK"),
+    // splice ops
+    (0x7e, "OP_CAT"),
+    (0x7f, "OP_SUBSTR"),
+    (0x80, "OP_LEFT"),
+    (0x81, "OP_RIGHT"),
+    (0x82, "OP_SIZE"),
+    // bit logic
+    (0x83, "OP_INVERT"),
+    (0x84, "OP_AND"),
+    (0x85, "OP_OR"),
+    (0x86, "OP_XOR"),
+    (0x87, "OP_EQUAL"),
+    (0x88, "OP_EQUALVERIFY"),
+    (0x89, "OP_RESERVED1"),
+    (0x8a, "OP_RESERVED2"),
+    // arithmetic
+    (0x8b, "OP_1ADD"),
+    (0x8c, "OP_1SUB"),
+    (0x8d, "OP_2MUL"),
+    (0x8e, "OP_2DIV"),
+    (0x8f, "OP_NEGATE"),
+    (0x90, "OP_ABS"),
+    (0x91, "OP_NOT"),
+    (0x92, "OP_0NOTEQUAL"),
+    (0x93, "OP_ADD"),
+    (0x94, "OP_SUB"),
+    (0x95, "OP_MUL"),
+    (0x96, "OP_DIV"),
+    (0x97, "OP_MOD"),
+    (0x98, "OP_LSHIFT"),
+    (0x99, "OP_RSHIFT"),
+    (0x9a, "OP_BOOLAND"),
+    (0x9b, "OP_BOOLOR"),
+    (0x9c, "OP_NUMEQUAL"),
+    (0x9d, "OP_NUMEQUALVERIFY"),
+    (0x9e, "OP_NUMNOTEQUAL"),
+    (0x9f, "OP_LESSTHAN"),
+    (0xa0, "OP_GREATERTHAN"),
+    (0xa1, "OP_LESSTHANOREQUAL"),
+    (0xa2, "OP_GREATERTHANOREQUAL"),
+    (0xa3, "OP_MIN"),
+    (0xa4, "OP_MAX"),
+    (0xa5, "OP_WITHIN"),
+    // crypto
+    (0xa6, "OP_RIPEMD160"),
+    (0xa7, "OP_SHA1"),
+    (0xa8, "OP_SHA256"),
+    (0xa9, "OP_HASH160"),
+    (0xaa, "OP_HASH256"),
+    (0xab, "OP_CODESEPARATOR"),
+    (0xac, "OP_CHECKSIG"),
+    (0xad, "OP_CHECKSIGVERIFY"),
+    (0xae, "OP_CHECKMULTISIG"),
+    (0xaf, "OP_CHECKMULTISIGVERIFY"),
+    // expansion
+    (0xb0, "OP_NOP1"),
+    (0xb1, "OP_CHECKLOCKTIMEVERIFY"),
+    (0xb2, "OP_CHECKSEQUENCEVERIFY"),
+    (0xb3, "OP_NOP4"),
+    (0xb4, "OP_NOP5"),
+    (0xb5, "OP_NOP6"),
+    (0xb6, "OP_NOP7"),
+    (0xb7, "OP_NOP8"),
+    (0xb8, "OP_NOP9"),
+    (0xb9, "OP_NOP10"),
+    // tapscript
+    (0xba, "OP_CHECKSIGADD"),
+    (0xff, "OP_INVALIDOPCODE"),
+];
+
+/// Alternative names accepted when reading scripts.
+const OPCODE_ALIASES: &[(&str, u8)] = &[
+    ("OP_FALSE", 0x00),
+    ("OP_TRUE", 0x51),
+    ("OP_NOP2", 0xb1),
+    ("OP_NOP3", 0xb2),
+];
+
+impl Opcode {
+    pub const OP_0: Opcode = Opcode(0x00);
+    pub const OP_PUSHDATA1: Opcode = Opcode(0x4c);
+    pub const OP_PUSHDATA2: Opcode = Opcode(0x4d);
+    pub const OP_PUSHDATA4: Opcode = Opcode(0x4e);
+    pub const OP_1NEGATE: Opcode = Opcode(0x4f);
+    pub const OP_1: Opcode = Opcode(0x51);
+    pub const OP_16: Opcode = Opcode(0x60);
+    pub const OP_IF: Opcode = Opcode(0x63);
+    pub const OP_NOTIF: Opcode = Opcode(0x64);
+    pub const OP_ELSE: Opcode = Opcode(0x67);
+    pub const OP_ENDIF: Opcode = Opcode(0x68);
+    pub const OP_TOALTSTACK: Opcode = Opcode(0x6b);
+    pub const OP_FROMALTSTACK: Opcode = Opcode(0x6c);
+    pub const OP_IFDUP: Opcode = Opcode(0x73);
+    pub const OP_PICK: Opcode = Opcode(0x79);
+    pub const OP_ROLL: Opcode = Opcode(0x7a);
+    pub const OP_CODESEPARATOR: Opcode = Opcode(0xab);
+    pub const OP_CHECKMULTISIG: Opcode = Opcode(0xae);
+    pub const OP_CHECKMULTISIGVERIFY: Opcode = Opcode(0xaf);
+
+    /// Looks up an opcode by its name (`OP_DUP`) or one of the accepted aliases (`OP_TRUE`).
+    pub fn from_name(name: &str) -> Option<Opcode> {
+        OPCODE_NAMES
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(code, _)| Opcode(*code))
+            .or_else(|| {
+                OPCODE_ALIASES
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, code)| Opcode(*code))
+            })
+    }
+
+    /// Canonical name of the opcode, `OP_UNKNOWN` for unassigned byte values.
+    pub fn name(&self) -> &'static str {
+        OPCODE_NAMES
+            .iter()
+            .find(|(code, _)| *code == self.0)
+            .map(|(_, n)| *n)
+            .unwrap_or("OP_UNKNOWN")
+    }
+
+    /// Returns true for the direct push opcodes (0x01 - 0x4b) and OP_PUSHDATA1/2/4.
+    pub fn is_push_data(&self) -> bool {
+        (0x01..=0x4e).contains(&self.0)
+    }
+
+    /// Main stack items popped and pushed by the opcode.
+    ///
+    /// `None` for the opcodes whose effect is not fixed : conditionals, the alt stack moves,
+    /// OP_IFDUP, OP_PICK / OP_ROLL and OP_CHECKMULTISIG (which depend on the values on the
+    /// stack) and the opcodes that fail the script (see `fails`).
+    pub fn stack_effect(&self) -> Option<StackEffect> {
+        match self.0 {
+            // OP_0, data pushes, OP_1NEGATE and OP_1 - OP_16
+            0x00..=0x4f | 0x51..=0x60 => effect(0, 1),
+            // OP_NOP, OP_CODESEPARATOR, OP_NOP1 and OP_NOP4 - OP_NOP10
+            0x61 | 0xab | 0xb0 | 0xb3..=0xb9 => effect(0, 0),
+            // OP_VERIFY, OP_DROP
+            0x69 | 0x75 => effect(1, 0),
+            // OP_2DROP
+            0x6d => effect(2, 0),
+            // OP_2DUP, OP_3DUP, OP_2OVER, OP_2ROT, OP_2SWAP
+            0x6e => effect(2, 4),
+            0x6f => effect(3, 6),
+            0x70 => effect(4, 6),
+            0x71 => effect(6, 6),
+            0x72 => effect(4, 4),
+            // OP_DEPTH
+            0x74 => effect(0, 1),
+            // OP_DUP, OP_NIP, OP_OVER, OP_ROT, OP_SWAP, OP_TUCK
+            0x76 => effect(1, 2),
+            0x77 => effect(2, 1),
+            0x78 => effect(2, 3),
+            0x7b => effect(3, 3),
+            0x7c => effect(2, 2),
+            0x7d => effect(2, 3),
+            // OP_SIZE
+            0x82 => effect(1, 2),
+            // OP_EQUAL, OP_EQUALVERIFY
+            0x87 => effect(2, 1),
+            0x88 => effect(2, 0),
+            // OP_1ADD - OP_0NOTEQUAL (without the disabled OP_2MUL / OP_2DIV)
+            0x8b | 0x8c | 0x8f..=0x92 => effect(1, 1),
+            // OP_ADD, OP_SUB, OP_BOOLAND - OP_MAX (without OP_NUMEQUALVERIFY)
+            0x93 | 0x94 | 0x9a..=0x9c | 0x9e..=0xa4 => effect(2, 1),
+            0x9d => effect(2, 0),
+            // OP_WITHIN
+            0xa5 => effect(3, 1),
+            // OP_RIPEMD160 - OP_HASH256
+            0xa6..=0xaa => effect(1, 1),
+            // OP_CHECKSIG, OP_CHECKSIGVERIFY
+            0xac => effect(2, 1),
+            0xad => effect(2, 0),
+            // OP_CHECKLOCKTIMEVERIFY, OP_CHECKSEQUENCEVERIFY leave their operand
+            0xb1 | 0xb2 => effect(1, 1),
+            // OP_CHECKSIGADD
+            0xba => effect(3, 1),
+            _ => None,
+        }
+    }
+
+    /// Opcodes that fail the script when they run : OP_RETURN, the reserved and disabled
+    /// opcodes (OP_CAT, OP_MUL...) and unassigned byte values.
+    pub fn fails(&self) -> bool {
+        matches!(
+            self.0,
+            0x50 | 0x62 | 0x65 | 0x66 | 0x6a | 0x7e..=0x81 | 0x83..=0x86 | 0x89 | 0x8a | 0x8d
+                | 0x8e | 0x95..=0x99 | 0xbb..=0xff
+        )
+    }
+
+    /// Disabled opcodes (OP_CAT, OP_MUL...), which fail a legacy or segwit v0 script even in an
+    /// unexecuted branch.
+    pub fn is_disabled(&self) -> bool {
+        matches!(
+            self.0,
+            0x7e..=0x81 | 0x83..=0x86 | 0x8d | 0x8e | 0x95..=0x99
+        )
+    }
+
+    /// OP_SUCCESSx opcodes of tapscript (BIP342), which make the script succeed wherever they
+    /// appear.
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self.0,
+            0x50 | 0x62 | 0x7e..=0x81 | 0x83..=0x86 | 0x89 | 0x8a | 0x8d | 0x8e | 0x95..=0x99
+                | 0xbb..=0xfe
+        )
+    }
+
+    /// Returns the number pushed by OP_1NEGATE and OP_1 - OP_16.
+    pub fn small_int(&self) -> Option<i64> {
+        match self.0 {
+            0x4f => Some(-1),
+            0x51..=0x60 => Some((self.0 - 0x50) as i64),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_opcode {
+    use crate::opcodes::opcode::Opcode;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("OP_DUP", 0x76)]
+    #[case("OP_CHECKSIG", 0xac)]
+    #[case("OP_TRUE", 0x51)]
+    #[case("OP_FALSE", 0x00)]
+    #[case("OP_NOP2", 0xb1)]
+    fn test_from_name(#[case] name: &str, #[case] expected: u8) {
+        assert_eq!(Opcode::from_name(name), Some(Opcode(expected)));
+    }
+
+    #[rstest]
+    fn test_name_round_trip() {
+        for byte in 0..=255u8 {
+            let opcode = Opcode(byte);
+            if opcode.name() != "OP_UNKNOWN" {
+                assert_eq!(Opcode::from_name(opcode.name()), Some(opcode));
+            }
+        }
+        assert_eq!(Opcode::from_name("OP_NOT_A_REAL_OPCODE"), None);
+    }
+
+    #[rstest]
+    #[case("OP_DUP", Some((1, 2)))]
+    #[case("OP_2ROT", Some((6, 6)))]
+    #[case("OP_CHECKSIGVERIFY", Some((2, 0)))]
+    #[case("OP_CHECKSEQUENCEVERIFY", Some((1, 1)))]
+    #[case("OP_16", Some((0, 1)))]
+    #[case("OP_IF", None)]
+    #[case("OP_PICK", None)]
+    #[case("OP_CAT", None)]
+    fn test_stack_effect(#[case] name: &str, #[case] expected: Option<(usize, usize)>) {
+        let effect = Opcode::from_name(name).unwrap().stack_effect();
+        assert_eq!(effect.map(|e| (e.pops, e.pushes)), expected);
+    }
+
+    #[rstest]
+    fn test_every_opcode_has_an_effect() {
+        // Apart from the few handled by the analyzer itself, opcodes either have a fixed
+        // effect or fail.
+        let variable = [
+            0x63, 0x64, 0x67, 0x68, 0x6b, 0x6c, 0x73, 0x79, 0x7a, 0xae, 0xaf,
+        ];
+        for byte in 0..=255u8 {
+            let opcode = Opcode(byte);
+            assert!(!(opcode.stack_effect().is_some() && opcode.fails()));
+            let known = opcode.stack_effect().is_some() || opcode.fails();
+            assert_eq!(known, !variable.contains(&byte), "{}", opcode.name());
+        }
+    }
+}
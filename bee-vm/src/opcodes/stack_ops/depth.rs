@@ -8,7 +8,7 @@ use crate::stack::Stack;
 /// [ 0x10 0x20 0x30 ]
 /// => [ 0x03 0x10 0x20 0x30 ]
 pub fn depth(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    let stack_depth = vm_state.length;
+    let stack_depth = vm_state.len();
     vm_state.push_to_top(stack_depth.to_string());
     Ok(())
 }
@@ -9,7 +9,13 @@ use crate::stack::Stack;
 ///
 /// [ 0x10 0x20 ]
 /// => [ 0x20 ]
-pub fn op_drop(vm_state: &mut Stack, number_of_drops: i32) -> Result<(), OpCodeErrors> {
+pub fn op_drop(vm_state: &mut Stack, number_of_drops: usize) -> Result<(), OpCodeErrors> {
+    if vm_state.len() < number_of_drops {
+        return Err(OpCodeErrors::MissingValues(format!(
+            "Stack length is less than {}",
+            number_of_drops
+        )));
+    }
     for _ in 0..number_of_drops {
         vm_state.pop_from_top();
     }
@@ -11,21 +11,18 @@ use crate::stack::Stack;
 ///
 /// [ 0x10 0x20 ]
 /// => [ 0x10 0x10 0x20 ]
-pub fn dup(vm_state: &mut Stack, number_of_duplicates: i32) -> Result<(), OpCodeErrors> {
-    let mut dup_stack = Stack::new();
-
-    for i in 0..number_of_duplicates {
-        match vm_state.read_ele_from_top(i) {
-            Some(ele) => dup_stack.push_to_top(ele.to_string()),
-            None => return Err(OpCodeErrors::MissingValue("dup : value 1".to_string())),
-        }
+pub fn dup(vm_state: &mut Stack, number_of_duplicates: usize) -> Result<(), OpCodeErrors> {
+    if vm_state.len() < number_of_duplicates {
+        return Err(OpCodeErrors::MissingValue("dup : value 1".to_string()));
     }
 
-    for i in 0..number_of_duplicates {
-        let ele = dup_stack
-            .read_ele_from_top(i)
-            .expect("Not able to read the element from dup stack_ops.");
-        vm_state.push_to_top(ele.to_string());
+    // The deepest copied item is pushed first, each push moves the next one to the same position
+    for _ in 0..number_of_duplicates {
+        let ele = vm_state
+            .peek(number_of_duplicates - 1)
+            .expect("Not able to read the element from stack_ops.")
+            .clone();
+        vm_state.push_to_top(ele);
     }
 
     Ok(())
@@ -1,5 +1,4 @@
 use crate::errors::OpCodeErrors;
-use crate::opcodes::utils::string_to_i32;
 use crate::stack::Stack;
 
 /// **OP_FROMALTSTACK**
@@ -16,16 +15,16 @@ use crate::stack::Stack;
 ///
 /// => ALT_STACK = []
 pub fn from_alt_stack(vm_state: &mut Stack, alt_stack: &mut Stack) -> Result<(), OpCodeErrors> {
-    let top_stack_item_alt_stack = string_to_i32(match &alt_stack.pop_from_top() {
+    let top_stack_item_alt_stack = match alt_stack.pop_from_top() {
         Some(val) => val,
         None => {
             return Err(OpCodeErrors::MissingValue(
                 "from_alt_stack : value 1".to_string(),
             ))
         }
-    })?;
+    };
 
-    vm_state.push_to_top(top_stack_item_alt_stack.to_string());
+    vm_state.push_to_top(top_stack_item_alt_stack);
 
     Ok(())
 }
@@ -1,25 +1,24 @@
 use crate::errors::OpCodeErrors;
-use crate::opcodes::utils::string_to_i32;
+use crate::opcodes::utils::cast_to_bool;
 use crate::stack::Stack;
 
 /// **OP_IFDUP**
 ///
-/// If the top stack_ops value is not 0, duplicate it.
+/// If the top stack_ops value is not false, duplicate it. Any element can be checked, not only
+/// numbers.
 ///
 /// [ OP_IFDUP 0x10 ]
 /// => [ 0x10 0x10 ]
 pub fn if_dup(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    let top_stack_item = string_to_i32(match &vm_state.pop_from_top() {
-        Some(val) => val,
+    let top_stack_item = match vm_state.peek(0) {
+        Some(val) => val.clone(),
         None => return Err(OpCodeErrors::MissingValue("if_dup : value 1".to_string())),
-    })?;
+    };
 
-    if top_stack_item != 0 {
-        vm_state.push_to_top(top_stack_item.to_string());
+    if cast_to_bool(&top_stack_item) {
+        vm_state.push_to_top(top_stack_item);
     }
 
-    vm_state.push_to_top(top_stack_item.to_string());
-
     Ok(())
 }
 
@@ -32,6 +31,8 @@ mod test_opcode_if_dup {
     #[rstest]
     #[case(vec!["5".to_string(), "1".to_string()], vec!["5".to_string(), "1".to_string(), "1".to_string()])]
     #[case(vec!["4".to_string(), "3".to_string(), "0".to_string()], vec!["4".to_string(), "3".to_string(), "0".to_string()])]
+    #[case(vec!["abcd".to_string()], vec!["abcd".to_string(), "abcd".to_string()])]
+    #[case(vec!["0x0000".to_string()], vec!["0x0000".to_string()])]
     fn test_if_dup(
         #[case] initial: Vec<String>,
         #[case] expected: Vec<String>,
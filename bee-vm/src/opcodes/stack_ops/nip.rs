@@ -1,5 +1,4 @@
 use crate::errors::OpCodeErrors;
-use crate::opcodes::utils::string_to_i32;
 use crate::stack::Stack;
 
 /// **OP_NIP**
@@ -9,13 +8,12 @@ use crate::stack::Stack;
 /// [ OP_NIP 0x20 0x10 0x30 ]
 /// => [ 0x20 0x30 ]
 pub fn nip(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    let top_element = string_to_i32(match &vm_state.pop_from_top() {
-        Some(val) => val,
-        None => return Err(OpCodeErrors::MissingValue("nip : value 1".to_string())),
-    })?;
-
-    vm_state.pop_from_top();
-    vm_state.push_to_top(top_element.to_string());
+    if vm_state.len() < 2 {
+        return Err(OpCodeErrors::MissingValues(
+            "Stack length is less than 2".to_string(),
+        ));
+    }
+    vm_state.remove_at(1)?;
 
     Ok(())
 }
@@ -29,6 +27,7 @@ mod test_opcode_nip {
     #[rstest]
     #[case(vec!["5".to_string(), "1".to_string()], vec!["1".to_string()])]
     #[case(vec!["0".to_string(), "3".to_string(), "4".to_string()], vec!["0".to_string(), "4".to_string()])]
+    #[case(vec!["5".to_string(), "abcd".to_string()], vec!["abcd".to_string()])]
     fn test_nip(
         #[case] initial: Vec<String>,
         #[case] expected: Vec<String>,
@@ -8,7 +8,7 @@ use crate::stack::Stack;
 /// [ OP_OVER 0x30 0x20 0x10 ]
 /// => [ 0x20 0x30 0x20 0x10 ]
 pub fn over(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    match vm_state.read_ele_from_top(1) {
+    match vm_state.peek(1) {
         Some(val) => vm_state.push_to_top(val.to_string()),
         None => return Err(OpCodeErrors::MissingValue("over : value 1".to_string())),
     }
@@ -19,19 +19,18 @@ pub fn over(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
 ///
 /// Copies the pair of items two spaces back in the stack_ops to the front.
 pub fn over_2(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    let mut stack_temp = Stack::new();
-
-    match vm_state.read_ele_from_top(2) {
-        Some(val) => stack_temp.push_to_top(val.to_string()),
-        None => return Err(OpCodeErrors::MissingValue("over_2 : value 1".to_string())),
-    }
-    match vm_state.read_ele_from_top(3) {
-        Some(val) => stack_temp.push_to_top(val.to_string()),
-        None => return Err(OpCodeErrors::MissingValue("over_2 : value 2".to_string())),
+    if vm_state.len() < 4 {
+        return Err(OpCodeErrors::MissingValue("over_2 : value 1".to_string()));
     }
 
-    vm_state.push_to_top(stack_temp.read_ele_from_top(0).unwrap().to_string());
-    vm_state.push_to_top(stack_temp.read_ele_from_top(1).unwrap().to_string());
+    // The first copy moves the second item of the pair to position 3
+    for _ in 0..2 {
+        let val = vm_state
+            .peek(3)
+            .expect("Stack has at least 4 elements")
+            .clone();
+        vm_state.push_to_top(val);
+    }
 
     Ok(())
 }
@@ -14,7 +14,10 @@ pub fn pick(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
         None => return Err(OpCodeErrors::MissingValue("pick : value 1".to_string())),
     })?;
 
-    match vm_state.read_ele_from_top(top_stack_element) {
+    match usize::try_from(top_stack_element)
+        .ok()
+        .and_then(|n| vm_state.peek(n))
+    {
         Some(val) => vm_state.push_to_top(val.to_string()),
         None => {
             return Err(OpCodeErrors::MissingValue(
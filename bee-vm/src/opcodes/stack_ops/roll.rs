@@ -9,7 +9,7 @@ use crate::stack::Stack;
 /// [ OP_ROLL 2 4 3 2 1 ]
 /// => [ 3 4 2 1 ]
 pub fn roll(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 2 {
+    if vm_state.len() < 2 {
         return Err(OpCodeErrors::MissingValues(
             "OP_ROLL: Insufficient items on stack_ops".to_string(),
         ));
@@ -20,18 +20,15 @@ pub fn roll(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
         .expect("[roll] : Not able to pop the element from main stack_ops.");
     let n_i32 = string_to_i32(&n_bytes)?;
 
-    if n_i32 >= vm_state.length {
-        return Err(OpCodeErrors::NIsLargerThanOrEqualToStackSize(
-            "OP_ROLL: n is larger than or equal to the stack_ops size".to_string(),
-        ));
-    }
-
-    if n_i32 > 0 {
-        let len = vm_state.elements.len();
-        let item = vm_state.elements.remove(len - n_i32 as usize);
-        vm_state.length -= 1;
-        vm_state.push_to_top(item);
-    }
+    let item = usize::try_from(n_i32)
+        .ok()
+        .and_then(|n| vm_state.remove_at(n).ok())
+        .ok_or_else(|| {
+            OpCodeErrors::NIsLargerThanOrEqualToStackSize(
+                "OP_ROLL: n is larger than or equal to the stack_ops size".to_string(),
+            )
+        })?;
+    vm_state.push_to_top(item);
 
     Ok(())
 }
@@ -8,27 +8,14 @@ use crate::stack::Stack;
 /// [ OP_ROT 3 2 1 ]
 /// => [ 2 3 1 ]
 pub fn rot(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 3 {
+    if vm_state.len() < 3 {
         return Err(OpCodeErrors::MissingValues(
             "Stack length is less than 3.".to_string(),
         ));
     }
 
-    // Pop the top three elements from the stack
-    let third = vm_state
-        .pop_from_top()
-        .expect("Failed to pop third element.");
-    let second = vm_state
-        .pop_from_top()
-        .expect("Failed to pop second element.");
-    let first = vm_state
-        .pop_from_top()
-        .expect("Failed to pop first element.");
-
-    // Push them back in the correct rotated order
-    vm_state.push_to_top(second);
+    let third = vm_state.remove_at(2)?;
     vm_state.push_to_top(third);
-    vm_state.push_to_top(first);
 
     Ok(())
 }
@@ -38,38 +25,17 @@ pub fn rot(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
 /// The fifth and sixth items back are moved to the top of the stack_ops.
 pub fn rot_2(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
     // Ensure there are at least 6 elements on the stack
-    if vm_state.length < 6 {
+    if vm_state.len() < 6 {
         return Err(OpCodeErrors::MissingValues(
             "Stack length is less than 6.".to_string(),
         ));
     }
 
-    let sixth = vm_state
-        .pop_from_top()
-        .expect("Failed to pop sixth element.");
-    let fifth = vm_state
-        .pop_from_top()
-        .expect("Failed to pop fifth element.");
-    let fourth = vm_state
-        .pop_from_top()
-        .expect("Failed to pop fourth element.");
-    let third = vm_state
-        .pop_from_top()
-        .expect("Failed to pop third element.");
-    let second = vm_state
-        .pop_from_top()
-        .expect("Failed to pop second element.");
-    let first = vm_state
-        .pop_from_top()
-        .expect("Failed to pop first element.");
-
-    // Inserting elements
-    vm_state.push_to_top(third);
-    vm_state.push_to_top(fourth);
-    vm_state.push_to_top(fifth);
+    // The sixth item back moves up to the fifth position once the first one is removed
+    let sixth = vm_state.remove_at(5)?;
+    let fifth = vm_state.remove_at(4)?;
     vm_state.push_to_top(sixth);
-    vm_state.push_to_top(first);
-    vm_state.push_to_top(second);
+    vm_state.push_to_top(fifth);
 
     Ok(())
 }
@@ -1,40 +1,21 @@
 use crate::errors::OpCodeErrors;
-use crate::opcodes::utils::{check_string_type, StringType};
+use crate::script::encode::element_to_bytes;
 use crate::stack::Stack;
 
 /// **OP_SIZE**
 ///
-/// Pushes the string length of the top element of the stack_ops (without popping it).
+/// Pushes the byte length of the top element of the stack_ops (without popping it). Decimal
+/// elements are script numbers, so `0` (the empty vector) has a size of 0.
 ///
 /// [ OP_SIZE "fruit" ]
 /// => [ 5 "fruit" ]
 pub fn size(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length == 0 {
+    if vm_state.is_empty() {
         return Err(OpCodeErrors::MissingValue("Stack is empty.".to_string()));
     }
 
-    let top_element = vm_state.read_ele_from_top(0).unwrap();
-    let string_type = check_string_type(top_element);
-
-    let size = match string_type {
-        StringType::STRING(val) => val.len() as i32,
-        StringType::DECIMAL(val) => {
-            let bits = val.checked_ilog2().unwrap_or(0) + 1;
-            let bytes = (bits + 7) / 8;
-            bytes as i32
-        }
-        StringType::HEX(val) => {
-            let val = val.replace("0x", "");
-            match hex::decode(&val) {
-                Ok(decoded_val) => decoded_val.len() as i32,
-                Err(_) => {
-                    return Err(OpCodeErrors::InvalidHex(
-                        "Failed to decode hex string.".to_string(),
-                    ))
-                }
-            }
-        }
-    };
+    let top_element = vm_state.peek(0).unwrap();
+    let size = element_to_bytes(top_element).len();
 
     vm_state.push_to_top(size.to_string());
 
@@ -52,6 +33,9 @@ mod test_opcode_size {
     #[case(vec!["1234".to_string()], vec!["1234".to_string(), "2".to_string()])]
     #[case(vec!["0x123456".to_string()], vec!["0x123456".to_string(), "3".to_string()])]
     #[case(vec!["fruit".to_string()], vec!["fruit".to_string(), "5".to_string()])]
+    #[case(vec!["0".to_string()], vec!["0".to_string(), "0".to_string()])]
+    #[case(vec!["-200".to_string()], vec!["-200".to_string(), "2".to_string()])]
+    #[case(vec!["128".to_string()], vec!["128".to_string(), "2".to_string()])]
     fn test_size(
         #[case] initial: Vec<String>,
         #[case] expected: Vec<String>,
@@ -8,47 +8,25 @@ use crate::stack::Stack;
 /// [ OP_SWAP 1 2 3 4 ]
 /// => [ 2 1 3 4 ]
 pub fn swap(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 2 {
+    if vm_state.len() < 2 {
         return Err(OpCodeErrors::MissingValues(
             "Stack length is less than 2".to_string(),
         ));
     }
-    let ele_1 = vm_state
-        .pop_from_top()
-        .expect("Not able to get element from stack_ops.");
-    let ele_2 = vm_state
-        .pop_from_top()
-        .expect("Not able to get element from stack_ops.");
-    vm_state.push_to_top(ele_1);
-    vm_state.push_to_top(ele_2);
-    Ok(())
+    vm_state.swap(0, 1)
 }
 
 /// **OP_2SWAP**
 ///
 /// Swaps the top two pairs of items.
 pub fn swap_2(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 4 {
+    if vm_state.len() < 4 {
         return Err(OpCodeErrors::MissingValues(
             "Stack length is less than 4".to_string(),
         ));
     }
-
-    let mut temp_vec = Stack::new();
-    for _ in 0..4 {
-        temp_vec.push_to_top(
-            vm_state
-                .pop_from_top()
-                .expect("Not able to get element from stack_ops."),
-        );
-    }
-
-    vm_state.push_to_top(temp_vec.read_ele_from_top(2).unwrap().to_string());
-    vm_state.push_to_top(temp_vec.read_ele_from_top(3).unwrap().to_string());
-    vm_state.push_to_top(temp_vec.read_ele_from_top(0).unwrap().to_string());
-    vm_state.push_to_top(temp_vec.read_ele_from_top(1).unwrap().to_string());
-
-    Ok(())
+    vm_state.swap(0, 2)?;
+    vm_state.swap(1, 3)
 }
 
 #[cfg(test)]
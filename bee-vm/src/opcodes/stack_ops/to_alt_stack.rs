@@ -1,5 +1,4 @@
 use crate::errors::OpCodeErrors;
-use crate::opcodes::utils::string_to_i32;
 use crate::stack::Stack;
 
 /// **OP_TOALTSTACK**
@@ -16,16 +15,16 @@ use crate::stack::Stack;
 ///
 /// ALT_STACK = [ 0x10 ]
 pub fn to_alt_stack(vm_state: &mut Stack, alt_stack: &mut Stack) -> Result<(), OpCodeErrors> {
-    let top_stack_item = string_to_i32(match &vm_state.pop_from_top() {
+    let top_stack_item = match vm_state.pop_from_top() {
         Some(val) => val,
         None => {
             return Err(OpCodeErrors::MissingValue(
                 "to_alt_stack : value 1".to_string(),
             ))
         }
-    })?;
+    };
 
-    alt_stack.push_to_top(top_stack_item.to_string());
+    alt_stack.push_to_top(top_stack_item);
 
     Ok(())
 }
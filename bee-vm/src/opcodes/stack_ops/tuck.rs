@@ -8,22 +8,17 @@ use crate::stack::Stack;
 /// [ OP_TUCK 1 2 3 4 ]
 /// => [ 1 2 1 3 4 ]
 pub fn tuck(vm_state: &mut Stack) -> Result<(), OpCodeErrors> {
-    if vm_state.length < 2 {
+    if vm_state.len() < 2 {
         return Err(OpCodeErrors::MissingValues(
             "Stack length is less than 2.".to_string(),
         ));
     }
 
     let top = vm_state
-        .pop_from_top()
-        .expect("Stack should have at least 2 elements");
-    let second = vm_state
-        .pop_from_top()
-        .expect("Stack should have at least 2 elements");
-
-    vm_state.push_to_top(top.clone());
-    vm_state.push_to_top(second);
-    vm_state.push_to_top(top);
+        .peek(0)
+        .expect("Stack should have at least 2 elements")
+        .clone();
+    vm_state.insert_at(2, top)?;
 
     Ok(())
 }
@@ -1,11 +1,18 @@
 use crate::errors::OpCodeErrors;
+use crate::script::decode::script_num_from_bytes;
+use crate::script::encode::element_to_bytes;
 
-/// To convert string into `i32` integer
+/// Largest operand of the numeric opcodes, in bytes (consensus).
+pub const MAX_NUM_SIZE: usize = 4;
+
+/// Reads a stack element as a numeric operand : a script number of at most 4 bytes, non
+/// minimal encodings included (MINIMALDATA is not a consensus rule).
 pub fn string_to_i32(s: &str) -> Result<i32, OpCodeErrors> {
-    match s.parse::<i32>() {
-        Ok(num) => Ok(num),
-        Err(_) => Err(OpCodeErrors::NumberNotInRange),
+    let bytes = element_to_bytes(s);
+    if bytes.len() > MAX_NUM_SIZE {
+        return Err(OpCodeErrors::NumberNotInRange);
     }
+    Ok(script_num_from_bytes(&bytes) as i32)
 }
 
 /// To check if the input is in `i32` integer range.
@@ -24,15 +31,50 @@ pub enum StringType {
     STRING(String),
 }
 
+/// Decimal strings out of the `i32` range are an error rather than data.
 #[allow(clippy::if_same_then_else)]
-pub fn check_string_type(s: &str) -> StringType {
-    if s.chars().all(|c| c.is_ascii_digit()) {
-        StringType::DECIMAL(string_to_i32(s).unwrap())
+pub fn check_string_type(s: &str) -> Result<StringType, OpCodeErrors> {
+    Ok(if s.chars().all(|c| c.is_ascii_digit()) {
+        StringType::DECIMAL(string_to_i32(s)?)
     } else if s.starts_with("0x") && s[2..].chars().all(|c| c.is_ascii_hexdigit()) {
         StringType::HEX(s.to_string())
     } else if s.chars().all(|c| c.is_ascii_hexdigit()) {
         StringType::HEX(s.to_string())
     } else {
         StringType::STRING(s.to_string())
+    })
+}
+
+/// Bitcoin truthiness of a stack element : false for the empty vector, zero and negative zero
+/// (all bytes 0x00 except a possible final 0x80), true otherwise.
+pub fn cast_to_bool(element: &str) -> bool {
+    let bytes = element_to_bytes(element);
+    match bytes.split_last() {
+        Some((last, rest)) => rest.iter().any(|b| *b != 0) || (*last != 0 && *last != 0x80),
+        None => false,
     }
 }
+
+/// Encodes a number in the minimal little-endian sign-magnitude format used for script numbers.
+pub fn script_num_to_bytes(n: i64) -> Vec<u8> {
+    if n == 0 {
+        return vec![];
+    }
+
+    let negative = n < 0;
+    let mut abs = n.unsigned_abs();
+    let mut result = vec![];
+    while abs > 0 {
+        result.push((abs & 0xff) as u8);
+        abs >>= 8;
+    }
+
+    // If the most significant byte already uses the sign bit, an extra byte is needed.
+    if result.last().unwrap() & 0x80 != 0 {
+        result.push(if negative { 0x80 } else { 0x00 });
+    } else if negative {
+        *result.last_mut().unwrap() |= 0x80;
+    }
+
+    result
+}
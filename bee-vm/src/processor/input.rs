@@ -1,17 +1,17 @@
 use crate::errors::InputParsingError;
 
 pub fn parse_input(input_codes: String) -> Result<Vec<String>, InputParsingError> {
-    let input_codes_vector: Vec<&str> = input_codes.split_ascii_whitespace().collect();
+    let res = split_tokens(&input_codes);
 
-    if input_codes_vector.len() <= 2 {
+    if res.len() <= 2 {
         return Err(InputParsingError::InputParsingErrorAtRun);
     }
 
-    let mut res: Vec<String> = vec![];
-
-    for code in &input_codes_vector {
-        res.push(code.to_string());
-    }
-
     Ok(res)
 }
+
+/// Splits a whitespace separated script into the tokens run by the executor, without the
+/// minimum length `parse_input` requires from the command line.
+pub fn split_tokens(script: &str) -> Vec<String> {
+    script.split_ascii_whitespace().map(String::from).collect()
+}
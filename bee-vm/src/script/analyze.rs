@@ -0,0 +1,496 @@
+use crate::errors::{AnalysisError, ScriptError};
+use crate::opcodes::crypto_ops::op_check_multi_sig::MAX_PUBKEYS_PER_MULTISIG;
+use crate::opcodes::opcode::Opcode;
+use crate::script::decode::{minimal_script_num, Instruction, Instructions};
+use crate::script::encode::tokens_to_bytes;
+use crate::stack::MAX_STACK_SIZE;
+
+/// What the analyzer knows about a stack item.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    /// A number pushed by the script (OP_0, OP_1 - OP_16 or a small push).
+    Number(i64),
+    /// Initial stack items and the results of opcodes.
+    Unknown,
+}
+
+/// The stack along one path through the script.
+#[derive(Debug, Clone)]
+struct State {
+    /// Items with the top last, the initial items read so far are at the bottom.
+    stack: Vec<Value>,
+    alt_stack: usize,
+    /// Initial stack items the path has reached down to.
+    borrowed: usize,
+    /// The path ran an opcode that fails the script.
+    failed: bool,
+}
+
+impl State {
+    fn height(&self) -> isize {
+        self.stack.len() as isize - self.borrowed as isize
+    }
+
+    /// Items on the stack, the initial stack being `initial_items` or, for a witness, the
+    /// items this path reads.
+    fn items(&self, initial_items: Option<usize>) -> isize {
+        initial_items.unwrap_or(self.borrowed) as isize + self.height()
+    }
+
+    /// Value `depth` items below the top, if the path has it.
+    fn peek(&self, depth: usize) -> Option<Value> {
+        self.stack.iter().rev().nth(depth).copied()
+    }
+}
+
+/// An OP_IF / OP_NOTIF waiting for its OP_ENDIF.
+struct Branch {
+    position: usize,
+    /// The path not being analyzed, every OP_ELSE switches paths.
+    other: State,
+    in_first_path: bool,
+}
+
+/// Stack effect of one instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// Byte offset of the instruction in the script.
+    pub position: usize,
+    pub instruction: Instruction,
+    pub pops: usize,
+    pub pushes: usize,
+    /// Stack height after the instruction, relative to the stack the script starts with.
+    pub height: isize,
+}
+
+/// Result of the static analysis of a script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    pub steps: Vec<Step>,
+    /// Items the script reads from the stack it starts with (the witness items of a witness
+    /// script), on its most demanding path.
+    pub witness_items: usize,
+    /// Items the script leaves on the stack (from a witness of exactly the items its path
+    /// reads). `None` when every path fails.
+    pub final_items: Option<isize>,
+    /// False when an OP_PICK / OP_ROLL / OP_CHECKMULTISIG operand or an OP_IFDUP outcome is
+    /// not known before running the script, the counts then assume the smallest operand.
+    pub exact: bool,
+    pub errors: Vec<AnalysisError>,
+}
+
+impl Analysis {
+    pub fn is_well_formed(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+struct Analyzer {
+    initial_items: Option<usize>,
+    witness_items: usize,
+    exact: bool,
+    errors: Vec<AnalysisError>,
+}
+
+impl Analyzer {
+    /// Pops `count` items, reaching into the initial stack when the path has fewer.
+    fn take(
+        &mut self,
+        state: &mut State,
+        count: usize,
+        position: usize,
+        opcode: Opcode,
+    ) -> Vec<Value> {
+        if state.stack.len() < count {
+            let missing = count - state.stack.len();
+            if let Some(initial_items) = self.initial_items {
+                if state.borrowed + missing > initial_items && !state.failed {
+                    self.errors.push(AnalysisError::StackUnderflow {
+                        position,
+                        opcode: opcode.name().to_string(),
+                        needed: count,
+                        available: state.stack.len() + initial_items.saturating_sub(state.borrowed),
+                    });
+                }
+            }
+            state.stack.splice(0..0, vec![Value::Unknown; missing]);
+            state.borrowed += missing;
+            if !state.failed {
+                self.witness_items = self.witness_items.max(state.borrowed);
+            }
+        }
+        state.stack.split_off(state.stack.len() - count)
+    }
+
+    /// Operand of OP_PICK / OP_ROLL / OP_CHECKMULTISIG, 0 when it is not known and `None`
+    /// when it is outside `0..=limit` and fails the script.
+    fn operand(&mut self, value: Option<Value>, limit: usize) -> Option<usize> {
+        match value {
+            Some(Value::Number(n)) => (0..=limit as i64).contains(&n).then_some(n as usize),
+            _ => {
+                self.exact = false;
+                Some(0)
+            }
+        }
+    }
+
+    /// Joins the two paths of a conditional at its OP_ENDIF.
+    ///
+    /// With a witness each path gets the items it reads, so paths reading a different number
+    /// of them (like or_d) are balanced when they leave as many items above those.
+    fn merge(&mut self, first: State, second: State, position: usize) -> State {
+        if first.failed {
+            return second;
+        }
+        if second.failed {
+            return first;
+        }
+        let items = first.items(self.initial_items);
+        if items != second.items(self.initial_items) {
+            self.errors.push(AnalysisError::UnbalancedBranches {
+                position,
+                first: items,
+                second: second.items(self.initial_items),
+            });
+            return first;
+        }
+        let borrowed = first.borrowed.max(second.borrowed);
+        let length = match self.initial_items {
+            Some(_) => (first.height() + borrowed as isize) as usize,
+            None => first.stack.len(),
+        };
+        let mut stack: Vec<Value> = (0..length)
+            .map(|depth| match (first.peek(depth), second.peek(depth)) {
+                (Some(a), Some(b)) if a == b => a,
+                _ => Value::Unknown,
+            })
+            .collect();
+        stack.reverse();
+        State {
+            stack,
+            alt_stack: first.alt_stack.max(second.alt_stack),
+            borrowed,
+            failed: false,
+        }
+    }
+}
+
+/// Computes the stack effect of every instruction of serialized script bytes, following both
+/// paths of each conditional, without running the script.
+///
+/// `initial_items` is the size of the stack the script starts with when it is known (0 for a
+/// Bee VM script or a scriptSig), reading below it is a guaranteed underflow. With `None` the
+/// initial stack is a witness providing whatever the script reads, see `witness_items`.
+pub fn analyze(script: &[u8], initial_items: Option<usize>) -> Result<Analysis, ScriptError> {
+    let mut instructions = vec![];
+    let mut iter = Instructions::new(script);
+    loop {
+        let position = iter.position();
+        match iter.next() {
+            Some(instruction) => instructions.push((position, instruction?)),
+            None => break,
+        }
+    }
+
+    let mut analyzer = Analyzer {
+        initial_items,
+        witness_items: 0,
+        exact: true,
+        errors: vec![],
+    };
+    let mut state = State {
+        stack: vec![],
+        alt_stack: 0,
+        borrowed: 0,
+        failed: false,
+    };
+    let mut branches: Vec<Branch> = vec![];
+    let mut steps = vec![];
+    // OP_IFDUP before a conditional only duplicates a true condition.
+    let mut if_dup = false;
+
+    for (index, (position, instruction)) in instructions.iter().enumerate() {
+        let position = *position;
+        let opcode = match instruction {
+            Instruction::Push { data, .. } => {
+                let value = minimal_script_num(data).map_or(Value::Unknown, Value::Number);
+                state.stack.push(value);
+                steps.push(Step {
+                    position,
+                    instruction: instruction.clone(),
+                    pops: 0,
+                    pushes: 1,
+                    height: state.height(),
+                });
+                continue;
+            }
+            Instruction::Op(opcode) => *opcode,
+        };
+
+        let (pops, pushes) = match opcode {
+            Opcode::OP_IF | Opcode::OP_NOTIF => {
+                analyzer.take(&mut state, 1, position, opcode);
+                let mut false_state = state.clone();
+                if std::mem::take(&mut if_dup) {
+                    analyzer.take(&mut false_state, 1, position, opcode);
+                }
+                let (first, other) = match opcode {
+                    Opcode::OP_IF => (state, false_state),
+                    _ => (false_state, state),
+                };
+                branches.push(Branch {
+                    position,
+                    other,
+                    in_first_path: true,
+                });
+                state = first;
+                (1, 0)
+            }
+            Opcode::OP_ELSE => {
+                match branches.last_mut() {
+                    Some(branch) => {
+                        std::mem::swap(&mut state, &mut branch.other);
+                        branch.in_first_path = !branch.in_first_path;
+                    }
+                    None => analyzer.errors.push(AnalysisError::UnmatchedConditional(
+                        position,
+                        opcode.name().to_string(),
+                    )),
+                }
+                (0, 0)
+            }
+            Opcode::OP_ENDIF => {
+                match branches.pop() {
+                    Some(branch) => {
+                        state = match branch.in_first_path {
+                            true => analyzer.merge(state, branch.other, position),
+                            false => analyzer.merge(branch.other, state, position),
+                        };
+                    }
+                    None => analyzer.errors.push(AnalysisError::UnmatchedConditional(
+                        position,
+                        opcode.name().to_string(),
+                    )),
+                }
+                (0, 0)
+            }
+            Opcode::OP_IFDUP => {
+                let value = analyzer.take(&mut state, 1, position, opcode)[0];
+                let next = instructions.get(index + 1).map(|(_, next)| next);
+                match next {
+                    Some(Instruction::Op(Opcode::OP_IF | Opcode::OP_NOTIF)) => if_dup = true,
+                    _ => analyzer.exact = false,
+                }
+                // Counted as a true value, the conditional drops the copy on the false path.
+                state.stack.extend([value, value]);
+                (1, 2)
+            }
+            Opcode::OP_TOALTSTACK => {
+                analyzer.take(&mut state, 1, position, opcode);
+                state.alt_stack += 1;
+                (1, 0)
+            }
+            Opcode::OP_FROMALTSTACK => {
+                match state.alt_stack {
+                    0 if !state.failed => analyzer
+                        .errors
+                        .push(AnalysisError::AltStackUnderflow(position)),
+                    0 => {}
+                    _ => state.alt_stack -= 1,
+                }
+                state.stack.push(Value::Unknown);
+                (0, 1)
+            }
+            Opcode::OP_PICK | Opcode::OP_ROLL => {
+                match analyzer.operand(state.peek(0), MAX_STACK_SIZE) {
+                    Some(n) => {
+                        let mut items = analyzer.take(&mut state, n + 2, position, opcode);
+                        items.pop();
+                        let item = items[0];
+                        if opcode == Opcode::OP_ROLL {
+                            items.remove(0);
+                        }
+                        state.stack.extend(items);
+                        state.stack.push(item);
+                        match opcode {
+                            Opcode::OP_PICK => (n + 2, n + 2),
+                            _ => (n + 2, n + 1),
+                        }
+                    }
+                    None => {
+                        state.failed = true;
+                        (0, 0)
+                    }
+                }
+            }
+            Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY => {
+                // <dummy> <signatures> <m> <keys> <n>
+                let counts = analyzer
+                    .operand(state.peek(0), MAX_PUBKEYS_PER_MULTISIG)
+                    .and_then(|keys| Some((keys, analyzer.operand(state.peek(keys + 1), keys)?)));
+                match counts {
+                    Some((keys, signatures)) => {
+                        let pops = keys + signatures + 3;
+                        analyzer.take(&mut state, pops, position, opcode);
+                        match opcode {
+                            Opcode::OP_CHECKMULTISIG => {
+                                state.stack.push(Value::Unknown);
+                                (pops, 1)
+                            }
+                            _ => (pops, 0),
+                        }
+                    }
+                    None => {
+                        state.failed = true;
+                        (0, 0)
+                    }
+                }
+            }
+            _ => match opcode.stack_effect() {
+                Some(effect) => {
+                    analyzer.take(&mut state, effect.pops, position, opcode);
+                    let value = match (opcode.small_int(), opcode) {
+                        (Some(n), _) => Value::Number(n),
+                        (None, Opcode::OP_0) => Value::Number(0),
+                        _ => Value::Unknown,
+                    };
+                    state.stack.extend(vec![value; effect.pushes]);
+                    (effect.pops, effect.pushes)
+                }
+                None => {
+                    state.failed = true;
+                    (0, 0)
+                }
+            },
+        };
+        steps.push(Step {
+            position,
+            instruction: instruction.clone(),
+            pops,
+            pushes,
+            height: state.height(),
+        });
+    }
+
+    for branch in &branches {
+        analyzer
+            .errors
+            .push(AnalysisError::UnterminatedConditional(branch.position));
+    }
+
+    Ok(Analysis {
+        steps,
+        witness_items: analyzer.witness_items,
+        final_items: (!state.failed).then(|| state.items(initial_items)),
+        exact: analyzer.exact,
+        errors: analyzer.errors,
+    })
+}
+
+/// Analyzes a Bee VM token list through its serialized form.
+pub fn analyze_tokens(
+    tokens: &[String],
+    initial_items: Option<usize>,
+) -> Result<Analysis, ScriptError> {
+    analyze(&tokens_to_bytes(tokens)?, initial_items)
+}
+
+#[cfg(test)]
+mod test_analyze {
+    use crate::errors::AnalysisError;
+    use crate::processor::input::split_tokens;
+    use crate::script::analyze::analyze_tokens;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "OP_DUP OP_HASH160 89abcdefabbaabbaabbaabbaabbaabbaabbaabba OP_EQUALVERIFY OP_CHECKSIG",
+        2,
+        Some(1)
+    )]
+    #[case("2 02aa 02bb 02cc 3 OP_CHECKMULTISIG", 3, Some(1))]
+    #[case(
+        "OP_SWAP OP_SIZE 32 OP_EQUALVERIFY OP_SHA256 aabb OP_EQUALVERIFY OP_CHECKSIG",
+        3,
+        Some(1)
+    )]
+    #[case(
+        "OP_IF 02aa OP_ELSE 144 OP_CHECKSEQUENCEVERIFY OP_DROP 02bb OP_ENDIF OP_CHECKSIG",
+        2,
+        Some(1)
+    )]
+    // or_d(pk(A),and_v(v:pkh(B),older(144))) : 1 item on the first path, 3 on the second
+    #[case("02aa OP_CHECKSIG OP_IFDUP OP_NOTIF OP_DUP OP_HASH160 aabb OP_EQUALVERIFY OP_CHECKSIGVERIFY 144 OP_CHECKSEQUENCEVERIFY OP_ENDIF", 3, Some(1))]
+    #[case("3 OP_PICK OP_DROP", 4, Some(4))]
+    #[case("2 OP_ROLL OP_ADD OP_ADD", 3, Some(1))]
+    #[case("OP_TOALTSTACK OP_FROMALTSTACK", 1, Some(1))]
+    #[case("1 OP_RETURN", 0, None)]
+    fn test_witness_items(
+        #[case] script: &str,
+        #[case] witness_items: usize,
+        #[case] final_items: Option<isize>,
+    ) {
+        let analysis = analyze_tokens(&split_tokens(script), None).unwrap();
+        assert_eq!(analysis.errors, vec![]);
+        assert!(analysis.exact);
+        assert_eq!(analysis.witness_items, witness_items);
+        assert_eq!(analysis.final_items, final_items);
+    }
+
+    #[rstest]
+    fn test_steps() {
+        let analysis = analyze_tokens(&split_tokens("1 2 OP_2DUP OP_ADD OP_NIP"), Some(0)).unwrap();
+        let effects: Vec<(usize, usize, isize)> = analysis
+            .steps
+            .iter()
+            .map(|step| (step.pops, step.pushes, step.height))
+            .collect();
+        assert_eq!(
+            effects,
+            vec![(0, 1, 1), (0, 1, 2), (2, 4, 4), (2, 1, 3), (2, 1, 2)]
+        );
+        assert_eq!(analysis.steps[2].position, 2);
+    }
+
+    #[rstest]
+    #[case("1 OP_ADD", AnalysisError::StackUnderflow { position: 1, opcode: "OP_ADD".to_string(), needed: 2, available: 1 })]
+    #[case("1 OP_IF OP_DROP 1 OP_ENDIF", AnalysisError::StackUnderflow { position: 2, opcode: "OP_DROP".to_string(), needed: 1, available: 0 })]
+    #[case("OP_FROMALTSTACK", AnalysisError::AltStackUnderflow(0))]
+    #[case("1 OP_ELSE", AnalysisError::UnmatchedConditional(1, "OP_ELSE".to_string()))]
+    #[case("1 OP_ENDIF", AnalysisError::UnmatchedConditional(1, "OP_ENDIF".to_string()))]
+    #[case("1 OP_IF 2", AnalysisError::UnterminatedConditional(1))]
+    #[case("1 1 OP_IF 2 OP_ELSE 2 3 OP_ENDIF", AnalysisError::UnbalancedBranches { position: 7, first: 2, second: 3 })]
+    #[case("1 1 OP_NOTIF 2 OP_ENDIF", AnalysisError::UnbalancedBranches { position: 4, first: 2, second: 1 })]
+    fn test_errors(#[case] script: &str, #[case] expected: AnalysisError) {
+        let analysis = analyze_tokens(&split_tokens(script), Some(0)).unwrap();
+        assert!(!analysis.is_well_formed());
+        assert_eq!(analysis.errors, vec![expected]);
+    }
+
+    #[rstest]
+    // Reading the initial stack is only an underflow when its size is known.
+    #[case("OP_ADD", Some(2), true)]
+    #[case("OP_ADD", Some(1), false)]
+    #[case("OP_ADD", None, true)]
+    // A failing path does not underflow, nor do branches ending in OP_RETURN unbalance.
+    #[case("OP_RETURN OP_ADD", Some(0), true)]
+    #[case("1 OP_IF 1 OP_ELSE OP_RETURN OP_ENDIF", Some(0), true)]
+    fn test_well_formed(
+        #[case] script: &str,
+        #[case] initial_items: Option<usize>,
+        #[case] expected: bool,
+    ) {
+        let analysis = analyze_tokens(&split_tokens(script), initial_items).unwrap();
+        assert_eq!(analysis.is_well_formed(), expected, "{:?}", analysis.errors);
+    }
+
+    #[rstest]
+    #[case("OP_PICK")]
+    #[case("OP_DEPTH OP_ROLL")]
+    #[case("OP_CHECKMULTISIG")]
+    #[case("OP_IFDUP OP_DROP")]
+    fn test_not_exact(#[case] script: &str) {
+        let analysis = analyze_tokens(&split_tokens(script), None).unwrap();
+        assert!(!analysis.exact);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::errors::ScriptError;
+use crate::opcodes::crypto_ops::is_valid_signature_encoding;
+use crate::opcodes::opcode::Opcode;
+use crate::script::decode::{
+    minimal_script_num, script_num_from_bytes, tokens_to_instructions, Instruction, Instructions,
+};
+use crate::script::encode::{element_to_bytes, tokens_to_bytes};
+use crate::stack::Stack;
+
+/// Indentation used for every level of nested IF/ELSE/ENDIF blocks.
+const INDENT: &str = "    ";
+
+/// Guessed meaning of a data push, used to annotate pretty printed scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushType {
+    Number,
+    PublicKey,
+    XOnlyPublicKey,
+    Signature,
+    SchnorrSignature,
+    Hash160,
+    Data,
+}
+
+impl PushType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PushType::Number => "number",
+            PushType::PublicKey => "pubkey",
+            PushType::XOnlyPublicKey => "x-only pubkey / hash256",
+            PushType::Signature => "signature",
+            PushType::SchnorrSignature => "schnorr signature",
+            PushType::Hash160 => "hash160",
+            PushType::Data => "data",
+        }
+    }
+}
+
+/// Guesses what a pushed value is from its length and shape.
+pub fn guess_push_type(data: &[u8]) -> PushType {
+    if minimal_script_num(data).is_some() {
+        return PushType::Number;
+    }
+    match data.len() {
+        33 if data[0] == 0x02 || data[0] == 0x03 => PushType::PublicKey,
+        65 if data[0] == 0x04 => PushType::PublicKey,
+        32 => PushType::XOnlyPublicKey,
+        20 => PushType::Hash160,
+        64 => PushType::SchnorrSignature,
+        65 if is_sighash_type(data[64]) => PushType::SchnorrSignature,
+        _ if is_valid_signature_encoding(data) => PushType::Signature,
+        _ => PushType::Data,
+    }
+}
+
+/// Bitcoin Core `ScriptToAsmStr` for serialized script bytes.
+pub fn to_asm(script: &[u8]) -> String {
+    script_to_asm(script, false)
+}
+
+/// Same as [`to_asm`], but signatures are printed with their decoded sighash type
+/// (`<der-hex>[ALL]`), like Core does with `fAttemptSighashDecode`.
+pub fn to_asm_with_sighash(script: &[u8]) -> String {
+    script_to_asm(script, true)
+}
+
+/// Canonical ASM for a Bee VM token list.
+pub fn tokens_to_asm(tokens: &[String]) -> Result<String, ScriptError> {
+    Ok(to_asm(&tokens_to_bytes(tokens)?))
+}
+
+fn script_to_asm(script: &[u8], attempt_sighash_decode: bool) -> String {
+    let unspendable = script.first() == Some(&0x6a);
+    let mut parts: Vec<String> = vec![];
+
+    for instruction in Instructions::new(script) {
+        match instruction {
+            Ok(Instruction::Op(opcode)) => parts.push(op_name(opcode)),
+            Ok(Instruction::Push { data, .. }) => {
+                if data.len() <= 4 {
+                    parts.push(script_num_from_bytes(&data).to_string());
+                } else if attempt_sighash_decode
+                    && !unspendable
+                    && is_valid_signature_encoding(&data)
+                    && sighash_name(data[data.len() - 1]).is_some()
+                {
+                    let (sig, hash_type) = data.split_at(data.len() - 1);
+                    parts.push(format!(
+                        "{}[{}]",
+                        hex::encode(sig),
+                        sighash_name(hash_type[0]).unwrap()
+                    ));
+                } else {
+                    parts.push(hex::encode(data));
+                }
+            }
+            Err(_) => {
+                parts.push("[error]".to_string());
+                break;
+            }
+        }
+    }
+
+    parts.join(" ")
+}
+
+/// Human friendly listing of a script: one instruction per line, IF/ELSE/ENDIF blocks are
+/// indented and pushes are annotated with their length and guessed type.
+pub fn pretty_print(instructions: &[Instruction]) -> String {
+    let mut lines = vec![];
+    let mut depth: usize = 0;
+
+    for instruction in instructions {
+        match instruction {
+            Instruction::Op(opcode) => {
+                let name = op_name(*opcode);
+                match name.as_str() {
+                    "OP_ELSE" => lines.push(format!(
+                        "{}{}",
+                        INDENT.repeat(depth.saturating_sub(1)),
+                        name
+                    )),
+                    "OP_ENDIF" => {
+                        depth = depth.saturating_sub(1);
+                        lines.push(format!("{}{}", INDENT.repeat(depth), name));
+                    }
+                    _ => {
+                        lines.push(format!("{}{}", INDENT.repeat(depth), name));
+                        if name == "OP_IF" || name == "OP_NOTIF" {
+                            depth += 1;
+                        }
+                    }
+                }
+            }
+            Instruction::Push { data, .. } => {
+                let push_type = guess_push_type(data);
+                let value = match push_type {
+                    PushType::Number => minimal_script_num(data).unwrap().to_string(),
+                    _ => format!("0x{}", hex::encode(data)),
+                };
+                lines.push(format!(
+                    "{}<{}>  // {} bytes, {}",
+                    INDENT.repeat(depth),
+                    value,
+                    data.len(),
+                    push_type.label()
+                ));
+            }
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Pretty prints a Bee VM token list, see [`pretty_print`].
+pub fn pretty_print_tokens(tokens: &[String]) -> Result<String, ScriptError> {
+    Ok(pretty_print(&tokens_to_instructions(tokens)?))
+}
+
+/// Debug trace of one executor step : the instruction, then the main and alt stacks from
+/// their top, every element printed like a push of [`pretty_print`].
+pub fn trace_step(code: &str, main_stack: &Stack, alt_stack: &Stack) -> String {
+    let instruction = pretty_print_tokens(std::slice::from_ref(&code.to_string()))
+        .unwrap_or_else(|_| code.to_string());
+    format!(
+        "{}\nSTACK :\n{}\nALT STACK :\n{}",
+        instruction,
+        pretty_print_stack(main_stack),
+        pretty_print_stack(alt_stack)
+    )
+}
+
+fn pretty_print_stack(stack: &Stack) -> String {
+    let pushes: Vec<Instruction> = stack
+        .items()
+        .iter()
+        .rev()
+        .map(|item| {
+            let data = element_to_bytes(item);
+            let opcode = match data.len() {
+                length @ 0..=0x4b => Opcode(length as u8),
+                0x4c..=0xff => Opcode::OP_PUSHDATA1,
+                0x100..=0xffff => Opcode::OP_PUSHDATA2,
+                _ => Opcode::OP_PUSHDATA4,
+            };
+            Instruction::Push { opcode, data }
+        })
+        .collect();
+    pretty_print(&pushes)
+        .lines()
+        .map(|line| format!("{}{}", INDENT, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Name used by Core for an opcode (small numbers are printed as numbers).
+fn op_name(opcode: Opcode) -> String {
+    match opcode.small_int() {
+        Some(n) => n.to_string(),
+        None if opcode == Opcode::OP_0 => "0".to_string(),
+        None => opcode.name().to_string(),
+    }
+}
+
+fn is_sighash_type(byte: u8) -> bool {
+    sighash_name(byte).is_some()
+}
+
+fn sighash_name(byte: u8) -> Option<&'static str> {
+    match byte {
+        0x01 => Some("ALL"),
+        0x02 => Some("NONE"),
+        0x03 => Some("SINGLE"),
+        0x81 => Some("ALL|ANYONECANPAY"),
+        0x82 => Some("NONE|ANYONECANPAY"),
+        0x83 => Some("SINGLE|ANYONECANPAY"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_asm {
+    use crate::script::asm::{
+        guess_push_type, pretty_print_tokens, to_asm, to_asm_with_sighash, trace_step, PushType,
+    };
+    use crate::stack::Stack;
+    use rstest::rstest;
+
+    const SIG: &str = "3045022100aba3db643a5da7855d6694172cbe0e94334720da930d076b421869599d50d7a502203decd2fdff6a5303aacbad30dc2310690912283555e9f43efa9ea650f87457b1";
+    const PUBKEY: &str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+
+    #[rstest]
+    #[case(
+        "76a91489abcdefabbaabbaabbaabbaabbaabbaabbaabba88ac",
+        "OP_DUP OP_HASH160 89abcdefabbaabbaabbaabbaabbaabbaabbaabba OP_EQUALVERIFY OP_CHECKSIG"
+    )]
+    #[case("0051604f", "0 1 16 -1")]
+    #[case("02e803010a", "1000 10")]
+    #[case("6a0568656c6c6f", "OP_RETURN 68656c6c6f")]
+    #[case("76a905aabb", "OP_DUP OP_HASH160 [error]")]
+    #[case(
+        "b1b2ba",
+        "OP_CHECKLOCKTIMEVERIFY OP_CHECKSEQUENCEVERIFY OP_CHECKSIGADD"
+    )]
+    fn test_to_asm(#[case] script: &str, #[case] expected: &str) {
+        assert_eq!(to_asm(&hex::decode(script).unwrap()), expected);
+    }
+
+    #[rstest]
+    fn test_to_asm_with_sighash() {
+        let script = hex::decode(format!("48{}0121{}", SIG, PUBKEY)).unwrap();
+        assert_eq!(
+            to_asm_with_sighash(&script),
+            format!("{}[ALL] {}", SIG, PUBKEY)
+        );
+    }
+
+    #[rstest]
+    #[case(PUBKEY, PushType::PublicKey)]
+    #[case("89abcdefabbaabbaabbaabbaabbaabbaabbaabba", PushType::Hash160)]
+    #[case("e803", PushType::Number)]
+    #[case("68656c6c6f", PushType::Data)]
+    fn test_guess_push_type(#[case] data: &str, #[case] expected: PushType) {
+        assert_eq!(guess_push_type(&hex::decode(data).unwrap()), expected);
+    }
+
+    #[rstest]
+    fn test_pretty_print_nested() -> color_eyre::Result<()> {
+        let tokens: Vec<String> = [
+            "1", "OP_IF", "0", "OP_IF", "OP_ELSE", PUBKEY, "OP_ENDIF", "OP_ENDIF",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let expected = format!(
+            "1\nOP_IF\n    0\n    OP_IF\n    OP_ELSE\n        <0x{}>  // 33 bytes, pubkey\n    OP_ENDIF\nOP_ENDIF",
+            PUBKEY
+        );
+        assert_eq!(pretty_print_tokens(&tokens)?, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_trace_step() {
+        let main_stack =
+            Stack::stack_from(vec![PUBKEY.to_string(), "".to_string(), "3".to_string()]);
+        let expected = format!(
+            "OP_DUP\nSTACK :\n    <3>  // 1 bytes, number\n    <0x>  // 0 bytes, data\n    <0x{}>  // 33 bytes, pubkey\nALT STACK :\n",
+            PUBKEY
+        );
+        assert_eq!(trace_step("OP_DUP", &main_stack, &Stack::new()), expected);
+        let signature = format!("{}01", SIG);
+        assert!(trace_step(&signature, &Stack::new(), &main_stack)
+            .starts_with(&format!("<0x{}>  // 72 bytes, signature\n", signature)));
+    }
+}
@@ -0,0 +1,342 @@
+use crate::errors::ScriptError;
+use crate::opcodes::opcode::Opcode;
+use crate::opcodes::utils::script_num_to_bytes;
+use crate::script::decode::{decode, Instruction};
+use crate::script::encode::tokens_to_bytes;
+
+const OP_RESERVED: Opcode = Opcode(0x50);
+const OP_RETURN: Opcode = Opcode(0x6a);
+const OP_DUP: Opcode = Opcode(0x76);
+const OP_EQUAL: Opcode = Opcode(0x87);
+const OP_EQUALVERIFY: Opcode = Opcode(0x88);
+const OP_HASH160: Opcode = Opcode(0xa9);
+const OP_CHECKSIG: Opcode = Opcode(0xac);
+const OP_CHECKMULTISIG: Opcode = Opcode(0xae);
+
+/// Maximum number of public keys in a bare multisig output.
+const MAX_MULTISIG_KEYS: usize = 20;
+
+/// Standard output templates and the parameters extracted from them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptType {
+    /// `<pubkey> OP_CHECKSIG`
+    P2PK {
+        pubkey: Vec<u8>,
+    },
+    /// `OP_DUP OP_HASH160 <pubkey hash> OP_EQUALVERIFY OP_CHECKSIG`
+    P2PKH {
+        pubkey_hash: [u8; 20],
+    },
+    /// `OP_HASH160 <script hash> OP_EQUAL`
+    P2SH {
+        script_hash: [u8; 20],
+    },
+    /// `OP_0 <20 byte pubkey hash>`
+    P2WPKH {
+        pubkey_hash: [u8; 20],
+    },
+    /// `OP_0 <32 byte script hash>`
+    P2WSH {
+        script_hash: [u8; 32],
+    },
+    /// `OP_1 <32 byte x-only output key>`
+    P2TR {
+        output_key: [u8; 32],
+    },
+    /// Witness outputs of versions / program lengths without a defined meaning yet.
+    WitnessUnknown {
+        version: u8,
+        program: Vec<u8>,
+    },
+    /// `<m> <pubkey>... <n> OP_CHECKMULTISIG`
+    Multisig {
+        required: usize,
+        pubkeys: Vec<Vec<u8>>,
+    },
+    /// `OP_RETURN <data>...`
+    NullData {
+        payload: Vec<u8>,
+    },
+    NonStandard {
+        reason: String,
+    },
+}
+
+impl ScriptType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptType::P2PK { .. } => "pubkey",
+            ScriptType::P2PKH { .. } => "pubkeyhash",
+            ScriptType::P2SH { .. } => "scripthash",
+            ScriptType::P2WPKH { .. } => "witness_v0_keyhash",
+            ScriptType::P2WSH { .. } => "witness_v0_scripthash",
+            ScriptType::P2TR { .. } => "witness_v1_taproot",
+            ScriptType::WitnessUnknown { .. } => "witness_unknown",
+            ScriptType::Multisig { .. } => "multisig",
+            ScriptType::NullData { .. } => "nulldata",
+            ScriptType::NonStandard { .. } => "nonstandard",
+        }
+    }
+
+    pub fn is_standard(&self) -> bool {
+        !matches!(self, ScriptType::NonStandard { .. })
+    }
+
+    pub fn is_witness(&self) -> bool {
+        matches!(
+            self,
+            ScriptType::P2WPKH { .. }
+                | ScriptType::P2WSH { .. }
+                | ScriptType::P2TR { .. }
+                | ScriptType::WitnessUnknown { .. }
+        )
+    }
+}
+
+/// Recognizes the standard template of a serialized output script.
+///
+/// The patterns are the ones written up in `scripts/p2pkh.bscript` and friends, matched on
+/// the decoded instructions (exact push sizes, no extra opcodes).
+pub fn classify(script: &[u8]) -> ScriptType {
+    if script.is_empty() {
+        return non_standard("empty script");
+    }
+
+    // Witness programs are matched on the raw bytes, like Core's `IsWitnessProgram`.
+    if let Some((version, program)) = witness_program(script) {
+        return classify_witness(version, program);
+    }
+
+    let instructions = match decode(script) {
+        Ok(instructions) => instructions,
+        Err(e) => return non_standard(&e.to_string()),
+    };
+
+    if instructions[0] == Instruction::Op(OP_RETURN) {
+        return classify_null_data(&instructions[1..]);
+    }
+
+    match instructions.as_slice() {
+        [push @ Instruction::Push { data, .. }, Instruction::Op(OP_CHECKSIG)]
+            if is_direct_push(push) && is_pubkey(data) =>
+        {
+            ScriptType::P2PK {
+                pubkey: data.clone(),
+            }
+        }
+        [Instruction::Op(OP_DUP), Instruction::Op(OP_HASH160), Instruction::Push { data, .. }, Instruction::Op(OP_EQUALVERIFY), Instruction::Op(OP_CHECKSIG)]
+            if data.len() == 20 && script.len() == 25 =>
+        {
+            ScriptType::P2PKH {
+                pubkey_hash: data.as_slice().try_into().unwrap(),
+            }
+        }
+        [Instruction::Op(OP_HASH160), Instruction::Push { data, .. }, Instruction::Op(OP_EQUAL)]
+            if data.len() == 20 && script.len() == 23 =>
+        {
+            ScriptType::P2SH {
+                script_hash: data.as_slice().try_into().unwrap(),
+            }
+        }
+        [.., Instruction::Op(OP_CHECKMULTISIG)] => classify_multisig(&instructions),
+        _ => non_standard("script does not match any standard template"),
+    }
+}
+
+/// Classifies a Bee VM token list through its serialized form.
+pub fn classify_tokens(tokens: &[String]) -> Result<ScriptType, ScriptError> {
+    Ok(classify(&tokens_to_bytes(tokens)?))
+}
+
+/// Returns the witness version and program when the script is a witness program.
+pub fn witness_program(script: &[u8]) -> Option<(u8, &[u8])> {
+    if script.len() < 4 || script.len() > 42 {
+        return None;
+    }
+    let version = match Opcode(script[0]) {
+        Opcode::OP_0 => 0,
+        opcode => match opcode.small_int() {
+            Some(n) if n > 0 => n as u8,
+            _ => return None,
+        },
+    };
+    if script[1] as usize + 2 != script.len() {
+        return None;
+    }
+    Some((version, &script[2..]))
+}
+
+fn classify_witness(version: u8, program: &[u8]) -> ScriptType {
+    match (version, program.len()) {
+        (0, 20) => ScriptType::P2WPKH {
+            pubkey_hash: program.try_into().unwrap(),
+        },
+        (0, 32) => ScriptType::P2WSH {
+            script_hash: program.try_into().unwrap(),
+        },
+        (0, _) => non_standard("witness v0 program must be 20 or 32 bytes"),
+        (1, 32) => ScriptType::P2TR {
+            output_key: program.try_into().unwrap(),
+        },
+        _ => ScriptType::WitnessUnknown {
+            version,
+            program: program.to_vec(),
+        },
+    }
+}
+
+fn classify_null_data(instructions: &[Instruction]) -> ScriptType {
+    let mut payload = vec![];
+    for instruction in instructions {
+        match instruction {
+            Instruction::Push { data, .. } => payload.extend_from_slice(data),
+            // Core counts OP_RESERVED as a push, it adds nothing to the payload.
+            Instruction::Op(opcode) if *opcode == Opcode::OP_0 || *opcode == OP_RESERVED => {}
+            Instruction::Op(opcode) if opcode.small_int().is_some() => {
+                payload.extend(script_num_to_bytes(opcode.small_int().unwrap()))
+            }
+            Instruction::Op(_) => return non_standard("OP_RETURN output with non push opcodes"),
+        }
+    }
+    ScriptType::NullData { payload }
+}
+
+fn classify_multisig(instructions: &[Instruction]) -> ScriptType {
+    let count = |instruction: &Instruction| match instruction {
+        Instruction::Op(opcode) => opcode.small_int().filter(|n| *n > 0).map(|n| n as usize),
+        _ => None,
+    };
+
+    let len = instructions.len();
+    if len < 3 {
+        return non_standard("multisig without m and n");
+    }
+    let (Some(required), Some(total)) = (count(&instructions[0]), count(&instructions[len - 2]))
+    else {
+        return non_standard("multisig m and n must be OP_1 - OP_16");
+    };
+
+    let keys = &instructions[1..len - 2];
+    if keys.len() != total || total > MAX_MULTISIG_KEYS {
+        return non_standard("multisig key count does not match n");
+    }
+    if required > total {
+        return non_standard("multisig m is greater than n");
+    }
+
+    let mut pubkeys = vec![];
+    for key in keys {
+        match key {
+            Instruction::Push { data, .. } if is_direct_push(key) && is_pubkey(data) => {
+                pubkeys.push(data.clone())
+            }
+            _ => return non_standard("multisig contains an invalid public key"),
+        }
+    }
+
+    ScriptType::Multisig { required, pubkeys }
+}
+
+/// Compressed (33 bytes, 0x02 / 0x03) or uncompressed (65 bytes, 0x04) public key.
+pub fn is_pubkey(data: &[u8]) -> bool {
+    match data.len() {
+        33 => data[0] == 0x02 || data[0] == 0x03,
+        65 => data[0] == 0x04,
+        _ => false,
+    }
+}
+
+/// True for a push whose opcode is the data length (0x01 - 0x4b), the only form standard
+/// templates use for keys and hashes.
+fn is_direct_push(instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::Push { opcode, data } => opcode.0 as usize == data.len(),
+        Instruction::Op(_) => false,
+    }
+}
+
+fn non_standard(reason: &str) -> ScriptType {
+    ScriptType::NonStandard {
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test_classify {
+    use crate::script::classify::{classify, classify_tokens, ScriptType};
+    use rstest::rstest;
+
+    const PUBKEY: &str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+    const HASH: &str = "89abcdefabbaabbaabbaabbaabbaabbaabbaabba";
+
+    #[rstest]
+    #[case(format!("21{}ac", PUBKEY), "pubkey")]
+    #[case(format!("76a914{}88ac", HASH), "pubkeyhash")]
+    #[case(format!("a914{}87", HASH), "scripthash")]
+    #[case(format!("0014{}", HASH), "witness_v0_keyhash")]
+    #[case(format!("0020{}{}", HASH, "000000000000000000000000"), "witness_v0_scripthash")]
+    #[case(format!("5120{}", &PUBKEY[2..]), "witness_v1_taproot")]
+    #[case("51024e73".to_string(), "witness_unknown")]
+    #[case(format!("5121{}21{}52ae", PUBKEY, PUBKEY), "multisig")]
+    #[case("6a0568656c6c6f".to_string(), "nulldata")]
+    #[case("6a".to_string(), "nulldata")]
+    #[case("6a5051".to_string(), "nulldata")]
+    fn test_classify_standard(#[case] script: String, #[case] expected: &str) {
+        let script_type = classify(&hex::decode(script).unwrap());
+        assert_eq!(script_type.name(), expected);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("0015aabbccddeeff00112233445566778899aabbccddee")]
+    #[case("6a76")]
+    #[case("76a905aabb")]
+    #[case("5221aabb52ae")]
+    #[case("935487")]
+    #[case("ae")]
+    // Keys and hashes pushed with OP_PUSHDATA1
+    #[case(format!("76a94c14{}88ac", HASH))]
+    #[case(format!("4c21{}ac", PUBKEY))]
+    #[case(format!("514c21{}51ae", PUBKEY))]
+    fn test_classify_non_standard(#[case] script: String) {
+        let script_type = classify(&hex::decode(script).unwrap());
+        assert!(!script_type.is_standard(), "{:?}", script_type);
+    }
+
+    #[rstest]
+    fn test_multisig_parameters() -> color_eyre::Result<()> {
+        let tokens: Vec<String> = vec![
+            "1".into(),
+            PUBKEY.into(),
+            PUBKEY.into(),
+            "2".into(),
+            "OP_CHECKMULTISIG".into(),
+        ];
+        match classify_tokens(&tokens)? {
+            ScriptType::Multisig { required, pubkeys } => {
+                assert_eq!(required, 1);
+                assert_eq!(pubkeys.len(), 2);
+            }
+            other => panic!("unexpected script type {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_p2pkh_parameters() -> color_eyre::Result<()> {
+        let tokens: Vec<String> = vec![
+            "OP_DUP".into(),
+            "OP_HASH160".into(),
+            HASH.into(),
+            "OP_EQUALVERIFY".into(),
+            "OP_CHECKSIG".into(),
+        ];
+        assert_eq!(
+            classify_tokens(&tokens)?,
+            ScriptType::P2PKH {
+                pubkey_hash: hex::decode(HASH)?.try_into().unwrap()
+            }
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use crate::errors::ScriptError;
+use crate::opcodes::opcode::Opcode;
+use crate::script::encode::tokens_to_bytes;
+
+/// A single decoded script instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    /// A data push, together with the opcode that was used to push it.
+    Push { opcode: Opcode, data: Vec<u8> },
+    /// Any non push opcode (including OP_0, OP_1NEGATE and OP_1 - OP_16).
+    Op(Opcode),
+}
+
+/// Iterates over the instructions of serialized script bytes.
+///
+/// A truncated push yields a single error and ends the iteration.
+pub struct Instructions<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    failed: bool,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Instructions {
+            bytes,
+            position: 0,
+            failed: false,
+        }
+    }
+
+    /// Byte offset of the next instruction.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn read_le(&mut self, size: usize) -> Result<usize, ScriptError> {
+        let end = self.position + size;
+        if end > self.bytes.len() {
+            return Err(ScriptError::TruncatedPush(self.position));
+        }
+        let mut value = 0usize;
+        for (i, byte) in self.bytes[self.position..end].iter().enumerate() {
+            value |= (*byte as usize) << (8 * i);
+        }
+        self.position = end;
+        Ok(value)
+    }
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = Result<Instruction, ScriptError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.position >= self.bytes.len() {
+            return None;
+        }
+
+        let start = self.position;
+        let opcode = Opcode(self.bytes[self.position]);
+        self.position += 1;
+
+        if !opcode.is_push_data() {
+            return Some(Ok(Instruction::Op(opcode)));
+        }
+
+        let length = match opcode {
+            Opcode::OP_PUSHDATA1 => self.read_le(1),
+            Opcode::OP_PUSHDATA2 => self.read_le(2),
+            Opcode::OP_PUSHDATA4 => self.read_le(4),
+            _ => Ok(opcode.0 as usize),
+        };
+
+        let data = length.and_then(|length| {
+            let end = self.position.saturating_add(length);
+            if end > self.bytes.len() {
+                return Err(ScriptError::TruncatedPush(start));
+            }
+            let data = self.bytes[self.position..end].to_vec();
+            self.position = end;
+            Ok(data)
+        });
+
+        match data {
+            Ok(data) => Some(Ok(Instruction::Push { opcode, data })),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Decodes serialized script bytes into instructions.
+pub fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, ScriptError> {
+    Instructions::new(bytes).collect()
+}
+
+/// Converts a Bee VM token list into instructions (through its serialized form).
+pub fn tokens_to_instructions(tokens: &[String]) -> Result<Vec<Instruction>, ScriptError> {
+    decode(&tokens_to_bytes(tokens)?)
+}
+
+/// Decodes serialized script bytes into the token list executed by Bee VM.
+///
+/// Small numbers become decimal tokens and other pushes become hex tokens. Hex made only of
+/// digits gets a `0x` prefix, otherwise Bee VM would read it back as a decimal number.
+pub fn bytes_to_tokens(bytes: &[u8]) -> Result<Vec<String>, ScriptError> {
+    Ok(decode(bytes)?.iter().map(instruction_to_token).collect())
+}
+
+pub fn instruction_to_token(instruction: &Instruction) -> String {
+    match instruction {
+        Instruction::Op(opcode) => match opcode.small_int() {
+            Some(n) => n.to_string(),
+            None if *opcode == Opcode::OP_0 => "0".to_string(),
+            None => opcode.name().to_string(),
+        },
+        Instruction::Push { data, .. } => data_to_token(data),
+    }
+}
+
+/// Token (and stack element) representing pushed data, the inverse of `element_to_bytes`.
+///
+/// Empty data becomes `0`, like the empty vector OP_0 pushes.
+pub fn data_to_token(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "0".to_string();
+    }
+    match minimal_script_num(data) {
+        Some(n) => n.to_string(),
+        None => {
+            let hex_data = hex::encode(data);
+            if hex_data.chars().all(|c| c.is_ascii_digit()) {
+                format!("0x{}", hex_data)
+            } else {
+                hex_data
+            }
+        }
+    }
+}
+
+/// Reads pushes of up to 4 bytes that are minimally encoded script numbers.
+pub fn minimal_script_num(data: &[u8]) -> Option<i64> {
+    if data.is_empty() || data.len() > 4 {
+        return None;
+    }
+
+    // The last byte can only be 0x00 / 0x80 when it is needed for the sign bit.
+    let last = data[data.len() - 1];
+    if last & 0x7f == 0 && (data.len() == 1 || data[data.len() - 2] & 0x80 == 0) {
+        return None;
+    }
+
+    Some(script_num_from_bytes(data))
+}
+
+/// Decodes a little-endian sign-magnitude script number (no minimality checks).
+pub fn script_num_from_bytes(data: &[u8]) -> i64 {
+    if data.is_empty() {
+        return 0;
+    }
+    let mut result: i64 = 0;
+    for (i, byte) in data.iter().enumerate() {
+        result |= (*byte as i64) << (8 * i);
+    }
+    let last = data[data.len() - 1];
+    if last & 0x80 != 0 {
+        -(result & !(0x80i64 << (8 * (data.len() - 1))))
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+mod test_decode {
+    use crate::opcodes::opcode::Opcode;
+    use crate::script::decode::{bytes_to_tokens, decode, minimal_script_num, Instruction};
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_decode_p2pkh() {
+        let script = hex::decode("76a91489abcdefabbaabbaabbaabbaabbaabbaabbaabba88ac").unwrap();
+        let instructions = decode(&script).unwrap();
+        assert_eq!(instructions.len(), 5);
+        assert_eq!(instructions[0], Instruction::Op(Opcode(0x76)));
+        assert!(matches!(&instructions[2], Instruction::Push { data, .. } if data.len() == 20));
+    }
+
+    #[rstest]
+    #[case("4c")]
+    #[case("4d0100")]
+    #[case("05aabb")]
+    fn test_decode_truncated(#[case] script: &str) {
+        assert!(decode(&hex::decode(script).unwrap()).is_err());
+    }
+
+    #[rstest]
+    #[case("0051604f", vec!["0", "1", "16", "-1"])]
+    #[case("0111018502e803", vec!["17", "-5", "1000"])]
+    #[case("02abcd", vec!["-19883"])]
+    #[case("05abcdef0102051234567890", vec!["abcdef0102", "0x1234567890"])]
+    #[case("0100", vec!["0x00"])]
+    fn test_bytes_to_tokens(#[case] script: &str, #[case] expected: Vec<&str>) {
+        let tokens = bytes_to_tokens(&hex::decode(script).unwrap()).unwrap();
+        assert_eq!(tokens, expected);
+    }
+
+    #[rstest]
+    #[case(vec![0x80], None)]
+    #[case(vec![0x05, 0x00], None)]
+    #[case(vec![0xff, 0x00], Some(255))]
+    #[case(vec![0xff, 0x80], Some(-255))]
+    fn test_minimal_script_num(#[case] data: Vec<u8>, #[case] expected: Option<i64>) {
+        assert_eq!(minimal_script_num(&data), expected);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::errors::ScriptError;
+use crate::opcodes::opcode::Opcode;
+use crate::opcodes::utils::script_num_to_bytes;
+
+/// Serializes a list of Bee VM tokens into script bytecode.
+///
+/// - `OP_*` tokens are encoded as their opcode byte.
+/// - Numbers use the smallest encoding (OP_0, OP_1NEGATE, OP_1 - OP_16 or a script number push).
+/// - Hex strings (with or without `0x`) are pushed as raw bytes.
+/// - Anything else is pushed as its utf-8 bytes.
+pub fn tokens_to_bytes(tokens: &[String]) -> Result<Vec<u8>, ScriptError> {
+    let mut script = vec![];
+
+    for token in tokens {
+        if token.starts_with("OP_") {
+            let opcode =
+                Opcode::from_name(token).ok_or(ScriptError::UnknownOpcode(token.to_string()))?;
+            script.push(opcode.0);
+            continue;
+        }
+
+        if let Some(number) = parse_number(token) {
+            match number {
+                0 => script.push(Opcode::OP_0.0),
+                -1 => script.push(Opcode::OP_1NEGATE.0),
+                1..=16 => script.push(Opcode::OP_1.0 + (number - 1) as u8),
+                _ => push_data(&mut script, &script_num_to_bytes(number))?,
+            }
+            continue;
+        }
+
+        push_data(&mut script, &token_to_bytes(token))?;
+    }
+
+    Ok(script)
+}
+
+/// Returns the bytes a data token represents: decoded hex, or the raw utf-8 bytes.
+pub fn token_to_bytes(token: &str) -> Vec<u8> {
+    let hex_part = token.strip_prefix("0x").unwrap_or(token);
+    if hex_part.len().is_multiple_of(2) && hex_part.chars().all(|c| c.is_ascii_hexdigit()) {
+        if let Ok(bytes) = hex::decode(hex_part) {
+            return bytes;
+        }
+    }
+    token.as_bytes().to_vec()
+}
+
+/// Returns the bytes a stack element stands for: decimal elements are script numbers, the rest
+/// goes through `token_to_bytes`.
+pub fn element_to_bytes(element: &str) -> Vec<u8> {
+    match parse_number(element) {
+        Some(number) => script_num_to_bytes(number),
+        None => token_to_bytes(element),
+    }
+}
+
+/// Appends a data push with the smallest push opcode for its length.
+pub fn push_data(script: &mut Vec<u8>, data: &[u8]) -> Result<(), ScriptError> {
+    let len = data.len();
+    if len <= 0x4b {
+        script.push(len as u8);
+    } else if len <= 0xff {
+        script.push(Opcode::OP_PUSHDATA1.0);
+        script.push(len as u8);
+    } else if len <= 0xffff {
+        script.push(Opcode::OP_PUSHDATA2.0);
+        script.extend_from_slice(&(len as u16).to_le_bytes());
+    } else if len <= u32::MAX as usize {
+        script.push(Opcode::OP_PUSHDATA4.0);
+        script.extend_from_slice(&(len as u32).to_le_bytes());
+    } else {
+        return Err(ScriptError::PushDataTooLarge(len));
+    }
+    script.extend_from_slice(data);
+    Ok(())
+}
+
+/// Decimal tokens (with an optional leading `-`) that fit into a script number.
+fn parse_number(token: &str) -> Option<i64> {
+    let digits = token.strip_prefix('-').unwrap_or(token);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    token.parse::<i64>().ok()
+}
+
+#[cfg(test)]
+mod test_encode {
+    use crate::script::decode::data_to_token;
+    use crate::script::encode::{element_to_bytes, tokens_to_bytes};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(vec!["OP_DUP", "OP_HASH160"], "76a9")]
+    #[case(vec!["0", "1", "16", "-1"], "0051604f")]
+    #[case(vec!["17", "-5", "1000"], "0111018502e803")]
+    #[case(vec!["0xabcd", "abcd"], "02abcd02abcd")]
+    #[case(vec!["hello"], "0568656c6c6f")]
+    fn test_tokens_to_bytes(#[case] tokens: Vec<&str>, #[case] expected: &str) {
+        let tokens: Vec<String> = tokens.into_iter().map(String::from).collect();
+        assert_eq!(hex::encode(tokens_to_bytes(&tokens).unwrap()), expected);
+    }
+
+    #[rstest]
+    fn test_large_push_uses_pushdata() {
+        let data = "ab".repeat(80);
+        let bytes = tokens_to_bytes(&[data]).unwrap();
+        assert_eq!(&bytes[..2], &[0x4c, 80]);
+        assert_eq!(bytes.len(), 82);
+    }
+
+    #[rstest]
+    #[case("", "0")]
+    #[case("05", "5")]
+    #[case("81", "-1")]
+    #[case("1234567890", "0x1234567890")]
+    #[case("02abcdef01", "02abcdef01")]
+    fn test_element_round_trip(#[case] data: &str, #[case] element: &str) {
+        let data = hex::decode(data).unwrap();
+        assert_eq!(data_to_token(&data), element);
+        assert_eq!(element_to_bytes(element), data);
+    }
+
+    #[rstest]
+    fn test_unknown_opcode() {
+        assert!(tokens_to_bytes(&["OP_FOO".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,11 @@
+// ==================================================
+// Script serialization
+// ==================================================
+
+pub mod analyze;
+pub mod asm;
+pub mod classify;
+pub mod decode;
+pub mod encode;
+#[cfg(feature = "fuzz")]
+pub mod generate;
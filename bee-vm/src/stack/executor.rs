@@ -21,15 +21,21 @@ use crate::opcodes::arithmetic_ops::sub1::sub_1;
 use crate::opcodes::arithmetic_ops::verify::verify;
 use crate::opcodes::arithmetic_ops::within::within;
 use crate::opcodes::control_flow::ControlFlow;
-use crate::opcodes::crypto_ops::check_sig::op_checksig;
-use crate::opcodes::crypto_ops::op_check_multi_sig::op_checkmultisig;
+use crate::opcodes::crypto_ops::check_sig::{
+    op_checksig, op_checksig_with_context, op_checksigadd_with_context,
+};
+use crate::opcodes::crypto_ops::op_check_multi_sig::{
+    op_checkmultisig, op_checkmultisig_with_context, MAX_PUBKEYS_PER_MULTISIG,
+};
 use crate::opcodes::crypto_ops::ripe_md_160::{hash_160, ripe_md_160};
 use crate::opcodes::crypto_ops::sha_1::sha_1;
 use crate::opcodes::crypto_ops::sha_256::{hash_256, sha_256};
+use crate::opcodes::locktime::{op_checklocktimeverify, op_checksequenceverify};
 use crate::opcodes::new_num::new_num;
 use crate::opcodes::op_equal::op_equal;
 use crate::opcodes::op_reserved::op_reserved;
 use crate::opcodes::op_return::op_return;
+use crate::opcodes::opcode::Opcode;
 use crate::opcodes::stack_ops::depth::depth;
 use crate::opcodes::stack_ops::drop::op_drop;
 use crate::opcodes::stack_ops::dup::dup;
@@ -44,8 +50,12 @@ use crate::opcodes::stack_ops::size::size;
 use crate::opcodes::stack_ops::swap::{swap, swap_2};
 use crate::opcodes::stack_ops::to_alt_stack::to_alt_stack;
 use crate::opcodes::stack_ops::tuck::tuck;
+use crate::opcodes::utils::string_to_i32;
 use crate::opcodes::zero_not_equal::zero_not_equal;
-use crate::stack::Stack;
+use crate::script::asm::trace_step;
+use crate::stack::{Stack, MAX_OPS_PER_SCRIPT, MAX_STACK_SIZE};
+use crate::transaction::context::ExecutionContext;
+use crate::transaction::sighash::SigVersion;
 use crate::utils::print_in_box;
 use color_eyre::Result;
 
@@ -57,17 +67,86 @@ use color_eyre::Result;
 ///
 /// Returns both the main stack and alternative stack after execution completes.
 pub fn execute_code(seq: Vec<String>) -> Result<(Stack, Stack)> {
-    let mut main_stack = Stack::new();
+    let (main_stack, alt_stack, ops_array) = execute(&seq, Stack::new(), None)?;
+
+    // Print final state for debugging
+    println!("\n======================================================\nSTACK (final) :");
+    print_in_box(&main_stack);
+    println!("\nALT STACK (final) :",);
+    print_in_box(&alt_stack);
+    println!("\nOPERATIONS (final) : \n{:?}", ops_array);
+
+    Ok((main_stack, alt_stack))
+}
+
+/// Executes a script spending a real transaction input, starting from `initial_stack`
+/// (the witness items or the result of the scriptSig).
+///
+/// Signature opcodes use the Bitcoin stack order (public key on top) and check the signature
+/// hash of `context` instead of the hash of the Bee VM tokens.
+pub fn execute_code_with_context(
+    seq: &[String],
+    initial_stack: Stack,
+    context: &ExecutionContext,
+) -> Result<(Stack, Stack)> {
+    let (main_stack, alt_stack, _) = execute(seq, initial_stack, Some(context))?;
+    Ok((main_stack, alt_stack))
+}
+
+/// Executes a script from `initial_stack` without a transaction context nor printing, for
+/// callers running many scripts (fuzzing, tests).
+pub fn execute_code_with_stack(seq: &[String], initial_stack: Stack) -> Result<(Stack, Stack)> {
+    let (main_stack, alt_stack, _) = execute(seq, initial_stack, None)?;
+    Ok((main_stack, alt_stack))
+}
+
+fn execute(
+    seq: &[String],
+    mut main_stack: Stack,
+    context: Option<&ExecutionContext>,
+) -> Result<(Stack, Stack, Vec<String>)> {
     let mut alt_stack = Stack::new();
     let mut ops_array: Vec<String> = vec![];
-    let mut control_flow = ControlFlow::new();
+    let tapscript = context.is_some_and(|context| context.sig_version == SigVersion::Tapscript);
+    let mut control_flow = match tapscript {
+        true => ControlFlow::tapscript(),
+        false => ControlFlow::new(),
+    };
+    // Tapscript has no opcode limit, signature opcodes are bounded by the witness size instead
+    let mut op_count = 0;
 
     // Tracks the position of the most recently executed OP_CODESEPARATOR
     // This affects which portion of the script is included in signature verification
     let mut last_code_separator_index = 0;
+    let mut executed_code_separator: Option<usize> = None;
 
     for (index, code) in seq.iter().enumerate() {
-        log::debug!("Processing code : {:?}", code.clone());
+        // Script code and OP_CODESEPARATOR position signed in a transaction context
+        let (script_code, code_separator_position) = match executed_code_separator {
+            Some(position) => (&seq[position + 1..], position as u32),
+            None => (seq, u32::MAX),
+        };
+
+        // Non push opcodes count even in a skipped branch
+        if !tapscript
+            && code.starts_with("OP_")
+            && Opcode::from_name(code).is_none_or(|opcode| opcode > Opcode::OP_16)
+        {
+            op_count += 1;
+            if control_flow.should_execute()
+                && matches!(code.as_str(), "OP_CHECKMULTISIG" | "OP_CHECKMULTISIGVERIFY")
+            {
+                op_count += main_stack
+                    .peek(0)
+                    .and_then(|keys| string_to_i32(keys).ok())
+                    .and_then(|keys| usize::try_from(keys).ok())
+                    .filter(|keys| *keys <= MAX_PUBKEYS_PER_MULTISIG)
+                    .unwrap_or(0);
+            }
+            if op_count > MAX_OPS_PER_SCRIPT {
+                Err(OpCodeErrors::OpCount(MAX_OPS_PER_SCRIPT))?
+            }
+        }
 
         // Only execute operations if we're not in a skipped branch of an IF/ELSE block
         if control_flow.should_execute() {
@@ -128,44 +207,91 @@ pub fn execute_code(seq: Vec<String>) -> Result<(Stack, Stack)> {
                 // CONTROL FLOW
                 // ============================================
                 "OP_IF" => control_flow.op_if(&mut main_stack)?,
+                "OP_NOTIF" => control_flow.op_notif(&mut main_stack)?,
                 "OP_ELSE" => control_flow.op_else()?,
                 "OP_ENDIF" => control_flow.op_endif()?,
 
+                // ============================================
+                // LOCK TIME OPS
+                // ============================================
+                "OP_CHECKLOCKTIMEVERIFY" => match context {
+                    Some(context) => op_checklocktimeverify(&mut main_stack, context)?,
+                    None => Err(OpCodeErrors::MissingContext(code.clone()))?,
+                },
+                "OP_CHECKSEQUENCEVERIFY" => match context {
+                    Some(context) => op_checksequenceverify(&mut main_stack, context)?,
+                    None => Err(OpCodeErrors::MissingContext(code.clone()))?,
+                },
+
                 // ============================================
                 // CRYPTO OPS
                 // ============================================
                 "OP_CHECKSIG" => {
-                    if seq[last_code_separator_index..].contains(&"OP_CODESEPARATOR".to_string()) {
+                    if let Some(context) = context {
+                        op_checksig_with_context(
+                            &mut main_stack,
+                            script_code,
+                            code_separator_position,
+                            context,
+                        )?
+                    } else if seq[last_code_separator_index..]
+                        .contains(&"OP_CODESEPARATOR".to_string())
+                    {
                         op_checksig(&mut main_stack, &seq[last_code_separator_index + 1..])?
                     } else {
-                        op_checksig(&mut main_stack, &seq)?
+                        op_checksig(&mut main_stack, seq)?
                     }
                 }
                 "OP_CHECKSIGVERIFY" => {
-                    if seq[last_code_separator_index..].contains(&"OP_CODESEPARATOR".to_string()) {
+                    if let Some(context) = context {
+                        op_checksig_with_context(
+                            &mut main_stack,
+                            script_code,
+                            code_separator_position,
+                            context,
+                        )?
+                    } else if seq[last_code_separator_index..]
+                        .contains(&"OP_CODESEPARATOR".to_string())
+                    {
                         op_checksig(&mut main_stack, &seq[last_code_separator_index + 1..])?
                     } else {
-                        op_checksig(&mut main_stack, &seq)?
+                        op_checksig(&mut main_stack, seq)?
                     }
                     verify(&mut main_stack)?
                 }
+                "OP_CHECKSIGADD" => match context {
+                    Some(context) => op_checksigadd_with_context(
+                        &mut main_stack,
+                        code_separator_position,
+                        context,
+                    )?,
+                    None => Err(OpCodeErrors::MissingContext(code.clone()))?,
+                },
                 "OP_RIPEMD160" => ripe_md_160(&mut main_stack)?,
                 "OP_SHA1" => sha_1(&mut main_stack)?,
                 "OP_SHA256" => sha_256(&mut main_stack)?,
                 "OP_HASH160" => hash_160(&mut main_stack)?,
                 "OP_HASH256" => hash_256(&mut main_stack)?,
                 "OP_CHECKMULTISIG" => {
-                    if seq[last_code_separator_index..].contains(&"OP_CODESEPARATOR".to_string()) {
+                    if let Some(context) = context {
+                        op_checkmultisig_with_context(&mut main_stack, script_code, context)?
+                    } else if seq[last_code_separator_index..]
+                        .contains(&"OP_CODESEPARATOR".to_string())
+                    {
                         op_checkmultisig(&mut main_stack, &seq[last_code_separator_index + 1..])?
                     } else {
-                        op_checkmultisig(&mut main_stack, &seq)?
+                        op_checkmultisig(&mut main_stack, seq)?
                     }
                 }
                 "OP_CHECKMULTISIGVERIFY" => {
-                    if seq[last_code_separator_index..].contains(&"OP_CODESEPARATOR".to_string()) {
+                    if let Some(context) = context {
+                        op_checkmultisig_with_context(&mut main_stack, script_code, context)?
+                    } else if seq[last_code_separator_index..]
+                        .contains(&"OP_CODESEPARATOR".to_string())
+                    {
                         op_checkmultisig(&mut main_stack, &seq[last_code_separator_index + 1..])?
                     } else {
-                        op_checkmultisig(&mut main_stack, &seq)?
+                        op_checkmultisig(&mut main_stack, seq)?
                     }
                     verify(&mut main_stack)?
                 }
@@ -183,11 +309,15 @@ pub fn execute_code(seq: Vec<String>) -> Result<(Stack, Stack)> {
                     verify(&mut main_stack)?
                 }
                 "OP_RESERVED" => op_reserved()?,
+                // Upgradable NOPs, OP_NOP2 and OP_NOP3 became the lock time checks
+                "OP_NOP" | "OP_NOP1" | "OP_NOP4" | "OP_NOP5" | "OP_NOP6" | "OP_NOP7"
+                | "OP_NOP8" | "OP_NOP9" | "OP_NOP10" => {}
                 "OP_CODESEPARATOR" => {
                     // When we encounter a code separator, update the index to the current position
                     // This means subsequent signature verifications will only consider script
                     // operations that come after this point
                     last_code_separator_index = index;
+                    executed_code_separator = Some(index);
                 }
 
                 _ => {
@@ -199,11 +329,12 @@ pub fn execute_code(seq: Vec<String>) -> Result<(Stack, Stack)> {
                     ops_array.push(code.clone());
                 }
             }
-        } else if code == "OP_IF" || code == "OP_ELSE" || code == "OP_ENDIF" {
+        } else if ["OP_IF", "OP_NOTIF", "OP_ELSE", "OP_ENDIF"].contains(&code.as_str()) {
             // Always process control flow operations, even in skipped branches
             // This maintains proper nesting of conditional blocks
             match code.as_str() {
                 "OP_IF" => control_flow.op_if(&mut main_stack)?,
+                "OP_NOTIF" => control_flow.op_notif(&mut main_stack)?,
                 "OP_ELSE" => control_flow.op_else()?,
                 "OP_ENDIF" => control_flow.op_endif()?,
                 _ => unreachable!(),
@@ -213,16 +344,13 @@ pub fn execute_code(seq: Vec<String>) -> Result<(Stack, Stack)> {
         // Keep track of all operations for debugging and analysis
         ops_array.push(code.clone());
 
-        log::debug!("STACK : {:?}", &main_stack.elements);
-        log::debug!("ALT STACK : {:?}", &alt_stack.elements);
-    }
+        if main_stack.len() + alt_stack.len() > MAX_STACK_SIZE {
+            Err(OpCodeErrors::StackOverflow(MAX_STACK_SIZE))?
+        }
 
-    // Print final state for debugging
-    println!("\n======================================================\nSTACK (final) :");
-    print_in_box(&mut main_stack.elements);
-    println!("\nALT STACK (final) :",);
-    print_in_box(&mut alt_stack.elements);
-    println!("\nOPERATIONS (final) : \n{:?}", ops_array);
+        log::debug!("{}", trace_step(code, &main_stack, &alt_stack));
+    }
+    control_flow.finish()?;
 
-    Ok((main_stack, alt_stack))
+    Ok((main_stack, alt_stack, ops_array))
 }
@@ -1,66 +1,107 @@
 pub mod executor;
+pub mod solver;
+pub mod symbolic;
 
-#[derive(Debug, Clone, PartialEq)]
+use crate::errors::OpCodeErrors;
+use crate::script::decode::data_to_token;
+
+/// Items allowed on the main and alt stacks together (consensus).
+pub const MAX_STACK_SIZE: usize = 1000;
+
+/// Non push opcodes allowed in a legacy or segwit v0 script (consensus), the public keys of
+/// an executed OP_CHECKMULTISIG count as opcodes too.
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+
+/// Bee VM stack, the top of the stack is the end of the vector so pushes and pops are O(1).
+///
+/// Positions given to `peek`, `swap`, `remove_at` and `insert_at` count down from the top
+/// item, at position 0.
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Stack {
-    pub elements: Vec<String>,
-    pub length: i32,
+    elements: Vec<String>,
 }
 
 impl Stack {
     pub fn new() -> Stack {
-        Stack {
-            elements: vec![],
-            length: 0,
-        }
+        Stack { elements: vec![] }
     }
 
-    pub fn pop(&mut self) -> Option<String> {
-        if self.length >= 1 {
-            self.length -= 1;
+    /// Stack holding serialized data items, like a witness, with the last item on top.
+    pub fn from_data(items: &[Vec<u8>]) -> Stack {
+        Stack {
+            elements: items.iter().map(|item| data_to_token(item)).collect(),
         }
-        self.elements.pop()
     }
 
-    pub fn push(&mut self, item: String) {
-        self.length += 1;
+    pub fn push_to_top(&mut self, item: String) {
         self.elements.push(item);
     }
 
-    pub fn push_to_top(&mut self, item: String) {
-        self.length += 1;
-        self.elements.insert(0, item);
+    pub fn pop_from_top(&mut self) -> Option<String> {
+        self.elements.pop()
     }
 
-    pub fn pop_from_top(&mut self) -> Option<String> {
-        if self.length >= 1 {
-            self.length -= 1;
-        } else {
-            return None;
-        }
-        Some(self.elements.remove(0))
+    pub fn len(&self) -> usize {
+        self.elements.len()
     }
 
     pub fn is_empty(&self) -> bool {
         self.elements.is_empty()
     }
 
-    pub fn read_ele_from_top(&self, number: i32) -> Option<&String> {
-        self.elements.get(number as usize)
+    /// Items from the bottom to the top of the stack.
+    pub fn items(&self) -> &[String] {
+        &self.elements
     }
 
-    #[cfg(test)]
-    pub fn stack_from(vec: Vec<String>) -> Stack {
-        let mut stack = Stack::new();
-        for i in vec {
-            stack.push_to_top(i);
+    /// Item `position` items below the top.
+    pub fn peek(&self, position: usize) -> Option<&String> {
+        self.index(position).map(|index| &self.elements[index])
+    }
+
+    /// Swaps the items at two positions.
+    pub fn swap(&mut self, first: usize, second: usize) -> Result<(), OpCodeErrors> {
+        let first = self.checked_index(first)?;
+        let second = self.checked_index(second)?;
+        self.elements.swap(first, second);
+        Ok(())
+    }
+
+    /// Removes the item at `position`, the items above it move down.
+    pub fn remove_at(&mut self, position: usize) -> Result<String, OpCodeErrors> {
+        let index = self.checked_index(position)?;
+        Ok(self.elements.remove(index))
+    }
+
+    /// Inserts an item at `position`, above the items that were there. Position `len()`
+    /// is the bottom of the stack.
+    pub fn insert_at(&mut self, position: usize, item: String) -> Result<(), OpCodeErrors> {
+        if position > self.len() {
+            return Err(self.out_of_range(position));
         }
-        stack
+        self.elements.insert(self.len() - position, item);
+        Ok(())
     }
-}
 
-impl Default for Stack {
-    fn default() -> Self {
-        Self::new()
+    fn index(&self, position: usize) -> Option<usize> {
+        self.len().checked_sub(position + 1)
+    }
+
+    fn checked_index(&self, position: usize) -> Result<usize, OpCodeErrors> {
+        self.index(position)
+            .ok_or_else(|| self.out_of_range(position))
+    }
+
+    fn out_of_range(&self, position: usize) -> OpCodeErrors {
+        OpCodeErrors::PositionOutOfRange {
+            position,
+            length: self.len(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn stack_from(vec: Vec<String>) -> Stack {
+        Stack { elements: vec }
     }
 }
 
@@ -72,26 +113,26 @@ mod tests {
     #[test]
     fn pop_element() {
         let mut stk = Stack::new();
-        stk.push("1".to_string());
-        stk.push("2".to_string());
-        stk.push("3".to_string());
+        stk.push_to_top("1".to_string());
+        stk.push_to_top("2".to_string());
+        stk.push_to_top("3".to_string());
 
-        let pop_res = stk.pop().unwrap();
+        let pop_res = stk.pop_from_top().unwrap();
         assert_eq!(pop_res, "3");
     }
 
     #[test]
     fn push_element() {
         let mut stk = Stack::new();
-        stk.push("1".to_string());
-        stk.push("2".to_string());
-        stk.push("3".to_string());
+        stk.push_to_top("1".to_string());
+        stk.push_to_top("2".to_string());
+        stk.push_to_top("3".to_string());
 
-        let mut pop_res = stk.pop().unwrap();
+        let mut pop_res = stk.pop_from_top().unwrap();
         assert_eq!(pop_res, "3");
-        pop_res = stk.pop().unwrap();
+        pop_res = stk.pop_from_top().unwrap();
         assert_eq!(pop_res, "2");
-        pop_res = stk.pop().unwrap();
+        pop_res = stk.pop_from_top().unwrap();
         assert_eq!(pop_res, "1");
     }
 
@@ -100,4 +141,48 @@ mod tests {
         let stk = Stack::new();
         assert!(stk.is_empty());
     }
+
+    fn abc() -> Stack {
+        Stack::stack_from(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+    }
+
+    #[test]
+    fn peek_element() {
+        let stk = abc();
+        assert_eq!(stk.peek(0).unwrap(), "c");
+        assert_eq!(stk.peek(2).unwrap(), "a");
+        assert_eq!(stk.peek(3), None);
+        assert_eq!(stk.len(), 3);
+    }
+
+    #[test]
+    fn swap_elements() {
+        let mut stk = abc();
+        stk.swap(0, 2).unwrap();
+        assert_eq!(stk.items(), ["c", "b", "a"]);
+        assert!(stk.swap(0, 3).is_err());
+    }
+
+    #[test]
+    fn remove_element_at() {
+        let mut stk = abc();
+        assert_eq!(stk.remove_at(1).unwrap(), "b");
+        assert_eq!(stk.items(), ["a", "c"]);
+        assert!(matches!(
+            stk.remove_at(2),
+            Err(OpCodeErrors::PositionOutOfRange {
+                position: 2,
+                length: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn insert_element_at() {
+        let mut stk = abc();
+        stk.insert_at(1, "x".to_string()).unwrap();
+        stk.insert_at(4, "y".to_string()).unwrap();
+        assert_eq!(stk.items(), ["y", "a", "b", "x", "c"]);
+        assert!(stk.insert_at(6, "z".to_string()).is_err());
+    }
 }
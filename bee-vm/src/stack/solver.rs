@@ -0,0 +1,781 @@
+use crate::errors::{SolverError, VerifyError};
+use crate::opcodes::crypto_ops::SIGHASH_ALL;
+use crate::opcodes::locktime::{
+    LOCKTIME_THRESHOLD, SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG,
+};
+use crate::stack::symbolic::{execute_symbolic, Constraint, Expr, SpendingPath};
+use crate::stack::Stack;
+use crate::transaction::context::ExecutionContext;
+use crate::transaction::encode::write_bytes;
+use crate::transaction::sighash::{SigVersion, SIGHASH_DEFAULT};
+use crate::transaction::verify::{check_success, execute_witness_script, run};
+use crate::transaction::{Transaction, SEQUENCE_FINAL};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::ecdsa::SigningKey;
+use ripemd::Ripemd160;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// Secrets and chain state available to satisfy a script.
+#[derive(Debug, Clone, Default)]
+pub struct KeyStore {
+    keys: Vec<SigningKey>,
+    preimages: Vec<Vec<u8>>,
+    /// Current block height and median time past, for absolute lock times.
+    height: Option<u32>,
+    time: Option<u32>,
+    /// Confirmations and age in seconds of the spent output, for relative lock times.
+    confirmations: Option<u32>,
+    age: Option<u32>,
+}
+
+/// A witness satisfying one spending path of a script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// Index of the path in `execute_symbolic` order.
+    pub path: usize,
+    /// Witness stack items, the last one on top like in a transaction witness.
+    pub witness: Vec<Vec<u8>>,
+    /// Spending transaction with the lock time, sequence and version the path needs, which
+    /// the signatures commit to.
+    pub tx: Transaction,
+}
+
+/// A witness item before signing.
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Data(Vec<u8>),
+    /// Signature with the key at this index of the store.
+    Signature(usize),
+}
+
+/// Witness items chosen for one path, `w0` first.
+struct Assignment<'a> {
+    store: &'a KeyStore,
+    sig_version: SigVersion,
+    items: Vec<Option<Item>>,
+}
+
+impl KeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key(mut self, key: SigningKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    /// Adds a secret revealed to satisfy hash locks.
+    pub fn with_preimage(mut self, preimage: Vec<u8>) -> Self {
+        self.preimages.push(preimage);
+        self
+    }
+
+    pub fn with_height(mut self, height: u32) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    pub fn with_time(mut self, time: u32) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Confirmations of the spent output, a relative lock of `n` blocks needs `n`.
+    pub fn with_confirmations(mut self, confirmations: u32) -> Self {
+        self.confirmations = Some(confirmations);
+        self
+    }
+
+    /// Seconds elapsed since the spent output confirmed.
+    pub fn with_age(mut self, age: u32) -> Self {
+        self.age = Some(age);
+        self
+    }
+
+    /// Public key as pushed in scripts : x-only in tapscript, compressed otherwise.
+    fn public_key(&self, key: &SigningKey, sig_version: SigVersion) -> Vec<u8> {
+        let public_key = key.verifying_key().to_sec1_bytes().to_vec();
+        match sig_version {
+            SigVersion::Taproot | SigVersion::Tapscript => public_key[1..].to_vec(),
+            _ => public_key,
+        }
+    }
+
+    fn find_key(&self, public_key: &[u8], sig_version: SigVersion) -> Option<usize> {
+        self.keys
+            .iter()
+            .position(|key| self.public_key(key, sig_version) == public_key)
+    }
+
+    /// Signs the context signature hash : ECDSA with SIGHASH_ALL before taproot, BIP340 with
+    /// SIGHASH_DEFAULT in tapscript.
+    fn sign(
+        &self,
+        key: &SigningKey,
+        script: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<Vec<u8>, String> {
+        match context.sig_version {
+            SigVersion::Taproot | SigVersion::Tapscript => {
+                let hash = context
+                    .signature_hash(&[], SIGHASH_DEFAULT as u32, u32::MAX)
+                    .map_err(|e| e.to_string())?;
+                let key = k256::schnorr::SigningKey::from_bytes(&key.to_bytes())
+                    .map_err(|e| e.to_string())?;
+                let signature = key.sign_raw(&hash, &[0; 32]).map_err(|e| e.to_string())?;
+                Ok(signature.to_bytes().to_vec())
+            }
+            SigVersion::Base | SigVersion::WitnessV0 => {
+                let hash = context
+                    .signature_hash(script, SIGHASH_ALL as u32, u32::MAX)
+                    .map_err(|e| e.to_string())?;
+                let signature: k256::ecdsa::Signature =
+                    key.sign_prehash(&hash).map_err(|e| e.to_string())?;
+                let mut signature = signature.to_der().as_bytes().to_vec();
+                signature.push(SIGHASH_ALL);
+                Ok(signature)
+            }
+        }
+    }
+
+    /// Known values hashing to `digest` : preimages and public keys (for hash160 locks).
+    fn find_preimage(
+        &self,
+        function: &str,
+        digest: &[u8],
+        sig_version: SigVersion,
+    ) -> Option<Vec<u8>> {
+        self.preimages
+            .iter()
+            .cloned()
+            .chain(
+                self.keys
+                    .iter()
+                    .map(|key| self.public_key(key, sig_version)),
+            )
+            .find(|candidate| hash(function, candidate) == digest)
+    }
+
+    /// Builds the witness of one path and the transaction fields it needs, or explains why
+    /// the store can not satisfy it.
+    fn satisfy(
+        &self,
+        script: &[u8],
+        path: &SpendingPath,
+        context: &ExecutionContext,
+    ) -> Result<(Vec<Vec<u8>>, Transaction), String> {
+        let mut assignment = Assignment {
+            store: self,
+            sig_version: context.sig_version,
+            items: vec![None; path.witness_items],
+        };
+        // Fixed values first, so that signatures find the keys the path checks hashes of.
+        for constraint in &path.constraints {
+            assignment.assign_data(constraint)?;
+        }
+        for constraint in &path.constraints {
+            assignment.assign_truth(constraint)?;
+        }
+        for constraint in &path.constraints {
+            assignment.assign_signatures(constraint)?;
+        }
+
+        let mut context = context.clone();
+        self.apply_locks(&path.constraints, &mut context)?;
+
+        let mut witness = vec![];
+        for item in assignment.items.into_iter().rev() {
+            witness.push(match item {
+                Some(Item::Data(data)) => data,
+                Some(Item::Signature(key)) => self.sign(&self.keys[key], script, &context)?,
+                // Items the path reads without checking them.
+                None => vec![],
+            });
+        }
+        Ok((witness, context.tx))
+    }
+
+    /// Sets the transaction lock time, input sequence and version required by the lock time
+    /// constraints, if the chain state allows them.
+    fn apply_locks(
+        &self,
+        constraints: &[Constraint],
+        context: &mut ExecutionContext,
+    ) -> Result<(), String> {
+        let mut lock_time: Option<i64> = None;
+        let mut sequence: Option<i64> = None;
+
+        for constraint in constraints {
+            match constraint {
+                Constraint::LockTime { value } => {
+                    let n = lock_value(constraint, value)?;
+                    let (current, unit) = match n < LOCKTIME_THRESHOLD {
+                        true => (self.height, "block height"),
+                        false => (self.time, "time"),
+                    };
+                    let current = current
+                        .ok_or_else(|| format!("{} : current {} unknown", constraint, unit))?;
+                    if n > current as i64 {
+                        return Err(format!("{} : current {} is {}", constraint, unit, current));
+                    }
+                    lock_time = Some(combine(constraint, lock_time, n, |n| {
+                        n < LOCKTIME_THRESHOLD
+                    })?);
+                }
+                Constraint::Sequence { value } => {
+                    let n = lock_value(constraint, value)?
+                        & (SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK);
+                    let (current, required, unit) = match n & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+                        true => (self.age, (n & SEQUENCE_LOCKTIME_MASK) * 512, "age"),
+                        false => (self.confirmations, n, "confirmations"),
+                    };
+                    let current = current
+                        .ok_or_else(|| format!("{} : output {} unknown", constraint, unit))?;
+                    if required > current as i64 {
+                        return Err(format!("{} : output {} is {}", constraint, unit, current));
+                    }
+                    sequence = Some(combine(constraint, sequence, n, |n| {
+                        n & SEQUENCE_LOCKTIME_TYPE_FLAG == 0
+                    })?);
+                }
+                _ => {}
+            }
+        }
+
+        let input = &mut context.tx.inputs[context.input_index];
+        if let Some(sequence) = sequence {
+            input.sequence = sequence as u32;
+            context.tx.version = context.tx.version.max(2);
+        }
+        if let Some(lock_time) = lock_time {
+            // Lock times are only enforced on inputs with a non final sequence.
+            if input.sequence == SEQUENCE_FINAL {
+                input.sequence = SEQUENCE_FINAL - 1;
+            }
+            context.tx.lock_time = lock_time as u32;
+        }
+        Ok(())
+    }
+}
+
+impl Assignment<'_> {
+    /// Gives item `index` a value, items already set must agree.
+    fn set(&mut self, index: usize, item: Item) -> Result<(), String> {
+        match &self.items[index] {
+            Some(existing) if *existing != item => {
+                Err(format!("w{} is needed with two different values", index))
+            }
+            _ => {
+                self.items[index] = Some(item);
+                Ok(())
+            }
+        }
+    }
+
+    /// Value of a constant or of an already assigned witness item.
+    fn value(&self, expr: &Expr) -> Option<Vec<u8>> {
+        match expr {
+            Expr::Witness { index } => match &self.items[*index] {
+                Some(Item::Data(data)) => Some(data.clone()),
+                _ => None,
+            },
+            _ => expr.bytes(),
+        }
+    }
+
+    /// Equalities with constants : pushed values, hash locks and the multisig dummy.
+    fn assign_data(&mut self, constraint: &Constraint) -> Result<(), String> {
+        let Constraint::True {
+            expr:
+                Expr::Binary {
+                    op: "==",
+                    left,
+                    right,
+                },
+        } = constraint
+        else {
+            return Ok(());
+        };
+        let (target, value) = match (right.bytes(), left.bytes()) {
+            (Some(value), None) => (left.as_ref(), value),
+            (None, Some(value)) => (right.as_ref(), value),
+            _ => return Ok(()),
+        };
+        match target {
+            Expr::Witness { index } => self.set(*index, Item::Data(value)),
+            Expr::Hash { function, input } => match input.as_ref() {
+                Expr::Witness { index } => {
+                    let preimage = self
+                        .store
+                        .find_preimage(function, &value, self.sig_version)
+                        .ok_or_else(|| format!("no known preimage for {}", constraint))?;
+                    self.set(*index, Item::Data(preimage))
+                }
+                _ => Ok(()),
+            },
+            // Other constraints are left to the execution of the witness.
+            _ => Ok(()),
+        }
+    }
+
+    /// Conditions of the branches taken and not taken.
+    fn assign_truth(&mut self, constraint: &Constraint) -> Result<(), String> {
+        let (index, holds) = match constraint {
+            Constraint::True {
+                expr: Expr::Witness { index },
+            } => (*index, true),
+            Constraint::False {
+                expr: Expr::Witness { index },
+            } => (*index, false),
+            _ => return Ok(()),
+        };
+        match &self.items[index] {
+            None => self.set(
+                index,
+                Item::Data(match holds {
+                    true => vec![1],
+                    false => vec![],
+                }),
+            ),
+            Some(Item::Data(data)) if Expr::data(data).truth() == Some(holds) => Ok(()),
+            Some(Item::Signature(_)) if holds => Ok(()),
+            Some(_) => Err(format!("w{} can not be {}", index, holds)),
+        }
+    }
+
+    fn assign_signatures(&mut self, constraint: &Constraint) -> Result<(), String> {
+        match constraint {
+            Constraint::True {
+                expr: Expr::Signature { key, signature },
+            } => self.sign(key, signature, constraint),
+            Constraint::False {
+                expr: Expr::Signature { signature, .. },
+            } => self.dissatisfy(signature),
+            Constraint::True {
+                expr:
+                    Expr::Multisig {
+                        required,
+                        keys,
+                        signatures,
+                    },
+            } => {
+                // Signatures are checked in the order of the keys.
+                let chosen = self.known_keys(keys);
+                if chosen.len() < *required {
+                    return Err(format!(
+                        "{} of the keys are known, {} needed for {}",
+                        chosen.len(),
+                        required,
+                        constraint
+                    ));
+                }
+                for (signature, key) in signatures.iter().zip(chosen) {
+                    if let Expr::Witness { index } = signature {
+                        self.set(*index, Item::Signature(key))?;
+                    }
+                }
+                Ok(())
+            }
+            Constraint::False {
+                expr: Expr::Multisig { signatures, .. },
+            } => signatures
+                .iter()
+                .try_for_each(|signature| self.dissatisfy(signature)),
+            // OP_CHECKSIGADD thresholds : `checksig(...) + ... == k`.
+            Constraint::True {
+                expr:
+                    Expr::Binary {
+                        op: "==",
+                        left,
+                        right,
+                    },
+            } => {
+                let (Some(checks), Some(required)) = (signature_sum(left), right.as_number())
+                else {
+                    return Ok(());
+                };
+                let keys: Vec<Expr> = checks.iter().map(|(key, _)| (*key).clone()).collect();
+                let chosen = self.known_keys(&keys);
+                if (chosen.len() as i64) < required {
+                    return Err(format!(
+                        "{} of the keys are known, {} needed for {}",
+                        chosen.len(),
+                        required,
+                        constraint
+                    ));
+                }
+                let mut chosen = chosen.into_iter().take(required as usize);
+                let mut next = chosen.next();
+                for (key, signature) in checks {
+                    match self
+                        .value(key)
+                        .and_then(|key| self.store.find_key(&key, self.sig_version))
+                    {
+                        Some(found) if Some(found) == next => {
+                            if let Expr::Witness { index } = signature {
+                                self.set(*index, Item::Signature(found))?;
+                            }
+                            next = chosen.next();
+                        }
+                        _ => self.dissatisfy(signature)?,
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn sign(
+        &mut self,
+        key: &Expr,
+        signature: &Expr,
+        constraint: &Constraint,
+    ) -> Result<(), String> {
+        let Expr::Witness { index } = signature else {
+            return Ok(());
+        };
+        let found = self
+            .value(key)
+            .and_then(|key| self.store.find_key(&key, self.sig_version))
+            .ok_or_else(|| format!("no private key for {}", constraint))?;
+        self.set(*index, Item::Signature(found))
+    }
+
+    /// An empty signature fails a check without failing the script.
+    fn dissatisfy(&mut self, signature: &Expr) -> Result<(), String> {
+        match signature {
+            Expr::Witness { index } => self.set(*index, Item::Data(vec![])),
+            _ => Ok(()),
+        }
+    }
+
+    /// Indexes in the store of the known keys among `keys`, in the same order.
+    fn known_keys(&self, keys: &[Expr]) -> Vec<usize> {
+        keys.iter()
+            .filter_map(|key| self.value(key))
+            .filter_map(|key| self.store.find_key(&key, self.sig_version))
+            .collect()
+    }
+}
+
+/// The `(key, signature)` checks added together by an OP_CHECKSIGADD chain.
+fn signature_sum(expr: &Expr) -> Option<Vec<(&Expr, &Expr)>> {
+    match expr {
+        Expr::Signature { key, signature } => Some(vec![(key.as_ref(), signature.as_ref())]),
+        Expr::Binary {
+            op: "+",
+            left,
+            right,
+        } => {
+            let mut checks = signature_sum(left)?;
+            checks.extend(signature_sum(right)?);
+            Some(checks)
+        }
+        _ => None,
+    }
+}
+
+fn lock_value(constraint: &Constraint, value: &Expr) -> Result<i64, String> {
+    value
+        .as_number()
+        .ok_or_else(|| format!("{} : the lock time is not a constant", constraint))
+}
+
+/// Strongest of two locks of the same kind (blocks or time).
+fn combine(
+    constraint: &Constraint,
+    current: Option<i64>,
+    n: i64,
+    in_blocks: impl Fn(i64) -> bool,
+) -> Result<i64, String> {
+    match current {
+        Some(current) if in_blocks(current) != in_blocks(n) => Err(format!(
+            "{} : the path mixes block and time locks",
+            constraint
+        )),
+        Some(current) => Ok(current.max(n)),
+        None => Ok(n),
+    }
+}
+
+fn hash(function: &str, data: &[u8]) -> Vec<u8> {
+    match function {
+        "ripemd160" => Ripemd160::digest(data).to_vec(),
+        "sha1" => Sha1::digest(data).to_vec(),
+        "sha256" => Sha256::digest(data).to_vec(),
+        "hash160" => Ripemd160::digest(Sha256::digest(data)).to_vec(),
+        _ => Sha256::digest(Sha256::digest(data)).to_vec(),
+    }
+}
+
+impl Solution {
+    /// Serialized witness size in bytes, item lengths included.
+    pub fn size(&self) -> usize {
+        let mut buffer = vec![];
+        for item in &self.witness {
+            write_bytes(&mut buffer, item);
+        }
+        buffer.len()
+    }
+}
+
+/// Runs the witness and the script together like the spending input would.
+fn confirm(
+    script: &[u8],
+    solution: &Solution,
+    context: &ExecutionContext,
+) -> Result<(), VerifyError> {
+    let mut context = context.clone();
+    context.tx = solution.tx.clone();
+    match context.sig_version {
+        SigVersion::Base => {
+            check_success(&run(script, Stack::from_data(&solution.witness), &context)?)
+        }
+        _ => execute_witness_script(script, &solution.witness, &context),
+    }
+}
+
+/// Builds the smallest witness satisfying `script` for the input of `context` with the
+/// secrets of `store`, or lists why no spending path can be satisfied.
+///
+/// Every path found by symbolic execution gets a witness from the keys, preimages and chain
+/// state of the store. The candidates are then run through the interpreter from the smallest,
+/// the first one succeeding is returned.
+pub fn solve(
+    script: &[u8],
+    context: &ExecutionContext,
+    store: &KeyStore,
+) -> Result<Solution, SolverError> {
+    let symbolic = execute_symbolic(script)?;
+    let mut reasons = vec![];
+    let mut solutions = vec![];
+
+    for (index, path) in symbolic.paths.iter().enumerate() {
+        match store.satisfy(script, path, context) {
+            Ok((witness, tx)) => solutions.push(Solution {
+                path: index,
+                witness,
+                tx,
+            }),
+            Err(reason) => reasons.push((index, reason)),
+        }
+    }
+
+    solutions.sort_by_key(Solution::size);
+    for solution in solutions {
+        match confirm(script, &solution, context) {
+            Ok(()) => return Ok(solution),
+            Err(error) => reasons.push((solution.path, error.to_string())),
+        }
+    }
+
+    reasons.sort();
+    let mut reasons: Vec<String> = reasons
+        .into_iter()
+        .map(|(index, reason)| format!("path {} : {}", index + 1, reason))
+        .collect();
+    if symbolic.unsatisfiable > 0 {
+        reasons.push(format!(
+            "{} paths fail whatever the witness",
+            symbolic.unsatisfiable
+        ));
+    }
+    Err(SolverError::Unsatisfiable(reasons))
+}
+
+#[cfg(test)]
+mod test_solver {
+    use crate::errors::SolverError;
+    use crate::script::encode::tokens_to_bytes;
+    use crate::stack::solver::{solve, KeyStore, Solution};
+    use crate::transaction::context::ExecutionContext;
+    use crate::transaction::sighash::{tap_leaf_hash, SigVersion, TAPSCRIPT_LEAF_VERSION};
+    use crate::transaction::verify::verify_input;
+    use crate::transaction::{Transaction, TxOut, SEQUENCE_FINAL};
+    use k256::ecdsa::SigningKey;
+    use ripemd::Ripemd160;
+    use rstest::rstest;
+    use sha2::{Digest, Sha256};
+
+    // Unsigned BIP143 transaction, the solved input is the first one.
+    const UNSIGNED_TX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+    const PREIMAGE: [u8; 32] = [7; 32];
+
+    /// Keys `A`, `B` and `C` of the scripts.
+    fn key(name: char) -> SigningKey {
+        SigningKey::from_slice(&[name as u8; 32]).unwrap()
+    }
+
+    fn public_key(name: char, x_only: bool) -> Vec<u8> {
+        let public_key = key(name).verifying_key().to_sec1_bytes().to_vec();
+        match x_only {
+            true => public_key[1..].to_vec(),
+            false => public_key,
+        }
+    }
+
+    fn store(names: &str) -> KeyStore {
+        names
+            .chars()
+            .fold(KeyStore::new(), |store, name| store.with_key(key(name)))
+    }
+
+    /// Script bytes with `A`, `B`, `C` replaced by public keys, `HA` by the hash160 of `A`
+    /// and `H` by the sha256 of the preimage.
+    fn script(text: &str, x_only: bool) -> Vec<u8> {
+        let tokens: Vec<String> = text
+            .split_whitespace()
+            .map(|token| match token {
+                "A" | "B" | "C" => hex::encode(public_key(token.chars().next().unwrap(), x_only)),
+                "HA" => hex::encode(Ripemd160::digest(Sha256::digest(public_key('A', false)))),
+                "H" => hex::encode(Sha256::digest(PREIMAGE)),
+                _ => token.to_string(),
+            })
+            .collect();
+        tokens_to_bytes(&tokens).unwrap()
+    }
+
+    fn p2wsh(script: &[u8]) -> TxOut {
+        let mut script_pubkey = vec![0x00, 0x20];
+        script_pubkey.extend_from_slice(&Sha256::digest(script));
+        TxOut {
+            value: 100_000,
+            script_pubkey,
+        }
+    }
+
+    fn prevouts(script: &[u8]) -> Vec<TxOut> {
+        vec![p2wsh(script), p2wsh(&[0x51])]
+    }
+
+    fn solve_segwit(text: &str, store: &KeyStore) -> Result<Solution, SolverError> {
+        let script = script(text, false);
+        let tx = Transaction::from_hex(UNSIGNED_TX).unwrap();
+        let context = ExecutionContext::new(tx, 0, p2wsh(&script), SigVersion::WitnessV0).unwrap();
+        solve(&script, &context, store)
+    }
+
+    /// Witness items with signatures, keys and the preimage named.
+    fn shape(solution: &Solution) -> Vec<String> {
+        solution
+            .witness
+            .iter()
+            .map(|item| match item.as_slice() {
+                [0x30, ..] if item.len() > 64 => "sig".to_string(),
+                _ if item.len() == 64 => "schnorr".to_string(),
+                _ if *item == PREIMAGE => "preimage".to_string(),
+                _ if *item == public_key('A', false) => "A".to_string(),
+                _ => hex::encode(item),
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case("A OP_CHECKSIG", "A", 0, vec!["sig"])]
+    #[case("OP_DUP OP_HASH160 HA OP_EQUALVERIFY OP_CHECKSIG", "A", 0, vec!["sig", "A"])]
+    #[case("OP_SIZE 32 OP_EQUALVERIFY OP_SHA256 H OP_EQUAL", "", 0, vec!["preimage"])]
+    #[case("OP_IF A OP_CHECKSIG OP_ELSE B OP_CHECKSIG OP_ENDIF", "A", 0, vec!["sig", "01"])]
+    #[case("OP_IF A OP_CHECKSIG OP_ELSE B OP_CHECKSIG OP_ENDIF", "B", 1, vec!["sig", ""])]
+    #[case("OP_IF A OP_CHECKSIG OP_ELSE OP_SHA256 H OP_EQUAL OP_ENDIF", "A", 1, vec!["preimage", ""])]
+    #[case("2 A B C 3 OP_CHECKMULTISIG", "CA", 0, vec!["", "sig", "sig"])]
+    #[case("A OP_CHECKSIG OP_NOTIF B OP_CHECKSIGVERIFY OP_ENDIF 1", "B", 1, vec!["sig", ""])]
+    fn test_solve(
+        #[case] text: &str,
+        #[case] keys: &str,
+        #[case] path: usize,
+        #[case] expected: Vec<&str>,
+    ) {
+        let solution = solve_segwit(text, &store(keys).with_preimage(PREIMAGE.to_vec())).unwrap();
+        assert_eq!(solution.path, path);
+        assert_eq!(shape(&solution), expected);
+    }
+
+    #[rstest]
+    fn test_solution_spends_the_output() {
+        let text = "OP_IF A OP_CHECKSIG OP_ELSE 2 B C 2 OP_CHECKMULTISIG OP_ENDIF";
+        let solution = solve_segwit(text, &store("BC")).unwrap();
+
+        let script = script(text, false);
+        let mut tx = solution.tx;
+        tx.inputs[0].witness = solution.witness;
+        tx.inputs[0].witness.push(script.clone());
+        assert_eq!(verify_input(&tx, 0, &prevouts(&script)), Ok(()));
+    }
+
+    #[rstest]
+    fn test_solve_locks() {
+        let relative = solve_segwit(
+            "144 OP_CHECKSEQUENCEVERIFY OP_DROP A OP_CHECKSIG",
+            &store("A").with_confirmations(144),
+        )
+        .unwrap();
+        assert_eq!(relative.tx.version, 2);
+        assert_eq!(relative.tx.inputs[0].sequence, 144);
+
+        let absolute = solve_segwit(
+            "800000 OP_CHECKLOCKTIMEVERIFY OP_DROP A OP_CHECKSIG",
+            &store("A").with_height(800_000),
+        )
+        .unwrap();
+        assert_eq!(absolute.tx.lock_time, 800_000);
+        assert_ne!(absolute.tx.inputs[0].sequence, SEQUENCE_FINAL);
+    }
+
+    #[rstest]
+    fn test_solve_tapscript() {
+        let script = script(
+            "A OP_CHECKSIG B OP_CHECKSIGADD C OP_CHECKSIGADD 2 OP_NUMEQUAL",
+            true,
+        );
+        let tx = Transaction::from_hex(UNSIGNED_TX).unwrap();
+        let leaf_hash = tap_leaf_hash(TAPSCRIPT_LEAF_VERSION, &script);
+        let context = ExecutionContext::taproot(tx, 0, prevouts(&script), Some(leaf_hash)).unwrap();
+
+        let solution = solve(&script, &context, &store("AC")).unwrap();
+        assert_eq!(shape(&solution), vec!["schnorr", "", "schnorr"]);
+    }
+
+    #[rstest]
+    #[case(
+        "A OP_CHECKSIG",
+        KeyStore::new(),
+        "path 1 : no private key for w0 is a valid signature"
+    )]
+    #[case(
+        "OP_SHA256 H OP_EQUAL",
+        store("A"),
+        "path 1 : no known preimage for sha256(w0) =="
+    )]
+    #[case(
+        "2 A B C 3 OP_CHECKMULTISIG",
+        store("B"),
+        "1 of the keys are known, 2 needed"
+    )]
+    #[case(
+        "144 OP_CHECKSEQUENCEVERIFY OP_DROP A OP_CHECKSIG",
+        store("A").with_confirmations(10),
+        "nSequence at least 144 blocks (relative) : output confirmations is 10"
+    )]
+    #[case(
+        "800000 OP_CHECKLOCKTIMEVERIFY OP_DROP A OP_CHECKSIG",
+        store("A"),
+        "nLockTime at least block 800000 (absolute) : current block height unknown"
+    )]
+    #[case(
+        "OP_IF OP_RETURN OP_ENDIF A OP_CHECKSIG",
+        KeyStore::new(),
+        "1 paths fail whatever the witness"
+    )]
+    fn test_unsatisfiable(#[case] text: &str, #[case] store: KeyStore, #[case] reason: &str) {
+        let error = solve_segwit(text, &store).unwrap_err();
+        assert!(
+            error.to_string().contains(reason),
+            "{} does not contain {}",
+            error,
+            reason
+        );
+    }
+}
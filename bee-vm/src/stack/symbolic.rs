@@ -0,0 +1,1063 @@
+use crate::errors::SymbolicError;
+use crate::opcodes::crypto_ops::op_check_multi_sig::MAX_PUBKEYS_PER_MULTISIG;
+use crate::opcodes::opcode::Opcode;
+use crate::opcodes::utils::script_num_to_bytes;
+use crate::script::decode::{minimal_script_num, script_num_from_bytes, Instruction, Instructions};
+use crate::script::encode::tokens_to_bytes;
+use crate::stack::MAX_STACK_SIZE;
+use serde::Serialize;
+use std::fmt;
+
+/// Forks allowed before giving up on a script.
+const MAX_PATHS: usize = 1000;
+/// Nodes allowed in one expression, `OP_DUP OP_ADD` doubles them.
+const MAX_EXPR_NODES: usize = 1000;
+
+/// CHECKSEQUENCEVERIFY flags (BIP68 / BIP112).
+const SEQUENCE_DISABLE_FLAG: i64 = 1 << 31;
+const SEQUENCE_TYPE_FLAG: i64 = 1 << 22;
+const SEQUENCE_MASK: i64 = 0xffff;
+/// nLockTime values below are block heights, above UNIX times.
+const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+
+/// A stack element in symbolic execution : a constant, an item of the initial stack (the
+/// witness) or what opcodes computed from them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Expr {
+    /// Item `index` of the initial stack, 0 being its top.
+    Witness {
+        index: usize,
+    },
+    Data {
+        hex: String,
+    },
+    Number {
+        value: i64,
+    },
+    Hash {
+        function: &'static str,
+        input: Box<Expr>,
+    },
+    Size {
+        input: Box<Expr>,
+    },
+    /// OP_CHECKSIG of `signature` against `key`.
+    Signature {
+        key: Box<Expr>,
+        signature: Box<Expr>,
+    },
+    /// OP_CHECKMULTISIG of `signatures` against `required` of `keys`.
+    Multisig {
+        required: usize,
+        keys: Vec<Expr>,
+        signatures: Vec<Expr>,
+    },
+    /// `abs`, `not` and `negate`.
+    Unary {
+        op: &'static str,
+        input: Box<Expr>,
+    },
+    /// Arithmetic, comparisons (`==` for OP_EQUAL and OP_NUMEQUAL), `min` and `max`.
+    Binary {
+        op: &'static str,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// OP_WITHIN : `min <= value < max`.
+    Within {
+        value: Box<Expr>,
+        min: Box<Expr>,
+        max: Box<Expr>,
+    },
+}
+
+/// A condition on the witness and the spending transaction for a path to succeed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Constraint {
+    /// OP_VERIFY and its variants, a taken conditional branch, the final stack top.
+    True { expr: Expr },
+    /// A conditional branch not taken, a dissatisfied OP_IFDUP.
+    False { expr: Expr },
+    /// OP_CHECKLOCKTIMEVERIFY : nLockTime at least `value` (and a non final nSequence).
+    LockTime { value: Expr },
+    /// OP_CHECKSEQUENCEVERIFY : the input's nSequence relative timelock at least `value`.
+    Sequence { value: Expr },
+}
+
+/// The constraints a witness must satisfy to spend through one path of the IF / ELSE tree.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpendingPath {
+    /// Initial stack items the path reads.
+    pub witness_items: usize,
+    pub constraints: Vec<Constraint>,
+}
+
+/// Spending paths of a script, without the paths that can never succeed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SymbolicResult {
+    pub paths: Vec<SpendingPath>,
+    /// Paths failing whatever the witness (OP_RETURN, a false constant verified...).
+    pub unsatisfiable: usize,
+}
+
+/// The interpreter state along one path.
+#[derive(Debug, Clone)]
+struct Path {
+    /// Index of the next instruction.
+    next: usize,
+    /// Top last.
+    stack: Vec<Expr>,
+    alt_stack: Vec<Expr>,
+    witness_items: usize,
+    /// Execution flags of the enclosing conditionals.
+    execute: Vec<bool>,
+    constraints: Vec<Constraint>,
+}
+
+/// Outcome of one instruction.
+enum Step {
+    Continue,
+    /// The path can not succeed.
+    Fail,
+    /// The path splits, the returned path takes the other side.
+    Fork(Box<Path>),
+}
+
+impl Expr {
+    pub fn data(bytes: &[u8]) -> Expr {
+        Expr::Data {
+            hex: hex::encode(bytes),
+        }
+    }
+
+    fn number(value: i64) -> Expr {
+        Expr::Number { value }
+    }
+
+    /// Nodes of the expression tree, counted up to just past `limit`.
+    fn nodes(&self, limit: usize) -> usize {
+        let children: Vec<&Expr> = match self {
+            Expr::Witness { .. } | Expr::Data { .. } | Expr::Number { .. } => vec![],
+            Expr::Hash { input, .. } | Expr::Size { input } | Expr::Unary { input, .. } => {
+                vec![input]
+            }
+            Expr::Signature { key, signature } => vec![key, signature],
+            Expr::Multisig {
+                keys, signatures, ..
+            } => keys.iter().chain(signatures).collect(),
+            Expr::Binary { left, right, .. } => vec![left, right],
+            Expr::Within { value, min, max } => vec![value, min, max],
+        };
+        children
+            .into_iter()
+            .fold(1, |nodes, child| match nodes > limit {
+                true => nodes,
+                false => nodes + child.nodes(limit - nodes),
+            })
+    }
+
+    fn boxed(&self) -> Box<Expr> {
+        Box::new(self.clone())
+    }
+
+    pub(crate) fn bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            Expr::Data { hex } => hex::decode(hex).ok(),
+            Expr::Number { value } => Some(script_num_to_bytes(*value)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_number(&self) -> Option<i64> {
+        match self {
+            Expr::Number { value } => Some(*value),
+            Expr::Data { .. } => self
+                .bytes()
+                .filter(|bytes| bytes.len() <= 4)
+                .map(|bytes| script_num_from_bytes(&bytes)),
+            _ => None,
+        }
+    }
+
+    /// Truthiness of constants, see `cast_to_bool`.
+    pub(crate) fn truth(&self) -> Option<bool> {
+        let bytes = self.bytes()?;
+        Some(match bytes.split_last() {
+            Some((last, rest)) => rest.iter().any(|b| *b != 0) || (*last != 0 && *last != 0x80),
+            None => false,
+        })
+    }
+}
+
+/// Numeric opcodes, folded when their operands are constants.
+fn unary(op: &'static str, input: Expr) -> Expr {
+    match input.as_number() {
+        Some(n) => Expr::number(match op {
+            "abs" => n.abs(),
+            "negate" => -n,
+            _ => (n == 0) as i64,
+        }),
+        None => Expr::Unary {
+            op,
+            input: Box::new(input),
+        },
+    }
+}
+
+fn binary(op: &'static str, left: Expr, right: Expr) -> Expr {
+    let value = match (left.as_number(), right.as_number()) {
+        (Some(a), Some(b)) => Some(match op {
+            "+" => a + b,
+            "-" => a - b,
+            "&&" => (a != 0 && b != 0) as i64,
+            "||" => (a != 0 || b != 0) as i64,
+            "==" => (a == b) as i64,
+            "!=" => (a != b) as i64,
+            "<" => (a < b) as i64,
+            ">" => (a > b) as i64,
+            "<=" => (a <= b) as i64,
+            ">=" => (a >= b) as i64,
+            "min" => a.min(b),
+            _ => a.max(b),
+        }),
+        _ => None,
+    };
+    match value {
+        Some(value) => Expr::number(value),
+        None => Expr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        },
+    }
+}
+
+/// OP_EQUAL compares bytes, equal expressions are equal whatever the witness.
+fn equal(left: Expr, right: Expr) -> Expr {
+    match (left.bytes(), right.bytes()) {
+        (Some(a), Some(b)) => Expr::number((a == b) as i64),
+        _ if left == right => Expr::number(1),
+        _ => Expr::Binary {
+            op: "==",
+            left: Box::new(left),
+            right: Box::new(right),
+        },
+    }
+}
+
+impl Path {
+    fn executing(&self) -> bool {
+        self.execute.iter().all(|execute| *execute)
+    }
+
+    /// Pops the top, reading the next initial stack item when the path pushed nothing left.
+    fn pop(&mut self) -> Expr {
+        self.stack.pop().unwrap_or_else(|| {
+            self.witness_items += 1;
+            Expr::Witness {
+                index: self.witness_items - 1,
+            }
+        })
+    }
+
+    /// Pops `count` items, top last.
+    fn pop_many(&mut self, count: usize) -> Vec<Expr> {
+        let mut items: Vec<Expr> = (0..count).map(|_| self.pop()).collect();
+        items.reverse();
+        items
+    }
+
+    fn push(&mut self, expr: Expr) {
+        self.stack.push(expr);
+    }
+
+    /// Requires `expr` to be true (or false), `false` when it never can.
+    fn constrain(&mut self, mut expr: Expr, mut holds: bool) -> bool {
+        // not(x) is true when x is false.
+        while let Expr::Unary { op: "not", input } = expr {
+            expr = *input;
+            holds = !holds;
+        }
+        if let Some(truth) = expr.truth() {
+            return truth == holds;
+        }
+        let (this, opposite) = match holds {
+            true => (
+                Constraint::True { expr: expr.clone() },
+                Constraint::False { expr },
+            ),
+            false => (
+                Constraint::False { expr: expr.clone() },
+                Constraint::True { expr },
+            ),
+        };
+        if self.constraints.contains(&opposite) {
+            return false;
+        }
+        if !self.constraints.contains(&this) {
+            self.constraints.push(this);
+        }
+        true
+    }
+
+    /// Splits the path on the truth of `condition`, `on_true` and `on_false` finishing each
+    /// side. This path takes the true side when both are possible.
+    fn fork(
+        &mut self,
+        condition: &Expr,
+        on_true: impl Fn(&mut Path),
+        on_false: impl Fn(&mut Path),
+    ) -> Step {
+        let mut other = self.clone();
+        let true_side = self.constrain(condition.clone(), true);
+        let false_side = other.constrain(condition.clone(), false);
+        on_true(self);
+        on_false(&mut other);
+        match (true_side, false_side) {
+            (true, true) => Step::Fork(Box::new(other)),
+            (true, false) => Step::Continue,
+            (false, true) => {
+                *self = other;
+                Step::Continue
+            }
+            (false, false) => Step::Fail,
+        }
+    }
+
+    fn verify(&mut self) -> Step {
+        let top = self.pop();
+        match self.constrain(top, true) {
+            true => Step::Continue,
+            false => Step::Fail,
+        }
+    }
+
+    /// Pops a constant operand of OP_PICK / OP_ROLL / OP_CHECKMULTISIG, `None` when it is
+    /// outside `0..=limit` and fails the script.
+    fn operand(
+        &mut self,
+        opcode: Opcode,
+        position: usize,
+        limit: usize,
+    ) -> Result<Option<usize>, SymbolicError> {
+        let n = self.pop().as_number().ok_or_else(|| {
+            SymbolicError::NonConstantOperand(opcode.name().to_string(), position)
+        })?;
+        Ok((0..=limit as i64).contains(&n).then_some(n as usize))
+    }
+
+    fn step(&mut self, instruction: &Instruction, position: usize) -> Result<Step, SymbolicError> {
+        let opcode = match instruction {
+            Instruction::Push { data, .. } => {
+                if self.executing() {
+                    self.push(minimal_script_num(data).map_or(Expr::data(data), Expr::number));
+                }
+                return Ok(Step::Continue);
+            }
+            Instruction::Op(opcode) => *opcode,
+        };
+
+        if !self.executing() {
+            match opcode {
+                Opcode::OP_IF | Opcode::OP_NOTIF => self.execute.push(false),
+                Opcode::OP_ELSE => {
+                    let last = self.execute.len() - 1;
+                    self.execute[last] = !self.execute[last];
+                }
+                Opcode::OP_ENDIF => {
+                    self.execute.pop();
+                }
+                _ => {}
+            }
+            return Ok(Step::Continue);
+        }
+
+        if let Some(n) = opcode.small_int() {
+            self.push(Expr::number(n));
+            return Ok(Step::Continue);
+        }
+
+        let name = opcode.name();
+        match name {
+            "OP_0" => self.push(Expr::data(&[])),
+            "OP_NOP" | "OP_NOP1" | "OP_NOP4" | "OP_NOP5" | "OP_NOP6" | "OP_NOP7" | "OP_NOP8"
+            | "OP_NOP9" | "OP_NOP10" | "OP_CODESEPARATOR" => {}
+
+            // ============================================
+            // CONTROL FLOW
+            // ============================================
+            "OP_IF" | "OP_NOTIF" => {
+                let condition = self.pop();
+                let is_if = name == "OP_IF";
+                return Ok(self.fork(
+                    &condition,
+                    |path| path.execute.push(is_if),
+                    |path| path.execute.push(!is_if),
+                ));
+            }
+            "OP_ELSE" => match self.execute.last_mut() {
+                Some(execute) => *execute = !*execute,
+                None => return Err(SymbolicError::UnbalancedConditional(position)),
+            },
+            "OP_ENDIF" => {
+                if self.execute.pop().is_none() {
+                    return Err(SymbolicError::UnbalancedConditional(position));
+                }
+            }
+            "OP_VERIFY" => return Ok(self.verify()),
+
+            // ============================================
+            // STACK OPS
+            // ============================================
+            "OP_TOALTSTACK" => {
+                let top = self.pop();
+                self.alt_stack.push(top);
+            }
+            "OP_FROMALTSTACK" => match self.alt_stack.pop() {
+                Some(item) => self.push(item),
+                None => return Ok(Step::Fail),
+            },
+            "OP_DROP" => {
+                self.pop();
+            }
+            "OP_2DROP" => {
+                self.pop_many(2);
+            }
+            "OP_DUP" | "OP_2DUP" | "OP_3DUP" => {
+                let count = match name {
+                    "OP_DUP" => 1,
+                    "OP_2DUP" => 2,
+                    _ => 3,
+                };
+                let items = self.pop_many(count);
+                self.stack.extend(items.clone());
+                self.stack.extend(items);
+            }
+            "OP_IFDUP" => {
+                let top = self.pop();
+                self.push(top.clone());
+                return Ok(self.fork(&top, |path| path.push(top.clone()), |_| {}));
+            }
+            "OP_NIP" => {
+                let items = self.pop_many(2);
+                self.push(items[1].clone());
+            }
+            "OP_OVER" | "OP_2OVER" => {
+                let count = match name {
+                    "OP_OVER" => 1,
+                    _ => 2,
+                };
+                let items = self.pop_many(2 * count);
+                self.stack.extend(items.clone());
+                self.stack.extend(items[..count].to_vec());
+            }
+            "OP_ROT" | "OP_2ROT" => {
+                let count = match name {
+                    "OP_ROT" => 1,
+                    _ => 2,
+                };
+                let mut items = self.pop_many(3 * count);
+                items.rotate_left(count);
+                self.stack.extend(items);
+            }
+            "OP_SWAP" | "OP_2SWAP" => {
+                let count = match name {
+                    "OP_SWAP" => 1,
+                    _ => 2,
+                };
+                let mut items = self.pop_many(2 * count);
+                items.rotate_left(count);
+                self.stack.extend(items);
+            }
+            "OP_TUCK" => {
+                let items = self.pop_many(2);
+                self.stack
+                    .extend([items[1].clone(), items[0].clone(), items[1].clone()]);
+            }
+            "OP_PICK" | "OP_ROLL" => {
+                let Some(n) = self.operand(opcode, position, MAX_STACK_SIZE)? else {
+                    return Ok(Step::Fail);
+                };
+                let mut items = self.pop_many(n + 1);
+                let item = match name {
+                    "OP_PICK" => items[0].clone(),
+                    _ => items.remove(0),
+                };
+                self.stack.extend(items);
+                self.push(item);
+            }
+            "OP_SIZE" => {
+                let top = self.pop();
+                let size = match top.bytes() {
+                    Some(bytes) => Expr::number(bytes.len() as i64),
+                    None => Expr::Size { input: top.boxed() },
+                };
+                self.stack.extend([top, size]);
+            }
+
+            // ============================================
+            // ARITHMETICS AND COMPARISONS
+            // ============================================
+            "OP_1ADD" | "OP_1SUB" => {
+                let top = self.pop();
+                let op = match name {
+                    "OP_1ADD" => "+",
+                    _ => "-",
+                };
+                self.push(binary(op, top, Expr::number(1)));
+            }
+            "OP_NEGATE" | "OP_ABS" | "OP_NOT" => {
+                let top = self.pop();
+                let op = match name {
+                    "OP_NEGATE" => "negate",
+                    "OP_ABS" => "abs",
+                    _ => "not",
+                };
+                self.push(unary(op, top));
+            }
+            "OP_0NOTEQUAL" => {
+                let top = self.pop();
+                self.push(binary("!=", top, Expr::number(0)));
+            }
+            "OP_ADD"
+            | "OP_SUB"
+            | "OP_BOOLAND"
+            | "OP_BOOLOR"
+            | "OP_NUMEQUAL"
+            | "OP_NUMEQUALVERIFY"
+            | "OP_NUMNOTEQUAL"
+            | "OP_LESSTHAN"
+            | "OP_GREATERTHAN"
+            | "OP_LESSTHANOREQUAL"
+            | "OP_GREATERTHANOREQUAL"
+            | "OP_MIN"
+            | "OP_MAX" => {
+                let items = self.pop_many(2);
+                let op = match name {
+                    "OP_ADD" => "+",
+                    "OP_SUB" => "-",
+                    "OP_BOOLAND" => "&&",
+                    "OP_BOOLOR" => "||",
+                    "OP_NUMEQUAL" | "OP_NUMEQUALVERIFY" => "==",
+                    "OP_NUMNOTEQUAL" => "!=",
+                    "OP_LESSTHAN" => "<",
+                    "OP_GREATERTHAN" => ">",
+                    "OP_LESSTHANOREQUAL" => "<=",
+                    "OP_GREATERTHANOREQUAL" => ">=",
+                    "OP_MIN" => "min",
+                    _ => "max",
+                };
+                let [left, right] = <[Expr; 2]>::try_from(items).unwrap();
+                self.push(binary(op, left, right));
+                if name == "OP_NUMEQUALVERIFY" {
+                    return Ok(self.verify());
+                }
+            }
+            "OP_WITHIN" => {
+                let items = self.pop_many(3);
+                let [value, min, max] = <[Expr; 3]>::try_from(items).unwrap();
+                let within = match (value.as_number(), min.as_number(), max.as_number()) {
+                    (Some(v), Some(lo), Some(hi)) => Expr::number((lo <= v && v < hi) as i64),
+                    _ => Expr::Within {
+                        value: Box::new(value),
+                        min: Box::new(min),
+                        max: Box::new(max),
+                    },
+                };
+                self.push(within);
+            }
+            "OP_EQUAL" | "OP_EQUALVERIFY" => {
+                let items = self.pop_many(2);
+                let [left, right] = <[Expr; 2]>::try_from(items).unwrap();
+                self.push(equal(left, right));
+                if name == "OP_EQUALVERIFY" {
+                    return Ok(self.verify());
+                }
+            }
+
+            // ============================================
+            // CRYPTO OPS
+            // ============================================
+            "OP_RIPEMD160" | "OP_SHA1" | "OP_SHA256" | "OP_HASH160" | "OP_HASH256" => {
+                let top = self.pop();
+                let function = match name {
+                    "OP_RIPEMD160" => "ripemd160",
+                    "OP_SHA1" => "sha1",
+                    "OP_SHA256" => "sha256",
+                    "OP_HASH160" => "hash160",
+                    _ => "hash256",
+                };
+                self.push(Expr::Hash {
+                    function,
+                    input: top.boxed(),
+                });
+            }
+            "OP_CHECKSIG" | "OP_CHECKSIGVERIFY" => {
+                let key = self.pop();
+                let signature = self.pop();
+                self.push(Expr::Signature {
+                    key: Box::new(key),
+                    signature: Box::new(signature),
+                });
+                if name == "OP_CHECKSIGVERIFY" {
+                    return Ok(self.verify());
+                }
+            }
+            "OP_CHECKSIGADD" => {
+                let key = self.pop();
+                let n = self.pop();
+                let signature = self.pop();
+                let check = Expr::Signature {
+                    key: Box::new(key),
+                    signature: Box::new(signature),
+                };
+                self.push(match n.as_number() {
+                    Some(0) => check,
+                    _ => binary("+", n, check),
+                });
+            }
+            "OP_CHECKMULTISIG" | "OP_CHECKMULTISIGVERIFY" => {
+                let Some(key_count) = self.operand(opcode, position, MAX_PUBKEYS_PER_MULTISIG)?
+                else {
+                    return Ok(Step::Fail);
+                };
+                let keys = self.pop_many(key_count);
+                let Some(required) = self.operand(opcode, position, key_count)? else {
+                    return Ok(Step::Fail);
+                };
+                let signatures = self.pop_many(required);
+                // The extra item popped by OP_CHECKMULTISIG must be empty (BIP147).
+                let dummy = self.pop();
+                if !self.constrain(equal(dummy, Expr::data(&[])), true) {
+                    return Ok(Step::Fail);
+                }
+                self.push(Expr::Multisig {
+                    required,
+                    keys,
+                    signatures,
+                });
+                if name == "OP_CHECKMULTISIGVERIFY" {
+                    return Ok(self.verify());
+                }
+            }
+
+            // ============================================
+            // LOCK TIME OPS
+            // ============================================
+            "OP_CHECKLOCKTIMEVERIFY" | "OP_CHECKSEQUENCEVERIFY" => {
+                let value = self.pop();
+                self.push(value.clone());
+                match value.as_number() {
+                    Some(n) if n < 0 => return Ok(Step::Fail),
+                    Some(n)
+                        if name == "OP_CHECKSEQUENCEVERIFY" && n & SEQUENCE_DISABLE_FLAG != 0 => {}
+                    _ => self.constraints.push(match name {
+                        "OP_CHECKLOCKTIMEVERIFY" => Constraint::LockTime { value },
+                        _ => Constraint::Sequence { value },
+                    }),
+                }
+            }
+
+            "OP_DEPTH" => return Err(SymbolicError::Unsupported(name.to_string())),
+            _ if opcode.fails() => return Ok(Step::Fail),
+            _ => return Err(SymbolicError::Unsupported(name.to_string())),
+        }
+        Ok(Step::Continue)
+    }
+}
+
+/// Runs serialized script bytes with a symbolic initial stack and returns, for every path
+/// through its conditionals, the constraints a witness (and the spending transaction) must
+/// satisfy for the script to succeed.
+pub fn execute_symbolic(script: &[u8]) -> Result<SymbolicResult, SymbolicError> {
+    let mut instructions = vec![];
+    let mut iter = Instructions::new(script);
+    loop {
+        let position = iter.position();
+        match iter.next() {
+            Some(instruction) => instructions.push((position, instruction?)),
+            None => break,
+        }
+    }
+
+    let mut pending = vec![Path {
+        next: 0,
+        stack: vec![],
+        alt_stack: vec![],
+        witness_items: 0,
+        execute: vec![],
+        constraints: vec![],
+    }];
+    let mut paths = vec![];
+    let mut unsatisfiable = 0;
+    let mut forks = 0;
+
+    'paths: while let Some(mut path) = pending.pop() {
+        while let Some((position, instruction)) = instructions.get(path.next) {
+            path.next += 1;
+            let step = path.step(instruction, *position)?;
+            // Opcodes combining items push the result on top.
+            if path
+                .stack
+                .last()
+                .is_some_and(|top| top.nodes(MAX_EXPR_NODES) > MAX_EXPR_NODES)
+            {
+                return Err(SymbolicError::ExpressionTooLarge(MAX_EXPR_NODES));
+            }
+            match step {
+                Step::Continue => {}
+                Step::Fail => {
+                    unsatisfiable += 1;
+                    continue 'paths;
+                }
+                Step::Fork(other) => {
+                    forks += 1;
+                    if forks > MAX_PATHS {
+                        return Err(SymbolicError::TooManyPaths(MAX_PATHS));
+                    }
+                    pending.push(*other);
+                }
+            }
+        }
+        if !path.execute.is_empty() {
+            return Err(SymbolicError::UnbalancedConditional(script.len()));
+        }
+        // The script succeeds with a true stack top.
+        let top = path.pop();
+        match path.constrain(top, true) {
+            true => paths.push(SpendingPath {
+                witness_items: path.witness_items,
+                constraints: path.constraints,
+            }),
+            false => unsatisfiable += 1,
+        }
+    }
+
+    Ok(SymbolicResult {
+        paths,
+        unsatisfiable,
+    })
+}
+
+/// Symbolic execution of a Bee VM token list through its serialized form.
+pub fn execute_symbolic_tokens(tokens: &[String]) -> Result<SymbolicResult, SymbolicError> {
+    execute_symbolic(&tokens_to_bytes(tokens)?)
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Witness { index } => write!(f, "w{}", index),
+            Expr::Data { hex } => write!(f, "<{}>", hex),
+            Expr::Number { value } => write!(f, "{}", value),
+            Expr::Hash { function, input } => write!(f, "{}({})", function, input),
+            Expr::Size { input } => write!(f, "size({})", input),
+            Expr::Signature { key, signature } => write!(f, "checksig({}, {})", signature, key),
+            Expr::Multisig {
+                required,
+                keys,
+                signatures,
+            } => write!(
+                f,
+                "checkmultisig({} of [{}], [{}])",
+                required,
+                join(keys),
+                join(signatures)
+            ),
+            Expr::Unary { op, input } => write!(f, "{}({})", op, input),
+            Expr::Binary { op, left, right } if matches!(*op, "min" | "max") => {
+                write!(f, "{}({}, {})", op, left, right)
+            }
+            Expr::Binary { op, left, right } => write!(f, "({} {} {})", left, op, right),
+            Expr::Within { value, min, max } => write!(f, "({} <= {} < {})", min, value, max),
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::True {
+                expr: Expr::Signature { key, signature },
+            } => write!(f, "{} is a valid signature for {}", signature, key),
+            Constraint::False {
+                expr: Expr::Signature { key, signature },
+            } => write!(f, "{} is empty (no signature for {})", signature, key),
+            Constraint::True {
+                expr:
+                    Expr::Multisig {
+                        required,
+                        keys,
+                        signatures,
+                    },
+            } => write!(
+                f,
+                "[{}] are valid signatures for {} of [{}]",
+                join(signatures),
+                required,
+                join(keys)
+            ),
+            Constraint::False {
+                expr: Expr::Multisig { signatures, .. },
+            } => write!(f, "[{}] are empty", join(signatures)),
+            Constraint::True {
+                expr:
+                    Expr::Binary {
+                        op: "==",
+                        left,
+                        right,
+                    },
+            } => write!(f, "{} == {}", left, right),
+            Constraint::False {
+                expr:
+                    Expr::Binary {
+                        op: "==",
+                        left,
+                        right,
+                    },
+            } => write!(f, "{} != {}", left, right),
+            Constraint::True { expr } => write!(f, "{} is true", expr),
+            Constraint::False { expr } => write!(f, "{} is false", expr),
+            Constraint::LockTime { value } => match value.as_number() {
+                Some(n) if n < LOCKTIME_THRESHOLD => {
+                    write!(f, "nLockTime at least block {} (absolute)", n)
+                }
+                Some(n) => write!(f, "nLockTime at least time {} (absolute)", n),
+                None => write!(f, "nLockTime at least {} (absolute)", value),
+            },
+            Constraint::Sequence { value } => match value.as_number() {
+                Some(n) if n & SEQUENCE_TYPE_FLAG != 0 => write!(
+                    f,
+                    "nSequence at least {} seconds (relative)",
+                    (n & SEQUENCE_MASK) * 512
+                ),
+                Some(n) => write!(
+                    f,
+                    "nSequence at least {} blocks (relative)",
+                    n & SEQUENCE_MASK
+                ),
+                None => write!(f, "nSequence at least {} (relative)", value),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SymbolicResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, path) in self.paths.iter().enumerate() {
+            writeln!(
+                f,
+                "Path {} ({} witness items, w0 on top)",
+                index + 1,
+                path.witness_items
+            )?;
+            for constraint in &path.constraints {
+                writeln!(f, "  - {}", constraint)?;
+            }
+        }
+        write!(
+            f,
+            "{} spending paths, {} unsatisfiable",
+            self.paths.len(),
+            self.unsatisfiable
+        )
+    }
+}
+
+fn join(exprs: &[Expr]) -> String {
+    exprs
+        .iter()
+        .map(|expr| expr.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test_symbolic {
+    use crate::errors::SymbolicError;
+    use crate::stack::symbolic::{execute_symbolic_tokens, SymbolicResult};
+    use rstest::rstest;
+
+    /// Keys and hashes named in the scripts and the expected constraints.
+    const NAMES: [(&str, &str); 4] = [
+        (
+            "A",
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        ),
+        (
+            "B",
+            "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+        ),
+        (
+            "C",
+            "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+        ),
+        (
+            "H",
+            "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925",
+        ),
+    ];
+
+    fn run(script: &str) -> Result<SymbolicResult, SymbolicError> {
+        let tokens: Vec<String> = script
+            .split_whitespace()
+            .map(
+                |token| match NAMES.iter().find(|(name, _)| *name == token) {
+                    Some((_, hex)) => hex.to_string(),
+                    None => token.to_string(),
+                },
+            )
+            .collect();
+        execute_symbolic_tokens(&tokens)
+    }
+
+    /// Witness items and constraints of every path, as text.
+    fn paths(script: &str) -> Vec<(usize, Vec<String>)> {
+        run(script)
+            .unwrap()
+            .paths
+            .iter()
+            .map(|path| {
+                let constraints = path
+                    .constraints
+                    .iter()
+                    .map(|constraint| {
+                        NAMES
+                            .iter()
+                            .fold(constraint.to_string(), |text, (name, hex)| {
+                                text.replace(hex, name)
+                            })
+                    })
+                    .collect();
+                (path.witness_items, constraints)
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case(
+        "OP_DUP OP_HASH160 H OP_EQUALVERIFY OP_CHECKSIG",
+        vec![(2, vec!["hash160(w0) == <H>", "w1 is a valid signature for w0"])]
+    )]
+    #[case(
+        "OP_SIZE 32 OP_EQUALVERIFY OP_SHA256 H OP_EQUAL",
+        vec![(1, vec!["size(w0) == 32", "sha256(w0) == <H>"])]
+    )]
+    #[case(
+        "OP_DUP 10 100 OP_WITHIN OP_VERIFY 5 OP_GREATERTHAN",
+        vec![(1, vec!["(10 <= w0 < 100) is true", "(w0 > 5) is true"])]
+    )]
+    #[case(
+        "2 A B C 3 OP_CHECKMULTISIG",
+        vec![(3, vec!["w2 == <>", "[w1, w0] are valid signatures for 2 of [<A>, <B>, <C>]"])]
+    )]
+    #[case(
+        "A OP_CHECKSIG B OP_CHECKSIGADD 2 OP_NUMEQUAL",
+        vec![(2, vec!["(checksig(w0, <A>) + checksig(w1, <B>)) == 2"])]
+    )]
+    fn test_single_path(#[case] script: &str, #[case] expected: Vec<(usize, Vec<&str>)>) {
+        let expected: Vec<(usize, Vec<String>)> = expected
+            .into_iter()
+            .map(|(items, c)| (items, c.into_iter().map(str::to_string).collect()))
+            .collect();
+        assert_eq!(paths(script), expected);
+    }
+
+    #[rstest]
+    fn test_if_else_paths() {
+        let script = "OP_IF OP_SHA256 H OP_EQUALVERIFY A OP_ELSE 1000 \
+            OP_CHECKLOCKTIMEVERIFY OP_DROP B OP_ENDIF OP_CHECKSIG";
+        assert_eq!(
+            paths(script),
+            vec![
+                (
+                    3,
+                    vec![
+                        "w0 is true".to_string(),
+                        "sha256(w1) == <H>".to_string(),
+                        "w2 is a valid signature for <A>".to_string(),
+                    ]
+                ),
+                (
+                    2,
+                    vec![
+                        "w0 is false".to_string(),
+                        "nLockTime at least block 1000 (absolute)".to_string(),
+                        "w1 is a valid signature for <B>".to_string(),
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_if_dup_paths() {
+        // or_d(pk(A),and_v(v:pk(B),older(4194305)))
+        let script = "A OP_CHECKSIG OP_IFDUP OP_NOTIF B OP_CHECKSIGVERIFY 4194305 \
+            OP_CHECKSEQUENCEVERIFY OP_ENDIF";
+        assert_eq!(
+            paths(script),
+            vec![
+                (1, vec!["w0 is a valid signature for <A>".to_string()]),
+                (
+                    2,
+                    vec![
+                        "w0 is empty (no signature for <A>)".to_string(),
+                        "w1 is a valid signature for <B>".to_string(),
+                        "nSequence at least 512 seconds (relative)".to_string(),
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[rstest]
+    // Nested conditionals on the same item : the contradicting paths are dropped.
+    #[case(
+        "OP_DUP OP_IF OP_IF 1 OP_ELSE 0 OP_ENDIF OP_ELSE OP_DROP 1 OP_ENDIF",
+        2,
+        0
+    )]
+    #[case("0 OP_IF OP_RETURN OP_ENDIF 1", 1, 0)]
+    #[case("1 OP_IF 1 OP_ELSE OP_RETURN OP_ENDIF", 1, 0)]
+    #[case("OP_IF OP_RETURN OP_ENDIF 1", 1, 1)]
+    #[case("0 OP_VERIFY 1", 0, 1)]
+    fn test_path_counts(
+        #[case] script: &str,
+        #[case] satisfiable: usize,
+        #[case] unsatisfiable: usize,
+    ) {
+        let result = run(script).unwrap();
+        assert_eq!(result.paths.len(), satisfiable);
+        assert_eq!(result.unsatisfiable, unsatisfiable);
+    }
+
+    #[rstest]
+    #[case("OP_PICK", SymbolicError::NonConstantOperand("OP_PICK".to_string(), 0))]
+    #[case("OP_DEPTH", SymbolicError::Unsupported("OP_DEPTH".to_string()))]
+    #[case("OP_IF 1", SymbolicError::UnbalancedConditional(2))]
+    #[case("1 OP_ENDIF", SymbolicError::UnbalancedConditional(1))]
+    fn test_errors(#[case] script: &str, #[case] expected: SymbolicError) {
+        assert_eq!(run(script), Err(expected));
+    }
+
+    #[rstest]
+    fn test_too_many_paths() {
+        let script = "OP_IF OP_ENDIF ".repeat(10) + "1";
+        assert_eq!(run(&script), Err(SymbolicError::TooManyPaths(1000)));
+    }
+
+    #[rstest]
+    fn test_json() {
+        let result = run("OP_SHA256 H OP_EQUAL").unwrap();
+        let json = serde_json::to_value(&result).unwrap();
+        let expr = &json["paths"][0]["constraints"][0]["expr"];
+        assert_eq!(json["paths"][0]["witness_items"], 1);
+        assert_eq!(expr["op"], "==");
+        assert_eq!(expr["left"]["type"], "hash");
+        assert_eq!(expr["left"]["input"]["index"], 0);
+        assert_eq!(expr["right"]["hex"], NAMES[3].1);
+    }
+}
@@ -11,7 +11,7 @@ use sha2::Sha256;
 fn test_p2sh() -> color_eyre::Result<()> {
     let signing_key: SigningKey = SigningKey::random(&mut OsRng);
     let verifying_key = signing_key.verifying_key().to_sec1_bytes();
-    let pub_key_hash = hash_160(hex::encode(verifying_key.clone()));
+    let pub_key_hash = hash_160(&verifying_key);
 
     let reedem_script: Vec<String> = vec![
         hex::encode(verifying_key.clone()),
@@ -56,7 +56,7 @@ fn test_p2sh() -> color_eyre::Result<()> {
 fn test_p2pkh() -> color_eyre::Result<()> {
     let signing_key: SigningKey = SigningKey::random(&mut OsRng);
     let verifying_key = signing_key.verifying_key().to_sec1_bytes();
-    let pub_key_hash = hash_160(hex::encode(verifying_key.clone()));
+    let pub_key_hash = hash_160(&verifying_key);
 
     let reedem_script = vec![
         hex::encode(verifying_key.clone()),
@@ -100,7 +100,8 @@ fn test_p2pkh() -> color_eyre::Result<()> {
     Ok(())
 }
 
-fn hash_160(data: String) -> String {
+/// OP_HASH160 hashes the bytes of the element, here the sec1 encoded public key.
+fn hash_160(data: &[u8]) -> String {
     // sha 256
     let hash = Sha256::digest(data);
     // ripe md 160
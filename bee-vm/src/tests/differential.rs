@@ -0,0 +1,1129 @@
+//! Differential tests against rust-bitcoin, rust-miniscript and libbitcoinconsensus, used as
+//! reference implementations.
+//!
+//! Script decoding and encoding, template classification, transaction serialization and the
+//! signature hashes are compared with rust-bitcoin on generated inputs. Script execution is
+//! compared with the rust-miniscript interpreter on signed spends of miniscript outputs, the
+//! scripts it knows how to run. Generated scripts and the scripts of `scripts/*.bscript` are
+//! run from generated stacks by Bitcoin Core's interpreter (libbitcoinconsensus) as bare,
+//! P2WSH and tapscript spends, both the outcome and the final stack have to match. The seeds of
+//! the `decode` fuzz target (`fuzz/seeds/decode`) and, after a fuzzing run, its corpus
+//! (`fuzz/corpus/decode`) are replayed through the script comparisons.
+
+use crate::opcodes::opcode::Opcode;
+use crate::script::classify::{classify, witness_program, ScriptType};
+use crate::script::decode::{data_to_token, Instruction, Instructions};
+use crate::script::encode::{element_to_bytes, tokens_to_bytes};
+use crate::stack::Stack;
+use crate::transaction::context::ExecutionContext;
+use crate::transaction::sighash::{
+    legacy_sighash, segwit_v0_sighash, tap_leaf_hash, taproot_sighash, SigVersion, TapLeafData,
+    TAPSCRIPT_LEAF_VERSION,
+};
+use crate::transaction::verify::{run, verify_input};
+use crate::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use bitcoin::absolute::LockTime;
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::opcodes::all::{OP_CODESEPARATOR, OP_DEPTH, OP_EQUALVERIFY, OP_PUSHNUM_1};
+use bitcoin::script::{self, read_scriptint, Builder, PushBytesBuf};
+use bitcoin::secp256k1::{Keypair, Message, Secp256k1, SecretKey};
+use bitcoin::sighash::{Annex, EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{LeafVersion, TapLeafHash, TapNodeHash, TaprootBuilder};
+use bitcoin::{Amount, Script, ScriptBuf, Sequence, Witness};
+use bitcoinconsensus::Utxo;
+use miniscript::descriptor::Descriptor;
+use miniscript::{Interpreter, Preimage32, Satisfier};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use rstest::rstest;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Preimage of the `H` hash in the spent descriptors.
+const PREIMAGE: [u8; 32] = [7; 32];
+
+/// Spent output descriptors and the keys the signer holds. `K1` - `K4` stand for the public
+/// keys of the secret keys `[1; 32]` - `[4; 32]`, `H` for the sha256 of `PREIMAGE`.
+const SPENDS: &[(&str, &[u8])] = &[
+    ("pk(K1)", &[1]),
+    ("pkh(K1)", &[1]),
+    ("wpkh(K1)", &[1]),
+    ("sh(wpkh(K1))", &[1]),
+    ("sh(multi(2,K1,K2,K3))", &[1, 3]),
+    ("wsh(multi(2,K1,K2,K3))", &[2, 3]),
+    ("wsh(and_v(v:pk(K1),sha256(H)))", &[1]),
+    ("wsh(or_d(pk(K1),and_v(v:pk(K2),older(10))))", &[2]),
+    ("sh(wsh(andor(pk(K1),older(5),pk(K2))))", &[2]),
+    ("wsh(and_v(v:pk(K1),after(20)))", &[1]),
+    ("tr(K1)", &[1]),
+    ("tr(K4,{pk(K1),and_v(v:pk(K2),sha256(H))})", &[2]),
+    ("tr(K4,multi_a(2,K1,K2,K3))", &[1, 3]),
+    ("tr(K4,or_i(and_v(v:pk(K1),older(10)),pk(K2)))", &[1]),
+];
+
+/// Opcode byte and pushed data of each instruction, up to the first decoding error.
+fn instructions(script: &[u8]) -> Vec<Result<(u8, Vec<u8>), ()>> {
+    Instructions::new(script)
+        .map(|instruction| match instruction {
+            Ok(Instruction::Push { opcode, data }) => Ok((opcode.0, data)),
+            Ok(Instruction::Op(opcode)) => Ok((opcode.0, vec![])),
+            Err(_) => Err(()),
+        })
+        .collect()
+}
+
+fn reference_instructions(script: &[u8]) -> Vec<Result<(u8, Vec<u8>), ()>> {
+    Script::from_bytes(script)
+        .instruction_indices()
+        .map(|instruction| match instruction {
+            Ok((position, script::Instruction::PushBytes(data))) => {
+                Ok((script[position], data.as_bytes().to_vec()))
+            }
+            Ok((_, script::Instruction::Op(opcode))) => Ok((opcode.to_u8(), vec![])),
+            Err(_) => Err(()),
+        })
+        .collect()
+}
+
+/// Compares the decoded instructions and the standard template of `script`.
+fn check_script(script: &[u8]) -> Result<(), TestCaseError> {
+    prop_assert_eq!(instructions(script), reference_instructions(script));
+
+    let reference = Script::from_bytes(script);
+    let script_type = classify(script);
+    prop_assert_eq!(
+        matches!(script_type, ScriptType::P2PKH { .. }),
+        reference.is_p2pkh()
+    );
+    prop_assert_eq!(
+        matches!(script_type, ScriptType::P2SH { .. }),
+        reference.is_p2sh()
+    );
+    prop_assert_eq!(
+        matches!(script_type, ScriptType::P2WPKH { .. }),
+        reference.is_p2wpkh()
+    );
+    prop_assert_eq!(
+        matches!(script_type, ScriptType::P2WSH { .. }),
+        reference.is_p2wsh()
+    );
+    prop_assert_eq!(
+        matches!(script_type, ScriptType::P2TR { .. }),
+        reference.is_p2tr()
+    );
+    prop_assert_eq!(
+        witness_program(script).is_some(),
+        reference.is_witness_program()
+    );
+    // Core's null data : OP_RETURN followed by push only opcodes.
+    prop_assert_eq!(
+        matches!(script_type, ScriptType::NullData { .. }),
+        reference.is_op_return() && Script::from_bytes(&script[1..]).is_push_only()
+    );
+    // rust-bitcoin does not check the keys, its templates are only a superset.
+    if let ScriptType::P2PK { .. } = script_type {
+        prop_assert!(reference.is_p2pk());
+    }
+    if let ScriptType::Multisig { .. } = script_type {
+        prop_assert!(reference.is_multisig());
+    }
+    Ok(())
+}
+
+/// Random bytes, and standard templates with a byte changed, removed or added.
+fn script_bytes() -> impl Strategy<Value = Vec<u8>> {
+    let key = prop_oneof![
+        vec(any::<u8>(), 32).prop_map(|key| [vec![0x02], key].concat()),
+        vec(any::<u8>(), 64).prop_map(|key| [vec![0x04], key].concat()),
+    ];
+    let template = prop_oneof![
+        vec(any::<u8>(), 20)
+            .prop_map(|hash| [vec![0x76, 0xa9, 0x14], hash, vec![0x88, 0xac]].concat()),
+        vec(any::<u8>(), 20).prop_map(|hash| [vec![0xa9, 0x14], hash, vec![0x87]].concat()),
+        (0u8..=16, vec(any::<u8>(), 2..=40)).prop_map(|(version, program)| {
+            let version = if version == 0 { 0 } else { 0x50 + version };
+            [vec![version, program.len() as u8], program].concat()
+        }),
+        key.clone()
+            .prop_map(|key| [vec![key.len() as u8], key, vec![0xac]].concat()),
+        (1u8..=3, vec(key, 1..=3)).prop_map(|(m, keys)| {
+            let mut script = vec![0x50 + m];
+            for key in &keys {
+                script.push(key.len() as u8);
+                script.extend_from_slice(key);
+            }
+            [script, vec![0x50 + keys.len() as u8, 0xae]].concat()
+        }),
+        vec(
+            prop_oneof![
+                Just(vec![0x00]),
+                Just(vec![0x50]),
+                Just(vec![0x51]),
+                Just(vec![0x61]),
+                Just(vec![0x4c, 0x02, 0xab, 0xcd]),
+                vec(any::<u8>(), 1..10).prop_map(|data| [vec![data.len() as u8], data].concat())
+            ],
+            0..4
+        )
+        .prop_map(|pushes| [vec![0x6a], pushes.concat()].concat()),
+    ];
+    let mutated = (template, any::<usize>(), any::<u8>(), 0..4).prop_map(
+        |(mut script, position, byte, mutation)| {
+            let position = position % script.len();
+            match mutation {
+                0 => script[position] = byte,
+                1 => {
+                    script.remove(position);
+                }
+                2 => script.insert(position, byte),
+                _ => {}
+            }
+            script
+        },
+    );
+    prop_oneof![vec(any::<u8>(), 0..64), mutated]
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Number(i64),
+    Data(Vec<u8>),
+    Op(Opcode),
+}
+
+fn token() -> impl Strategy<Value = Token> {
+    prop_oneof![
+        (-(i32::MAX as i64)..=i32::MAX as i64).prop_map(Token::Number),
+        // Data that is not a minimal script number, which Bee VM would push as a number.
+        vec(any::<u8>(), 1..600)
+            .prop_filter("script number", |data| read_scriptint(data).is_err())
+            .prop_map(Token::Data),
+        (0x61u8..=0xb9).prop_map(|opcode| Token::Op(Opcode(opcode))),
+    ]
+}
+
+/// rust-bitcoin `Script` with `script`'s OP_CODESEPARATORs removed, the script code committed to
+/// by a legacy signature.
+fn without_code_separators(script: &[u8]) -> ScriptBuf {
+    let mut result = vec![];
+    let mut end = 0;
+    for instruction in Script::from_bytes(script).instruction_indices() {
+        match instruction {
+            Ok((position, script::Instruction::Op(OP_CODESEPARATOR))) => {
+                result.extend_from_slice(&script[end..position]);
+                end = position + 1;
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    result.extend_from_slice(&script[end..]);
+    ScriptBuf::from_bytes(result)
+}
+
+fn tx_out() -> impl Strategy<Value = TxOut> {
+    (any::<u64>(), vec(any::<u8>(), 0..40)).prop_map(|(value, script_pubkey)| TxOut {
+        value,
+        script_pubkey,
+    })
+}
+
+fn transaction() -> impl Strategy<Value = Transaction> {
+    let input = (
+        any::<[u8; 32]>(),
+        any::<u32>(),
+        vec(any::<u8>(), 0..40),
+        any::<u32>(),
+        vec(vec(any::<u8>(), 0..40), 0..3),
+    )
+        .prop_map(|(txid, vout, script_sig, sequence, witness)| TxIn {
+            previous_output: OutPoint { txid, vout },
+            script_sig,
+            sequence,
+            witness,
+        });
+    (
+        any::<i32>(),
+        vec(input, 1..4),
+        vec(tx_out(), 0..4),
+        any::<u32>(),
+    )
+        .prop_map(|(version, inputs, outputs, lock_time)| Transaction {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        })
+}
+
+fn reference_tx(tx: &Transaction) -> bitcoin::Transaction {
+    deserialize(&tx.serialize()).unwrap()
+}
+
+fn reference_out(output: &TxOut) -> bitcoin::TxOut {
+    bitcoin::TxOut {
+        value: Amount::from_sat(output.value),
+        script_pubkey: ScriptBuf::from_bytes(output.script_pubkey.clone()),
+    }
+}
+
+fn secret_key(seed: u8) -> SecretKey {
+    SecretKey::from_slice(&[seed; 32]).unwrap()
+}
+
+fn descriptor(template: &str) -> Descriptor<bitcoin::PublicKey> {
+    let secp = Secp256k1::new();
+    let mut descriptor = template.to_string();
+    for seed in 1..=4 {
+        let key = bitcoin::PublicKey::new(secret_key(seed).public_key(&secp));
+        descriptor = descriptor.replace(&format!("K{}", seed), &key.to_string());
+    }
+    let hash = sha256::Hash::hash(&PREIMAGE);
+    Descriptor::from_str(&descriptor.replace('H', &hash.to_string())).unwrap()
+}
+
+/// Signs input 0 of `tx` for the satisfier of a spent descriptor, with rust-bitcoin's
+/// signature hashes.
+struct Signer<'a> {
+    tx: &'a bitcoin::Transaction,
+    prevouts: &'a [bitcoin::TxOut],
+    descriptor: &'a Descriptor<bitcoin::PublicKey>,
+    keys: Vec<SecretKey>,
+}
+
+impl Signer<'_> {
+    fn keypair(&self, key: &bitcoin::PublicKey) -> Option<Keypair> {
+        let secp = Secp256k1::new();
+        self.keys
+            .iter()
+            .map(|secret| Keypair::from_secret_key(&secp, secret))
+            .find(|keypair| keypair.x_only_public_key().0 == key.inner.x_only_public_key().0)
+    }
+
+    fn schnorr(&self, keypair: &Keypair, hash: [u8; 32]) -> bitcoin::taproot::Signature {
+        let secp = Secp256k1::new();
+        bitcoin::taproot::Signature {
+            signature: secp.sign_schnorr_no_aux_rand(&Message::from_digest(hash), keypair),
+            sighash_type: TapSighashType::Default,
+        }
+    }
+}
+
+impl Satisfier<bitcoin::PublicKey> for Signer<'_> {
+    fn lookup_ecdsa_sig(&self, key: &bitcoin::PublicKey) -> Option<bitcoin::ecdsa::Signature> {
+        let keypair = self.keypair(key)?;
+        let script_code = self.descriptor.script_code().unwrap();
+        let mut cache = SighashCache::new(self.tx);
+        let hash = if self.descriptor.desc_type().segwit_version().is_some() {
+            let value = self.prevouts[0].value;
+            cache
+                .p2wsh_signature_hash(0, &script_code, value, EcdsaSighashType::All)
+                .unwrap()
+                .to_byte_array()
+        } else {
+            cache
+                .legacy_signature_hash(0, &script_code, 0x01)
+                .unwrap()
+                .to_byte_array()
+        };
+        let signature =
+            Secp256k1::new().sign_ecdsa(&Message::from_digest(hash), &keypair.secret_key());
+        Some(bitcoin::ecdsa::Signature::sighash_all(signature))
+    }
+
+    fn lookup_tap_key_spend_sig(&self) -> Option<bitcoin::taproot::Signature> {
+        let Descriptor::Tr(tr) = self.descriptor else {
+            return None;
+        };
+        let keypair = self.keypair(tr.internal_key())?;
+        let merkle_root: Option<TapNodeHash> = tr.spend_info().merkle_root();
+        let tweaked = bitcoin::key::TapTweak::tap_tweak(keypair, &Secp256k1::new(), merkle_root);
+        let hash = SighashCache::new(self.tx)
+            .taproot_key_spend_signature_hash(
+                0,
+                &Prevouts::All(self.prevouts),
+                TapSighashType::Default,
+            )
+            .unwrap();
+        Some(self.schnorr(&tweaked.to_keypair(), hash.to_byte_array()))
+    }
+
+    fn lookup_tap_leaf_script_sig(
+        &self,
+        key: &bitcoin::PublicKey,
+        leaf_hash: &TapLeafHash,
+    ) -> Option<bitcoin::taproot::Signature> {
+        let keypair = self.keypair(key)?;
+        let hash = SighashCache::new(self.tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(self.prevouts),
+                *leaf_hash,
+                TapSighashType::Default,
+            )
+            .unwrap();
+        Some(self.schnorr(&keypair, hash.to_byte_array()))
+    }
+
+    fn lookup_sha256(&self, hash: &sha256::Hash) -> Option<Preimage32> {
+        (*hash == sha256::Hash::hash(&PREIMAGE)).then_some(PREIMAGE)
+    }
+
+    // The timelocks are left to the interpreters, which both see the spending transaction.
+    fn check_older(&self, _: bitcoin::relative::LockTime) -> bool {
+        true
+    }
+
+    fn check_after(&self, _: LockTime) -> bool {
+        true
+    }
+}
+
+/// Change made to a signed spend before it is verified.
+#[derive(Debug, Clone)]
+enum Mutation {
+    None,
+    /// Flips a bit of a non empty witness item, or of a scriptSig push for legacy spends.
+    FlipBit {
+        item: usize,
+        byte: usize,
+        bit: u8,
+    },
+    DropItem(usize),
+    ChangeOutputValue,
+}
+
+fn mutation() -> impl Strategy<Value = Mutation> {
+    prop_oneof![
+        Just(Mutation::None),
+        (any::<usize>(), any::<usize>(), 0u8..8).prop_map(|(item, byte, bit)| Mutation::FlipBit {
+            item,
+            byte,
+            bit
+        }),
+        any::<usize>().prop_map(Mutation::DropItem),
+        Just(Mutation::ChangeOutputValue),
+    ]
+}
+
+/// Transaction spending `descriptor` in its first input (version 2 so relative timelocks
+/// apply), and the outputs spent by its inputs.
+fn signed_spend(
+    descriptor: &Descriptor<bitcoin::PublicKey>,
+    signers: &[u8],
+    sequence: u32,
+    lock_time: u32,
+) -> (bitcoin::Transaction, Vec<bitcoin::TxOut>) {
+    let input = |vout| bitcoin::TxIn {
+        previous_output: bitcoin::OutPoint {
+            txid: bitcoin::Txid::from_byte_array([9; 32]),
+            vout,
+        },
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence(sequence),
+        witness: Witness::new(),
+    };
+    let mut tx = bitcoin::Transaction {
+        version: bitcoin::transaction::Version::TWO,
+        lock_time: LockTime::from_consensus(lock_time),
+        input: vec![input(0), input(1)],
+        output: vec![bitcoin::TxOut {
+            value: Amount::from_sat(140_000),
+            script_pubkey: ScriptBuf::from_bytes(vec![0x51]),
+        }],
+    };
+    let prevouts = vec![
+        bitcoin::TxOut {
+            value: Amount::from_sat(100_000),
+            script_pubkey: descriptor.script_pubkey(),
+        },
+        bitcoin::TxOut {
+            value: Amount::from_sat(50_000),
+            script_pubkey: ScriptBuf::from_bytes(vec![0x51]),
+        },
+    ];
+
+    let unsigned = tx.clone();
+    let signer = Signer {
+        tx: &unsigned,
+        prevouts: &prevouts,
+        descriptor,
+        keys: signers.iter().map(|seed| secret_key(*seed)).collect(),
+    };
+    descriptor.satisfy(&mut tx.input[0], &signer).unwrap();
+    (tx, prevouts)
+}
+
+fn mutate(tx: &mut bitcoin::Transaction, mutation: &Mutation) {
+    let input = &mut tx.input[0];
+    let legacy = input.witness.is_empty();
+    let mut items: Vec<Vec<u8>> = if legacy {
+        reference_instructions(input.script_sig.as_bytes())
+            .into_iter()
+            .map(|instruction| instruction.unwrap().1)
+            .collect()
+    } else {
+        input.witness.to_vec()
+    };
+
+    match *mutation {
+        Mutation::None => return,
+        Mutation::FlipBit { item, byte, bit } => {
+            let candidates: Vec<usize> = (0..items.len())
+                .filter(|index| !items[*index].is_empty())
+                .collect();
+            if candidates.is_empty() {
+                return;
+            }
+            let data = &mut items[candidates[item % candidates.len()]];
+            let position = byte % data.len();
+            data[position] ^= 1 << bit;
+        }
+        Mutation::DropItem(item) => {
+            if !items.is_empty() {
+                items.remove(item % items.len());
+            }
+        }
+        Mutation::ChangeOutputValue => {
+            tx.output[0].value = Amount::from_sat(139_999);
+            return;
+        }
+    }
+
+    if legacy {
+        let mut builder = Builder::new();
+        for item in items {
+            builder = builder.push_slice(PushBytesBuf::try_from(item).unwrap());
+        }
+        input.script_sig = builder.into_script();
+    } else {
+        input.witness = Witness::from_slice(&items);
+    }
+}
+
+fn reference_accepts(
+    tx: &bitcoin::Transaction,
+    input_index: usize,
+    prevouts: &[bitcoin::TxOut],
+) -> bool {
+    let input = &tx.input[input_index];
+    let Ok(interpreter) = Interpreter::from_txdata(
+        &prevouts[input_index].script_pubkey,
+        &input.script_sig,
+        &input.witness,
+        input.sequence,
+        tx.lock_time,
+    ) else {
+        return false;
+    };
+    let secp = Secp256k1::verification_only();
+    let prevouts = Prevouts::All(prevouts);
+    let mut constraints = interpreter.iter(&secp, tx, input_index, &prevouts);
+    constraints.all(|constraint| constraint.is_ok())
+}
+
+fn bee_accepts(tx: &bitcoin::Transaction, input_index: usize, prevouts: &[bitcoin::TxOut]) -> bool {
+    let tx = Transaction::deserialize(&serialize(tx)).unwrap();
+    let prevouts: Vec<TxOut> = prevouts
+        .iter()
+        .map(|output| TxOut {
+            value: output.value.to_sat(),
+            script_pubkey: output.script_pubkey.to_bytes(),
+        })
+        .collect();
+    verify_input(&tx, input_index, &prevouts).is_ok()
+}
+
+/// Consensus rules the executions are checked with, every soft fork up to taproot.
+const CONSENSUS_FLAGS: u32 =
+    bitcoinconsensus::VERIFY_ALL_PRE_TAPROOT | bitcoinconsensus::VERIFY_TAPROOT;
+
+/// Output type a script is executed from.
+#[derive(Debug, Clone, Copy)]
+enum SpendMode {
+    /// The script is the scriptPubKey, the initial stack is pushed by the scriptSig.
+    Bare,
+    P2wsh,
+    /// The script is the only leaf of a taproot tree.
+    Tapscript,
+}
+
+fn spend_mode() -> impl Strategy<Value = SpendMode> {
+    prop_oneof![
+        Just(SpendMode::Bare),
+        Just(SpendMode::P2wsh),
+        Just(SpendMode::Tapscript)
+    ]
+}
+
+/// Script instruction of the generated executions.
+#[derive(Debug, Clone)]
+enum ScriptItem {
+    Number(i64),
+    Data(Vec<u8>),
+    Op(u8),
+    /// Data named in a `.bscript` file (`<sig>`), replaced by a generated item.
+    Placeholder,
+}
+
+fn script_item() -> impl Strategy<Value = ScriptItem> {
+    prop_oneof![
+        3 => (-1i64..=16).prop_map(ScriptItem::Number),
+        1 => any::<i32>().prop_map(|n| ScriptItem::Number(n as i64)),
+        2 => stack_item().prop_map(ScriptItem::Data),
+        // Conditionals often, so that both branches and the nesting get exercised.
+        2 => prop_oneof![Just(0x63u8), Just(0x64), Just(0x67), Just(0x68)].prop_map(ScriptItem::Op),
+        6 => (0x61u8..=0xba).prop_map(ScriptItem::Op),
+        1 => any::<u8>().prop_map(ScriptItem::Op),
+    ]
+}
+
+fn stack_item() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        Just(vec![]),
+        Just(vec![0x80]),
+        Just(vec![0x01]),
+        vec(any::<u8>(), 1..5),
+        vec(any::<u8>(), 5..80),
+    ]
+}
+
+/// Serializes script items with rust-bitcoin, placeholders taking the next `data` item.
+fn build_script(items: &[ScriptItem], data: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = data.iter().cycle();
+    let mut builder = Builder::new();
+    for item in items {
+        builder = match item {
+            ScriptItem::Number(n) => builder.push_int(*n),
+            ScriptItem::Data(bytes) => {
+                builder.push_slice(PushBytesBuf::try_from(bytes.clone()).unwrap())
+            }
+            ScriptItem::Op(opcode) => builder.push_opcode((*opcode).into()),
+            ScriptItem::Placeholder => {
+                let bytes = data.next().cloned().unwrap_or_default();
+                builder.push_slice(PushBytesBuf::try_from(bytes).unwrap())
+            }
+        };
+    }
+    builder.into_bytes()
+}
+
+/// Script of a `.bscript` file, which are notes mixing opcode lines and comments.
+#[derive(Debug)]
+struct BScript {
+    location: String,
+    items: Vec<ScriptItem>,
+}
+
+/// Lines of `scripts/*.bscript` made only of opcodes, numbers and `<placeholders>`, outside
+/// comments. `OP_DROP / [OP_2DROP]` alternatives are separate scripts.
+fn bscript_scripts() -> &'static [BScript] {
+    static SCRIPTS: OnceLock<Vec<BScript>> = OnceLock::new();
+    SCRIPTS.get_or_init(|| {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("../scripts");
+        let mut paths: Vec<_> = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "bscript")
+            })
+            .collect();
+        paths.sort();
+
+        let mut scripts = vec![];
+        for path in paths {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let mut in_comment = false;
+            for (number, line) in fs::read_to_string(&path).unwrap().lines().enumerate() {
+                let line = line.trim();
+                if in_comment || line.starts_with("/*") {
+                    in_comment = !line.contains("*/");
+                    continue;
+                }
+                let code = line
+                    .split("//")
+                    .next()
+                    .unwrap()
+                    .replace(['✅', '[', ']'], "");
+                for alternative in code.split('/') {
+                    let items: Option<Vec<ScriptItem>> = alternative
+                        .split_whitespace()
+                        .map(|word| {
+                            if word.starts_with('<') && word.ends_with('>') {
+                                Some(ScriptItem::Placeholder)
+                            } else if let Ok(n) = word.parse::<i64>() {
+                                Some(ScriptItem::Number(n))
+                            } else {
+                                Opcode::from_name(word).map(|opcode| ScriptItem::Op(opcode.0))
+                            }
+                        })
+                        .collect();
+                    match items {
+                        Some(items) if !items.is_empty() => scripts.push(BScript {
+                            location: format!("{}:{}", name, number + 1),
+                            items,
+                        }),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        scripts
+    })
+}
+
+/// Transaction spending `script` from an output of `mode` in its only input, with `stack` as
+/// the initial stack, and the spent output.
+fn script_spend(
+    script: &[u8],
+    stack: &[Vec<u8>],
+    mode: SpendMode,
+    sequence: u32,
+    lock_time: u32,
+) -> (bitcoin::Transaction, Vec<bitcoin::TxOut>) {
+    let secp = Secp256k1::new();
+    let mut input = bitcoin::TxIn {
+        previous_output: bitcoin::OutPoint {
+            txid: bitcoin::Txid::from_byte_array([9; 32]),
+            vout: 0,
+        },
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence(sequence),
+        witness: Witness::new(),
+    };
+    let script = ScriptBuf::from_bytes(script.to_vec());
+    let script_pubkey = match mode {
+        SpendMode::Bare => {
+            let mut builder = Builder::new();
+            for item in stack {
+                builder = builder.push_slice(PushBytesBuf::try_from(item.clone()).unwrap());
+            }
+            input.script_sig = builder.into_script();
+            script
+        }
+        SpendMode::P2wsh => {
+            let mut witness = stack.to_vec();
+            witness.push(script.to_bytes());
+            input.witness = Witness::from_slice(&witness);
+            ScriptBuf::new_p2wsh(&script.wscript_hash())
+        }
+        SpendMode::Tapscript => {
+            let internal_key = Keypair::from_secret_key(&secp, &secret_key(3))
+                .x_only_public_key()
+                .0;
+            let spend_info = TaprootBuilder::new()
+                .add_leaf(0, script.clone())
+                .unwrap()
+                .finalize(&secp, internal_key)
+                .unwrap();
+            let control_block = spend_info
+                .control_block(&(script.clone(), LeafVersion::TapScript))
+                .unwrap();
+            let mut witness = stack.to_vec();
+            witness.push(script.to_bytes());
+            witness.push(control_block.serialize());
+            input.witness = Witness::from_slice(&witness);
+            ScriptBuf::new_p2tr_tweaked(spend_info.output_key())
+        }
+    };
+    let tx = bitcoin::Transaction {
+        version: bitcoin::transaction::Version::TWO,
+        lock_time: LockTime::from_consensus(lock_time),
+        input: vec![input],
+        output: vec![bitcoin::TxOut {
+            value: Amount::from_sat(90_000),
+            script_pubkey: ScriptBuf::from_bytes(vec![0x51]),
+        }],
+    };
+    let prevouts = vec![bitcoin::TxOut {
+        value: Amount::from_sat(100_000),
+        script_pubkey,
+    }];
+    (tx, prevouts)
+}
+
+fn consensus_accepts(tx: &bitcoin::Transaction, prevouts: &[bitcoin::TxOut]) -> bool {
+    let utxos: Vec<Utxo> = prevouts
+        .iter()
+        .map(|output| Utxo {
+            script_pubkey: output.script_pubkey.as_bytes().as_ptr(),
+            script_pubkey_len: output.script_pubkey.len() as u32,
+            value: output.value.to_sat() as i64,
+        })
+        .collect();
+    bitcoinconsensus::verify_with_flags(
+        prevouts[0].script_pubkey.as_bytes(),
+        prevouts[0].value.to_sat(),
+        &serialize(tx),
+        Some(&utxos),
+        0,
+        CONSENSUS_FLAGS,
+    )
+    .is_ok()
+}
+
+/// Main stack left by Bee VM running `script` from `stack` in `mode`, `None` when the
+/// execution fails or does not run (tapscript OP_SUCCESSx).
+fn bee_final_stack(
+    script: &[u8],
+    stack: &[Vec<u8>],
+    mode: SpendMode,
+    tx: &bitcoin::Transaction,
+    prevouts: &[bitcoin::TxOut],
+) -> Option<Vec<Vec<u8>>> {
+    let tx = Transaction::deserialize(&serialize(tx)).unwrap();
+    let prevouts: Vec<TxOut> = prevouts
+        .iter()
+        .map(|output| TxOut {
+            value: output.value.to_sat(),
+            script_pubkey: output.script_pubkey.to_bytes(),
+        })
+        .collect();
+    let context = match mode {
+        SpendMode::Bare => ExecutionContext::new(tx, 0, prevouts[0].clone(), SigVersion::Base),
+        SpendMode::P2wsh => ExecutionContext::new(tx, 0, prevouts[0].clone(), SigVersion::WitnessV0),
+        SpendMode::Tapscript => {
+            let success = Instructions::new(script)
+                .any(|instruction| matches!(instruction, Ok(Instruction::Op(opcode)) if opcode.is_success()));
+            if success {
+                return None;
+            }
+            let leaf_hash = tap_leaf_hash(TAPSCRIPT_LEAF_VERSION, script);
+            ExecutionContext::taproot(tx, 0, prevouts, Some(leaf_hash))
+        }
+    }
+    .unwrap();
+    let final_stack = run(script, Stack::from_data(stack), &context).ok()?;
+    Some(
+        final_stack
+            .items()
+            .iter()
+            .map(|item| element_to_bytes(item))
+            .collect(),
+    )
+}
+
+/// `script` followed by checks that the stack is exactly `stack` (bottom first), leaving a
+/// single true element.
+fn with_stack_checks(script: &[u8], stack: &[Vec<u8>]) -> Vec<u8> {
+    let mut builder = Builder::from(script.to_vec())
+        .push_opcode(OP_DEPTH)
+        .push_int(stack.len() as i64)
+        .push_opcode(OP_EQUALVERIFY);
+    for item in stack.iter().rev() {
+        builder = builder
+            .push_slice(PushBytesBuf::try_from(item.clone()).unwrap())
+            .push_opcode(OP_EQUALVERIFY);
+    }
+    builder.push_opcode(OP_PUSHNUM_1).into_bytes()
+}
+
+/// Runs `script` with both interpreters and compares the outcome. When Bee VM runs it to the
+/// end, the script is run again followed by checks of the stack Bee VM left, which Core must
+/// accept : both interpreters end with the same stack.
+fn check_execution(
+    script: &[u8],
+    stack: &[Vec<u8>],
+    mode: SpendMode,
+    sequence: u32,
+    lock_time: u32,
+) -> Result<(), TestCaseError> {
+    let (tx, prevouts) = script_spend(script, stack, mode, sequence, lock_time);
+    prop_assert_eq!(
+        bee_accepts(&tx, 0, &prevouts),
+        consensus_accepts(&tx, &prevouts),
+        "{:?} {} from {:?}",
+        mode,
+        hex::encode(script),
+        stack
+    );
+
+    let Some(final_stack) = bee_final_stack(script, stack, mode, &tx, &prevouts) else {
+        return Ok(());
+    };
+    let checked = with_stack_checks(script, &final_stack);
+    // The checks must not hit the script size and opcode limits themselves.
+    let operations = Instructions::new(&checked)
+        .filter(|instruction| matches!(instruction, Ok(Instruction::Op(opcode)) if *opcode > Opcode::OP_16))
+        .count();
+    if !matches!(mode, SpendMode::Tapscript) && (checked.len() > 10_000 || operations > 201) {
+        return Ok(());
+    }
+    let (tx, prevouts) = script_spend(&checked, stack, mode, sequence, lock_time);
+    prop_assert!(
+        consensus_accepts(&tx, &prevouts),
+        "{:?} {} from {:?} : Bee VM ended with {:?}",
+        mode,
+        hex::encode(script),
+        stack,
+        final_stack
+    );
+    prop_assert!(bee_accepts(&tx, 0, &prevouts));
+    Ok(())
+}
+
+#[test]
+fn bscript_files_hold_scripts() {
+    let scripts = bscript_scripts();
+    assert!(scripts.len() >= 20, "{:?}", scripts);
+    for name in ["opcodes.bscript", "p2pkh.bscript", "stack_ops.bscript"] {
+        assert!(scripts
+            .iter()
+            .any(|script| script.location.starts_with(name)));
+    }
+}
+
+#[test]
+fn decode_corpus_matches_rust_bitcoin() {
+    let fuzz = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz");
+    let seeds: Vec<_> = fs::read_dir(fuzz.join("seeds/decode"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert!(seeds.len() >= 10, "missing decode seeds");
+    // The corpus grown by the fuzzer is not committed, it is only there after a fuzzing run.
+    let corpus = fs::read_dir(fuzz.join("corpus/decode"))
+        .into_iter()
+        .flatten()
+        .map(|entry| entry.unwrap().path());
+    for path in seeds.into_iter().chain(corpus) {
+        let script = fs::read(&path).unwrap();
+        if let Err(e) = check_script(&script) {
+            panic!("{} : {}", path.display(), e);
+        }
+    }
+}
+
+// BIP143 examples : a P2PK and a P2WPKH input, then a P2SH-P2WPKH input.
+#[rstest]
+#[case(
+    "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000",
+    &[
+        ("2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac", 625_000_000),
+        ("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1", 600_000_000),
+    ]
+)]
+#[case(
+    "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000",
+    &[("a9144733f37cf4db86fbc2efed2500b4f4e49f31202387", 1_000_000_000)]
+)]
+fn test_bip143_vectors(#[case] tx_hex: &str, #[case] spent: &[(&str, u64)]) {
+    let mut tx: bitcoin::Transaction = deserialize(&hex::decode(tx_hex).unwrap()).unwrap();
+    let prevouts: Vec<bitcoin::TxOut> = spent
+        .iter()
+        .map(|(script_pubkey, value)| bitcoin::TxOut {
+            value: Amount::from_sat(*value),
+            script_pubkey: ScriptBuf::from_hex(script_pubkey).unwrap(),
+        })
+        .collect();
+    let bee_tx = Transaction::from_hex(tx_hex).unwrap();
+    assert_eq!(bee_tx.txid(), tx.compute_txid().to_byte_array());
+    assert_eq!(bee_tx.wtxid(), tx.compute_wtxid().to_byte_array());
+    assert_eq!(bee_tx.weight(), tx.weight().to_wu() as usize);
+
+    for input_index in 0..tx.input.len() {
+        assert!(reference_accepts(&tx, input_index, &prevouts));
+        assert!(bee_accepts(&tx, input_index, &prevouts));
+    }
+    tx.output[0].value -= Amount::from_sat(1);
+    for input_index in 0..tx.input.len() {
+        assert!(!reference_accepts(&tx, input_index, &prevouts));
+        assert!(!bee_accepts(&tx, input_index, &prevouts));
+    }
+}
+
+#[rstest]
+fn test_signed_spends_are_valid() {
+    for (template, signers) in SPENDS {
+        let (tx, prevouts) = signed_spend(&descriptor(template), signers, 30, 25);
+        assert!(reference_accepts(&tx, 0, &prevouts), "{}", template);
+        assert!(bee_accepts(&tx, 0, &prevouts), "{}", template);
+    }
+}
+
+proptest! {
+    #[test]
+    fn scripts_match_rust_bitcoin(script in script_bytes()) {
+        check_script(&script)?;
+    }
+
+    #[test]
+    fn encoding_matches_rust_bitcoin(tokens in vec(token(), 0..20)) {
+        let mut builder = Builder::new();
+        let mut bee_tokens = vec![];
+        for token in tokens {
+            match token {
+                Token::Number(n) => {
+                    builder = builder.push_int(n);
+                    bee_tokens.push(n.to_string());
+                }
+                Token::Data(data) => {
+                    bee_tokens.push(data_to_token(&data));
+                    builder = builder.push_slice(PushBytesBuf::try_from(data).unwrap());
+                }
+                Token::Op(opcode) => {
+                    builder = builder.push_opcode(opcode.0.into());
+                    bee_tokens.push(opcode.name().to_string());
+                }
+            }
+        }
+        prop_assert_eq!(tokens_to_bytes(&bee_tokens).unwrap(), builder.into_bytes());
+    }
+
+    #[test]
+    fn transactions_match_rust_bitcoin(tx in transaction()) {
+        let bytes = tx.serialize();
+        let reference: bitcoin::Transaction = deserialize(&bytes).unwrap();
+        prop_assert_eq!(&serialize(&reference), &bytes);
+        prop_assert_eq!(tx.txid(), reference.compute_txid().to_byte_array());
+        prop_assert_eq!(tx.wtxid(), reference.compute_wtxid().to_byte_array());
+        prop_assert_eq!(tx.size(), reference.total_size());
+        prop_assert_eq!(tx.weight(), reference.weight().to_wu() as usize);
+        prop_assert_eq!(tx.vsize(), reference.vsize());
+    }
+
+    #[test]
+    fn malformed_transactions_match_rust_bitcoin(
+        tx in transaction(),
+        position in any::<usize>(),
+        byte in any::<u8>(),
+        mutation in 0..3,
+    ) {
+        let mut bytes = tx.serialize();
+        let position = position % bytes.len();
+        match mutation {
+            0 => bytes[position] = byte,
+            1 => bytes.truncate(position),
+            _ => bytes.insert(position, byte),
+        }
+        let decoded = Transaction::deserialize(&bytes);
+        let reference = deserialize::<bitcoin::Transaction>(&bytes);
+        prop_assert_eq!(decoded.is_ok(), reference.is_ok(), "{}", hex::encode(&bytes));
+        if let (Ok(decoded), Ok(reference)) = (decoded, reference) {
+            prop_assert_eq!(decoded.serialize(), serialize(&reference));
+        }
+    }
+
+    #[test]
+    fn legacy_sighash_matches_rust_bitcoin(
+        tx in transaction(),
+        input_index in any::<usize>(),
+        script_code in vec(any::<u8>(), 0..40),
+        sighash_type in prop_oneof![Just(0x01u32), Just(0x02), Just(0x03), Just(0x81), Just(0x82), Just(0x83), any::<u32>()],
+    ) {
+        let input_index = input_index % tx.inputs.len();
+        let reference = SighashCache::new(reference_tx(&tx))
+            .legacy_signature_hash(input_index, &without_code_separators(&script_code), sighash_type)
+            .unwrap();
+        prop_assert_eq!(
+            legacy_sighash(&tx, input_index, &script_code, sighash_type).unwrap(),
+            reference.to_byte_array()
+        );
+    }
+
+    #[test]
+    fn segwit_v0_sighash_matches_rust_bitcoin(
+        tx in transaction(),
+        input_index in any::<usize>(),
+        script_code in vec(any::<u8>(), 0..40),
+        value in any::<u64>(),
+        sighash_type in prop_oneof![Just(0x01u32), Just(0x02), Just(0x03), Just(0x81), Just(0x82), Just(0x83)],
+    ) {
+        let input_index = input_index % tx.inputs.len();
+        let reference = SighashCache::new(reference_tx(&tx))
+            .p2wsh_signature_hash(
+                input_index,
+                Script::from_bytes(&script_code),
+                Amount::from_sat(value),
+                EcdsaSighashType::from_standard(sighash_type).unwrap(),
+            )
+            .unwrap();
+        prop_assert_eq!(
+            segwit_v0_sighash(&tx, input_index, &script_code, value, sighash_type).unwrap(),
+            reference.to_byte_array()
+        );
+    }
+
+    #[test]
+    fn taproot_sighash_matches_rust_bitcoin(
+        (tx, prevouts) in transaction().prop_flat_map(|tx| {
+            let inputs = tx.inputs.len();
+            (Just(tx), vec(tx_out(), inputs))
+        }),
+        input_index in any::<usize>(),
+        sighash_type in prop_oneof![Just(0x00u8), Just(0x01), Just(0x02), Just(0x03), Just(0x81), Just(0x82), Just(0x83), any::<u8>()],
+        annex in proptest::option::of(vec(any::<u8>(), 0..40).prop_map(|annex| [vec![0x50], annex].concat())),
+        leaf in proptest::option::of((any::<[u8; 32]>(), any::<u32>())),
+    ) {
+        let input_index = input_index % tx.inputs.len();
+        let leaf = leaf.map(|(leaf_hash, code_separator_position)| TapLeafData {
+            leaf_hash,
+            code_separator_position,
+        });
+        let hash = taproot_sighash(&tx, input_index, &prevouts, sighash_type, annex.as_deref(), leaf.as_ref());
+
+        let reference_prevouts: Vec<bitcoin::TxOut> = prevouts.iter().map(reference_out).collect();
+        let reference = TapSighashType::from_consensus_u8(sighash_type).ok().and_then(|sighash_type| {
+            SighashCache::new(reference_tx(&tx))
+                .taproot_signature_hash(
+                    input_index,
+                    &Prevouts::All(&reference_prevouts),
+                    annex.as_deref().map(|annex| Annex::new(annex).unwrap()),
+                    leaf.map(|leaf| (TapLeafHash::from_byte_array(leaf.leaf_hash), leaf.code_separator_position)),
+                    sighash_type,
+                )
+                .ok()
+        });
+        prop_assert_eq!(hash.ok(), reference.map(|hash| hash.to_byte_array()));
+    }
+
+    #[test]
+    fn executions_match_libbitcoinconsensus(
+        items in vec(script_item(), 0..30),
+        stack in vec(stack_item(), 0..4),
+        mode in spend_mode(),
+        sequence in prop_oneof![0u32..30, Just(0xffff_ffff)],
+        lock_time in 0u32..30,
+    ) {
+        check_execution(&build_script(&items, &[]), &stack, mode, sequence, lock_time)?;
+    }
+
+    #[test]
+    fn bscript_executions_match_libbitcoinconsensus(
+        index in any::<prop::sample::Index>(),
+        data in vec(stack_item(), 1..4),
+        stack in vec(stack_item(), 0..4),
+        mode in spend_mode(),
+    ) {
+        let script = &bscript_scripts()[index.index(bscript_scripts().len())];
+        check_execution(&build_script(&script.items, &data), &stack, mode, 10, 10)
+            .map_err(|error| TestCaseError::fail(format!("{} : {}", script.location, error)))?;
+    }
+
+    #[test]
+    fn spends_match_the_miniscript_interpreter(
+        spend in 0..SPENDS.len(),
+        sequence in prop_oneof![0u32..30, Just(0xffff_fffe), Just(0xffff_ffff), any::<u32>()],
+        lock_time in prop_oneof![0u32..30, 500_000_000u32..500_000_030],
+        mutation in mutation(),
+    ) {
+        let (template, signers) = SPENDS[spend];
+        let (mut tx, prevouts) = signed_spend(&descriptor(template), signers, sequence, lock_time);
+        mutate(&mut tx, &mutation);
+        // The miniscript interpreter only compares lock times, consensus also fails
+        // OP_CHECKLOCKTIMEVERIFY in a final input.
+        let final_input = tx.input[0].sequence == Sequence::MAX;
+        let reference = reference_accepts(&tx, 0, &prevouts) && !(template.contains("after(") && final_input);
+        prop_assert_eq!(
+            bee_accepts(&tx, 0, &prevouts),
+            reference,
+            "{} {:?}",
+            template,
+            mutation
+        );
+    }
+}
@@ -51,6 +51,42 @@ use rstest::rstest;
     vec![],
     "Mixing control flow and arithmetic"
 )]
+#[case(
+    vec!["1", "OP_NOP", "OP_NOP1", "OP_NOP4", "OP_NOP5", "OP_NOP6", "OP_NOP7", "OP_NOP8", "OP_NOP9", "OP_NOP10"],
+    vec!["1"],
+    vec![],
+    "NOPs leave the stack unchanged"
+)]
+#[case(
+    vec!["0x0080", "OP_IF", "2", "OP_ELSE", "3", "OP_ENDIF"],
+    vec!["3"],
+    vec![],
+    "Negative zero is false"
+)]
+#[case(
+    vec!["0", "OP_NOTIF", "2", "OP_ELSE", "3", "OP_ENDIF"],
+    vec!["2"],
+    vec![],
+    "Notif execution"
+)]
+#[case(
+    vec!["0", "OP_IF", "1", "OP_NOTIF", "2", "OP_ENDIF", "OP_ELSE", "3", "OP_ENDIF"],
+    vec!["3"],
+    vec![],
+    "Notif in a skipped branch"
+)]
+#[case(
+    vec!["5", "OP_IFDUP", "OP_NOTIF", "2", "OP_ENDIF"],
+    vec!["5"],
+    vec![],
+    "Ifdup before notif"
+)]
+#[case(
+    vec!["0", "OP_IF", "1", "OP_IF", "2", "OP_ELSE", "3", "OP_ENDIF", "OP_ENDIF"],
+    vec![],
+    vec![],
+    "Else nested in a skipped branch"
+)]
 fn test_execute_code_success(
     #[case] opcodes: Vec<&str>,
     #[case] expected_main: Vec<&str>,
@@ -83,7 +119,12 @@ fn test_execute_code_success(
 #[case(vec!["1", "OP_ELSE"], "Else without if")]
 #[case(vec!["1", "OP_ENDIF"], "Endif without if")]
 #[case(vec!["OP_IF", "1", "OP_ENDIF"], "If without condition")]
+#[case(vec!["1", "OP_IF", "1"], "If without endif")]
+#[case(vec!["0", "OP_NOTIF", "1", "OP_ELSE"], "Else without endif")]
+#[case(vec!["1", "OP_IF", "1", "OP_IF", "OP_ENDIF"], "Nested if without endif")]
 #[case(vec!["OP_UNKNOWN"], "Unknown opcode")]
+#[case(vec!["OP_NOTIF", "1", "OP_ENDIF"], "Notif without condition")]
+#[case(vec!["100", "OP_CHECKLOCKTIMEVERIFY"], "Lock time without a transaction")]
 fn test_execute_code_failure(#[case] opcodes: Vec<&str>, #[case] test_name: &str) {
     let opcodes = opcodes.into_iter().map(String::from).collect();
     let result = execute_code(opcodes);
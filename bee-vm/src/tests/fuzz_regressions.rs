@@ -0,0 +1,86 @@
+//! Minimized inputs of crashes found by the fuzz targets (see `fuzz/`).
+
+use crate::errors::{OpCodeErrors, SymbolicError};
+use crate::processor::input::split_tokens;
+use crate::script::analyze::analyze_tokens;
+use crate::stack::executor::execute_code_with_stack;
+use crate::stack::symbolic::{execute_symbolic, execute_symbolic_tokens};
+use crate::stack::Stack;
+use rstest::rstest;
+
+fn top(script: &str) -> Result<String, String> {
+    execute_code_with_stack(&split_tokens(script), Stack::new())
+        .map(|(stack, _)| stack.peek(0).cloned().unwrap_or_default())
+        .map_err(|error| error.to_string())
+}
+
+// Decimal tokens past i32 were unwrapped by `check_string_type`.
+#[rstest]
+#[case("99999999999")]
+#[case("1 4294967296 OP_ADD")]
+fn test_long_decimal_token(#[case] script: &str) {
+    assert_eq!(top(script), Err(OpCodeErrors::NumberNotInRange.to_string()));
+}
+
+// Arithmetic on 4 byte operands overflowed i32, results are 5 byte numbers instead.
+#[rstest]
+#[case("2147483647 1 OP_ADD", "2147483648")]
+#[case("2147483647 OP_1ADD", "2147483648")]
+#[case("-2147483647 2 OP_SUB", "-2147483649")]
+#[case("-2147483647 OP_1SUB", "-2147483648")]
+fn test_arithmetic_overflow(#[case] script: &str, #[case] expected: &str) {
+    assert_eq!(top(script), Ok(expected.to_string()));
+}
+
+// -2^31 takes 5 bytes as a script number, so it is not an operand either.
+#[rstest]
+#[case("-2147483648 OP_NEGATE")]
+#[case("-2147483648 OP_ABS")]
+fn test_smallest_i32_is_not_a_number(#[case] script: &str) {
+    assert_eq!(top(script), Err(OpCodeErrors::NumberNotInRange.to_string()));
+}
+
+// Which are not numbers for the next arithmetic opcode.
+#[rstest]
+fn test_overflow_result_is_not_a_number() {
+    assert_eq!(
+        top("2147483647 1 OP_ADD 1 OP_ADD"),
+        Err(OpCodeErrors::NumberNotInRange.to_string())
+    );
+}
+
+// Huge OP_PICK / OP_ROLL / OP_CHECKMULTISIG operands made the static analyses allocate (or
+// loop) for every item they claimed to read, the script just fails.
+#[rstest]
+#[case("2147483647 OP_PICK")]
+#[case("2147483647 OP_ROLL")]
+#[case("-1 OP_PICK")]
+#[case("1 21 OP_CHECKMULTISIG")]
+#[case("2147483647 0 OP_CHECKMULTISIG")]
+fn test_out_of_range_operand(#[case] script: &str) {
+    let analysis = analyze_tokens(&split_tokens(script), None).unwrap();
+    assert_eq!(analysis.final_items, None);
+
+    let result = execute_symbolic_tokens(&split_tokens(script)).unwrap();
+    assert_eq!((result.paths.len(), result.unsatisfiable), (0, 1));
+}
+
+// Symbolic expressions of `OP_DUP OP_ADD` chains doubled until memory ran out.
+#[rstest]
+#[case("OP_DUP OP_ADD ".repeat(64))]
+fn test_expression_growth(#[case] script: String) {
+    assert_eq!(
+        execute_symbolic_tokens(&split_tokens(&script)),
+        Err(SymbolicError::ExpressionTooLarge(1000))
+    );
+}
+
+// Fuzzer input mixing OP_IFDUP forks with growing expressions.
+#[rstest]
+fn test_forks_of_growing_expressions() {
+    let script = hex::decode("00aa00aa9a738f737373ad73769a737673ad739a738f7373738f878773a1a1a173a87373a873a8a873a8ada99a738f737373ad73769a73b173a1a1a97300ad737673ad739a738f7373738f878773a1a1a173a8737373a873a8a873a8ada973b173a1a1a97300ad73").unwrap();
+    assert_eq!(
+        execute_symbolic(&script),
+        Err(SymbolicError::ExpressionTooLarge(1000))
+    );
+}
@@ -3,3 +3,15 @@ pub mod basic_script_test;
 
 #[cfg(test)]
 mod flow_tests;
+
+#[cfg(test)]
+mod transaction_tests;
+
+#[cfg(test)]
+mod fuzz_regressions;
+
+#[cfg(test)]
+mod opcode_properties;
+
+#[cfg(test)]
+mod differential;
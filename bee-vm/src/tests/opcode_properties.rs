@@ -0,0 +1,127 @@
+//! Properties every opcode should have, checked on generated stacks.
+//!
+//! The properties run single opcodes through the executor on stacks of serialized items, so
+//! they do not depend on how the stack stores its elements.
+
+use crate::opcodes::opcode::Opcode;
+use crate::opcodes::utils::script_num_to_bytes;
+use crate::processor::input::split_tokens;
+use crate::script::decode::{instruction_to_token, Instruction};
+use crate::script::encode::element_to_bytes;
+use crate::stack::executor::execute_code_with_stack;
+use crate::stack::Stack;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// Numbers with a 4 byte script encoding (-2^31 needs a fifth byte).
+const MAX_NUM: i64 = i32::MAX as i64;
+
+fn run(script: &[String], items: &[Vec<u8>]) -> Option<Vec<Vec<u8>>> {
+    execute_code_with_stack(script, Stack::from_data(items))
+        .ok()
+        .map(|(stack, _)| data(&stack))
+}
+
+/// Stack items bottom first, like the `items` given to `Stack::from_data`.
+fn data(stack: &Stack) -> Vec<Vec<u8>> {
+    stack
+        .items()
+        .iter()
+        .map(|element| element_to_bytes(element))
+        .collect()
+}
+
+/// Token of every opcode but the data pushes, as the executor reads it (`1` for OP_1).
+fn opcodes() -> Vec<(Opcode, String)> {
+    (Opcode::OP_1NEGATE.0..=u8::MAX)
+        .map(Opcode)
+        .map(|opcode| (opcode, instruction_to_token(&Instruction::Op(opcode))))
+        .collect()
+}
+
+fn number() -> impl Strategy<Value = i64> {
+    -MAX_NUM..=MAX_NUM
+}
+
+/// Script numbers and arbitrary data, the two kinds of items opcodes meet.
+fn item() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        number().prop_map(script_num_to_bytes),
+        vec(any::<u8>(), 0..=40),
+    ]
+}
+
+#[test]
+fn opcodes_error_on_an_empty_stack() {
+    for (opcode, token) in opcodes() {
+        let pops = opcode.stack_effect().map(|effect| effect.pops);
+        if pops != Some(0) {
+            assert!(
+                run(std::slice::from_ref(&token), &[]).is_none(),
+                "{} ran on an empty stack",
+                token
+            );
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn stack_effect_matches_spec(items in vec(item(), 0..8)) {
+        for (opcode, token) in opcodes() {
+            let result = run(std::slice::from_ref(&token), &items);
+            if let Some(effect) = opcode.stack_effect() {
+                if items.len() < effect.pops {
+                    prop_assert!(result.is_none(), "{} ran on {} items", token, items.len());
+                }
+                if let Some(stack) = result {
+                    prop_assert_eq!(stack.len(), items.len() - effect.pops + effect.pushes, "{}", token);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn failing_opcodes_always_error(items in vec(item(), 0..8)) {
+        for (_, token) in opcodes().into_iter().filter(|(opcode, _)| opcode.fails()) {
+            prop_assert!(run(std::slice::from_ref(&token), &items).is_none(), "{} did not fail", token);
+        }
+    }
+
+    #[test]
+    fn swap_twice_is_identity(items in vec(item(), 2..8)) {
+        let initial = run(&[], &items);
+        prop_assert_eq!(run(&split_tokens("OP_SWAP OP_SWAP"), &items), initial.clone());
+        if items.len() >= 4 {
+            prop_assert_eq!(run(&split_tokens("OP_2SWAP OP_2SWAP"), &items), initial);
+        }
+    }
+
+    #[test]
+    fn add_commutes(a in number(), b in number()) {
+        let ab = run(&split_tokens(&format!("{} {} OP_ADD", a, b)), &[]);
+        let ba = run(&split_tokens(&format!("{} {} OP_ADD", b, a)), &[]);
+        prop_assert_eq!(ab.clone(), ba);
+        prop_assert_eq!(ab, Some(vec![script_num_to_bytes(a + b)]));
+    }
+
+    #[test]
+    fn negate_twice_is_identity(a in number()) {
+        let negated = run(&split_tokens(&format!("{} OP_NEGATE OP_NEGATE", a)), &[]);
+        prop_assert_eq!(negated, Some(vec![script_num_to_bytes(a)]));
+    }
+
+    #[test]
+    fn hash_outputs_have_fixed_length(item in vec(any::<u8>(), 0..=520)) {
+        for (token, length) in [
+            ("OP_RIPEMD160", 20),
+            ("OP_SHA1", 20),
+            ("OP_SHA256", 32),
+            ("OP_HASH160", 20),
+            ("OP_HASH256", 32),
+        ] {
+            let stack = run(&split_tokens(token), std::slice::from_ref(&item));
+            prop_assert_eq!(stack.map(|stack| stack[0].len()), Some(length), "{}", token);
+        }
+    }
+}
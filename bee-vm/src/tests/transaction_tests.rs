@@ -0,0 +1,149 @@
+use crate::processor::input::split_tokens;
+use crate::script::decode::bytes_to_tokens;
+use crate::script::encode::tokens_to_bytes;
+use crate::stack::executor::execute_code_with_context;
+use crate::stack::Stack;
+use crate::transaction::context::ExecutionContext;
+use crate::transaction::sighash::{tap_leaf_hash, SigVersion, TAPSCRIPT_LEAF_VERSION};
+use crate::transaction::{Transaction, TxOut};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::ecdsa::{Signature, SigningKey};
+use rstest::rstest;
+
+// BIP143 native P2WPKH example.
+const BIP143_TX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+fn signing_key(seed: u8) -> SigningKey {
+    SigningKey::from_slice(&[seed; 32]).unwrap()
+}
+
+fn public_key(key: &SigningKey) -> String {
+    hex::encode(key.verifying_key().to_sec1_bytes())
+}
+
+/// DER signature of the context signature hash with SIGHASH_ALL appended.
+fn sign(key: &SigningKey, context: &ExecutionContext, script: &[String]) -> Vec<u8> {
+    let script_code = tokens_to_bytes(script).unwrap();
+    let hash = context
+        .signature_hash(&script_code, 0x01, u32::MAX)
+        .unwrap();
+    let signature: Signature = key.sign_prehash(&hash).unwrap();
+    let mut signature = signature.to_der().as_bytes().to_vec();
+    signature.push(0x01);
+    signature
+}
+
+fn run(script: &[String], stack: Vec<Vec<u8>>, context: &ExecutionContext) -> Option<String> {
+    let (mut main_stack, _) =
+        execute_code_with_context(script, Stack::from_data(&stack), context).ok()?;
+    main_stack.pop_from_top()
+}
+
+#[rstest]
+fn test_bip143_p2wpkh_witness() -> color_eyre::Result<()> {
+    let tx = Transaction::from_hex(BIP143_TX)?;
+    let spent_output = TxOut {
+        value: 600_000_000,
+        script_pubkey: hex::decode("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1")?,
+    };
+    let context = ExecutionContext::new(tx, 1, spent_output, SigVersion::WitnessV0)?;
+
+    // Witness of the signed transaction, run against the P2WPKH script code.
+    let witness = vec![
+        hex::decode("304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01")?,
+        hex::decode("025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357")?,
+    ];
+    let script = bytes_to_tokens(&hex::decode(
+        "76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac",
+    )?)?;
+    assert_eq!(run(&script, witness, &context), Some("1".to_string()));
+    Ok(())
+}
+
+#[rstest]
+fn test_legacy_p2pk() -> color_eyre::Result<()> {
+    let key = signing_key(1);
+    let script = split_tokens(&format!("{} OP_CHECKSIG", public_key(&key)));
+    let spent_output = TxOut {
+        value: 0,
+        script_pubkey: tokens_to_bytes(&script)?,
+    };
+    let tx = Transaction::from_hex(BIP143_TX)?;
+    let context = ExecutionContext::new(tx.clone(), 0, spent_output.clone(), SigVersion::Base)?;
+    let signature = sign(&key, &context, &script);
+    assert_eq!(
+        run(&script, vec![signature.clone()], &context),
+        Some("1".to_string())
+    );
+
+    // The signature commits to the input index.
+    let other = ExecutionContext::new(tx, 1, spent_output, SigVersion::Base)?;
+    assert_eq!(run(&script, vec![signature], &other), Some("0".to_string()));
+    Ok(())
+}
+
+#[rstest]
+#[case(vec![0, 2], "1")]
+#[case(vec![2, 0], "0")]
+#[case(vec![1, 1], "0")]
+fn test_multisig_signature_order(
+    #[case] signers: Vec<u8>,
+    #[case] expected: &str,
+) -> color_eyre::Result<()> {
+    let keys: Vec<SigningKey> = (1..=3).map(signing_key).collect();
+    let script = split_tokens(&format!(
+        "2 {} {} {} 3 OP_CHECKMULTISIG",
+        public_key(&keys[0]),
+        public_key(&keys[1]),
+        public_key(&keys[2])
+    ));
+    let spent_output = TxOut {
+        value: 100_000,
+        script_pubkey: vec![],
+    };
+    let tx = Transaction::from_hex(BIP143_TX)?;
+    let context = ExecutionContext::new(tx, 0, spent_output, SigVersion::WitnessV0)?;
+
+    let mut stack = vec![vec![]];
+    for signer in signers {
+        stack.push(sign(&keys[signer as usize], &context, &script));
+    }
+    assert_eq!(run(&script, stack, &context), Some(expected.to_string()));
+    Ok(())
+}
+
+#[rstest]
+#[case("{key} OP_CHECKSIG", u32::MAX)]
+#[case("OP_CODESEPARATOR {key} OP_CHECKSIG", 0)]
+fn test_tapscript_checksig(
+    #[case] script: &str,
+    #[case] code_separator_position: u32,
+) -> color_eyre::Result<()> {
+    let key = k256::schnorr::SigningKey::from_bytes(&[7; 32])?;
+    let script =
+        split_tokens(&script.replace("{key}", &hex::encode(key.verifying_key().to_bytes())));
+    let leaf_hash = tap_leaf_hash(TAPSCRIPT_LEAF_VERSION, &tokens_to_bytes(&script)?);
+
+    let tx = Transaction::from_hex(BIP143_TX)?;
+    let prevouts = vec![
+        TxOut {
+            value: 1_000,
+            script_pubkey: vec![0x51],
+        };
+        2
+    ];
+    let context = ExecutionContext::taproot(tx, 1, prevouts, Some(leaf_hash))?;
+    let hash = context.signature_hash(&[], 0x00, code_separator_position)?;
+    let signature = key.sign_raw(&hash, &[0; 32])?.to_bytes().to_vec();
+    assert_eq!(
+        run(&script, vec![signature.clone()], &context),
+        Some("1".to_string())
+    );
+
+    // Failing non empty signatures abort the script in tapscript.
+    let mut invalid = signature;
+    invalid[0] ^= 1;
+    assert_eq!(run(&script, vec![invalid], &context), None);
+    assert_eq!(run(&script, vec![vec![]], &context), Some("0".to_string()));
+    Ok(())
+}
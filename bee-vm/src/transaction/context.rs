@@ -0,0 +1,252 @@
+use crate::errors::TransactionError;
+use crate::opcodes::crypto_ops::is_valid_signature_encoding;
+use crate::transaction::sigcache::signature_cache;
+use crate::transaction::sighash::{
+    legacy_sighash, segwit_v0_sighash, taproot_sighash, SigVersion, TapLeafData, SIGHASH_DEFAULT,
+};
+use crate::transaction::{Transaction, TxOut};
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+/// Everything OP_CHECKSIG needs to compute the real signature hash of an input.
+#[derive(Debug, Clone)]
+pub struct ExecutionContext {
+    pub tx: Transaction,
+    pub input_index: usize,
+    /// Output spent by the input, its amount is committed to by segwit signatures.
+    pub spent_output: TxOut,
+    /// Outputs spent by every input of `tx`, only needed for taproot.
+    pub prevouts: Vec<TxOut>,
+    pub sig_version: SigVersion,
+    pub annex: Option<Vec<u8>>,
+    /// Leaf hash of the executed tapscript.
+    pub leaf_hash: Option<[u8; 32]>,
+}
+
+impl ExecutionContext {
+    /// Context for a legacy or segwit v0 input.
+    pub fn new(
+        tx: Transaction,
+        input_index: usize,
+        spent_output: TxOut,
+        sig_version: SigVersion,
+    ) -> Result<ExecutionContext, TransactionError> {
+        if input_index >= tx.inputs.len() {
+            return Err(TransactionError::InputIndexOutOfRange(input_index));
+        }
+        Ok(ExecutionContext {
+            tx,
+            input_index,
+            spent_output,
+            prevouts: vec![],
+            sig_version,
+            annex: None,
+            leaf_hash: None,
+        })
+    }
+
+    /// Context for a taproot input, which commits to the outputs spent by all inputs.
+    pub fn taproot(
+        tx: Transaction,
+        input_index: usize,
+        prevouts: Vec<TxOut>,
+        leaf_hash: Option<[u8; 32]>,
+    ) -> Result<ExecutionContext, TransactionError> {
+        if prevouts.len() != tx.inputs.len() {
+            return Err(TransactionError::MissingPrevout(
+                prevouts.len().min(tx.inputs.len()),
+            ));
+        }
+        let spent_output = prevouts
+            .get(input_index)
+            .cloned()
+            .ok_or(TransactionError::InputIndexOutOfRange(input_index))?;
+        let sig_version = match leaf_hash {
+            Some(_) => SigVersion::Tapscript,
+            None => SigVersion::Taproot,
+        };
+        Ok(ExecutionContext {
+            tx,
+            input_index,
+            spent_output,
+            prevouts,
+            sig_version,
+            annex: None,
+            leaf_hash,
+        })
+    }
+
+    /// Signature hash for this input.
+    ///
+    /// `script_code` is used by legacy and segwit v0, `code_separator_position` by tapscript.
+    pub fn signature_hash(
+        &self,
+        script_code: &[u8],
+        sighash_type: u32,
+        code_separator_position: u32,
+    ) -> Result<[u8; 32], TransactionError> {
+        match self.sig_version {
+            SigVersion::Base => {
+                legacy_sighash(&self.tx, self.input_index, script_code, sighash_type)
+            }
+            SigVersion::WitnessV0 => segwit_v0_sighash(
+                &self.tx,
+                self.input_index,
+                script_code,
+                self.spent_output.value,
+                sighash_type,
+            ),
+            SigVersion::Taproot | SigVersion::Tapscript => {
+                let sighash_type = u8::try_from(sighash_type)
+                    .map_err(|_| TransactionError::InvalidSighashType(sighash_type))?;
+                let leaf = match self.sig_version {
+                    SigVersion::Tapscript => Some(TapLeafData {
+                        leaf_hash: self.leaf_hash.unwrap_or_default(),
+                        code_separator_position,
+                    }),
+                    _ => None,
+                };
+                taproot_sighash(
+                    &self.tx,
+                    self.input_index,
+                    &self.prevouts,
+                    sighash_type,
+                    self.annex.as_deref(),
+                    leaf.as_ref(),
+                )
+            }
+        }
+    }
+
+    /// Checks a DER signature with its sighash byte appended (legacy and segwit v0).
+    ///
+    /// An empty signature is a valid way to fail, badly encoded ones are errors (BIP66).
+    pub fn check_ecdsa_signature(
+        &self,
+        signature: &[u8],
+        public_key: &[u8],
+        script_code: &[u8],
+    ) -> Result<bool, TransactionError> {
+        let Some((sighash_type, der)) = signature.split_last() else {
+            return Ok(false);
+        };
+        if !is_valid_signature_encoding(signature) {
+            return Err(TransactionError::InvalidSignature(hex::encode(signature)));
+        }
+        let hash = self.signature_hash(script_code, *sighash_type as u32, u32::MAX)?;
+        signature_cache().check(&hash, public_key, signature, || {
+            let Ok(public_key) = k256::ecdsa::VerifyingKey::from_sec1_bytes(public_key) else {
+                return Ok(false);
+            };
+            let Ok(signature) = k256::ecdsa::Signature::from_der(der) else {
+                return Ok(false);
+            };
+            // Consensus accepts high S values, only relay policy rejects them.
+            let signature = signature.normalize_s().unwrap_or(signature);
+            Ok(public_key.verify_prehash(&hash, &signature).is_ok())
+        })
+    }
+
+    /// Checks a BIP340 signature (64 bytes, or 65 with an explicit sighash byte).
+    pub fn check_schnorr_signature(
+        &self,
+        signature: &[u8],
+        public_key: &[u8],
+        code_separator_position: u32,
+    ) -> Result<bool, TransactionError> {
+        let (signature, sighash_type) = match signature.len() {
+            64 => (signature, SIGHASH_DEFAULT),
+            // An explicit SIGHASH_DEFAULT byte is not allowed.
+            65 if signature[64] != SIGHASH_DEFAULT => (&signature[..64], signature[64]),
+            _ => return Err(TransactionError::InvalidSignature(hex::encode(signature))),
+        };
+        let hash = self.signature_hash(&[], sighash_type as u32, code_separator_position)?;
+        signature_cache().check(&hash, public_key, signature, || {
+            let Ok(public_key) = k256::schnorr::VerifyingKey::from_bytes(public_key) else {
+                return Ok(false);
+            };
+            let Ok(signature) = k256::schnorr::Signature::try_from(signature) else {
+                return Ok(false);
+            };
+            Ok(public_key.verify_raw(&hash, &signature).is_ok())
+        })
+    }
+
+    /// Checks a taproot key path spend, the single witness element signs for the output key.
+    pub fn check_taproot_key_path(&self, signature: &[u8]) -> Result<bool, TransactionError> {
+        let output_key = match self.spent_output.script_pubkey.as_slice() {
+            [0x51, 0x20, key @ ..] if key.len() == 32 => key,
+            _ => return Ok(false),
+        };
+        self.check_schnorr_signature(signature, output_key, u32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod test_context {
+    use crate::transaction::context::ExecutionContext;
+    use crate::transaction::sigcache::signature_cache;
+    use crate::transaction::sighash::SigVersion;
+    use crate::transaction::{Transaction, TxOut};
+    use rstest::rstest;
+
+    // BIP143 native P2WPKH example, signature of the second input.
+    const BIP143_TX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+    const SIGNATURE: &str = "304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01";
+    const PUBLIC_KEY: &str = "025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357";
+    const SCRIPT_CODE: &str = "76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac";
+
+    fn context() -> ExecutionContext {
+        let spent_output = TxOut {
+            value: 600_000_000,
+            script_pubkey: hex::decode("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap(),
+        };
+        let tx = Transaction::from_hex(BIP143_TX).unwrap();
+        ExecutionContext::new(tx, 1, spent_output, SigVersion::WitnessV0).unwrap()
+    }
+
+    #[rstest]
+    fn test_bip143_signature() -> color_eyre::Result<()> {
+        let ctx = context();
+        let signature = hex::decode(SIGNATURE)?;
+        let public_key = hex::decode(PUBLIC_KEY)?;
+        let script_code = hex::decode(SCRIPT_CODE)?;
+        assert!(ctx.check_ecdsa_signature(&signature, &public_key, &script_code)?);
+
+        // Same signature with another amount.
+        let mut other = context();
+        other.spent_output.value += 1;
+        assert!(!other.check_ecdsa_signature(&signature, &public_key, &script_code)?);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_valid_signatures_are_cached() -> color_eyre::Result<()> {
+        let ctx = context();
+        let signature = hex::decode(SIGNATURE)?;
+        let public_key = hex::decode(PUBLIC_KEY)?;
+        let script_code = hex::decode(SCRIPT_CODE)?;
+        let hash = ctx.signature_hash(&script_code, 0x01, u32::MAX)?;
+        assert!(ctx.check_ecdsa_signature(&signature, &public_key, &script_code)?);
+        assert!(signature_cache().contains(&hash, &public_key, &signature));
+
+        // The failing check of another amount is not recorded.
+        let mut other = context();
+        other.spent_output.value += 1;
+        let other_hash = other.signature_hash(&script_code, 0x01, u32::MAX)?;
+        assert!(!other.check_ecdsa_signature(&signature, &public_key, &script_code)?);
+        assert!(!signature_cache().contains(&other_hash, &public_key, &signature));
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_invalid_encodings() -> color_eyre::Result<()> {
+        let ctx = context();
+        let public_key = hex::decode(PUBLIC_KEY)?;
+        let script_code = hex::decode(SCRIPT_CODE)?;
+        assert!(!ctx.check_ecdsa_signature(&[], &public_key, &script_code)?);
+        assert!(ctx
+            .check_ecdsa_signature(&[0x30, 0x01], &public_key, &script_code)
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use crate::errors::TransactionError;
+
+/// Appends a Bitcoin compact size (var int).
+pub fn write_compact_size(buffer: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0xfc => buffer.push(value as u8),
+        0xfd..=0xffff => {
+            buffer.push(0xfd);
+            buffer.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            buffer.push(0xfe);
+            buffer.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        _ => {
+            buffer.push(0xff);
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// Appends a compact size prefixed byte string.
+pub fn write_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    write_compact_size(buffer, bytes.len() as u64);
+    buffer.extend_from_slice(bytes);
+}
+
+/// Cursor over consensus encoded bytes.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pub position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, position: 0 }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    pub fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    pub fn read_slice(&mut self, length: usize) -> Result<&'a [u8], TransactionError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(TransactionError::Truncated(self.bytes.len()))?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], TransactionError> {
+        Ok(self.read_slice(N)?.try_into().unwrap())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, TransactionError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, TransactionError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, TransactionError> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    /// Reads a compact size, rejecting non minimal encodings like Core does.
+    pub fn read_compact_size(&mut self) -> Result<u64, TransactionError> {
+        let start = self.position;
+        let (value, minimum) = match self.read_u8()? {
+            0xfd => (u16::from_le_bytes(self.read_array()?) as u64, 0xfd),
+            0xfe => (u32::from_le_bytes(self.read_array()?) as u64, 0x1_0000),
+            0xff => (self.read_u64()?, 0x1_0000_0000),
+            byte => return Ok(byte as u64),
+        };
+        if value < minimum {
+            return Err(TransactionError::NonCanonicalCompactSize(start));
+        }
+        Ok(value)
+    }
+
+    /// Reads a compact size prefixed byte string.
+    pub fn read_bytes(&mut self) -> Result<Vec<u8>, TransactionError> {
+        let length = self.read_compact_size()?;
+        if length > self.remaining() as u64 {
+            return Err(TransactionError::Truncated(self.bytes.len()));
+        }
+        Ok(self.read_slice(length as usize)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test_encode {
+    use crate::errors::TransactionError;
+    use crate::transaction::encode::{write_compact_size, Reader};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, "00")]
+    #[case(0xfc, "fc")]
+    #[case(0xfd, "fdfd00")]
+    #[case(0x1_0000, "fe00000100")]
+    #[case(0x1_0000_0000, "ff0000000001000000")]
+    fn test_compact_size(#[case] value: u64, #[case] encoded: &str) {
+        let mut buffer = vec![];
+        write_compact_size(&mut buffer, value);
+        assert_eq!(hex::encode(&buffer), encoded);
+        assert_eq!(Reader::new(&buffer).read_compact_size(), Ok(value));
+    }
+
+    #[rstest]
+    fn test_non_canonical_compact_size() {
+        let bytes = hex::decode("fd1000").unwrap();
+        assert_eq!(
+            Reader::new(&bytes).read_compact_size(),
+            Err(TransactionError::NonCanonicalCompactSize(0))
+        );
+    }
+}
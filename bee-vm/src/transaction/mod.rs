@@ -0,0 +1,350 @@
+// ==================================================
+// Transactions (consensus serialization, sighash, execution context, verification)
+// ==================================================
+//
+// Bitcoin Core serializes transactions in two formats :
+// - legacy : version | inputs | outputs | locktime
+// - segwit (BIP144) : version | 0x00 0x01 | inputs | outputs | witnesses | locktime
+//
+// The segwit format is only used when at least one input has a witness.
+//
+// `verify::verify_input` runs scriptSig, scriptPubKey, P2SH redeem scripts and witness
+// programs (v0 and taproot) of an input through the executor, like Core's `VerifyScript`.
+// `verify::verify_transaction` checks the inputs in parallel, valid signatures are kept in
+// the shared `sigcache::signature_cache`.
+
+pub mod context;
+pub mod encode;
+pub mod sigcache;
+pub mod sighash;
+pub mod verify;
+
+use crate::errors::TransactionError;
+use crate::transaction::encode::{write_bytes, write_compact_size, Reader};
+use sha2::{Digest, Sha256};
+
+/// Sequence of an input that opts out of relative locktime and replace-by-fee.
+pub const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+
+/// Witness data is discounted by this factor in the transaction weight (BIP141).
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+/// Reference to an output of a previous transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OutPoint {
+    /// Txid in internal byte order (reversed compared to the usual hex display).
+    pub txid: [u8; 32],
+    pub vout: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxIn {
+    pub previous_output: OutPoint,
+    pub script_sig: Vec<u8>,
+    pub sequence: u32,
+    pub witness: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxOut {
+    /// Amount in satoshis.
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub version: i32,
+    pub inputs: Vec<TxIn>,
+    pub outputs: Vec<TxOut>,
+    pub lock_time: u32,
+}
+
+impl OutPoint {
+    /// Builds an outpoint from a txid as displayed by explorers / RPC (byte reversed hex).
+    pub fn from_display_txid(txid: &str, vout: u32) -> Result<OutPoint, TransactionError> {
+        let mut bytes: [u8; 32] = hex::decode(txid)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(TransactionError::InvalidHex(txid.to_string()))?;
+        bytes.reverse();
+        Ok(OutPoint { txid: bytes, vout })
+    }
+
+    pub fn display_txid(&self) -> String {
+        display_hash(&self.txid)
+    }
+
+    pub fn serialize(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.txid);
+        buffer.extend_from_slice(&self.vout.to_le_bytes());
+    }
+}
+
+impl TxIn {
+    /// An input spending `previous_output` with an empty scriptSig and witness.
+    pub fn new(previous_output: OutPoint, sequence: u32) -> TxIn {
+        TxIn {
+            previous_output,
+            script_sig: vec![],
+            sequence,
+            witness: vec![],
+        }
+    }
+}
+
+impl TxOut {
+    pub fn serialize(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.value.to_le_bytes());
+        write_bytes(buffer, &self.script_pubkey);
+    }
+}
+
+impl Transaction {
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    /// Consensus serialization, in the segwit format when any input has a witness.
+    pub fn serialize(&self) -> Vec<u8> {
+        self.serialize_with_witness(self.has_witness())
+    }
+
+    /// Serialization without witnesses, the one hashed into the txid.
+    pub fn serialize_legacy(&self) -> Vec<u8> {
+        self.serialize_with_witness(false)
+    }
+
+    fn serialize_with_witness(&self, with_witness: bool) -> Vec<u8> {
+        let mut buffer = vec![];
+        buffer.extend_from_slice(&self.version.to_le_bytes());
+        if with_witness {
+            buffer.extend_from_slice(&[0x00, 0x01]);
+        }
+
+        write_compact_size(&mut buffer, self.inputs.len() as u64);
+        for input in &self.inputs {
+            input.previous_output.serialize(&mut buffer);
+            write_bytes(&mut buffer, &input.script_sig);
+            buffer.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+
+        write_compact_size(&mut buffer, self.outputs.len() as u64);
+        for output in &self.outputs {
+            output.serialize(&mut buffer);
+        }
+
+        if with_witness {
+            for input in &self.inputs {
+                write_compact_size(&mut buffer, input.witness.len() as u64);
+                for item in &input.witness {
+                    write_bytes(&mut buffer, item);
+                }
+            }
+        }
+
+        buffer.extend_from_slice(&self.lock_time.to_le_bytes());
+        buffer
+    }
+
+    /// Parses a transaction in either format, the whole input must be consumed.
+    pub fn deserialize(bytes: &[u8]) -> Result<Transaction, TransactionError> {
+        let mut reader = Reader::new(bytes);
+        let version = reader.read_u32()? as i32;
+
+        // A zero input count followed by 0x01 is the segwit marker and flag.
+        let mut with_witness = false;
+        if reader.peek() == Some(0x00) {
+            reader.read_u8()?;
+            match reader.read_u8()? {
+                0x01 => with_witness = true,
+                flag => return Err(TransactionError::InvalidSegwitFlag(flag)),
+            }
+        }
+
+        let input_count = reader.read_compact_size()?;
+        let mut inputs = Vec::with_capacity(input_count.min(reader.remaining() as u64) as usize);
+        for _ in 0..input_count {
+            let txid = reader.read_array::<32>()?;
+            let vout = reader.read_u32()?;
+            let script_sig = reader.read_bytes()?;
+            let sequence = reader.read_u32()?;
+            inputs.push(TxIn {
+                previous_output: OutPoint { txid, vout },
+                script_sig,
+                sequence,
+                witness: vec![],
+            });
+        }
+
+        let output_count = reader.read_compact_size()?;
+        let mut outputs = Vec::with_capacity(output_count.min(reader.remaining() as u64) as usize);
+        for _ in 0..output_count {
+            let value = reader.read_u64()?;
+            let script_pubkey = reader.read_bytes()?;
+            outputs.push(TxOut {
+                value,
+                script_pubkey,
+            });
+        }
+
+        if with_witness {
+            for input in inputs.iter_mut() {
+                let items = reader.read_compact_size()?;
+                for _ in 0..items {
+                    input.witness.push(reader.read_bytes()?);
+                }
+            }
+            // Like Core, the segwit serialization is only valid for a transaction with witnesses.
+            if inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(TransactionError::SuperfluousWitness);
+            }
+        }
+
+        let lock_time = reader.read_u32()?;
+        if reader.remaining() != 0 {
+            return Err(TransactionError::TrailingBytes(reader.remaining()));
+        }
+
+        Ok(Transaction {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        })
+    }
+
+    pub fn from_hex(tx_hex: &str) -> Result<Transaction, TransactionError> {
+        let bytes =
+            hex::decode(tx_hex.trim()).map_err(|e| TransactionError::InvalidHex(e.to_string()))?;
+        Transaction::deserialize(&bytes)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.serialize())
+    }
+
+    /// Size in bytes of the full serialization.
+    pub fn size(&self) -> usize {
+        self.serialize().len()
+    }
+
+    /// BIP141 weight : base size * 3 + total size.
+    pub fn weight(&self) -> usize {
+        self.serialize_legacy().len() * (WITNESS_SCALE_FACTOR - 1) + self.size()
+    }
+
+    /// Virtual size, the weight in vbytes rounded up.
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    /// Double sha256 of the legacy serialization (internal byte order).
+    pub fn txid(&self) -> [u8; 32] {
+        sha256d(&self.serialize_legacy())
+    }
+
+    /// Double sha256 of the full serialization, equal to the txid without witnesses.
+    pub fn wtxid(&self) -> [u8; 32] {
+        sha256d(&self.serialize())
+    }
+}
+
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// Hex of a hash in the reversed order used to display txids and block hashes.
+pub fn display_hash(hash: &[u8; 32]) -> String {
+    let mut reversed = *hash;
+    reversed.reverse();
+    hex::encode(reversed)
+}
+
+#[cfg(test)]
+mod test_transaction {
+    use crate::errors::TransactionError;
+    use crate::transaction::{display_hash, Transaction};
+    use rstest::rstest;
+
+    // BIP143 native P2WPKH example, unsigned and signed.
+    pub const UNSIGNED: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+    const SIGNED: &str = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+
+    #[rstest]
+    #[case(UNSIGNED, false)]
+    #[case(SIGNED, true)]
+    fn test_round_trip(#[case] tx_hex: &str, #[case] has_witness: bool) -> color_eyre::Result<()> {
+        let tx = Transaction::from_hex(tx_hex)?;
+        assert_eq!(tx.inputs.len(), 2);
+        assert_eq!(tx.outputs.len(), 2);
+        assert_eq!(tx.lock_time, 17);
+        assert_eq!(tx.has_witness(), has_witness);
+        assert_eq!(tx.to_hex(), tx_hex);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_txid_and_wtxid() -> color_eyre::Result<()> {
+        let signed = Transaction::from_hex(SIGNED)?;
+        let unsigned = Transaction::from_hex(UNSIGNED)?;
+        assert_ne!(signed.txid(), unsigned.txid());
+        assert_ne!(signed.txid(), signed.wtxid());
+        assert_eq!(unsigned.txid(), unsigned.wtxid());
+        assert_eq!(
+            display_hash(&signed.txid()),
+            "e8151a2af31c368a35053ddd4bdb285a8595c769a3ad83e0fa02314a602d4609"
+        );
+        assert_eq!(
+            display_hash(&signed.wtxid()),
+            "c36c38370907df2324d9ce9d149d191192f338b37665a82e78e76a12c909b762"
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(UNSIGNED, 160, 640, 160)]
+    // Weight computed with rust-bitcoin.
+    #[case(SIGNED, 343, 1042, 261)]
+    fn test_size_and_weight(
+        #[case] tx_hex: &str,
+        #[case] size: usize,
+        #[case] weight: usize,
+        #[case] vsize: usize,
+    ) -> color_eyre::Result<()> {
+        let tx = Transaction::from_hex(tx_hex)?;
+        assert_eq!(tx.size(), size);
+        assert_eq!(tx.weight(), weight);
+        assert_eq!(tx.vsize(), vsize);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(&UNSIGNED[..UNSIGNED.len() - 2])]
+    #[case("01000000000201")]
+    fn test_invalid_transactions(#[case] tx_hex: &str) {
+        assert!(Transaction::from_hex(tx_hex).is_err());
+    }
+
+    #[rstest]
+    fn test_trailing_bytes() {
+        assert_eq!(
+            Transaction::from_hex(&format!("{}00", UNSIGNED)),
+            Err(TransactionError::TrailingBytes(1))
+        );
+    }
+
+    #[rstest]
+    fn test_superfluous_witness() {
+        // UNSIGNED with the segwit marker and flag and two empty witnesses.
+        let tx_hex = format!(
+            "010000000001{}0000{}",
+            &UNSIGNED[8..UNSIGNED.len() - 8],
+            &UNSIGNED[UNSIGNED.len() - 8..]
+        );
+        assert_eq!(
+            Transaction::from_hex(&tx_hex),
+            Err(TransactionError::SuperfluousWitness)
+        );
+    }
+}
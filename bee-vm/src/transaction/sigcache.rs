@@ -0,0 +1,147 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::{OnceLock, RwLock};
+
+/// Entries kept by the shared cache, about 2 MB of keys.
+pub const DEFAULT_CACHE_ENTRIES: usize = 65_536;
+
+/// Signatures already found valid, like Bitcoin Core's signature cache.
+///
+/// Entries are keyed on the signature hash, the public key and the signature, so a hit is
+/// exactly the verification that succeeded before. Only valid signatures are stored : a miss
+/// just means verifying again, which keeps results independent of the cache content.
+#[derive(Debug)]
+pub struct SignatureCache {
+    entries: RwLock<HashSet<[u8; 32]>>,
+    max_entries: usize,
+}
+
+impl SignatureCache {
+    pub fn new(max_entries: usize) -> SignatureCache {
+        SignatureCache {
+            entries: RwLock::new(HashSet::new()),
+            max_entries,
+        }
+    }
+
+    /// Returns true when the signature of `sighash` by `public_key` was verified before.
+    pub fn contains(&self, sighash: &[u8; 32], public_key: &[u8], signature: &[u8]) -> bool {
+        let key = entry_key(sighash, public_key, signature);
+        self.entries.read().unwrap().contains(&key)
+    }
+
+    /// Records a valid signature. Once the cache is full it is emptied before the insertion.
+    pub fn insert(&self, sighash: &[u8; 32], public_key: &[u8], signature: &[u8]) {
+        let key = entry_key(sighash, public_key, signature);
+        let mut entries = self.entries.write().unwrap();
+        if entries.len() >= self.max_entries {
+            entries.clear();
+        }
+        entries.insert(key);
+    }
+
+    /// Looks `signature` up, running `verify` and recording its result when it is a miss.
+    pub fn check<E>(
+        &self,
+        sighash: &[u8; 32],
+        public_key: &[u8],
+        signature: &[u8],
+        verify: impl FnOnce() -> Result<bool, E>,
+    ) -> Result<bool, E> {
+        if self.contains(sighash, public_key, signature) {
+            return Ok(true);
+        }
+        let valid = verify()?;
+        if valid {
+            self.insert(sighash, public_key, signature);
+        }
+        Ok(valid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+}
+
+/// Cache shared by every signature check of the process, and so by the inputs verified in
+/// parallel by `verify_transaction`.
+pub fn signature_cache() -> &'static SignatureCache {
+    static CACHE: OnceLock<SignatureCache> = OnceLock::new();
+    CACHE.get_or_init(|| SignatureCache::new(DEFAULT_CACHE_ENTRIES))
+}
+
+/// Fixed size key of an entry, the public key length keeps the key / signature split unique.
+/// It is written on 8 bytes : tapscript passes public keys of any length to the checker.
+fn entry_key(sighash: &[u8; 32], public_key: &[u8], signature: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(sighash);
+    hasher.update((public_key.len() as u64).to_le_bytes());
+    hasher.update(public_key);
+    hasher.update(signature);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod test_sigcache {
+    use crate::transaction::sigcache::SignatureCache;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_contains_only_inserted_entries() {
+        let cache = SignatureCache::new(10);
+        cache.insert(&[1; 32], &[2; 33], &[3; 71]);
+        assert!(cache.contains(&[1; 32], &[2; 33], &[3; 71]));
+        assert!(!cache.contains(&[0; 32], &[2; 33], &[3; 71]));
+        assert!(!cache.contains(&[1; 32], &[0; 33], &[3; 71]));
+        assert!(!cache.contains(&[1; 32], &[2; 33], &[0; 71]));
+        // The same bytes split differently between the key and the signature.
+        assert!(!cache.contains(&[1; 32], &[2; 32], &[2, 3, 3, 3]));
+    }
+
+    #[rstest]
+    fn test_long_public_keys_keep_the_split_unique() {
+        // With a one byte length, 256 and 0 would give the same prefix to these two splits.
+        let bytes = [7; 300];
+        let cache = SignatureCache::new(10);
+        cache.insert(&[1; 32], &bytes[..256], &bytes[256..]);
+        assert!(cache.contains(&[1; 32], &bytes[..256], &bytes[256..]));
+        assert!(!cache.contains(&[1; 32], &[], &bytes));
+    }
+
+    #[rstest]
+    fn test_full_cache_is_emptied() {
+        let cache = SignatureCache::new(2);
+        for i in 0..3 {
+            cache.insert(&[i; 32], &[], &[]);
+        }
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains(&[2; 32], &[], &[]));
+    }
+
+    #[rstest]
+    #[case(Ok(true), 1)]
+    #[case(Ok(false), 0)]
+    #[case(Err(()), 0)]
+    fn test_check_records_valid_signatures(
+        #[case] result: Result<bool, ()>,
+        #[case] entries: usize,
+    ) {
+        let cache = SignatureCache::new(10);
+        assert_eq!(cache.check(&[1; 32], &[2], &[3], || result), result);
+        assert_eq!(cache.len(), entries);
+
+        // A hit does not verify again.
+        if entries == 1 {
+            let hit: Result<bool, ()> = cache.check(&[1; 32], &[2], &[3], || unreachable!());
+            assert_eq!(hit, Ok(true));
+        }
+    }
+}
@@ -0,0 +1,444 @@
+use crate::errors::TransactionError;
+use crate::opcodes::crypto_ops::{SIGHASH_ANYONECANPAY, SIGHASH_NONE, SIGHASH_SINGLE};
+use crate::opcodes::opcode::Opcode;
+use crate::script::decode::{Instruction, Instructions};
+use crate::transaction::encode::{write_bytes, write_compact_size};
+use crate::transaction::{sha256d, Transaction, TxOut};
+use sha2::{Digest, Sha256};
+
+/// BIP341 `SIGHASH_DEFAULT`, only valid for taproot signatures (64 bytes without hashtype).
+pub const SIGHASH_DEFAULT: u8 = 0x00;
+
+/// Leaf version of tapscript (BIP342).
+pub const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+
+/// Signature hash algorithm used for the script being executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigVersion {
+    /// Pre-segwit scripts (scriptSig / scriptPubKey / P2SH redeem script).
+    Base,
+    /// BIP143, P2WPKH and P2WSH.
+    WitnessV0,
+    /// BIP341 key path spend.
+    Taproot,
+    /// BIP342 script path spend.
+    Tapscript,
+}
+
+/// Hash committed to by an out of range legacy SIGHASH_SINGLE (the famous "one" bug).
+const SIGHASH_ONE: [u8; 32] = {
+    let mut one = [0u8; 32];
+    one[0] = 1;
+    one
+};
+
+/// Removes every OP_CODESEPARATOR from serialized script bytes, keeping pushes untouched.
+fn remove_code_separators(script: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(script.len());
+    let mut instructions = Instructions::new(script);
+    loop {
+        let start = instructions.position();
+        match instructions.next() {
+            Some(Ok(Instruction::Op(Opcode::OP_CODESEPARATOR))) => {}
+            Some(Ok(_)) => result.extend_from_slice(&script[start..instructions.position()]),
+            // Keep the undecodable tail as it is.
+            Some(Err(_)) => {
+                result.extend_from_slice(&script[start..]);
+                break;
+            }
+            None => break,
+        }
+    }
+    result
+}
+
+/// Original (pre-segwit) signature hash, computed on a modified copy of the transaction.
+pub fn legacy_sighash(
+    tx: &Transaction,
+    input_index: usize,
+    script_code: &[u8],
+    sighash_type: u32,
+) -> Result<[u8; 32], TransactionError> {
+    let base_type = (sighash_type & 0x1f) as u8;
+    let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY as u32 != 0;
+
+    // Consensus keeps these two cases signing the constant 1 instead of failing.
+    if input_index >= tx.inputs.len()
+        || (base_type == SIGHASH_SINGLE && input_index >= tx.outputs.len())
+    {
+        return Ok(SIGHASH_ONE);
+    }
+
+    let script_code = remove_code_separators(script_code);
+    let mut buffer = vec![];
+    buffer.extend_from_slice(&tx.version.to_le_bytes());
+
+    let signed_inputs: Vec<usize> = if anyone_can_pay {
+        vec![input_index]
+    } else {
+        (0..tx.inputs.len()).collect()
+    };
+    write_compact_size(&mut buffer, signed_inputs.len() as u64);
+    for index in signed_inputs {
+        let input = &tx.inputs[index];
+        input.previous_output.serialize(&mut buffer);
+        if index == input_index {
+            write_bytes(&mut buffer, &script_code);
+            buffer.extend_from_slice(&input.sequence.to_le_bytes());
+        } else {
+            write_bytes(&mut buffer, &[]);
+            // Other inputs can be replaced freely with NONE and SINGLE.
+            let sequence = match base_type {
+                SIGHASH_NONE | SIGHASH_SINGLE => 0,
+                _ => input.sequence,
+            };
+            buffer.extend_from_slice(&sequence.to_le_bytes());
+        }
+    }
+
+    match base_type {
+        SIGHASH_NONE => write_compact_size(&mut buffer, 0),
+        SIGHASH_SINGLE => {
+            write_compact_size(&mut buffer, input_index as u64 + 1);
+            for _ in 0..input_index {
+                // Blanked output : value -1 and an empty script.
+                buffer.extend_from_slice(&u64::MAX.to_le_bytes());
+                write_bytes(&mut buffer, &[]);
+            }
+            tx.outputs[input_index].serialize(&mut buffer);
+        }
+        _ => {
+            write_compact_size(&mut buffer, tx.outputs.len() as u64);
+            for output in &tx.outputs {
+                output.serialize(&mut buffer);
+            }
+        }
+    }
+
+    buffer.extend_from_slice(&tx.lock_time.to_le_bytes());
+    buffer.extend_from_slice(&sighash_type.to_le_bytes());
+    Ok(sha256d(&buffer))
+}
+
+/// BIP143 signature hash for segwit v0 inputs, committing to the spent amount.
+pub fn segwit_v0_sighash(
+    tx: &Transaction,
+    input_index: usize,
+    script_code: &[u8],
+    value: u64,
+    sighash_type: u32,
+) -> Result<[u8; 32], TransactionError> {
+    let input = tx
+        .inputs
+        .get(input_index)
+        .ok_or(TransactionError::InputIndexOutOfRange(input_index))?;
+    let base_type = (sighash_type & 0x1f) as u8;
+    let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY as u32 != 0;
+
+    let mut hash_prevouts = [0u8; 32];
+    let mut hash_sequence = [0u8; 32];
+    let mut hash_outputs = [0u8; 32];
+
+    if !anyone_can_pay {
+        let mut prevouts = vec![];
+        for input in &tx.inputs {
+            input.previous_output.serialize(&mut prevouts);
+        }
+        hash_prevouts = sha256d(&prevouts);
+
+        if base_type != SIGHASH_SINGLE && base_type != SIGHASH_NONE {
+            let sequences: Vec<u8> = tx
+                .inputs
+                .iter()
+                .flat_map(|input| input.sequence.to_le_bytes())
+                .collect();
+            hash_sequence = sha256d(&sequences);
+        }
+    }
+
+    if base_type != SIGHASH_SINGLE && base_type != SIGHASH_NONE {
+        let mut outputs = vec![];
+        for output in &tx.outputs {
+            output.serialize(&mut outputs);
+        }
+        hash_outputs = sha256d(&outputs);
+    } else if base_type == SIGHASH_SINGLE && input_index < tx.outputs.len() {
+        let mut output = vec![];
+        tx.outputs[input_index].serialize(&mut output);
+        hash_outputs = sha256d(&output);
+    }
+
+    let mut buffer = vec![];
+    buffer.extend_from_slice(&tx.version.to_le_bytes());
+    buffer.extend_from_slice(&hash_prevouts);
+    buffer.extend_from_slice(&hash_sequence);
+    input.previous_output.serialize(&mut buffer);
+    write_bytes(&mut buffer, script_code);
+    buffer.extend_from_slice(&value.to_le_bytes());
+    buffer.extend_from_slice(&input.sequence.to_le_bytes());
+    buffer.extend_from_slice(&hash_outputs);
+    buffer.extend_from_slice(&tx.lock_time.to_le_bytes());
+    buffer.extend_from_slice(&sighash_type.to_le_bytes());
+    Ok(sha256d(&buffer))
+}
+
+/// Script path data committed to by a tapscript signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapLeafData {
+    pub leaf_hash: [u8; 32],
+    /// Opcode position of the last executed OP_CODESEPARATOR, `u32::MAX` when there is none.
+    pub code_separator_position: u32,
+}
+
+/// BIP341 signature hash. `prevouts` are the outputs spent by every input of `tx`.
+pub fn taproot_sighash(
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[TxOut],
+    sighash_type: u8,
+    annex: Option<&[u8]>,
+    leaf: Option<&TapLeafData>,
+) -> Result<[u8; 32], TransactionError> {
+    if !matches!(sighash_type, 0x00..=0x03 | 0x81..=0x83) {
+        return Err(TransactionError::InvalidSighashType(sighash_type as u32));
+    }
+    let input = tx
+        .inputs
+        .get(input_index)
+        .ok_or(TransactionError::InputIndexOutOfRange(input_index))?;
+    if prevouts.len() != tx.inputs.len() {
+        return Err(TransactionError::MissingPrevout(
+            prevouts.len().min(tx.inputs.len()),
+        ));
+    }
+
+    let output_type = match sighash_type {
+        SIGHASH_DEFAULT => 0x01,
+        _ => sighash_type & 0x03,
+    };
+    let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+    if output_type == SIGHASH_SINGLE && input_index >= tx.outputs.len() {
+        return Err(TransactionError::SingleWithoutOutput(input_index));
+    }
+
+    // Epoch 0, then the BIP341 SigMsg.
+    let mut buffer = vec![0x00, sighash_type];
+    buffer.extend_from_slice(&tx.version.to_le_bytes());
+    buffer.extend_from_slice(&tx.lock_time.to_le_bytes());
+
+    if !anyone_can_pay {
+        let mut outpoints = vec![];
+        let mut amounts = vec![];
+        let mut script_pubkeys = vec![];
+        let mut sequences = vec![];
+        for (input, prevout) in tx.inputs.iter().zip(prevouts) {
+            input.previous_output.serialize(&mut outpoints);
+            amounts.extend_from_slice(&prevout.value.to_le_bytes());
+            write_bytes(&mut script_pubkeys, &prevout.script_pubkey);
+            sequences.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+        buffer.extend_from_slice(&Sha256::digest(&outpoints));
+        buffer.extend_from_slice(&Sha256::digest(&amounts));
+        buffer.extend_from_slice(&Sha256::digest(&script_pubkeys));
+        buffer.extend_from_slice(&Sha256::digest(&sequences));
+    }
+    if output_type != SIGHASH_NONE && output_type != SIGHASH_SINGLE {
+        let mut outputs = vec![];
+        for output in &tx.outputs {
+            output.serialize(&mut outputs);
+        }
+        buffer.extend_from_slice(&Sha256::digest(&outputs));
+    }
+
+    let ext_flag = if leaf.is_some() { 1 } else { 0 };
+    buffer.push(ext_flag * 2 + annex.is_some() as u8);
+    if anyone_can_pay {
+        input.previous_output.serialize(&mut buffer);
+        prevouts[input_index].serialize(&mut buffer);
+        buffer.extend_from_slice(&input.sequence.to_le_bytes());
+    } else {
+        buffer.extend_from_slice(&(input_index as u32).to_le_bytes());
+    }
+    if let Some(annex) = annex {
+        let mut data = vec![];
+        write_bytes(&mut data, annex);
+        buffer.extend_from_slice(&Sha256::digest(&data));
+    }
+    if output_type == SIGHASH_SINGLE {
+        let mut output = vec![];
+        tx.outputs[input_index].serialize(&mut output);
+        buffer.extend_from_slice(&Sha256::digest(&output));
+    }
+    if let Some(leaf) = leaf {
+        buffer.extend_from_slice(&leaf.leaf_hash);
+        // key_version 0
+        buffer.push(0x00);
+        buffer.extend_from_slice(&leaf.code_separator_position.to_le_bytes());
+    }
+
+    Ok(tagged_hash("TapSighash", &buffer))
+}
+
+/// BIP340 tagged hash : `sha256(sha256(tag) || sha256(tag) || data)`.
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// BIP341 leaf hash of a script in a taproot tree.
+pub fn tap_leaf_hash(leaf_version: u8, script: &[u8]) -> [u8; 32] {
+    let mut data = vec![leaf_version];
+    write_bytes(&mut data, script);
+    tagged_hash("TapLeaf", &data)
+}
+
+/// BIP341 hash of two taproot tree nodes, sorted so the tree does not commit to their order.
+pub fn tap_branch_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    let mut data = left.to_vec();
+    data.extend_from_slice(right);
+    tagged_hash("TapBranch", &data)
+}
+
+#[cfg(test)]
+mod test_sighash {
+    use crate::transaction::sighash::{
+        legacy_sighash, remove_code_separators, segwit_v0_sighash, taproot_sighash, TapLeafData,
+    };
+    use crate::transaction::{Transaction, TxOut};
+    use rstest::rstest;
+
+    // BIP143 native P2WPKH example.
+    const BIP143_TX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+    fn prevouts() -> Vec<TxOut> {
+        vec![
+            TxOut {
+                value: 625_000_000,
+                script_pubkey: hex::decode(
+                    "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac",
+                )
+                .unwrap(),
+            },
+            TxOut {
+                value: 600_000_000,
+                script_pubkey: hex::decode("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap(),
+            },
+        ]
+    }
+
+    #[rstest]
+    fn test_bip143_p2wpkh() -> color_eyre::Result<()> {
+        let tx = Transaction::from_hex(BIP143_TX)?;
+        let script_code = hex::decode("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac")?;
+        assert_eq!(
+            hex::encode(segwit_v0_sighash(&tx, 1, &script_code, 600_000_000, 0x01)?),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+        Ok(())
+    }
+
+    // Expected values computed with rust-bitcoin 0.32 (`SighashCache`).
+    #[rstest]
+    #[case(
+        0x01,
+        "63cec688ee06a91e913875356dd4dea2f8e0f2a2659885372da2a37e32c7532e"
+    )]
+    #[case(
+        0x02,
+        "b5b85036f284c90e641fc6b6fd25fbe29f632a75051e05b0b006a6fbfedd0af2"
+    )]
+    #[case(
+        0x03,
+        "0be090c73eb6bac7b789bb553a2a9775e8d5bcbe292f359f57fd0a13363de709"
+    )]
+    #[case(
+        0x81,
+        "1f948bed57a053e52f7bcaf5767ded39306b9168b0e204a76f087f2059d63088"
+    )]
+    #[case(
+        0x83,
+        "8cac7d2ba39a9b5787185c70e7b1a3dc597f1a180cab558b1c40b69757826296"
+    )]
+    fn test_legacy(#[case] sighash_type: u32, #[case] expected: &str) -> color_eyre::Result<()> {
+        let tx = Transaction::from_hex(BIP143_TX)?;
+        let script_code = &prevouts()[0].script_pubkey;
+        assert_eq!(
+            hex::encode(legacy_sighash(&tx, 0, script_code, sighash_type)?),
+            expected
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_legacy_single_bug() -> color_eyre::Result<()> {
+        let mut tx = Transaction::from_hex(BIP143_TX)?;
+        tx.outputs.truncate(1);
+        let hash = legacy_sighash(&tx, 1, &[], 0x03)?;
+        assert_eq!(hash[0], 1);
+        assert!(hash[1..].iter().all(|byte| *byte == 0));
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_remove_code_separators() {
+        // OP_1 OP_CODESEPARATOR <ab> OP_CODESEPARATOR, the push of 0xab is kept.
+        let script = hex::decode("51ab01abab").unwrap();
+        assert_eq!(
+            remove_code_separators(&script),
+            hex::decode("5101ab").unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        0x00,
+        None,
+        "2c26d9637c636adac065e4cb2d45bdb3eee6a4cbccd63ae7804efe503f5d03f3"
+    )]
+    #[case(
+        0x83,
+        None,
+        "97afd3cc0c63f940626878a9d1ff7009a147aa4ae11d54e9d18c7c568fb4cb87"
+    )]
+    #[case(
+        0x01,
+        Some(2),
+        "8ddfb92867fd700dff7375d39c30931e33fadaec8d2455d8c9f215db57dae68f"
+    )]
+    fn test_taproot(
+        #[case] sighash_type: u8,
+        #[case] code_separator: Option<u32>,
+        #[case] expected: &str,
+    ) -> color_eyre::Result<()> {
+        let tx = Transaction::from_hex(BIP143_TX)?;
+        let leaf = code_separator.map(|position| TapLeafData {
+            leaf_hash: [0x11; 32],
+            code_separator_position: position,
+        });
+        assert_eq!(
+            hex::encode(taproot_sighash(
+                &tx,
+                1,
+                &prevouts(),
+                sighash_type,
+                None,
+                leaf.as_ref()
+            )?),
+            expected
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_taproot_invalid_type() -> color_eyre::Result<()> {
+        let tx = Transaction::from_hex(BIP143_TX)?;
+        assert!(taproot_sighash(&tx, 0, &prevouts(), 0x04, None, None).is_err());
+        assert!(taproot_sighash(&tx, 0, &prevouts()[..1], 0x01, None, None).is_err());
+        Ok(())
+    }
+}
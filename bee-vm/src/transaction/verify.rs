@@ -0,0 +1,868 @@
+use crate::errors::{TransactionError, VerifyError};
+use crate::opcodes::opcode::Opcode;
+use crate::script::classify::{classify, witness_program, ScriptType};
+use crate::script::decode::{bytes_to_tokens, decode, Instruction, Instructions};
+use crate::script::encode::{element_to_bytes, push_data};
+use crate::stack::executor::execute_code_with_context;
+use crate::stack::{Stack, MAX_STACK_SIZE};
+use crate::transaction::context::ExecutionContext;
+use crate::transaction::sighash::{
+    tagged_hash, tap_branch_hash, tap_leaf_hash, SigVersion, TAPSCRIPT_LEAF_VERSION,
+};
+use crate::transaction::{Transaction, TxOut};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::{ProjectivePoint, PublicKey, Scalar};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+/// Conditionals reserved for upgrades, they fail a script even in an unexecuted branch.
+const OP_VERIF: Opcode = Opcode(0x65);
+const OP_VERNOTIF: Opcode = Opcode(0x66);
+
+/// First byte of a taproot annex, the last witness item when there are at least two.
+const ANNEX_TAG: u8 = 0x50;
+
+/// Control blocks hold the internal key and at most 128 merkle path nodes.
+const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+const TAPROOT_CONTROL_MAX_SIZE: usize = TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * 128;
+
+/// Largest element pushed by a script or given in a witness (consensus).
+const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+
+/// Largest legacy or segwit v0 script (consensus), tapscripts are only bounded by the block.
+const MAX_SCRIPT_SIZE: usize = 10_000;
+
+/// Verifies that an input spends its output, like Bitcoin Core's `VerifyScript` with the
+/// P2SH, WITNESS and TAPROOT rules and the consensus limits on script, element and stack sizes.
+///
+/// `prevouts` are the outputs spent by every input of `tx`, in input order.
+pub fn verify_input(
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[TxOut],
+) -> Result<(), VerifyError> {
+    let input = tx
+        .inputs
+        .get(input_index)
+        .ok_or(TransactionError::InputIndexOutOfRange(input_index))?;
+    if prevouts.len() != tx.inputs.len() {
+        return Err(TransactionError::MissingPrevout(prevouts.len().min(tx.inputs.len())).into());
+    }
+    let script_pubkey = &prevouts[input_index].script_pubkey;
+    let context = ExecutionContext::new(
+        tx.clone(),
+        input_index,
+        prevouts[input_index].clone(),
+        SigVersion::Base,
+    )?;
+
+    let stack = run(&input.script_sig, Stack::new(), &context)?;
+    let p2sh_stack = stack.clone();
+    check_success(&run(script_pubkey, stack, &context)?)?;
+
+    let mut had_witness = false;
+    if let Some((version, program)) = witness_program(script_pubkey) {
+        had_witness = true;
+        if !input.script_sig.is_empty() {
+            return Err(VerifyError::WitnessMalleated);
+        }
+        verify_witness_program(tx, input_index, prevouts, version, program, false)?;
+    } else if let ScriptType::P2SH { .. } = classify(script_pubkey) {
+        if !is_push_only(&input.script_sig)? {
+            return Err(VerifyError::SigPushOnly);
+        }
+        // The scriptPubKey checked the hash of the top element, it is now run as a script.
+        let mut stack = p2sh_stack;
+        let redeem_script = element_to_bytes(&stack.pop_from_top().ok_or(VerifyError::EvalFalse)?);
+        check_success(&run(&redeem_script, stack, &context)?)?;
+
+        if let Some((version, program)) = witness_program(&redeem_script) {
+            had_witness = true;
+            let mut expected_script_sig = vec![];
+            push_data(&mut expected_script_sig, &redeem_script)?;
+            if input.script_sig != expected_script_sig {
+                return Err(VerifyError::WitnessMalleatedP2SH);
+            }
+            verify_witness_program(tx, input_index, prevouts, version, program, true)?;
+        }
+    }
+
+    if !had_witness && !input.witness.is_empty() {
+        return Err(VerifyError::WitnessUnexpected);
+    }
+    Ok(())
+}
+
+/// Verifies every input of a transaction, in parallel.
+///
+/// The error returned is the one of the first failing input, whatever order the inputs are
+/// checked in.
+pub fn verify_transaction(tx: &Transaction, prevouts: &[TxOut]) -> Result<(), VerifyError> {
+    (0..tx.inputs.len())
+        .into_par_iter()
+        .map(|input_index| verify_input(tx, input_index, prevouts))
+        .find_first(Result::is_err)
+        .unwrap_or(Ok(()))
+}
+
+fn verify_witness_program(
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[TxOut],
+    version: u8,
+    program: &[u8],
+    is_p2sh: bool,
+) -> Result<(), VerifyError> {
+    let witness = &tx.inputs[input_index].witness;
+    let spent_output = prevouts[input_index].clone();
+
+    match (version, program.len()) {
+        (0, 20) => {
+            if witness.len() != 2 {
+                return Err(VerifyError::WitnessProgramMismatch);
+            }
+            let mut script = vec![0x76, 0xa9, 0x14];
+            script.extend_from_slice(program);
+            script.extend_from_slice(&[0x88, 0xac]);
+            let context = ExecutionContext::new(
+                tx.clone(),
+                input_index,
+                spent_output,
+                SigVersion::WitnessV0,
+            )?;
+            execute_witness_script(&script, witness, &context)
+        }
+        (0, 32) => {
+            let (script, stack) = witness
+                .split_last()
+                .ok_or(VerifyError::WitnessProgramWitnessEmpty)?;
+            if Sha256::digest(script).as_slice() != program {
+                return Err(VerifyError::WitnessProgramMismatch);
+            }
+            let context = ExecutionContext::new(
+                tx.clone(),
+                input_index,
+                spent_output,
+                SigVersion::WitnessV0,
+            )?;
+            execute_witness_script(script, stack, &context)
+        }
+        (0, length) => Err(VerifyError::WitnessProgramWrongLength(length)),
+        (1, 32) if !is_p2sh => verify_taproot(tx, input_index, prevouts, program),
+        // Future witness versions are anyone-can-spend until a soft fork gives them a meaning.
+        _ => Ok(()),
+    }
+}
+
+fn verify_taproot(
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[TxOut],
+    output_key: &[u8],
+) -> Result<(), VerifyError> {
+    let mut items = tx.inputs[input_index].witness.as_slice();
+    if items.is_empty() {
+        return Err(VerifyError::WitnessProgramWitnessEmpty);
+    }
+    let mut annex = None;
+    if let [rest @ .., last] = items {
+        if !rest.is_empty() && last.first() == Some(&ANNEX_TAG) {
+            annex = Some(last.clone());
+            items = rest;
+        }
+    }
+
+    // Key path : a single signature for the output key.
+    if let [signature] = items {
+        let mut context =
+            ExecutionContext::taproot(tx.clone(), input_index, prevouts.to_vec(), None)?;
+        context.annex = annex;
+        return match context.check_taproot_key_path(signature)? {
+            true => Ok(()),
+            false => Err(VerifyError::SchnorrSig),
+        };
+    }
+
+    // Script path : <stack ...> <script> <control block>.
+    let [stack @ .., script, control_block] = items else {
+        unreachable!("the witness holds at least two items");
+    };
+    if control_block.len() < TAPROOT_CONTROL_BASE_SIZE
+        || control_block.len() > TAPROOT_CONTROL_MAX_SIZE
+        || !(control_block.len() - TAPROOT_CONTROL_BASE_SIZE)
+            .is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+    {
+        return Err(VerifyError::TaprootWrongControlSize(control_block.len()));
+    }
+    let leaf_version = control_block[0] & 0xfe;
+    let leaf_hash = tap_leaf_hash(leaf_version, script);
+    if !verify_taproot_commitment(control_block, output_key, leaf_hash) {
+        return Err(VerifyError::WitnessProgramMismatch);
+    }
+    // Unknown leaf versions are left for future soft forks.
+    if leaf_version != TAPSCRIPT_LEAF_VERSION {
+        return Ok(());
+    }
+
+    let mut context =
+        ExecutionContext::taproot(tx.clone(), input_index, prevouts.to_vec(), Some(leaf_hash))?;
+    context.annex = annex;
+    execute_witness_script(script, stack, &context)
+}
+
+/// Checks that the output key is the internal key of the control block tweaked with the
+/// merkle root reached from `leaf_hash` through the control block path.
+fn verify_taproot_commitment(control_block: &[u8], output_key: &[u8], leaf_hash: [u8; 32]) -> bool {
+    let internal_key = &control_block[1..TAPROOT_CONTROL_BASE_SIZE];
+    let merkle_root = control_block[TAPROOT_CONTROL_BASE_SIZE..]
+        .chunks(TAPROOT_CONTROL_NODE_SIZE)
+        .fold(leaf_hash, |node, sibling| {
+            tap_branch_hash(&node, sibling.try_into().expect("32 bytes chunk"))
+        });
+
+    // x-only keys are the even y point with that x coordinate.
+    let mut even_key = vec![0x02];
+    even_key.extend_from_slice(internal_key);
+    let Ok(internal_point) = PublicKey::from_sec1_bytes(&even_key) else {
+        return false;
+    };
+    let mut data = internal_key.to_vec();
+    data.extend_from_slice(&merkle_root);
+    let tweak: Option<Scalar> = Scalar::from_repr(tagged_hash("TapTweak", &data).into()).into();
+    let Some(tweak) = tweak else {
+        return false;
+    };
+
+    let output = (internal_point.to_projective() + ProjectivePoint::GENERATOR * tweak)
+        .to_affine()
+        .to_encoded_point(true);
+    let expected_parity = if control_block[0] & 1 == 1 {
+        0x03
+    } else {
+        0x02
+    };
+    output.as_bytes().len() == 33
+        && output.as_bytes()[0] == expected_parity
+        && &output.as_bytes()[1..] == output_key
+}
+
+/// Runs a witness script (or the P2WPKH script code), which must leave a single true element.
+pub(crate) fn execute_witness_script(
+    script: &[u8],
+    witness_stack: &[Vec<u8>],
+    context: &ExecutionContext,
+) -> Result<(), VerifyError> {
+    if context.sig_version == SigVersion::Tapscript {
+        // An OP_SUCCESSx before any decoding error makes the script succeed, whatever the
+        // witness holds.
+        for instruction in Instructions::new(script) {
+            if let Instruction::Op(opcode) = instruction? {
+                if opcode.is_success() {
+                    return Ok(());
+                }
+            }
+        }
+        if witness_stack.len() > MAX_STACK_SIZE {
+            return Err(VerifyError::StackSize);
+        }
+    }
+    if let Some(item) = witness_stack
+        .iter()
+        .find(|item| item.len() > MAX_SCRIPT_ELEMENT_SIZE)
+    {
+        return Err(VerifyError::PushSize(item.len()));
+    }
+
+    let stack = run(script, Stack::from_data(witness_stack), context)?;
+    check_success(&stack)?;
+    if stack.len() != 1 {
+        return Err(VerifyError::CleanStack);
+    }
+    Ok(())
+}
+
+pub(crate) fn run(
+    script: &[u8],
+    stack: Stack,
+    context: &ExecutionContext,
+) -> Result<Stack, VerifyError> {
+    check_script(script, context.sig_version)?;
+    let (main_stack, _) = execute_code_with_context(&bytes_to_tokens(script)?, stack, context)
+        .map_err(|error| VerifyError::Execution(error.to_string()))?;
+    Ok(main_stack)
+}
+
+/// Checks the rules failing a script wherever they apply, even in an unexecuted branch : the
+/// script size, the size of the pushes, OP_VERIF / OP_VERNOTIF and the disabled opcodes.
+fn check_script(script: &[u8], sig_version: SigVersion) -> Result<(), VerifyError> {
+    let tapscript = sig_version == SigVersion::Tapscript;
+    if !tapscript && script.len() > MAX_SCRIPT_SIZE {
+        return Err(VerifyError::ScriptSize(script.len()));
+    }
+    for instruction in decode(script)? {
+        match instruction {
+            Instruction::Push { data, .. } if data.len() > MAX_SCRIPT_ELEMENT_SIZE => {
+                return Err(VerifyError::PushSize(data.len()));
+            }
+            Instruction::Push { .. } => {}
+            Instruction::Op(opcode) if opcode == OP_VERIF || opcode == OP_VERNOTIF => {
+                return Err(VerifyError::BadOpcode(opcode.name().to_string()));
+            }
+            Instruction::Op(opcode) if !tapscript && opcode.is_disabled() => {
+                return Err(VerifyError::DisabledOpcode(opcode.name().to_string()));
+            }
+            Instruction::Op(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// The script succeeds when the top element is true (not zero or negative zero).
+pub(crate) fn check_success(stack: &Stack) -> Result<(), VerifyError> {
+    let top = stack.peek(0).ok_or(VerifyError::EvalFalse)?;
+    match cast_to_bool(&element_to_bytes(top)) {
+        true => Ok(()),
+        false => Err(VerifyError::EvalFalse),
+    }
+}
+
+fn cast_to_bool(data: &[u8]) -> bool {
+    match data.split_last() {
+        Some((last, rest)) => rest.iter().any(|byte| *byte != 0) || (*last != 0 && *last != 0x80),
+        None => false,
+    }
+}
+
+/// Only pushes (and OP_1NEGATE / OP_1 ... OP_16) are allowed in the scriptSig of a P2SH spend.
+fn is_push_only(script: &[u8]) -> Result<bool, VerifyError> {
+    Ok(decode(script)?.iter().all(|instruction| match instruction {
+        Instruction::Push { .. } => true,
+        Instruction::Op(opcode) => opcode.0 <= Opcode::OP_16.0,
+    }))
+}
+
+#[cfg(test)]
+mod test_verify {
+    use crate::errors::VerifyError;
+    use crate::script::encode::push_data;
+    use crate::transaction::context::ExecutionContext;
+    use crate::transaction::sighash::{
+        tagged_hash, tap_branch_hash, tap_leaf_hash, SigVersion, TAPSCRIPT_LEAF_VERSION,
+    };
+    use crate::transaction::verify::{verify_input, verify_transaction};
+    use crate::transaction::{Transaction, TxOut};
+    use k256::ecdsa::signature::hazmat::PrehashSigner;
+    use k256::ecdsa::{Signature, SigningKey};
+    use k256::elliptic_curve::sec1::ToEncodedPoint;
+    use k256::elliptic_curve::PrimeField;
+    use k256::{ProjectivePoint, PublicKey, Scalar};
+    use rstest::rstest;
+    use sha2::{Digest, Sha256};
+
+    // BIP143 native P2WPKH example, input 0 spends a P2PK output and input 1 a P2WPKH output.
+    const NATIVE_P2WPKH: &str = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+    // BIP143 P2SH-P2WPKH example.
+    const NESTED_P2WPKH: &str = "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000";
+
+    fn output(script_pubkey: &str, value: u64) -> TxOut {
+        TxOut {
+            value,
+            script_pubkey: hex::decode(script_pubkey).unwrap(),
+        }
+    }
+
+    fn native_prevouts() -> Vec<TxOut> {
+        vec![
+            output(
+                "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac",
+                625_000_000,
+            ),
+            output("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1", 600_000_000),
+        ]
+    }
+
+    /// Unsigned copy of the BIP143 transaction spending `prevouts` outputs of the test.
+    fn unsigned_tx() -> Transaction {
+        let mut tx = Transaction::from_hex(NATIVE_P2WPKH).unwrap();
+        for input in tx.inputs.iter_mut() {
+            input.script_sig.clear();
+            input.witness.clear();
+        }
+        tx
+    }
+
+    fn ecdsa_sign(key: &SigningKey, context: &ExecutionContext, script_code: &[u8]) -> Vec<u8> {
+        let hash = context.signature_hash(script_code, 0x01, u32::MAX).unwrap();
+        let signature: Signature = key.sign_prehash(&hash).unwrap();
+        let mut signature = signature.to_der().as_bytes().to_vec();
+        signature.push(0x01);
+        signature
+    }
+
+    /// 2-of-2 multisig script of the keys seeded with 1 and 2.
+    fn multisig() -> (Vec<SigningKey>, Vec<u8>) {
+        let keys: Vec<SigningKey> = (1..=2)
+            .map(|seed| SigningKey::from_slice(&[seed; 32]).unwrap())
+            .collect();
+        let mut script = vec![0x52];
+        for key in &keys {
+            push_data(&mut script, &key.verifying_key().to_sec1_bytes()).unwrap();
+        }
+        script.extend_from_slice(&[0x52, 0xae]);
+        (keys, script)
+    }
+
+    /// Output key (and its parity) of an internal key committing to `merkle_root`.
+    fn taproot_output(internal_key: &[u8; 32], merkle_root: &[u8; 32]) -> ([u8; 32], u8) {
+        let mut data = internal_key.to_vec();
+        data.extend_from_slice(merkle_root);
+        let tweak = Scalar::from_repr(tagged_hash("TapTweak", &data).into()).unwrap();
+        let mut even_key = vec![0x02];
+        even_key.extend_from_slice(internal_key);
+        let point = PublicKey::from_sec1_bytes(&even_key)
+            .unwrap()
+            .to_projective()
+            + ProjectivePoint::GENERATOR * tweak;
+        let encoded = point.to_affine().to_encoded_point(true);
+        (
+            encoded.as_bytes()[1..].try_into().unwrap(),
+            encoded.as_bytes()[0] - 2,
+        )
+    }
+
+    #[rstest]
+    #[case(NATIVE_P2WPKH, native_prevouts())]
+    #[case(
+        NESTED_P2WPKH,
+        vec![output("a9144733f37cf4db86fbc2efed2500b4f4e49f31202387", 1_000_000_000)]
+    )]
+    fn test_bip143_transactions(
+        #[case] tx: &str,
+        #[case] prevouts: Vec<TxOut>,
+    ) -> color_eyre::Result<()> {
+        let tx = Transaction::from_hex(tx)?;
+        verify_transaction(&tx, &prevouts)?;
+
+        // Every signature commits to the outputs.
+        let mut tampered = tx.clone();
+        tampered.outputs[0].value -= 1;
+        for input_index in 0..tx.inputs.len() {
+            assert_eq!(
+                verify_input(&tampered, input_index, &prevouts),
+                Err(VerifyError::EvalFalse)
+            );
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_witness_malleation() -> color_eyre::Result<()> {
+        let tx = Transaction::from_hex(NATIVE_P2WPKH)?;
+        let prevouts = native_prevouts();
+
+        let mut extra_witness = tx.clone();
+        extra_witness.inputs[0].witness.push(vec![0x01]);
+        assert_eq!(
+            verify_input(&extra_witness, 0, &prevouts),
+            Err(VerifyError::WitnessUnexpected)
+        );
+
+        let mut script_sig = tx.clone();
+        script_sig.inputs[1].script_sig = vec![0x51];
+        assert_eq!(
+            verify_input(&script_sig, 1, &prevouts),
+            Err(VerifyError::WitnessMalleated)
+        );
+        assert!(verify_input(&tx, 2, &prevouts).is_err());
+        assert!(verify_input(&tx, 0, &prevouts[..1]).is_err());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_first_failing_input_is_reported() -> color_eyre::Result<()> {
+        let mut tx = Transaction::from_hex(NATIVE_P2WPKH)?;
+        let prevouts = native_prevouts();
+        tx.inputs[1].script_sig = vec![0x51];
+        assert_eq!(
+            verify_transaction(&tx, &prevouts),
+            Err(VerifyError::WitnessMalleated)
+        );
+
+        // Both inputs fail, the parallel checks still report the first one.
+        tx.inputs[0].witness.push(vec![0x01]);
+        for _ in 0..16 {
+            assert_eq!(
+                verify_transaction(&tx, &prevouts),
+                Err(VerifyError::WitnessUnexpected)
+            );
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_p2sh_multisig() -> color_eyre::Result<()> {
+        let (keys, redeem_script) = multisig();
+        let mut script_pubkey = vec![0xa9, 0x14];
+        script_pubkey.extend_from_slice(&ripemd::Ripemd160::digest(Sha256::digest(&redeem_script)));
+        script_pubkey.push(0x87);
+        let mut prevouts = native_prevouts();
+        prevouts[0] = TxOut {
+            value: 50_000,
+            script_pubkey,
+        };
+
+        let mut tx = unsigned_tx();
+        tx.inputs[1].witness = Transaction::from_hex(NATIVE_P2WPKH)?.inputs[1]
+            .witness
+            .clone();
+        let context = ExecutionContext::new(tx.clone(), 0, prevouts[0].clone(), SigVersion::Base)?;
+        let mut script_sig = vec![0x00];
+        for key in &keys {
+            push_data(&mut script_sig, &ecdsa_sign(key, &context, &redeem_script))?;
+        }
+        push_data(&mut script_sig, &redeem_script)?;
+        tx.inputs[0].script_sig = script_sig;
+        verify_transaction(&tx, &prevouts)?;
+
+        // Signatures in the wrong order.
+        let mut swapped = vec![0x00];
+        for key in keys.iter().rev() {
+            push_data(&mut swapped, &ecdsa_sign(key, &context, &redeem_script))?;
+        }
+        push_data(&mut swapped, &redeem_script)?;
+        tx.inputs[0].script_sig = swapped;
+        assert_eq!(verify_input(&tx, 0, &prevouts), Err(VerifyError::EvalFalse));
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_p2wsh_multisig() -> color_eyre::Result<()> {
+        let (keys, witness_script) = multisig();
+        let mut script_pubkey = vec![0x00, 0x20];
+        script_pubkey.extend_from_slice(&Sha256::digest(&witness_script));
+        let mut prevouts = native_prevouts();
+        prevouts[1] = TxOut {
+            value: 70_000,
+            script_pubkey,
+        };
+
+        let mut tx = unsigned_tx();
+        tx.inputs[0].script_sig = Transaction::from_hex(NATIVE_P2WPKH)?.inputs[0]
+            .script_sig
+            .clone();
+        let context =
+            ExecutionContext::new(tx.clone(), 1, prevouts[1].clone(), SigVersion::WitnessV0)?;
+        let mut witness = vec![vec![]];
+        for key in &keys {
+            witness.push(ecdsa_sign(key, &context, &witness_script));
+        }
+        witness.push(witness_script.clone());
+        tx.inputs[1].witness = witness;
+        verify_transaction(&tx, &prevouts)?;
+
+        // The witness script must hash to the program.
+        let mut other_script = tx.clone();
+        other_script.inputs[1].witness.last_mut().unwrap()[0] = 0x51;
+        assert_eq!(
+            verify_input(&other_script, 1, &prevouts),
+            Err(VerifyError::WitnessProgramMismatch)
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_taproot_spends() -> color_eyre::Result<()> {
+        let internal = k256::schnorr::SigningKey::from_bytes(&[3; 32])?;
+        let internal_key: [u8; 32] = internal.verifying_key().to_bytes().into();
+        let leaf_key = k256::schnorr::SigningKey::from_bytes(&[4; 32])?;
+        let mut leaf_script = vec![0x20];
+        leaf_script.extend_from_slice(&leaf_key.verifying_key().to_bytes());
+        leaf_script.push(0xac);
+
+        // Two leaves tree, the sibling is only known by its hash.
+        let leaf_hash = tap_leaf_hash(TAPSCRIPT_LEAF_VERSION, &leaf_script);
+        let sibling = [0x42; 32];
+        let (output_key, parity) =
+            taproot_output(&internal_key, &tap_branch_hash(&leaf_hash, &sibling));
+        let mut script_pubkey = vec![0x51, 0x20];
+        script_pubkey.extend_from_slice(&output_key);
+        let prevouts = vec![
+            TxOut {
+                value: 10_000,
+                script_pubkey,
+            };
+            2
+        ];
+
+        let mut tx = unsigned_tx();
+        let context = ExecutionContext::taproot(tx.clone(), 0, prevouts.clone(), Some(leaf_hash))?;
+        let hash = context.signature_hash(&[], 0x00, u32::MAX)?;
+        let signature = leaf_key.sign_raw(&hash, &[0; 32])?.to_bytes().to_vec();
+        let mut control_block = vec![TAPSCRIPT_LEAF_VERSION | parity];
+        control_block.extend_from_slice(&internal_key);
+        control_block.extend_from_slice(&sibling);
+        tx.inputs[0].witness = vec![signature, leaf_script, control_block.clone()];
+        verify_input(&tx, 0, &prevouts)?;
+
+        // Wrong parity bit and truncated control block.
+        let mut wrong_parity = tx.clone();
+        wrong_parity.inputs[0].witness[2][0] ^= 1;
+        assert_eq!(
+            verify_input(&wrong_parity, 0, &prevouts),
+            Err(VerifyError::WitnessProgramMismatch)
+        );
+        let mut truncated = tx.clone();
+        truncated.inputs[0].witness[2].pop();
+        assert_eq!(
+            verify_input(&truncated, 0, &prevouts),
+            Err(VerifyError::TaprootWrongControlSize(64))
+        );
+
+        // A key path signature from the internal key alone is not valid for the tweaked key.
+        let context = ExecutionContext::taproot(tx.clone(), 1, prevouts.clone(), None)?;
+        let hash = context.signature_hash(&[], 0x00, u32::MAX)?;
+        tx.inputs[1].witness = vec![internal.sign_raw(&hash, &[0; 32])?.to_bytes().to_vec()];
+        assert_eq!(
+            verify_input(&tx, 1, &prevouts),
+            Err(VerifyError::SchnorrSig)
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_unbalanced_conditional() -> color_eyre::Result<()> {
+        // OP_1 OP_IF OP_1, the OP_IF is never closed.
+        let script = vec![0x51, 0x63, 0x51];
+        let mut p2wsh = vec![0x00, 0x20];
+        p2wsh.extend_from_slice(&Sha256::digest(&script));
+        let prevouts = vec![
+            output(&hex::encode(&script), 10_000),
+            output(&hex::encode(p2wsh), 10_000),
+        ];
+
+        let mut tx = unsigned_tx();
+        tx.inputs[1].witness = vec![script];
+        for input_index in 0..2 {
+            let result = verify_input(&tx, input_index, &prevouts);
+            assert!(
+                matches!(&result, Err(VerifyError::Execution(error)) if error.contains("OP_ENDIF")),
+                "{:?}",
+                result
+            );
+        }
+        Ok(())
+    }
+
+    /// Spends input 1 of the test transaction from a P2WSH output of `witness_script`.
+    fn p2wsh_spend(witness_script: &[u8], stack: Vec<Vec<u8>>) -> (Transaction, Vec<TxOut>) {
+        let mut script_pubkey = vec![0x00, 0x20];
+        script_pubkey.extend_from_slice(&Sha256::digest(witness_script));
+        let mut prevouts = native_prevouts();
+        prevouts[1] = TxOut {
+            value: 10_000,
+            script_pubkey,
+        };
+        let mut tx = unsigned_tx();
+        tx.inputs[1].witness = stack;
+        tx.inputs[1].witness.push(witness_script.to_vec());
+        (tx, prevouts)
+    }
+
+    /// Spends input 0 of the test transaction from a taproot output with `leaf_script` alone
+    /// in its tree.
+    fn tapscript_spend(
+        leaf_script: &[u8],
+        stack: Vec<Vec<u8>>,
+    ) -> color_eyre::Result<(Transaction, Vec<TxOut>)> {
+        let internal_key: [u8; 32] = k256::schnorr::SigningKey::from_bytes(&[3; 32])?
+            .verifying_key()
+            .to_bytes()
+            .into();
+        let leaf_hash = tap_leaf_hash(TAPSCRIPT_LEAF_VERSION, leaf_script);
+        let (output_key, parity) = taproot_output(&internal_key, &leaf_hash);
+        let mut script_pubkey = vec![0x51, 0x20];
+        script_pubkey.extend_from_slice(&output_key);
+        let prevouts = vec![
+            TxOut {
+                value: 10_000,
+                script_pubkey,
+            };
+            2
+        ];
+
+        let mut tx = unsigned_tx();
+        let mut control_block = vec![TAPSCRIPT_LEAF_VERSION | parity];
+        control_block.extend_from_slice(&internal_key);
+        tx.inputs[0].witness = stack;
+        tx.inputs[0].witness.push(leaf_script.to_vec());
+        tx.inputs[0].witness.push(control_block);
+        Ok((tx, prevouts))
+    }
+
+    /// Message of a script execution error.
+    fn execution_error(result: Result<(), VerifyError>) -> String {
+        match result {
+            Err(VerifyError::Execution(error)) => error,
+            other => panic!("expected an execution error, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case(vec![1], true)]
+    #[case(vec![2], false)]
+    #[case(vec![1, 0], false)]
+    fn test_tapscript_minimal_if(
+        #[case] argument: Vec<u8>,
+        #[case] valid: bool,
+    ) -> color_eyre::Result<()> {
+        // OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF
+        let (tx, prevouts) = tapscript_spend(&[0x63, 0x51, 0x67, 0x00, 0x68], vec![argument])?;
+        let result = verify_input(&tx, 0, &prevouts);
+        if valid {
+            result?;
+        } else {
+            assert!(
+                matches!(result, Err(VerifyError::Execution(_))),
+                "{:?}",
+                result
+            );
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(520, Ok(()))]
+    #[case(521, Err(VerifyError::PushSize(521)))]
+    fn test_push_size(#[case] size: usize, #[case] expected: Result<(), VerifyError>) {
+        // <data> OP_DROP OP_1, the limit holds even in an unexecuted branch.
+        let mut script = vec![];
+        push_data(&mut script, &vec![0x42; size]).unwrap();
+        script.extend_from_slice(&[0x75, 0x51]);
+        let (tx, prevouts) = p2wsh_spend(&script, vec![]);
+        assert_eq!(verify_input(&tx, 1, &prevouts), expected);
+
+        let mut skipped = vec![0x00, 0x63];
+        push_data(&mut skipped, &vec![0x42; size]).unwrap();
+        skipped.extend_from_slice(&[0x68, 0x51]);
+        let (tx, prevouts) = p2wsh_spend(&skipped, vec![]);
+        assert_eq!(verify_input(&tx, 1, &prevouts), expected);
+    }
+
+    #[rstest]
+    #[case(vec![], true)]
+    #[case(vec![0], false)]
+    #[case(vec![0x80], false)]
+    fn test_null_dummy(#[case] dummy: Vec<u8>, #[case] valid: bool) {
+        // OP_0 OP_0 OP_CHECKMULTISIG, a 0-of-0 multisig only checks its dummy element.
+        let (tx, prevouts) = p2wsh_spend(&[0x00, 0x00, 0xae], vec![dummy]);
+        assert_eq!(verify_input(&tx, 1, &prevouts).is_ok(), valid);
+    }
+
+    #[rstest]
+    #[case(520, Ok(()))]
+    #[case(521, Err(VerifyError::PushSize(521)))]
+    fn test_witness_item_size(#[case] size: usize, #[case] expected: Result<(), VerifyError>) {
+        // OP_DROP OP_1
+        let (tx, prevouts) = p2wsh_spend(&[0x75, 0x51], vec![vec![0x42; size]]);
+        assert_eq!(verify_input(&tx, 1, &prevouts), expected);
+    }
+
+    #[rstest]
+    fn test_stack_size() {
+        // OP_1 OP_TOALTSTACK OP_1 on top of the witness items, alt stack items count too.
+        let script = [0x51, 0x6b, 0x51];
+        let (tx, prevouts) = p2wsh_spend(&script, vec![vec![]; 998]);
+        assert_eq!(
+            verify_input(&tx, 1, &prevouts),
+            Err(VerifyError::CleanStack)
+        );
+        let (tx, prevouts) = p2wsh_spend(&script, vec![vec![]; 999]);
+        assert!(execution_error(verify_input(&tx, 1, &prevouts)).contains("1000 items"));
+
+        // Tapscript also limits the witness stack before running the script.
+        let (tx, prevouts) = tapscript_spend(&[0x51], vec![vec![]; 1001]).unwrap();
+        assert_eq!(verify_input(&tx, 0, &prevouts), Err(VerifyError::StackSize));
+    }
+
+    #[rstest]
+    #[case(184, true)]
+    #[case(185, false)]
+    fn test_op_count(#[case] nops: usize, #[case] valid: bool) {
+        // <nops> OP_0 OP_0 <16 x OP_0> OP_16 OP_CHECKMULTISIG : a 0-of-16 multisig counts as 17
+        // opcodes.
+        let mut script = vec![0x61; nops];
+        script.extend_from_slice(&[0x00; 18]);
+        script.extend_from_slice(&[0x60, 0xae]);
+        let (tx, prevouts) = p2wsh_spend(&script, vec![]);
+        let result = verify_input(&tx, 1, &prevouts);
+        if valid {
+            assert_eq!(result, Ok(()));
+        } else {
+            assert!(execution_error(result).contains("201 non push opcodes"));
+        }
+
+        // Tapscript has no opcode limit.
+        let (tx, prevouts) = tapscript_spend(&vec![0x61; 300], vec![vec![1]]).unwrap();
+        assert_eq!(verify_input(&tx, 0, &prevouts), Ok(()));
+    }
+
+    #[rstest]
+    #[case(0, Ok(()))]
+    #[case(1, Err(VerifyError::ScriptSize(10_001)))]
+    fn test_script_size(#[case] extra: usize, #[case] expected: Result<(), VerifyError>) {
+        // OP_1 followed by 20 pushes dropped right away, 10000 bytes in total.
+        let mut script = vec![0x51];
+        for index in 0..20 {
+            let size = if index == 0 { 495 + extra } else { 496 };
+            push_data(&mut script, &vec![0x42; size]).unwrap();
+            script.push(0x75);
+        }
+        assert_eq!(script.len(), 10_000 + extra);
+        let (tx, prevouts) = p2wsh_spend(&script, vec![]);
+        assert_eq!(verify_input(&tx, 1, &prevouts), expected);
+
+        // Tapscripts are not limited.
+        let (tx, prevouts) = tapscript_spend(&script, vec![]).unwrap();
+        assert_eq!(verify_input(&tx, 0, &prevouts), Ok(()));
+    }
+
+    #[rstest]
+    #[case(vec![0x50])]
+    #[case(vec![0x6a, 0x89])]
+    #[case(vec![0x00, 0x63, 0x7e, 0x68, 0x00])]
+    #[case(vec![0xbb, 0x4c])]
+    #[case(vec![0xfe])]
+    fn test_tapscript_op_success(#[case] script: Vec<u8>) -> color_eyre::Result<()> {
+        // OP_SUCCESSx anywhere, even after OP_RETURN or before a truncated push, and whatever
+        // the witness holds.
+        let (tx, prevouts) = tapscript_spend(&script, vec![vec![0x42; 521]; 1001])?;
+        verify_input(&tx, 0, &prevouts)?;
+
+        // The same opcodes fail legacy and segwit v0 scripts.
+        let (tx, prevouts) = p2wsh_spend(&script, vec![]);
+        assert!(verify_input(&tx, 1, &prevouts).is_err());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(vec![0x4c, 0x50])]
+    #[case(vec![0x65])]
+    #[case(vec![0xff])]
+    fn test_tapscript_without_op_success(#[case] script: Vec<u8>) -> color_eyre::Result<()> {
+        // A truncated push before the OP_SUCCESSx, OP_VERIF and OP_INVALIDOPCODE still fail.
+        let (tx, prevouts) = tapscript_spend(&script, vec![])?;
+        assert!(verify_input(&tx, 0, &prevouts).is_err());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(0x7e, VerifyError::DisabledOpcode("OP_CAT".to_string()))]
+    #[case(0x65, VerifyError::BadOpcode("OP_VERIF".to_string()))]
+    fn test_opcodes_failing_unexecuted(#[case] opcode: u8, #[case] expected: VerifyError) {
+        // OP_0 OP_IF <opcode> OP_ENDIF OP_1
+        let (tx, prevouts) = p2wsh_spend(&[0x00, 0x63, opcode, 0x68, 0x51], vec![]);
+        assert_eq!(verify_input(&tx, 1, &prevouts), Err(expected));
+    }
+}
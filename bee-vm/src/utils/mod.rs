@@ -1,13 +1,15 @@
-/// Print the stack_ops in a formatted box
-pub fn print_in_box(array: &mut Vec<String>) {
+use crate::stack::Stack;
+
+/// Print the stack_ops in a formatted box, top item first
+pub fn print_in_box(stack: &Stack) {
     // Find the longest string to determine the width of the box
-    let max_length = array.iter().map(|s| s.len()).max().unwrap_or(0);
+    let max_length = stack.items().iter().map(|s| s.len()).max().unwrap_or(0);
 
     // Print the top border
     println!("\n<TOP>\n{}", "-".repeat(max_length + 4));
 
     // Print each item in the array, centered in the box
-    for item in array {
+    for item in stack.items().iter().rev() {
         let padding = max_length - item.len();
         let left_padding = padding / 2;
         let _right_padding = padding - left_padding;
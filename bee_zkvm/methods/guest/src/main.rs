@@ -8,10 +8,10 @@ fn main() {
     let mut commit_vector = vec![];
     commit_vector.push((input.len() as usize).to_string());
     commit_vector.extend(input);
-    commit_vector.push(main_stack.length.to_string());
-    commit_vector.extend(main_stack.elements);
-    commit_vector.push(alt_stack.length.to_string());
-    commit_vector.extend(alt_stack.elements);
+    commit_vector.push(main_stack.len().to_string());
+    commit_vector.extend(main_stack.items().iter().rev().cloned());
+    commit_vector.push(alt_stack.len().to_string());
+    commit_vector.extend(alt_stack.items().iter().rev().cloned());
 
     env::commit(&commit_vector);
 }
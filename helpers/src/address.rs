@@ -0,0 +1,349 @@
+use crate::errors::AddressError;
+use crate::hashes::{hash160, sha256};
+use crate::network::Network;
+use bech32::{segwit, Fe32, Hrp};
+use bee_vm::script::classify::{classify, ScriptType};
+use std::fmt;
+
+/// What an address pays to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    PubkeyHash([u8; 20]),
+    ScriptHash([u8; 20]),
+    WitnessProgram { version: u8, program: Vec<u8> },
+}
+
+/// A Bitcoin address: a payload plus the network it is encoded for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub network: Network,
+    pub payload: Payload,
+}
+
+impl Address {
+    pub fn p2pkh(pubkey: &[u8], network: Network) -> Address {
+        Address {
+            network,
+            payload: Payload::PubkeyHash(hash160(pubkey)),
+        }
+    }
+
+    /// P2SH address of a redeem script.
+    pub fn p2sh(redeem_script: &[u8], network: Network) -> Address {
+        Address {
+            network,
+            payload: Payload::ScriptHash(hash160(redeem_script)),
+        }
+    }
+
+    /// P2WPKH address of a compressed public key.
+    pub fn p2wpkh(pubkey: &[u8], network: Network) -> Address {
+        Address {
+            network,
+            payload: Payload::WitnessProgram {
+                version: 0,
+                program: hash160(pubkey).to_vec(),
+            },
+        }
+    }
+
+    /// P2WSH address of a witness script.
+    pub fn p2wsh(witness_script: &[u8], network: Network) -> Address {
+        Address {
+            network,
+            payload: Payload::WitnessProgram {
+                version: 0,
+                program: sha256(witness_script).to_vec(),
+            },
+        }
+    }
+
+    /// P2TR address of an (already tweaked) x-only output key.
+    pub fn p2tr(output_key: &[u8; 32], network: Network) -> Address {
+        Address {
+            network,
+            payload: Payload::WitnessProgram {
+                version: 1,
+                program: output_key.to_vec(),
+            },
+        }
+    }
+
+    /// Builds the address of an output script, if the script has one.
+    pub fn from_script_pubkey(script: &[u8], network: Network) -> Result<Address, AddressError> {
+        let payload = match classify(script) {
+            ScriptType::P2PKH { pubkey_hash } => Payload::PubkeyHash(pubkey_hash),
+            ScriptType::P2SH { script_hash } => Payload::ScriptHash(script_hash),
+            ScriptType::P2WPKH { pubkey_hash } => Payload::WitnessProgram {
+                version: 0,
+                program: pubkey_hash.to_vec(),
+            },
+            ScriptType::P2WSH { script_hash } => Payload::WitnessProgram {
+                version: 0,
+                program: script_hash.to_vec(),
+            },
+            ScriptType::P2TR { output_key } => Payload::WitnessProgram {
+                version: 1,
+                program: output_key.to_vec(),
+            },
+            ScriptType::WitnessUnknown { version, program } => {
+                Payload::WitnessProgram { version, program }
+            }
+            other => return Err(AddressError::NoAddressForm(other.name().to_string())),
+        };
+        Ok(Address { network, payload })
+    }
+
+    /// The output script (scriptPubKey) paying to this address.
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        match &self.payload {
+            Payload::PubkeyHash(hash) => {
+                let mut script = vec![0x76, 0xa9, 0x14];
+                script.extend_from_slice(hash);
+                script.extend_from_slice(&[0x88, 0xac]);
+                script
+            }
+            Payload::ScriptHash(hash) => {
+                let mut script = vec![0xa9, 0x14];
+                script.extend_from_slice(hash);
+                script.push(0x87);
+                script
+            }
+            Payload::WitnessProgram { version, program } => {
+                let version_opcode = if *version == 0 { 0x00 } else { 0x50 + version };
+                let mut script = vec![version_opcode, program.len() as u8];
+                script.extend_from_slice(program);
+                script
+            }
+        }
+    }
+
+    pub fn address_type(&self) -> &'static str {
+        match &self.payload {
+            Payload::PubkeyHash(_) => "p2pkh",
+            Payload::ScriptHash(_) => "p2sh",
+            Payload::WitnessProgram {
+                version: 0,
+                program,
+            } if program.len() == 20 => "p2wpkh",
+            Payload::WitnessProgram { version: 0, .. } => "p2wsh",
+            Payload::WitnessProgram {
+                version: 1,
+                program,
+            } if program.len() == 32 => "p2tr",
+            Payload::WitnessProgram { .. } => "witness_unknown",
+        }
+    }
+
+    /// Parses an address and checks that it is encoded for the given network.
+    pub fn parse(address: &str, network: Network) -> Result<Address, AddressError> {
+        let decoded = Address::decode(address)?;
+        let matches = match decoded.payload {
+            Payload::WitnessProgram { .. } => decoded.network.bech32_hrp() == network.bech32_hrp(),
+            _ => decoded.network.p2pkh_version() == network.p2pkh_version(),
+        };
+
+        if !matches {
+            return Err(AddressError::NetworkMismatch {
+                expected: network.to_string(),
+                found: decoded.network.to_string(),
+            });
+        }
+        Ok(Address {
+            network,
+            payload: decoded.payload,
+        })
+    }
+
+    /// Decodes an address on its own.
+    ///
+    /// Testnet, signet and (base58) regtest addresses share their encoding, those are reported
+    /// as testnet. Use [`Address::parse`] to read an address for a known network.
+    pub fn decode(address: &str) -> Result<Address, AddressError> {
+        let lowercase = address.to_lowercase();
+        let is_segwit = ["bc1", "tb1", "bcrt1"]
+            .iter()
+            .any(|prefix| lowercase.starts_with(prefix));
+
+        if is_segwit {
+            decode_segwit(address)
+        } else {
+            decode_base58(address)
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.payload {
+            Payload::PubkeyHash(hash) => {
+                write!(f, "{}", base58_check(self.network.p2pkh_version(), hash))
+            }
+            Payload::ScriptHash(hash) => {
+                write!(f, "{}", base58_check(self.network.p2sh_version(), hash))
+            }
+            Payload::WitnessProgram { version, program } => {
+                let hrp = Hrp::parse_unchecked(self.network.bech32_hrp());
+                let version = Fe32::try_from(*version).map_err(|_| fmt::Error)?;
+                let encoded = segwit::encode(hrp, version, program).map_err(|_| fmt::Error)?;
+                write!(f, "{}", encoded)
+            }
+        }
+    }
+}
+
+fn base58_check(version: u8, payload: &[u8]) -> String {
+    let mut data = vec![version];
+    data.extend_from_slice(payload);
+    bs58::encode(data).with_check().into_string()
+}
+
+fn decode_base58(address: &str) -> Result<Address, AddressError> {
+    let data = bs58::decode(address)
+        .with_check(None)
+        .into_vec()
+        .map_err(|e| AddressError::InvalidBase58(e.to_string()))?;
+
+    if data.len() != 21 {
+        return Err(AddressError::InvalidBase58(format!(
+            "expected 21 bytes, found {}",
+            data.len()
+        )));
+    }
+    let hash: [u8; 20] = data[1..].try_into().unwrap();
+
+    match data[0] {
+        0x00 => Ok(Address {
+            network: Network::Mainnet,
+            payload: Payload::PubkeyHash(hash),
+        }),
+        0x05 => Ok(Address {
+            network: Network::Mainnet,
+            payload: Payload::ScriptHash(hash),
+        }),
+        0x6f => Ok(Address {
+            network: Network::Testnet,
+            payload: Payload::PubkeyHash(hash),
+        }),
+        0xc4 => Ok(Address {
+            network: Network::Testnet,
+            payload: Payload::ScriptHash(hash),
+        }),
+        version => Err(AddressError::UnknownVersion(version)),
+    }
+}
+
+fn decode_segwit(address: &str) -> Result<Address, AddressError> {
+    let (hrp, version, program) =
+        segwit::decode(address).map_err(|e| AddressError::InvalidBech32(e.to_string()))?;
+
+    let network = match hrp.to_lowercase().as_str() {
+        "bc" => Network::Mainnet,
+        "tb" => Network::Testnet,
+        "bcrt" => Network::Regtest,
+        other => {
+            return Err(AddressError::InvalidBech32(format!(
+                "unknown hrp {}",
+                other
+            )))
+        }
+    };
+
+    Ok(Address {
+        network,
+        payload: Payload::WitnessProgram {
+            version: version.to_u8(),
+            program,
+        },
+    })
+}
+
+#[cfg(test)]
+mod test_address {
+    use crate::address::{Address, Payload};
+    use crate::network::Network;
+    use rstest::rstest;
+
+    // Test vectors from BIP173 / BIP350 and the well known genesis block address.
+    #[rstest]
+    #[case(
+        "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+        Network::Mainnet,
+        "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac"
+    )]
+    #[case(
+        "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+        Network::Mainnet,
+        "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87"
+    )]
+    #[case(
+        "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+        Network::Mainnet,
+        "0014751e76e8199196d454941c45d1b3a323f1433bd6"
+    )]
+    #[case(
+        "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+        Network::Testnet,
+        "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"
+    )]
+    #[case(
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+        Network::Mainnet,
+        "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+    )]
+    #[case(
+        "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080",
+        Network::Regtest,
+        "0014751e76e8199196d454941c45d1b3a323f1433bd6"
+    )]
+    fn test_address_to_script(
+        #[case] address: &str,
+        #[case] network: Network,
+        #[case] script: &str,
+    ) -> color_eyre::Result<()> {
+        let parsed = Address::parse(address, network)?;
+        assert_eq!(hex::encode(parsed.script_pubkey()), script);
+
+        let round_trip = Address::from_script_pubkey(&hex::decode(script)?, network)?;
+        // bech32 addresses are case insensitive and always encoded in lower case
+        assert_eq!(
+            round_trip.to_string().to_lowercase(),
+            address.to_lowercase()
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_signet_and_regtest_share_base58() -> color_eyre::Result<()> {
+        let address = Address {
+            network: Network::Signet,
+            payload: Payload::PubkeyHash([7; 20]),
+        };
+        let encoded = address.to_string();
+        assert!(encoded.starts_with('m') || encoded.starts_with('n'));
+        assert_eq!(
+            Address::parse(&encoded, Network::Regtest)?.payload,
+            address.payload
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb", Network::Mainnet)]
+    #[case("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", Network::Testnet)]
+    #[case("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", Network::Regtest)]
+    #[case("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5", Network::Mainnet)]
+    // bech32 checksum on a v1 program (must be bech32m)
+    #[case(
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+        Network::Mainnet
+    )]
+    fn test_invalid_addresses(#[case] address: &str, #[case] network: Network) {
+        assert!(Address::parse(address, network).is_err());
+    }
+
+    #[rstest]
+    fn test_no_address_for_null_data() {
+        assert!(Address::from_script_pubkey(&[0x6a, 0x01, 0x01], Network::Mainnet).is_err());
+    }
+}
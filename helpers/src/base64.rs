@@ -0,0 +1,75 @@
+use crate::errors::Base64Error;
+
+/// Standard (RFC 4648) alphabet with `=` padding, used by PSBTs and BIP322 signatures.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+pub fn decode(encoded: &str) -> Result<Vec<u8>, Base64Error> {
+    if !encoded.len().is_multiple_of(4) {
+        return Err(Base64Error::InvalidLength(encoded.len()));
+    }
+
+    let chunks = encoded.as_bytes().chunks(4);
+    let last = chunks.len().saturating_sub(1);
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+    for (index, chunk) in chunks.enumerate() {
+        let padding = chunk.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 || (padding > 0 && index != last) {
+            return Err(Base64Error::MisplacedPadding);
+        }
+        let mut group = 0u32;
+        for (i, c) in chunk[..4 - padding].iter().enumerate() {
+            let sextet = ALPHABET
+                .iter()
+                .position(|a| a == c)
+                .ok_or(Base64Error::InvalidCharacter(*c as char))?;
+            group |= (sextet as u32) << (18 - 6 * i);
+        }
+        bytes.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test_base64 {
+    use crate::base64::{decode, encode};
+    use crate::errors::Base64Error;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("", "")]
+    #[case("f", "Zg==")]
+    #[case("fo", "Zm8=")]
+    #[case("foo", "Zm9v")]
+    #[case("foobar", "Zm9vYmFy")]
+    fn test_base64(#[case] decoded: &str, #[case] encoded: &str) -> color_eyre::Result<()> {
+        assert_eq!(encode(decoded.as_bytes()), encoded);
+        assert_eq!(decode(encoded)?, decoded.as_bytes());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("Zg=", Base64Error::InvalidLength(3))]
+    #[case("Zg==Zg==", Base64Error::MisplacedPadding)]
+    #[case("Z===", Base64Error::MisplacedPadding)]
+    #[case("Zm9*", Base64Error::InvalidCharacter('*'))]
+    fn test_invalid_base64(#[case] encoded: &str, #[case] error: Base64Error) {
+        assert_eq!(decode(encoded), Err(error));
+    }
+}
@@ -0,0 +1,575 @@
+use crate::address::Address;
+use crate::errors::Bip32Error;
+use crate::hashes::hash160;
+use crate::keys::{PrivateKey, PublicKey};
+use crate::network::Network;
+use crate::schnorr::taproot_output_key;
+use hmac::{Hmac, Mac};
+use k256::ecdsa::{SigningKey, VerifyingKey};
+use k256::elliptic_curve::PrimeField;
+use k256::{ProjectivePoint, Scalar};
+use sha2::Sha512;
+use std::fmt;
+use std::str::FromStr;
+
+type HmacSha512 = Hmac<Sha512>;
+
+/// First hardened child index.
+pub const HARDENED: u32 = 0x8000_0000;
+
+const XPRV_MAINNET: u32 = 0x0488_ade4;
+const XPUB_MAINNET: u32 = 0x0488_b21e;
+const XPRV_TESTNET: u32 = 0x0435_8394;
+const XPUB_TESTNET: u32 = 0x0435_87cf;
+
+/// Length of a serialized extended key (before the base58check encoding).
+const EXTENDED_KEY_LENGTH: usize = 78;
+
+/// A child index, `m/0'` is `ChildNumber(HARDENED)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChildNumber(pub u32);
+
+impl ChildNumber {
+    pub fn normal(index: u32) -> ChildNumber {
+        ChildNumber(index & !HARDENED)
+    }
+
+    pub fn hardened(index: u32) -> ChildNumber {
+        ChildNumber(index | HARDENED)
+    }
+
+    pub fn is_hardened(&self) -> bool {
+        self.0 & HARDENED != 0
+    }
+}
+
+impl fmt::Display for ChildNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_hardened() {
+            write!(f, "{}'", self.0 & !HARDENED)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+/// A derivation path such as `m/84'/0'/0'/0/1` (`h` and `'` both mark hardened steps).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DerivationPath(pub Vec<ChildNumber>);
+
+impl FromStr for DerivationPath {
+    type Err = Bip32Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split('/');
+        if parts.next() != Some("m") {
+            return Err(Bip32Error::InvalidPath(s.to_string()));
+        }
+
+        parts
+            .map(|part| {
+                let (index, hardened) = match part.strip_suffix(['\'', 'h', 'H']) {
+                    Some(index) => (index, true),
+                    None => (part, false),
+                };
+                match index.parse::<u32>() {
+                    Ok(index) if index < HARDENED && hardened => Ok(ChildNumber::hardened(index)),
+                    Ok(index) if index < HARDENED => Ok(ChildNumber::normal(index)),
+                    _ => Err(Bip32Error::InvalidPath(s.to_string())),
+                }
+            })
+            .collect::<Result<Vec<ChildNumber>, Bip32Error>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for child in &self.0 {
+            write!(f, "/{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+/// Standard single key wallet layouts and the output type each one uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Purpose {
+    /// BIP44, P2PKH
+    Bip44,
+    /// BIP49, P2SH-P2WPKH
+    Bip49,
+    /// BIP84, P2WPKH
+    Bip84,
+    /// BIP86, P2TR key path only
+    Bip86,
+}
+
+impl Purpose {
+    pub fn number(&self) -> u32 {
+        match self {
+            Purpose::Bip44 => 44,
+            Purpose::Bip49 => 49,
+            Purpose::Bip84 => 84,
+            Purpose::Bip86 => 86,
+        }
+    }
+
+    /// `m / purpose' / coin_type' / account' / change / index`
+    pub fn path(&self, network: Network, account: u32, change: bool, index: u32) -> DerivationPath {
+        let coin_type = if network == Network::Mainnet { 0 } else { 1 };
+        DerivationPath(vec![
+            ChildNumber::hardened(self.number()),
+            ChildNumber::hardened(coin_type),
+            ChildNumber::hardened(account),
+            ChildNumber::normal(change as u32),
+            ChildNumber::normal(index),
+        ])
+    }
+
+    /// Address of a key derived on this purpose's path.
+    pub fn address(&self, public_key: &PublicKey, network: Network) -> Address {
+        let compressed = public_key.compressed_bytes();
+        match self {
+            Purpose::Bip44 => Address::p2pkh(&compressed, network),
+            Purpose::Bip49 => Address::p2sh(
+                &Address::p2wpkh(&compressed, network).script_pubkey(),
+                network,
+            ),
+            Purpose::Bip84 => Address::p2wpkh(&compressed, network),
+            Purpose::Bip86 => {
+                let output_key = taproot_output_key(&public_key.x_only(), None)
+                    .expect("tweaking a valid key without a merkle root can not fail");
+                Address::p2tr(&output_key, network)
+            }
+        }
+    }
+}
+
+impl FromStr for Purpose {
+    type Err = Bip32Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().trim_start_matches("bip") {
+            "44" => Ok(Purpose::Bip44),
+            "49" => Ok(Purpose::Bip49),
+            "84" => Ok(Purpose::Bip84),
+            "86" => Ok(Purpose::Bip86),
+            _ => Err(Bip32Error::InvalidPath(format!("unknown purpose {}", s))),
+        }
+    }
+}
+
+/// Fields shared by extended private and public keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyInfo {
+    pub network: Network,
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: ChildNumber,
+    pub chain_code: [u8; 32],
+}
+
+/// BIP32 extended private key (`xprv` / `tprv`).
+#[derive(Debug, Clone)]
+pub struct Xpriv {
+    pub info: KeyInfo,
+    pub private_key: SigningKey,
+}
+
+/// BIP32 extended public key (`xpub` / `tpub`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xpub {
+    pub info: KeyInfo,
+    pub public_key: VerifyingKey,
+}
+
+impl Xpriv {
+    /// Master key of a seed: `HMAC-SHA512(key = "Bitcoin seed", seed)`.
+    pub fn new_master(seed: &[u8], network: Network) -> Result<Xpriv, Bip32Error> {
+        if !(16..=64).contains(&seed.len()) {
+            return Err(Bip32Error::InvalidSeedLength(seed.len()));
+        }
+        let (key, chain_code) = hmac_sha512(b"Bitcoin seed", &[seed]);
+        let private_key = SigningKey::from_slice(&key).map_err(|_| Bip32Error::InvalidChild)?;
+
+        Ok(Xpriv {
+            info: KeyInfo {
+                network,
+                depth: 0,
+                parent_fingerprint: [0; 4],
+                child_number: ChildNumber(0),
+                chain_code,
+            },
+            private_key,
+        })
+    }
+
+    pub fn derive_child(&self, child: ChildNumber) -> Result<Xpriv, Bip32Error> {
+        let public_key = self.private_key.verifying_key().to_encoded_point(true);
+        let secret = self.private_key.to_bytes();
+        let index = child.0.to_be_bytes();
+
+        let (tweak, chain_code) = if child.is_hardened() {
+            hmac_sha512(&self.info.chain_code, &[&[0], &secret, &index])
+        } else {
+            hmac_sha512(&self.info.chain_code, &[public_key.as_bytes(), &index])
+        };
+
+        let tweak = parse_scalar(&tweak)?;
+        let parent: Scalar = *self.private_key.as_nonzero_scalar().as_ref();
+        let private_key = SigningKey::from_bytes(&(parent + tweak).to_bytes())
+            .map_err(|_| Bip32Error::InvalidChild)?;
+
+        Ok(Xpriv {
+            info: self.info.child(self.fingerprint(), child, chain_code)?,
+            private_key,
+        })
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Xpriv, Bip32Error> {
+        path.0
+            .iter()
+            .try_fold(self.clone(), |key, child| key.derive_child(*child))
+    }
+
+    pub fn to_xpub(&self) -> Xpub {
+        Xpub {
+            info: self.info,
+            public_key: *self.private_key.verifying_key(),
+        }
+    }
+
+    /// The derived key as a (compressed) [`PrivateKey`].
+    pub fn to_private_key(&self) -> PrivateKey {
+        PrivateKey::new(self.private_key.clone(), self.info.network)
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        self.to_xpub().fingerprint()
+    }
+}
+
+impl Xpub {
+    /// Public derivation, only possible for normal (non hardened) children.
+    pub fn derive_child(&self, child: ChildNumber) -> Result<Xpub, Bip32Error> {
+        if child.is_hardened() {
+            return Err(Bip32Error::HardenedFromPublic);
+        }
+        let public_key = self.public_key.to_encoded_point(true);
+        let (tweak, chain_code) = hmac_sha512(
+            &self.info.chain_code,
+            &[public_key.as_bytes(), &child.0.to_be_bytes()],
+        );
+
+        let tweak = parse_scalar(&tweak)?;
+        let point = ProjectivePoint::from(*self.public_key.as_affine())
+            + ProjectivePoint::GENERATOR * tweak;
+        let public_key =
+            VerifyingKey::from_affine(point.to_affine()).map_err(|_| Bip32Error::InvalidChild)?;
+
+        Ok(Xpub {
+            info: self.info.child(self.fingerprint(), child, chain_code)?,
+            public_key,
+        })
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Xpub, Bip32Error> {
+        path.0
+            .iter()
+            .try_fold(*self, |key, child| key.derive_child(*child))
+    }
+
+    pub fn to_public_key(&self) -> PublicKey {
+        PublicKey {
+            key: self.public_key,
+            compressed: true,
+        }
+    }
+
+    /// First 4 bytes of the hash160 of the public key.
+    pub fn fingerprint(&self) -> [u8; 4] {
+        hash160(self.public_key.to_encoded_point(true).as_bytes())[..4]
+            .try_into()
+            .unwrap()
+    }
+}
+
+impl KeyInfo {
+    /// Fields of a child key, the depth is a single byte so depth 255 keys have no children.
+    fn child(
+        &self,
+        parent_fingerprint: [u8; 4],
+        child: ChildNumber,
+        chain_code: [u8; 32],
+    ) -> Result<Self, Bip32Error> {
+        Ok(KeyInfo {
+            network: self.network,
+            depth: self
+                .depth
+                .checked_add(1)
+                .ok_or(Bip32Error::MaxDepthExceeded)?,
+            parent_fingerprint,
+            child_number: child,
+            chain_code,
+        })
+    }
+
+    fn serialize(&self, version: u32, key: &[u8]) -> String {
+        let mut data = Vec::with_capacity(EXTENDED_KEY_LENGTH);
+        data.extend_from_slice(&version.to_be_bytes());
+        data.push(self.depth);
+        data.extend_from_slice(&self.parent_fingerprint);
+        data.extend_from_slice(&self.child_number.0.to_be_bytes());
+        data.extend_from_slice(&self.chain_code);
+        data.extend_from_slice(key);
+        bs58::encode(data).with_check().into_string()
+    }
+
+    /// Decodes a base58check extended key into its version, info and 33 key bytes.
+    fn deserialize(s: &str) -> Result<(u32, KeyInfo, Vec<u8>), Bip32Error> {
+        let data = bs58::decode(s)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| Bip32Error::InvalidExtendedKey(e.to_string()))?;
+        if data.len() != EXTENDED_KEY_LENGTH {
+            return Err(Bip32Error::InvalidExtendedKey(format!(
+                "expected {} bytes, found {}",
+                EXTENDED_KEY_LENGTH,
+                data.len()
+            )));
+        }
+
+        let version = u32::from_be_bytes(data[0..4].try_into().unwrap());
+        let network = match version {
+            XPRV_MAINNET | XPUB_MAINNET => Network::Mainnet,
+            XPRV_TESTNET | XPUB_TESTNET => Network::Testnet,
+            _ => return Err(Bip32Error::UnknownVersion(version)),
+        };
+        let info = KeyInfo {
+            network,
+            depth: data[4],
+            parent_fingerprint: data[5..9].try_into().unwrap(),
+            child_number: ChildNumber(u32::from_be_bytes(data[9..13].try_into().unwrap())),
+            chain_code: data[13..45].try_into().unwrap(),
+        };
+        if info.depth == 0 && (info.parent_fingerprint != [0; 4] || info.child_number.0 != 0) {
+            return Err(Bip32Error::InvalidExtendedKey(
+                "master key with a parent".to_string(),
+            ));
+        }
+        Ok((version, info, data[45..].to_vec()))
+    }
+}
+
+impl fmt::Display for Xpriv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let version = match self.info.network {
+            Network::Mainnet => XPRV_MAINNET,
+            _ => XPRV_TESTNET,
+        };
+        let mut key = vec![0];
+        key.extend_from_slice(&self.private_key.to_bytes());
+        write!(f, "{}", self.info.serialize(version, &key))
+    }
+}
+
+impl fmt::Display for Xpub {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let version = match self.info.network {
+            Network::Mainnet => XPUB_MAINNET,
+            _ => XPUB_TESTNET,
+        };
+        let key = self.public_key.to_encoded_point(true);
+        write!(f, "{}", self.info.serialize(version, key.as_bytes()))
+    }
+}
+
+impl FromStr for Xpriv {
+    type Err = Bip32Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (version, info, key) = KeyInfo::deserialize(s)?;
+        if version != XPRV_MAINNET && version != XPRV_TESTNET {
+            return Err(Bip32Error::UnknownVersion(version));
+        }
+        if key[0] != 0 {
+            return Err(Bip32Error::InvalidExtendedKey(
+                "private key must be prefixed with 0x00".to_string(),
+            ));
+        }
+        let private_key = SigningKey::from_slice(&key[1..])
+            .map_err(|e| Bip32Error::InvalidExtendedKey(e.to_string()))?;
+        Ok(Xpriv { info, private_key })
+    }
+}
+
+impl FromStr for Xpub {
+    type Err = Bip32Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (version, info, key) = KeyInfo::deserialize(s)?;
+        if version != XPUB_MAINNET && version != XPUB_TESTNET {
+            return Err(Bip32Error::UnknownVersion(version));
+        }
+        let public_key = VerifyingKey::from_sec1_bytes(&key)
+            .map_err(|e| Bip32Error::InvalidExtendedKey(e.to_string()))?;
+        Ok(Xpub { info, public_key })
+    }
+}
+
+/// Splits `HMAC-SHA512(key, data...)` into its left and right 32 bytes.
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = HmacSha512::new_from_slice(key).expect("hmac accepts keys of any size");
+    for part in data {
+        mac.update(part);
+    }
+    let result = mac.finalize().into_bytes();
+    (
+        result[..32].try_into().unwrap(),
+        result[32..].try_into().unwrap(),
+    )
+}
+
+fn parse_scalar(bytes: &[u8; 32]) -> Result<Scalar, Bip32Error> {
+    let scalar: Option<Scalar> = Scalar::from_repr((*bytes).into()).into();
+    scalar.ok_or(Bip32Error::InvalidChild)
+}
+
+#[cfg(test)]
+mod test_bip32 {
+    use crate::bip32::{ChildNumber, DerivationPath, Purpose, Xpriv, Xpub};
+    use crate::bip39::Mnemonic;
+    use crate::errors::Bip32Error;
+    use crate::network::Network;
+    use rstest::rstest;
+
+    const VECTOR_1_SEED: &str = "000102030405060708090a0b0c0d0e0f";
+    const VECTOR_3_SEED: &str = "4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be";
+    const ABANDON: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    // BIP32 test vectors 1 and 3.
+    #[rstest]
+    #[case(
+        VECTOR_1_SEED,
+        "m",
+        "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+        "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi"
+    )]
+    #[case(
+        VECTOR_1_SEED,
+        "m/0H",
+        "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+        "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7"
+    )]
+    #[case(
+        VECTOR_1_SEED,
+        "m/0'/1/2'/2/1000000000",
+        "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+        "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76"
+    )]
+    #[case(
+        VECTOR_3_SEED,
+        "m",
+        "xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13",
+        "xprv9s21ZrQH143K25QhxbucbDDuQ4naNntJRi4KUfWT7xo4EKsHt2QJDu7KXp1A3u7Bi1j8ph3EGsZ9Xvz9dGuVrtHHs7pXeTzjuxBrCmmhgC6"
+    )]
+    #[case(
+        VECTOR_3_SEED,
+        "m/0H",
+        "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y",
+        "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L"
+    )]
+    fn test_bip32_vectors(
+        #[case] seed: &str,
+        #[case] path: &str,
+        #[case] xpub: &str,
+        #[case] xprv: &str,
+    ) -> color_eyre::Result<()> {
+        let master = Xpriv::new_master(&hex::decode(seed)?, Network::Mainnet)?;
+        let derived = master.derive_path(&path.parse()?)?;
+        assert_eq!(derived.to_string(), xprv);
+        assert_eq!(derived.to_xpub().to_string(), xpub);
+
+        // Round trip through the serialization.
+        assert_eq!(xprv.parse::<Xpriv>()?.to_string(), xprv);
+        assert_eq!(xpub.parse::<Xpub>()?, derived.to_xpub());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_public_derivation_matches_private() -> color_eyre::Result<()> {
+        let master = Xpriv::new_master(&hex::decode(VECTOR_1_SEED)?, Network::Testnet)?;
+        let account = master.derive_path(&"m/0'/1".parse()?)?;
+        let path: DerivationPath = "m/2/7".parse()?;
+
+        let from_private = account.derive_path(&path)?.to_xpub();
+        let from_public = account.to_xpub().derive_path(&path)?;
+        assert_eq!(from_private, from_public);
+        assert!(from_public.to_string().starts_with("tpub"));
+
+        assert_eq!(
+            account.to_xpub().derive_path(&"m/1'".parse()?),
+            Err(Bip32Error::HardenedFromPublic)
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_max_depth() -> color_eyre::Result<()> {
+        let mut key = Xpriv::new_master(&hex::decode(VECTOR_1_SEED)?, Network::Mainnet)?;
+        key.info.depth = 254;
+        let key = key.derive_child(ChildNumber(0))?;
+        assert_eq!(key.info.depth, 255);
+
+        assert_eq!(
+            key.derive_child(ChildNumber(0)).err(),
+            Some(Bip32Error::MaxDepthExceeded)
+        );
+        assert_eq!(
+            key.to_xpub().derive_child(ChildNumber(0)),
+            Err(Bip32Error::MaxDepthExceeded)
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("m/0'/a")]
+    #[case("0/1")]
+    #[case("m/2147483648")]
+    fn test_invalid_paths(#[case] path: &str) {
+        assert!(path.parse::<DerivationPath>().is_err());
+    }
+
+    // First receiving address of the BIP44 / 49 / 84 / 86 documents for the "abandon ... about" mnemonic.
+    #[rstest]
+    #[case(Purpose::Bip44, Network::Mainnet, "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA")]
+    #[case(
+        Purpose::Bip49,
+        Network::Testnet,
+        "2Mww8dCYPUpKHofjgcXcBCEGmniw9CoaiD2"
+    )]
+    #[case(
+        Purpose::Bip84,
+        Network::Mainnet,
+        "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+    )]
+    #[case(
+        Purpose::Bip86,
+        Network::Mainnet,
+        "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+    )]
+    fn test_standard_paths(
+        #[case] purpose: Purpose,
+        #[case] network: Network,
+        #[case] address: &str,
+    ) -> color_eyre::Result<()> {
+        let seed = ABANDON.parse::<Mnemonic>()?.to_seed("");
+        let master = Xpriv::new_master(&seed, network)?;
+        let key = master.derive_path(&purpose.path(network, 0, false, 0))?;
+        let public_key = key.to_private_key().public_key();
+        assert_eq!(purpose.address(&public_key, network).to_string(), address);
+        Ok(())
+    }
+}
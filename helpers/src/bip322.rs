@@ -0,0 +1,583 @@
+// BIP322 generic signed messages.
+//
+// A message is signed by spending a virtual `to_spend` transaction, whose only output pays to the
+// address, with a virtual `to_sign` transaction. The signature is the witness of that spend
+// (simple), the whole `to_sign` transaction (full) or, for P2PKH addresses only, Bitcoin Core's
+// `signmessage` signature (legacy).
+//
+// Signing goes through the PSBT signer / finalizer and verification runs the `to_sign` input
+// through bee-vm's `verify_input`, so any address a real transaction can spend can sign a message.
+
+use crate::base64;
+use crate::errors::Bip322Error;
+use crate::hashes::{hash160, sha256d, tagged_hash};
+use crate::keys::PrivateKey;
+use crate::psbt::finalize::{extract, finalize};
+use crate::psbt::sign::sign as sign_psbt;
+use crate::psbt::{Input, Psbt};
+use bee_vm::script::classify::{classify, witness_program, ScriptType};
+use bee_vm::transaction::encode::{write_bytes, write_compact_size, Reader};
+use bee_vm::transaction::verify::verify_input;
+use bee_vm::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use std::fmt;
+use std::str::FromStr;
+
+pub const MESSAGE_TAG: &str = "BIP0322-signed-message";
+
+/// Prefix of the messages signed by Bitcoin Core's `signmessage`.
+const LEGACY_MAGIC: &[u8] = b"Bitcoin Signed Message:\n";
+/// Header byte of a compact signature : 27 + recovery id (+ 4 for a compressed key).
+const LEGACY_HEADER: u8 = 27;
+const LEGACY_SIGNATURE_SIZE: usize = 65;
+
+/// OP_RETURN, the only output of `to_sign`.
+const TO_SIGN_SCRIPT: [u8; 1] = [0x6a];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Bitcoin Core `signmessage` signature (P2PKH addresses).
+    Legacy,
+    /// Witness of the `to_sign` input (segwit addresses).
+    Simple,
+    /// Whole `to_sign` transaction (any address).
+    Full,
+}
+
+impl Format {
+    /// Shortest format able to hold a signature for `script_pubkey`.
+    pub fn default_for(script_pubkey: &[u8]) -> Format {
+        match classify(script_pubkey) {
+            ScriptType::P2PKH { .. } => Format::Legacy,
+            _ if witness_program(script_pubkey).is_some() => Format::Simple,
+            _ => Format::Full,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Format::Legacy => "legacy",
+            Format::Simple => "simple",
+            Format::Full => "full",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Format {
+    type Err = Bip322Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "legacy" => Ok(Format::Legacy),
+            "simple" => Ok(Format::Simple),
+            "full" => Ok(Format::Full),
+            _ => Err(Bip322Error::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+pub fn message_hash(message: &[u8]) -> [u8; 32] {
+    tagged_hash(MESSAGE_TAG, message)
+}
+
+/// Virtual transaction paying to `script_pubkey`, its input commits to the message.
+pub fn to_spend(script_pubkey: &[u8], message: &[u8]) -> Transaction {
+    // OP_0 PUSH32 <message hash>
+    let mut script_sig = vec![0x00, 0x20];
+    script_sig.extend_from_slice(&message_hash(message));
+
+    Transaction {
+        version: 0,
+        inputs: vec![TxIn {
+            previous_output: OutPoint {
+                txid: [0; 32],
+                vout: u32::MAX,
+            },
+            script_sig,
+            sequence: 0,
+            witness: vec![],
+        }],
+        outputs: vec![TxOut {
+            value: 0,
+            script_pubkey: script_pubkey.to_vec(),
+        }],
+        lock_time: 0,
+    }
+}
+
+/// Unsigned virtual transaction spending the output of `to_spend`.
+pub fn to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: 0,
+        inputs: vec![TxIn::new(
+            OutPoint {
+                txid: to_spend.txid(),
+                vout: 0,
+            },
+            0,
+        )],
+        outputs: vec![TxOut {
+            value: 0,
+            script_pubkey: TO_SIGN_SCRIPT.to_vec(),
+        }],
+        lock_time: 0,
+    }
+}
+
+/// Signs `message` for `script_pubkey` and returns the base64 signature.
+///
+/// `input` carries what the keys alone do not tell (redeem / witness scripts, taproot merkle root
+/// or leaves), the same way a PSBT updater fills an input. A P2SH-P2WPKH redeem script is found
+/// from the keys when it is not given.
+pub fn sign(
+    script_pubkey: &[u8],
+    message: &[u8],
+    keys: &[PrivateKey],
+    input: Input,
+    format: Format,
+) -> Result<String, Bip322Error> {
+    if format == Format::Legacy {
+        let ScriptType::P2PKH { pubkey_hash } = classify(script_pubkey) else {
+            return Err(Bip322Error::UnsupportedFormat(format));
+        };
+        let key = keys
+            .iter()
+            .find(|key| key.public_key().hash160() == pubkey_hash)
+            .ok_or(Bip322Error::NoSigningKey)?;
+        return Ok(base64::encode(&sign_legacy(key, message)?));
+    }
+
+    let to_spend = to_spend(script_pubkey, message);
+    let mut psbt = Psbt::from_unsigned_tx(&to_sign(&to_spend), 0)?;
+    let mut input = Input {
+        previous_output: psbt.inputs[0].previous_output,
+        sequence: psbt.inputs[0].sequence,
+        non_witness_utxo: Some(to_spend.clone()),
+        witness_utxo: Some(to_spend.outputs[0].clone()),
+        ..input
+    };
+    if let ScriptType::P2SH { script_hash } = classify(script_pubkey) {
+        if input.redeem_script.is_none() {
+            input.redeem_script = keys
+                .iter()
+                .map(|key| [vec![0x00, 0x14], key.public_key().hash160().to_vec()].concat())
+                .find(|redeem_script| hash160(redeem_script) == script_hash);
+        }
+    }
+    psbt.inputs[0] = input;
+
+    if sign_psbt(&mut psbt, keys)? == 0 {
+        return Err(Bip322Error::NoSigningKey);
+    }
+    finalize(&mut psbt)?;
+    let to_sign = extract(&psbt)?;
+
+    match format {
+        Format::Simple => {
+            if !to_sign.inputs[0].script_sig.is_empty() {
+                return Err(Bip322Error::UnsupportedFormat(format));
+            }
+            Ok(base64::encode(&serialize_witness(
+                &to_sign.inputs[0].witness,
+            )))
+        }
+        _ => Ok(base64::encode(&to_sign.serialize())),
+    }
+}
+
+/// Verifies a base64 signature of `message` for `script_pubkey` and returns its format.
+///
+/// 65 bytes signatures of P2PKH addresses are legacy signatures, the others are a witness stack
+/// (simple) or a `to_sign` transaction (full).
+pub fn verify(
+    script_pubkey: &[u8],
+    message: &[u8],
+    signature: &str,
+) -> Result<Format, Bip322Error> {
+    let signature = base64::decode(signature.trim())?;
+    if let ScriptType::P2PKH { pubkey_hash } = classify(script_pubkey) {
+        if signature.len() == LEGACY_SIGNATURE_SIZE {
+            verify_legacy(&pubkey_hash, message, &signature)?;
+            return Ok(Format::Legacy);
+        }
+    }
+    // Witness versions 2 to 16 are not defined yet, anything would spend them.
+    if let Some((version, _)) = witness_program(script_pubkey) {
+        if version > 1 {
+            return Err(Bip322Error::Inconclusive(format!(
+                "witness version {} is not defined",
+                version
+            )));
+        }
+    }
+
+    let to_spend = to_spend(script_pubkey, message);
+    let (to_sign, format) = match deserialize_witness(&signature) {
+        Some(witness) => {
+            let mut to_sign = to_sign(&to_spend);
+            to_sign.inputs[0].witness = witness;
+            (to_sign, Format::Simple)
+        }
+        None => {
+            let to_sign = Transaction::deserialize(&signature)
+                .map_err(|e| Bip322Error::InvalidSignature(e.to_string()))?;
+            check_to_sign(&to_sign, &to_spend)?;
+            (to_sign, Format::Full)
+        }
+    };
+
+    verify_input(&to_sign, 0, &to_spend.outputs).map_err(|e| Bip322Error::Verify(e.to_string()))?;
+    Ok(format)
+}
+
+/// A full signature may pick its version, locktime and sequence but must spend `to_spend` and
+/// pay to OP_RETURN only.
+fn check_to_sign(to_sign: &Transaction, to_spend: &Transaction) -> Result<(), Bip322Error> {
+    let spent = OutPoint {
+        txid: to_spend.txid(),
+        vout: 0,
+    };
+    match to_sign.inputs.as_slice() {
+        [input] if input.previous_output == spent => {}
+        [input, ..] if input.previous_output == spent => {
+            // Proof of funds : the extra inputs spend real UTXOs we do not know.
+            return Err(Bip322Error::Inconclusive(
+                "proof of funds inputs can not be verified without their UTXOs".to_string(),
+            ));
+        }
+        _ => {
+            return Err(Bip322Error::InvalidToSign(
+                "the first input does not spend to_spend".to_string(),
+            ))
+        }
+    }
+    let expected_output = TxOut {
+        value: 0,
+        script_pubkey: TO_SIGN_SCRIPT.to_vec(),
+    };
+    if to_sign.outputs != [expected_output] {
+        return Err(Bip322Error::InvalidToSign(
+            "the only output must be a 0 value OP_RETURN".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Hash signed by Bitcoin Core's `signmessage` : sha256d(magic | message), both length prefixed.
+pub fn legacy_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut data = vec![];
+    write_bytes(&mut data, LEGACY_MAGIC);
+    write_bytes(&mut data, message);
+    sha256d(&data)
+}
+
+fn sign_legacy(key: &PrivateKey, message: &[u8]) -> Result<Vec<u8>, Bip322Error> {
+    let (signature, recovery_id) = key
+        .signing_key
+        .sign_prehash_recoverable(&legacy_message_hash(message))
+        .map_err(|e| Bip322Error::Signing(e.to_string()))?;
+
+    let mut header = LEGACY_HEADER + recovery_id.to_byte();
+    if key.compressed {
+        header += 4;
+    }
+    Ok([vec![header], signature.to_bytes().to_vec()].concat())
+}
+
+fn verify_legacy(
+    pubkey_hash: &[u8; 20],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), Bip322Error> {
+    let header = signature[0]
+        .checked_sub(LEGACY_HEADER)
+        .filter(|header| *header < 8)
+        .ok_or_else(|| {
+            Bip322Error::InvalidSignature(format!("invalid legacy header {}", signature[0]))
+        })?;
+    let recovery_id = RecoveryId::from_byte(header & 3).expect("recovery id is below 4");
+    let compressed = header & 4 != 0;
+    let invalid = |e: k256::ecdsa::Error| Bip322Error::InvalidSignature(e.to_string());
+
+    let public_key = VerifyingKey::recover_from_prehash(
+        &legacy_message_hash(message),
+        &Signature::from_slice(&signature[1..]).map_err(invalid)?,
+        recovery_id,
+    )
+    .map_err(invalid)?;
+    let public_key = public_key.to_encoded_point(compressed);
+    if hash160(public_key.as_bytes()) != *pubkey_hash {
+        return Err(Bip322Error::LegacyKeyMismatch);
+    }
+    Ok(())
+}
+
+/// Consensus encoding of a witness stack : item count then length prefixed items.
+fn serialize_witness(witness: &[Vec<u8>]) -> Vec<u8> {
+    let mut buffer = vec![];
+    write_compact_size(&mut buffer, witness.len() as u64);
+    for item in witness {
+        write_bytes(&mut buffer, item);
+    }
+    buffer
+}
+
+/// Reads a witness stack, `None` when the bytes are not exactly one.
+fn deserialize_witness(bytes: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut reader = Reader::new(bytes);
+    let count = reader.read_compact_size().ok()?;
+    // Every item takes at least one byte, this bounds the allocation.
+    if count as usize > reader.remaining() {
+        return None;
+    }
+    let witness = (0..count)
+        .map(|_| reader.read_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    (reader.remaining() == 0 && !witness.is_empty()).then_some(witness)
+}
+
+#[cfg(test)]
+mod test_bip322 {
+    use crate::address::Address;
+    use crate::bip322::{message_hash, sign, to_sign, to_spend, verify, Format};
+    use crate::errors::Bip322Error;
+    use crate::hashes::sha256;
+    use crate::keys::PrivateKey;
+    use crate::network::Network;
+    use crate::psbt::Input;
+    use bee_vm::transaction::display_hash;
+    use rstest::rstest;
+
+    // BIP322 test vectors.
+    const P2WPKH_ADDRESS: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+    const P2TR_ADDRESS: &str = "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3";
+    const PRIVATE_KEY: &str = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k";
+
+    fn script_pubkey(address: &str) -> Vec<u8> {
+        Address::decode(address).unwrap().script_pubkey()
+    }
+
+    #[rstest]
+    #[case(
+        "",
+        "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1",
+        "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7",
+        "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"
+    )]
+    #[case(
+        "Hello World",
+        "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a",
+        "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b",
+        "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf"
+    )]
+    fn test_virtual_transactions(
+        #[case] message: &str,
+        #[case] hash: &str,
+        #[case] to_spend_txid: &str,
+        #[case] to_sign_txid: &str,
+    ) {
+        assert_eq!(hex::encode(message_hash(message.as_bytes())), hash);
+        let to_spend = to_spend(&script_pubkey(P2WPKH_ADDRESS), message.as_bytes());
+        assert_eq!(display_hash(&to_spend.txid()), to_spend_txid);
+        assert_eq!(display_hash(&to_sign(&to_spend).txid()), to_sign_txid);
+    }
+
+    #[rstest]
+    #[case(P2WPKH_ADDRESS, "", "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=", Format::Simple)]
+    #[case(P2WPKH_ADDRESS, "Hello World", "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=", Format::Simple)]
+    #[case(P2WPKH_ADDRESS, "Hello World", "AkgwRQIhAOzyynlqt93lOKJr+wmmxIens//zPzl9tqIOua93wO6MAiBi5n5EyAcPScOjf1lAqIUIQtr3zKNeavYabHyR8eGhowEhAsfxIAMZZEKUPYWI4BruhAQjzFT8FSFSajuFwrDL1Yhy", Format::Simple)]
+    #[case(P2TR_ADDRESS, "Hello World", "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==", Format::Simple)]
+    // Bitcoin Core `signmessage` signatures (compressed and uncompressed keys).
+    #[case(
+        "14vV3aCHBeStb5bkenkNHbe2YAFinYdXgc",
+        "Hello World",
+        "IOW2xi+ebJLeBtr674l4QH76dqDoVjLV80R9EFKFQX5rBrlCXPIZaYs8Yuayg0ZqjyiCbLy9pzZIS7JWT65/nsU=",
+        Format::Legacy
+    )]
+    #[case(
+        "1HZwkjkeaoZfTSaJxDw6aKkxp45agDiEzN",
+        "",
+        "HMfld3DXJYm4QLME3v3ozNslNn7Bf/JsmJam1KDw8o5oAcF7kH8a5IXfCPcqetRw2bU9EtrFPNgCfzkdWvW5sCY=",
+        Format::Legacy
+    )]
+    fn test_verify(
+        #[case] address: &str,
+        #[case] message: &str,
+        #[case] signature: &str,
+        #[case] format: Format,
+    ) {
+        let script_pubkey = script_pubkey(address);
+        assert_eq!(
+            verify(&script_pubkey, message.as_bytes(), signature),
+            Ok(format)
+        );
+        assert!(verify(&script_pubkey, b"Another message", signature).is_err());
+    }
+
+    #[rstest]
+    #[case(
+        PRIVATE_KEY,
+        "Hello World",
+        "IOW2xi+ebJLeBtr674l4QH76dqDoVjLV80R9EFKFQX5rBrlCXPIZaYs8Yuayg0ZqjyiCbLy9pzZIS7JWT65/nsU="
+    )]
+    #[case(
+        "5KYZdUEo39z3FPrtuX2QbbwGnNP5zTd7yyr2SC1j299sBCnWjss",
+        "",
+        "HMfld3DXJYm4QLME3v3ozNslNn7Bf/JsmJam1KDw8o5oAcF7kH8a5IXfCPcqetRw2bU9EtrFPNgCfzkdWvW5sCY="
+    )]
+    fn test_sign_legacy(
+        #[case] key: &str,
+        #[case] message: &str,
+        #[case] signature: &str,
+    ) -> color_eyre::Result<()> {
+        let key = PrivateKey::from_wif(key)?;
+        let address = Address::p2pkh(&key.public_key().to_bytes(), Network::Mainnet);
+        let signed = sign(
+            &address.script_pubkey(),
+            message.as_bytes(),
+            &[key],
+            Input::default(),
+            Format::Legacy,
+        )?;
+        // RFC6979 nonces : the same signature as Bitcoin Core.
+        assert_eq!(signed, signature);
+        Ok(())
+    }
+
+    #[rstest]
+    // RFC6979 nonce without low R grinding : the second BIP322 P2WPKH signature.
+    #[case("p2wpkh", Format::Simple, Some("AkgwRQIhAOzyynlqt93lOKJr+wmmxIens//zPzl9tqIOua93wO6MAiBi5n5EyAcPScOjf1lAqIUIQtr3zKNeavYabHyR8eGhowEhAsfxIAMZZEKUPYWI4BruhAQjzFT8FSFSajuFwrDL1Yhy"))]
+    #[case("p2wpkh", Format::Full, None)]
+    #[case("p2pkh", Format::Full, None)]
+    #[case("p2sh-p2wpkh", Format::Full, None)]
+    // Schnorr signatures use random auxiliary data.
+    #[case("p2tr", Format::Simple, None)]
+    fn test_sign_single_key(
+        #[case] address_type: &str,
+        #[case] format: Format,
+        #[case] expected: Option<&str>,
+    ) -> color_eyre::Result<()> {
+        let key = PrivateKey::from_wif(PRIVATE_KEY)?;
+        let (_, address) = key
+            .public_key()
+            .addresses(Network::Mainnet)
+            .into_iter()
+            .find(|(name, _)| *name == address_type)
+            .unwrap();
+        let script_pubkey = address.script_pubkey();
+
+        let signature = sign(
+            &script_pubkey,
+            b"Hello World",
+            &[key],
+            Input::default(),
+            format,
+        )?;
+        assert_eq!(
+            verify(&script_pubkey, b"Hello World", &signature),
+            Ok(format)
+        );
+        if let Some(expected) = expected {
+            assert_eq!(signature, expected);
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_sign_script() -> color_eyre::Result<()> {
+        // 2-of-2 multisig P2WSH : both keys sign the same to_sign input.
+        let alice = PrivateKey::from_seed(b"alice", Network::Mainnet);
+        let bob = PrivateKey::from_seed(b"bob", Network::Mainnet);
+        let witness_script = [
+            vec![0x52, 0x21],
+            alice.public_key().to_bytes(),
+            vec![0x21],
+            bob.public_key().to_bytes(),
+            vec![0x52, 0xae],
+        ]
+        .concat();
+        let script_pubkey = [vec![0x00, 0x20], sha256(&witness_script).to_vec()].concat();
+        let input = Input {
+            witness_script: Some(witness_script),
+            ..Default::default()
+        };
+
+        let signature = sign(
+            &script_pubkey,
+            b"2 of 2",
+            &[alice.clone(), bob],
+            input.clone(),
+            Format::Simple,
+        )?;
+        assert_eq!(
+            verify(&script_pubkey, b"2 of 2", &signature),
+            Ok(Format::Simple)
+        );
+
+        // One signature is not enough to finalize the spend.
+        assert!(sign(&script_pubkey, b"2 of 2", &[alice], input, Format::Simple).is_err());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_invalid() -> color_eyre::Result<()> {
+        let key = PrivateKey::from_wif(PRIVATE_KEY)?;
+        let p2wpkh = script_pubkey(P2WPKH_ADDRESS);
+        let sign_with = |script_pubkey: &[u8], format| {
+            sign(
+                script_pubkey,
+                b"",
+                std::slice::from_ref(&key),
+                Input::default(),
+                format,
+            )
+        };
+
+        assert_eq!(
+            sign_with(&p2wpkh, Format::Legacy),
+            Err(Bip322Error::UnsupportedFormat(Format::Legacy))
+        );
+        let p2sh_p2wpkh = Address::p2sh(
+            &[[0x00, 0x14].as_slice(), &key.public_key().hash160()].concat(),
+            Network::Mainnet,
+        );
+        assert_eq!(
+            sign_with(&p2sh_p2wpkh.script_pubkey(), Format::Simple),
+            Err(Bip322Error::UnsupportedFormat(Format::Simple))
+        );
+        let bob = PrivateKey::from_seed(b"bob", Network::Mainnet);
+        let bob_p2wpkh = Address::p2wpkh(&bob.public_key().to_bytes(), Network::Mainnet);
+        assert_eq!(
+            sign_with(&bob_p2wpkh.script_pubkey(), Format::Simple),
+            Err(Bip322Error::NoSigningKey)
+        );
+
+        // Legacy signature of another key.
+        let other = script_pubkey("1HZwkjkeaoZfTSaJxDw6aKkxp45agDiEzN");
+        assert_eq!(
+            verify(&other, b"Hello World", "IOW2xi+ebJLeBtr674l4QH76dqDoVjLV80R9EFKFQX5rBrlCXPIZaYs8Yuayg0ZqjyiCbLy9pzZIS7JWT65/nsU="),
+            Err(Bip322Error::LegacyKeyMismatch)
+        );
+
+        // A full signature whose to_sign pays to an address.
+        let mut to_sign = to_sign(&to_spend(&p2wpkh, b""));
+        to_sign.outputs[0].script_pubkey = p2wpkh.clone();
+        assert!(matches!(
+            verify(&p2wpkh, b"", &crate::base64::encode(&to_sign.serialize())),
+            Err(Bip322Error::InvalidToSign(_))
+        ));
+
+        // Anything spends a witness v2 output.
+        let mut v2 = p2wpkh.clone();
+        v2[0] = 0x52;
+        assert!(matches!(
+            verify(&v2, b"", "AA=="),
+            Err(Bip322Error::Inconclusive(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use crate::errors::MnemonicError;
+use crate::hashes::sha256;
+use k256::elliptic_curve::rand_core::{OsRng, RngCore};
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha512;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use unicode_normalization::UnicodeNormalization;
+
+/// BIP39 english wordlist (sha256 `2f5eed53a4727b4bf8880d8f3f199efc90e58503646d9ff8eff3a2ed3b24dbda`).
+const ENGLISH: &str = include_str!("wordlists/english.txt");
+
+const PBKDF2_ROUNDS: u32 = 2048;
+
+fn wordlist() -> &'static Vec<&'static str> {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| ENGLISH.lines().collect())
+}
+
+/// A BIP39 mnemonic sentence (english wordlist).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mnemonic {
+    entropy: Vec<u8>,
+}
+
+impl Mnemonic {
+    /// Builds the mnemonic of 16, 20, 24, 28 or 32 bytes of entropy.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Mnemonic, MnemonicError> {
+        if !(16..=32).contains(&entropy.len()) || !entropy.len().is_multiple_of(4) {
+            return Err(MnemonicError::InvalidEntropyLength(entropy.len()));
+        }
+        Ok(Mnemonic {
+            entropy: entropy.to_vec(),
+        })
+    }
+
+    /// Generates a random mnemonic of the given number of words.
+    pub fn generate(word_count: usize) -> Result<Mnemonic, MnemonicError> {
+        if !(12..=24).contains(&word_count) || !word_count.is_multiple_of(3) {
+            return Err(MnemonicError::InvalidWordCount(word_count));
+        }
+        let mut entropy = vec![0u8; word_count / 3 * 4];
+        OsRng.fill_bytes(&mut entropy);
+        Mnemonic::from_entropy(&entropy)
+    }
+
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    pub fn words(&self) -> Vec<&'static str> {
+        let checksum_bits = self.entropy.len() / 4;
+        let mut bits = self.entropy.clone();
+        bits.push(sha256(&self.entropy)[0]);
+
+        (0..(self.entropy.len() * 8 + checksum_bits) / 11)
+            .map(|i| {
+                let index = (0..11).fold(0usize, |index, bit| {
+                    let position = i * 11 + bit;
+                    let value = (bits[position / 8] >> (7 - position % 8)) & 1;
+                    (index << 1) | value as usize
+                });
+                wordlist()[index]
+            })
+            .collect()
+    }
+
+    /// BIP39 seed: PBKDF2-HMAC-SHA512 of the sentence, salted with `"mnemonic" || passphrase`.
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let sentence: String = self.to_string().nfkd().collect();
+        let salt: String = format!("mnemonic{}", passphrase).nfkd().collect();
+
+        let mut seed = [0u8; 64];
+        pbkdf2_hmac::<Sha512>(
+            sentence.as_bytes(),
+            salt.as_bytes(),
+            PBKDF2_ROUNDS,
+            &mut seed,
+        );
+        seed
+    }
+}
+
+impl FromStr for Mnemonic {
+    type Err = MnemonicError;
+
+    /// Parses a sentence and checks its checksum.
+    fn from_str(sentence: &str) -> Result<Self, Self::Err> {
+        let words: Vec<String> = sentence
+            .split_whitespace()
+            .map(|word| word.nfkd().collect::<String>().to_lowercase())
+            .collect();
+        if !(12..=24).contains(&words.len()) || !words.len().is_multiple_of(3) {
+            return Err(MnemonicError::InvalidWordCount(words.len()));
+        }
+
+        let mut bits: Vec<bool> = Vec::with_capacity(words.len() * 11);
+        for word in &words {
+            let index = wordlist()
+                .binary_search(&word.as_str())
+                .map_err(|_| MnemonicError::UnknownWord(word.clone()))?;
+            bits.extend((0..11).rev().map(|bit| (index >> bit) & 1 == 1));
+        }
+
+        let checksum_bits = words.len() / 3;
+        let entropy_bits = bits.len() - checksum_bits;
+        let entropy: Vec<u8> = bits[..entropy_bits]
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, bit| (acc << 1) | *bit as u8))
+            .collect();
+
+        let mnemonic = Mnemonic::from_entropy(&entropy)?;
+        let expected = sha256(&entropy)[0] >> (8 - checksum_bits);
+        let found = bits[entropy_bits..]
+            .iter()
+            .fold(0u8, |acc, bit| (acc << 1) | *bit as u8);
+        if expected != found {
+            return Err(MnemonicError::InvalidChecksum);
+        }
+        Ok(mnemonic)
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.words().join(" "))
+    }
+}
+
+#[cfg(test)]
+mod test_bip39 {
+    use crate::bip39::Mnemonic;
+    use crate::errors::MnemonicError;
+    use rstest::rstest;
+
+    // Vectors from the reference implementation (trezor/python-mnemonic), passphrase "TREZOR".
+    #[rstest]
+    #[case(
+        "00000000000000000000000000000000",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+    )]
+    #[case(
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "legal winner thank year wave sausage worth useful legal winner thank yellow",
+        "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607"
+    )]
+    #[case(
+        "8080808080808080808080808080808080808080808080808080808080808080",
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic bless",
+        "c0c519bd0e91a2ed54357d9d1ebef6f5af218a153624cf4f2da911a0ed8f7a09e2ef61af0aca007096df430022f7a2b6fb91661a9589097069720d015e4e982f"
+    )]
+    #[case(
+        "ffffffffffffffffffffffffffffffffffffffffffffffff",
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo when",
+        "0cd6e5d827bb62eb8fc1e262254223817fd068a74b5b449cc2f667c3f1f985a76379b43348d952e2265b4cd129090758b3e3c2c49103b5051aac2eaeb890a528"
+    )]
+    fn test_trezor_vectors(
+        #[case] entropy: &str,
+        #[case] sentence: &str,
+        #[case] seed: &str,
+    ) -> color_eyre::Result<()> {
+        let mnemonic = Mnemonic::from_entropy(&hex::decode(entropy)?)?;
+        assert_eq!(mnemonic.to_string(), sentence);
+        assert_eq!(sentence.parse::<Mnemonic>()?, mnemonic);
+        assert_eq!(hex::encode(mnemonic.to_seed("TREZOR")), seed);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon", MnemonicError::InvalidChecksum)]
+    #[case("abandon abandon abandon", MnemonicError::InvalidWordCount(3))]
+    #[case("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon bitcoins", MnemonicError::UnknownWord("bitcoins".to_string()))]
+    fn test_invalid_mnemonics(#[case] sentence: &str, #[case] error: MnemonicError) {
+        assert_eq!(sentence.parse::<Mnemonic>(), Err(error));
+    }
+
+    #[rstest]
+    fn test_generate() -> color_eyre::Result<()> {
+        let mnemonic = Mnemonic::generate(24)?;
+        assert_eq!(mnemonic.words().len(), 24);
+        assert_eq!(mnemonic.to_string().parse::<Mnemonic>()?, mnemonic);
+        assert!(Mnemonic::generate(13).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use bee_vm::script::asm::to_asm;
+use clap::{Arg, ArgMatches, Command};
+use helpers::address::Address;
+use helpers::network::Network;
+
+pub fn command() -> Command {
+    Command::new("address")
+        .about("Converts between addresses and scriptPubKeys")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("encode")
+                .about("Encodes a scriptPubKey (hex) as an address")
+                .arg(
+                    Arg::new("script")
+                        .short('s')
+                        .long("script")
+                        .help("The scriptPubKey in hex")
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(network_arg()),
+        )
+        .subcommand(
+            Command::new("decode")
+                .about("Decodes an address into its scriptPubKey")
+                .arg(
+                    Arg::new("address")
+                        .help("The address to decode")
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(network_arg()),
+        )
+}
+
+pub fn network_arg() -> Arg {
+    Arg::new("network")
+        .short('n')
+        .long("network")
+        .help("mainnet, testnet, signet or regtest")
+        .default_value("mainnet")
+        .num_args(1)
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    match matches.subcommand() {
+        Some(("encode", sub_matches)) => {
+            let network: Network = sub_matches.get_one::<String>("network").unwrap().parse()?;
+            let script = hex::decode(sub_matches.get_one::<String>("script").unwrap())?;
+            let address = Address::from_script_pubkey(&script, network)?;
+            print_address(&address);
+        }
+        Some(("decode", sub_matches)) => {
+            let network: Network = sub_matches.get_one::<String>("network").unwrap().parse()?;
+            let address =
+                Address::parse(sub_matches.get_one::<String>("address").unwrap(), network)?;
+            print_address(&address);
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+    Ok(())
+}
+
+fn print_address(address: &Address) {
+    let script = address.script_pubkey();
+    println!("Address      : {}", address);
+    println!("Type         : {}", address.address_type());
+    println!("Network      : {}", address.network);
+    println!("ScriptPubKey : {}", hex::encode(&script));
+    println!("ASM          : {}", to_asm(&script));
+}
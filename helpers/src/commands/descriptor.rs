@@ -0,0 +1,99 @@
+use crate::commands::address::network_arg;
+use bee_vm::script::asm::to_asm;
+use clap::{Arg, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::descriptor::{Descriptor, WitnessItem};
+use helpers::network::Network;
+
+pub fn command() -> Command {
+    Command::new("descriptor")
+        .about("Expands an output descriptor (BIP380 - BIP386) into scripts, addresses and spend templates")
+        .arg(
+            Arg::new("descriptor")
+                .help("Descriptor, e.g. \"wsh(multi(2,<key>,<key>))\" (the #checksum is optional)")
+                .required(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("range")
+                .short('r')
+                .long("range")
+                .help("Index or range of indexes (<start>..<end>, end excluded) of ranged keys")
+                .default_value("0")
+                .num_args(1),
+        )
+        .arg(network_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let network: Network = matches.get_one::<String>("network").unwrap().parse()?;
+    let descriptor = Descriptor::parse(matches.get_one::<String>("descriptor").unwrap())?;
+    let range = matches.get_one::<String>("range").unwrap();
+    let (start, end) = match range.split_once("..") {
+        Some((start, end)) => (start.parse::<u32>()?, end.parse::<u32>()?),
+        None => {
+            let index = range.parse::<u32>()?;
+            (index, index + 1)
+        }
+    };
+    if start >= end {
+        return Err(eyre!("Empty range : {}", range));
+    }
+
+    println!("Descriptor    : {}", descriptor.to_string_with_checksum());
+    println!("Ranged        : {}", descriptor.is_ranged());
+
+    // Non ranged descriptors are the same at every index.
+    let end = if descriptor.is_ranged() {
+        end
+    } else {
+        start + 1
+    };
+    for index in start..end {
+        println!();
+        if descriptor.is_ranged() {
+            println!("Index {}", index);
+        }
+        let script_pubkey = descriptor.script_pubkey(index)?;
+        println!("  scriptPubKey  : {}", hex::encode(&script_pubkey));
+        println!("  Script        : {}", to_asm(&script_pubkey));
+        match descriptor.address(index, network) {
+            Ok(address) => println!("  Address       : {}", address),
+            Err(_) => println!("  Address       : none (bare script)"),
+        }
+        if let Some(redeem_script) = descriptor.redeem_script(index)? {
+            println!("  Redeem Script : {}", to_asm(&redeem_script));
+        }
+        if let Some(witness_script) = descriptor.witness_script(index)? {
+            println!("  Witness Script: {}", to_asm(&witness_script));
+        }
+        if let Some(info) = descriptor.taproot_spend_info(index)? {
+            println!("  Internal Key  : {}", hex::encode(info.internal_key));
+            if let Some(root) = info.merkle_root {
+                println!("  Merkle Root   : {}", hex::encode(root));
+            }
+            for (leaf_index, leaf) in info.leaves.iter().enumerate() {
+                println!("  Leaf {:<9}: {}", leaf_index, to_asm(&leaf.script));
+            }
+        }
+
+        for template in descriptor.spend_templates(index)? {
+            println!("  Spend ({})", template.name);
+            if !template.script_sig.is_empty() {
+                println!("    scriptSig   : {}", items(&template.script_sig));
+            }
+            if !template.witness.is_empty() {
+                println!("    Witness     : {}", items(&template.witness));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn items(items: &[WitnessItem]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
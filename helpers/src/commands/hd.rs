@@ -0,0 +1,200 @@
+use crate::commands::address::network_arg;
+use crate::commands::keys::print_key;
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::bip32::{DerivationPath, Purpose, Xpriv, Xpub};
+use helpers::bip39::Mnemonic;
+use helpers::network::Network;
+
+pub fn command() -> Command {
+    Command::new("hd")
+        .about("BIP32 HD keys and BIP39 mnemonics")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("mnemonic")
+                .about("Generates a random BIP39 mnemonic")
+                .arg(
+                    Arg::new("words")
+                        .short('w')
+                        .long("words")
+                        .help("Number of words (12, 15, 18, 21 or 24)")
+                        .default_value("12")
+                        .value_parser(clap::value_parser!(usize))
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("derive")
+                .about("Derives a child key from a mnemonic, a seed or an extended key")
+                .arg(
+                    Arg::new("mnemonic")
+                        .long("mnemonic")
+                        .help("BIP39 mnemonic sentence")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("passphrase")
+                        .long("passphrase")
+                        .help("BIP39 passphrase (used with --mnemonic)")
+                        .default_value("")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .long("seed")
+                        .help("Hex encoded BIP32 seed")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("xkey")
+                        .long("xkey")
+                        .help("Extended private or public key (xprv / xpub / tprv / tpub)")
+                        .num_args(1),
+                )
+                .group(
+                    ArgGroup::new("source")
+                        .args(["mnemonic", "seed", "xkey"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("path")
+                        .short('p')
+                        .long("path")
+                        .help("Derivation path, e.g. m/84'/0'/0'/0/0")
+                        .conflicts_with("purpose")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("purpose")
+                        .long("purpose")
+                        .help("Standard path to use: 44 (p2pkh), 49 (p2sh-p2wpkh), 84 (p2wpkh) or 86 (p2tr)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("account")
+                        .long("account")
+                        .default_value("0")
+                        .value_parser(clap::value_parser!(u32))
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("index")
+                        .long("index")
+                        .default_value("0")
+                        .value_parser(clap::value_parser!(u32))
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("change")
+                        .long("change")
+                        .help("Uses the internal (change) chain of the standard path")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(network_arg()),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    match matches.subcommand() {
+        Some(("mnemonic", sub_matches)) => {
+            let mnemonic = Mnemonic::generate(*sub_matches.get_one::<usize>("words").unwrap())?;
+            println!("Mnemonic : {}", mnemonic);
+            println!("Entropy  : {}", hex::encode(mnemonic.entropy()));
+            Ok(())
+        }
+        Some(("derive", sub_matches)) => derive(sub_matches),
+        _ => unreachable!("subcommand is required"),
+    }
+}
+
+fn derive(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let network: Network = matches.get_one::<String>("network").unwrap().parse()?;
+    let purpose = matches
+        .get_one::<String>("purpose")
+        .map(|purpose| purpose.parse::<Purpose>())
+        .transpose()?;
+    let path: DerivationPath = match (purpose, matches.get_one::<String>("path")) {
+        (Some(purpose), _) => purpose.path(
+            network,
+            *matches.get_one::<u32>("account").unwrap(),
+            matches.get_flag("change"),
+            *matches.get_one::<u32>("index").unwrap(),
+        ),
+        (None, Some(path)) => path.parse()?,
+        (None, None) => DerivationPath::default(),
+    };
+
+    let master = if let Some(sentence) = matches.get_one::<String>("mnemonic") {
+        let mnemonic: Mnemonic = sentence.parse()?;
+        let passphrase = matches.get_one::<String>("passphrase").unwrap();
+        Xpriv::new_master(&mnemonic.to_seed(passphrase), network)?
+    } else if let Some(seed) = matches.get_one::<String>("seed") {
+        Xpriv::new_master(&hex::decode(seed)?, network)?
+    } else {
+        let xkey = matches.get_one::<String>("xkey").unwrap();
+        if let Ok(xpub) = xkey.parse::<Xpub>() {
+            return print_xpub(&xpub.derive_path(&path)?, &path, purpose, network);
+        }
+        xkey.parse::<Xpriv>()
+            .map_err(|e| eyre!("{} is not an extended key : {}", xkey, e))?
+    };
+
+    let derived = master.derive_path(&path)?;
+    println!(
+        "Master Fingerprint      : {}",
+        hex::encode(master.fingerprint())
+    );
+    println!("Path                    : {}", path);
+    println!("Xprv                    : {}", derived);
+    println!("Xpub                    : {}", derived.to_xpub());
+    if let Some(purpose) = purpose {
+        let public_key = derived.to_private_key().public_key();
+        println!(
+            "BIP{} Address           : {}",
+            purpose.number(),
+            purpose.address(&public_key, network)
+        );
+    }
+    println!();
+
+    let mut key = derived.to_private_key();
+    key.network = network;
+    print_key(&key)
+}
+
+fn print_xpub(
+    xpub: &Xpub,
+    path: &DerivationPath,
+    purpose: Option<Purpose>,
+    network: Network,
+) -> color_eyre::Result<()> {
+    let public_key = xpub.to_public_key();
+    println!("Path                    : {}", path);
+    println!("Xpub                    : {}", xpub);
+    println!(
+        "Public Key (compressed) : {}",
+        hex::encode(public_key.compressed_bytes())
+    );
+    println!(
+        "Public Key (x-only)     : {}",
+        hex::encode(public_key.x_only())
+    );
+    println!(
+        "Hash160                 : {}",
+        hex::encode(public_key.hash160())
+    );
+    match purpose {
+        Some(purpose) => println!(
+            "BIP{} Address           : {}",
+            purpose.number(),
+            purpose.address(&public_key, network)
+        ),
+        None => {
+            println!();
+            for (name, address) in public_key.addresses(network) {
+                println!("{:<24}: {}", name, address);
+            }
+        }
+    }
+    Ok(())
+}
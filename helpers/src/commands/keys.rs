@@ -0,0 +1,114 @@
+use crate::commands::address::network_arg;
+use bee_vm::script::decode::bytes_to_tokens;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use helpers::address::Address;
+use helpers::keys::PrivateKey;
+use helpers::network::Network;
+
+pub fn command() -> Command {
+    Command::new("keys")
+        .about("Generates, imports and inspects keys (WIF, compressed / uncompressed / x-only)")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("generate")
+                .about("Generates a key, randomly or deterministically from a seed")
+                .arg(
+                    Arg::new("seed")
+                        .short('s')
+                        .long("seed")
+                        .help("Derives the key from this seed (same seed, same key)")
+                        .num_args(1),
+                )
+                .arg(uncompressed_arg())
+                .arg(network_arg()),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Imports a WIF or hex private key and prints every derived form")
+                .arg(
+                    Arg::new("private_key")
+                        .help("The private key (WIF or 32 bytes hex)")
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(uncompressed_arg())
+                .arg(network_arg()),
+        )
+}
+
+fn uncompressed_arg() -> Arg {
+    Arg::new("uncompressed")
+        .short('u')
+        .long("uncompressed")
+        .help("Uses the uncompressed public key for the WIF and the P2PKH fixture")
+        .action(ArgAction::SetTrue)
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let (name, sub_matches) = matches.subcommand().expect("subcommand is required");
+    let network: Network = sub_matches.get_one::<String>("network").unwrap().parse()?;
+
+    let mut key = match name {
+        "generate" => match sub_matches.get_one::<String>("seed") {
+            Some(seed) => PrivateKey::from_seed(seed.as_bytes(), network),
+            None => PrivateKey::random(network),
+        },
+        "import" => {
+            let key = sub_matches.get_one::<String>("private_key").unwrap();
+            let mut key = PrivateKey::from_str_with_network(key, network)?;
+            // Testnet, signet and regtest share the WIF version byte.
+            if key.network.wif_version() == network.wif_version() {
+                key.network = network;
+            }
+            key
+        }
+        _ => unreachable!("unknown keys subcommand"),
+    };
+    if sub_matches.get_flag("uncompressed") {
+        key.compressed = false;
+    }
+
+    print_key(&key)
+}
+
+pub fn print_key(key: &PrivateKey) -> color_eyre::Result<()> {
+    let public_key = key.public_key();
+
+    println!("Network                 : {}", key.network);
+    println!("Private Key (hex)       : {}", hex::encode(key.to_bytes()));
+    println!("Private Key (WIF)       : {}", key.to_wif());
+    println!(
+        "Public Key (compressed) : {}",
+        hex::encode(public_key.compressed_bytes())
+    );
+    println!(
+        "Public Key (uncompr.)   : {}",
+        hex::encode(public_key.uncompressed_bytes())
+    );
+    println!(
+        "Public Key (x-only)     : {}",
+        hex::encode(public_key.x_only())
+    );
+    println!(
+        "Hash160                 : {}",
+        hex::encode(public_key.hash160())
+    );
+
+    println!();
+    for (name, address) in public_key.addresses(key.network) {
+        println!("{:<24}: {}", name, address);
+    }
+
+    // Ready to paste bee-vm scripts for this key.
+    let lock = Address::p2pkh(&public_key.to_bytes(), key.network);
+    println!();
+    println!(
+        "bee-vm P2PKH lock       : {}",
+        bytes_to_tokens(&lock.script_pubkey())?.join(" ")
+    );
+    println!(
+        "bee-vm P2PK lock        : {} OP_CHECKSIG",
+        hex::encode(public_key.to_bytes())
+    );
+    Ok(())
+}
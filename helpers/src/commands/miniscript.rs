@@ -0,0 +1,192 @@
+use crate::commands::address::network_arg;
+use bee_vm::script::asm::to_asm;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::address::Address;
+use helpers::keys::PrivateKey;
+use helpers::miniscript::satisfy::spend;
+use helpers::miniscript::{replace_key_names, Miniscript, ScriptContext};
+use helpers::network::Network;
+use helpers::taproot::{TapTree, TaprootSpendInfo, UNSPENDABLE_KEY};
+
+pub fn command() -> Command {
+    Command::new("miniscript")
+        .about("Type checks and compiles a miniscript, and satisfies it in bee-vm")
+        .arg(
+            Arg::new("expression")
+                .help("Miniscript, e.g. \"and_v(v:pk(A),older(144))\" with A named by --key or --sign")
+                .required(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("tap")
+                .long("tap")
+                .help("Tapscript leaf (under the BIP341 unspendable key) instead of P2WSH")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("insane")
+                .long("insane")
+                .help("Accepts miniscripts that are valid but not sane (malleable, no signature...)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("key")
+                .short('k')
+                .long("key")
+                .help("Names a public / extended key as NAME=<key> (repeatable)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("index")
+                .short('i')
+                .long("index")
+                .help("Index of ranged keys")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("0")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("satisfy")
+                .long("satisfy")
+                .help("Satisfies the miniscript and spends it in a test transaction checked by bee-vm")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("sign")
+                .short('p')
+                .long("sign")
+                .help("Private key signing the satisfaction, as [NAME=]<WIF> (repeatable)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("preimage")
+                .long("preimage")
+                .help("Hash preimage (hex) available to the satisfaction (repeatable)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("sequence")
+                .long("sequence")
+                .help("nSequence of the spending input, checked by older()")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("4294967295")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("lock_time")
+                .long("lock-time")
+                .help("nLockTime of the spending transaction, checked by after()")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("0")
+                .num_args(1),
+        )
+        .arg(network_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let network: Network = matches.get_one::<String>("network").unwrap().parse()?;
+    let context = match matches.get_flag("tap") {
+        true => ScriptContext::Tap,
+        false => ScriptContext::Segwitv0,
+    };
+    let index = *matches.get_one::<u32>("index").unwrap();
+
+    let mut names = vec![];
+    for key in matches.get_many::<String>("key").unwrap_or_default() {
+        let (name, key) = key
+            .split_once('=')
+            .ok_or(eyre!("Expected NAME=<key> : {}", key))?;
+        names.push((name.to_string(), key.to_string()));
+    }
+    let mut private_keys = vec![];
+    for key in matches.get_many::<String>("sign").unwrap_or_default() {
+        let (name, wif) = match key.split_once('=') {
+            Some((name, wif)) => (Some(name), wif),
+            None => (None, key.as_str()),
+        };
+        let private_key = PrivateKey::from_str_with_network(wif, network)?;
+        if let Some(name) = name {
+            let public_key = private_key.public_key().compressed_bytes();
+            names.push((name.to_string(), hex::encode(public_key)));
+        }
+        private_keys.push(private_key);
+    }
+
+    let expression = replace_key_names(matches.get_one::<String>("expression").unwrap(), &names);
+    let miniscript = match matches.get_flag("insane") {
+        true => Miniscript::parse_insane(&expression, context)?,
+        false => Miniscript::parse(&expression, context)?,
+    };
+    let script = miniscript.encode(index)?;
+
+    println!("Miniscript    : {}", miniscript);
+    println!("Context       : {}", context);
+    println!("Type          : {}", miniscript.ty);
+    let reasons = miniscript.insane_reasons();
+    match reasons.is_empty() {
+        true => println!("Sane          : yes"),
+        false => println!("Sane          : no, {}", reasons.join(", ")),
+    }
+    println!("Script        : {}", to_asm(&script));
+    println!("Script Hex    : {}", hex::encode(&script));
+    match context {
+        ScriptContext::Segwitv0 => {
+            println!(
+                "Size          : {} bytes, {} ops",
+                script.len(),
+                miniscript.ops_count(index)?
+            );
+            println!("Address       : {}", Address::p2wsh(&script, network));
+        }
+        ScriptContext::Tap => {
+            println!("Size          : {} bytes", script.len());
+            let tree = TapTree::leaf(script.clone());
+            let info = TaprootSpendInfo::new(&UNSPENDABLE_KEY, Some(&tree))?;
+            println!("Address       : {}", info.address(network));
+        }
+    }
+
+    if matches.get_flag("satisfy") {
+        let preimages = matches
+            .get_many::<String>("preimage")
+            .unwrap_or_default()
+            .map(hex::decode)
+            .collect::<Result<Vec<_>, _>>()?;
+        let sequence = *matches.get_one::<u32>("sequence").unwrap();
+        let lock_time = *matches.get_one::<u32>("lock_time").unwrap();
+        let tx = spend(
+            &miniscript,
+            index,
+            &private_keys,
+            &preimages,
+            sequence,
+            lock_time,
+        )?;
+
+        println!();
+        println!(
+            "Satisfaction (nSequence {}, nLockTime {})",
+            sequence, lock_time
+        );
+        let witness = &tx.inputs[0].witness;
+        // The witness script (or leaf script and control block) follows the script inputs.
+        let inputs = match context {
+            ScriptContext::Segwitv0 => &witness[..witness.len() - 1],
+            ScriptContext::Tap => &witness[..witness.len() - 2],
+        };
+        for (position, item) in inputs.iter().enumerate() {
+            let item = match item.is_empty() {
+                true => "<empty>".to_string(),
+                false => hex::encode(item),
+            };
+            println!("  Input {:<8}: {}", position, item);
+        }
+        println!("  Transaction   : {}", tx.to_hex());
+        println!("  Verified      : OK (bee-vm)");
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+// ==================================================
+// CLI subcommands
+// ==================================================
+//
+// Every subcommand lives in its own module and exposes `command()` (the clap definition)
+// and `run()` (called with the subcommand matches).
+
+pub mod address;
+pub mod descriptor;
+pub mod hd;
+pub mod keys;
+pub mod miniscript;
+pub mod musig;
+pub mod policy;
+pub mod psbt;
+pub mod schnorr;
+pub mod sign;
+pub mod sign_tx;
+pub mod taptree;
+pub mod tx;
+pub mod verify;
@@ -0,0 +1,187 @@
+use crate::commands::address::network_arg;
+use bee_vm::transaction::context::ExecutionContext;
+use bee_vm::transaction::sighash::SIGHASH_DEFAULT;
+use bee_vm::transaction::verify::verify_input;
+use bee_vm::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::address::Address;
+use helpers::keys::PrivateKey;
+use helpers::musig2::{nonce_agg, nonce_gen, KeyAggContext, NonceInputs, Session};
+use helpers::network::Network;
+
+/// Fee of the simulated key path spend.
+const FEE: u64 = 1_000;
+
+pub fn command() -> Command {
+    Command::new("musig")
+        .about("Simulates a MuSig2 (BIP327) session spending a taproot output by its key path")
+        .arg(
+            Arg::new("private_key")
+                .short('p')
+                .long("private-key")
+                .help("Private key of a signer, WIF or 32 bytes hex (repeatable)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("parties")
+                .long("parties")
+                .help("Number of signers with keys derived from the seeds party-1, party-2...")
+                .conflicts_with("private_key")
+                .value_parser(clap::value_parser!(usize))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("merkle_root")
+                .long("merkle-root")
+                .help(
+                    "Merkle root of the output's script tree (hex, default : BIP86 key path only)",
+                )
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("prevout")
+                .long("prevout")
+                .help("Outpoint of the spent output as <txid>:<vout>")
+                .default_value("0000000000000000000000000000000000000000000000000000000000000001:0")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("amount")
+                .short('a')
+                .long("amount")
+                .help("Amount of the spent output in satoshis")
+                .default_value("100000")
+                .value_parser(clap::value_parser!(u64))
+                .num_args(1),
+        )
+        .arg(network_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let network: Network = matches.get_one::<String>("network").unwrap().parse()?;
+    let mut keys = match (
+        matches.get_many::<String>("private_key"),
+        matches.get_one::<usize>("parties"),
+    ) {
+        (Some(keys), _) => keys
+            .map(|key| PrivateKey::from_str_with_network(key, network))
+            .collect::<Result<Vec<_>, _>>()?,
+        (None, Some(&parties)) if parties > 0 => (1..=parties)
+            .map(|party| PrivateKey::from_seed(format!("party-{}", party).as_bytes(), network))
+            .collect(),
+        _ => {
+            return Err(eyre!(
+                "Give the signers with -p or their number with --parties"
+            ))
+        }
+    };
+    let merkle_root = matches
+        .get_one::<String>("merkle_root")
+        .map(|root| -> color_eyre::Result<[u8; 32]> {
+            hex::decode(root)?
+                .try_into()
+                .map_err(|_| eyre!("The merkle root must be 32 bytes"))
+        })
+        .transpose()?;
+
+    // Key aggregation, every signer sorts the keys the same way (BIP327 KeySort).
+    keys.sort_by_key(|key| key.public_key().compressed_bytes());
+    let public_keys: Vec<[u8; 33]> = keys
+        .iter()
+        .map(|key| key.public_key().compressed_bytes())
+        .collect();
+    let mut key_agg = KeyAggContext::new(&public_keys)?;
+    let internal_key = key_agg.x_only_key();
+    key_agg.apply_taproot_tweak(merkle_root.as_ref())?;
+    let output_key = key_agg.x_only_key();
+    let address = Address::p2tr(&output_key, network);
+
+    for (index, public_key) in public_keys.iter().enumerate() {
+        println!("Signer {:<6} : {}", index + 1, hex::encode(public_key));
+    }
+    println!("Internal Key  : {}", hex::encode(internal_key));
+    println!("Output Key    : {}", hex::encode(output_key));
+    println!("Address       : {}", address);
+
+    // The transaction spends the output back to the same address.
+    let (txid, vout) = matches
+        .get_one::<String>("prevout")
+        .unwrap()
+        .split_once(':')
+        .ok_or(eyre!("The prevout must be <txid>:<vout>"))?;
+    let amount = *matches.get_one::<u64>("amount").unwrap();
+    let spent_output = TxOut {
+        value: amount,
+        script_pubkey: address.script_pubkey(),
+    };
+    let mut tx = Transaction {
+        version: 2,
+        inputs: vec![TxIn::new(
+            OutPoint::from_display_txid(txid, vout.parse()?)?,
+            0xffff_fffd,
+        )],
+        outputs: vec![TxOut {
+            value: amount
+                .checked_sub(FEE)
+                .ok_or(eyre!("The amount must cover the {} sats fee", FEE))?,
+            script_pubkey: address.script_pubkey(),
+        }],
+        lock_time: 0,
+    };
+    let prevouts = vec![spent_output];
+    let context = ExecutionContext::taproot(tx.clone(), 0, prevouts.clone(), None)?;
+    let sighash = context.signature_hash(&[], SIGHASH_DEFAULT as u32, u32::MAX)?;
+    println!("Sighash       : {}", hex::encode(sighash));
+
+    // Round 1 : every signer publishes its public nonce.
+    println!();
+    let mut signers = vec![];
+    for (index, key) in keys.iter().enumerate() {
+        let secret_key = key.to_bytes();
+        let public_key = key.public_key().compressed_bytes();
+        let inputs = NonceInputs {
+            secret_key: Some(&secret_key),
+            aggregate_key: Some(&output_key),
+            message: Some(&sighash),
+            extra_input: None,
+        };
+        let (secret_nonce, public_nonce) = nonce_gen(&public_key, &inputs)?;
+        println!("Nonce {:<7} : {}", index + 1, hex::encode(public_nonce));
+        signers.push((key, public_key, secret_nonce, public_nonce));
+    }
+    let public_nonces: Vec<_> = signers.iter().map(|signer| signer.3).collect();
+    let aggregate_nonce = nonce_agg(&public_nonces)?;
+    println!("Aggregate     : {}", hex::encode(aggregate_nonce));
+
+    // Round 2 : partial signatures, checked by the aggregator.
+    println!();
+    let session = Session::new(&key_agg, &aggregate_nonce, &sighash)?;
+    let mut partial_signatures = vec![];
+    for (index, (key, public_key, secret_nonce, public_nonce)) in signers.into_iter().enumerate() {
+        let partial_signature = session.sign(secret_nonce, &key.to_bytes())?;
+        if !session.verify_partial(&partial_signature, &public_nonce, &public_key) {
+            return Err(eyre!("Invalid partial signature of signer {}", index + 1));
+        }
+        println!(
+            "Partial {:<5} : {}",
+            index + 1,
+            hex::encode(partial_signature)
+        );
+        partial_signatures.push(partial_signature);
+    }
+
+    let signature = session.aggregate(&partial_signatures)?;
+    if !context.check_taproot_key_path(&signature)? {
+        return Err(eyre!("bee-vm rejected the aggregated signature"));
+    }
+    tx.inputs[0].witness = vec![signature.to_vec()];
+    verify_input(&tx, 0, &prevouts)?;
+
+    println!();
+    println!("Signature     : {}", hex::encode(signature));
+    println!("Transaction   : {}", tx.to_hex());
+    println!("Verified      : OK (bee-vm taproot key path)");
+    Ok(())
+}
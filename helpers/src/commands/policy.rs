@@ -0,0 +1,177 @@
+use crate::commands::address::network_arg;
+use bee_vm::script::asm::to_asm;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::address::Address;
+use helpers::errors::PolicyError;
+use helpers::keys::PrivateKey;
+use helpers::miniscript::policy::{spend_path, witness_size, Policy};
+use helpers::miniscript::{replace_key_names, ScriptContext};
+use helpers::network::Network;
+use helpers::taproot::{TapTree, TaprootSpendInfo, UNSPENDABLE_KEY};
+
+pub fn command() -> Command {
+    Command::new("policy")
+        .about("Compiles a spending policy to the cheapest miniscript and spends every path in bee-vm")
+        .arg(
+            Arg::new("expression")
+                .help("Policy, e.g. \"or(99@pk(A),and(pk(B),after(1000)))\" with A and B named by --key or --sign")
+                .required(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("tap")
+                .long("tap")
+                .help("Tapscript leaf (under the BIP341 unspendable key) instead of P2WSH")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("key")
+                .short('k')
+                .long("key")
+                .help("Names a public / extended key as NAME=<key> (repeatable)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("index")
+                .short('i')
+                .long("index")
+                .help("Index of ranged keys")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("0")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("sign")
+                .short('p')
+                .long("sign")
+                .help("Private key spending the paths, as [NAME=]<WIF or hex> (repeatable)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("preimage")
+                .long("preimage")
+                .help("Hash preimage (hex) available to the paths (repeatable)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(network_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let network: Network = matches.get_one::<String>("network").unwrap().parse()?;
+    let context = match matches.get_flag("tap") {
+        true => ScriptContext::Tap,
+        false => ScriptContext::Segwitv0,
+    };
+    let index = *matches.get_one::<u32>("index").unwrap();
+
+    let mut names = vec![];
+    for key in matches.get_many::<String>("key").unwrap_or_default() {
+        let (name, key) = key
+            .split_once('=')
+            .ok_or(eyre!("Expected NAME=<key> : {}", key))?;
+        names.push((name.to_string(), key.to_string()));
+    }
+    let mut private_keys = vec![];
+    for key in matches.get_many::<String>("sign").unwrap_or_default() {
+        let (name, secret) = match key.split_once('=') {
+            Some((name, secret)) => (Some(name), secret),
+            None => (None, key.as_str()),
+        };
+        let private_key = PrivateKey::from_str_with_network(secret, network)?;
+        if let Some(name) = name {
+            let public_key = private_key.public_key().compressed_bytes();
+            names.push((name.to_string(), hex::encode(public_key)));
+        }
+        private_keys.push(private_key);
+    }
+    let preimages = matches
+        .get_many::<String>("preimage")
+        .unwrap_or_default()
+        .map(hex::decode)
+        .collect::<Result<Vec<_>, _>>()?;
+    // Paths are printed with the key names.
+    let keys_to_names: Vec<(String, String)> = names
+        .iter()
+        .map(|(name, key)| (key.clone(), name.clone()))
+        .collect();
+
+    let expression = matches.get_one::<String>("expression").unwrap();
+    let policy = Policy::parse(&replace_key_names(expression, &names), context)?;
+    let compilation = policy.compile(context)?;
+    let miniscript = &compilation.miniscript;
+    let script = miniscript.encode(index)?;
+
+    println!("Policy        : {}", expression);
+    println!("Context       : {}", context);
+    println!(
+        "Miniscript    : {}",
+        replace_key_names(&miniscript.to_string(), &keys_to_names)
+    );
+    println!("Type          : {}", miniscript.ty);
+    println!("Script        : {}", to_asm(&script));
+    println!("Script Hex    : {}", hex::encode(&script));
+    match context {
+        ScriptContext::Segwitv0 => {
+            println!(
+                "Size          : {} bytes, {} ops",
+                script.len(),
+                miniscript.ops_count(index)?
+            );
+            println!("Address       : {}", Address::p2wsh(&script, network));
+        }
+        ScriptContext::Tap => {
+            println!("Size          : {} bytes", script.len());
+            let tree = TapTree::leaf(script.clone());
+            let info = TaprootSpendInfo::new(&UNSPENDABLE_KEY, Some(&tree))?;
+            println!("Address       : {}", info.address(network));
+        }
+    }
+    println!(
+        "Expected cost : {:.2} bytes ({} script + {:.2} witness)",
+        compilation.script_size as f64 + compilation.expected_witness_size,
+        compilation.script_size,
+        compilation.expected_witness_size
+    );
+
+    println!();
+    println!("Spending paths");
+    for (position, path) in policy.spending_paths().iter().enumerate() {
+        let conditions: Vec<String> = path
+            .conditions
+            .iter()
+            .map(|condition| replace_key_names(&condition.to_string(), &keys_to_names))
+            .collect();
+        println!(
+            "  Path {} ({:.2}%) : {}",
+            position + 1,
+            path.probability * 100.0,
+            conditions.join(", ")
+        );
+        match spend_path(miniscript, index, path, &private_keys, &preimages) {
+            Ok(tx) => {
+                let witness = &tx.inputs[0].witness;
+                // The witness script (or leaf script and control block) follows the inputs.
+                let inputs = match context {
+                    ScriptContext::Segwitv0 => &witness[..witness.len() - 1],
+                    ScriptContext::Tap => &witness[..witness.len() - 2],
+                };
+                println!(
+                    "    Witness     : {} bytes, {} items",
+                    witness_size(inputs),
+                    inputs.len()
+                );
+                println!("    Verified    : OK (bee-vm)");
+            }
+            Err(PolicyError::MissingSecret(condition)) => println!(
+                "    Verified    : skipped, no secret for {}",
+                replace_key_names(&condition, &keys_to_names)
+            ),
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,357 @@
+use crate::commands::address::network_arg;
+use crate::commands::sign_tx::parse_sighash;
+use bee_vm::transaction::sighash::TAPSCRIPT_LEAF_VERSION;
+use bee_vm::transaction::{display_hash, Transaction, TxOut};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::bip32::Xpriv;
+use helpers::keys::PrivateKey;
+use helpers::network::Network;
+use helpers::psbt::analyze::analyze;
+use helpers::psbt::finalize::{extract, finalize};
+use helpers::psbt::sign::{derive_keys, sign};
+use helpers::psbt::{parse_key_with_origin, Input, Psbt};
+use helpers::transaction::{create, parse_u32};
+
+pub fn command() -> Command {
+    Command::new("psbt")
+        .about("Partially signed transactions (BIP174 v0 / BIP370 v2)")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("create")
+                .about("Creator : a PSBT spending the given inputs")
+                .arg(
+                    Arg::new("input")
+                        .long("input")
+                        .help("Input as <txid>:<vout>[:<sequence>] (repeatable)")
+                        .action(ArgAction::Append)
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .help("Output as <address | scriptPubKey hex>:<satoshis> (repeatable)")
+                        .action(ArgAction::Append)
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("locktime")
+                        .long("locktime")
+                        .help("Transaction locktime (the fallback locktime of a version 2 PSBT)")
+                        .default_value("0")
+                        .value_parser(clap::value_parser!(u32))
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("sequence")
+                        .long("sequence")
+                        .help("Sequence of the inputs that do not set one (decimal or 0x hex)")
+                        .default_value("0xfffffffd")
+                        .num_args(1),
+                )
+                .arg(version_arg())
+                .arg(network_arg()),
+        )
+        .subcommand(
+            Command::new("update")
+                .about("Updater : adds UTXOs, scripts and key origins to an input")
+                .arg(psbt_arg())
+                .arg(
+                    Arg::new("input")
+                        .short('i')
+                        .long("input")
+                        .help("Index of the input to update")
+                        .default_value("0")
+                        .value_parser(clap::value_parser!(usize))
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("witness_utxo")
+                        .long("witness-utxo")
+                        .help("Spent output as <scriptPubKey hex>:<satoshis>")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("non_witness_utxo")
+                        .long("non-witness-utxo")
+                        .help("Raw transaction holding the spent output (required for legacy inputs)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("redeem_script")
+                        .long("redeem-script")
+                        .help("P2SH redeem script (hex)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("witness_script")
+                        .long("witness-script")
+                        .help("P2WSH witness script (hex)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("sighash")
+                        .long("sighash")
+                        .help("all, none, single, with |anyonecanpay, or default (taproot only)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("tap_internal_key")
+                        .long("tap-internal-key")
+                        .help("Taproot internal key (x-only, hex)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("tap_merkle_root")
+                        .long("tap-merkle-root")
+                        .help("Taproot merkle root (hex)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("tap_leaf")
+                        .long("tap-leaf")
+                        .help("Taproot leaf as <control block hex>:<script hex> (repeatable)")
+                        .action(ArgAction::Append)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("key_origin")
+                        .long("key-origin")
+                        .help("Key with its origin, [d34db33f/84'/0'/0'/0/0]<pubkey hex> (repeatable)")
+                        .action(ArgAction::Append)
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("sign")
+                .about("Signer : signs every input the keys can sign")
+                .arg(psbt_arg())
+                .arg(
+                    Arg::new("private_key")
+                        .short('p')
+                        .long("private-key")
+                        .help("Private key to sign with, WIF or 32 bytes hex (repeatable)")
+                        .action(ArgAction::Append)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("xprv")
+                        .long("xprv")
+                        .help("Master extended private key, keys are found through the key origins")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("finalize")
+                .about("Finalizer : builds the scriptSigs / witnesses and checks them with bee-vm")
+                .arg(psbt_arg())
+                .arg(
+                    Arg::new("extract")
+                        .long("extract")
+                        .help("Also extract the network transaction")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("extract")
+                .about("Extractor : the network transaction of a finalized PSBT")
+                .arg(psbt_arg()),
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("Converts a PSBT between version 0 and version 2")
+                .arg(psbt_arg())
+                .arg(version_arg()),
+        )
+        .subcommand(
+            Command::new("analyze")
+                .about("Reports what each input is missing and the next role (analyzepsbt format)")
+                .arg(psbt_arg()),
+        )
+}
+
+fn psbt_arg() -> Arg {
+    Arg::new("psbt")
+        .help("PSBT in base64 or hex")
+        .required(true)
+        .num_args(1)
+}
+
+fn version_arg() -> Arg {
+    Arg::new("psbt_version")
+        .long("psbt-version")
+        .help("PSBT version, 0 (BIP174) or 2 (BIP370)")
+        .value_parser(["0", "2"])
+        .default_value("0")
+        .num_args(1)
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    match matches.subcommand() {
+        Some(("create", sub_matches)) => {
+            let network: Network = sub_matches.get_one::<String>("network").unwrap().parse()?;
+            let sequence = sub_matches.get_one::<String>("sequence").unwrap();
+            let sequence = parse_u32(sequence).ok_or(eyre!("Invalid sequence : {}", sequence))?;
+            let inputs: Vec<String> = sub_matches
+                .get_many::<String>("input")
+                .unwrap()
+                .cloned()
+                .collect();
+            let outputs: Vec<String> = sub_matches
+                .get_many::<String>("output")
+                .unwrap()
+                .cloned()
+                .collect();
+
+            let tx = create(
+                2,
+                &inputs,
+                &outputs,
+                *sub_matches.get_one::<u32>("locktime").unwrap(),
+                sequence,
+                network,
+            )?;
+            let psbt = Psbt::from_unsigned_tx(&tx, psbt_version(sub_matches))?;
+            println!("{}", psbt);
+        }
+        Some(("update", sub_matches)) => {
+            let mut psbt = parse_psbt(sub_matches)?;
+            let index = *sub_matches.get_one::<usize>("input").unwrap();
+            update(&mut psbt, index, sub_matches)?;
+            println!("{}", psbt);
+        }
+        Some(("sign", sub_matches)) => {
+            let mut psbt = parse_psbt(sub_matches)?;
+            let mut keys = sub_matches
+                .get_many::<String>("private_key")
+                .unwrap_or_default()
+                .map(|key| PrivateKey::from_str_with_network(key, Network::Mainnet))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(xprv) = sub_matches.get_one::<String>("xprv") {
+                keys.extend(derive_keys(&psbt, &xprv.parse::<Xpriv>()?)?);
+            }
+            if keys.is_empty() {
+                return Err(eyre!("Pass at least one key with -p or --xprv"));
+            }
+
+            let signatures = sign(&mut psbt, &keys)?;
+            eprintln!("Added {} signature(s)", signatures);
+            println!("{}", psbt);
+        }
+        Some(("finalize", sub_matches)) => {
+            let mut psbt = parse_psbt(sub_matches)?;
+            finalize(&mut psbt)?;
+            println!("PSBT        : {}", psbt);
+            if sub_matches.get_flag("extract") {
+                print_transaction(&extract(&psbt)?);
+            }
+        }
+        Some(("extract", sub_matches)) => {
+            print_transaction(&extract(&parse_psbt(sub_matches)?)?);
+        }
+        Some(("convert", sub_matches)) => {
+            let psbt = parse_psbt(sub_matches)?;
+            println!("{}", psbt.convert(psbt_version(sub_matches))?);
+        }
+        Some(("analyze", sub_matches)) => {
+            let psbt = parse_psbt(sub_matches)?;
+            println!("{}", serde_json::to_string_pretty(&analyze(&psbt))?);
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+    Ok(())
+}
+
+fn update(psbt: &mut Psbt, index: usize, matches: &ArgMatches) -> color_eyre::Result<()> {
+    let input = psbt
+        .inputs
+        .get_mut(index)
+        .ok_or(eyre!("Input {} is out of range", index))?;
+    let hex_arg = |name: &str| matches.get_one::<String>(name).map(hex::decode).transpose();
+
+    if let Some(utxo) = matches.get_one::<String>("witness_utxo") {
+        let (script, amount) = utxo.split_once(':').ok_or(eyre!(
+            "Witness UTXO {} is not <scriptPubKey>:<amount>",
+            utxo
+        ))?;
+        input.witness_utxo = Some(TxOut {
+            value: amount.parse()?,
+            script_pubkey: hex::decode(script)?,
+        });
+    }
+    if let Some(tx) = matches.get_one::<String>("non_witness_utxo") {
+        let tx = Transaction::from_hex(tx)?;
+        if tx.txid() != input.previous_output.txid {
+            return Err(eyre!(
+                "The non witness UTXO is not the transaction spent by input {}",
+                index
+            ));
+        }
+        input.non_witness_utxo = Some(tx);
+    }
+    if let Some(script) = hex_arg("redeem_script")? {
+        input.redeem_script = Some(script);
+    }
+    if let Some(script) = hex_arg("witness_script")? {
+        input.witness_script = Some(script);
+    }
+    if let Some(sighash) = matches.get_one::<String>("sighash") {
+        input.sighash_type = Some(parse_sighash(sighash)? as u32);
+    }
+    if let Some(key) = hex_arg("tap_internal_key")? {
+        input.tap_internal_key = Some(
+            key.try_into()
+                .map_err(|_| eyre!("The internal key must be 32 bytes"))?,
+        );
+    }
+    if let Some(root) = hex_arg("tap_merkle_root")? {
+        input.tap_merkle_root = Some(
+            root.try_into()
+                .map_err(|_| eyre!("The merkle root must be 32 bytes"))?,
+        );
+    }
+    for leaf in matches.get_many::<String>("tap_leaf").unwrap_or_default() {
+        add_tap_leaf(input, leaf)?;
+    }
+    for key in matches.get_many::<String>("key_origin").unwrap_or_default() {
+        let (public_key, source) = parse_key_with_origin(key)?;
+        input.bip32_derivation.insert(public_key, source);
+    }
+    Ok(())
+}
+
+/// Adds a taproot leaf given as `<control block hex>:<script hex>` to an input, the leaf version
+/// is read from the control block.
+pub fn add_tap_leaf(input: &mut Input, leaf: &str) -> color_eyre::Result<()> {
+    let (control_block, script) = leaf
+        .split_once(':')
+        .ok_or(eyre!("Leaf {} is not <control block>:<script>", leaf))?;
+    let control_block = hex::decode(control_block)?;
+    let leaf_version = control_block
+        .first()
+        .map_or(TAPSCRIPT_LEAF_VERSION, |c| c & 0xfe);
+    input
+        .tap_leaf_scripts
+        .insert(control_block, (hex::decode(script)?, leaf_version));
+    Ok(())
+}
+
+fn parse_psbt(matches: &ArgMatches) -> color_eyre::Result<Psbt> {
+    Ok(matches.get_one::<String>("psbt").unwrap().parse()?)
+}
+
+fn psbt_version(matches: &ArgMatches) -> u32 {
+    matches
+        .get_one::<String>("psbt_version")
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+fn print_transaction(tx: &Transaction) {
+    println!("Transaction : {}", tx.to_hex());
+    println!("Txid        : {}", display_hash(&tx.txid()));
+    println!("Verified    : OK (bee-vm verify_input on every input)");
+}
@@ -0,0 +1,183 @@
+use crate::commands::address::network_arg;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::address::Address;
+use helpers::keys::PrivateKey;
+use helpers::network::Network;
+use helpers::schnorr::{
+    self, tap_tweak_hash, tweak_public_key, tweak_secret_key, x_only_public_key,
+};
+
+pub fn command() -> Command {
+    Command::new("schnorr")
+        .about("BIP340 Schnorr signatures and BIP341 key tweaking for taproot fixtures")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("sign")
+                .about("Signs a message (usually a 32 bytes sighash) with BIP340")
+                .arg(private_key_arg().required(true))
+                .arg(message_arg())
+                .arg(
+                    Arg::new("aux")
+                        .long("aux")
+                        .help("32 bytes of auxiliary randomness (hex, default : random)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("tweak")
+                        .long("tweak")
+                        .help("Signs with the taproot output key (BIP86 key path without --merkle-root)")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(merkle_root_arg())
+                .arg(network_arg()),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Verifies a BIP340 signature")
+                .arg(
+                    Arg::new("public_key")
+                        .short('k')
+                        .long("public-key")
+                        .help("Public key (x-only, compressed or uncompressed hex)")
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(message_arg())
+                .arg(
+                    Arg::new("signature")
+                        .short('s')
+                        .long("signature")
+                        .help("64 bytes signature (hex)")
+                        .required(true)
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("tweak")
+                .about("Tweaks an internal key into a taproot output key")
+                .arg(
+                    Arg::new("public_key")
+                        .help("Internal key (x-only, compressed or uncompressed hex)")
+                        .required_unless_present("private_key")
+                        .num_args(1),
+                )
+                .arg(private_key_arg().conflicts_with("public_key"))
+                .arg(merkle_root_arg())
+                .arg(network_arg()),
+        )
+}
+
+fn private_key_arg() -> Arg {
+    Arg::new("private_key")
+        .short('p')
+        .long("private-key")
+        .help("Private key, WIF or 32 bytes hex")
+        .num_args(1)
+}
+
+fn message_arg() -> Arg {
+    Arg::new("message")
+        .short('m')
+        .long("message")
+        .help("Message (hex)")
+        .required(true)
+        .num_args(1)
+}
+
+fn merkle_root_arg() -> Arg {
+    Arg::new("merkle_root")
+        .long("merkle-root")
+        .help("Merkle root of the taproot script tree (hex)")
+        .num_args(1)
+}
+
+fn bytes32_arg(matches: &ArgMatches, name: &str) -> color_eyre::Result<Option<[u8; 32]>> {
+    matches
+        .get_one::<String>(name)
+        .map(|value| {
+            hex::decode(value)?
+                .try_into()
+                .map_err(|_| eyre!("--{} must be 32 bytes", name.replace('_', "-")))
+        })
+        .transpose()
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    match matches.subcommand() {
+        Some(("sign", sub_matches)) => {
+            let network: Network = sub_matches.get_one::<String>("network").unwrap().parse()?;
+            let key = PrivateKey::from_str_with_network(
+                sub_matches.get_one::<String>("private_key").unwrap(),
+                network,
+            )?;
+            let message = hex::decode(sub_matches.get_one::<String>("message").unwrap())?;
+            let aux_rand = bytes32_arg(sub_matches, "aux")?;
+            let merkle_root = bytes32_arg(sub_matches, "merkle_root")?;
+
+            let mut secret_key = key.to_bytes();
+            if sub_matches.get_flag("tweak") || merkle_root.is_some() {
+                secret_key = tweak_secret_key(&secret_key, merkle_root.as_ref())?;
+            }
+            let signing_key = PrivateKey::from_slice(&secret_key, network)?;
+            let signature = schnorr::sign(&secret_key, &message, aux_rand.as_ref())?;
+
+            println!(
+                "Public Key : {}",
+                hex::encode(signing_key.public_key().x_only())
+            );
+            println!("Signature  : {}", hex::encode(signature));
+        }
+        Some(("verify", sub_matches)) => {
+            let (public_key, _) = x_only_public_key(&hex::decode(
+                sub_matches.get_one::<String>("public_key").unwrap(),
+            )?)?;
+            let message = hex::decode(sub_matches.get_one::<String>("message").unwrap())?;
+            let signature = hex::decode(sub_matches.get_one::<String>("signature").unwrap())?;
+
+            if !schnorr::verify(&public_key, &message, &signature) {
+                return Err(eyre!("Invalid BIP340 signature"));
+            }
+            println!("Public Key : {}", hex::encode(public_key));
+            println!("Verified   : OK");
+        }
+        Some(("tweak", sub_matches)) => {
+            let network: Network = sub_matches.get_one::<String>("network").unwrap().parse()?;
+            let merkle_root = bytes32_arg(sub_matches, "merkle_root")?;
+            let key = sub_matches
+                .get_one::<String>("private_key")
+                .map(|key| PrivateKey::from_str_with_network(key, network))
+                .transpose()?;
+            let (internal_key, parity) = match &key {
+                Some(key) => x_only_public_key(&key.public_key().compressed_bytes())?,
+                None => x_only_public_key(&hex::decode(
+                    sub_matches.get_one::<String>("public_key").unwrap(),
+                )?)?,
+            };
+            let (output_key, output_parity) =
+                tweak_public_key(&internal_key, merkle_root.as_ref())?;
+
+            println!(
+                "Internal Key  : {} ({} y)",
+                hex::encode(internal_key),
+                parity
+            );
+            if let Some(root) = merkle_root {
+                println!("Merkle Root   : {}", hex::encode(root));
+            }
+            println!(
+                "Tweak         : {}",
+                hex::encode(tap_tweak_hash(&internal_key, merkle_root.as_ref()))
+            );
+            println!("Output Key    : {}", hex::encode(output_key));
+            println!("Output Parity : {}", output_parity);
+            println!("Address       : {}", Address::p2tr(&output_key, network));
+            if let Some(key) = key {
+                let secret_key = tweak_secret_key(&key.to_bytes(), merkle_root.as_ref())?;
+                println!("Tweaked Key   : {}", hex::encode(secret_key));
+            }
+        }
+        _ => unreachable!("unknown schnorr subcommand"),
+    }
+    Ok(())
+}
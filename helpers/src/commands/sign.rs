@@ -0,0 +1,126 @@
+use crate::commands::address::network_arg;
+use crate::commands::psbt::add_tap_leaf;
+use crate::commands::verify::print_verified;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::address::Address;
+use helpers::bip322::{self, Format};
+use helpers::keys::PrivateKey;
+use helpers::network::Network;
+use helpers::psbt::Input;
+
+pub fn command() -> Command {
+    Command::new("sign")
+        .about("Signs a message for an address (BIP322 legacy, simple or full)")
+        .arg(
+            Arg::new("private_key")
+                .short('p')
+                .long("private-key")
+                .help("Private key to sign with, WIF or 32 bytes hex (repeatable)")
+                .required(true)
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("message")
+                .short('m')
+                .long("message")
+                .help("The message to sign")
+                .required(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("address")
+                .short('a')
+                .long("address")
+                .help("Address to sign for (default : P2WPKH address of the first key, P2PKH if uncompressed)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .help("Signature format (default : legacy for P2PKH, simple for segwit, else full)")
+                .value_parser(["legacy", "simple", "full"])
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("redeem_script")
+                .long("redeem-script")
+                .help("P2SH redeem script (hex)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("witness_script")
+                .long("witness-script")
+                .help("P2WSH witness script (hex)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("tap_merkle_root")
+                .long("tap-merkle-root")
+                .help("Taproot merkle root, to sign the key path of an output with a script tree (hex)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("tap_leaf")
+                .long("tap-leaf")
+                .help("Taproot leaf as <control block hex>:<script hex> (repeatable)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(network_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let network: Network = matches.get_one::<String>("network").unwrap().parse()?;
+    let keys = matches
+        .get_many::<String>("private_key")
+        .unwrap()
+        .map(|key| PrivateKey::from_str_with_network(key, network))
+        .collect::<Result<Vec<_>, _>>()?;
+    let message = matches.get_one::<String>("message").unwrap();
+
+    let address = match matches.get_one::<String>("address") {
+        Some(address) => Address::decode(address)?,
+        None => {
+            let public_key = keys[0].public_key().to_bytes();
+            if keys[0].compressed {
+                Address::p2wpkh(&public_key, network)
+            } else {
+                Address::p2pkh(&public_key, network)
+            }
+        }
+    };
+    let script_pubkey = address.script_pubkey();
+    let format = match matches.get_one::<String>("format") {
+        Some(format) => format.parse()?,
+        None => Format::default_for(&script_pubkey),
+    };
+
+    let hex_arg = |name: &str| matches.get_one::<String>(name).map(hex::decode).transpose();
+    let mut input = Input {
+        redeem_script: hex_arg("redeem_script")?,
+        witness_script: hex_arg("witness_script")?,
+        ..Default::default()
+    };
+    if let Some(root) = hex_arg("tap_merkle_root")? {
+        input.tap_merkle_root = Some(
+            root.try_into()
+                .map_err(|_| eyre!("The merkle root must be 32 bytes"))?,
+        );
+    }
+    for leaf in matches.get_many::<String>("tap_leaf").unwrap_or_default() {
+        add_tap_leaf(&mut input, leaf)?;
+    }
+
+    let signature = bip322::sign(&script_pubkey, message.as_bytes(), &keys, input, format)?;
+    println!("Address   : {}", address);
+    println!("Format    : {}", format);
+    println!("Signature : {}", signature);
+    print_verified(bip322::verify(
+        &script_pubkey,
+        message.as_bytes(),
+        &signature,
+    )?);
+    Ok(())
+}
@@ -0,0 +1,278 @@
+use bee_vm::opcodes::crypto_ops::{
+    SIGHASH_ALL, SIGHASH_ANYONECANPAY, SIGHASH_NONE, SIGHASH_SINGLE,
+};
+use bee_vm::script::classify::{classify, ScriptType};
+use bee_vm::script::decode::bytes_to_tokens;
+use bee_vm::stack::executor::execute_code_with_context;
+use bee_vm::stack::Stack;
+use bee_vm::transaction::context::ExecutionContext;
+use bee_vm::transaction::sighash::{SigVersion, SIGHASH_DEFAULT};
+use bee_vm::transaction::{Transaction, TxOut};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::hashes::sha256;
+use helpers::keys::PrivateKey;
+use helpers::network::Network;
+use helpers::schnorr::{self, taproot_output_key, tweak_secret_key};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+
+pub fn command() -> Command {
+    Command::new("sign-tx")
+        .about("Signs an input of a raw transaction (legacy, segwit v0 or taproot key path)")
+        .arg(
+            Arg::new("transaction")
+                .help("Raw unsigned transaction (hex)")
+                .required(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .help("Index of the input to sign")
+                .default_value("0")
+                .value_parser(clap::value_parser!(usize))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("prevout_script")
+                .short('s')
+                .long("prevout-script")
+                .help("scriptPubKey of the spent output (hex)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("amount")
+                .short('a')
+                .long("amount")
+                .help("Amount of the spent output in satoshis")
+                .default_value("0")
+                .value_parser(clap::value_parser!(u64))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("prevouts")
+                .long("prevouts")
+                .help("Every spent output as <scriptPubKey hex>:<amount>, in input order (taproot)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("script")
+                .long("script")
+                .help("Redeem script (P2SH) or witness script (P2WSH) being spent (hex)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("sighash")
+                .long("sighash")
+                .help("all, none, single, with |anyonecanpay, or default (taproot only)")
+                .default_value("all")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("sigversion")
+                .long("sigversion")
+                .help("Signature hash algorithm : legacy, v0 (BIP143) or taproot (BIP341)")
+                .value_parser(["legacy", "v0", "taproot"])
+                .default_value("legacy")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("private_key")
+                .short('p')
+                .long("private-key")
+                .help("Private key to sign with (WIF or 32 bytes hex)")
+                .required(true)
+                .num_args(1),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let tx = Transaction::from_hex(matches.get_one::<String>("transaction").unwrap())?;
+    let input_index = *matches.get_one::<usize>("input").unwrap();
+    let sighash_type = parse_sighash(matches.get_one::<String>("sighash").unwrap())?;
+    let key = PrivateKey::from_str_with_network(
+        matches.get_one::<String>("private_key").unwrap(),
+        Network::Mainnet,
+    )?;
+    let script = matches
+        .get_one::<String>("script")
+        .map(hex::decode)
+        .transpose()?;
+
+    match matches.get_one::<String>("sigversion").unwrap().as_str() {
+        "taproot" => {
+            let prevouts = matches
+                .get_many::<String>("prevouts")
+                .ok_or(eyre!("--prevouts is required to sign a taproot input"))?
+                .map(|prevout| parse_prevout(prevout))
+                .collect::<color_eyre::Result<Vec<TxOut>>>()?;
+            let context = ExecutionContext::taproot(tx, input_index, prevouts, None)?;
+            sign_taproot(&context, &key, sighash_type)
+        }
+        sig_version => {
+            let spent_output = TxOut {
+                value: *matches.get_one::<u64>("amount").unwrap(),
+                script_pubkey: hex::decode(
+                    matches
+                        .get_one::<String>("prevout_script")
+                        .ok_or(eyre!("--prevout-script is required"))?,
+                )?,
+            };
+            let sig_version = match sig_version {
+                "v0" => SigVersion::WitnessV0,
+                _ => SigVersion::Base,
+            };
+            let context = ExecutionContext::new(tx, input_index, spent_output, sig_version)?;
+            sign_ecdsa(&context, &key, sighash_type, script)
+        }
+    }
+}
+
+/// Parses `all`, `none`, `single` (optionally `|anyonecanpay`), `default` or a raw number.
+pub fn parse_sighash(sighash: &str) -> color_eyre::Result<u8> {
+    let mut value = 0;
+    for part in sighash.to_lowercase().split(['|', '+']) {
+        value |= match part.trim() {
+            "default" => SIGHASH_DEFAULT,
+            "all" => SIGHASH_ALL,
+            "none" => SIGHASH_NONE,
+            "single" => SIGHASH_SINGLE,
+            "anyonecanpay" | "acp" => SIGHASH_ANYONECANPAY,
+            other => match other.strip_prefix("0x") {
+                Some(hex_value) => u8::from_str_radix(hex_value, 16)?,
+                None => other.parse::<u8>()?,
+            },
+        };
+    }
+    Ok(value)
+}
+
+fn parse_prevout(prevout: &str) -> color_eyre::Result<TxOut> {
+    let (script, amount) = prevout
+        .split_once(':')
+        .ok_or(eyre!("Prevout {} is not <scriptPubKey>:<amount>", prevout))?;
+    Ok(TxOut {
+        value: amount.parse()?,
+        script_pubkey: hex::decode(script)?,
+    })
+}
+
+/// Script executed by OP_CHECKSIG for the spent output.
+fn script_code(context: &ExecutionContext, script: Option<Vec<u8>>) -> color_eyre::Result<Vec<u8>> {
+    let spent_script = &context.spent_output.script_pubkey;
+    let script_code = match (classify(spent_script), context.sig_version) {
+        (ScriptType::P2WPKH { pubkey_hash }, SigVersion::WitnessV0) => p2pkh_script(&pubkey_hash),
+        (ScriptType::P2WSH { script_hash }, SigVersion::WitnessV0) => {
+            let script = script.ok_or(eyre!("--script is required to spend a P2WSH output"))?;
+            if sha256(&script) != script_hash {
+                return Err(eyre!("The witness script does not match the P2WSH output"));
+            }
+            script
+        }
+        // Nested segwit : --script is the P2SH redeem script or the wrapped witness script.
+        (_, SigVersion::WitnessV0) => {
+            let script = script.ok_or(eyre!(
+                "The spent output is not a native segwit v0 output, pass its script with --script"
+            ))?;
+            match classify(&script) {
+                ScriptType::P2WPKH { pubkey_hash } => p2pkh_script(&pubkey_hash),
+                _ => script,
+            }
+        }
+        (ScriptType::P2SH { .. }, _) => {
+            script.ok_or(eyre!("--script is required to spend a P2SH output"))?
+        }
+        _ => spent_script.clone(),
+    };
+
+    Ok(script_code)
+}
+
+/// Initial stack below the signature, when bee-vm can run the script from a single signature.
+fn unlocking_stack(script_code: &[u8], public_key: &[u8]) -> Option<Vec<Vec<u8>>> {
+    match classify(script_code) {
+        ScriptType::P2PKH { .. } => Some(vec![public_key.to_vec()]),
+        ScriptType::P2PK { .. } => Some(vec![]),
+        _ => None,
+    }
+}
+
+/// BIP143 script code of a P2WPKH output : the matching P2PKH script.
+fn p2pkh_script(pubkey_hash: &[u8; 20]) -> Vec<u8> {
+    let mut script = vec![0x76, 0xa9, 0x14];
+    script.extend_from_slice(pubkey_hash);
+    script.extend_from_slice(&[0x88, 0xac]);
+    script
+}
+
+fn sign_ecdsa(
+    context: &ExecutionContext,
+    key: &PrivateKey,
+    sighash_type: u8,
+    script: Option<Vec<u8>>,
+) -> color_eyre::Result<()> {
+    if sighash_type == SIGHASH_DEFAULT {
+        return Err(eyre!("SIGHASH_DEFAULT only exists for taproot signatures"));
+    }
+    let public_key = key.public_key().to_bytes();
+    let script_code = script_code(context, script)?;
+
+    let sighash = context.signature_hash(&script_code, sighash_type as u32, u32::MAX)?;
+    let signature: k256::ecdsa::Signature = key.signing_key.sign_prehash(&sighash)?;
+    let mut signature = signature.to_der().as_bytes().to_vec();
+    signature.push(sighash_type);
+
+    // Runs the script in bee-vm when possible, checks the signature alone otherwise.
+    let verified = match unlocking_stack(&script_code, &public_key) {
+        Some(mut stack) => {
+            stack.insert(0, signature.clone());
+            let (mut main_stack, _) = execute_code_with_context(
+                &bytes_to_tokens(&script_code)?,
+                Stack::from_data(&stack),
+                context,
+            )?;
+            main_stack.pop_from_top() == Some("1".to_string())
+        }
+        None => context.check_ecdsa_signature(&signature, &public_key, &script_code)?,
+    };
+    if !verified {
+        return Err(eyre!("bee-vm rejected the signature"));
+    }
+
+    println!("Sighash     : {}", hex::encode(sighash));
+    println!("Script Code : {}", hex::encode(&script_code));
+    println!("Public Key  : {}", hex::encode(&public_key));
+    println!("Signature   : {}", hex::encode(&signature));
+    println!("Verified    : OK (bee-vm OP_CHECKSIG)");
+    Ok(())
+}
+
+fn sign_taproot(
+    context: &ExecutionContext,
+    key: &PrivateKey,
+    sighash_type: u8,
+) -> color_eyre::Result<()> {
+    // Key path spend of a BIP86 output (no script tree).
+    let secret_key = tweak_secret_key(&key.to_bytes(), None)?;
+    let output_key = taproot_output_key(&key.public_key().x_only(), None)?;
+    let sighash = context.signature_hash(&[], sighash_type as u32, u32::MAX)?;
+
+    let mut signature = schnorr::sign(&secret_key, &sighash, None)?.to_vec();
+    if sighash_type != SIGHASH_DEFAULT {
+        signature.push(sighash_type);
+    }
+
+    if !context.check_taproot_key_path(&signature)? {
+        return Err(eyre!(
+            "bee-vm rejected the signature, is the spent output the BIP86 output of this key ?"
+        ));
+    }
+
+    println!("Sighash     : {}", hex::encode(sighash));
+    println!("Output Key  : {}", hex::encode(output_key));
+    println!("Signature   : {}", hex::encode(&signature));
+    println!("Verified    : OK (bee-vm taproot key path)");
+    Ok(())
+}
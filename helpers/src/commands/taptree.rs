@@ -0,0 +1,180 @@
+use crate::commands::address::network_arg;
+use bee_vm::processor::input::split_tokens;
+use bee_vm::script::asm::to_asm;
+use bee_vm::script::encode::tokens_to_bytes;
+use bee_vm::transaction::context::ExecutionContext;
+use bee_vm::transaction::sighash::SIGHASH_DEFAULT;
+use bee_vm::transaction::verify::verify_input;
+use bee_vm::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::keys::PrivateKey;
+use helpers::network::Network;
+use helpers::schnorr::{self, x_only_public_key};
+use helpers::taproot::{TaprootBuilder, TaprootSpendInfo, UNSPENDABLE_KEY};
+
+/// Witness item replaced by a signature of the `--sign` key.
+const SIGNATURE_PLACEHOLDER: &str = "<sig>";
+
+pub fn command() -> Command {
+    Command::new("taptree")
+        .about("Builds a P2TR output from weighted leaf scripts (Huffman tree) with control blocks")
+        .arg(
+            Arg::new("leaf")
+                .short('l')
+                .long("leaf")
+                .help("Leaf as [<weight>:]<bee-vm tokens>, e.g. \"3:OP_SHA256 <hash> OP_EQUAL\" (repeatable)")
+                .required(true)
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("hex")
+                .long("hex")
+                .help("Leaf scripts are hex instead of bee-vm tokens")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("internal_key")
+                .short('k')
+                .long("internal-key")
+                .help("Internal key (default : the BIP341 unspendable point, script path only)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("spend")
+                .long("spend")
+                .help("Spends this leaf (index) in a test transaction checked by bee-vm")
+                .value_parser(clap::value_parser!(usize))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("stack")
+                .short('s')
+                .long("stack")
+                .help("Script input of the spend (hex, or <sig> for a signature), bottom first (repeatable)")
+                .action(ArgAction::Append)
+                .requires("spend")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("sign")
+                .short('p')
+                .long("sign")
+                .help("Private key replacing the <sig> inputs with BIP340 signatures")
+                .requires("spend")
+                .num_args(1),
+        )
+        .arg(network_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let network: Network = matches.get_one::<String>("network").unwrap().parse()?;
+    let internal_key = match matches.get_one::<String>("internal_key") {
+        Some(key) => x_only_public_key(&hex::decode(key)?)?.0,
+        None => UNSPENDABLE_KEY,
+    };
+
+    let mut builder = TaprootBuilder::new();
+    let mut weights = vec![];
+    for leaf in matches.get_many::<String>("leaf").unwrap() {
+        let (weight, script) = match leaf.split_once(':') {
+            Some((weight, script)) if weight.chars().all(|c| c.is_ascii_digit()) => {
+                (weight.parse()?, script)
+            }
+            _ => (1, leaf.as_str()),
+        };
+        let script = if matches.get_flag("hex") {
+            hex::decode(script)?
+        } else {
+            tokens_to_bytes(&split_tokens(script))?
+        };
+        builder = builder.add_leaf(weight, script);
+        weights.push(weight);
+    }
+    let info = builder.finalize(&internal_key)?;
+
+    println!("Internal Key  : {}", hex::encode(info.internal_key));
+    println!("Merkle Root   : {}", hex::encode(info.merkle_root.unwrap()));
+    println!("Output Key    : {}", hex::encode(info.output_key));
+    println!("Output Parity : {}", info.parity);
+    println!("scriptPubKey  : {}", hex::encode(info.script_pubkey()));
+    println!("Address       : {}", info.address(network));
+
+    for (index, leaf) in info.leaves.iter().enumerate() {
+        println!();
+        println!("Leaf {}", index);
+        println!("  Weight        : {}", weights[index]);
+        println!("  Depth         : {}", leaf.merkle_path.len());
+        println!("  Script        : {}", to_asm(&leaf.script));
+        println!("  Leaf Hash     : {}", hex::encode(leaf.leaf_hash));
+        println!(
+            "  Control Block : {}",
+            hex::encode(info.control_block(index)?)
+        );
+        println!(
+            "  Witness       : <script inputs...> {} {}",
+            hex::encode(&leaf.script),
+            hex::encode(info.control_block(index)?)
+        );
+    }
+
+    if let Some(&leaf) = matches.get_one::<usize>("spend") {
+        spend_leaf(matches, &info, leaf)?;
+    }
+    Ok(())
+}
+
+/// Spends a leaf of the output in a one input transaction and runs bee-vm's verification.
+fn spend_leaf(
+    matches: &ArgMatches,
+    info: &TaprootSpendInfo,
+    leaf: usize,
+) -> color_eyre::Result<()> {
+    let prevouts = vec![TxOut {
+        value: 100_000,
+        script_pubkey: info.script_pubkey(),
+    }];
+    let mut tx = Transaction {
+        version: 2,
+        inputs: vec![TxIn::new(
+            OutPoint::from_display_txid(&hex::encode([0x11; 32]), 0)?,
+            0xffff_fffd,
+        )],
+        outputs: vec![TxOut {
+            value: 99_000,
+            script_pubkey: info.script_pubkey(),
+        }],
+        lock_time: 0,
+    };
+    let leaf_hash = info
+        .leaves
+        .get(leaf)
+        .ok_or(eyre!("Leaf {} is out of range", leaf))?
+        .leaf_hash;
+    let context = ExecutionContext::taproot(tx.clone(), 0, prevouts.clone(), Some(leaf_hash))?;
+    let sighash = context.signature_hash(&[], SIGHASH_DEFAULT as u32, u32::MAX)?;
+
+    let mut stack = vec![];
+    for item in matches.get_many::<String>("stack").unwrap_or_default() {
+        if item == SIGNATURE_PLACEHOLDER {
+            let key = matches.get_one::<String>("sign").ok_or(eyre!(
+                "--sign is required for {} inputs",
+                SIGNATURE_PLACEHOLDER
+            ))?;
+            let key = PrivateKey::from_str_with_network(key, Network::Mainnet)?;
+            stack.push(schnorr::sign(&key.to_bytes(), &sighash, None)?.to_vec());
+        } else {
+            stack.push(hex::decode(item)?);
+        }
+    }
+    tx.inputs[0].witness = info.script_path_witness(leaf, stack)?;
+
+    println!();
+    println!("Spend of leaf {}", leaf);
+    println!("  Sighash       : {}", hex::encode(sighash));
+    println!("  Transaction   : {}", tx.to_hex());
+    verify_input(&tx, 0, &prevouts).map_err(|e| eyre!("bee-vm rejected the spend : {}", e))?;
+    println!("  Verified      : OK (bee-vm taproot script path)");
+    Ok(())
+}
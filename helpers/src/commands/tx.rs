@@ -0,0 +1,195 @@
+use crate::commands::address::network_arg;
+use bee_vm::processor::input::split_tokens;
+use bee_vm::script::encode::tokens_to_bytes;
+use bee_vm::transaction::{display_hash, Transaction};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::eyre;
+use helpers::network::Network;
+use helpers::transaction::{add_witness, create, parse_u32, set_script_sig, to_json};
+
+pub fn command() -> Command {
+    Command::new("tx")
+        .about("Builds, edits and decodes raw transactions")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("create")
+                .about("Creates an unsigned transaction")
+                .arg(
+                    Arg::new("input")
+                        .long("input")
+                        .help("Input as <txid>:<vout>[:<sequence>] (repeatable)")
+                        .action(ArgAction::Append)
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .help("Output as <address | scriptPubKey hex>:<satoshis> (repeatable)")
+                        .action(ArgAction::Append)
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("locktime")
+                        .long("locktime")
+                        .help("Transaction locktime (block height or timestamp)")
+                        .default_value("0")
+                        .value_parser(clap::value_parser!(u32))
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("sequence")
+                        .long("sequence")
+                        .help("Sequence of the inputs that do not set one (decimal or 0x hex)")
+                        .default_value("0xffffffff")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("version")
+                        .long("version")
+                        .help("Transaction version")
+                        .default_value("2")
+                        .value_parser(clap::value_parser!(i32))
+                        .num_args(1),
+                )
+                .arg(network_arg()),
+        )
+        .subcommand(
+            Command::new("decode")
+                .about("Decodes a raw transaction into JSON (decoderawtransaction format)")
+                .arg(transaction_arg())
+                .arg(network_arg()),
+        )
+        .subcommand(
+            Command::new("add-witness")
+                .about("Appends witness items (hex) to an input")
+                .arg(transaction_arg())
+                .arg(input_arg())
+                .arg(
+                    Arg::new("items")
+                        .help("Witness items in hex, \"\" for an empty item")
+                        .required(true)
+                        .num_args(1..),
+                ),
+        )
+        .subcommand(
+            Command::new("set-scriptsig")
+                .about("Replaces the scriptSig of an input")
+                .arg(transaction_arg())
+                .arg(input_arg())
+                .arg(
+                    Arg::new("script")
+                        .help("scriptSig in hex, or bee-vm tokens with --tokens")
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("tokens")
+                        .long("tokens")
+                        .help("Compile the script from bee-vm tokens (\"<sig> <pubkey>\")")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+}
+
+fn transaction_arg() -> Arg {
+    Arg::new("transaction")
+        .help("Raw transaction (hex)")
+        .required(true)
+        .num_args(1)
+}
+
+fn input_arg() -> Arg {
+    Arg::new("input")
+        .short('i')
+        .long("input")
+        .help("Index of the input to edit")
+        .default_value("0")
+        .value_parser(clap::value_parser!(usize))
+        .num_args(1)
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    match matches.subcommand() {
+        Some(("create", sub_matches)) => {
+            let network: Network = sub_matches.get_one::<String>("network").unwrap().parse()?;
+            let sequence = sub_matches.get_one::<String>("sequence").unwrap();
+            let sequence = parse_u32(sequence).ok_or(eyre!("Invalid sequence : {}", sequence))?;
+            let inputs: Vec<String> = sub_matches
+                .get_many::<String>("input")
+                .unwrap()
+                .cloned()
+                .collect();
+            let outputs: Vec<String> = sub_matches
+                .get_many::<String>("output")
+                .unwrap()
+                .cloned()
+                .collect();
+
+            let tx = create(
+                *sub_matches.get_one::<i32>("version").unwrap(),
+                &inputs,
+                &outputs,
+                *sub_matches.get_one::<u32>("locktime").unwrap(),
+                sequence,
+                network,
+            )?;
+            print_transaction(&tx);
+        }
+        Some(("decode", sub_matches)) => {
+            let network: Network = sub_matches.get_one::<String>("network").unwrap().parse()?;
+            let tx = parse_transaction(sub_matches)?;
+            println!("{}", serde_json::to_string_pretty(&to_json(&tx, network))?);
+        }
+        Some(("add-witness", sub_matches)) => {
+            let mut tx = parse_transaction(sub_matches)?;
+            let items = sub_matches
+                .get_many::<String>("items")
+                .unwrap()
+                .map(hex::decode)
+                .collect::<Result<Vec<_>, _>>()?;
+            add_witness(
+                &mut tx,
+                *sub_matches.get_one::<usize>("input").unwrap(),
+                items,
+            )?;
+            print_transaction(&tx);
+        }
+        Some(("set-scriptsig", sub_matches)) => {
+            let mut tx = parse_transaction(sub_matches)?;
+            let script = sub_matches.get_one::<String>("script").unwrap();
+            let script_sig = if sub_matches.get_flag("tokens") {
+                tokens_to_bytes(&split_tokens(script))?
+            } else {
+                hex::decode(script)?
+            };
+            set_script_sig(
+                &mut tx,
+                *sub_matches.get_one::<usize>("input").unwrap(),
+                script_sig,
+            )?;
+            print_transaction(&tx);
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+    Ok(())
+}
+
+fn parse_transaction(matches: &ArgMatches) -> color_eyre::Result<Transaction> {
+    Ok(Transaction::from_hex(
+        matches.get_one::<String>("transaction").unwrap(),
+    )?)
+}
+
+fn print_transaction(tx: &Transaction) {
+    println!("Transaction : {}", tx.to_hex());
+    println!("Txid        : {}", display_hash(&tx.txid()));
+    println!("Wtxid       : {}", display_hash(&tx.wtxid()));
+    println!(
+        "Size        : {} bytes, {} vbytes, {} weight units",
+        tx.size(),
+        tx.vsize(),
+        tx.weight()
+    );
+}
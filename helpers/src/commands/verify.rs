@@ -0,0 +1,51 @@
+use clap::{Arg, ArgMatches, Command};
+use helpers::address::Address;
+use helpers::bip322::{self, Format};
+
+pub fn command() -> Command {
+    Command::new("verify")
+        .about("Verifies a BIP322 message signature (legacy, simple or full) with bee-vm")
+        .arg(
+            Arg::new("address")
+                .short('a')
+                .long("address")
+                .help("Address the message was signed for")
+                .required(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("message")
+                .short('m')
+                .long("message")
+                .help("The signed message")
+                .required(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("signature")
+                .short('s')
+                .long("signature")
+                .help("Signature (base64)")
+                .required(true)
+                .num_args(1),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> color_eyre::Result<()> {
+    let address = Address::decode(matches.get_one::<String>("address").unwrap())?;
+    let message = matches.get_one::<String>("message").unwrap();
+    let signature = matches.get_one::<String>("signature").unwrap();
+
+    let format = bip322::verify(&address.script_pubkey(), message.as_bytes(), signature)?;
+    println!("Address   : {}", address);
+    println!("Format    : {}", format);
+    print_verified(format);
+    Ok(())
+}
+
+pub fn print_verified(format: Format) {
+    match format {
+        Format::Legacy => println!("Verified  : OK (public key recovery)"),
+        _ => println!("Verified  : OK (bee-vm verify_input of to_sign)"),
+    }
+}
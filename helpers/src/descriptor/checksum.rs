@@ -0,0 +1,107 @@
+// Descriptor checksums (BIP380).
+//
+// An 8 character BCH code over the descriptor, computed on groups of 3 characters : the
+// position of each character in INPUT_CHARSET is fed 5 bits at a time, and the high bits of 3
+// characters form one more symbol. It catches any typo up to 4 characters.
+
+use crate::errors::DescriptorError;
+
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn polymod(mut c: u64, value: u64) -> u64 {
+    const GENERATOR: [u64; 5] = [
+        0xf5dee51989,
+        0xa9fdca3312,
+        0x1bab10e32d,
+        0x3706b1677a,
+        0x644d626ffd,
+    ];
+    let c0 = c >> 35;
+    c = ((c & 0x7ffffffff) << 5) ^ value;
+    for (bit, generator) in GENERATOR.iter().enumerate() {
+        if (c0 >> bit) & 1 == 1 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/// Checksum of a descriptor (without its `#`).
+pub fn checksum(descriptor: &str) -> Result<String, DescriptorError> {
+    let mut c = 1;
+    let mut class = 0;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let position = INPUT_CHARSET
+            .find(ch)
+            .ok_or(DescriptorError::InvalidCharacter(ch))? as u64;
+        c = polymod(c, position & 31);
+        class = class * 3 + (position >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+
+    Ok((0..8)
+        .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+        .collect())
+}
+
+/// Splits `descriptor#checksum`, checking the checksum when there is one.
+pub fn verify_checksum(descriptor: &str) -> Result<&str, DescriptorError> {
+    let Some((body, found)) = descriptor.split_once('#') else {
+        checksum(descriptor)?;
+        return Ok(descriptor);
+    };
+    let expected = checksum(body)?;
+    if found != expected {
+        return Err(DescriptorError::InvalidChecksum {
+            expected,
+            found: found.to_string(),
+        });
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod test_checksum {
+    use crate::descriptor::checksum::{checksum, verify_checksum};
+    use crate::errors::DescriptorError;
+    use rstest::rstest;
+
+    // BIP380 test vectors.
+    #[rstest]
+    #[case("raw(deadbeef)#89f8spxm", Ok("raw(deadbeef)"))]
+    #[case("raw(deadbeef)", Ok("raw(deadbeef)"))]
+    #[case("raw(deadbeef)#", Err(DescriptorError::InvalidChecksum { expected: "89f8spxm".to_string(), found: "".to_string() }))]
+    #[case("raw(deadbeef)#89f8spxmx", Err(DescriptorError::InvalidChecksum { expected: "89f8spxm".to_string(), found: "89f8spxmx".to_string() }))]
+    #[case("raw(deedbeef)#89f8spxm", Err(DescriptorError::InvalidChecksum { expected: "xj8ljs75".to_string(), found: "89f8spxm".to_string() }))]
+    #[case("raw(Ü)#00000000", Err(DescriptorError::InvalidCharacter('Ü')))]
+    fn test_verify_checksum(
+        #[case] descriptor: &str,
+        #[case] expected: Result<&str, DescriptorError>,
+    ) {
+        assert_eq!(verify_checksum(descriptor), expected);
+    }
+
+    #[rstest]
+    #[case(
+        "wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)",
+        "8zl0zxma"
+    )]
+    #[case("sh(multi(2,022f01e5e15cca351daff3843fb70f3c2f0a1bdd05e5af888a67784ef3e10a2a01,03acd484e2f0c7f65309ad178a9f559abde09796974c57e714c35f110dfc27ccbe))", "y9zthqta")]
+    fn test_checksum(#[case] descriptor: &str, #[case] expected: &str) {
+        assert_eq!(checksum(descriptor).unwrap(), expected);
+    }
+}
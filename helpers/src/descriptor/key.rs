@@ -0,0 +1,299 @@
+// Descriptor key expressions (BIP380).
+//
+// `[d34db33f/84'/0'/0']xpub.../0/*` : an optional origin (master fingerprint and path), then a
+// hex public key, a WIF private key or an extended key followed by a derivation path that may
+// end with a `*` wildcard. Ranged keys are derived at the index the descriptor is expanded for.
+
+use crate::bip32::{ChildNumber, DerivationPath, Xpriv, Xpub};
+use crate::errors::DescriptorError;
+use crate::keys::{PrivateKey, PublicKey};
+use crate::psbt::KeySource;
+use std::fmt;
+use std::str::FromStr;
+
+/// The `*` at the end of a ranged key path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wildcard {
+    None,
+    /// `*`, the index is a normal child.
+    Normal,
+    /// `*'` or `*h`, the index is a hardened child (extended private keys only).
+    Hardened,
+}
+
+/// The key material of a key expression.
+#[derive(Debug, Clone)]
+pub enum KeyKind {
+    /// Hex compressed or uncompressed public key.
+    Public(PublicKey),
+    /// Hex 32 bytes x-only key, only allowed in `tr()`.
+    XOnly([u8; 32]),
+    /// WIF private key.
+    Private(PrivateKey),
+    Xpub(Xpub),
+    Xpriv(Xpriv),
+}
+
+#[derive(Debug, Clone)]
+pub struct DescriptorKey {
+    pub origin: Option<KeySource>,
+    pub key: KeyKind,
+    /// Derivation steps after an extended key.
+    pub path: DerivationPath,
+    pub wildcard: Wildcard,
+}
+
+impl DescriptorKey {
+    pub fn is_ranged(&self) -> bool {
+        self.wildcard != Wildcard::None
+    }
+
+    /// Whether the key serializes uncompressed (not allowed in segwit scripts).
+    pub fn is_uncompressed(&self) -> bool {
+        match &self.key {
+            KeyKind::Public(key) => !key.compressed,
+            KeyKind::Private(key) => !key.compressed,
+            _ => false,
+        }
+    }
+
+    pub fn is_x_only(&self) -> bool {
+        matches!(self.key, KeyKind::XOnly(_))
+    }
+
+    /// The public key at `index` (ignored by non ranged keys). x-only keys are given their even
+    /// y point.
+    pub fn derive(&self, index: u32) -> Result<PublicKey, DescriptorError> {
+        Ok(match &self.key {
+            KeyKind::Public(key) => *key,
+            KeyKind::XOnly(x_only) => {
+                let mut bytes = vec![0x02];
+                bytes.extend_from_slice(x_only);
+                PublicKey::from_slice(&bytes)?
+            }
+            KeyKind::Private(key) => key.public_key(),
+            KeyKind::Xpub(xpub) => xpub.derive_path(&self.full_path(index))?.to_public_key(),
+            KeyKind::Xpriv(xpriv) => xpriv
+                .derive_path(&self.full_path(index))?
+                .to_private_key()
+                .public_key(),
+        })
+    }
+
+    /// The private key at `index`, for keys given as WIF or xprv.
+    pub fn derive_private(&self, index: u32) -> Result<Option<PrivateKey>, DescriptorError> {
+        Ok(match &self.key {
+            KeyKind::Private(key) => Some(key.clone()),
+            KeyKind::Xpriv(xpriv) => {
+                Some(xpriv.derive_path(&self.full_path(index))?.to_private_key())
+            }
+            _ => None,
+        })
+    }
+
+    /// Origin of the key at `index` : the origin path followed by the derivation steps, for
+    /// PSBT BIP32 derivation fields.
+    pub fn key_source(&self, index: u32) -> Option<KeySource> {
+        let fingerprint = match (&self.origin, &self.key) {
+            (Some(origin), _) => origin.fingerprint,
+            (None, KeyKind::Xpub(xpub)) => xpub.fingerprint(),
+            (None, KeyKind::Xpriv(xpriv)) => xpriv.fingerprint(),
+            _ => return None,
+        };
+        let mut path = self
+            .origin
+            .as_ref()
+            .map(|origin| origin.path.0.clone())
+            .unwrap_or_default();
+        path.extend(self.full_path(index).0);
+        Some(KeySource {
+            fingerprint,
+            path: DerivationPath(path),
+        })
+    }
+
+    fn full_path(&self, index: u32) -> DerivationPath {
+        let mut path = self.path.clone();
+        match self.wildcard {
+            Wildcard::None => {}
+            Wildcard::Normal => path.0.push(ChildNumber::normal(index)),
+            Wildcard::Hardened => path.0.push(ChildNumber::hardened(index)),
+        }
+        path
+    }
+}
+
+impl FromStr for DescriptorKey {
+    type Err = DescriptorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| DescriptorError::InvalidKey(format!("{} ({})", s, reason));
+
+        let (origin, key) = match s.strip_prefix('[') {
+            Some(rest) => {
+                let (origin, key) = rest
+                    .split_once(']')
+                    .ok_or_else(|| invalid("unclosed origin"))?;
+                if origin.split('/').next().map(str::len) != Some(8) {
+                    return Err(invalid("fingerprint must be 8 hex characters"));
+                }
+                let origin = origin
+                    .parse::<KeySource>()
+                    .map_err(|_| invalid("invalid origin"))?;
+                (Some(origin), key)
+            }
+            None => (None, s),
+        };
+
+        let mut parts = key.split('/');
+        let key = parts.next().unwrap_or_default();
+        let steps: Vec<&str> = parts.collect();
+
+        let key = if let Ok(xpub) = key.parse::<Xpub>() {
+            KeyKind::Xpub(xpub)
+        } else if let Ok(xpriv) = key.parse::<Xpriv>() {
+            KeyKind::Xpriv(xpriv)
+        } else if !steps.is_empty() {
+            return Err(invalid("only extended keys have a derivation path"));
+        } else if let Ok(bytes) = hex::decode(key) {
+            match bytes.len() {
+                32 => {
+                    // Checks the key is on the curve.
+                    let mut compressed = vec![0x02];
+                    compressed.extend_from_slice(&bytes);
+                    PublicKey::from_slice(&compressed).map_err(|_| invalid("not a curve point"))?;
+                    KeyKind::XOnly(bytes.try_into().unwrap())
+                }
+                33 | 65 => KeyKind::Public(
+                    PublicKey::from_slice(&bytes).map_err(|_| invalid("not a curve point"))?,
+                ),
+                _ => return Err(invalid("public keys are 32, 33 or 65 bytes")),
+            }
+        } else {
+            KeyKind::Private(PrivateKey::from_wif(key).map_err(|e| invalid(&e.to_string()))?)
+        };
+
+        let (steps, wildcard) = match steps.split_last() {
+            Some((&"*", steps)) => (steps, Wildcard::Normal),
+            Some((&("*'" | "*h" | "*H"), steps)) => (steps, Wildcard::Hardened),
+            _ => (steps.as_slice(), Wildcard::None),
+        };
+        let path = if steps.is_empty() {
+            DerivationPath::default()
+        } else {
+            format!("m/{}", steps.join("/"))
+                .parse::<DerivationPath>()
+                .map_err(|_| invalid("invalid derivation path"))?
+        };
+
+        let hardened =
+            wildcard == Wildcard::Hardened || path.0.iter().any(ChildNumber::is_hardened);
+        if hardened && matches!(key, KeyKind::Xpub(_)) {
+            return Err(invalid("hardened derivation needs an xprv"));
+        }
+
+        Ok(DescriptorKey {
+            origin,
+            key,
+            path,
+            wildcard,
+        })
+    }
+}
+
+impl fmt::Display for DescriptorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "[{}]", origin)?;
+        }
+        match &self.key {
+            KeyKind::Public(key) => write!(f, "{}", hex::encode(key.to_bytes()))?,
+            KeyKind::XOnly(key) => write!(f, "{}", hex::encode(key))?,
+            KeyKind::Private(key) => write!(f, "{}", key.to_wif())?,
+            KeyKind::Xpub(xpub) => write!(f, "{}", xpub)?,
+            KeyKind::Xpriv(xpriv) => write!(f, "{}", xpriv)?,
+        }
+        for child in &self.path.0 {
+            write!(f, "/{}", child)?;
+        }
+        match self.wildcard {
+            Wildcard::None => Ok(()),
+            Wildcard::Normal => write!(f, "/*"),
+            Wildcard::Hardened => write!(f, "/*'"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_key {
+    use crate::descriptor::key::{DescriptorKey, Wildcard};
+    use crate::errors::DescriptorError;
+    use rstest::rstest;
+
+    // BIP32 test vector 1 master keys.
+    const XPUB: &str = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+    const XPRV: &str = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+
+    #[rstest]
+    #[case(
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        0,
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+    )]
+    #[case(
+        "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        0,
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+    )]
+    #[case(
+        "L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1",
+        0,
+        "03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd"
+    )]
+    #[case(&format!("{}/0'/1/2'", XPRV), 0, "0357bfe1e341d01c69fe5654309956cbea516822fba8a601743a012a7896ee8dc2")]
+    #[case(&format!("{}/0/*", XPUB), 0, "02756de182c5dd4b717ea87e693006da62dbb3cddaa4a5cad2ed1f5bbab755f0f5")]
+    fn test_derive(#[case] key: &str, #[case] index: u32, #[case] expected: &str) {
+        let key: DescriptorKey = key.parse().unwrap();
+        assert_eq!(hex::encode(key.derive(index).unwrap().to_bytes()), expected);
+    }
+
+    #[rstest]
+    #[case(&format!("[d34db33f/44'/0'/0']{}/1/*", XPUB), &format!("[d34db33f/44'/0'/0']{}/1/*", XPUB), Wildcard::Normal)]
+    #[case(&format!("{}/1h/*h", XPRV), &format!("{}/1'/*'", XPRV), Wildcard::Hardened)]
+    #[case(
+        "[deadbeef/0h]03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+        "[deadbeef/0']03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+        Wildcard::None
+    )]
+    fn test_round_trip(#[case] key: &str, #[case] expected: &str, #[case] wildcard: Wildcard) {
+        let parsed: DescriptorKey = key.parse().unwrap();
+        assert_eq!(parsed.wildcard, wildcard);
+        assert_eq!(parsed.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case(&format!("{}/1'/*", XPUB))]
+    #[case(&format!("{}/*'", XPUB))]
+    #[case("[deadbeef03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd")]
+    #[case("[dead/0]03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd")]
+    #[case("03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd/0")]
+    #[case("04a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd")]
+    #[case("xpubnotakey")]
+    fn test_invalid(#[case] key: &str) {
+        assert!(matches!(
+            key.parse::<DescriptorKey>(),
+            Err(DescriptorError::InvalidKey(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_key_source() {
+        let key: DescriptorKey = format!("[d34db33f/84'/0'/0']{}/1/*", XPUB).parse().unwrap();
+        assert_eq!(
+            key.key_source(7).unwrap().to_string(),
+            "d34db33f/84'/0'/0'/1/7"
+        );
+        let key: DescriptorKey = format!("{}/0/*", XPUB).parse().unwrap();
+        assert_eq!(key.key_source(3).unwrap().to_string(), "3442193e/0/3");
+    }
+}
@@ -0,0 +1,1190 @@
+// ==================================================
+// Output descriptors (BIP380 - BIP386)
+// ==================================================
+//
+// A descriptor describes an output script and how to spend it in one line :
+// `sh(wsh(multi(2,KEY,KEY)))`, `tr(KEY,{pk(KEY),pk(KEY)})`... Parsing checks the optional
+// checksum and where every function may appear, expanding a descriptor at an index derives its
+// ranged keys and gives :
+// - the scriptPubKey (and the redeem / witness script or taproot tree behind it)
+// - the shape of the scriptSig / witness spending it, as templates of signatures, keys and
+//   scripts (`SpendTemplate`)
+//
+// Supported : pk, pkh, wpkh, multi, sortedmulti, sh, wsh, tr (pk, multi_a and sortedmulti_a
+// leaves), addr and raw. Other expressions inside wsh() and tr() leaves are miniscripts.
+
+pub mod checksum;
+pub mod key;
+
+use crate::address::Address;
+use crate::descriptor::checksum::{checksum, verify_checksum};
+use crate::descriptor::key::DescriptorKey;
+use crate::errors::DescriptorError;
+use crate::keys::PrivateKey;
+use crate::miniscript::{Miniscript, ScriptContext};
+use crate::network::Network;
+use crate::psbt::Input;
+use crate::taproot::{TapTree, TaprootSpendInfo};
+use bee_vm::opcodes::utils::script_num_to_bytes;
+use bee_vm::script::encode::push_data;
+use bee_vm::transaction::sighash::TAPSCRIPT_LEAF_VERSION;
+use std::fmt;
+use std::str::FromStr;
+
+const OP_CHECKSIG: u8 = 0xac;
+const OP_CHECKMULTISIG: u8 = 0xae;
+const OP_CHECKSIGADD: u8 = 0xba;
+const OP_NUMEQUAL: u8 = 0x9c;
+
+/// Limit of keys of CHECKMULTISIG.
+pub const MAX_MULTISIG_KEYS: usize = 20;
+/// Bare multisig outputs are only standard up to 3 keys.
+pub const MAX_BARE_MULTISIG_KEYS: usize = 3;
+/// Limit of keys of a `multi_a()` leaf (the tapscript stack limit).
+pub const MAX_MULTI_A_KEYS: usize = 999;
+/// Redeem scripts are pushed by the scriptSig, so they can not exceed a push.
+pub const MAX_REDEEM_SCRIPT_SIZE: usize = 520;
+
+/// Where a script expression appears, it decides which functions and keys are allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Top,
+    Sh,
+    Wsh,
+    Tap,
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Context::Top => write!(f, "at the top level"),
+            Context::Sh => write!(f, "inside sh()"),
+            Context::Wsh => write!(f, "inside wsh()"),
+            Context::Tap => write!(f, "inside tr()"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Descriptor {
+    Pk(DescriptorKey),
+    Pkh(DescriptorKey),
+    Wpkh(DescriptorKey),
+    /// `multi()` / `sortedmulti()`, CHECKMULTISIG.
+    Multi {
+        threshold: usize,
+        keys: Vec<DescriptorKey>,
+        sorted: bool,
+    },
+    /// `multi_a()` / `sortedmulti_a()`, CHECKSIG / CHECKSIGADD tapscript leaves.
+    MultiA {
+        threshold: usize,
+        keys: Vec<DescriptorKey>,
+        sorted: bool,
+    },
+    Sh(Box<Descriptor>),
+    Wsh(Box<Descriptor>),
+    Tr {
+        internal_key: DescriptorKey,
+        tree: Option<TapTreeDescriptor>,
+    },
+    Addr(Address),
+    Raw(Vec<u8>),
+    /// Miniscript inside `wsh()` or a `tr()` leaf.
+    Miniscript(Miniscript),
+}
+
+/// The script tree of a `tr()` descriptor, `{left,right}` branches of leaf scripts.
+#[derive(Debug, Clone)]
+pub enum TapTreeDescriptor {
+    Leaf(Box<Descriptor>),
+    Branch(Box<TapTreeDescriptor>, Box<TapTreeDescriptor>),
+}
+
+/// One item of a scriptSig or witness template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitnessItem {
+    /// Signature of the public key.
+    Signature(Vec<u8>),
+    PublicKey(Vec<u8>),
+    /// Redeem script, witness script or taproot leaf.
+    Script(Vec<u8>),
+    ControlBlock(Vec<u8>),
+    /// Empty push : the CHECKMULTISIG dummy or a missing multi_a signature.
+    Empty,
+}
+
+/// The scriptSig and witness (bottom first) of one way to spend a descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendTemplate {
+    pub name: String,
+    pub script_sig: Vec<WitnessItem>,
+    pub witness: Vec<WitnessItem>,
+}
+
+impl Descriptor {
+    /// Parses a descriptor, its `#checksum` is checked when present.
+    pub fn parse(descriptor: &str) -> Result<Descriptor, DescriptorError> {
+        parse_script(verify_checksum(descriptor.trim())?, Context::Top)
+    }
+
+    /// The descriptor followed by its checksum.
+    pub fn to_string_with_checksum(&self) -> String {
+        let descriptor = self.to_string();
+        let checksum = checksum(&descriptor).expect("descriptors print in the checksum charset");
+        format!("{}#{}", descriptor, checksum)
+    }
+
+    /// Whether some key has a `*` wildcard.
+    pub fn is_ranged(&self) -> bool {
+        self.keys().iter().any(|key| key.is_ranged())
+    }
+
+    /// Every key expression, in the order they appear.
+    pub fn keys(&self) -> Vec<&DescriptorKey> {
+        match self {
+            Descriptor::Pk(key) | Descriptor::Pkh(key) | Descriptor::Wpkh(key) => vec![key],
+            Descriptor::Multi { keys, .. } | Descriptor::MultiA { keys, .. } => {
+                keys.iter().collect()
+            }
+            Descriptor::Sh(inner) | Descriptor::Wsh(inner) => inner.keys(),
+            Descriptor::Tr { internal_key, tree } => {
+                let mut keys = vec![internal_key];
+                if let Some(tree) = tree {
+                    keys.extend(tree.leaves().iter().flat_map(|leaf| leaf.keys()));
+                }
+                keys
+            }
+            Descriptor::Miniscript(miniscript) => miniscript.keys(),
+            Descriptor::Addr(_) | Descriptor::Raw(_) => vec![],
+        }
+    }
+
+    /// The scriptPubKey of the descriptor expanded at `index`.
+    pub fn script_pubkey(&self, index: u32) -> Result<Vec<u8>, DescriptorError> {
+        Ok(match self {
+            Descriptor::Pkh(key) => {
+                Address::p2pkh(&key.derive(index)?.to_bytes(), Network::Mainnet).script_pubkey()
+            }
+            Descriptor::Wpkh(key) => {
+                Address::p2wpkh(&key.derive(index)?.compressed_bytes(), Network::Mainnet)
+                    .script_pubkey()
+            }
+            Descriptor::Sh(inner) => {
+                Address::p2sh(&inner.script_pubkey(index)?, Network::Mainnet).script_pubkey()
+            }
+            Descriptor::Wsh(inner) => {
+                Address::p2wsh(&inner.script_pubkey(index)?, Network::Mainnet).script_pubkey()
+            }
+            Descriptor::Tr { .. } => self.taproot_spend_info(index)?.unwrap().script_pubkey(),
+            Descriptor::Addr(address) => address.script_pubkey(),
+            Descriptor::Raw(script) => script.clone(),
+            Descriptor::Pk(_)
+            | Descriptor::Multi { .. }
+            | Descriptor::MultiA { .. }
+            | Descriptor::Miniscript(_) => self.script(index, false)?,
+        })
+    }
+
+    /// Address of the scriptPubKey, bare scripts and `raw()` usually have none.
+    pub fn address(&self, index: u32, network: Network) -> Result<Address, DescriptorError> {
+        Ok(Address::from_script_pubkey(
+            &self.script_pubkey(index)?,
+            network,
+        )?)
+    }
+
+    /// Redeem script of a `sh()` descriptor.
+    pub fn redeem_script(&self, index: u32) -> Result<Option<Vec<u8>>, DescriptorError> {
+        match self {
+            Descriptor::Sh(inner) => Ok(Some(inner.script_pubkey(index)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Witness script of a `wsh()` or `sh(wsh())` descriptor.
+    pub fn witness_script(&self, index: u32) -> Result<Option<Vec<u8>>, DescriptorError> {
+        match self {
+            Descriptor::Sh(inner) => inner.witness_script(index),
+            Descriptor::Wsh(inner) => Ok(Some(inner.script_pubkey(index)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Output key, tree and control blocks of a `tr()` descriptor.
+    pub fn taproot_spend_info(
+        &self,
+        index: u32,
+    ) -> Result<Option<TaprootSpendInfo>, DescriptorError> {
+        let Descriptor::Tr { internal_key, tree } = self else {
+            return Ok(None);
+        };
+        let internal_key = internal_key.derive(index)?.x_only();
+        let tree = tree.as_ref().map(|tree| tree.tap_tree(index)).transpose()?;
+        Ok(Some(TaprootSpendInfo::new(&internal_key, tree.as_ref())?))
+    }
+
+    /// Private keys of the descriptor at `index`, from its WIF and xprv keys.
+    pub fn private_keys(&self, index: u32) -> Result<Vec<PrivateKey>, DescriptorError> {
+        let mut private_keys = vec![];
+        for key in self.keys() {
+            private_keys.extend(key.derive_private(index)?);
+        }
+        Ok(private_keys)
+    }
+
+    /// Updater role : adds the redeem / witness script, key origins and taproot tree of the
+    /// descriptor at `index` to a PSBT input. The UTXO is left to the caller.
+    pub fn update_psbt_input(&self, input: &mut Input, index: u32) -> Result<(), DescriptorError> {
+        input.redeem_script = self.redeem_script(index)?;
+        input.witness_script = self.witness_script(index)?;
+
+        let Some(info) = self.taproot_spend_info(index)? else {
+            for key in self.keys() {
+                if let Some(source) = key.key_source(index) {
+                    input
+                        .bip32_derivation
+                        .insert(key.derive(index)?.to_bytes(), source);
+                }
+            }
+            return Ok(());
+        };
+        input.tap_internal_key = Some(info.internal_key);
+        input.tap_merkle_root = info.merkle_root;
+        for (leaf_index, leaf) in info.leaves.iter().enumerate() {
+            input.tap_leaf_scripts.insert(
+                info.control_block(leaf_index)?,
+                (leaf.script.clone(), leaf.leaf_version),
+            );
+        }
+        Ok(())
+    }
+
+    /// The scriptSig / witness shapes spending the descriptor at `index`. Multisigs are
+    /// satisfied by their first `threshold` keys, `tr()` lists its key path then every leaf.
+    /// `addr()` and `raw()` do not say how they are spent and have no template, miniscripts
+    /// depend on the secrets at hand and are satisfied by `Miniscript::satisfy` instead.
+    pub fn spend_templates(&self, index: u32) -> Result<Vec<SpendTemplate>, DescriptorError> {
+        let template = |name: &str, script_sig, witness| SpendTemplate {
+            name: name.to_string(),
+            script_sig,
+            witness,
+        };
+
+        Ok(match self {
+            Descriptor::Addr(_) | Descriptor::Raw(_) | Descriptor::Miniscript(_) => vec![],
+            Descriptor::Wsh(inner) if matches!(inner.as_ref(), Descriptor::Miniscript(_)) => {
+                vec![]
+            }
+            Descriptor::Wpkh(_) => vec![template("wpkh", vec![], self.script_items(index, false)?)],
+            Descriptor::Sh(inner) => {
+                let redeem_script = WitnessItem::Script(inner.script_pubkey(index)?);
+                match inner.as_ref() {
+                    Descriptor::Wpkh(_) | Descriptor::Wsh(_) => inner
+                        .spend_templates(index)?
+                        .into_iter()
+                        .map(|inner| {
+                            template(
+                                &format!("sh-{}", inner.name),
+                                vec![redeem_script.clone()],
+                                inner.witness,
+                            )
+                        })
+                        .collect(),
+                    _ => {
+                        let mut script_sig = inner.script_items(index, false)?;
+                        script_sig.push(redeem_script);
+                        vec![template(
+                            &format!("sh-{}", inner.name()),
+                            script_sig,
+                            vec![],
+                        )]
+                    }
+                }
+            }
+            Descriptor::Wsh(inner) => {
+                let mut witness = inner.script_items(index, false)?;
+                witness.push(WitnessItem::Script(inner.script_pubkey(index)?));
+                vec![template(&format!("wsh-{}", inner.name()), vec![], witness)]
+            }
+            Descriptor::Tr { tree, .. } => {
+                let info = self.taproot_spend_info(index)?.unwrap();
+                let mut templates = vec![template(
+                    "key path",
+                    vec![],
+                    vec![WitnessItem::Signature(info.output_key.to_vec())],
+                )];
+                let leaves = tree.as_ref().map(|tree| tree.leaves()).unwrap_or_default();
+                for (leaf_index, leaf) in leaves.iter().enumerate() {
+                    if matches!(leaf, Descriptor::Miniscript(_)) {
+                        continue;
+                    }
+                    let mut witness = leaf.script_items(index, true)?;
+                    witness.push(WitnessItem::Script(info.leaves[leaf_index].script.clone()));
+                    witness.push(WitnessItem::ControlBlock(info.control_block(leaf_index)?));
+                    templates.push(template(&format!("leaf {}", leaf_index), vec![], witness));
+                }
+                templates
+            }
+            Descriptor::Pk(_)
+            | Descriptor::Pkh(_)
+            | Descriptor::Multi { .. }
+            | Descriptor::MultiA { .. } => {
+                vec![template(
+                    self.name(),
+                    self.script_items(index, false)?,
+                    vec![],
+                )]
+            }
+        })
+    }
+
+    /// Function name of the descriptor.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Descriptor::Pk(_) => "pk",
+            Descriptor::Pkh(_) => "pkh",
+            Descriptor::Wpkh(_) => "wpkh",
+            Descriptor::Multi { sorted: false, .. } => "multi",
+            Descriptor::Multi { sorted: true, .. } => "sortedmulti",
+            Descriptor::MultiA { sorted: false, .. } => "multi_a",
+            Descriptor::MultiA { sorted: true, .. } => "sortedmulti_a",
+            Descriptor::Sh(_) => "sh",
+            Descriptor::Wsh(_) => "wsh",
+            Descriptor::Tr { .. } => "tr",
+            Descriptor::Addr(_) => "addr",
+            Descriptor::Raw(_) => "raw",
+            Descriptor::Miniscript(_) => "miniscript",
+        }
+    }
+
+    /// Script of a key script (`pk`, `multi`, `multi_a`), tapscripts use x-only keys.
+    fn script(&self, index: u32, tap: bool) -> Result<Vec<u8>, DescriptorError> {
+        let mut script = vec![];
+        match self {
+            Descriptor::Pk(key) => {
+                push_key(&mut script, key, index, tap)?;
+                script.push(OP_CHECKSIG);
+            }
+            Descriptor::Multi { threshold, .. } => {
+                push_int(&mut script, *threshold as i64)?;
+                let keys = self.derived_keys(index, false)?;
+                for key in &keys {
+                    push_data(&mut script, key)?;
+                }
+                push_int(&mut script, keys.len() as i64)?;
+                script.push(OP_CHECKMULTISIG);
+            }
+            Descriptor::MultiA { threshold, .. } => {
+                for (position, key) in self.derived_keys(index, true)?.iter().enumerate() {
+                    push_data(&mut script, key)?;
+                    script.push(if position == 0 {
+                        OP_CHECKSIG
+                    } else {
+                        OP_CHECKSIGADD
+                    });
+                }
+                push_int(&mut script, *threshold as i64)?;
+                script.push(OP_NUMEQUAL);
+            }
+            Descriptor::Miniscript(miniscript) => return Ok(miniscript.encode(index)?),
+            // Other descriptors are not key scripts, their script is the scriptPubKey.
+            _ => return self.script_pubkey(index),
+        }
+        Ok(script)
+    }
+
+    /// Stack items (bottom first) satisfying a key script, tapscripts sign with x-only keys.
+    fn script_items(&self, index: u32, tap: bool) -> Result<Vec<WitnessItem>, DescriptorError> {
+        Ok(match self {
+            Descriptor::Pk(key) => {
+                let public_key = key.derive(index)?;
+                vec![WitnessItem::Signature(match tap {
+                    true => public_key.x_only().to_vec(),
+                    false => public_key.to_bytes(),
+                })]
+            }
+            Descriptor::Pkh(key) | Descriptor::Wpkh(key) => {
+                let public_key = key.derive(index)?.to_bytes();
+                vec![
+                    WitnessItem::Signature(public_key.clone()),
+                    WitnessItem::PublicKey(public_key),
+                ]
+            }
+            Descriptor::Multi { threshold, .. } => {
+                let keys = self.derived_keys(index, false)?;
+                let mut items = vec![WitnessItem::Empty];
+                items.extend(
+                    keys.into_iter()
+                        .take(*threshold)
+                        .map(WitnessItem::Signature),
+                );
+                items
+            }
+            Descriptor::MultiA { threshold, .. } => {
+                // The first key is checked first, so its signature is on top of the stack.
+                let keys = self.derived_keys(index, true)?;
+                keys.into_iter()
+                    .enumerate()
+                    .map(|(position, key)| match position < *threshold {
+                        true => WitnessItem::Signature(key),
+                        false => WitnessItem::Empty,
+                    })
+                    .rev()
+                    .collect()
+            }
+            _ => vec![],
+        })
+    }
+
+    /// Serialized keys of a multisig, sorted for `sortedmulti` / `sortedmulti_a`.
+    fn derived_keys(&self, index: u32, tap: bool) -> Result<Vec<Vec<u8>>, DescriptorError> {
+        let (Descriptor::Multi { keys, sorted, .. } | Descriptor::MultiA { keys, sorted, .. }) =
+            self
+        else {
+            return Ok(vec![]);
+        };
+        let mut keys = keys
+            .iter()
+            .map(|key| {
+                let public_key = key.derive(index)?;
+                Ok(match tap {
+                    true => public_key.x_only().to_vec(),
+                    false => public_key.to_bytes(),
+                })
+            })
+            .collect::<Result<Vec<_>, DescriptorError>>()?;
+        if *sorted {
+            keys.sort();
+        }
+        Ok(keys)
+    }
+}
+
+impl TapTreeDescriptor {
+    /// Leaf scripts from left to right.
+    pub fn leaves(&self) -> Vec<&Descriptor> {
+        match self {
+            TapTreeDescriptor::Leaf(leaf) => vec![leaf],
+            TapTreeDescriptor::Branch(left, right) => {
+                let mut leaves = left.leaves();
+                leaves.extend(right.leaves());
+                leaves
+            }
+        }
+    }
+
+    fn tap_tree(&self, index: u32) -> Result<TapTree, DescriptorError> {
+        Ok(match self {
+            TapTreeDescriptor::Leaf(leaf) => TapTree::Leaf {
+                script: leaf.script(index, true)?,
+                leaf_version: TAPSCRIPT_LEAF_VERSION,
+            },
+            TapTreeDescriptor::Branch(left, right) => {
+                TapTree::branch(left.tap_tree(index)?, right.tap_tree(index)?)
+            }
+        })
+    }
+}
+
+impl FromStr for Descriptor {
+    type Err = DescriptorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Descriptor::parse(s)
+    }
+}
+
+impl fmt::Display for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Descriptor::Pk(key) | Descriptor::Pkh(key) | Descriptor::Wpkh(key) => {
+                write!(f, "{}({})", self.name(), key)
+            }
+            Descriptor::Multi {
+                threshold, keys, ..
+            }
+            | Descriptor::MultiA {
+                threshold, keys, ..
+            } => {
+                write!(f, "{}({}", self.name(), threshold)?;
+                for key in keys {
+                    write!(f, ",{}", key)?;
+                }
+                write!(f, ")")
+            }
+            Descriptor::Sh(inner) | Descriptor::Wsh(inner) => {
+                write!(f, "{}({})", self.name(), inner)
+            }
+            Descriptor::Tr {
+                internal_key,
+                tree: None,
+            } => write!(f, "tr({})", internal_key),
+            Descriptor::Tr {
+                internal_key,
+                tree: Some(tree),
+            } => write!(f, "tr({},{})", internal_key, tree),
+            Descriptor::Addr(address) => write!(f, "addr({})", address),
+            Descriptor::Raw(script) => write!(f, "raw({})", hex::encode(script)),
+            Descriptor::Miniscript(miniscript) => write!(f, "{}", miniscript),
+        }
+    }
+}
+
+impl fmt::Display for TapTreeDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapTreeDescriptor::Leaf(leaf) => write!(f, "{}", leaf),
+            TapTreeDescriptor::Branch(left, right) => write!(f, "{{{},{}}}", left, right),
+        }
+    }
+}
+
+impl fmt::Display for WitnessItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessItem::Signature(key) => write!(f, "<sig({})>", hex::encode(key)),
+            WitnessItem::PublicKey(key) => write!(f, "{}", hex::encode(key)),
+            WitnessItem::Script(script) | WitnessItem::ControlBlock(script) => {
+                write!(f, "{}", hex::encode(script))
+            }
+            WitnessItem::Empty => write!(f, "<>"),
+        }
+    }
+}
+
+fn push_key(
+    script: &mut Vec<u8>,
+    key: &DescriptorKey,
+    index: u32,
+    tap: bool,
+) -> Result<(), DescriptorError> {
+    let public_key = key.derive(index)?;
+    match tap {
+        true => push_data(script, &public_key.x_only())?,
+        false => push_data(script, &public_key.to_bytes())?,
+    }
+    Ok(())
+}
+
+/// Pushes a number with OP_1 - OP_16 or a script number.
+pub(crate) fn push_int(
+    script: &mut Vec<u8>,
+    number: i64,
+) -> Result<(), bee_vm::errors::ScriptError> {
+    match number {
+        1..=16 => script.push(0x50 + number as u8),
+        _ => push_data(script, &script_num_to_bytes(number))?,
+    }
+    Ok(())
+}
+
+/// Splits `name(args)` into its name and arguments.
+pub(crate) fn split_call(expression: &str) -> Result<(&str, &str), DescriptorError> {
+    let (name, rest) = expression
+        .split_once('(')
+        .ok_or_else(|| DescriptorError::Syntax(format!("expected a function : {}", expression)))?;
+    let args = rest
+        .strip_suffix(')')
+        .ok_or_else(|| DescriptorError::Syntax(format!("unclosed function : {}", expression)))?;
+    Ok((name, args))
+}
+
+/// Splits arguments on the commas that are not nested in `()`, `[]` or `{}`.
+pub(crate) fn split_args(args: &str) -> Result<Vec<&str>, DescriptorError> {
+    let mut parts = vec![];
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (position, ch) in args.char_indices() {
+        match ch {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&args[start..position]);
+                start = position + 1;
+            }
+            _ => {}
+        }
+        if depth < 0 {
+            return Err(DescriptorError::Syntax(format!(
+                "unbalanced brackets : {}",
+                args
+            )));
+        }
+    }
+    if depth != 0 {
+        return Err(DescriptorError::Syntax(format!(
+            "unbalanced brackets : {}",
+            args
+        )));
+    }
+    parts.push(&args[start..]);
+    Ok(parts)
+}
+
+fn parse_script(expression: &str, context: Context) -> Result<Descriptor, DescriptorError> {
+    let miniscript_context = match context {
+        Context::Wsh => Some(ScriptContext::Segwitv0),
+        Context::Tap => Some(ScriptContext::Tap),
+        _ => None,
+    };
+    let (name, args) = match (split_call(expression), miniscript_context) {
+        // Miniscripts like `0`, `1` or `n:0` are not function calls.
+        (Err(_), Some(context)) if !expression.contains('(') => {
+            return Ok(Descriptor::Miniscript(Miniscript::parse(
+                expression, context,
+            )?));
+        }
+        (call, _) => call?,
+    };
+    let allowed = match name {
+        "pk" => true,
+        // Inside tr() pkh() is the miniscript fragment, hashing the x-only key.
+        "pkh" if context == Context::Tap => {
+            return Ok(Descriptor::Miniscript(Miniscript::parse(
+                expression,
+                ScriptContext::Tap,
+            )?));
+        }
+        "pkh" | "multi" | "sortedmulti" => context != Context::Tap,
+        "wpkh" => matches!(context, Context::Top | Context::Sh),
+        "wsh" => matches!(context, Context::Top | Context::Sh),
+        "multi_a" | "sortedmulti_a" => context == Context::Tap,
+        "sh" | "tr" | "addr" | "raw" => context == Context::Top,
+        _ => {
+            let context = miniscript_context
+                .ok_or_else(|| DescriptorError::UnknownFunction(name.to_string()))?;
+            return Ok(Descriptor::Miniscript(Miniscript::parse(
+                expression, context,
+            )?));
+        }
+    };
+    if !allowed {
+        return Err(DescriptorError::InvalidContext {
+            function: name.to_string(),
+            context: context.to_string(),
+        });
+    }
+
+    let single = |args: &str| -> Result<(), DescriptorError> {
+        match split_args(args)?.len() {
+            1 => Ok(()),
+            found => Err(DescriptorError::Syntax(format!(
+                "{}() takes 1 argument, found {}",
+                name, found
+            ))),
+        }
+    };
+
+    let descriptor = match name {
+        "pk" => {
+            single(args)?;
+            Descriptor::Pk(parse_key(args, context)?)
+        }
+        "pkh" => {
+            single(args)?;
+            Descriptor::Pkh(parse_key(args, context)?)
+        }
+        "wpkh" => {
+            single(args)?;
+            Descriptor::Wpkh(parse_key(args, Context::Wsh)?)
+        }
+        "multi" | "sortedmulti" | "multi_a" | "sortedmulti_a" => {
+            let parts = split_args(args)?;
+            let (threshold, keys) = parts
+                .split_first()
+                .ok_or_else(|| DescriptorError::Syntax(format!("{}() needs a threshold", name)))?;
+            let threshold: usize = threshold.parse().map_err(|_| {
+                DescriptorError::Syntax(format!("invalid threshold : {}", threshold))
+            })?;
+            let keys = keys
+                .iter()
+                .map(|key| parse_key(key, context))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let max_keys = match (name, context) {
+                ("multi_a" | "sortedmulti_a", _) => MAX_MULTI_A_KEYS,
+                (_, Context::Top) => MAX_BARE_MULTISIG_KEYS,
+                _ => MAX_MULTISIG_KEYS,
+            };
+            if keys.is_empty() || keys.len() > max_keys {
+                return Err(DescriptorError::InvalidKeyCount {
+                    found: keys.len(),
+                    max: max_keys,
+                    context: context.to_string(),
+                });
+            }
+            if threshold == 0 || threshold > keys.len() {
+                return Err(DescriptorError::InvalidThreshold {
+                    threshold,
+                    keys: keys.len(),
+                });
+            }
+
+            let sorted = name.starts_with("sorted");
+            match name.ends_with("_a") {
+                true => Descriptor::MultiA {
+                    threshold,
+                    keys,
+                    sorted,
+                },
+                false => Descriptor::Multi {
+                    threshold,
+                    keys,
+                    sorted,
+                },
+            }
+        }
+        "sh" => {
+            single(args)?;
+            let inner = parse_script(args, Context::Sh)?;
+            if !matches!(inner, Descriptor::Wpkh(_) | Descriptor::Wsh(_)) {
+                let size = inner.script_pubkey(0)?.len();
+                if size > MAX_REDEEM_SCRIPT_SIZE {
+                    return Err(DescriptorError::ScriptTooLarge(size));
+                }
+            }
+            Descriptor::Sh(Box::new(inner))
+        }
+        "wsh" => {
+            single(args)?;
+            Descriptor::Wsh(Box::new(parse_script(args, Context::Wsh)?))
+        }
+        "tr" => {
+            let parts = split_args(args)?;
+            let (internal_key, tree) = match parts.as_slice() {
+                [key] => (parse_key(key, Context::Tap)?, None),
+                [key, tree] => (parse_key(key, Context::Tap)?, Some(parse_tree(tree)?)),
+                _ => {
+                    return Err(DescriptorError::Syntax(format!(
+                        "tr() takes 1 or 2 arguments : {}",
+                        args
+                    )))
+                }
+            };
+            let descriptor = Descriptor::Tr { internal_key, tree };
+            // Checks the tree depth.
+            descriptor.taproot_spend_info(0)?;
+            descriptor
+        }
+        "addr" => Descriptor::Addr(Address::decode(args)?),
+        "raw" => Descriptor::Raw(
+            hex::decode(args)
+                .map_err(|_| DescriptorError::Syntax(format!("invalid hex : {}", args)))?,
+        ),
+        _ => unreachable!("checked above"),
+    };
+    Ok(descriptor)
+}
+
+fn parse_tree(tree: &str) -> Result<TapTreeDescriptor, DescriptorError> {
+    let Some(branch) = tree.strip_prefix('{') else {
+        return Ok(TapTreeDescriptor::Leaf(Box::new(parse_script(
+            tree,
+            Context::Tap,
+        )?)));
+    };
+    let branch = branch
+        .strip_suffix('}')
+        .ok_or_else(|| DescriptorError::Syntax(format!("unclosed branch : {}", tree)))?;
+    match split_args(branch)?.as_slice() {
+        [left, right] => Ok(TapTreeDescriptor::Branch(
+            Box::new(parse_tree(left)?),
+            Box::new(parse_tree(right)?),
+        )),
+        _ => Err(DescriptorError::Syntax(format!(
+            "branches have 2 children : {}",
+            tree
+        ))),
+    }
+}
+
+/// Parses a key, segwit scripts reject uncompressed keys and only tr() takes x-only keys.
+fn parse_key(key: &str, context: Context) -> Result<DescriptorKey, DescriptorError> {
+    let parsed: DescriptorKey = key.parse()?;
+    if parsed.is_uncompressed() && matches!(context, Context::Wsh | Context::Tap) {
+        return Err(DescriptorError::UncompressedKey(key.to_string()));
+    }
+    if parsed.is_x_only() && context != Context::Tap {
+        return Err(DescriptorError::InvalidKey(format!(
+            "{} (x-only keys are only allowed inside tr())",
+            key
+        )));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod test_descriptor {
+    use crate::descriptor::key::DescriptorKey;
+    use crate::descriptor::{Descriptor, WitnessItem};
+    use crate::errors::{DescriptorError, MiniscriptError};
+    use crate::network::Network;
+    use crate::psbt::finalize::finalize;
+    use crate::psbt::sign::sign;
+    use crate::psbt::{Input, Output, Psbt};
+    use bee_vm::transaction::{OutPoint, TxOut};
+    use rstest::rstest;
+
+    // BIP32 test vector 1 master keys.
+    const XPUB: &str = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+    const XPRV: &str = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+    const WIF: &str = "L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1";
+    const X_ONLY: &str = "a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd";
+    const UNCOMPRESSED: &str = "04a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd5b8dec5235a0fa8722476c7709c02559e3aa73aa03918ba2d492eea75abea235";
+
+    fn with_keys(descriptor: &str) -> String {
+        descriptor
+            .replace("XPUB", XPUB)
+            .replace("XPRV", XPRV)
+            .replace("WIF", WIF)
+    }
+
+    // Expected scripts and addresses from rust-miniscript.
+    #[rstest]
+    #[case(
+        "pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)",
+        0,
+        "210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798ac",
+        None
+    )]
+    #[case("pk(5KYZdUEo39z3FPrtuX2QbbwGnNP5zTd7yyr2SC1j299sBCnWjss)", 0, "4104a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd5b8dec5235a0fa8722476c7709c02559e3aa73aa03918ba2d492eea75abea235ac", None)]
+    #[case(
+        "pkh(WIF)",
+        0,
+        "76a9149a1c78a507689f6f54b847ad1cef1e614ee23f1e88ac",
+        Some("1F3sAm6ZtwLAUnj7d38pGFxtP3RVEvtsbV")
+    )]
+    #[case(
+        "wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)",
+        0,
+        "00147dd65592d0ab2fe0d0257d571abf032cd9db93dc",
+        Some("bc1q0ht9tyks4vh7p5p904t340cr9nvahy7u3re7zg")
+    )]
+    #[case(
+        "sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))",
+        0,
+        "a914cc6ffbc0bf31af759451068f90ba7a0272b6b33287",
+        Some("3LKyvRN6SmYXGBNn8fcQvYxW9MGKtwcinN")
+    )]
+    #[case("sh(multi(2,022f01e5e15cca351daff3843fb70f3c2f0a1bdd05e5af888a67784ef3e10a2a01,03acd484e2f0c7f65309ad178a9f559abde09796974c57e714c35f110dfc27ccbe))", 0, "a914a6a8b030a38762f4c1f5cbe387b61a3c5da5cd2687", Some("3GtEB3yg3r5de2cDJG48SkQwxfxJumKQdN"))]
+    #[case("wsh(multi(2,03a0434d9e47f3c86235477c7b1ae6ae5d3442d49b1943c2b752a68e2a47e247c7,03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb,03d01115d548e7561b15c38f004d734633687cf4419620095bc5b0f47070afe85a))", 0, "0020773d709598b76c4e3b575c08aad40658963f9322affc0f8c28d1d9a68d0c944a", Some("bc1qwu7hp9vckakyuw6htsy244qxtztrlyez4l7qlrpg68v6drgvj39qn4zazc"))]
+    #[case(
+        "pkh([d34db33f/44'/0'/0']XPUB/1/*)",
+        0,
+        "76a914f09cb16010dc6d58dfafee3d3f9f027dc03be2c488ac",
+        Some("1NwEtFZ6Td7cpKaJtYoeryS6avP2TUkSMh")
+    )]
+    #[case(
+        "pkh([d34db33f/44'/0'/0']XPUB/1/*)",
+        5,
+        "76a914d600d660d57f023a4c56d96cc9e9edff3df54b7f88ac",
+        Some("1LWYcEi1bUMfiCoXJwTXF4q8iRGnqhb8jr")
+    )]
+    #[case(
+        "wpkh(XPUB/0/*)",
+        0,
+        "00140d1c9c02a7be9ba8b8842804feb961481ce6561b",
+        Some("bc1qp5wfcq48h6d63wyy9qz0awtpfqwwv4sma86mhz")
+    )]
+    #[case(
+        "sh(wsh(sortedmulti(1,XPUB/1/0/*,XPUB/0/0/*)))",
+        0,
+        "a914ff697be44f3558de8e7c8eb4893a6e2a8692d75b87",
+        Some("3QyWbcZoqRotypWzmwueR5prgNYau1vpXW")
+    )]
+    #[case(
+        "sh(wsh(sortedmulti(1,XPUB/1/0/*,XPUB/0/0/*)))",
+        1,
+        "a91400448b847977dc9762a101ee14218f77d331e55387",
+        Some("31iS38gDQTVBUFWKSUXHucLAgYZfmxRXRT")
+    )]
+    #[case(
+        "tr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)",
+        0,
+        "512077aab6e066f8a7419c5ab714c12c67d25007ed55a43cadcacb4d7a970a093f11",
+        Some("bc1pw74tdcrxlzn5r8z6ku2vztr86fgq0m245s72mjktf4afwzsf8ugs0gs8zu")
+    )]
+    #[case(
+        "tr(XPUB/0/*)",
+        3,
+        "512016d4f9600c2fa75edd5e8271b72c8b24968200c79c24007f9a59d2aa807e011a",
+        Some("bc1pzm20jcqv97n4ah27sfcmwtytyjtgyqx8nsjqqlu6t8f24qr7qydq4r8zcl")
+    )]
+    #[case(
+        "tr(WIF,pk(669b8afcec803a0d323e9a17f3ea8e68e8abe5a278020a929adbec52421adbd0))",
+        0,
+        "512017cf18db381d836d8923b1bdb246cfcd818da1a9f0e6e7907f187f0b2f937754",
+        Some("bc1pzl833kecrkpkmzfrkx7my3k0ekqcmgdf7rnw0yrlrplsktunwa2q7vxsg5")
+    )]
+    #[case("tr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd,{pk(XPUB/0/*),{multi_a(2,XPUB/1/*,XPUB/2/*),multi_a(1,XPUB/3/*,XPUB/4/*)}})", 2, "512043a70677555133e3f77814730685b400c5a8f51de960e04d2251b4fec22c894a", Some("bc1pgwnsva642ye78amcz3esdpd5qrz63agaa9swqnfz2x60as3v399qjh979m"))]
+    #[case(
+        "addr(bc1q0ht9tyks4vh7p5p904t340cr9nvahy7u3re7zg)",
+        0,
+        "00147dd65592d0ab2fe0d0257d571abf032cd9db93dc",
+        Some("bc1q0ht9tyks4vh7p5p904t340cr9nvahy7u3re7zg")
+    )]
+    #[case("raw(6a0401020304)", 0, "6a0401020304", None)]
+    #[case(
+        "wsh(and_v(v:pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798),older(144)))",
+        0,
+        "0020b401bd478b827e698f59d9296e5192913a0d1bef57614486cf07eb93d3a4b8d0",
+        Some("bc1qksqm63utsflxnr6emy5ku5vjjyaq6xl02as5fpk0ql4e85ayhrgq9eg35a")
+    )]
+    #[case(
+        "tr(50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0,and_v(v:pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798),older(144)))",
+        0,
+        "51201d5d6f587502c9c5200963c3713042c6ec2a8ce60d587767fa4719aaa8008c0d",
+        Some("bc1pr4wk7kr4qtyu2gqfv0phzvzzcmkz4r8xp4v8wel6guv642qq3sxs2ur555")
+    )]
+    #[case(
+        "tr(50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0,{pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798),n:0})",
+        0,
+        "512069897359da922b4f289a65c94b6361551bb8ebc06bd4756d729d2f39253abc8a",
+        Some("bc1pdxyhxkw6jg4572y6vhy5kcmp25dm367qd0282mtjn5hnjff6hj9qfnstfy")
+    )]
+    #[case(
+        "tr(50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0,pkh(79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798))",
+        0,
+        "51201856a4ca0e36c0eaeeb7621361e043eb8ff0805ab9768922f0e200bb3db3978f",
+        Some("bc1prpt2fjswxmqw4m4hvgfkrczraw8lpqz6h9mgjghsugqtk0dnj78srdwedx")
+    )]
+    fn test_script_pubkey(
+        #[case] descriptor: &str,
+        #[case] index: u32,
+        #[case] script_pubkey: &str,
+        #[case] address: Option<&str>,
+    ) -> color_eyre::Result<()> {
+        let descriptor: Descriptor = with_keys(descriptor).parse()?;
+        assert_eq!(hex::encode(descriptor.script_pubkey(index)?), script_pubkey);
+        assert_eq!(
+            descriptor
+                .address(index, Network::Mainnet)
+                .ok()
+                .map(|address| address.to_string())
+                .as_deref(),
+            address
+        );
+        Ok(())
+    }
+
+    // Checksums from rust-miniscript.
+    #[rstest]
+    #[case("pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)#gn28ywm7")]
+    #[case("pkh([d34db33f/44'/0'/0']XPUB/1/*)#65q0dqxx")]
+    #[case("sh(wsh(sortedmulti(1,XPUB/1/0/*,XPUB/0/0/*)))#neu9gcag")]
+    #[case("tr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd,{pk(XPUB/0/*),{multi_a(2,XPUB/1/*,XPUB/2/*),multi_a(1,XPUB/3/*,XPUB/4/*)}})#56f8e9ph")]
+    #[case("sh(wsh(or_d(pk(XPUB/0/*),and_v(v:pk(XPUB/1/*),older(1000)))))#9jvnjjh2")]
+    fn test_round_trip(#[case] descriptor: &str) -> color_eyre::Result<()> {
+        let descriptor = with_keys(descriptor);
+        let parsed: Descriptor = descriptor.parse()?;
+        assert_eq!(parsed.to_string_with_checksum(), descriptor);
+        assert!(parsed.is_ranged() != descriptor.starts_with("pk("));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("wsh({}(2,{}))", "sortedmulti", false)]
+    #[case("tr(X_ONLY,{}(2,{}))", "sortedmulti_a", true)]
+    fn test_sorted_keys(
+        #[case] template: &str,
+        #[case] sorted: &str,
+        #[case] x_only: bool,
+    ) -> color_eyre::Result<()> {
+        let keys = ["XPUB/3/*", "XPUB/4/*", "XPUB/5/*"];
+        let index = 1;
+        let describe = |function: &str, keys: &[&str]| -> color_eyre::Result<Descriptor> {
+            let descriptor = template
+                .replacen("{}", function, 1)
+                .replacen("{}", &keys.join(","), 1)
+                .replace("X_ONLY", X_ONLY);
+            Ok(with_keys(&descriptor).parse()?)
+        };
+
+        let mut by_key = vec![];
+        for key in keys {
+            let public_key = with_keys(key).parse::<DescriptorKey>()?.derive(index)?;
+            let serialized = match x_only {
+                true => public_key.x_only().to_vec(),
+                false => public_key.to_bytes(),
+            };
+            by_key.push((serialized, key));
+        }
+        by_key.sort();
+        let ordered: Vec<&str> = by_key.into_iter().map(|(_, key)| key).collect();
+
+        assert_eq!(
+            describe(sorted, &keys)?.script_pubkey(index)?,
+            describe(sorted.trim_start_matches("sorted"), &ordered)?.script_pubkey(index)?
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)#8zl0zxmb", DescriptorError::InvalidChecksum { expected: "8zl0zxma".to_string(), found: "8zl0zxmb".to_string() })]
+    #[case("foo(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)", DescriptorError::UnknownFunction("foo".to_string()))]
+    #[case("wsh(wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9))", DescriptorError::InvalidContext { function: "wpkh".to_string(), context: "inside wsh()".to_string() })]
+    #[case("sh(sh(pkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)))", DescriptorError::InvalidContext { function: "sh".to_string(), context: "inside sh()".to_string() })]
+    #[case("wsh(tr(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9))", DescriptorError::InvalidContext { function: "tr".to_string(), context: "inside wsh()".to_string() })]
+    #[case("tr(X_ONLY,multi(1,02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9))", DescriptorError::InvalidContext { function: "multi".to_string(), context: "inside tr()".to_string() })]
+    #[case("multi_a(1,02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)", DescriptorError::InvalidContext { function: "multi_a".to_string(), context: "at the top level".to_string() })]
+    #[case("wpkh(UNCOMPRESSED)", DescriptorError::UncompressedKey("UNCOMPRESSED".to_string()))]
+    #[case("wsh(pk(UNCOMPRESSED))", DescriptorError::UncompressedKey("UNCOMPRESSED".to_string()))]
+    #[case("wsh(multi(3,XPUB/0/*,XPUB/1/*))", DescriptorError::InvalidThreshold { threshold: 3, keys: 2 })]
+    #[case("wsh(multi(0,XPUB/0/*,XPUB/1/*))", DescriptorError::InvalidThreshold { threshold: 0, keys: 2 })]
+    #[case("multi(1,XPUB/0/*,XPUB/1/*,XPUB/2/*,XPUB/3/*)", DescriptorError::InvalidKeyCount { found: 4, max: 3, context: "at the top level".to_string() })]
+    #[case("sh(multi(1,XPUB/0/*,XPUB/1/*,XPUB/2/*,XPUB/3/*,XPUB/4/*,XPUB/5/*,XPUB/6/*,XPUB/7/*,XPUB/8/*,XPUB/9/*,XPUB/10/*,XPUB/11/*,XPUB/12/*,XPUB/13/*,XPUB/14/*,XPUB/15/*))", DescriptorError::ScriptTooLarge(547))]
+    #[case("tr(X_ONLY,{pk(X_ONLY)})", DescriptorError::Syntax("branches have 2 children : {pk(X_ONLY)}".to_string()))]
+    #[case("wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9", DescriptorError::Syntax("unclosed function : wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9".to_string()))]
+    #[case("pkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9,02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)", DescriptorError::Syntax("pkh() takes 1 argument, found 2".to_string()))]
+    #[case("wsh(older(144))", DescriptorError::Miniscript(MiniscriptError::Insane("some satisfactions need no signature".to_string())))]
+    #[case("sh(and_v(v:pk(X_ONLY),older(144)))", DescriptorError::UnknownFunction("and_v".to_string()))]
+    fn test_invalid(#[case] descriptor: &str, #[case] expected: DescriptorError) {
+        let descriptor = with_keys(descriptor)
+            .replace("X_ONLY", X_ONLY)
+            .replace("UNCOMPRESSED", UNCOMPRESSED);
+        let expected = match expected {
+            DescriptorError::UncompressedKey(key) => {
+                DescriptorError::UncompressedKey(key.replace("UNCOMPRESSED", UNCOMPRESSED))
+            }
+            DescriptorError::Syntax(reason) => {
+                DescriptorError::Syntax(reason.replace("X_ONLY", X_ONLY))
+            }
+            expected => expected,
+        };
+        assert_eq!(Descriptor::parse(&descriptor).unwrap_err(), expected);
+    }
+
+    #[rstest]
+    fn test_x_only_key_outside_tr() {
+        assert!(matches!(
+            Descriptor::parse(&format!("pk({})", X_ONLY)),
+            Err(DescriptorError::InvalidKey(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_spend_templates() -> color_eyre::Result<()> {
+        let descriptor: Descriptor =
+            with_keys("sh(wsh(multi(2,XPUB/0/*,XPUB/1/*,XPUB/2/*)))").parse()?;
+        let keys: Vec<Vec<u8>> = descriptor
+            .keys()
+            .iter()
+            .map(|key| key.derive(4).unwrap().to_bytes())
+            .collect();
+        let witness_script = descriptor.witness_script(4)?.unwrap();
+        let templates = descriptor.spend_templates(4)?;
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].name, "sh-wsh-multi");
+        assert_eq!(
+            templates[0].script_sig,
+            vec![WitnessItem::Script(descriptor.redeem_script(4)?.unwrap())]
+        );
+        assert_eq!(
+            templates[0].witness,
+            vec![
+                WitnessItem::Empty,
+                WitnessItem::Signature(keys[0].clone()),
+                WitnessItem::Signature(keys[1].clone()),
+                WitnessItem::Script(witness_script),
+            ]
+        );
+
+        // Key path, then every leaf with its script and control block.
+        let descriptor: Descriptor = with_keys(&format!(
+            "tr({},{{pk(XPUB/0/*),multi_a(2,XPUB/1/*,XPUB/2/*,XPUB/3/*)}})",
+            X_ONLY
+        ))
+        .parse()?;
+        let info = descriptor.taproot_spend_info(0)?.unwrap();
+        let x_only = |path: &str| -> Vec<u8> {
+            with_keys(path)
+                .parse::<DescriptorKey>()
+                .unwrap()
+                .derive(0)
+                .unwrap()
+                .x_only()
+                .to_vec()
+        };
+        let templates = descriptor.spend_templates(0)?;
+        let names: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["key path", "leaf 0", "leaf 1"]);
+        assert_eq!(
+            templates[0].witness,
+            vec![WitnessItem::Signature(info.output_key.to_vec())]
+        );
+        assert_eq!(
+            templates[1].witness,
+            vec![
+                WitnessItem::Signature(x_only("XPUB/0/*")),
+                WitnessItem::Script(info.leaves[0].script.clone()),
+                WitnessItem::ControlBlock(info.control_block(0)?),
+            ]
+        );
+        assert_eq!(
+            templates[2].witness,
+            vec![
+                WitnessItem::Empty,
+                WitnessItem::Signature(x_only("XPUB/2/*")),
+                WitnessItem::Signature(x_only("XPUB/1/*")),
+                WitnessItem::Script(info.leaves[1].script.clone()),
+                WitnessItem::ControlBlock(info.control_block(1)?),
+            ]
+        );
+        assert!(Descriptor::parse("raw(6a)")?.spend_templates(0)?.is_empty());
+        Ok(())
+    }
+
+    // Signs a PSBT spending the descriptor with its private keys, the finalizer runs the
+    // result through bee-vm. The witness / scriptSig must have the shape of the template.
+    #[rstest]
+    #[case("pk(WIF)")]
+    #[case("pkh(XPRV/44'/0'/0'/0/*)")]
+    #[case("wpkh(XPRV/84'/0'/0'/0/*)")]
+    #[case("sh(wpkh(XPRV/49'/0'/0'/0/*'))")]
+    #[case("sh(pkh(WIF))")]
+    #[case("sh(multi(1,XPRV/0/*,XPUB/1/*))")]
+    #[case("wsh(sortedmulti(2,XPRV/0/*,XPRV/1/*,XPUB/2/*))")]
+    #[case("sh(wsh(multi(2,XPRV/0/*,XPRV/1/*,XPUB/2/*)))")]
+    #[case("tr(XPRV/86'/0'/0'/0/*)")]
+    #[case("tr(50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0,{pk(XPRV/0/*),pk(XPUB/1/*)})")]
+    fn test_spend_with_bee_vm(#[case] descriptor: &str) -> color_eyre::Result<()> {
+        let descriptor: Descriptor = with_keys(descriptor).parse()?;
+        let index = 9;
+
+        let mut input = Input {
+            previous_output: OutPoint {
+                txid: [7; 32],
+                vout: 0,
+            },
+            witness_utxo: Some(TxOut {
+                value: 100_000,
+                script_pubkey: descriptor.script_pubkey(index)?,
+            }),
+            ..Default::default()
+        };
+        descriptor.update_psbt_input(&mut input, index)?;
+        let mut psbt = Psbt::new(2, 2)?;
+        psbt.inputs = vec![input];
+        psbt.outputs = vec![Output {
+            amount: 90_000,
+            script_pubkey: [vec![0x00, 0x14], [1; 20].to_vec()].concat(),
+            ..Default::default()
+        }];
+
+        assert!(sign(&mut psbt, &descriptor.private_keys(index)?)? > 0);
+        finalize(&mut psbt)?;
+
+        let final_script_sig = psbt.inputs[0].final_script_sig.clone().unwrap_or_default();
+        let final_witness = psbt.inputs[0]
+            .final_script_witness
+            .clone()
+            .unwrap_or_default();
+        let script_sig_pushes = bee_vm::script::decode::decode(&final_script_sig)?.len();
+        assert!(descriptor.spend_templates(index)?.iter().any(|template| {
+            template.script_sig.len() == script_sig_pushes
+                && template.witness.len() == final_witness.len()
+        }));
+        Ok(())
+    }
+}
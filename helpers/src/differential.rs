@@ -0,0 +1,496 @@
+//! Differential tests against rust-miniscript and rust-bitcoin, used as reference
+//! implementations.
+//!
+//! Descriptor checksums and scripts, miniscript parsing, types and encodings are compared with
+//! rust-miniscript, policies compile to miniscripts it reads with the same semantics, and
+//! taproot trees give the same keys and control blocks as rust-bitcoin. Where rust-miniscript
+//! departs from Bitcoin Core, which this crate follows, the cases are left out.
+
+use crate::descriptor::checksum::checksum;
+use crate::descriptor::Descriptor;
+use crate::miniscript::policy::Policy;
+use crate::miniscript::{Miniscript, ScriptContext};
+use crate::schnorr::Parity;
+use crate::taproot::{TapTree, TaprootBuilder, TaprootSpendInfo};
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bitcoin::taproot::{LeafVersion, TaprootBuilder as ReferenceBuilder};
+use bitcoin::{PublicKey, ScriptBuf, XOnlyPublicKey};
+use miniscript::descriptor::checksum::desc_checksum;
+use miniscript::miniscript::types::{Base, Dissat, Input};
+use miniscript::policy::{Concrete, Liftable};
+use miniscript::{DescriptorPublicKey, FromStrKey, Segwitv0, Tap, Terminal, ToPublicKey};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use rstest::rstest;
+use std::str::FromStr;
+
+// BIP32 test vector 1 master keys, as in the descriptor tests.
+const XPUB: &str = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+const XPRV: &str = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+const WIF: &str = "L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1";
+const X_ONLY: &str = "a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd";
+
+/// Keys of the generated miniscripts and policies, repeated keys included.
+const KEYS: usize = 4;
+
+/// Compressed public key of the secret `[seed; 32]`, x-only in tapscript.
+fn key(seed: u8, context: ScriptContext) -> String {
+    let secret = SecretKey::from_slice(&[seed; 32]).unwrap();
+    let public_key = secret.public_key(&Secp256k1::new());
+    match context {
+        ScriptContext::Segwitv0 => public_key.to_string(),
+        ScriptContext::Tap => public_key.x_only_public_key().0.to_string(),
+    }
+}
+
+fn reference_script_pubkey(descriptor: &str, index: u32) -> Option<ScriptBuf> {
+    let secp = Secp256k1::new();
+    let (descriptor, _) =
+        miniscript::Descriptor::<DescriptorPublicKey>::parse_descriptor(&secp, descriptor).ok()?;
+    Some(descriptor.at_derivation_index(index).ok()?.script_pubkey())
+}
+
+/// Type of a rust-miniscript fragment, written like `Type`'s Display.
+fn reference_type<Pk: miniscript::MiniscriptKey, Ctx: miniscript::ScriptContext>(
+    miniscript: &miniscript::Miniscript<Pk, Ctx>,
+) -> String {
+    let (corr, mall) = (miniscript.ty.corr, miniscript.ty.mall);
+    let base = match corr.base {
+        Base::B => 'B',
+        Base::V => 'V',
+        Base::K => 'K',
+        Base::W => 'W',
+    };
+    let properties = [
+        (corr.input == Input::Zero, 'z'),
+        (matches!(corr.input, Input::One | Input::OneNonZero), 'o'),
+        (
+            matches!(corr.input, Input::OneNonZero | Input::AnyNonZero),
+            'n',
+        ),
+        (corr.dissatisfiable, 'd'),
+        (corr.unit, 'u'),
+        (mall.dissat == Dissat::Unique, 'e'),
+        (mall.dissat == Dissat::None, 'f'),
+        (mall.safe, 's'),
+        (mall.non_malleable, 'm'),
+        (!miniscript.ext.timelock_info.contains_combination, 'k'),
+    ];
+    let mut letters = base.to_string();
+    letters.extend(
+        properties
+            .iter()
+            .filter(|(has, _)| *has)
+            .map(|(_, letter)| letter),
+    );
+    letters
+}
+
+/// Whether an `or_b` of `miniscript` has a child without a unique dissatisfaction, rust-miniscript
+/// still types it e where Core needs both children to be e (which changes m above it).
+fn has_reference_or_b_dissat<Pk: miniscript::MiniscriptKey, Ctx: miniscript::ScriptContext>(
+    miniscript: &miniscript::Miniscript<Pk, Ctx>,
+) -> bool {
+    miniscript.iter().any(|node| match &node.node {
+        Terminal::OrB(left, right) => {
+            left.ty.mall.dissat != Dissat::Unique || right.ty.mall.dissat != Dissat::Unique
+        }
+        _ => false,
+    })
+}
+
+/// Compares parsing, type and script of `expression` with rust-miniscript, `Pk` and `Ctx`
+/// being its key type and script context for `context`.
+fn check_miniscript<Pk, Ctx>(expression: &str, context: ScriptContext) -> Result<(), TestCaseError>
+where
+    Pk: FromStrKey + ToPublicKey,
+    Ctx: miniscript::ScriptContext,
+{
+    if matches!(context, ScriptContext::Tap) && has_dupif(expression) {
+        return Ok(());
+    }
+    let insane = Miniscript::parse_insane(expression, context);
+    let reference = miniscript::Miniscript::<Pk, Ctx>::from_str_insane(expression);
+    prop_assert_eq!(
+        insane.is_ok(),
+        reference.is_ok(),
+        "{} : {:?} / {:?}",
+        expression,
+        insane.as_ref().err(),
+        reference.as_ref().err()
+    );
+    let (Ok(insane), Ok(reference)) = (insane, reference) else {
+        return Ok(());
+    };
+    prop_assert_eq!(
+        insane.encode(0).unwrap(),
+        reference.encode().to_bytes(),
+        "{}",
+        expression
+    );
+    if has_reference_or_b_dissat(&reference) {
+        return Ok(());
+    }
+    prop_assert_eq!(
+        insane.ty.to_string(),
+        reference_type(&reference),
+        "{}",
+        expression
+    );
+
+    // rust-miniscript finds scripts without any satisfaction insane, Core and this crate do not.
+    if reference.max_satisfaction_witness_elements().is_err() {
+        return Ok(());
+    }
+    let sane = Miniscript::parse(expression, context);
+    let reference = miniscript::Miniscript::<Pk, Ctx>::from_str(expression);
+    prop_assert_eq!(
+        sane.is_ok(),
+        reference.is_ok(),
+        "{} : {:?} / {:?}",
+        expression,
+        sane.as_ref().err(),
+        reference.as_ref().err()
+    );
+    if sane.is_ok() {
+        let descriptor = match context {
+            ScriptContext::Segwitv0 => format!("wsh({})", expression),
+            ScriptContext::Tap => format!("tr({},{})", X_ONLY, expression),
+        };
+        prop_assert_eq!(
+            Some(
+                Descriptor::parse(&descriptor)
+                    .unwrap()
+                    .script_pubkey(0)
+                    .unwrap()
+            ),
+            reference_script_pubkey(&descriptor, 0).map(|script| script.to_bytes()),
+            "{}",
+            descriptor
+        );
+    }
+    Ok(())
+}
+
+/// Compiles `policy` and checks that rust-miniscript reads the result as a sane miniscript
+/// with the semantics of the policy.
+fn check_compilation<Pk, Ctx>(policy: &str, context: ScriptContext) -> Result<(), TestCaseError>
+where
+    Pk: FromStrKey + ToPublicKey,
+    Ctx: miniscript::ScriptContext,
+{
+    let Ok(compilation) = Policy::parse(policy, context).and_then(|policy| policy.compile(context))
+    else {
+        return Ok(());
+    };
+    let compiled = compilation.miniscript.to_string();
+    if matches!(context, ScriptContext::Tap) && has_dupif(&compiled) {
+        return Ok(());
+    }
+    let reference = miniscript::Miniscript::<Pk, Ctx>::from_str(&compiled);
+    prop_assert!(
+        reference.is_ok(),
+        "{} : {} {:?}",
+        policy,
+        compiled,
+        reference.err()
+    );
+    let reference = reference.unwrap();
+    prop_assert_eq!(
+        compilation.miniscript.encode(0).unwrap(),
+        reference.encode().to_bytes()
+    );
+    prop_assert_eq!(compilation.script_size, reference.script_size());
+
+    let semantics = Concrete::<Pk>::from_str(policy).unwrap().lift().unwrap();
+    prop_assert_eq!(
+        reference.lift().unwrap().normalized().sorted(),
+        semantics.normalized().sorted(),
+        "{} : {}",
+        policy,
+        compiled
+    );
+    Ok(())
+}
+
+fn timelock() -> impl Strategy<Value = u32> {
+    prop_oneof![
+        1u32..1000,
+        // BIP68 time based relative locks, and UNIX times for after().
+        (1u32..1000).prop_map(|n| n | 1 << 22),
+        500_000_000u32..500_001_000,
+        Just(0),
+        Just(0x7fff_ffff),
+        Just(0x8000_0000),
+        any::<u32>(),
+    ]
+}
+
+fn hash(bytes: usize) -> impl Strategy<Value = String> {
+    vec(any::<u8>(), bytes).prop_map(hex::encode)
+}
+
+fn keys(context: ScriptContext) -> impl Strategy<Value = Vec<String>> {
+    vec(1..=KEYS as u8, 1..=4)
+        .prop_map(move |seeds| seeds.into_iter().map(|seed| key(seed, context)).collect())
+}
+
+/// Whether `expression` has a d: wrapper. rust-miniscript does not type it u in tapscript,
+/// where Core does since BIP342 makes MINIMALIF a consensus rule.
+fn has_dupif(expression: &str) -> bool {
+    expression.match_indices(':').any(|(position, _)| {
+        expression[..position]
+            .chars()
+            .rev()
+            .take_while(|c| c.is_ascii_lowercase())
+            .any(|c| c == 'd')
+    })
+}
+
+/// Prefixes `wrappers` to `expression`, merging them with its own wrappers.
+fn wrap(wrappers: &str, expression: &str) -> String {
+    match expression.split_once(':') {
+        Some((own, _)) if own.chars().all(|c| c.is_ascii_lowercase()) => {
+            format!("{}{}", wrappers, expression)
+        }
+        _ => format!("{}:{}", wrappers, expression),
+    }
+}
+
+/// Random miniscript expressions : fragments and wrappers combined without regard to types,
+/// so both valid and invalid expressions come out.
+fn miniscript(context: ScriptContext) -> impl Strategy<Value = String> {
+    let key = (1..=KEYS as u8).prop_map(move |seed| key(seed, context));
+    let leaf = prop_oneof![
+        Just("0".to_string()),
+        Just("1".to_string()),
+        (
+            prop_oneof![Just("pk_k"), Just("pk_h"), Just("pk"), Just("pkh")],
+            key
+        )
+            .prop_map(|(name, key)| format!("{}({})", name, key)),
+        timelock().prop_map(|n| format!("older({})", n)),
+        // rust-miniscript takes after() values from 2^31 on, Core rejects them.
+        timelock().prop_map(|n| format!("after({})", n.min(0x7fff_ffff))),
+        (prop_oneof![Just("sha256"), Just("hash256")], hash(32))
+            .prop_map(|(name, hash)| format!("{}({})", name, hash)),
+        (prop_oneof![Just("ripemd160"), Just("hash160")], hash(20))
+            .prop_map(|(name, hash)| format!("{}({})", name, hash)),
+        (
+            prop_oneof![Just("multi"), Just("multi_a")],
+            0..=5usize,
+            keys(context)
+        )
+            .prop_map(|(name, k, keys)| format!("{}({},{})", name, k, keys.join(","))),
+    ];
+    leaf.prop_recursive(4, 24, 3, |inner| {
+        prop_oneof![
+            ("[asc dvjntlu]{1,2}", inner.clone())
+                .prop_map(|(wrappers, expression)| wrap(&wrappers.replace(' ', ""), &expression))
+                .prop_filter("no wrapper", |expression| !expression.starts_with(':')),
+            (
+                prop_oneof![
+                    Just("and_v"),
+                    Just("and_b"),
+                    Just("and_n"),
+                    Just("or_b"),
+                    Just("or_c"),
+                    Just("or_d"),
+                    Just("or_i"),
+                ],
+                inner.clone(),
+                inner.clone()
+            )
+                .prop_map(|(name, x, y)| format!("{}({},{})", name, x, y)),
+            (inner.clone(), inner.clone(), inner.clone())
+                .prop_map(|(x, y, z)| format!("andor({},{},{})", x, y, z)),
+            (0..=4usize, vec(inner, 1..=3)).prop_map(|(k, subs)| format!(
+                "thresh({},{})",
+                k,
+                subs.join(",")
+            )),
+        ]
+    })
+}
+
+/// Random spending policies, with or without or() weights.
+fn policy(context: ScriptContext) -> impl Strategy<Value = String> {
+    let key = (1..=KEYS as u8).prop_map(move |seed| key(seed, context));
+    let leaf = prop_oneof![
+        3 => key.prop_map(|key| format!("pk({})", key)),
+        1 => (1u32..1000).prop_map(|n| format!("older({})", n)),
+        1 => (1u32..1000).prop_map(|n| format!("after({})", n)),
+        1 => hash(32).prop_map(|hash| format!("sha256({})", hash)),
+        1 => hash(20).prop_map(|hash| format!("hash160({})", hash)),
+    ];
+    leaf.prop_recursive(3, 12, 3, |inner| {
+        let weight = prop_oneof![
+            Just(String::new()),
+            (1u32..100).prop_map(|w| format!("{}@", w))
+        ];
+        prop_oneof![
+            (inner.clone(), inner.clone()).prop_map(|(x, y)| format!("and({},{})", x, y)),
+            (weight.clone(), inner.clone(), weight, inner.clone())
+                .prop_map(|(v, x, w, y)| format!("or({}{},{}{})", v, x, w, y)),
+            (vec(inner, 2..=3), 1..=3usize).prop_map(|(subs, k)| {
+                format!("thresh({},{})", k.min(subs.len()), subs.join(","))
+            }),
+        ]
+    })
+}
+
+/// Random script trees, as nested leaves and branches with distinct scripts.
+fn tap_tree() -> impl Strategy<Value = TapTree> {
+    let leaf = vec(any::<u8>(), 1..10).prop_map(TapTree::leaf);
+    leaf.prop_recursive(6, 32, 2, |inner| {
+        (inner.clone(), inner).prop_map(|(left, right)| TapTree::branch(left, right))
+    })
+}
+
+/// Leaves of `tree` left to right, with their depth.
+fn leaves(tree: &TapTree, depth: u8, leaves_found: &mut Vec<(u8, Vec<u8>)>) {
+    match tree {
+        TapTree::Leaf { script, .. } => leaves_found.push((depth, script.clone())),
+        TapTree::Branch(left, right) => {
+            leaves(left, depth + 1, leaves_found);
+            leaves(right, depth + 1, leaves_found);
+        }
+    }
+}
+
+fn internal_key() -> impl Strategy<Value = [u8; 32]> {
+    any::<[u8; 32]>()
+        .prop_filter_map("invalid secret key", |secret| {
+            SecretKey::from_slice(&secret).ok()
+        })
+        .prop_map(|secret| secret.x_only_public_key(&Secp256k1::new()).0.serialize())
+}
+
+// The descriptor test vectors, with ranged keys.
+#[rstest]
+#[case("pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)")]
+#[case("pk(5KYZdUEo39z3FPrtuX2QbbwGnNP5zTd7yyr2SC1j299sBCnWjss)")]
+#[case("pkh(WIF)")]
+#[case("wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)")]
+#[case("sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))")]
+#[case("sh(multi(2,022f01e5e15cca351daff3843fb70f3c2f0a1bdd05e5af888a67784ef3e10a2a01,03acd484e2f0c7f65309ad178a9f559abde09796974c57e714c35f110dfc27ccbe))")]
+#[case("wsh(multi(2,03a0434d9e47f3c86235477c7b1ae6ae5d3442d49b1943c2b752a68e2a47e247c7,03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb,03d01115d548e7561b15c38f004d734633687cf4419620095bc5b0f47070afe85a))")]
+#[case("pkh([d34db33f/44'/0'/0']XPUB/1/*)")]
+#[case("wpkh(XPRV/44'/0'/0'/0/*)")]
+#[case("sh(wsh(sortedmulti(1,XPUB/1/0/*,XPUB/0/0/*)))")]
+#[case("wsh(sortedmulti(2,XPUB/0/*,XPUB/1/*,XPUB/2/*))")]
+#[case("sh(wsh(or_d(pk(XPUB/0/*),and_v(v:pk(XPUB/1/*),older(1000)))))")]
+#[case("tr(X_ONLY)")]
+#[case("tr(XPUB/0/*)")]
+#[case("tr(X_ONLY,{pk(XPUB/0/*),{multi_a(2,XPUB/1/*,XPUB/2/*),multi_a(1,XPUB/3/*,XPUB/4/*)}})")]
+#[case("tr(50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0,and_v(v:pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798),older(144)))")]
+fn test_descriptors_match_rust_miniscript(#[case] descriptor: &str) {
+    let descriptor = descriptor
+        .replace("XPUB", XPUB)
+        .replace("XPRV", XPRV)
+        .replace("WIF", WIF)
+        .replace("X_ONLY", X_ONLY);
+    let parsed = Descriptor::parse(&descriptor).unwrap();
+    for index in 0..3 {
+        assert_eq!(
+            Some(parsed.script_pubkey(index).unwrap()),
+            reference_script_pubkey(&descriptor, index).map(|script| script.to_bytes()),
+            "{} at {}",
+            descriptor,
+            index
+        );
+    }
+    assert_eq!(checksum(&descriptor).ok(), desc_checksum(&descriptor).ok());
+}
+
+proptest! {
+    #[test]
+    fn checksums_match_rust_miniscript(descriptor in "[ -~]{0,60}|\\PC{0,10}") {
+        prop_assert_eq!(checksum(&descriptor).ok(), desc_checksum(&descriptor).ok());
+    }
+
+    #[test]
+    fn segwit_miniscripts_match_rust_miniscript(expression in miniscript(ScriptContext::Segwitv0)) {
+        check_miniscript::<PublicKey, Segwitv0>(&expression, ScriptContext::Segwitv0)?;
+    }
+
+    #[test]
+    fn tap_miniscripts_match_rust_miniscript(expression in miniscript(ScriptContext::Tap)) {
+        check_miniscript::<XOnlyPublicKey, Tap>(&expression, ScriptContext::Tap)?;
+    }
+
+    #[test]
+    fn segwit_compilations_match_rust_miniscript(policy in policy(ScriptContext::Segwitv0)) {
+        check_compilation::<PublicKey, Segwitv0>(&policy, ScriptContext::Segwitv0)?;
+    }
+
+    #[test]
+    fn tap_compilations_match_rust_miniscript(policy in policy(ScriptContext::Tap)) {
+        check_compilation::<XOnlyPublicKey, Tap>(&policy, ScriptContext::Tap)?;
+    }
+
+    #[test]
+    fn tap_trees_match_rust_bitcoin(tree in tap_tree(), internal_key in internal_key()) {
+        let mut tree_leaves = vec![];
+        leaves(&tree, 0, &mut tree_leaves);
+        let mut scripts: Vec<&Vec<u8>> = tree_leaves.iter().map(|(_, script)| script).collect();
+        scripts.sort();
+        scripts.dedup();
+        prop_assume!(scripts.len() == tree_leaves.len());
+
+        let mut reference = ReferenceBuilder::new();
+        for (depth, script) in &tree_leaves {
+            reference = reference.add_leaf(*depth, ScriptBuf::from_bytes(script.clone())).unwrap();
+        }
+        let key = XOnlyPublicKey::from_slice(&internal_key).unwrap();
+        let reference = reference.finalize(&Secp256k1::verification_only(), key).unwrap();
+
+        let info = TaprootSpendInfo::new(&internal_key, Some(&tree)).unwrap();
+        prop_assert_eq!(info.merkle_root, reference.merkle_root().map(|root| root.to_byte_array()));
+        prop_assert_eq!(info.output_key, reference.output_key().serialize());
+        prop_assert_eq!(info.parity == Parity::Odd, reference.output_key_parity() == bitcoin::key::Parity::Odd);
+        for (index, leaf) in info.leaves.iter().enumerate() {
+            let control_block = reference
+                .control_block(&(ScriptBuf::from_bytes(leaf.script.clone()), LeafVersion::TapScript))
+                .unwrap();
+            prop_assert_eq!(info.control_block(index).unwrap(), control_block.serialize());
+        }
+    }
+
+    #[test]
+    fn huffman_trees_match_rust_bitcoin(
+        weights in vec(1u32..100, 1..12),
+        internal_key in internal_key(),
+    ) {
+        // Ties are broken differently, the weighted depth of the leaves is the same.
+        let scripts: Vec<Vec<u8>> = (0..weights.len()).map(|i| vec![0x51, i as u8]).collect();
+        let mut builder = TaprootBuilder::new();
+        for (weight, script) in weights.iter().zip(&scripts) {
+            builder = builder.add_leaf(*weight, script.clone());
+        }
+        let info = builder.finalize(&internal_key).unwrap();
+        let reference = ReferenceBuilder::with_huffman_tree(
+            weights.iter().zip(&scripts).map(|(weight, script)| (*weight, ScriptBuf::from_bytes(script.clone()))),
+        )
+        .unwrap()
+        .finalize(&Secp256k1::verification_only(), XOnlyPublicKey::from_slice(&internal_key).unwrap())
+        .unwrap();
+
+        let weighted_depth = |depth: &dyn Fn(&[u8]) -> usize| -> u64 {
+            weights.iter().zip(&scripts).map(|(weight, script)| *weight as u64 * depth(script) as u64).sum()
+        };
+        let ours = weighted_depth(&|script| {
+            let leaf = info.find_leaf(script, 0xc0).unwrap();
+            info.leaves[leaf].merkle_path.len()
+        });
+        let theirs = weighted_depth(&|script| {
+            reference.script_map()[&(ScriptBuf::from_bytes(script.to_vec()), LeafVersion::TapScript)]
+                .iter()
+                .next()
+                .unwrap()
+                .len()
+        });
+        prop_assert_eq!(ours, theirs);
+    }
+}
@@ -0,0 +1,291 @@
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AddressError {
+    #[error("Invalid base58check address : {0}")]
+    InvalidBase58(String),
+    #[error("Invalid bech32 address : {0}")]
+    InvalidBech32(String),
+    #[error("Unknown address version byte : {0}")]
+    UnknownVersion(u8),
+    #[error("Address belongs to {found} and not to {expected}")]
+    NetworkMismatch { expected: String, found: String },
+    #[error("Script has no address form : {0}")]
+    NoAddressForm(String),
+    #[error("Unknown network : {0}")]
+    UnknownNetwork(String),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum KeyError {
+    #[error("Invalid WIF private key : {0}")]
+    InvalidWif(String),
+    #[error("Unknown WIF version byte : {0}")]
+    UnknownWifVersion(u8),
+    #[error("Invalid private key : {0}")]
+    InvalidPrivateKey(String),
+    #[error("Invalid public key : {0}")]
+    InvalidPublicKey(String),
+    #[error("Taproot tweak is not a valid scalar / produces the point at infinity")]
+    InvalidTweak,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Bip32Error {
+    #[error("Invalid extended key : {0}")]
+    InvalidExtendedKey(String),
+    #[error("Unknown extended key version : {0:08x}")]
+    UnknownVersion(u32),
+    #[error("Invalid derivation path : {0}")]
+    InvalidPath(String),
+    #[error("Hardened derivation needs a private key")]
+    HardenedFromPublic,
+    #[error("Derived key is invalid, use the next index")]
+    InvalidChild,
+    #[error("Invalid seed length : {0} (must be 16 to 64 bytes)")]
+    InvalidSeedLength(usize),
+    #[error("Key at depth 255 can not be derived further")]
+    MaxDepthExceeded,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MnemonicError {
+    #[error("Invalid number of words : {0} (must be 12, 15, 18, 21 or 24)")]
+    InvalidWordCount(usize),
+    #[error("Invalid entropy length : {0} bytes (must be 16 to 32 and a multiple of 4)")]
+    InvalidEntropyLength(usize),
+    #[error("Unknown word : {0}")]
+    UnknownWord(String),
+    #[error("Invalid mnemonic checksum")]
+    InvalidChecksum,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TxError {
+    #[error("Invalid input, expected <txid>:<vout>[:<sequence>] : {0}")]
+    InvalidInput(String),
+    #[error("Invalid output, expected <address | scriptPubKey hex>:<satoshis> : {0}")]
+    InvalidOutput(String),
+    #[error("Input index {index} is out of range, the transaction has {inputs} inputs")]
+    InputIndexOutOfRange { index: usize, inputs: usize },
+    #[error(transparent)]
+    Transaction(#[from] bee_vm::errors::TransactionError),
+    #[error(transparent)]
+    Address(#[from] AddressError),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Base64Error {
+    #[error("Invalid base64 length : {0} (must be a multiple of 4)")]
+    InvalidLength(usize),
+    #[error("Misplaced base64 padding")]
+    MisplacedPadding,
+    #[error("Invalid base64 character : {0:?}")]
+    InvalidCharacter(char),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PsbtError {
+    #[error("Invalid PSBT magic bytes")]
+    InvalidMagic,
+    #[error(transparent)]
+    InvalidBase64(#[from] Base64Error),
+    #[error("Duplicate key in PSBT map : {0}")]
+    DuplicateKey(String),
+    #[error("Invalid key for PSBT field {0:#04x} : {1}")]
+    InvalidKey(u8, String),
+    #[error("Invalid value for PSBT field {0:#04x} : {1}")]
+    InvalidValue(u8, String),
+    #[error("Missing PSBT field : {0}")]
+    MissingField(String),
+    #[error("PSBT field {0:#04x} is not allowed in a version {1} PSBT")]
+    UnexpectedField(u8, u32),
+    #[error("Unsupported PSBT version : {0}")]
+    UnsupportedVersion(u32),
+    #[error("The unsigned transaction of a PSBT must have empty scriptSigs and witnesses")]
+    SignedUnsignedTx,
+    #[error("Input {0} is out of range")]
+    InputIndexOutOfRange(usize),
+    #[error("UTXO of input {0} is missing")]
+    MissingUtxo(usize),
+    #[error("Non witness UTXO of input {0} is not the transaction it spends")]
+    UtxoMismatch(usize),
+    #[error("Script of input {0} does not match its output : {1}")]
+    ScriptMismatch(usize, String),
+    #[error("Input {0} can not be finalized : {1}")]
+    CanNotFinalize(usize, String),
+    #[error("Input {0} is not finalized")]
+    NotFinalized(usize),
+    #[error("Input {input} does not pass bee-vm verification : {reason}")]
+    VerifyFailed { input: usize, reason: String },
+    #[error("Conflicting locktime requirements")]
+    LocktimeConflict,
+    #[error("Signing failed : {0}")]
+    Signing(String),
+    #[error(transparent)]
+    Transaction(#[from] bee_vm::errors::TransactionError),
+    #[error(transparent)]
+    Key(#[from] KeyError),
+    #[error(transparent)]
+    Bip32(#[from] Bip32Error),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Bip322Error {
+    #[error("Unknown BIP322 signature format : {0} (legacy, simple or full)")]
+    UnknownFormat(String),
+    #[error("The {0} format can not hold a signature for this address")]
+    UnsupportedFormat(crate::bip322::Format),
+    #[error("None of the keys can sign for the address")]
+    NoSigningKey,
+    #[error("Invalid BIP322 signature : {0}")]
+    InvalidSignature(String),
+    #[error("Invalid to_sign transaction : {0}")]
+    InvalidToSign(String),
+    #[error("Legacy signature was made by another key")]
+    LegacyKeyMismatch,
+    #[error("Inconclusive : {0}")]
+    Inconclusive(String),
+    #[error("Signing failed : {0}")]
+    Signing(String),
+    #[error("Signature does not pass bee-vm verification : {0}")]
+    Verify(String),
+    #[error(transparent)]
+    Base64(#[from] Base64Error),
+    #[error(transparent)]
+    Psbt(#[from] PsbtError),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MusigError {
+    #[error("MuSig2 needs at least one public key")]
+    NoPublicKeys,
+    #[error("Invalid public key of signer {0}")]
+    InvalidPublicKey(usize),
+    #[error("The aggregate key is the point at infinity")]
+    InfinityKey,
+    #[error("Tweak is not a valid scalar / produces the point at infinity")]
+    InvalidTweak,
+    #[error("Invalid public nonce of signer {0}")]
+    InvalidPublicNonce(usize),
+    #[error("Invalid aggregate nonce")]
+    InvalidAggregateNonce,
+    #[error("Invalid secret nonce : {0}")]
+    InvalidSecretNonce(String),
+    #[error("Invalid partial signature of signer {0}")]
+    InvalidPartialSignature(usize),
+    #[error("The public key is not one of the aggregated keys")]
+    UnknownPublicKey,
+    #[error(transparent)]
+    Key(#[from] KeyError),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TaprootError {
+    #[error("Script tree is {0} levels deep, the limit is 128")]
+    TreeTooDeep(usize),
+    #[error("Invalid leaf version : {0:#04x}")]
+    InvalidLeafVersion(u8),
+    #[error("Leaf {0} is out of range")]
+    LeafIndexOutOfRange(usize),
+    #[error(transparent)]
+    Key(#[from] KeyError),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum DescriptorError {
+    #[error("Invalid descriptor checksum, expected {expected} and found {found}")]
+    InvalidChecksum { expected: String, found: String },
+    #[error("Invalid descriptor character : {0:?}")]
+    InvalidCharacter(char),
+    #[error("Invalid descriptor : {0}")]
+    Syntax(String),
+    #[error("Unknown descriptor function : {0}")]
+    UnknownFunction(String),
+    #[error("{function}() is not allowed {context}")]
+    InvalidContext { function: String, context: String },
+    #[error("Invalid descriptor key : {0}")]
+    InvalidKey(String),
+    #[error("Uncompressed keys are not allowed in segwit scripts : {0}")]
+    UncompressedKey(String),
+    #[error("Multisig of {found} keys, the limit {context} is {max}")]
+    InvalidKeyCount {
+        found: usize,
+        max: usize,
+        context: String,
+    },
+    #[error("Invalid threshold {threshold} for {keys} keys")]
+    InvalidThreshold { threshold: usize, keys: usize },
+    #[error("Redeem script of {0} bytes, the limit is 520")]
+    ScriptTooLarge(usize),
+    #[error(transparent)]
+    Key(#[from] KeyError),
+    #[error(transparent)]
+    Bip32(#[from] Bip32Error),
+    #[error(transparent)]
+    Address(#[from] AddressError),
+    #[error(transparent)]
+    Taproot(#[from] TaprootError),
+    #[error(transparent)]
+    Script(#[from] bee_vm::errors::ScriptError),
+    #[error(transparent)]
+    Miniscript(#[from] MiniscriptError),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MiniscriptError {
+    #[error("Invalid miniscript : {0}")]
+    Syntax(String),
+    #[error("Unknown miniscript fragment : {0}")]
+    UnknownFragment(String),
+    #[error("Unknown miniscript wrapper : {0}")]
+    UnknownWrapper(char),
+    #[error("Invalid miniscript key : {0}")]
+    InvalidKey(String),
+    #[error("{fragment}() does not type check : {reason}")]
+    TypeCheck { fragment: String, reason: String },
+    #[error("A miniscript must be of type B at the top level, found {0}")]
+    NotTopLevel(String),
+    #[error("Invalid timelock {0}, it must be between 1 and 2^31 - 1")]
+    InvalidTimelock(u32),
+    #[error("Invalid threshold {threshold} for {count} sub-expressions or keys")]
+    InvalidThreshold { threshold: usize, count: usize },
+    #[error("{found} keys, the limit is {max}")]
+    InvalidKeyCount { found: usize, max: usize },
+    #[error("Script of {size} bytes, the limit is {max}")]
+    ScriptTooLarge { size: usize, max: usize },
+    #[error("Script of {ops} opcodes, the limit is {max}")]
+    TooManyOps { ops: usize, max: usize },
+    #[error("Miniscript is not sane : {0}")]
+    Insane(String),
+    #[error(
+        "No non-malleable satisfaction with the available signatures, preimages and timelocks"
+    )]
+    CanNotSatisfy,
+    #[error("Signing failed : {0}")]
+    Signing(String),
+    #[error("bee-vm rejected the satisfaction : {0}")]
+    VerifyFailed(String),
+    #[error(transparent)]
+    Taproot(#[from] TaprootError),
+    #[error(transparent)]
+    Transaction(#[from] bee_vm::errors::TransactionError),
+    #[error(transparent)]
+    Script(#[from] bee_vm::errors::ScriptError),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PolicyError {
+    #[error("Invalid policy : {0}")]
+    Syntax(String),
+    #[error("Unknown policy fragment : {0}")]
+    UnknownFragment(String),
+    #[error("Invalid branch weight {0}, weights are positive integers")]
+    InvalidWeight(String),
+    #[error("No sane miniscript for this policy : {0}")]
+    NoSaneCompilation(String),
+    #[error("No secret for {0} to spend this path")]
+    MissingSecret(String),
+    #[error(transparent)]
+    Miniscript(#[from] MiniscriptError),
+}
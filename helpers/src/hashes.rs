@@ -0,0 +1,26 @@
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// Double sha256, used for txids and base58 checksums.
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    sha256(&sha256(data))
+}
+
+/// `ripemd160(sha256(data))`, used for P2PKH and P2SH.
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+/// BIP340 tagged hash: `sha256(sha256(tag) || sha256(tag) || data)`.
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag_hash = sha256(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    hasher.update(data);
+    hasher.finalize().into()
+}
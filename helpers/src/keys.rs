@@ -0,0 +1,278 @@
+use crate::address::Address;
+use crate::errors::KeyError;
+use crate::hashes::{hash160, sha256};
+use crate::network::Network;
+use crate::schnorr::taproot_output_key;
+use k256::ecdsa::{SigningKey, VerifyingKey};
+use k256::elliptic_curve::rand_core::OsRng;
+
+/// Domain separation prefix of the keys derived with [`PrivateKey::from_seed`].
+const SEED_PREFIX: &str = "bee-vm/helpers/key/";
+
+/// A secp256k1 private key together with the flags stored in its WIF encoding.
+#[derive(Debug, Clone)]
+pub struct PrivateKey {
+    pub signing_key: SigningKey,
+    /// Whether the public key is serialized compressed (33 bytes) or not (65 bytes).
+    pub compressed: bool,
+    pub network: Network,
+}
+
+impl PrivateKey {
+    pub fn new(signing_key: SigningKey, network: Network) -> PrivateKey {
+        PrivateKey {
+            signing_key,
+            compressed: true,
+            network,
+        }
+    }
+
+    pub fn random(network: Network) -> PrivateKey {
+        PrivateKey::new(SigningKey::random(&mut OsRng), network)
+    }
+
+    /// Derives a key from a seed, the same seed always gives the same key.
+    ///
+    /// Meant for test fixtures only: the key is `sha256(prefix || seed || counter)`, the counter
+    /// being bumped in the (practically impossible) case the hash is not a valid scalar.
+    pub fn from_seed(seed: &[u8], network: Network) -> PrivateKey {
+        let mut counter: u32 = 0;
+        loop {
+            let mut data = SEED_PREFIX.as_bytes().to_vec();
+            data.extend_from_slice(seed);
+            data.extend_from_slice(&counter.to_be_bytes());
+            if let Ok(signing_key) = SigningKey::from_slice(&sha256(&data)) {
+                return PrivateKey::new(signing_key, network);
+            }
+            counter += 1;
+        }
+    }
+
+    pub fn from_slice(bytes: &[u8], network: Network) -> Result<PrivateKey, KeyError> {
+        let signing_key = SigningKey::from_slice(bytes)
+            .map_err(|e| KeyError::InvalidPrivateKey(e.to_string()))?;
+        Ok(PrivateKey::new(signing_key, network))
+    }
+
+    pub fn from_wif(wif: &str) -> Result<PrivateKey, KeyError> {
+        let data = bs58::decode(wif)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| KeyError::InvalidWif(e.to_string()))?;
+
+        let compressed = match data.len() {
+            33 => false,
+            34 if data[33] == 0x01 => true,
+            _ => return Err(KeyError::InvalidWif("invalid payload length".to_string())),
+        };
+        let network = match data[0] {
+            0x80 => Network::Mainnet,
+            0xef => Network::Testnet,
+            version => return Err(KeyError::UnknownWifVersion(version)),
+        };
+
+        let mut key = PrivateKey::from_slice(&data[1..33], network)?;
+        key.compressed = compressed;
+        Ok(key)
+    }
+
+    /// Reads a private key given either in WIF or as 32 hex encoded bytes.
+    pub fn from_str_with_network(key: &str, network: Network) -> Result<PrivateKey, KeyError> {
+        match hex::decode(key) {
+            Ok(bytes) if bytes.len() == 32 => PrivateKey::from_slice(&bytes, network),
+            _ => PrivateKey::from_wif(key),
+        }
+    }
+
+    pub fn to_wif(&self) -> String {
+        let mut data = vec![self.network.wif_version()];
+        data.extend_from_slice(&self.to_bytes());
+        if self.compressed {
+            data.push(0x01);
+        }
+        bs58::encode(data).with_check().into_string()
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.signing_key.to_bytes().into()
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey {
+            key: *self.signing_key.verifying_key(),
+            compressed: self.compressed,
+        }
+    }
+}
+
+/// A secp256k1 public key, serialized compressed or uncompressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey {
+    pub key: VerifyingKey,
+    pub compressed: bool,
+}
+
+impl PublicKey {
+    /// Parses a 33 byte compressed or 65 byte uncompressed SEC1 public key.
+    pub fn from_slice(bytes: &[u8]) -> Result<PublicKey, KeyError> {
+        let key = VerifyingKey::from_sec1_bytes(bytes)
+            .map_err(|e| KeyError::InvalidPublicKey(e.to_string()))?;
+        Ok(PublicKey {
+            key,
+            compressed: bytes.len() == 33,
+        })
+    }
+
+    /// The key in its own serialization (compressed or not).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if self.compressed {
+            self.compressed_bytes().to_vec()
+        } else {
+            self.uncompressed_bytes().to_vec()
+        }
+    }
+
+    pub fn compressed_bytes(&self) -> [u8; 33] {
+        self.key
+            .to_encoded_point(true)
+            .as_bytes()
+            .try_into()
+            .unwrap()
+    }
+
+    pub fn uncompressed_bytes(&self) -> [u8; 65] {
+        self.key
+            .to_encoded_point(false)
+            .as_bytes()
+            .try_into()
+            .unwrap()
+    }
+
+    /// BIP340 x-only key (the x coordinate, the y parity is dropped).
+    pub fn x_only(&self) -> [u8; 32] {
+        self.compressed_bytes()[1..].try_into().unwrap()
+    }
+
+    pub fn hash160(&self) -> [u8; 20] {
+        hash160(&self.to_bytes())
+    }
+
+    /// Every standard address paying to this key.
+    ///
+    /// Segwit outputs always commit to the compressed key, uncompressed keys only have
+    /// a P2PKH form on chain but the segwit addresses of the same point are listed anyway.
+    pub fn addresses(&self, network: Network) -> Vec<(&'static str, Address)> {
+        let compressed = self.compressed_bytes();
+        let wpkh_script = Address::p2wpkh(&compressed, network).script_pubkey();
+        let output_key = taproot_output_key(&self.x_only(), None)
+            .expect("tweaking a valid key without a merkle root can not fail");
+
+        vec![
+            ("p2pkh", Address::p2pkh(&compressed, network)),
+            (
+                "p2pkh (uncompressed)",
+                Address::p2pkh(&self.uncompressed_bytes(), network),
+            ),
+            ("p2sh-p2wpkh", Address::p2sh(&wpkh_script, network)),
+            ("p2wpkh", Address::p2wpkh(&compressed, network)),
+            ("p2tr", Address::p2tr(&output_key, network)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test_keys {
+    use crate::keys::{PrivateKey, PublicKey};
+    use crate::network::Network;
+    use rstest::rstest;
+
+    const SECRET: &str = "0c28fca386c7a227600b2fe50b7cae11ec86d3bf1fbe471be89827e19d72aa1d";
+
+    #[rstest]
+    #[case(
+        "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ",
+        false,
+        Network::Mainnet
+    )]
+    #[case(
+        "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617",
+        true,
+        Network::Mainnet
+    )]
+    fn test_wif_round_trip(
+        #[case] wif: &str,
+        #[case] compressed: bool,
+        #[case] network: Network,
+    ) -> color_eyre::Result<()> {
+        let key = PrivateKey::from_wif(wif)?;
+        assert_eq!(hex::encode(key.to_bytes()), SECRET);
+        assert_eq!(key.compressed, compressed);
+        assert_eq!(key.network, network);
+        assert_eq!(key.to_wif(), wif);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_testnet_wif() -> color_eyre::Result<()> {
+        let key = PrivateKey::from_str_with_network(SECRET, Network::Signet)?;
+        let wif = key.to_wif();
+        assert!(wif.starts_with('c'));
+        assert_eq!(PrivateKey::from_wif(&wif)?.to_bytes(), key.to_bytes());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98618")]
+    #[case("not a key")]
+    fn test_invalid_wif(#[case] wif: &str) {
+        assert!(PrivateKey::from_wif(wif).is_err());
+    }
+
+    #[rstest]
+    fn test_generator_key_forms() -> color_eyre::Result<()> {
+        let mut secret = [0u8; 32];
+        secret[31] = 1;
+        let public_key = PrivateKey::from_slice(&secret, Network::Mainnet)?.public_key();
+
+        assert_eq!(
+            hex::encode(public_key.x_only()),
+            "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        );
+        assert_eq!(
+            hex::encode(public_key.hash160()),
+            "751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+        let uncompressed = PublicKey::from_slice(&public_key.uncompressed_bytes())?;
+        assert!(!uncompressed.compressed);
+        assert_eq!(
+            uncompressed.compressed_bytes(),
+            public_key.compressed_bytes()
+        );
+
+        let addresses = public_key.addresses(Network::Mainnet);
+        let find = |name: &str| {
+            addresses
+                .iter()
+                .find(|(n, _)| *n == name)
+                .unwrap()
+                .1
+                .to_string()
+        };
+        assert_eq!(find("p2pkh"), "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+        assert_eq!(
+            find("p2pkh (uncompressed)"),
+            "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm"
+        );
+        assert_eq!(find("p2wpkh"), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_seeded_keys_are_deterministic() {
+        let first = PrivateKey::from_seed(b"alice", Network::Regtest);
+        let second = PrivateKey::from_seed(b"alice", Network::Regtest);
+        let other = PrivateKey::from_seed(b"bob", Network::Regtest);
+        assert_eq!(first.to_bytes(), second.to_bytes());
+        assert_ne!(first.to_bytes(), other.to_bytes());
+    }
+}
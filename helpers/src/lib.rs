@@ -0,0 +1,30 @@
+// Address encoding (base58check / bech32 / bech32m)
+pub mod address;
+pub mod base64;
+// BIP322 generic message signing
+pub mod bip322;
+// BIP32 HD keys and BIP39 mnemonics
+pub mod bip32;
+pub mod bip39;
+// Differential tests against rust-bitcoin and rust-miniscript
+#[cfg(test)]
+mod differential;
+// Output descriptors (BIP380 - BIP386)
+pub mod descriptor;
+pub mod errors;
+pub mod hashes;
+// Private / public key handling (WIF, sec1, x-only)
+pub mod keys;
+// Miniscript parsing, type checking, compilation and satisfaction
+pub mod miniscript;
+// MuSig2 (BIP327) key aggregation and multi-party signing
+pub mod musig2;
+pub mod network;
+// Partially signed transactions (BIP174 / BIP370)
+pub mod psbt;
+// BIP340 Schnorr signatures and BIP341 key tweaking
+pub mod schnorr;
+// Taproot script trees, control blocks and Huffman leaf placement
+pub mod taproot;
+// Raw transaction building, editing and decoding (JSON)
+pub mod transaction;
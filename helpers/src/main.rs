@@ -1,11 +1,9 @@
-use clap::{Arg, Command};
-use k256::ecdsa::signature::Signer;
-use k256::ecdsa::signature::Verifier;
-use k256::ecdsa::Signature;
-use k256::ecdsa::SigningKey;
-use k256::ecdsa::VerifyingKey;
-use k256::elliptic_curve::rand_core::OsRng;
-use sha2::{Digest, Sha256};
+mod commands;
+
+use clap::Command;
+use helpers::address::Address;
+use helpers::keys::PrivateKey;
+use helpers::network::Network;
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -17,31 +15,35 @@ fn main() -> Result<(), Box<dyn Error>> {
 BTC helper tools:
 ==================\n
 1. Random Address Generator (generate)
-2. Sign message with a private key (sign -p <secret_key> -m <message>)\n
-
-[⚠️ Message will be hashed before signing]",
+2. BIP322 message signatures (sign -p <key> -m <message> [-a <address>] / verify -a <address> -m <message> -s <signature>)
+3. Address <-> scriptPubKey conversion (address encode -s <script> / address decode <address>)
+4. Key toolkit: WIF, compressed / uncompressed / x-only keys (keys generate / keys import <key>)
+5. BIP32 / BIP39 HD keys (hd mnemonic / hd derive --mnemonic <words> --purpose 84)
+6. Sign a transaction input (sign-tx <tx> -i <input> -s <prevout script> -a <amount> -p <key>)
+7. Raw transactions (tx create --input <txid:vout> --output <address:sats> / tx decode <tx>)
+8. PSBT roles (psbt create / update / sign / finalize / extract / analyze <psbt>)
+9. BIP340 Schnorr / BIP341 tweaks (schnorr sign -p <key> -m <hex> / verify / tweak <internal key>)
+10. MuSig2 key path spend simulation (musig --parties 3 / musig -p <key> -p <key>)
+11. Taproot script trees (taptree -l 3:<tokens> -l 1:<tokens> [--spend <leaf> -s <hex>])
+12. Output descriptors (descriptor wsh(sortedmulti(2,<xpub>/0/*,<xpub>/1/*)) -r 0..5)
+13. Miniscript (miniscript \"and_v(v:pk(A),older(144))\" -p A=<WIF> --satisfy --sequence 144)
+14. Spending policies (policy \"or(99@pk(A),and(pk(B),after(1000)))\" -p A=<key> -p B=<key>)\n",
         )
         .subcommand(Command::new("generate").about("Generates a random BTC Address"))
-        .subcommand(
-            Command::new("sign")
-                .about("Signs a message with a private key")
-                .arg(
-                    Arg::new("private_key")
-                        .short('p')
-                        .long("private-key")
-                        .help("The private key to sign with")
-                        .required(true)
-                        .num_args(1),
-                )
-                .arg(
-                    Arg::new("message")
-                        .short('m')
-                        .long("message")
-                        .help("The message to sign")
-                        .required(true)
-                        .num_args(1),
-                ),
-        )
+        .subcommand(commands::sign::command())
+        .subcommand(commands::verify::command())
+        .subcommand(commands::address::command())
+        .subcommand(commands::keys::command())
+        .subcommand(commands::hd::command())
+        .subcommand(commands::sign_tx::command())
+        .subcommand(commands::tx::command())
+        .subcommand(commands::psbt::command())
+        .subcommand(commands::schnorr::command())
+        .subcommand(commands::musig::command())
+        .subcommand(commands::taptree::command())
+        .subcommand(commands::descriptor::command())
+        .subcommand(commands::miniscript::command())
+        .subcommand(commands::policy::command())
         .get_matches();
 
     match matches.subcommand() {
@@ -52,19 +54,20 @@ BTC helper tools:
             }
             Err(e) => eprintln!("Error generating address: {}", e),
         },
-        Some(("sign", sub_matches)) => {
-            let private_key = sub_matches
-                .get_one::<String>("private_key")
-                .expect("Private key is required");
-            let message = sub_matches
-                .get_one::<String>("message")
-                .expect("Message is required");
-
-            match sign_message(private_key, message) {
-                Ok(signature) => println!("Signature: {}", signature),
-                Err(e) => eprintln!("Error signing message: {}", e),
-            }
-        }
+        Some(("sign", sub_matches)) => commands::sign::run(sub_matches)?,
+        Some(("verify", sub_matches)) => commands::verify::run(sub_matches)?,
+        Some(("address", sub_matches)) => commands::address::run(sub_matches)?,
+        Some(("keys", sub_matches)) => commands::keys::run(sub_matches)?,
+        Some(("hd", sub_matches)) => commands::hd::run(sub_matches)?,
+        Some(("sign-tx", sub_matches)) => commands::sign_tx::run(sub_matches)?,
+        Some(("tx", sub_matches)) => commands::tx::run(sub_matches)?,
+        Some(("psbt", sub_matches)) => commands::psbt::run(sub_matches)?,
+        Some(("schnorr", sub_matches)) => commands::schnorr::run(sub_matches)?,
+        Some(("musig", sub_matches)) => commands::musig::run(sub_matches)?,
+        Some(("taptree", sub_matches)) => commands::taptree::run(sub_matches)?,
+        Some(("descriptor", sub_matches)) => commands::descriptor::run(sub_matches)?,
+        Some(("miniscript", sub_matches)) => commands::miniscript::run(sub_matches)?,
+        Some(("policy", sub_matches)) => commands::policy::run(sub_matches)?,
         _ => println!("No subcommand was used. Use --help for usage information."),
     }
 
@@ -72,38 +75,11 @@ BTC helper tools:
 }
 
 fn generate_address() -> color_eyre::Result<(String, String)> {
-    let signing_key: SigningKey = SigningKey::random(&mut OsRng);
-    let verifying_key = signing_key.verifying_key().to_sec1_bytes();
+    let private_key = PrivateKey::random(Network::Mainnet);
+    let public_key = private_key.public_key();
 
     Ok((
-        hex::encode(hex::encode(signing_key.to_bytes())),
-        hex::encode(hex::encode(verifying_key)),
+        hex::encode(private_key.to_bytes()),
+        Address::p2pkh(&public_key.to_bytes(), Network::Mainnet).to_string(),
     ))
 }
-
-#[allow(deprecated)]
-fn sign_message(private_key: &str, message: &str) -> color_eyre::Result<String> {
-    let private_key_bytes = hex::decode(private_key)?;
-    if private_key_bytes.len() != 32 {
-        return Err(color_eyre::eyre::eyre!("Private key must be 32 bytes"));
-    }
-
-    let secret_key: SigningKey = SigningKey::from_slice(private_key_bytes.as_slice())
-        .expect("Unable to derive signing key.");
-    let public_key: VerifyingKey = *secret_key.verifying_key();
-
-    // Hash the message
-    let mut hasher = Sha256::new();
-    hasher.update(message.as_bytes());
-    let message_hash = hasher.finalize();
-    let message = message_hash.as_slice();
-
-    let signature: Signature = secret_key.sign(&message);
-
-    let result = public_key.verify(message, &signature);
-
-    match result {
-        Ok(_) => Ok(signature.to_string()),
-        Err(e) => Err(color_eyre::eyre::eyre!(e)),
-    }
-}
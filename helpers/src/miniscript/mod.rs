@@ -0,0 +1,1009 @@
+// ==================================================
+// Miniscript
+// ==================================================
+//
+// Miniscript writes scripts as a tree of spending conditions, `and_v(v:pk(A),older(144))`,
+// each fragment having a fixed script template. Because the templates compose, a miniscript
+// can be analysed without running it :
+// - its type (B / V / K / W and the z o n d u e f s m k properties, see `types`) tells whether
+//   it is valid, whether it can be satisfied without malleability and which timelocks it mixes
+// - its satisfactions are built bottom up from the signatures, preimages and timelocks at hand
+//   (see `satisfy`)
+//
+// Keys are descriptor keys, so miniscripts also appear inside `wsh()` and `tr()` leaves.
+// Fragments : 0, 1, pk_k, pk_h, pk, pkh, older, after, sha256, hash256, ripemd160, hash160,
+// andor, and_v, and_b, and_n, or_b, or_c, or_d, or_i, thresh, multi, multi_a and the
+// a: s: c: d: v: j: n: t: l: u: wrappers.
+// Spending policies, `or(99@pk(A),and(pk(B),after(1000)))`, compile to miniscript (see `policy`).
+
+pub mod policy;
+pub mod satisfy;
+pub mod types;
+
+use crate::descriptor::key::DescriptorKey;
+use crate::descriptor::{push_int, split_args, split_call};
+use crate::errors::{DescriptorError, MiniscriptError};
+use crate::hashes::hash160;
+use crate::miniscript::types::{type_check, Base, Type};
+use bee_vm::script::decode::{decode, Instruction};
+use bee_vm::script::encode::push_data;
+use std::collections::HashSet;
+use std::fmt;
+
+const OP_0: u8 = 0x00;
+const OP_1: u8 = 0x51;
+const OP_IF: u8 = 0x63;
+const OP_NOTIF: u8 = 0x64;
+const OP_ELSE: u8 = 0x67;
+const OP_ENDIF: u8 = 0x68;
+const OP_VERIFY: u8 = 0x69;
+const OP_TOALTSTACK: u8 = 0x6b;
+const OP_FROMALTSTACK: u8 = 0x6c;
+const OP_IFDUP: u8 = 0x73;
+const OP_DUP: u8 = 0x76;
+const OP_SWAP: u8 = 0x7c;
+const OP_SIZE: u8 = 0x82;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_0NOTEQUAL: u8 = 0x92;
+const OP_ADD: u8 = 0x93;
+const OP_BOOLAND: u8 = 0x9a;
+const OP_BOOLOR: u8 = 0x9b;
+const OP_NUMEQUAL: u8 = 0x9c;
+const OP_RIPEMD160: u8 = 0xa6;
+const OP_SHA256: u8 = 0xa8;
+const OP_HASH160: u8 = 0xa9;
+const OP_HASH256: u8 = 0xaa;
+const OP_CHECKSIG: u8 = 0xac;
+const OP_CHECKMULTISIG: u8 = 0xae;
+const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+const OP_CHECKSEQUENCEVERIFY: u8 = 0xb2;
+const OP_CHECKSIGADD: u8 = 0xba;
+
+/// Limit of non push opcodes (and CHECKMULTISIG keys) of a segwit v0 script.
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+/// Consensus limit of a segwit v0 witness script.
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+/// Witness scripts above this size are not relayed.
+pub const MAX_STANDARD_P2WSH_SCRIPT_SIZE: usize = 3600;
+/// Limit of keys of `multi()` (CHECKMULTISIG).
+pub const MAX_MULTISIG_KEYS: usize = 20;
+/// Limit of keys of `multi_a()` (the tapscript stack limit).
+pub const MAX_MULTI_A_KEYS: usize = 999;
+
+/// The script a miniscript compiles to : a segwit v0 witness script or a tapscript leaf.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptContext {
+    Segwitv0,
+    Tap,
+}
+
+#[derive(Debug, Clone)]
+pub enum Fragment {
+    False,
+    True,
+    PkK(DescriptorKey),
+    PkH(DescriptorKey),
+    /// Relative timelock, a BIP68 nSequence value.
+    Older(u32),
+    /// Absolute timelock, a height or a UNIX time.
+    After(u32),
+    Sha256([u8; 32]),
+    Hash256([u8; 32]),
+    Ripemd160([u8; 20]),
+    Hash160([u8; 20]),
+    /// `a:`
+    Alt(Box<Miniscript>),
+    /// `s:`
+    Swap(Box<Miniscript>),
+    /// `c:`
+    Check(Box<Miniscript>),
+    /// `d:`
+    DupIf(Box<Miniscript>),
+    /// `v:`
+    Verify(Box<Miniscript>),
+    /// `j:`
+    NonZero(Box<Miniscript>),
+    /// `n:`
+    ZeroNotEqual(Box<Miniscript>),
+    AndV(Box<Miniscript>, Box<Miniscript>),
+    AndB(Box<Miniscript>, Box<Miniscript>),
+    AndOr(Box<Miniscript>, Box<Miniscript>, Box<Miniscript>),
+    OrB(Box<Miniscript>, Box<Miniscript>),
+    OrC(Box<Miniscript>, Box<Miniscript>),
+    OrD(Box<Miniscript>, Box<Miniscript>),
+    OrI(Box<Miniscript>, Box<Miniscript>),
+    Thresh(usize, Vec<Miniscript>),
+    Multi(usize, Vec<DescriptorKey>),
+    MultiA(usize, Vec<DescriptorKey>),
+}
+
+/// A type checked fragment.
+#[derive(Debug, Clone)]
+pub struct Miniscript {
+    pub fragment: Fragment,
+    pub ty: Type,
+    pub context: ScriptContext,
+}
+
+impl Miniscript {
+    /// Parses a miniscript that is valid and sane (see `sanity_check`).
+    pub fn parse(expression: &str, context: ScriptContext) -> Result<Miniscript, MiniscriptError> {
+        let miniscript = Miniscript::parse_insane(expression, context)?;
+        miniscript.sanity_check()?;
+        Ok(miniscript)
+    }
+
+    /// Parses a miniscript that type checks as B and fits the consensus limits of its context,
+    /// it may still be malleable or unspendable.
+    pub fn parse_insane(
+        expression: &str,
+        context: ScriptContext,
+    ) -> Result<Miniscript, MiniscriptError> {
+        let miniscript = parse_node(expression, context)?;
+        if miniscript.ty.base != Base::B {
+            return Err(MiniscriptError::NotTopLevel(miniscript.ty.to_string()));
+        }
+        if context == ScriptContext::Segwitv0 {
+            let size = miniscript.encode(0)?.len();
+            if size > MAX_SCRIPT_SIZE {
+                return Err(MiniscriptError::ScriptTooLarge {
+                    size,
+                    max: MAX_SCRIPT_SIZE,
+                });
+            }
+            let ops = miniscript.ops_count(0)?;
+            if ops > MAX_OPS_PER_SCRIPT {
+                return Err(MiniscriptError::TooManyOps {
+                    ops,
+                    max: MAX_OPS_PER_SCRIPT,
+                });
+            }
+        }
+        Ok(miniscript)
+    }
+
+    /// Type checks a fragment.
+    pub fn new(fragment: Fragment, context: ScriptContext) -> Result<Miniscript, MiniscriptError> {
+        let ty = type_check(&fragment, context)?;
+        Ok(Miniscript {
+            fragment,
+            ty,
+            context,
+        })
+    }
+
+    /// Why the miniscript is not safe to use : malleable, spendable without a signature,
+    /// mixing height and time locks, repeating keys or above the standard script size.
+    pub fn insane_reasons(&self) -> Vec<String> {
+        let mut reasons = vec![];
+        if !self.ty.m {
+            reasons.push("some satisfactions are malleable".to_string());
+        }
+        if !self.ty.s {
+            reasons.push("some satisfactions need no signature".to_string());
+        }
+        if self.ty.timelocks.mixed {
+            reasons.push("some satisfactions mix height and time locks".to_string());
+        }
+        let keys: Vec<String> = self.keys().iter().map(|key| key.to_string()).collect();
+        if keys.iter().collect::<HashSet<_>>().len() != keys.len() {
+            reasons.push("some keys are repeated".to_string());
+        }
+        if self.context == ScriptContext::Segwitv0 {
+            if let Ok(script) = self.encode(0) {
+                if script.len() > MAX_STANDARD_P2WSH_SCRIPT_SIZE {
+                    reasons.push(format!(
+                        "the script is {} bytes, above the standard {}",
+                        script.len(),
+                        MAX_STANDARD_P2WSH_SCRIPT_SIZE
+                    ));
+                }
+            }
+        }
+        reasons
+    }
+
+    /// Fails with the first reason the miniscript is not sane.
+    pub fn sanity_check(&self) -> Result<(), MiniscriptError> {
+        match self.insane_reasons().into_iter().next() {
+            Some(reason) => Err(MiniscriptError::Insane(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Every key, in the order they appear.
+    pub fn keys(&self) -> Vec<&DescriptorKey> {
+        match &self.fragment {
+            Fragment::PkK(key) | Fragment::PkH(key) => vec![key],
+            Fragment::Multi(_, keys) | Fragment::MultiA(_, keys) => keys.iter().collect(),
+            _ => self
+                .children()
+                .into_iter()
+                .flat_map(|child| child.keys())
+                .collect(),
+        }
+    }
+
+    /// Direct sub-expressions.
+    pub fn children(&self) -> Vec<&Miniscript> {
+        match &self.fragment {
+            Fragment::Alt(x)
+            | Fragment::Swap(x)
+            | Fragment::Check(x)
+            | Fragment::DupIf(x)
+            | Fragment::Verify(x)
+            | Fragment::NonZero(x)
+            | Fragment::ZeroNotEqual(x) => vec![x],
+            Fragment::AndV(x, y)
+            | Fragment::AndB(x, y)
+            | Fragment::OrB(x, y)
+            | Fragment::OrC(x, y)
+            | Fragment::OrD(x, y)
+            | Fragment::OrI(x, y) => vec![x, y],
+            Fragment::AndOr(x, y, z) => vec![x, y, z],
+            Fragment::Thresh(_, subs) => subs.iter().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Whether a key is ranged, the script then depends on the index.
+    pub fn is_ranged(&self) -> bool {
+        self.keys().iter().any(|key| key.is_ranged())
+    }
+
+    /// The key as pushed by the script at `index` : compressed, or x-only in tapscript.
+    pub fn key_bytes(&self, key: &DescriptorKey, index: u32) -> Result<Vec<u8>, MiniscriptError> {
+        let public_key = key.derive(index).map_err(invalid_key)?;
+        Ok(match self.context {
+            ScriptContext::Segwitv0 => public_key.to_bytes(),
+            ScriptContext::Tap => public_key.x_only().to_vec(),
+        })
+    }
+
+    /// The script with ranged keys derived at `index`.
+    pub fn encode(&self, index: u32) -> Result<Vec<u8>, MiniscriptError> {
+        let mut script = vec![];
+        self.write(&mut script, index)?;
+        Ok(script)
+    }
+
+    /// Non push opcodes of the script plus the keys of its CHECKMULTISIGs, the segwit v0
+    /// opcode limit counts opcodes in unexecuted branches too.
+    pub fn ops_count(&self, index: u32) -> Result<usize, MiniscriptError> {
+        let opcodes = decode(&self.encode(index)?)?
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::Op(opcode) if opcode.0 > 0x60))
+            .count();
+        Ok(opcodes + self.multisig_keys())
+    }
+
+    fn multisig_keys(&self) -> usize {
+        match &self.fragment {
+            Fragment::Multi(_, keys) => keys.len(),
+            _ => self
+                .children()
+                .iter()
+                .map(|child| child.multisig_keys())
+                .sum(),
+        }
+    }
+
+    fn write(&self, script: &mut Vec<u8>, index: u32) -> Result<(), MiniscriptError> {
+        match &self.fragment {
+            Fragment::False => script.push(OP_0),
+            Fragment::True => script.push(OP_1),
+            Fragment::PkK(key) => push_data(script, &self.key_bytes(key, index)?)?,
+            Fragment::PkH(key) => {
+                script.extend([OP_DUP, OP_HASH160]);
+                push_data(script, &hash160(&self.key_bytes(key, index)?))?;
+                script.push(OP_EQUALVERIFY);
+            }
+            Fragment::Older(n) => {
+                push_int(script, *n as i64)?;
+                script.push(OP_CHECKSEQUENCEVERIFY);
+            }
+            Fragment::After(n) => {
+                push_int(script, *n as i64)?;
+                script.push(OP_CHECKLOCKTIMEVERIFY);
+            }
+            Fragment::Sha256(hash) => write_hash(script, OP_SHA256, hash)?,
+            Fragment::Hash256(hash) => write_hash(script, OP_HASH256, hash)?,
+            Fragment::Ripemd160(hash) => write_hash(script, OP_RIPEMD160, hash)?,
+            Fragment::Hash160(hash) => write_hash(script, OP_HASH160, hash)?,
+            Fragment::Alt(x) => {
+                script.push(OP_TOALTSTACK);
+                x.write(script, index)?;
+                script.push(OP_FROMALTSTACK);
+            }
+            Fragment::Swap(x) => {
+                script.push(OP_SWAP);
+                x.write(script, index)?;
+            }
+            Fragment::Check(x) => {
+                x.write(script, index)?;
+                script.push(OP_CHECKSIG);
+            }
+            Fragment::DupIf(x) => {
+                script.extend([OP_DUP, OP_IF]);
+                x.write(script, index)?;
+                script.push(OP_ENDIF);
+            }
+            Fragment::Verify(x) => {
+                x.write(script, index)?;
+                // CHECKSIG, CHECKMULTISIG, EQUAL and NUMEQUAL have a VERIFY variant, one more.
+                match x.has_free_verify() {
+                    true => *script.last_mut().expect("the script ends with an opcode") += 1,
+                    false => script.push(OP_VERIFY),
+                }
+            }
+            Fragment::NonZero(x) => {
+                script.extend([OP_SIZE, OP_0NOTEQUAL, OP_IF]);
+                x.write(script, index)?;
+                script.push(OP_ENDIF);
+            }
+            Fragment::ZeroNotEqual(x) => {
+                x.write(script, index)?;
+                script.push(OP_0NOTEQUAL);
+            }
+            Fragment::AndV(x, y) => {
+                x.write(script, index)?;
+                y.write(script, index)?;
+            }
+            Fragment::AndB(x, y) => {
+                x.write(script, index)?;
+                y.write(script, index)?;
+                script.push(OP_BOOLAND);
+            }
+            Fragment::AndOr(x, y, z) => {
+                x.write(script, index)?;
+                script.push(OP_NOTIF);
+                z.write(script, index)?;
+                script.push(OP_ELSE);
+                y.write(script, index)?;
+                script.push(OP_ENDIF);
+            }
+            Fragment::OrB(x, z) => {
+                x.write(script, index)?;
+                z.write(script, index)?;
+                script.push(OP_BOOLOR);
+            }
+            Fragment::OrC(x, z) => {
+                x.write(script, index)?;
+                script.push(OP_NOTIF);
+                z.write(script, index)?;
+                script.push(OP_ENDIF);
+            }
+            Fragment::OrD(x, z) => {
+                x.write(script, index)?;
+                script.extend([OP_IFDUP, OP_NOTIF]);
+                z.write(script, index)?;
+                script.push(OP_ENDIF);
+            }
+            Fragment::OrI(x, z) => {
+                script.push(OP_IF);
+                x.write(script, index)?;
+                script.push(OP_ELSE);
+                z.write(script, index)?;
+                script.push(OP_ENDIF);
+            }
+            Fragment::Thresh(k, subs) => {
+                for (position, sub) in subs.iter().enumerate() {
+                    sub.write(script, index)?;
+                    if position > 0 {
+                        script.push(OP_ADD);
+                    }
+                }
+                push_int(script, *k as i64)?;
+                script.push(OP_EQUAL);
+            }
+            Fragment::Multi(k, keys) => {
+                push_int(script, *k as i64)?;
+                for key in keys {
+                    push_data(script, &self.key_bytes(key, index)?)?;
+                }
+                push_int(script, keys.len() as i64)?;
+                script.push(OP_CHECKMULTISIG);
+            }
+            Fragment::MultiA(k, keys) => {
+                for (position, key) in keys.iter().enumerate() {
+                    push_data(script, &self.key_bytes(key, index)?)?;
+                    script.push(match position {
+                        0 => OP_CHECKSIG,
+                        _ => OP_CHECKSIGADD,
+                    });
+                }
+                push_int(script, *k as i64)?;
+                script.push(OP_NUMEQUAL);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the script ends with an opcode that has a VERIFY variant.
+    fn has_free_verify(&self) -> bool {
+        match &self.fragment {
+            Fragment::Check(_)
+            | Fragment::Sha256(_)
+            | Fragment::Hash256(_)
+            | Fragment::Ripemd160(_)
+            | Fragment::Hash160(_)
+            | Fragment::Thresh(..)
+            | Fragment::Multi(..)
+            | Fragment::MultiA(..) => true,
+            Fragment::Swap(x) | Fragment::AndV(_, x) => x.has_free_verify(),
+            _ => false,
+        }
+    }
+
+    /// The wrapper letter this fragment is written as, with the fragment it wraps.
+    fn wrapper(&self) -> Option<(char, &Miniscript)> {
+        match &self.fragment {
+            Fragment::Alt(x) => Some(('a', x)),
+            Fragment::Swap(x) => Some(('s', x)),
+            // c:pk_k() and c:pk_h() are written pk() and pkh().
+            Fragment::Check(x) if !matches!(x.fragment, Fragment::PkK(_) | Fragment::PkH(_)) => {
+                Some(('c', x))
+            }
+            Fragment::DupIf(x) => Some(('d', x)),
+            Fragment::Verify(x) => Some(('v', x)),
+            Fragment::NonZero(x) => Some(('j', x)),
+            Fragment::ZeroNotEqual(x) => Some(('n', x)),
+            Fragment::AndV(x, y) if matches!(y.fragment, Fragment::True) => Some(('t', x)),
+            Fragment::OrI(x, z) if matches!(x.fragment, Fragment::False) => Some(('l', z)),
+            Fragment::OrI(x, z) if matches!(z.fragment, Fragment::False) => Some(('u', x)),
+            _ => None,
+        }
+    }
+}
+
+impl Fragment {
+    /// Name of the fragment in expressions (`c`, `v`... for the wrappers).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fragment::False => "0",
+            Fragment::True => "1",
+            Fragment::PkK(_) => "pk_k",
+            Fragment::PkH(_) => "pk_h",
+            Fragment::Older(_) => "older",
+            Fragment::After(_) => "after",
+            Fragment::Sha256(_) => "sha256",
+            Fragment::Hash256(_) => "hash256",
+            Fragment::Ripemd160(_) => "ripemd160",
+            Fragment::Hash160(_) => "hash160",
+            Fragment::Alt(_) => "a",
+            Fragment::Swap(_) => "s",
+            Fragment::Check(_) => "c",
+            Fragment::DupIf(_) => "d",
+            Fragment::Verify(_) => "v",
+            Fragment::NonZero(_) => "j",
+            Fragment::ZeroNotEqual(_) => "n",
+            Fragment::AndV(..) => "and_v",
+            Fragment::AndB(..) => "and_b",
+            Fragment::AndOr(..) => "andor",
+            Fragment::OrB(..) => "or_b",
+            Fragment::OrC(..) => "or_c",
+            Fragment::OrD(..) => "or_d",
+            Fragment::OrI(..) => "or_i",
+            Fragment::Thresh(..) => "thresh",
+            Fragment::Multi(..) => "multi",
+            Fragment::MultiA(..) => "multi_a",
+        }
+    }
+}
+
+impl fmt::Display for ScriptContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptContext::Segwitv0 => write!(f, "segwit v0"),
+            ScriptContext::Tap => write!(f, "tapscript"),
+        }
+    }
+}
+
+impl fmt::Display for Miniscript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.wrapper().is_some() {
+            let mut wrappers = String::new();
+            let mut node = self;
+            while let Some((letter, inner)) = node.wrapper() {
+                wrappers.push(letter);
+                node = inner;
+            }
+            return write!(f, "{}:{}", wrappers, node);
+        }
+
+        let list = |f: &mut fmt::Formatter<'_>, items: Vec<String>| {
+            write!(f, "{}({})", self.fragment.name(), items.join(","))
+        };
+        match &self.fragment {
+            Fragment::False | Fragment::True => write!(f, "{}", self.fragment.name()),
+            Fragment::PkK(key) | Fragment::PkH(key) => {
+                write!(f, "{}({})", self.fragment.name(), key)
+            }
+            Fragment::Check(x) => match &x.fragment {
+                Fragment::PkK(key) => write!(f, "pk({})", key),
+                Fragment::PkH(key) => write!(f, "pkh({})", key),
+                _ => unreachable!("other c: fragments are wrappers"),
+            },
+            Fragment::Older(n) | Fragment::After(n) => {
+                write!(f, "{}({})", self.fragment.name(), n)
+            }
+            Fragment::Sha256(hash) | Fragment::Hash256(hash) => {
+                write!(f, "{}({})", self.fragment.name(), hex::encode(hash))
+            }
+            Fragment::Ripemd160(hash) | Fragment::Hash160(hash) => {
+                write!(f, "{}({})", self.fragment.name(), hex::encode(hash))
+            }
+            // andor(X,Y,0) is written and_n(X,Y).
+            Fragment::AndOr(x, y, z) if matches!(z.fragment, Fragment::False) => {
+                write!(f, "and_n({},{})", x, y)
+            }
+            Fragment::Thresh(k, subs) => list(
+                f,
+                std::iter::once(k.to_string())
+                    .chain(subs.iter().map(|sub| sub.to_string()))
+                    .collect(),
+            ),
+            Fragment::Multi(k, keys) | Fragment::MultiA(k, keys) => list(
+                f,
+                std::iter::once(k.to_string())
+                    .chain(keys.iter().map(|key| key.to_string()))
+                    .collect(),
+            ),
+            _ => list(
+                f,
+                self.children()
+                    .iter()
+                    .map(|child| child.to_string())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Replaces the key names of an expression (`pk(A)`) by keys, only whole arguments are
+/// replaced so names may also appear inside keys or fragment names.
+pub fn replace_key_names(expression: &str, names: &[(String, String)]) -> String {
+    let mut replaced = String::new();
+    let mut token = String::new();
+    let flush = |token: &mut String, replaced: &mut String| {
+        let key = names
+            .iter()
+            .find(|(name, _)| name == token)
+            .map(|(_, key)| key.as_str())
+            .unwrap_or(token.as_str());
+        replaced.push_str(key);
+        token.clear();
+    };
+    for ch in expression.chars() {
+        match ch {
+            '(' | ')' | ',' | '{' | '}' | ':' | '@' => {
+                flush(&mut token, &mut replaced);
+                replaced.push(ch);
+            }
+            _ => token.push(ch),
+        }
+    }
+    flush(&mut token, &mut replaced);
+    replaced
+}
+
+fn invalid_key(error: DescriptorError) -> MiniscriptError {
+    MiniscriptError::InvalidKey(error.to_string())
+}
+
+fn syntax(error: DescriptorError) -> MiniscriptError {
+    match error {
+        DescriptorError::Syntax(reason) => MiniscriptError::Syntax(reason),
+        error => MiniscriptError::Syntax(error.to_string()),
+    }
+}
+
+/// `SIZE 32 EQUALVERIFY <hash opcode> <hash> EQUAL`, preimages are always 32 bytes.
+fn write_hash(script: &mut Vec<u8>, opcode: u8, hash: &[u8]) -> Result<(), MiniscriptError> {
+    script.push(OP_SIZE);
+    push_int(script, 32)?;
+    script.extend([OP_EQUALVERIFY, opcode]);
+    push_data(script, hash)?;
+    script.push(OP_EQUAL);
+    Ok(())
+}
+
+fn parse_node(expression: &str, context: ScriptContext) -> Result<Miniscript, MiniscriptError> {
+    // Wrappers : `sv:older(144)` is s:(v:(older(144))).
+    let name_end = expression.find('(').unwrap_or(expression.len());
+    if let Some((wrappers, _)) = expression[..name_end].split_once(':') {
+        if wrappers.is_empty() {
+            return Err(MiniscriptError::Syntax(format!(
+                "empty wrapper : {}",
+                expression
+            )));
+        }
+        let mut node = parse_node(&expression[wrappers.len() + 1..], context)?;
+        for wrapper in wrappers.chars().rev() {
+            node = wrap(wrapper, node, context)?;
+        }
+        return Ok(node);
+    }
+
+    let fragment = match expression {
+        "0" => Fragment::False,
+        "1" => Fragment::True,
+        _ => {
+            let (name, args) = split_call(expression).map_err(syntax)?;
+            let args = split_args(args).map_err(syntax)?;
+            let arity = |count: usize| match args.len() == count {
+                true => Ok(()),
+                false => Err(MiniscriptError::Syntax(format!(
+                    "{}() takes {} arguments, found {}",
+                    name,
+                    count,
+                    args.len()
+                ))),
+            };
+            let sub = |position: usize| parse_node(args[position], context).map(Box::new);
+
+            match name {
+                "pk_k" | "pk_h" | "pk" | "pkh" => {
+                    arity(1)?;
+                    let key = parse_key(args[0], context)?;
+                    let inner = match name {
+                        "pk_k" | "pk" => Fragment::PkK(key),
+                        _ => Fragment::PkH(key),
+                    };
+                    match name {
+                        "pk" | "pkh" => Fragment::Check(Box::new(Miniscript::new(inner, context)?)),
+                        _ => inner,
+                    }
+                }
+                "older" | "after" => {
+                    arity(1)?;
+                    let n = parse_timelock(args[0])?;
+                    match name {
+                        "older" => Fragment::Older(n),
+                        _ => Fragment::After(n),
+                    }
+                }
+                "sha256" | "hash256" => {
+                    arity(1)?;
+                    let hash = parse_hash::<32>(args[0])?;
+                    match name {
+                        "sha256" => Fragment::Sha256(hash),
+                        _ => Fragment::Hash256(hash),
+                    }
+                }
+                "ripemd160" | "hash160" => {
+                    arity(1)?;
+                    let hash = parse_hash::<20>(args[0])?;
+                    match name {
+                        "ripemd160" => Fragment::Ripemd160(hash),
+                        _ => Fragment::Hash160(hash),
+                    }
+                }
+                "andor" => {
+                    arity(3)?;
+                    Fragment::AndOr(sub(0)?, sub(1)?, sub(2)?)
+                }
+                "and_n" => {
+                    arity(2)?;
+                    let zero = Miniscript::new(Fragment::False, context)?;
+                    Fragment::AndOr(sub(0)?, sub(1)?, Box::new(zero))
+                }
+                "and_v" | "and_b" | "or_b" | "or_c" | "or_d" | "or_i" => {
+                    arity(2)?;
+                    let (x, y) = (sub(0)?, sub(1)?);
+                    match name {
+                        "and_v" => Fragment::AndV(x, y),
+                        "and_b" => Fragment::AndB(x, y),
+                        "or_b" => Fragment::OrB(x, y),
+                        "or_c" => Fragment::OrC(x, y),
+                        "or_d" => Fragment::OrD(x, y),
+                        _ => Fragment::OrI(x, y),
+                    }
+                }
+                "thresh" => {
+                    let (k, subs) = parse_threshold(name, &args)?;
+                    let subs = subs
+                        .iter()
+                        .map(|sub| parse_node(sub, context))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Fragment::Thresh(k, subs)
+                }
+                "multi" | "multi_a" => {
+                    let (k, keys) = parse_threshold(name, &args)?;
+                    let max = match name {
+                        "multi" => MAX_MULTISIG_KEYS,
+                        _ => MAX_MULTI_A_KEYS,
+                    };
+                    if keys.len() > max {
+                        return Err(MiniscriptError::InvalidKeyCount {
+                            found: keys.len(),
+                            max,
+                        });
+                    }
+                    let keys = keys
+                        .iter()
+                        .map(|key| parse_key(key, context))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    match name {
+                        "multi" => Fragment::Multi(k, keys),
+                        _ => Fragment::MultiA(k, keys),
+                    }
+                }
+                _ => return Err(MiniscriptError::UnknownFragment(name.to_string())),
+            }
+        }
+    };
+    Miniscript::new(fragment, context)
+}
+
+fn wrap(
+    wrapper: char,
+    node: Miniscript,
+    context: ScriptContext,
+) -> Result<Miniscript, MiniscriptError> {
+    let node = Box::new(node);
+    let fragment = match wrapper {
+        'a' => Fragment::Alt(node),
+        's' => Fragment::Swap(node),
+        'c' => Fragment::Check(node),
+        'd' => Fragment::DupIf(node),
+        'v' => Fragment::Verify(node),
+        'j' => Fragment::NonZero(node),
+        'n' => Fragment::ZeroNotEqual(node),
+        't' => Fragment::AndV(node, Box::new(Miniscript::new(Fragment::True, context)?)),
+        'l' => Fragment::OrI(Box::new(Miniscript::new(Fragment::False, context)?), node),
+        'u' => Fragment::OrI(node, Box::new(Miniscript::new(Fragment::False, context)?)),
+        _ => return Err(MiniscriptError::UnknownWrapper(wrapper)),
+    };
+    Miniscript::new(fragment, context)
+}
+
+/// Keys are compressed in segwit v0 scripts, tapscripts also take x-only keys.
+fn parse_key(key: &str, context: ScriptContext) -> Result<DescriptorKey, MiniscriptError> {
+    let parsed: DescriptorKey = key.parse().map_err(invalid_key)?;
+    if parsed.is_uncompressed() {
+        return Err(MiniscriptError::InvalidKey(format!(
+            "{} (uncompressed keys are not allowed in segwit scripts)",
+            key
+        )));
+    }
+    if parsed.is_x_only() && context != ScriptContext::Tap {
+        return Err(MiniscriptError::InvalidKey(format!(
+            "{} (x-only keys are only allowed in tapscript)",
+            key
+        )));
+    }
+    Ok(parsed)
+}
+
+/// Timelocks are between 1 and 2^31 - 1, 0 is not a timelock and the top bit disables BIP68.
+fn parse_timelock(value: &str) -> Result<u32, MiniscriptError> {
+    let n: u32 = value
+        .parse()
+        .map_err(|_| MiniscriptError::Syntax(format!("invalid timelock : {}", value)))?;
+    if n == 0 || n >= 1 << 31 {
+        return Err(MiniscriptError::InvalidTimelock(n));
+    }
+    Ok(n)
+}
+
+fn parse_hash<const N: usize>(value: &str) -> Result<[u8; N], MiniscriptError> {
+    hex::decode(value)
+        .ok()
+        .and_then(|hash| hash.try_into().ok())
+        .ok_or_else(|| MiniscriptError::Syntax(format!("expected a {} bytes hash : {}", N, value)))
+}
+
+/// Splits `k,X1,...,Xn` checking that 1 <= k <= n.
+fn parse_threshold<'a>(
+    name: &str,
+    args: &[&'a str],
+) -> Result<(usize, Vec<&'a str>), MiniscriptError> {
+    let (k, rest) = args
+        .split_first()
+        .ok_or_else(|| MiniscriptError::Syntax(format!("{}() needs a threshold", name)))?;
+    let k: usize = k
+        .parse()
+        .map_err(|_| MiniscriptError::Syntax(format!("invalid threshold : {}", k)))?;
+    if k == 0 || k > rest.len() {
+        return Err(MiniscriptError::InvalidThreshold {
+            threshold: k,
+            count: rest.len(),
+        });
+    }
+    Ok((k, rest.to_vec()))
+}
+
+#[cfg(test)]
+mod test_miniscript {
+    use crate::errors::MiniscriptError;
+    use crate::miniscript::{replace_key_names, Miniscript, ScriptContext};
+    use rstest::rstest;
+
+    const A: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const B: &str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+    const C: &str = "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+    const H: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const R: &str = "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb";
+
+    fn names() -> Vec<(String, String)> {
+        [("A", A), ("B", B), ("C", C), ("H", H), ("R", R)]
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Script hex with `<A>` standing for the push of key A.
+    fn script_hex(template: &str) -> String {
+        template
+            .replace("<A>", &format!("21{}", A))
+            .replace("<B>", &format!("21{}", B))
+            .replace("<C>", &format!("21{}", C))
+    }
+
+    // Expected scripts from rust-miniscript.
+    #[rstest]
+    #[case("pk(A)", "<A>ac")]
+    #[case("pkh(A)", "76a914751e76e8199196d454941c45d1b3a323f1433bd688ac")]
+    #[case("and_v(v:pk(A),older(144))", "<A>ad029000b2")]
+    #[case("and_v(v:pk(A),after(500000001))", "<A>ad040165cd1db1")]
+    #[case("or_b(pk(A),s:pk(B))", "<A>ac7c<B>ac9b")]
+    #[case(
+        "or_d(pk(A),pkh(B))",
+        "<A>ac736476a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac68"
+    )]
+    #[case("or_i(pk(A),pk(B))", "63<A>ac67<B>ac68")]
+    #[case("andor(pk(A),pk(B),pk(C))", "<A>ac64<C>ac67<B>ac68")]
+    #[case("and_n(pk(A),pk(B))", "<A>ac640067<B>ac68")]
+    #[case("thresh(2,pk(A),s:pk(B),s:pk(C))", "<A>ac7c<B>ac937c<C>ac935287")]
+    #[case("thresh(2,pk(A),a:pk(B),a:pk(C))", "<A>ac6b<B>ac6c936b<C>ac6c935287")]
+    #[case("multi(2,A,B,C)", "52<A><B><C>53ae")]
+    #[case(
+        "and_v(v:sha256(H),pk(A))",
+        "82012088a820e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b85588<A>ac"
+    )]
+    #[case(
+        "and_v(v:hash256(H),pk(A))",
+        "82012088aa20e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b85588<A>ac"
+    )]
+    #[case(
+        "and_v(v:ripemd160(R),pk(A))",
+        "82012088a614b472a266d0bd89c13706a4132ccfb16f7c3b9fcb88<A>ac"
+    )]
+    #[case(
+        "t:or_c(pk(A),and_v(v:pk(B),or_c(pk(C),v:hash160(R))))",
+        "<A>ac64<B>ad<C>ac6482012088a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb88686851"
+    )]
+    #[case(
+        "or_b(pk(A),a:and_n(pk(B),n:after(100)))",
+        "<A>ac6b<B>ac6400670164b192686c9b"
+    )]
+    #[case(
+        "and_v(v:pk(A),or_d(pk(B),older(4194305)))",
+        "<A>ad<B>ac736403010040b268"
+    )]
+    #[case(
+        "thresh(3,pk(A),s:pk(B),s:pk(C),sln:older(12))",
+        "<A>ac7c<B>ac937c<C>ac937c6300675cb29268935387"
+    )]
+    #[case(
+        "or_d(multi(1,A,B),and_v(v:pk(C),older(144)))",
+        "51<A><B>52ae7364<C>ad029000b268"
+    )]
+    #[case("l:pk(A)", "630067<A>ac68")]
+    #[case("u:pk(A)", "63<A>ac670068")]
+    fn test_encode(#[case] template: &str, #[case] expected: &str) {
+        let expression = replace_key_names(template, &names());
+        let miniscript = Miniscript::parse(&expression, ScriptContext::Segwitv0).unwrap();
+        assert_eq!(
+            hex::encode(miniscript.encode(0).unwrap()),
+            script_hex(expected)
+        );
+        assert_eq!(miniscript.to_string(), expression);
+    }
+
+    #[rstest]
+    #[case("c:pk_k(A)", "pk(A)")]
+    #[case("c:pk_h(A)", "pkh(A)")]
+    #[case("and_v(vc:pk_h(A),c:pk_h(B))", "and_v(v:pkh(A),pkh(B))")]
+    #[case("and_v(v:pk(A),1)", "tv:pk(A)")]
+    #[case("or_i(0,pk(A))", "l:pk(A)")]
+    #[case("andor(pk(A),pk(B),0)", "and_n(pk(A),pk(B))")]
+    fn test_display_aliases(#[case] template: &str, #[case] expected: &str) {
+        let expression = replace_key_names(template, &names());
+        let miniscript = Miniscript::parse(&expression, ScriptContext::Segwitv0).unwrap();
+        assert_eq!(
+            miniscript.to_string(),
+            replace_key_names(expected, &names())
+        );
+    }
+
+    #[rstest]
+    #[case("multi_a(2,A,B,C)", "20<A>ac20<B>ba20<C>ba529c")]
+    #[case("and_v(v:pk(A),older(144))", "20<A>ad029000b2")]
+    fn test_encode_tapscript(#[case] template: &str, #[case] expected: &str) {
+        let miniscript =
+            Miniscript::parse(&replace_key_names(template, &names()), ScriptContext::Tap).unwrap();
+        let expected = expected
+            .replace("<A>", &A[2..])
+            .replace("<B>", &B[2..])
+            .replace("<C>", &C[2..]);
+        assert_eq!(hex::encode(miniscript.encode(0).unwrap()), expected);
+    }
+
+    #[rstest]
+    #[case("and_v(v:pk(A),older(144))", 2)]
+    #[case("multi(2,A,B,C)", 4)]
+    #[case("thresh(2,pk(A),s:pk(B),s:pk(C))", 8)]
+    fn test_ops_count(#[case] template: &str, #[case] expected: usize) {
+        let expression = replace_key_names(template, &names());
+        let miniscript = Miniscript::parse(&expression, ScriptContext::Segwitv0).unwrap();
+        assert_eq!(miniscript.ops_count(0).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("pk(A", ScriptContext::Segwitv0, "Syntax")]
+    #[case("foo(A)", ScriptContext::Segwitv0, "UnknownFragment")]
+    #[case("x:pk(A)", ScriptContext::Segwitv0, "UnknownWrapper")]
+    #[case("pk(nokey)", ScriptContext::Segwitv0, "InvalidKey")]
+    #[case(
+        "pk(79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)",
+        ScriptContext::Segwitv0,
+        "InvalidKey"
+    )]
+    #[case("or_d(d:v:older(144),pk(A))", ScriptContext::Segwitv0, "TypeCheck")]
+    #[case("multi_a(1,A,B)", ScriptContext::Segwitv0, "TypeCheck")]
+    #[case("multi(1,A,B)", ScriptContext::Tap, "TypeCheck")]
+    #[case("v:pk(A)", ScriptContext::Segwitv0, "NotTopLevel")]
+    #[case("and_v(v:pk(A),older(0))", ScriptContext::Segwitv0, "InvalidTimelock")]
+    #[case(
+        "thresh(4,pk(A),s:pk(B),s:pk(C))",
+        ScriptContext::Segwitv0,
+        "InvalidThreshold"
+    )]
+    #[case("multi(0,A,B)", ScriptContext::Segwitv0, "InvalidThreshold")]
+    #[case("older(144)", ScriptContext::Segwitv0, "Insane")]
+    #[case("or_i(pk(A),pk(A))", ScriptContext::Segwitv0, "Insane")]
+    #[case(
+        "and_v(v:pk(A),and_v(v:after(100),after(500000001)))",
+        ScriptContext::Tap,
+        "Insane"
+    )]
+    fn test_invalid(#[case] template: &str, #[case] context: ScriptContext, #[case] error: &str) {
+        let expression = replace_key_names(template, &names());
+        let res = Miniscript::parse(&expression, context);
+        let found = format!("{:?}", res.as_ref().unwrap_err());
+        assert!(found.starts_with(error), "{} : {}", template, found);
+    }
+
+    #[rstest]
+    fn test_parse_insane() {
+        let miniscript = Miniscript::parse_insane("older(144)", ScriptContext::Segwitv0).unwrap();
+        assert_eq!(miniscript.ty.to_string(), "Bzfmk");
+        assert_eq!(
+            miniscript.insane_reasons(),
+            vec!["some satisfactions need no signature".to_string()]
+        );
+        assert_eq!(
+            miniscript.sanity_check(),
+            Err(MiniscriptError::Insane(
+                "some satisfactions need no signature".to_string()
+            ))
+        );
+    }
+
+    #[rstest]
+    fn test_ranged_keys() {
+        let xpub = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+        let expression = format!("and_v(v:pk({}/0/*),pk({}/1/*))", xpub, xpub);
+        let miniscript = Miniscript::parse(&expression, ScriptContext::Segwitv0).unwrap();
+        assert!(miniscript.is_ranged());
+        assert_eq!(miniscript.keys().len(), 2);
+        assert_eq!(
+            hex::encode(miniscript.encode(3).unwrap()),
+            "21025b80fcc12bbbcff8f5562b86642fe8583738d9c0e558d39313701a24ac9b90a6ad2102e347b51cef755094b35cf0c26c20886e57d3407f708f797c2ef5880cb484ace4ac"
+        );
+    }
+}
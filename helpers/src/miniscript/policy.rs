@@ -0,0 +1,1092 @@
+// Spending policies and their compilation to miniscript.
+//
+// A policy only says who can spend and when, `or(99@pk(A),and(pk(B),after(1000)))`, the
+// compiler picks the script. Fragments : pk, after, older, sha256, hash256, ripemd160, hash160,
+// and(X,Y), or([N@]X,[M@]Y) and thresh(k,X1,...,Xn). The weights of or() are the odds of each
+// branch being used (1 by default).
+//
+// The compiler follows rust-miniscript : every policy node is compiled bottom up into the
+// cheapest miniscript of each type, the wrappers being tried on every candidate. The cost of a
+// candidate is its script size plus its expected witness size, the satisfaction size weighted
+// by the probability that the node is satisfied and the dissatisfaction size by the probability
+// that it is dissatisfied. The or() weights flow down to the children :
+// - or(l@X,r@Y) satisfied with probability p and dissatisfied with q compiles X for p * l / (l+r)
+//   and q + p * r / (l+r)
+// - and(X,Y) compiles X and Y for p and q
+// - thresh(k,...) compiles its n children for p * k / n and q + p * (n-k) / n
+// so rarely used branches get small scripts (pkh() instead of pk()) and large witnesses.
+//
+// The result must be sane (see `Miniscript::sanity_check`). Each spending path of the policy
+// can be spent in bee-vm with `spend_path`.
+
+use crate::descriptor::key::DescriptorKey;
+use crate::descriptor::{split_args, split_call};
+use crate::errors::{DescriptorError, PolicyError};
+use crate::hashes::{hash160, sha256, sha256d};
+use crate::keys::PrivateKey;
+use crate::miniscript::satisfy::spend;
+use crate::miniscript::types::Base;
+use crate::miniscript::{
+    parse_hash, parse_key, parse_threshold, parse_timelock, wrap, Fragment, Miniscript,
+    ScriptContext, MAX_MULTISIG_KEYS, MAX_OPS_PER_SCRIPT,
+};
+use bee_vm::transaction::Transaction;
+use ripemd::{Digest, Ripemd160};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Wrappers tried on every candidate.
+const WRAPPERS: [char; 10] = ['a', 's', 'c', 'd', 'v', 'j', 'n', 't', 'l', 'u'];
+/// Opcodes having a VERIFY version, so v: wrapping them adds no byte.
+const FREE_VERIFY: [u8; 4] = [0x87, 0x9c, 0xac, 0xae];
+/// nSequence of an input that does not enable nLockTime.
+const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+
+#[derive(Debug, Clone)]
+pub enum Policy {
+    Key(Box<DescriptorKey>),
+    /// Absolute timelock, a height or a UNIX time.
+    After(u32),
+    /// Relative timelock, a BIP68 nSequence value.
+    Older(u32),
+    Sha256([u8; 32]),
+    Hash256([u8; 32]),
+    Ripemd160([u8; 20]),
+    Hash160([u8; 20]),
+    And(Box<Policy>, Box<Policy>),
+    /// Two weighted branches.
+    Or(Vec<(u32, Policy)>),
+    Thresh(usize, Vec<Policy>),
+}
+
+/// A compiled policy.
+#[derive(Debug, Clone)]
+pub struct Compilation {
+    pub miniscript: Miniscript,
+    pub script_size: usize,
+    /// Satisfaction size weighted by the branch probabilities (script and control block
+    /// excluded).
+    pub expected_witness_size: f64,
+}
+
+/// One way to satisfy a policy.
+#[derive(Debug, Clone)]
+pub struct SpendingPath {
+    /// Probability of the path given by the or() weights, thresh() children being equally
+    /// likely.
+    pub probability: f64,
+    /// The keys, hashes and timelocks the path needs.
+    pub conditions: Vec<Policy>,
+}
+
+/// A miniscript with its script size and the expected sizes of its satisfaction and
+/// dissatisfaction, `None` when there is none.
+#[derive(Debug, Clone)]
+struct Candidate {
+    miniscript: Miniscript,
+    size: usize,
+    /// The script ends with an opcode that has a VERIFY version.
+    free_verify: bool,
+    /// Length of the expression, ties go to the shorter one.
+    length: usize,
+    sat: Option<f64>,
+    dissat: Option<f64>,
+}
+
+/// The cheapest non-malleable candidate of each type (and free VERIFY) for a policy node
+/// satisfied with probability `sat_probability` and dissatisfied with `dissat_probability`.
+#[derive(Clone)]
+struct Candidates {
+    best: Vec<Candidate>,
+    sat_probability: f64,
+    dissat_probability: f64,
+    context: ScriptContext,
+}
+
+/// Compiles policy nodes, the candidates of a node being cached for each pair of
+/// probabilities since or() compiles its branches several times.
+struct Compiler {
+    context: ScriptContext,
+    cache: HashMap<(String, u64, u64), Candidates>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Or {
+    B,
+    C,
+    D,
+    I,
+}
+
+impl Policy {
+    /// Parses a policy, keys are compressed (or x-only in tapscript) descriptor keys.
+    pub fn parse(expression: &str, context: ScriptContext) -> Result<Policy, PolicyError> {
+        let (name, args) = split_call(expression).map_err(syntax)?;
+        let args = split_args(args).map_err(syntax)?;
+        let arity = |count: usize| match args.len() == count {
+            true => Ok(()),
+            false => Err(PolicyError::Syntax(format!(
+                "{}() takes {} arguments, found {}",
+                name,
+                count,
+                args.len()
+            ))),
+        };
+
+        Ok(match name {
+            "pk" => {
+                arity(1)?;
+                Policy::Key(Box::new(parse_key(args[0], context)?))
+            }
+            "after" => {
+                arity(1)?;
+                Policy::After(parse_timelock(args[0])?)
+            }
+            "older" => {
+                arity(1)?;
+                Policy::Older(parse_timelock(args[0])?)
+            }
+            "sha256" => {
+                arity(1)?;
+                Policy::Sha256(parse_hash(args[0])?)
+            }
+            "hash256" => {
+                arity(1)?;
+                Policy::Hash256(parse_hash(args[0])?)
+            }
+            "ripemd160" => {
+                arity(1)?;
+                Policy::Ripemd160(parse_hash(args[0])?)
+            }
+            "hash160" => {
+                arity(1)?;
+                Policy::Hash160(parse_hash(args[0])?)
+            }
+            "and" => {
+                arity(2)?;
+                Policy::And(
+                    Box::new(Policy::parse(args[0], context)?),
+                    Box::new(Policy::parse(args[1], context)?),
+                )
+            }
+            "or" => {
+                arity(2)?;
+                Policy::Or(vec![
+                    parse_branch(args[0], context)?,
+                    parse_branch(args[1], context)?,
+                ])
+            }
+            "thresh" => {
+                let (k, subs) = parse_threshold(name, &args)?;
+                let subs = subs
+                    .iter()
+                    .map(|sub| Policy::parse(sub, context))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Policy::Thresh(k, subs)
+            }
+            _ => return Err(PolicyError::UnknownFragment(name.to_string())),
+        })
+    }
+
+    /// Compiles the policy to the sane miniscript with the smallest script plus expected
+    /// witness.
+    pub fn compile(&self, context: ScriptContext) -> Result<Compilation, PolicyError> {
+        let mut compiler = Compiler {
+            context,
+            cache: HashMap::new(),
+        };
+        let candidates = compiler.candidates(self, 1.0, 0.0)?;
+        let mut top_level: Vec<&Candidate> = candidates
+            .best
+            .iter()
+            .filter(|candidate| candidate.miniscript.ty.base == Base::B && candidate.sat.is_some())
+            .collect();
+        top_level.sort_by_key(|candidate| candidate.length);
+        top_level.sort_by(|a, b| a.cost(1.0, 0.0).total_cmp(&b.cost(1.0, 0.0)));
+
+        let mut first_reasons = None;
+        for candidate in top_level {
+            let mut reasons = candidate.miniscript.insane_reasons();
+            if context == ScriptContext::Segwitv0 {
+                let ops = candidate.miniscript.ops_count(0)?;
+                if ops > MAX_OPS_PER_SCRIPT {
+                    reasons.push(format!(
+                        "{} opcodes, above the limit of {}",
+                        ops, MAX_OPS_PER_SCRIPT
+                    ));
+                }
+            }
+            if reasons.is_empty() {
+                return Ok(Compilation {
+                    miniscript: candidate.miniscript.clone(),
+                    script_size: candidate.size,
+                    expected_witness_size: candidate.sat.unwrap_or_default(),
+                });
+            }
+            first_reasons.get_or_insert(reasons.join(", "));
+        }
+        Err(PolicyError::NoSaneCompilation(
+            first_reasons.unwrap_or_else(|| "no miniscript of type B".to_string()),
+        ))
+    }
+
+    /// Every way to satisfy the policy, with its probability.
+    pub fn spending_paths(&self) -> Vec<SpendingPath> {
+        match self {
+            Policy::And(x, y) => combine(&[x.spending_paths(), y.spending_paths()]),
+            Policy::Or(branches) => {
+                let total: f64 = branches.iter().map(|(weight, _)| *weight as f64).sum();
+                branches
+                    .iter()
+                    .flat_map(|(weight, branch)| {
+                        branch
+                            .spending_paths()
+                            .into_iter()
+                            .map(move |path| SpendingPath {
+                                probability: path.probability * *weight as f64 / total,
+                                ..path
+                            })
+                    })
+                    .collect()
+            }
+            Policy::Thresh(k, subs) => {
+                let paths: Vec<Vec<SpendingPath>> =
+                    subs.iter().map(|sub| sub.spending_paths()).collect();
+                let chosen = combinations(subs.len(), *k);
+                let count = chosen.len() as f64;
+                chosen
+                    .iter()
+                    .flat_map(|chosen| {
+                        let groups: Vec<Vec<SpendingPath>> =
+                            chosen.iter().map(|i| paths[*i].clone()).collect();
+                        combine(&groups).into_iter().map(|path| SpendingPath {
+                            probability: path.probability / count,
+                            ..path
+                        })
+                    })
+                    .collect()
+            }
+            condition => vec![SpendingPath {
+                probability: 1.0,
+                conditions: vec![condition.clone()],
+            }],
+        }
+    }
+
+    /// Whether `preimage` hashes to the hash of a hash condition.
+    fn is_preimage(&self, preimage: &[u8]) -> bool {
+        match self {
+            Policy::Sha256(hash) => sha256(preimage) == *hash,
+            Policy::Hash256(hash) => sha256d(preimage) == *hash,
+            Policy::Ripemd160(hash) => Ripemd160::digest(preimage)[..] == hash[..],
+            Policy::Hash160(hash) => hash160(preimage) == *hash,
+            _ => false,
+        }
+    }
+}
+
+impl Compiler {
+    fn candidates(
+        &mut self,
+        policy: &Policy,
+        sat_probability: f64,
+        dissat_probability: f64,
+    ) -> Result<Candidates, PolicyError> {
+        let cache_key = (
+            policy.to_string(),
+            sat_probability.to_bits(),
+            dissat_probability.to_bits(),
+        );
+        if let Some(candidates) = self.cache.get(&cache_key) {
+            return Ok(candidates.clone());
+        }
+
+        let mut candidates = Candidates {
+            best: vec![],
+            sat_probability,
+            dissat_probability,
+            context: self.context,
+        };
+        let (p, q) = (sat_probability, dissat_probability);
+        // Pushed signatures are at most 73 bytes (71 bytes DER + the sighash byte) in segwit v0
+        // and 66 in tapscript (64 bytes + the sighash byte), pk_h also reveals the key.
+        let (signature, key) = match self.context {
+            ScriptContext::Segwitv0 => (73.0, 34.0),
+            ScriptContext::Tap => (66.0, 33.0),
+        };
+
+        match policy {
+            Policy::Key(pk) => {
+                candidates.add(Fragment::PkK(*pk.clone()), Some(signature), Some(1.0))?;
+                candidates.add(
+                    Fragment::PkH(*pk.clone()),
+                    Some(signature + key),
+                    Some(1.0 + key),
+                )?;
+            }
+            Policy::After(n) => candidates.add(Fragment::After(*n), Some(0.0), None)?,
+            Policy::Older(n) => candidates.add(Fragment::Older(*n), Some(0.0), None)?,
+            // A 32 bytes preimage, or any other 32 bytes to dissatisfy.
+            Policy::Sha256(hash) => {
+                candidates.add(Fragment::Sha256(*hash), Some(33.0), Some(33.0))?
+            }
+            Policy::Hash256(hash) => {
+                candidates.add(Fragment::Hash256(*hash), Some(33.0), Some(33.0))?
+            }
+            Policy::Ripemd160(hash) => {
+                candidates.add(Fragment::Ripemd160(*hash), Some(33.0), Some(33.0))?
+            }
+            Policy::Hash160(hash) => {
+                candidates.add(Fragment::Hash160(*hash), Some(33.0), Some(33.0))?
+            }
+            Policy::And(x, y) => {
+                let left = self.candidates(x, p, q)?;
+                let right = self.candidates(y, p, q)?;
+                // and_n(X,Y) does not run Y when X is dissatisfied.
+                let left_only_sat = self.candidates(x, p, 0.0)?;
+                let right_only_sat = self.candidates(y, p, 0.0)?;
+                candidates.add_and(&left, &right, &right_only_sat)?;
+                candidates.add_and(&right, &left, &left_only_sat)?;
+            }
+            Policy::Or(branches) => {
+                let total: f64 = branches.iter().map(|(weight, _)| *weight as f64).sum();
+                let (left_weight, x) = &branches[0];
+                let (right_weight, y) = &branches[1];
+                let (pl, pr) = (*left_weight as f64 / total, *right_weight as f64 / total);
+                // A branch is dissatisfied whenever the or() is not satisfied through it (or_b,
+                // left of or_d), only when the other branch is used (left of or_c), only when
+                // the or() is dissatisfied (or_i, right of or_d) or never (right of or_c).
+                let variants = |other: f64| [q + other * p, other * p, q, 0.0];
+                let mut left = vec![];
+                for dissat in variants(pr) {
+                    left.push(self.candidates(x, p * pl, dissat)?);
+                }
+                let mut right = vec![];
+                for dissat in variants(pl) {
+                    right.push(self.candidates(y, p * pr, dissat)?);
+                }
+                for (x, z, px) in [(&left, &right, pl), (&right, &left, pr)] {
+                    candidates.add_or(Or::B, &x[0], &z[0], px)?;
+                    candidates.add_or(Or::D, &x[0], &z[2], px)?;
+                    candidates.add_or(Or::C, &x[1], &z[3], px)?;
+                    candidates.add_or(Or::I, &x[2], &z[3], px)?;
+                    candidates.add_or(Or::I, &x[3], &z[2], px)?;
+                }
+
+                // or(and(X,Y),Z) is also andor(X,Y,Z), Y only runs when X is satisfied.
+                for (branch, other, px) in [(x, y, pl), (y, x, pr)] {
+                    if let Policy::And(a, b) = branch {
+                        let pz = 1.0 - px;
+                        let other = self.candidates(other, p * pz, q)?;
+                        let a_first = self.candidates(a, p * px, q + p * pz)?;
+                        let a_second = self.candidates(a, p * px, 0.0)?;
+                        let b_first = self.candidates(b, p * px, q + p * pz)?;
+                        let b_second = self.candidates(b, p * px, 0.0)?;
+                        candidates.add_andor(&a_first, &b_second, &other, px)?;
+                        candidates.add_andor(&b_first, &a_second, &other, px)?;
+                    }
+                }
+            }
+            Policy::Thresh(k, subs) => {
+                // thresh(n,X1,...,Xn) is and(...and(X1,X2)...,Xn).
+                if *k == subs.len() {
+                    let and = subs[1..].iter().fold(subs[0].clone(), |and, sub| {
+                        Policy::And(Box::new(and), Box::new(sub.clone()))
+                    });
+                    for candidate in self.candidates(&and, p, q)?.best {
+                        candidates.insert(candidate)?;
+                    }
+                }
+                // Each child is satisfied k times out of n.
+                let satisfied = *k as f64 / subs.len() as f64;
+                let children = subs
+                    .iter()
+                    .map(|sub| self.candidates(sub, p * satisfied, q + p * (1.0 - satisfied)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                candidates.add_thresh(*k, subs, &children, signature)?;
+            }
+        }
+        // d: j: l: and u: bring their own dissatisfaction, so candidates compiled to never be
+        // dissatisfied are worth wrapping too.
+        if q > 0.0 {
+            for candidate in self.candidates(policy, p, 0.0)?.best {
+                for wrapped in candidates.wrappings(&candidate)? {
+                    candidates.insert(wrapped)?;
+                }
+            }
+        }
+        self.cache.insert(cache_key, candidates.clone());
+        Ok(candidates)
+    }
+}
+
+impl Candidate {
+    fn new(
+        miniscript: Miniscript,
+        sat: Option<f64>,
+        dissat: Option<f64>,
+    ) -> Result<Candidate, PolicyError> {
+        let script = miniscript.encode(0)?;
+        Ok(Candidate {
+            size: script.len(),
+            free_verify: script
+                .last()
+                .is_some_and(|opcode| FREE_VERIFY.contains(opcode)),
+            length: miniscript.to_string().len(),
+            miniscript,
+            sat,
+            dissat,
+        })
+    }
+
+    /// Whether the candidate is at most as costly as `other`, and as short on a tie.
+    fn beats(&self, other: &Candidate, p: f64, q: f64) -> bool {
+        match self.cost(p, q).total_cmp(&other.cost(p, q)) {
+            Ordering::Equal => self.length <= other.length,
+            ordering => ordering == Ordering::Less,
+        }
+    }
+
+    /// Whether the candidate can be used wherever `other` can : same base type and free
+    /// VERIFY, and at least its properties.
+    fn covers(&self, other: &Candidate) -> bool {
+        let (ty, other_ty) = (&self.miniscript.ty, &other.miniscript.ty);
+        let properties = [
+            (ty.z, other_ty.z),
+            (ty.o, other_ty.o),
+            (ty.n, other_ty.n),
+            (ty.d, other_ty.d),
+            (ty.u, other_ty.u),
+            (ty.e, other_ty.e),
+            (ty.f, other_ty.f),
+            (ty.s, other_ty.s),
+            (ty.m, other_ty.m),
+            (!ty.timelocks.mixed, !other_ty.timelocks.mixed),
+        ];
+        ty.base == other_ty.base
+            && self.free_verify == other.free_verify
+            && properties
+                .iter()
+                .all(|(has, other_has)| *has || !*other_has)
+    }
+
+    fn cost(&self, sat_probability: f64, dissat_probability: f64) -> f64 {
+        self.size as f64
+            + self.sat.map_or(0.0, |sat| sat_probability * sat)
+            + self
+                .dissat
+                .map_or(0.0, |dissat| dissat_probability * dissat)
+    }
+
+    fn boxed(&self) -> Box<Miniscript> {
+        Box::new(self.miniscript.clone())
+    }
+
+    fn base(&self) -> Base {
+        self.miniscript.ty.base
+    }
+}
+
+impl Candidates {
+    fn values(&self) -> impl Iterator<Item = &Candidate> {
+        self.best.iter()
+    }
+
+    /// Adds the fragment if it type checks.
+    fn add(
+        &mut self,
+        fragment: Fragment,
+        sat: Option<f64>,
+        dissat: Option<f64>,
+    ) -> Result<(), PolicyError> {
+        let miniscript = match Miniscript::new(fragment, self.context) {
+            Ok(miniscript) => miniscript,
+            Err(_) => return Ok(()),
+        };
+        self.insert(Candidate::new(miniscript, sat, dissat)?)
+    }
+
+    /// Keeps the candidate unless a candidate with at least its properties is as cheap, then
+    /// tries it with every wrapper. Malleable candidates are dropped, wrapping them would not
+    /// make them non-malleable.
+    fn insert(&mut self, candidate: Candidate) -> Result<(), PolicyError> {
+        let (p, q) = (self.sat_probability, self.dissat_probability);
+        let mut queue = vec![candidate];
+        while let Some(candidate) = queue.pop() {
+            if !candidate.miniscript.ty.m {
+                continue;
+            }
+            let dominated = self
+                .best
+                .iter()
+                .any(|best| best.covers(&candidate) && best.beats(&candidate, p, q));
+            if dominated {
+                continue;
+            }
+            self.best
+                .retain(|best| !(candidate.covers(best) && candidate.beats(best, p, q)));
+            queue.extend(self.wrappings(&candidate)?);
+            self.best.push(candidate);
+        }
+        Ok(())
+    }
+
+    /// The candidate under each wrapper it type checks with.
+    fn wrappings(&self, candidate: &Candidate) -> Result<Vec<Candidate>, PolicyError> {
+        let mut wrappings = vec![];
+        for wrapper in WRAPPERS {
+            let wrapped = match wrap(wrapper, candidate.miniscript.clone(), self.context) {
+                Ok(wrapped) => wrapped,
+                Err(_) => continue,
+            };
+            // IF branches are chosen by a 1 (2 bytes) or an empty push (1 byte).
+            let (sat, dissat) = match wrapper {
+                'd' | 'u' => (candidate.sat.map(|sat| sat + 2.0), Some(1.0)),
+                'l' => (candidate.sat.map(|sat| sat + 1.0), Some(2.0)),
+                'j' => (candidate.sat, Some(1.0)),
+                'v' | 't' => (candidate.sat, None),
+                _ => (candidate.sat, candidate.dissat),
+            };
+            wrappings.push(Candidate::new(wrapped, sat, dissat)?);
+        }
+        Ok(wrappings)
+    }
+
+    /// and_v(X,Y), and_b(X,Y) and and_n(X,Y), the Y of and_n being from `right_only_sat`.
+    fn add_and(
+        &mut self,
+        left: &Candidates,
+        right: &Candidates,
+        right_only_sat: &Candidates,
+    ) -> Result<(), PolicyError> {
+        for x in left.values() {
+            for y in right.values() {
+                let sat = sum(&[x.sat, y.sat]);
+                match (x.base(), y.base()) {
+                    (Base::V, _) => self.add(Fragment::AndV(x.boxed(), y.boxed()), sat, None)?,
+                    (Base::B, Base::W) => self.add(
+                        Fragment::AndB(x.boxed(), y.boxed()),
+                        sat,
+                        sum(&[x.dissat, y.dissat]),
+                    )?,
+                    _ => {}
+                }
+            }
+            if x.base() != Base::B {
+                continue;
+            }
+            for y in right_only_sat.values().filter(|y| y.base() == Base::B) {
+                let zero = Miniscript::new(Fragment::False, self.context)?;
+                self.add(
+                    Fragment::AndOr(x.boxed(), y.boxed(), Box::new(zero)),
+                    sum(&[x.sat, y.sat]),
+                    x.dissat,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// or_b(X,Z), or_d(X,Z), or_c(X,Z) or or_i(X,Z), X being used with probability `px`.
+    fn add_or(
+        &mut self,
+        or: Or,
+        left: &Candidates,
+        right: &Candidates,
+        px: f64,
+    ) -> Result<(), PolicyError> {
+        let pz = 1.0 - px;
+        for x in left.values() {
+            for z in right.values() {
+                let dissat = sum(&[x.dissat, z.dissat]);
+                match (or, x.base(), z.base()) {
+                    (Or::B, Base::B, Base::W) => {
+                        let sat =
+                            weighted(px, sum(&[x.sat, z.dissat]), pz, sum(&[x.dissat, z.sat]));
+                        self.add(Fragment::OrB(x.boxed(), z.boxed()), sat, dissat)?;
+                    }
+                    (Or::D, Base::B, Base::B) => {
+                        let sat = weighted(px, x.sat, pz, sum(&[x.dissat, z.sat]));
+                        self.add(Fragment::OrD(x.boxed(), z.boxed()), sat, dissat)?;
+                    }
+                    (Or::C, Base::B, Base::V) => {
+                        let sat = weighted(px, x.sat, pz, sum(&[x.dissat, z.sat]));
+                        self.add(Fragment::OrC(x.boxed(), z.boxed()), sat, None)?;
+                    }
+                    (Or::I, _, _) if x.base() == z.base() && x.base() != Base::W => {
+                        // The IF branch is chosen by a 1, the ELSE branch by an empty push.
+                        let sat =
+                            weighted(px, sum(&[x.sat, Some(2.0)]), pz, sum(&[z.sat, Some(1.0)]));
+                        let dissat = match (x.dissat, z.dissat) {
+                            (Some(dx), Some(dz)) => Some((dx + 2.0).min(dz + 1.0)),
+                            (Some(dx), None) => Some(dx + 2.0),
+                            (None, dz) => dz.map(|dz| dz + 1.0),
+                        };
+                        self.add(Fragment::OrI(x.boxed(), z.boxed()), sat, dissat)?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// andor(X,Y,Z), X and Y being used with probability `px`.
+    fn add_andor(
+        &mut self,
+        first: &Candidates,
+        second: &Candidates,
+        other: &Candidates,
+        px: f64,
+    ) -> Result<(), PolicyError> {
+        let pz = 1.0 - px;
+        for x in first.values().filter(|x| x.base() == Base::B) {
+            for y in second.values() {
+                for z in other.values().filter(|z| z.base() == y.base()) {
+                    let sat = weighted(px, sum(&[x.sat, y.sat]), pz, sum(&[x.dissat, z.sat]));
+                    let dissat = sum(&[x.dissat, z.dissat]);
+                    self.add(
+                        Fragment::AndOr(x.boxed(), y.boxed(), z.boxed()),
+                        sat,
+                        dissat,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// thresh(k,...) of the cheapest `children`, and multi() / multi_a() for keys.
+    fn add_thresh(
+        &mut self,
+        k: usize,
+        subs: &[Policy],
+        children: &[Candidates],
+        signature: f64,
+    ) -> Result<(), PolicyError> {
+        let n = subs.len();
+        let keys: Vec<DescriptorKey> = subs
+            .iter()
+            .filter_map(|sub| match sub {
+                Policy::Key(key) => Some(*key.clone()),
+                _ => None,
+            })
+            .collect();
+        if keys.len() == n {
+            // multi() takes k signatures and a dummy element, multi_a() one element per key.
+            match self.context {
+                ScriptContext::Segwitv0 if n <= MAX_MULTISIG_KEYS => self.add(
+                    Fragment::Multi(k, keys),
+                    Some(1.0 + k as f64 * signature),
+                    Some(1.0 + k as f64),
+                )?,
+                ScriptContext::Segwitv0 => {}
+                ScriptContext::Tap => self.add(
+                    Fragment::MultiA(k, keys),
+                    Some(k as f64 * signature + (n - k) as f64),
+                    Some(n as f64),
+                )?,
+            }
+        }
+
+        let (ps, pd) = (k as f64 / n as f64, (n - k) as f64 / n as f64);
+        let cheapest = |candidates: &Candidates, base: Base| -> Option<Candidate> {
+            let (p, q) = (candidates.sat_probability, candidates.dissat_probability);
+            candidates
+                .values()
+                .filter(|c| c.base() == base && c.miniscript.ty.d && c.miniscript.ty.u)
+                // A thresh() is only non-malleable if its children have a unique dissatisfaction.
+                .filter(|c| c.miniscript.ty.e)
+                .filter(|c| c.sat.is_some() && c.dissat.is_some())
+                .min_by(|a, b| a.cost(p, q).total_cmp(&b.cost(p, q)))
+                .cloned()
+        };
+        // The first child is a B, the others W, try each child first.
+        for first in 0..n {
+            let mut chosen = vec![];
+            for (position, child) in children.iter().enumerate() {
+                let base = match position == first {
+                    true => Base::B,
+                    false => Base::W,
+                };
+                chosen.extend(cheapest(child, base));
+            }
+            if chosen.len() != n {
+                continue;
+            }
+            let first = chosen.remove(first);
+            chosen.insert(0, first);
+            let sat = chosen
+                .iter()
+                .map(|c| ps * c.sat.unwrap_or_default() + pd * c.dissat.unwrap_or_default())
+                .sum();
+            let dissat = chosen.iter().map(|c| c.dissat.unwrap_or_default()).sum();
+            let subs = chosen.into_iter().map(|c| c.miniscript).collect();
+            self.add(Fragment::Thresh(k, subs), Some(sat), Some(dissat))?;
+        }
+        Ok(())
+    }
+}
+
+/// Spends `path` of a compiled policy in bee-vm (see `satisfy::spend`) with only the secrets
+/// the path needs, the nSequence and nLockTime meeting its timelocks. Keys and preimages are
+/// picked from `keys` and `preimages`.
+pub fn spend_path(
+    miniscript: &Miniscript,
+    index: u32,
+    path: &SpendingPath,
+    keys: &[PrivateKey],
+    preimages: &[Vec<u8>],
+) -> Result<Transaction, PolicyError> {
+    let mut path_keys = vec![];
+    let mut path_preimages = vec![];
+    let (mut sequence, mut lock_time) = (None, None);
+    for condition in &path.conditions {
+        let missing = || PolicyError::MissingSecret(condition.to_string());
+        match condition {
+            Policy::Key(key) => {
+                let pushed = miniscript.key_bytes(key, index)?;
+                let key = keys
+                    .iter()
+                    .find(|private_key| {
+                        let public_key = private_key.public_key();
+                        match miniscript.context {
+                            ScriptContext::Segwitv0 => public_key.to_bytes() == pushed,
+                            ScriptContext::Tap => public_key.x_only()[..] == pushed[..],
+                        }
+                    })
+                    .ok_or_else(missing)?;
+                path_keys.push(key.clone());
+            }
+            Policy::Older(n) => sequence = sequence.max(Some(*n)),
+            Policy::After(n) => lock_time = lock_time.max(Some(*n)),
+            hash => {
+                let preimage = preimages
+                    .iter()
+                    .find(|preimage| hash.is_preimage(preimage))
+                    .ok_or_else(missing)?;
+                path_preimages.push(preimage.clone());
+            }
+        }
+    }
+    // nLockTime is only enforced when the input is not final.
+    let sequence = match (sequence, lock_time) {
+        (Some(sequence), _) => sequence,
+        (None, Some(_)) => SEQUENCE_FINAL - 1,
+        (None, None) => SEQUENCE_FINAL,
+    };
+    Ok(spend(
+        miniscript,
+        index,
+        &path_keys,
+        &path_preimages,
+        sequence,
+        lock_time.unwrap_or(0),
+    )?)
+}
+
+/// Serialized size of witness items : the compact size length and the bytes of each.
+pub fn witness_size(items: &[Vec<u8>]) -> usize {
+    items
+        .iter()
+        .map(|item| {
+            let length = match item.len() {
+                0..=252 => 1,
+                253..=0xffff => 3,
+                _ => 5,
+            };
+            length + item.len()
+        })
+        .sum()
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Policy::Key(key) => write!(f, "pk({})", key),
+            Policy::After(n) => write!(f, "after({})", n),
+            Policy::Older(n) => write!(f, "older({})", n),
+            Policy::Sha256(hash) => write!(f, "sha256({})", hex::encode(hash)),
+            Policy::Hash256(hash) => write!(f, "hash256({})", hex::encode(hash)),
+            Policy::Ripemd160(hash) => write!(f, "ripemd160({})", hex::encode(hash)),
+            Policy::Hash160(hash) => write!(f, "hash160({})", hex::encode(hash)),
+            Policy::And(x, y) => write!(f, "and({},{})", x, y),
+            Policy::Or(branches) => {
+                let branches: Vec<String> = branches
+                    .iter()
+                    .map(|(weight, branch)| match weight {
+                        1 => branch.to_string(),
+                        _ => format!("{}@{}", weight, branch),
+                    })
+                    .collect();
+                write!(f, "or({})", branches.join(","))
+            }
+            Policy::Thresh(k, subs) => {
+                let subs: Vec<String> = subs.iter().map(|sub| sub.to_string()).collect();
+                write!(f, "thresh({},{})", k, subs.join(","))
+            }
+        }
+    }
+}
+
+fn syntax(error: DescriptorError) -> PolicyError {
+    match error {
+        DescriptorError::Syntax(reason) => PolicyError::Syntax(reason),
+        error => PolicyError::Syntax(error.to_string()),
+    }
+}
+
+/// `N@X`, a weight defaulting to 1.
+fn parse_branch(arg: &str, context: ScriptContext) -> Result<(u32, Policy), PolicyError> {
+    let name_end = arg.find('(').unwrap_or(arg.len());
+    match arg[..name_end].split_once('@') {
+        Some((weight, _)) => {
+            let parsed = weight
+                .parse::<u32>()
+                .ok()
+                .filter(|weight| *weight > 0)
+                .ok_or_else(|| PolicyError::InvalidWeight(weight.to_string()))?;
+            Ok((parsed, Policy::parse(&arg[weight.len() + 1..], context)?))
+        }
+        None => Ok((1, Policy::parse(arg, context)?)),
+    }
+}
+
+fn sum(sizes: &[Option<f64>]) -> Option<f64> {
+    sizes.iter().copied().sum()
+}
+
+fn weighted(px: f64, x: Option<f64>, pz: f64, z: Option<f64>) -> Option<f64> {
+    match (x, z) {
+        (Some(x), Some(z)) => Some(px * x + pz * z),
+        (Some(x), None) => Some(x),
+        (None, Some(z)) => Some(z),
+        (None, None) => None,
+    }
+}
+
+/// Paths satisfying every group, one path of each.
+fn combine(groups: &[Vec<SpendingPath>]) -> Vec<SpendingPath> {
+    let empty = SpendingPath {
+        probability: 1.0,
+        conditions: vec![],
+    };
+    groups.iter().fold(vec![empty], |paths, group| {
+        paths
+            .iter()
+            .flat_map(|path| {
+                group.iter().map(move |other| SpendingPath {
+                    probability: path.probability * other.probability,
+                    conditions: [path.conditions.clone(), other.conditions.clone()].concat(),
+                })
+            })
+            .collect()
+    })
+}
+
+/// The k elements subsets of 0..n.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    match k {
+        0 => vec![vec![]],
+        _ => (k - 1..n)
+            .flat_map(|last| {
+                combinations(last, k - 1)
+                    .into_iter()
+                    .map(move |mut subset| {
+                        subset.push(last);
+                        subset
+                    })
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test_policy {
+    use crate::errors::PolicyError;
+    use crate::hashes::sha256;
+    use crate::keys::PrivateKey;
+    use crate::miniscript::policy::{spend_path, Policy};
+    use crate::miniscript::{replace_key_names, ScriptContext};
+    use crate::network::Network;
+    use rstest::rstest;
+
+    const PREIMAGE: [u8; 32] = [0; 32];
+
+    /// Private keys 1, 2 and 3, named A, B and C.
+    fn private_key(scalar: u8) -> PrivateKey {
+        let mut bytes = [0u8; 32];
+        bytes[31] = scalar;
+        PrivateKey::from_slice(&bytes, Network::Mainnet).unwrap()
+    }
+
+    fn names() -> Vec<(String, String)> {
+        let mut names: Vec<(String, String)> = ["A", "B", "C"]
+            .iter()
+            .zip(1..)
+            .map(|(name, scalar)| {
+                let key = private_key(scalar).public_key().compressed_bytes();
+                (name.to_string(), hex::encode(key))
+            })
+            .collect();
+        names.push(("H".to_string(), hex::encode(sha256(&PREIMAGE))));
+        names
+    }
+
+    fn parse(template: &str, context: ScriptContext) -> Result<Policy, PolicyError> {
+        Policy::parse(&replace_key_names(template, &names()), context)
+    }
+
+    /// The compiled miniscript, keys and hashes named back.
+    fn compile(template: &str, context: ScriptContext) -> Result<String, PolicyError> {
+        let compilation = parse(template, context)?.compile(context)?;
+        let reverse: Vec<(String, String)> = names()
+            .into_iter()
+            .map(|(name, value)| (value, name))
+            .collect();
+        Ok(replace_key_names(
+            &compilation.miniscript.to_string(),
+            &reverse,
+        ))
+    }
+
+    #[rstest]
+    #[case("pk(A)", "pk(A)")]
+    #[case("and(pk(A),pk(B))", "and_v(v:pk(A),pk(B))")]
+    #[case("or(pk(A),pk(B))", "or_b(pk(A),s:pk(B))")]
+    #[case("thresh(2,pk(A),pk(B),pk(C))", "multi(2,A,B,C)")]
+    #[case(
+        "or(99@pk(A),and(pk(B),after(1000)))",
+        "or_d(pk(A),and_v(v:pkh(B),after(1000)))"
+    )]
+    #[case(
+        "or(1@pk(A),99@and(pk(B),after(1000)))",
+        "andor(pk(B),after(1000),pkh(A))"
+    )]
+    #[case(
+        "or(and(pk(A),older(4032)),and(pk(B),sha256(H)))",
+        "andor(pk(A),older(4032),and_v(v:pk(B),sha256(H)))"
+    )]
+    #[case(
+        "thresh(3,pk(A),pk(B),pk(C),older(100))",
+        "thresh(3,pk(A),s:pk(B),s:pk(C),snl:older(100))"
+    )]
+    fn test_compile_segwit(#[case] policy: &str, #[case] expected: &str) {
+        assert_eq!(
+            compile(policy, ScriptContext::Segwitv0),
+            Ok(expected.to_string())
+        );
+    }
+
+    #[rstest]
+    #[case("thresh(2,pk(A),pk(B),pk(C))", "multi_a(2,A,B,C)")]
+    #[case(
+        "or(10@pk(A),or(pk(B),and(pk(C),older(100))))",
+        "c:or_i(or_i(pk_h(B),and_v(v:older(100),pk_h(C))),pk_k(A))"
+    )]
+    fn test_compile_tap(#[case] policy: &str, #[case] expected: &str) {
+        assert_eq!(
+            compile(policy, ScriptContext::Tap),
+            Ok(expected.to_string())
+        );
+    }
+
+    #[test]
+    fn test_expected_cost() {
+        let policy = parse(
+            "or(99@pk(A),and(pk(B),after(1000)))",
+            ScriptContext::Segwitv0,
+        );
+        let compilation = policy.unwrap().compile(ScriptContext::Segwitv0).unwrap();
+        assert_eq!(compilation.script_size, 67);
+        // 99% a 73 bytes signature, 1% an empty signature, a signature and a key.
+        assert!((compilation.expected_witness_size - 73.35).abs() < 1e-9);
+    }
+
+    #[rstest]
+    #[case("pk(A)", vec![1.0])]
+    #[case("or(99@pk(A),and(pk(B),after(1000)))", vec![0.99, 0.01])]
+    #[case("or(pk(A),or(3@pk(B),pk(C)))", vec![0.5, 0.375, 0.125])]
+    #[case("and(or(pk(A),pk(B)),or(3@pk(C),sha256(H)))", vec![0.375, 0.125, 0.375, 0.125])]
+    #[case("thresh(2,pk(A),pk(B),pk(C))", vec![1.0 / 3.0; 3])]
+    fn test_spending_paths(#[case] policy: &str, #[case] expected: Vec<f64>) {
+        let paths = parse(policy, ScriptContext::Segwitv0)
+            .unwrap()
+            .spending_paths();
+        let probabilities: Vec<f64> = paths.iter().map(|path| path.probability).collect();
+        assert_eq!(probabilities.len(), expected.len());
+        for (probability, expected) in probabilities.iter().zip(expected) {
+            assert!((probability - expected).abs() < 1e-9, "{:?}", probabilities);
+        }
+    }
+
+    #[rstest]
+    #[case("or(99@pk(A),and(pk(B),after(1000)))")]
+    #[case("or(and(pk(A),older(4032)),and(pk(B),sha256(H)))")]
+    #[case("thresh(2,pk(A),pk(B),pk(C))")]
+    #[case("thresh(3,pk(A),pk(B),pk(C),older(100))")]
+    #[case("or(10@pk(A),or(pk(B),and(pk(C),older(100))))")]
+    #[case("and(or(pk(A),pk(B)),or(3@pk(C),sha256(H)))")]
+    #[case("or(pk(A),thresh(2,pk(B),pk(C),after(500)))")]
+    fn test_spend_every_path(#[case] policy: &str) {
+        let keys: Vec<PrivateKey> = (1..=3).map(private_key).collect();
+        let preimages = vec![PREIMAGE.to_vec()];
+        for context in [ScriptContext::Segwitv0, ScriptContext::Tap] {
+            let policy = parse(policy, context).unwrap();
+            let miniscript = policy.compile(context).unwrap().miniscript;
+            for path in policy.spending_paths() {
+                let res = spend_path(&miniscript, 0, &path, &keys, &preimages);
+                assert!(res.is_ok(), "{} {:?} in {}", policy, res, context);
+            }
+        }
+    }
+
+    #[test]
+    fn test_spend_path_missing_secret() {
+        let policy = parse("or(pk(A),and(pk(B),sha256(H)))", ScriptContext::Segwitv0).unwrap();
+        let miniscript = policy.compile(ScriptContext::Segwitv0).unwrap().miniscript;
+        let paths = policy.spending_paths();
+        let res = spend_path(&miniscript, 0, &paths[0], &[private_key(2)], &[]);
+        assert_eq!(
+            res.err(),
+            Some(PolicyError::MissingSecret(
+                paths[0].conditions[0].to_string()
+            ))
+        );
+        let res = spend_path(&miniscript, 0, &paths[1], &[private_key(2)], &[]);
+        assert_eq!(
+            res.err(),
+            Some(PolicyError::MissingSecret(
+                paths[1].conditions[1].to_string()
+            ))
+        );
+    }
+
+    #[rstest]
+    #[case("multi(1,A,B)", PolicyError::UnknownFragment("multi".to_string()))]
+    #[case("or(0@pk(A),pk(B))", PolicyError::InvalidWeight("0".to_string()))]
+    #[case("or(x@pk(A),pk(B))", PolicyError::InvalidWeight("x".to_string()))]
+    #[case(
+        "and(pk(A))",
+        PolicyError::Syntax("and() takes 2 arguments, found 1".to_string())
+    )]
+    fn test_parse_errors(#[case] policy: &str, #[case] expected: PolicyError) {
+        assert_eq!(parse(policy, ScriptContext::Segwitv0).err(), Some(expected));
+    }
+
+    #[rstest]
+    #[case("or(pk(A),after(10))")]
+    #[case("and(pk(A),thresh(1,pk(B),older(10),sha256(H)))")]
+    #[case("or(pk(A),and(pk(A),pk(B)))")]
+    fn test_no_sane_compilation(#[case] policy: &str) {
+        for context in [ScriptContext::Segwitv0, ScriptContext::Tap] {
+            let res = compile(policy, context);
+            assert!(
+                matches!(res, Err(PolicyError::NoSaneCompilation(_))),
+                "{:?}",
+                res
+            );
+        }
+    }
+}
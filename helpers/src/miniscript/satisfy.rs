@@ -0,0 +1,696 @@
+// Miniscript satisfactions.
+//
+// Every fragment builds its satisfaction and dissatisfaction from the ones of its children, the
+// witness stacks (bottom first) being concatenated in the order the script consumes them.
+// When a fragment has several ways to be satisfied, the choice follows the non-malleable rules
+// of rust-miniscript : options without a signature could be used by anyone relaying the
+// transaction, so
+// - when every option but one needs a signature, the one without is taken
+// - when several options need no signature, a third party could swap them and no
+//   non-malleable satisfaction exists
+// - between options that all need a signature, the smallest witness is taken.
+//
+// The `spend` helper runs the result in bee-vm : it pays the miniscript output (P2WSH, or a
+// leaf under the BIP341 unspendable key) and spends it in a test transaction.
+
+use crate::errors::MiniscriptError;
+use crate::hashes::{hash160, sha256, sha256d};
+use crate::keys::PrivateKey;
+use crate::miniscript::types::{LOCKTIME_THRESHOLD, SEQUENCE_LOCKTIME_TYPE_FLAG};
+use crate::miniscript::{Fragment, Miniscript, ScriptContext};
+use crate::schnorr;
+use crate::taproot::{TapTree, TaprootSpendInfo, UNSPENDABLE_KEY};
+use bee_vm::opcodes::crypto_ops::SIGHASH_ALL;
+use bee_vm::transaction::context::ExecutionContext;
+use bee_vm::transaction::sighash::{SigVersion, SIGHASH_DEFAULT, TAPSCRIPT_LEAF_VERSION};
+use bee_vm::transaction::verify::verify_input;
+use bee_vm::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use ripemd::{Digest, Ripemd160};
+use std::collections::BTreeMap;
+
+/// nSequence bit disabling the BIP68 relative lock of an input.
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000_ffff;
+/// nSequence of an input that does not enable nLockTime.
+const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+
+/// What satisfactions can use.
+#[derive(Debug, Clone)]
+pub struct Satisfier {
+    /// Signatures by the key the script pushes (x-only in tapscript), sighash byte included.
+    pub signatures: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Known hash preimages.
+    pub preimages: Vec<Vec<u8>>,
+    /// nSequence of the spending input, checked by older().
+    pub sequence: u32,
+    /// nLockTime of the spending transaction, checked by after().
+    pub lock_time: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Witness {
+    Stack(Vec<Vec<u8>>),
+    /// Needs a signature or a preimage we do not have.
+    Unavailable,
+    /// Can never be satisfied (this way).
+    Impossible,
+}
+
+#[derive(Debug, Clone)]
+struct Satisfaction {
+    witness: Witness,
+    has_sig: bool,
+}
+
+impl Default for Satisfier {
+    fn default() -> Self {
+        Satisfier {
+            signatures: BTreeMap::new(),
+            preimages: vec![],
+            sequence: SEQUENCE_FINAL,
+            lock_time: 0,
+        }
+    }
+}
+
+impl Satisfier {
+    /// Signs with every key for the input of `context`, SIGHASH_ALL ECDSA signatures for a
+    /// segwit v0 `script` and SIGHASH_DEFAULT Schnorr signatures for a tapscript leaf.
+    pub fn sign(
+        &mut self,
+        keys: &[PrivateKey],
+        context: &ExecutionContext,
+        script: &[u8],
+    ) -> Result<(), MiniscriptError> {
+        for key in keys {
+            match context.sig_version {
+                SigVersion::Tapscript => {
+                    let sighash = context.signature_hash(&[], SIGHASH_DEFAULT as u32, u32::MAX)?;
+                    let signature = schnorr::sign(&key.to_bytes(), &sighash, None)
+                        .map_err(|e| MiniscriptError::Signing(e.to_string()))?;
+                    self.signatures
+                        .insert(key.public_key().x_only().to_vec(), signature.to_vec());
+                }
+                _ => {
+                    let sighash = context.signature_hash(script, SIGHASH_ALL as u32, u32::MAX)?;
+                    let signature: k256::ecdsa::Signature = key
+                        .signing_key
+                        .sign_prehash(&sighash)
+                        .map_err(|e| MiniscriptError::Signing(e.to_string()))?;
+                    let mut signature = signature.to_der().as_bytes().to_vec();
+                    signature.push(SIGHASH_ALL);
+                    self.signatures
+                        .insert(key.public_key().to_bytes(), signature);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// BIP68 : the input's nSequence is a relative lock of the same unit, at least `n`.
+    pub fn check_older(&self, n: u32) -> bool {
+        self.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG == 0
+            && self.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG == n & SEQUENCE_LOCKTIME_TYPE_FLAG
+            && self.sequence & SEQUENCE_LOCKTIME_MASK >= n & SEQUENCE_LOCKTIME_MASK
+    }
+
+    /// BIP65 : the nLockTime is of the same kind (height or time), at least `n`, and enabled by
+    /// a non final nSequence.
+    pub fn check_after(&self, n: u32) -> bool {
+        self.sequence != SEQUENCE_FINAL
+            && (self.lock_time < LOCKTIME_THRESHOLD) == (n < LOCKTIME_THRESHOLD)
+            && self.lock_time >= n
+    }
+
+    fn preimage(&self, hash: &[u8], digest: fn(&[u8]) -> Vec<u8>) -> Option<Vec<u8>> {
+        self.preimages
+            .iter()
+            .find(|preimage| preimage.len() == 32 && digest(preimage) == hash)
+            .cloned()
+    }
+}
+
+impl Miniscript {
+    /// Witness stack (bottom first, without the script) of the non-malleable satisfaction with
+    /// the smallest witness.
+    pub fn satisfy(
+        &self,
+        satisfier: &Satisfier,
+        index: u32,
+    ) -> Result<Vec<Vec<u8>>, MiniscriptError> {
+        match self.satisfactions(satisfier, index)?.0.witness {
+            Witness::Stack(stack) => Ok(stack),
+            _ => Err(MiniscriptError::CanNotSatisfy),
+        }
+    }
+
+    /// Satisfaction and dissatisfaction.
+    fn satisfactions(
+        &self,
+        satisfier: &Satisfier,
+        index: u32,
+    ) -> Result<(Satisfaction, Satisfaction), MiniscriptError> {
+        let children = self
+            .children()
+            .iter()
+            .map(|child| child.satisfactions(satisfier, index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sat = |position: usize| children[position].0.clone();
+        let dissat = |position: usize| children[position].1.clone();
+
+        Ok(match &self.fragment {
+            Fragment::False => (impossible(), push(vec![])),
+            Fragment::True => (push(vec![]), impossible()),
+            Fragment::PkK(key) => {
+                let key = self.key_bytes(key, index)?;
+                (signature(satisfier, &key, vec![]), push(vec![vec![]]))
+            }
+            Fragment::PkH(key) => {
+                let key = self.key_bytes(key, index)?;
+                (
+                    signature(satisfier, &key, vec![key.clone()]),
+                    push(vec![vec![], key]),
+                )
+            }
+            Fragment::Older(n) => (timelock(satisfier.check_older(*n)), impossible()),
+            Fragment::After(n) => (timelock(satisfier.check_after(*n)), impossible()),
+            Fragment::Sha256(hash) => {
+                hash_satisfactions(satisfier, hash, |data| sha256(data).to_vec())
+            }
+            Fragment::Hash256(hash) => {
+                hash_satisfactions(satisfier, hash, |data| sha256d(data).to_vec())
+            }
+            Fragment::Ripemd160(hash) => {
+                hash_satisfactions(satisfier, hash, |data| Ripemd160::digest(data).to_vec())
+            }
+            Fragment::Hash160(hash) => {
+                hash_satisfactions(satisfier, hash, |data| hash160(data).to_vec())
+            }
+            Fragment::Alt(_)
+            | Fragment::Swap(_)
+            | Fragment::Check(_)
+            | Fragment::ZeroNotEqual(_) => children[0].clone(),
+            Fragment::DupIf(_) => (concat(&[&sat(0), &push(vec![vec![1]])]), push(vec![vec![]])),
+            Fragment::Verify(_) => (sat(0), impossible()),
+            Fragment::NonZero(_) => (sat(0), push(vec![vec![]])),
+            // X runs first, so its inputs are on top of Y's.
+            Fragment::AndV(..) => (concat(&[&sat(1), &sat(0)]), concat(&[&dissat(1), &sat(0)])),
+            Fragment::AndB(..) => (
+                concat(&[&sat(1), &sat(0)]),
+                concat(&[&dissat(1), &dissat(0)]),
+            ),
+            Fragment::AndOr(..) => (
+                minimum(concat(&[&sat(1), &sat(0)]), concat(&[&sat(2), &dissat(0)])),
+                concat(&[&dissat(2), &dissat(0)]),
+            ),
+            Fragment::OrB(..) => (
+                minimum(
+                    concat(&[&dissat(1), &sat(0)]),
+                    concat(&[&sat(1), &dissat(0)]),
+                ),
+                concat(&[&dissat(1), &dissat(0)]),
+            ),
+            Fragment::OrC(..) => (
+                minimum(sat(0), concat(&[&sat(1), &dissat(0)])),
+                impossible(),
+            ),
+            Fragment::OrD(..) => (
+                minimum(sat(0), concat(&[&sat(1), &dissat(0)])),
+                concat(&[&dissat(1), &dissat(0)]),
+            ),
+            // The IF input selects the branch : 1 for X, empty for Z.
+            Fragment::OrI(..) => (
+                minimum(
+                    concat(&[&sat(0), &push(vec![vec![1]])]),
+                    concat(&[&sat(1), &push(vec![vec![]])]),
+                ),
+                minimum(
+                    concat(&[&dissat(0), &push(vec![vec![1]])]),
+                    concat(&[&dissat(1), &push(vec![vec![]])]),
+                ),
+            ),
+            Fragment::Thresh(k, _) => threshold(*k, &children),
+            Fragment::Multi(k, keys) => {
+                let mut signatures = vec![vec![]];
+                for key in keys {
+                    let key = self.key_bytes(key, index)?;
+                    if signatures.len() <= *k {
+                        signatures.extend(satisfier.signatures.get(&key).cloned());
+                    }
+                }
+                let sat = match signatures.len() == k + 1 {
+                    true => Witness::Stack(signatures),
+                    false => Witness::Unavailable,
+                };
+                (
+                    Satisfaction {
+                        witness: sat,
+                        has_sig: true,
+                    },
+                    push(vec![vec![]; k + 1]),
+                )
+            }
+            Fragment::MultiA(k, keys) => {
+                // The first key is checked first, so its signature is on top of the stack.
+                let mut signatures = vec![];
+                let mut count = 0;
+                for key in keys {
+                    let key = self.key_bytes(key, index)?;
+                    match satisfier.signatures.get(&key) {
+                        Some(signature) if count < *k => {
+                            signatures.push(signature.clone());
+                            count += 1;
+                        }
+                        _ => signatures.push(vec![]),
+                    }
+                }
+                signatures.reverse();
+                let sat = match count == *k {
+                    true => Witness::Stack(signatures),
+                    false => Witness::Unavailable,
+                };
+                (
+                    Satisfaction {
+                        witness: sat,
+                        has_sig: true,
+                    },
+                    push(vec![vec![]; keys.len()]),
+                )
+            }
+        })
+    }
+}
+
+/// Pays `miniscript` at `index` and spends it in a one input transaction with the given
+/// nSequence and nLockTime, signing with `keys` and revealing `preimages`. The witness is
+/// checked by bee-vm before the transaction is returned.
+pub fn spend(
+    miniscript: &Miniscript,
+    index: u32,
+    keys: &[PrivateKey],
+    preimages: &[Vec<u8>],
+    sequence: u32,
+    lock_time: u32,
+) -> Result<Transaction, MiniscriptError> {
+    let script = miniscript.encode(index)?;
+    let (script_pubkey, info) = match miniscript.context {
+        ScriptContext::Segwitv0 => {
+            let mut script_pubkey = vec![0x00, 0x20];
+            script_pubkey.extend(sha256(&script));
+            (script_pubkey, None)
+        }
+        ScriptContext::Tap => {
+            let tree = TapTree::Leaf {
+                script: script.clone(),
+                leaf_version: TAPSCRIPT_LEAF_VERSION,
+            };
+            let info = TaprootSpendInfo::new(&UNSPENDABLE_KEY, Some(&tree))?;
+            (info.script_pubkey(), Some(info))
+        }
+    };
+    let prevouts = vec![TxOut {
+        value: 100_000,
+        script_pubkey: script_pubkey.clone(),
+    }];
+    let mut tx = Transaction {
+        version: 2,
+        inputs: vec![TxIn::new(
+            OutPoint::from_display_txid(&hex::encode([0x11; 32]), 0)?,
+            sequence,
+        )],
+        outputs: vec![TxOut {
+            value: 99_000,
+            script_pubkey,
+        }],
+        lock_time,
+    };
+
+    let context = match &info {
+        Some(info) => ExecutionContext::taproot(
+            tx.clone(),
+            0,
+            prevouts.clone(),
+            Some(info.leaves[0].leaf_hash),
+        )?,
+        None => ExecutionContext::new(tx.clone(), 0, prevouts[0].clone(), SigVersion::WitnessV0)?,
+    };
+    let mut satisfier = Satisfier {
+        preimages: preimages.to_vec(),
+        sequence,
+        lock_time,
+        ..Satisfier::default()
+    };
+    satisfier.sign(keys, &context, &script)?;
+    let stack = miniscript.satisfy(&satisfier, index)?;
+
+    tx.inputs[0].witness = match &info {
+        Some(info) => info.script_path_witness(0, stack)?,
+        None => [stack, vec![script]].concat(),
+    };
+    verify_input(&tx, 0, &prevouts).map_err(|e| MiniscriptError::VerifyFailed(e.to_string()))?;
+    Ok(tx)
+}
+
+fn push(stack: Vec<Vec<u8>>) -> Satisfaction {
+    Satisfaction {
+        witness: Witness::Stack(stack),
+        has_sig: false,
+    }
+}
+
+fn impossible() -> Satisfaction {
+    Satisfaction {
+        witness: Witness::Impossible,
+        has_sig: false,
+    }
+}
+
+fn timelock(satisfied: bool) -> Satisfaction {
+    match satisfied {
+        true => push(vec![]),
+        false => impossible(),
+    }
+}
+
+/// A signature of `key`, followed by `then` (the key of pk_h).
+fn signature(satisfier: &Satisfier, key: &[u8], then: Vec<Vec<u8>>) -> Satisfaction {
+    Satisfaction {
+        witness: match satisfier.signatures.get(key) {
+            Some(signature) => Witness::Stack([vec![signature.clone()], then].concat()),
+            None => Witness::Unavailable,
+        },
+        has_sig: true,
+    }
+}
+
+/// The preimage, or any other 32 bytes to dissatisfy.
+fn hash_satisfactions(
+    satisfier: &Satisfier,
+    hash: &[u8],
+    digest: fn(&[u8]) -> Vec<u8>,
+) -> (Satisfaction, Satisfaction) {
+    let sat = match satisfier.preimage(hash, digest) {
+        Some(preimage) => push(vec![preimage]),
+        None => Satisfaction {
+            witness: Witness::Unavailable,
+            has_sig: false,
+        },
+    };
+    (sat, push(vec![vec![0; 32]]))
+}
+
+fn witness_size(stack: &[Vec<u8>]) -> usize {
+    stack.iter().map(|item| item.len() + 1).sum()
+}
+
+/// Satisfactions consumed in order, the first one at the bottom of the stack.
+fn concat(parts: &[&Satisfaction]) -> Satisfaction {
+    let has_sig = parts.iter().any(|part| part.has_sig);
+    let mut stack = vec![];
+    for part in parts {
+        match &part.witness {
+            Witness::Stack(items) => stack.extend(items.iter().cloned()),
+            Witness::Impossible => return impossible(),
+            Witness::Unavailable => {}
+        }
+    }
+    let witness = match parts
+        .iter()
+        .any(|part| part.witness == Witness::Unavailable)
+    {
+        true => Witness::Unavailable,
+        false => Witness::Stack(stack),
+    };
+    Satisfaction { witness, has_sig }
+}
+
+/// Non-malleable choice between two satisfactions.
+fn minimum(first: Satisfaction, second: Satisfaction) -> Satisfaction {
+    let (Witness::Stack(first_stack), Witness::Stack(second_stack)) =
+        (&first.witness, &second.witness)
+    else {
+        return match first.witness {
+            Witness::Stack(_) => first,
+            _ => second,
+        };
+    };
+    match (first.has_sig, second.has_sig) {
+        (false, false) => Satisfaction {
+            witness: Witness::Unavailable,
+            has_sig: false,
+        },
+        (true, false) => second,
+        (false, true) => first,
+        (true, true) => match witness_size(first_stack) <= witness_size(second_stack) {
+            true => first,
+            false => second,
+        },
+    }
+}
+
+/// thresh(k, ...) : satisfies k sub-expressions and dissatisfies the others. Satisfactions
+/// with a signature come first, then the ones costing the least over their dissatisfaction.
+/// A third party could swap a chosen satisfaction without a signature for another one, the
+/// result is then malleable.
+fn threshold(k: usize, children: &[(Satisfaction, Satisfaction)]) -> (Satisfaction, Satisfaction) {
+    let size = |satisfaction: &Satisfaction| match &satisfaction.witness {
+        Witness::Stack(stack) => witness_size(stack) as i64,
+        _ => i64::MAX / 2,
+    };
+    let mut order: Vec<usize> = (0..children.len())
+        .filter(|&position| matches!(children[position].0.witness, Witness::Stack(_)))
+        .collect();
+    order.sort_by_key(|&position| {
+        let (sat, dissat) = &children[position];
+        (!sat.has_sig, size(sat) - size(dissat))
+    });
+    let chosen = &order[..k.min(order.len())];
+
+    // Sub-expressions run in order, so the last one is at the bottom of the stack.
+    let stack_of = |pick: &dyn Fn(usize) -> Satisfaction| {
+        let parts: Vec<Satisfaction> = (0..children.len()).rev().map(pick).collect();
+        concat(&parts.iter().collect::<Vec<_>>())
+    };
+    let dissat = stack_of(&|position| children[position].1.clone());
+    let malleable = chosen.iter().any(|&position| !children[position].0.has_sig)
+        && order[chosen.len()..]
+            .iter()
+            .any(|&position| !children[position].0.has_sig);
+    let sat = match (chosen.len() == k, malleable) {
+        (true, false) => stack_of(&|position| match chosen.contains(&position) {
+            true => children[position].0.clone(),
+            false => children[position].1.clone(),
+        }),
+        (true, true) => Satisfaction {
+            witness: Witness::Unavailable,
+            has_sig: false,
+        },
+        (false, _) => match children
+            .iter()
+            .filter(|(sat, _)| sat.witness != Witness::Impossible)
+            .count()
+            >= k
+        {
+            true => Satisfaction {
+                witness: Witness::Unavailable,
+                has_sig: true,
+            },
+            false => impossible(),
+        },
+    };
+    (sat, dissat)
+}
+
+#[cfg(test)]
+mod test_satisfy {
+    use crate::errors::MiniscriptError;
+    use crate::hashes::{hash160, sha256, sha256d};
+    use crate::keys::PrivateKey;
+    use crate::miniscript::satisfy::{spend, Satisfier};
+    use crate::miniscript::{replace_key_names, Miniscript, ScriptContext};
+    use crate::network::Network;
+    use ripemd::{Digest, Ripemd160};
+    use rstest::rstest;
+
+    const PREIMAGE: [u8; 32] = [0x42; 32];
+
+    /// Private keys 1, 2 and 3, named A, B and C.
+    fn private_key(scalar: u8) -> PrivateKey {
+        let mut bytes = [0u8; 32];
+        bytes[31] = scalar;
+        PrivateKey::from_slice(&bytes, Network::Mainnet).unwrap()
+    }
+
+    fn expression(template: &str) -> String {
+        let mut names: Vec<(String, String)> = ["A", "B", "C"]
+            .iter()
+            .zip(1..)
+            .map(|(name, scalar)| {
+                let key = private_key(scalar).public_key().compressed_bytes();
+                (name.to_string(), hex::encode(key))
+            })
+            .collect();
+        names.push(("SHA".to_string(), hex::encode(sha256(&PREIMAGE))));
+        names.push(("HASH256".to_string(), hex::encode(sha256d(&PREIMAGE))));
+        names.push((
+            "RIPEMD".to_string(),
+            hex::encode(Ripemd160::digest(PREIMAGE)),
+        ));
+        names.push(("HASH160".to_string(), hex::encode(hash160(&PREIMAGE))));
+        replace_key_names(template, &names)
+    }
+
+    fn run(
+        template: &str,
+        context: ScriptContext,
+        signers: &[u8],
+        preimage: bool,
+        sequence: u32,
+        lock_time: u32,
+    ) -> Result<(), MiniscriptError> {
+        let miniscript = Miniscript::parse(&expression(template), context)?;
+        let keys: Vec<PrivateKey> = signers.iter().map(|s| private_key(*s)).collect();
+        let preimages = match preimage {
+            true => vec![PREIMAGE.to_vec()],
+            false => vec![],
+        };
+        spend(&miniscript, 0, &keys, &preimages, sequence, lock_time).map(|_| ())
+    }
+
+    #[rstest]
+    #[case("pk(A)", &[1], false, 0xffffffff, 0)]
+    #[case("pkh(A)", &[1], false, 0xffffffff, 0)]
+    #[case("and_v(v:pk(A),older(144))", &[1], false, 144, 0)]
+    #[case("and_v(v:pk(A),after(1000))", &[1], false, 0xfffffffe, 1000)]
+    #[case("and_v(v:pk(A),after(500000001))", &[1], false, 0, 500000002)]
+    #[case("or_d(pk(A),and_v(v:pk(B),older(10)))", &[2], false, 10, 0)]
+    #[case("or_d(pk(A),and_v(v:pkh(B),older(10)))", &[1], false, 0xffffffff, 0)]
+    #[case("or_b(pk(A),s:pk(B))", &[2], false, 0xffffffff, 0)]
+    #[case("t:or_c(pk(A),v:pk(B))", &[2], false, 0xffffffff, 0)]
+    #[case("or_i(pk(A),pk(B))", &[2], false, 0xffffffff, 0)]
+    #[case("or_i(pk(A),pk(B))", &[1, 2], false, 0xffffffff, 0)]
+    #[case("andor(pk(A),pk(B),pk(C))", &[3], false, 0xffffffff, 0)]
+    #[case("andor(pk(A),pk(B),pk(C))", &[1, 2], false, 0xffffffff, 0)]
+    #[case("and_n(pk(A),pk(B))", &[1, 2], false, 0xffffffff, 0)]
+    #[case("and_b(pk(A),a:pk(B))", &[1, 2], false, 0xffffffff, 0)]
+    #[case("thresh(2,pk(A),s:pk(B),s:pk(C))", &[1, 3], false, 0xffffffff, 0)]
+    #[case("thresh(2,pk(A),a:pk(B),a:pk(C))", &[2, 3], false, 0xffffffff, 0)]
+    #[case("thresh(3,pk(A),s:pk(B),s:pk(C),sln:older(12))", &[1, 2], false, 12, 0)]
+    #[case("and_v(v:sha256(SHA),pk(A))", &[1], true, 0xffffffff, 0)]
+    #[case("and_v(v:hash256(HASH256),pk(A))", &[1], true, 0xffffffff, 0)]
+    #[case("and_v(v:ripemd160(RIPEMD),pk(A))", &[1], true, 0xffffffff, 0)]
+    #[case("and_v(v:hash160(HASH160),pk(A))", &[1], true, 0xffffffff, 0)]
+    #[case("andor(pk(A),older(10),and_v(v:pk(B),sha256(SHA)))", &[1], false, 10, 0)]
+    #[case("andor(pk(A),older(10),and_v(v:pk(B),sha256(SHA)))", &[2], true, 0, 0)]
+    #[case("or_i(and_v(v:pkh(A),hash160(HASH160)),and_v(v:pk(B),older(144)))", &[2], false, 144, 0)]
+    #[case("and_v(v:pk(A),dv:older(144))", &[1], false, 144, 0)]
+    #[case("and_v(v:pk(A),or_d(pk(B),older(4194305)))", &[1], false, 0x00400001, 0)]
+    #[case("and_v(v:pk(A),j:pk(B))", &[1, 2], false, 0xffffffff, 0)]
+    fn test_spend_both_contexts(
+        #[case] template: &str,
+        #[case] signers: &[u8],
+        #[case] preimage: bool,
+        #[case] sequence: u32,
+        #[case] lock_time: u32,
+    ) {
+        for context in [ScriptContext::Segwitv0, ScriptContext::Tap] {
+            let res = run(template, context, signers, preimage, sequence, lock_time);
+            assert_eq!(res, Ok(()), "{} in {}", template, context);
+        }
+    }
+
+    #[rstest]
+    #[case("multi(2,A,B,C)", ScriptContext::Segwitv0, &[1, 3])]
+    #[case("multi(2,A,B,C)", ScriptContext::Segwitv0, &[2, 3])]
+    #[case("multi_a(2,A,B,C)", ScriptContext::Tap, &[1, 3])]
+    #[case("multi_a(2,A,B,C)", ScriptContext::Tap, &[1, 2, 3])]
+    #[case("or_d(multi(1,A,B),and_v(v:pk(C),older(144)))", ScriptContext::Segwitv0, &[2])]
+    fn test_spend_multisig(
+        #[case] template: &str,
+        #[case] context: ScriptContext,
+        #[case] signers: &[u8],
+    ) {
+        assert_eq!(
+            run(template, context, signers, false, 0xffffffff, 0),
+            Ok(())
+        );
+    }
+
+    #[rstest]
+    #[case("pk(A)", &[2], false, 0xffffffff, 0)]
+    #[case("and_v(v:pk(A),older(144))", &[1], false, 143, 0)]
+    #[case("and_v(v:pk(A),older(144))", &[1], false, 0x00400090, 0)]
+    #[case("and_v(v:pk(A),after(1000))", &[1], false, 0xffffffff, 1000)]
+    #[case("and_v(v:pk(A),after(1000))", &[1], false, 0, 999)]
+    #[case("and_v(v:sha256(SHA),pk(A))", &[1], false, 0xffffffff, 0)]
+    #[case("thresh(2,pk(A),s:pk(B),s:pk(C))", &[3], false, 0xffffffff, 0)]
+    fn test_can_not_satisfy(
+        #[case] template: &str,
+        #[case] signers: &[u8],
+        #[case] preimage: bool,
+        #[case] sequence: u32,
+        #[case] lock_time: u32,
+    ) {
+        for context in [ScriptContext::Segwitv0, ScriptContext::Tap] {
+            let res = run(template, context, signers, preimage, sequence, lock_time);
+            assert_eq!(res, Err(MiniscriptError::CanNotSatisfy), "{}", template);
+        }
+    }
+
+    #[rstest]
+    fn test_malleable_choice_can_not_satisfy() {
+        // Both branches of or_i can be taken without a signature, so whoever relays the
+        // transaction could swap them.
+        let miniscript = Miniscript::parse_insane(
+            &expression("and_v(v:pk(A),or_i(sha256(SHA),older(10)))"),
+            ScriptContext::Segwitv0,
+        )
+        .unwrap();
+        assert!(!miniscript.insane_reasons().is_empty());
+        let res = spend(
+            &miniscript,
+            0,
+            &[private_key(1)],
+            &[PREIMAGE.to_vec()],
+            10,
+            0,
+        );
+        assert_eq!(res.map(|_| ()), Err(MiniscriptError::CanNotSatisfy));
+        let res = spend(&miniscript, 0, &[private_key(1)], &[], 10, 0);
+        assert_eq!(res.map(|_| ()), Ok(()));
+    }
+
+    #[rstest]
+    #[case(0xfffffffe, 1000, 1000, true)]
+    #[case(0xfffffffe, 999, 1000, false)]
+    #[case(0xffffffff, 1000, 1000, false)]
+    #[case(0xfffffffe, 500_000_001, 1000, false)]
+    fn test_check_after(
+        #[case] sequence: u32,
+        #[case] lock_time: u32,
+        #[case] after: u32,
+        #[case] expected: bool,
+    ) {
+        let satisfier = Satisfier {
+            sequence,
+            lock_time,
+            ..Satisfier::default()
+        };
+        assert_eq!(satisfier.check_after(after), expected);
+    }
+
+    #[rstest]
+    #[case(144, 144, true)]
+    #[case(143, 144, false)]
+    #[case(0x80000000 | 144, 144, false)]
+    #[case(0x00400001, 0x00400001, true)]
+    #[case(0x00400090, 144, false)]
+    fn test_check_older(#[case] sequence: u32, #[case] older: u32, #[case] expected: bool) {
+        let satisfier = Satisfier {
+            sequence,
+            ..Satisfier::default()
+        };
+        assert_eq!(satisfier.check_older(older), expected);
+    }
+}
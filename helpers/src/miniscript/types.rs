@@ -0,0 +1,566 @@
+// Miniscript type system (https://bitcoin.sipa.be/miniscript/).
+//
+// Every fragment has a basic type, computed bottom up from the types of its children :
+// - B (base) : takes its inputs from the top of the stack, pushes a nonzero value when satisfied
+//   and an exact 0 when dissatisfied
+// - V (verify) : pushes nothing, it continues when satisfied and aborts the script otherwise
+// - K (key) : pushes a public key, turned into a B by a CHECKSIG
+// - W (wrapped) : a B taking its inputs under the top element, for and_b / or_b / thresh
+//
+// Correctness properties : z (consumes no input), o (consumes one input), n (the top input is
+// never zero), d (can be dissatisfied), u (pushes exactly 1 when satisfied).
+// Malleability properties : e (a single dissatisfaction, which third parties can not change),
+// f (forced : there is no dissatisfaction), s (safe : every satisfaction needs a signature),
+// m (non-malleable satisfactions can be built).
+// Timelocks : k (no satisfaction needs both a height and a time lock of the same kind).
+
+use crate::errors::MiniscriptError;
+use crate::miniscript::{Fragment, Miniscript, ScriptContext};
+use std::fmt;
+
+/// nSequence bit making older() count 512 seconds units instead of blocks (BIP68).
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+/// nLockTime values from this one are UNIX times instead of heights.
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    B,
+    V,
+    K,
+    W,
+}
+
+/// The kinds of timelocks some satisfaction of a fragment needs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timelocks {
+    pub relative_height: bool,
+    pub relative_time: bool,
+    pub absolute_height: bool,
+    pub absolute_time: bool,
+    /// Some satisfaction needs a height and a time lock of the same kind, so it is never valid.
+    pub mixed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Type {
+    pub base: Base,
+    pub z: bool,
+    pub o: bool,
+    pub n: bool,
+    pub d: bool,
+    pub u: bool,
+    pub e: bool,
+    pub f: bool,
+    pub s: bool,
+    pub m: bool,
+    pub timelocks: Timelocks,
+}
+
+impl Timelocks {
+    /// Timelocks of two fragments that are both satisfied.
+    fn and(self, other: Timelocks) -> Timelocks {
+        let mixed = (self.relative_height && other.relative_time)
+            || (self.relative_time && other.relative_height)
+            || (self.absolute_height && other.absolute_time)
+            || (self.absolute_time && other.absolute_height);
+        Timelocks {
+            mixed: self.mixed || other.mixed || mixed,
+            ..self.or(other)
+        }
+    }
+
+    /// Timelocks of two fragments of which one is satisfied.
+    fn or(self, other: Timelocks) -> Timelocks {
+        Timelocks {
+            relative_height: self.relative_height || other.relative_height,
+            relative_time: self.relative_time || other.relative_time,
+            absolute_height: self.absolute_height || other.absolute_height,
+            absolute_time: self.absolute_time || other.absolute_time,
+            mixed: self.mixed || other.mixed,
+        }
+    }
+}
+
+impl Type {
+    fn new(base: Base) -> Type {
+        Type {
+            base,
+            z: false,
+            o: false,
+            n: false,
+            d: false,
+            u: false,
+            e: false,
+            f: false,
+            s: false,
+            m: false,
+            timelocks: Timelocks::default(),
+        }
+    }
+
+    /// Same properties with another base type, for the wrappers.
+    fn with_base(self, base: Base) -> Type {
+        Type { base, ..self }
+    }
+}
+
+impl fmt::Display for Base {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Base type followed by its properties, like `Bondusemk`.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base)?;
+        let properties = [
+            (self.z, 'z'),
+            (self.o, 'o'),
+            (self.n, 'n'),
+            (self.d, 'd'),
+            (self.u, 'u'),
+            (self.e, 'e'),
+            (self.f, 'f'),
+            (self.s, 's'),
+            (self.m, 'm'),
+            (!self.timelocks.mixed, 'k'),
+        ];
+        for (_, letter) in properties.iter().filter(|(has, _)| *has) {
+            write!(f, "{}", letter)?;
+        }
+        Ok(())
+    }
+}
+
+/// Type of a fragment from the types of its children, or why they can not be combined.
+pub fn type_check(fragment: &Fragment, context: ScriptContext) -> Result<Type, MiniscriptError> {
+    let error = |reason: &str| MiniscriptError::TypeCheck {
+        fragment: fragment.name().to_string(),
+        reason: reason.to_string(),
+    };
+    let require = |child: &Miniscript, bases: &[Base], properties: &str, name: &str| {
+        let ty = child.ty;
+        let has = |property| match property {
+            'z' => ty.z,
+            'o' => ty.o,
+            'n' => ty.n,
+            'd' => ty.d,
+            'u' => ty.u,
+            _ => unreachable!("only correctness properties are required"),
+        };
+        if !bases.contains(&ty.base) || !properties.chars().all(has) {
+            let bases: Vec<String> = bases.iter().map(|base| base.to_string()).collect();
+            return Err(error(&format!(
+                "{} must be {}{} but is {}",
+                name,
+                bases.join("/"),
+                properties,
+                ty
+            )));
+        }
+        Ok(ty)
+    };
+
+    let ty = match fragment {
+        Fragment::False => Type {
+            z: true,
+            u: true,
+            d: true,
+            e: true,
+            s: true,
+            m: true,
+            ..Type::new(Base::B)
+        },
+        Fragment::True => Type {
+            z: true,
+            u: true,
+            f: true,
+            m: true,
+            ..Type::new(Base::B)
+        },
+        Fragment::PkK(_) => Type {
+            o: true,
+            n: true,
+            d: true,
+            u: true,
+            e: true,
+            s: true,
+            m: true,
+            ..Type::new(Base::K)
+        },
+        Fragment::PkH(_) => Type {
+            n: true,
+            d: true,
+            u: true,
+            e: true,
+            s: true,
+            m: true,
+            ..Type::new(Base::K)
+        },
+        Fragment::Older(n) | Fragment::After(n) => {
+            let mut timelocks = Timelocks::default();
+            match fragment {
+                Fragment::Older(_) if n & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 => {
+                    timelocks.relative_time = true
+                }
+                Fragment::Older(_) => timelocks.relative_height = true,
+                _ if *n >= LOCKTIME_THRESHOLD => timelocks.absolute_time = true,
+                _ => timelocks.absolute_height = true,
+            }
+            Type {
+                z: true,
+                f: true,
+                m: true,
+                timelocks,
+                ..Type::new(Base::B)
+            }
+        }
+        Fragment::Sha256(_)
+        | Fragment::Hash256(_)
+        | Fragment::Ripemd160(_)
+        | Fragment::Hash160(_) => Type {
+            o: true,
+            n: true,
+            d: true,
+            u: true,
+            m: true,
+            ..Type::new(Base::B)
+        },
+        Fragment::Alt(x) => {
+            let x = require(x, &[Base::B], "", "X")?;
+            Type {
+                z: false,
+                o: false,
+                n: false,
+                ..x.with_base(Base::W)
+            }
+        }
+        Fragment::Swap(x) => {
+            let x = require(x, &[Base::B], "o", "X")?;
+            Type {
+                z: false,
+                o: false,
+                n: false,
+                ..x.with_base(Base::W)
+            }
+        }
+        Fragment::Check(x) => {
+            let x = require(x, &[Base::K], "", "X")?;
+            Type {
+                u: true,
+                ..x.with_base(Base::B)
+            }
+        }
+        Fragment::DupIf(x) => {
+            let x = require(x, &[Base::V], "z", "X")?;
+            Type {
+                o: true,
+                n: true,
+                d: true,
+                // MINIMALIF is consensus in tapscript, the IF input is exactly 1.
+                u: context == ScriptContext::Tap,
+                e: true,
+                s: x.s,
+                m: x.m,
+                timelocks: x.timelocks,
+                ..Type::new(Base::B)
+            }
+        }
+        Fragment::Verify(x) => {
+            let x = require(x, &[Base::B], "", "X")?;
+            Type {
+                d: false,
+                u: false,
+                e: false,
+                f: true,
+                ..x.with_base(Base::V)
+            }
+        }
+        Fragment::NonZero(x) => {
+            let x = require(x, &[Base::B], "n", "X")?;
+            Type {
+                z: false,
+                n: true,
+                d: true,
+                e: x.f,
+                f: false,
+                ..x
+            }
+        }
+        Fragment::ZeroNotEqual(x) => {
+            let x = require(x, &[Base::B], "", "X")?;
+            Type { u: true, ..x }
+        }
+        Fragment::AndV(x, y) => {
+            let x = require(x, &[Base::V], "", "X")?;
+            let y = require(y, &[Base::B, Base::K, Base::V], "", "Y")?;
+            Type {
+                base: y.base,
+                z: x.z && y.z,
+                o: (x.z && y.o) || (x.o && y.z),
+                n: x.n || (x.z && y.n),
+                d: false,
+                u: y.u,
+                e: false,
+                f: x.s || y.f,
+                s: x.s || y.s,
+                m: x.m && y.m,
+                timelocks: x.timelocks.and(y.timelocks),
+            }
+        }
+        Fragment::AndB(x, y) => {
+            let x = require(x, &[Base::B], "", "X")?;
+            let y = require(y, &[Base::W], "", "Y")?;
+            Type {
+                base: Base::B,
+                z: x.z && y.z,
+                o: (x.z && y.o) || (x.o && y.z),
+                n: x.n || (x.z && y.n),
+                d: x.d && y.d,
+                u: true,
+                e: x.e && y.e && x.s && y.s,
+                f: (x.f && (y.f || x.s)) || (y.s && y.f),
+                s: x.s || y.s,
+                m: x.m && y.m,
+                timelocks: x.timelocks.and(y.timelocks),
+            }
+        }
+        Fragment::AndOr(x, y, z) => {
+            let x = require(x, &[Base::B], "du", "X")?;
+            let y = require(y, &[Base::B, Base::K, Base::V], "", "Y")?;
+            let z = require(z, &[y.base], "", "Z")?;
+            Type {
+                base: y.base,
+                z: x.z && y.z && z.z,
+                o: (x.z && y.o && z.o) || (x.o && y.z && z.z),
+                n: false,
+                d: z.d,
+                u: y.u && z.u,
+                e: z.e && (x.s || y.f),
+                f: z.f && (x.s || y.f),
+                s: z.s && (x.s || y.s),
+                m: x.m && y.m && z.m && x.e && (x.s || y.s || z.s),
+                timelocks: x.timelocks.and(y.timelocks).or(z.timelocks),
+            }
+        }
+        Fragment::OrB(x, z) => {
+            let x = require(x, &[Base::B], "d", "X")?;
+            let z = require(z, &[Base::W], "d", "Z")?;
+            Type {
+                base: Base::B,
+                z: x.z && z.z,
+                o: (x.z && z.o) || (x.o && z.z),
+                n: false,
+                d: true,
+                u: true,
+                e: x.e && z.e,
+                f: false,
+                s: x.s && z.s,
+                m: x.m && z.m && x.e && z.e && (x.s || z.s),
+                timelocks: x.timelocks.or(z.timelocks),
+            }
+        }
+        Fragment::OrC(x, z) => {
+            let x = require(x, &[Base::B], "du", "X")?;
+            let z = require(z, &[Base::V], "", "Z")?;
+            Type {
+                base: Base::V,
+                z: x.z && z.z,
+                o: x.o && z.z,
+                n: false,
+                d: false,
+                u: false,
+                e: false,
+                f: true,
+                s: x.s && z.s,
+                m: x.m && z.m && x.e && (x.s || z.s),
+                timelocks: x.timelocks.or(z.timelocks),
+            }
+        }
+        Fragment::OrD(x, z) => {
+            let x = require(x, &[Base::B], "du", "X")?;
+            let z = require(z, &[Base::B], "", "Z")?;
+            Type {
+                base: Base::B,
+                z: x.z && z.z,
+                o: x.o && z.z,
+                n: false,
+                d: z.d,
+                u: z.u,
+                e: z.e,
+                f: z.f,
+                s: x.s && z.s,
+                m: x.m && z.m && x.e && (x.s || z.s),
+                timelocks: x.timelocks.or(z.timelocks),
+            }
+        }
+        Fragment::OrI(x, z) => {
+            let x = require(x, &[Base::B, Base::K, Base::V], "", "X")?;
+            let z = require(z, &[x.base], "", "Z")?;
+            Type {
+                base: x.base,
+                z: false,
+                o: x.z && z.z,
+                n: false,
+                d: x.d || z.d,
+                u: x.u && z.u,
+                e: (x.e && z.f) || (z.e && x.f),
+                f: x.f && z.f,
+                s: x.s && z.s,
+                m: x.m && z.m && (x.s || z.s),
+                timelocks: x.timelocks.or(z.timelocks),
+            }
+        }
+        Fragment::Thresh(k, subs) => {
+            let mut types = vec![];
+            for (position, sub) in subs.iter().enumerate() {
+                types.push(match position {
+                    0 => require(sub, &[Base::B], "du", "X1")?,
+                    _ => require(sub, &[Base::W], "du", &format!("X{}", position + 1))?,
+                });
+            }
+            let not_safe = types.iter().filter(|ty| !ty.s).count();
+            let all_e = types.iter().all(|ty| ty.e);
+            let timelocks = types
+                .iter()
+                .fold(Timelocks::default(), |timelocks, ty| match k {
+                    1 => timelocks.or(ty.timelocks),
+                    _ => timelocks.and(ty.timelocks),
+                });
+            Type {
+                base: Base::B,
+                z: types.iter().all(|ty| ty.z),
+                o: types.iter().filter(|ty| ty.o).count() == 1
+                    && types.iter().filter(|ty| !ty.z).count() == 1,
+                n: false,
+                d: true,
+                u: true,
+                e: all_e && not_safe == 0,
+                f: false,
+                s: not_safe < *k,
+                m: all_e && types.iter().all(|ty| ty.m) && not_safe <= *k,
+                timelocks,
+            }
+        }
+        Fragment::Multi(..) | Fragment::MultiA(..) => {
+            match (fragment, context) {
+                (Fragment::Multi(..), ScriptContext::Tap) => {
+                    return Err(error("CHECKMULTISIG is disabled in tapscript, use multi_a"))
+                }
+                (Fragment::MultiA(..), ScriptContext::Segwitv0) => {
+                    return Err(error("CHECKSIGADD only exists in tapscript, use multi"))
+                }
+                _ => {}
+            }
+            Type {
+                n: matches!(fragment, Fragment::Multi(..)),
+                d: true,
+                u: true,
+                e: true,
+                s: true,
+                m: true,
+                ..Type::new(Base::B)
+            }
+        }
+    };
+    Ok(ty)
+}
+
+#[cfg(test)]
+mod test_types {
+    use crate::errors::MiniscriptError;
+    use crate::miniscript::{parse_node, ScriptContext};
+    use rstest::rstest;
+
+    const A: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    fn type_of(template: &str, context: ScriptContext) -> Result<String, MiniscriptError> {
+        parse_node(&template.replace('A', A), context).map(|miniscript| miniscript.ty.to_string())
+    }
+
+    #[rstest]
+    #[case("0", "Bzduesmk")]
+    #[case("1", "Bzufmk")]
+    #[case("pk_k(A)", "Konduesmk")]
+    #[case("pk_h(A)", "Knduesmk")]
+    #[case("pk(A)", "Bonduesmk")]
+    #[case("pkh(A)", "Bnduesmk")]
+    #[case("v:pk(A)", "Vonfsmk")]
+    #[case("s:pk(A)", "Wduesmk")]
+    #[case("a:pk(A)", "Wduesmk")]
+    #[case("older(144)", "Bzfmk")]
+    #[case(
+        "sha256(e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855)",
+        "Bondumk"
+    )]
+    #[case("multi(1,A)", "Bnduesmk")]
+    #[case("or_d(pk(A),older(1))", "Bofmk")]
+    #[case("or_i(pk(A),older(1))", "Bdemk")]
+    #[case("l:older(1)", "Bodemk")]
+    #[case("u:pk(A)", "Bdusmk")]
+    #[case("tv:pk(A)", "Bonufsmk")]
+    #[case("and_v(v:pk(A),older(144))", "Bonfsmk")]
+    #[case("thresh(1,pk(A),sln:older(12))", "Bdumk")]
+    #[case("d:v:older(1)", "Bondemk")]
+    #[case("j:pk(A)", "Bondusmk")]
+    #[case("or_b(pk(A),s:pk(A))", "Bduesmk")]
+    #[case("or_c(pk(A),v:older(1))", "Vofmk")]
+    // e only depends on X through s and f, malleable dissatisfactions of X already rule out m.
+    #[case(
+        "or_d(sha256(e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855),pk(A))",
+        "Bduek"
+    )]
+    #[case(
+        "and_n(or_d(0,sha256(e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855)),older(1))",
+        "Bdek"
+    )]
+    fn test_type(#[case] template: &str, #[case] expected: &str) {
+        assert_eq!(
+            type_of(template, ScriptContext::Segwitv0).unwrap(),
+            expected
+        );
+    }
+
+    #[rstest]
+    // d: is only unit in tapscript, where MINIMALIF is consensus.
+    #[case("d:v:older(1)", "Bonduemk")]
+    #[case("multi_a(1,A)", "Bduesmk")]
+    fn test_type_tapscript(#[case] template: &str, #[case] expected: &str) {
+        assert_eq!(type_of(template, ScriptContext::Tap).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("and_v(after(1),pk(A))", "X must be V")]
+    #[case("or_b(pk(A),pk(A))", "Z must be Wd")]
+    #[case("or_d(older(1),pk(A))", "X must be Bdu")]
+    #[case("v:v:pk(A)", "X must be B")]
+    #[case("d:pk(A)", "X must be Vz")]
+    #[case("thresh(1,pk(A),pk(A))", "X2 must be Wdu")]
+    fn test_type_error(#[case] template: &str, #[case] reason: &str) {
+        match type_of(template, ScriptContext::Segwitv0) {
+            Err(MiniscriptError::TypeCheck { reason: found, .. }) => {
+                assert!(found.starts_with(reason), "{} : {}", template, found)
+            }
+            res => panic!("{} : {:?}", template, res),
+        }
+    }
+
+    #[rstest]
+    fn test_timelock_mixing() {
+        let mixed = parse_node(
+            &"and_v(v:pk(A),and_v(v:after(100),after(500000001)))".replace('A', A),
+            ScriptContext::Segwitv0,
+        )
+        .unwrap();
+        assert!(mixed.ty.timelocks.mixed);
+        // Only one branch is taken, the two kinds of lock never need to hold together.
+        let alternatives = parse_node(
+            &"and_v(v:pk(A),or_i(after(100),after(500000001)))".replace('A', A),
+            ScriptContext::Segwitv0,
+        )
+        .unwrap();
+        assert!(!alternatives.ty.timelocks.mixed);
+    }
+}
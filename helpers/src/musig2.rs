@@ -0,0 +1,732 @@
+// MuSig2 (BIP327) multi-signatures.
+//
+// n signers aggregate their keys into one x-only key and produce a single BIP340 signature for
+// it in two rounds : every signer publishes two public nonces, then a partial signature once the
+// nonces are aggregated. The aggregate key can be tweaked (plain BIP32 tweaks, x-only BIP341
+// tweaks) so the final signature spends a taproot output by its key path.
+
+use crate::errors::{KeyError, MusigError};
+use crate::hashes::tagged_hash;
+use crate::schnorr::tap_tweak_hash;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::elliptic_curve::rand_core::{OsRng, RngCore};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::{AffinePoint, ProjectivePoint, PublicKey, Scalar, U256};
+
+pub const PUBLIC_NONCE_SIZE: usize = 66;
+pub const SECRET_NONCE_SIZE: usize = 97;
+
+/// Aggregate key of a set of signers, with the tweaks applied to it.
+#[derive(Debug, Clone)]
+pub struct KeyAggContext {
+    public_keys: Vec<[u8; 33]>,
+    list_hash: [u8; 32],
+    second_key: Option<[u8; 33]>,
+    q: ProjectivePoint,
+    /// Sign (1 or -1) the signers' keys are multiplied by after the x-only tweaks.
+    gacc: Scalar,
+    /// Sum of the tweaks.
+    tacc: Scalar,
+}
+
+impl KeyAggContext {
+    /// `KeyAgg` : aggregates compressed public keys, in the given order (see [`key_sort`]).
+    pub fn new(public_keys: &[[u8; 33]]) -> Result<KeyAggContext, MusigError> {
+        if public_keys.is_empty() {
+            return Err(MusigError::NoPublicKeys);
+        }
+        let list_hash = tagged_hash("KeyAgg list", &public_keys.concat());
+        // The second distinct key gets the coefficient 1, which saves a multiplication.
+        let second_key = public_keys
+            .iter()
+            .find(|key| *key != &public_keys[0])
+            .copied();
+
+        let mut context = KeyAggContext {
+            public_keys: public_keys.to_vec(),
+            list_hash,
+            second_key,
+            q: ProjectivePoint::IDENTITY,
+            gacc: Scalar::ONE,
+            tacc: Scalar::ZERO,
+        };
+        for (index, public_key) in public_keys.iter().enumerate() {
+            let point = point_from_bytes(public_key).ok_or(MusigError::InvalidPublicKey(index))?;
+            context.q += point * context.coefficient(public_key);
+        }
+        if context.q == ProjectivePoint::IDENTITY {
+            return Err(MusigError::InfinityKey);
+        }
+        Ok(context)
+    }
+
+    pub fn public_keys(&self) -> &[[u8; 33]] {
+        &self.public_keys
+    }
+
+    /// Compressed aggregate key (`GetPlainPubkey`), what plain tweaks are derived from.
+    pub fn plain_key(&self) -> [u8; 33] {
+        point_to_bytes(&self.q.to_affine())
+    }
+
+    /// X-only aggregate key (`GetXonlyPubkey`), the key the final signature verifies under.
+    pub fn x_only_key(&self) -> [u8; 32] {
+        self.q.to_affine().x().into()
+    }
+
+    /// `ApplyTweak` : adds `tweak * G` to the aggregate key, after negating it for x-only tweaks
+    /// when its y is odd.
+    pub fn apply_tweak(&mut self, tweak: &[u8; 32], x_only: bool) -> Result<(), MusigError> {
+        let g = match x_only && has_odd_y(&self.q) {
+            true => -Scalar::ONE,
+            false => Scalar::ONE,
+        };
+        let tweak: Option<Scalar> = Scalar::from_repr((*tweak).into()).into();
+        let tweak = tweak.ok_or(MusigError::InvalidTweak)?;
+
+        let q = self.q * g + ProjectivePoint::GENERATOR * tweak;
+        if q == ProjectivePoint::IDENTITY {
+            return Err(MusigError::InvalidTweak);
+        }
+        self.q = q;
+        self.gacc *= g;
+        self.tacc = tweak + g * self.tacc;
+        Ok(())
+    }
+
+    /// BIP341 tweak of the current x-only key, without a merkle root for BIP86 outputs.
+    pub fn apply_taproot_tweak(
+        &mut self,
+        merkle_root: Option<&[u8; 32]>,
+    ) -> Result<(), MusigError> {
+        let tweak = tap_tweak_hash(&self.x_only_key(), merkle_root);
+        self.apply_tweak(&tweak, true)
+    }
+
+    /// `KeyAggCoeff` of a key of the list.
+    fn coefficient(&self, public_key: &[u8; 33]) -> Scalar {
+        if self.second_key.as_ref() == Some(public_key) {
+            return Scalar::ONE;
+        }
+        let mut data = self.list_hash.to_vec();
+        data.extend_from_slice(public_key);
+        reduce(&tagged_hash("KeyAgg coefficient", &data))
+    }
+
+    /// Coefficient of a signer, `None` if the key was not aggregated.
+    fn signer_coefficient(&self, public_key: &[u8; 33]) -> Option<Scalar> {
+        self.public_keys
+            .contains(public_key)
+            .then(|| self.coefficient(public_key))
+    }
+}
+
+/// `KeySort` : sorts the keys so every signer aggregates them in the same order.
+pub fn key_sort(public_keys: &mut [[u8; 33]]) {
+    public_keys.sort();
+}
+
+/// Secret nonce of a signer. [`Session::sign`] takes it by value so it can not be used twice.
+pub struct SecretNonce {
+    k1: Scalar,
+    k2: Scalar,
+    public_key: [u8; 33],
+}
+
+impl SecretNonce {
+    pub fn from_bytes(bytes: &[u8; SECRET_NONCE_SIZE]) -> Result<SecretNonce, MusigError> {
+        let scalar = |bytes: &[u8]| -> Option<Scalar> {
+            let bytes: [u8; 32] = bytes.try_into().unwrap();
+            Option::<Scalar>::from(Scalar::from_repr(bytes.into()))
+                .filter(|scalar| !bool::from(scalar.is_zero()))
+        };
+        let invalid = || MusigError::InvalidSecretNonce("nonces must be in [1, n-1]".to_string());
+        Ok(SecretNonce {
+            k1: scalar(&bytes[..32]).ok_or_else(invalid)?,
+            k2: scalar(&bytes[32..64]).ok_or_else(invalid)?,
+            public_key: bytes[64..].try_into().unwrap(),
+        })
+    }
+
+    /// `k1 || k2 || public key`.
+    pub fn to_bytes(&self) -> [u8; SECRET_NONCE_SIZE] {
+        let mut bytes = [0u8; SECRET_NONCE_SIZE];
+        bytes[..32].copy_from_slice(&self.k1.to_bytes());
+        bytes[32..64].copy_from_slice(&self.k2.to_bytes());
+        bytes[64..].copy_from_slice(&self.public_key);
+        bytes
+    }
+
+    /// `cbytes(k1 * G) || cbytes(k2 * G)`.
+    pub fn public_nonce(&self) -> [u8; PUBLIC_NONCE_SIZE] {
+        let mut bytes = [0u8; PUBLIC_NONCE_SIZE];
+        bytes[..33].copy_from_slice(&point_to_bytes(
+            &(ProjectivePoint::GENERATOR * self.k1).to_affine(),
+        ));
+        bytes[33..].copy_from_slice(&point_to_bytes(
+            &(ProjectivePoint::GENERATOR * self.k2).to_affine(),
+        ));
+        bytes
+    }
+}
+
+/// Optional data mixed into the nonces, each makes an accidental nonce reuse less likely.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NonceInputs<'a> {
+    pub secret_key: Option<&'a [u8; 32]>,
+    pub aggregate_key: Option<&'a [u8; 32]>,
+    pub message: Option<&'a [u8]>,
+    pub extra_input: Option<&'a [u8]>,
+}
+
+/// `NonceGen` with 32 random bytes.
+pub fn nonce_gen(
+    public_key: &[u8; 33],
+    inputs: &NonceInputs,
+) -> Result<(SecretNonce, [u8; PUBLIC_NONCE_SIZE]), MusigError> {
+    let mut rand = [0u8; 32];
+    OsRng.fill_bytes(&mut rand);
+    nonce_gen_with_rand(&rand, public_key, inputs)
+}
+
+/// `NonceGen` with the given randomness, `rand` must never be reused (test vectors only).
+pub fn nonce_gen_with_rand(
+    rand: &[u8; 32],
+    public_key: &[u8; 33],
+    inputs: &NonceInputs,
+) -> Result<(SecretNonce, [u8; PUBLIC_NONCE_SIZE]), MusigError> {
+    let mut rand = *rand;
+    if let Some(secret_key) = inputs.secret_key {
+        let mask = tagged_hash("MuSig/aux", &rand);
+        rand = std::array::from_fn(|i| secret_key[i] ^ mask[i]);
+    }
+
+    let aggregate_key = inputs.aggregate_key.map_or(&[][..], |key| &key[..]);
+    let extra_input = inputs.extra_input.unwrap_or_default();
+    let mut data = rand.to_vec();
+    data.push(public_key.len() as u8);
+    data.extend_from_slice(public_key);
+    data.push(aggregate_key.len() as u8);
+    data.extend_from_slice(aggregate_key);
+    match inputs.message {
+        Some(message) => {
+            data.push(1);
+            data.extend_from_slice(&(message.len() as u64).to_be_bytes());
+            data.extend_from_slice(message);
+        }
+        None => data.push(0),
+    }
+    data.extend_from_slice(&(extra_input.len() as u32).to_be_bytes());
+    data.extend_from_slice(extra_input);
+
+    let nonce = |index: u8| {
+        let mut data = data.clone();
+        data.push(index);
+        reduce(&tagged_hash("MuSig/nonce", &data))
+    };
+    let (k1, k2) = (nonce(0), nonce(1));
+    if bool::from(k1.is_zero()) || bool::from(k2.is_zero()) {
+        return Err(MusigError::InvalidSecretNonce("nonce is zero".to_string()));
+    }
+
+    let secret_nonce = SecretNonce {
+        k1,
+        k2,
+        public_key: *public_key,
+    };
+    let public_nonce = secret_nonce.public_nonce();
+    Ok((secret_nonce, public_nonce))
+}
+
+/// `NonceAgg` : sums the first and the second nonces of every signer.
+pub fn nonce_agg(
+    public_nonces: &[[u8; PUBLIC_NONCE_SIZE]],
+) -> Result<[u8; PUBLIC_NONCE_SIZE], MusigError> {
+    let mut aggregate_nonce = [0u8; PUBLIC_NONCE_SIZE];
+    for half in 0..2 {
+        let mut sum = ProjectivePoint::IDENTITY;
+        for (index, public_nonce) in public_nonces.iter().enumerate() {
+            sum += point_from_bytes(&public_nonce[half * 33..(half + 1) * 33])
+                .ok_or(MusigError::InvalidPublicNonce(index))?;
+        }
+        aggregate_nonce[half * 33..(half + 1) * 33].copy_from_slice(&point_to_bytes_ext(&sum));
+    }
+    Ok(aggregate_nonce)
+}
+
+/// A signing session : the aggregate key, the aggregate nonce and the message.
+#[derive(Debug, Clone)]
+pub struct Session {
+    key_agg: KeyAggContext,
+    /// Nonce coefficient.
+    b: Scalar,
+    /// Final nonce of the signature.
+    r: AffinePoint,
+    /// BIP340 challenge.
+    e: Scalar,
+}
+
+impl Session {
+    /// `GetSessionValues`.
+    pub fn new(
+        key_agg: &KeyAggContext,
+        aggregate_nonce: &[u8; PUBLIC_NONCE_SIZE],
+        message: &[u8],
+    ) -> Result<Session, MusigError> {
+        let x_only_key = key_agg.x_only_key();
+
+        let mut data = aggregate_nonce.to_vec();
+        data.extend_from_slice(&x_only_key);
+        data.extend_from_slice(message);
+        let b = reduce(&tagged_hash("MuSig/noncecoef", &data));
+
+        let r1 = point_from_bytes_ext(&aggregate_nonce[..33])
+            .ok_or(MusigError::InvalidAggregateNonce)?;
+        let r2 = point_from_bytes_ext(&aggregate_nonce[33..])
+            .ok_or(MusigError::InvalidAggregateNonce)?;
+        let mut r = r1 + r2 * b;
+        // Only happens when a signer is malicious, the signature is then still valid.
+        if r == ProjectivePoint::IDENTITY {
+            r = ProjectivePoint::GENERATOR;
+        }
+        let r = r.to_affine();
+
+        let mut data = r.x().to_vec();
+        data.extend_from_slice(&x_only_key);
+        data.extend_from_slice(message);
+        let e = reduce(&tagged_hash("BIP0340/challenge", &data));
+
+        Ok(Session {
+            key_agg: key_agg.clone(),
+            b,
+            r,
+            e,
+        })
+    }
+
+    /// `Sign` : partial signature of a signer, checked before it is returned.
+    pub fn sign(
+        &self,
+        secret_nonce: SecretNonce,
+        secret_key: &[u8; 32],
+    ) -> Result<[u8; 32], MusigError> {
+        let d: Option<Scalar> = Scalar::from_repr((*secret_key).into()).into();
+        let d = d
+            .filter(|d| !bool::from(d.is_zero()))
+            .ok_or(KeyError::InvalidPrivateKey("not in [1, n-1]".to_string()))?;
+        let public_key = point_to_bytes(&(ProjectivePoint::GENERATOR * d).to_affine());
+        if public_key != secret_nonce.public_key {
+            return Err(MusigError::InvalidSecretNonce(
+                "it was generated for another key".to_string(),
+            ));
+        }
+        let a = self
+            .key_agg
+            .signer_coefficient(&public_key)
+            .ok_or(MusigError::UnknownPublicKey)?;
+
+        let (mut k1, mut k2) = (secret_nonce.k1, secret_nonce.k2);
+        if bool::from(self.r.y_is_odd()) {
+            k1 = -k1;
+            k2 = -k2;
+        }
+        let d = self.key_sign() * self.key_agg.gacc * d;
+        let s = k1 + self.b * k2 + self.e * a * d;
+
+        let partial_signature: [u8; 32] = s.to_bytes().into();
+        if !self.verify_partial(
+            &partial_signature,
+            &secret_nonce.public_nonce(),
+            &public_key,
+        ) {
+            let index = self
+                .key_agg
+                .public_keys
+                .iter()
+                .position(|key| key == &public_key);
+            return Err(MusigError::InvalidPartialSignature(
+                index.unwrap_or_default(),
+            ));
+        }
+        Ok(partial_signature)
+    }
+
+    /// `PartialSigVerify` : checks the partial signature of a signer against its public nonce.
+    pub fn verify_partial(
+        &self,
+        partial_signature: &[u8; 32],
+        public_nonce: &[u8; PUBLIC_NONCE_SIZE],
+        public_key: &[u8; 33],
+    ) -> bool {
+        let s: Option<Scalar> = Scalar::from_repr((*partial_signature).into()).into();
+        let (Some(s), Some(r1), Some(r2), Some(point), Some(a)) = (
+            s,
+            point_from_bytes(&public_nonce[..33]),
+            point_from_bytes(&public_nonce[33..]),
+            point_from_bytes(public_key),
+            self.key_agg.signer_coefficient(public_key),
+        ) else {
+            return false;
+        };
+
+        let mut r = r1 + r2 * self.b;
+        if bool::from(self.r.y_is_odd()) {
+            r = -r;
+        }
+        let g = self.key_sign() * self.key_agg.gacc;
+        ProjectivePoint::GENERATOR * s == r + point * (self.e * a * g)
+    }
+
+    /// `PartialSigAgg` : the BIP340 signature of the message for the aggregate key.
+    pub fn aggregate(&self, partial_signatures: &[[u8; 32]]) -> Result<[u8; 64], MusigError> {
+        let mut s = Scalar::ZERO;
+        for (index, partial_signature) in partial_signatures.iter().enumerate() {
+            let partial: Option<Scalar> = Scalar::from_repr((*partial_signature).into()).into();
+            s += partial.ok_or(MusigError::InvalidPartialSignature(index))?;
+        }
+        s += self.e * self.key_sign() * self.key_agg.tacc;
+
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(&self.r.x());
+        signature[32..].copy_from_slice(&s.to_bytes());
+        Ok(signature)
+    }
+
+    /// Negates the keys when the final aggregate key has an odd y.
+    fn key_sign(&self) -> Scalar {
+        match has_odd_y(&self.key_agg.q) {
+            true => -Scalar::ONE,
+            false => Scalar::ONE,
+        }
+    }
+}
+
+fn reduce(hash: &[u8; 32]) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(&(*hash).into())
+}
+
+fn has_odd_y(point: &ProjectivePoint) -> bool {
+    point.to_affine().y_is_odd().into()
+}
+
+/// `cpoint` : a compressed point.
+fn point_from_bytes(bytes: &[u8]) -> Option<ProjectivePoint> {
+    match bytes {
+        [0x02 | 0x03, ..] if bytes.len() == 33 => PublicKey::from_sec1_bytes(bytes)
+            .ok()
+            .map(|key| key.to_projective()),
+        _ => None,
+    }
+}
+
+/// `cpoint_ext` : a compressed point or 33 zero bytes for the point at infinity.
+fn point_from_bytes_ext(bytes: &[u8]) -> Option<ProjectivePoint> {
+    match bytes.iter().all(|byte| *byte == 0) {
+        true => Some(ProjectivePoint::IDENTITY),
+        false => point_from_bytes(bytes),
+    }
+}
+
+fn point_to_bytes(point: &AffinePoint) -> [u8; 33] {
+    point.to_encoded_point(true).as_bytes().try_into().unwrap()
+}
+
+fn point_to_bytes_ext(point: &ProjectivePoint) -> [u8; 33] {
+    match *point == ProjectivePoint::IDENTITY {
+        true => [0; 33],
+        false => point_to_bytes(&point.to_affine()),
+    }
+}
+
+#[cfg(test)]
+mod test_musig2 {
+    use crate::errors::MusigError;
+    use crate::musig2::{
+        key_sort, nonce_agg, nonce_gen, nonce_gen_with_rand, KeyAggContext, NonceInputs,
+        SecretNonce, Session,
+    };
+    use crate::schnorr;
+    use bee_vm::transaction::context::ExecutionContext;
+    use bee_vm::transaction::sighash::SIGHASH_DEFAULT;
+    use bee_vm::transaction::verify::verify_input;
+    use bee_vm::transaction::{OutPoint, Transaction, TxIn, TxOut};
+    use k256::elliptic_curve::sec1::ToEncodedPoint;
+    use k256::SecretKey;
+    use rstest::rstest;
+
+    // BIP327 key aggregation vectors.
+    const PUBLIC_KEYS: [&str; 4] = [
+        "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+        "03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+        "023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66",
+        // Not on the curve.
+        "020000000000000000000000000000000000000000000000000000000000000005",
+    ];
+
+    const SECRET_KEYS: [&str; 3] = [
+        "7fb9e0e687ada1eebf7ecfe2f21e73ebdb51a7d450948dfe8d76d7f2d1007671",
+        "3874d22de7a7290c49ce7f1ef17d5e0f8ebe6ec6c1ecb99d3d1f9bd4c2e6a9c1",
+        "b6a6a1a5b0fa3b6d6f24f40a56d6a4e8e21f1e64d5cf8d7e3a8f7b91f8a5d4c2",
+    ];
+    const MESSAGE: &str = "f95466d086770e689964664219266fe5ed215c92ae20bab5c9d79addddf3c0cf";
+
+    fn bytes<const N: usize>(hex: &str) -> [u8; N] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    fn public_key(secret_key: &[u8; 32]) -> [u8; 33] {
+        let key = SecretKey::from_bytes(secret_key.into()).unwrap();
+        bytes(&hex::encode(key.public_key().to_encoded_point(true)))
+    }
+
+    #[rstest]
+    #[case(&[0, 1, 2], "90539eede565f5d054f32cc0c220126889ed1e5d193baf15aef344fe59d4610c")]
+    #[case(&[2, 1, 0], "6204de8b083426dc6eaf9502d27024d53fc826bf7d2012148a0575435df54b2b")]
+    #[case(&[0, 0, 0], "b436e3bad62b8cd409969a224731c193d051162d8c5ae8b109306127da3aa935")]
+    #[case(&[0, 0, 1, 1], "69bc22bfa5d106306e48a20679de1d7389386124d07571d0d872686028c26a3e")]
+    fn test_key_agg(#[case] indices: &[usize], #[case] expected: &str) {
+        let public_keys: Vec<[u8; 33]> = indices.iter().map(|i| bytes(PUBLIC_KEYS[*i])).collect();
+        let context = KeyAggContext::new(&public_keys).unwrap();
+        assert_eq!(hex::encode(context.x_only_key()), expected);
+    }
+
+    #[rstest]
+    fn test_invalid_key_agg() {
+        let public_keys = [bytes(PUBLIC_KEYS[0]), bytes(PUBLIC_KEYS[3])];
+        assert_eq!(
+            KeyAggContext::new(&public_keys).unwrap_err(),
+            MusigError::InvalidPublicKey(1)
+        );
+        assert_eq!(
+            KeyAggContext::new(&[]).unwrap_err(),
+            MusigError::NoPublicKeys
+        );
+    }
+
+    #[rstest]
+    fn test_key_sort() {
+        let mut public_keys: Vec<[u8; 33]> =
+            PUBLIC_KEYS[..3].iter().map(|key| bytes(key)).collect();
+        key_sort(&mut public_keys);
+        assert_eq!(
+            public_keys,
+            [PUBLIC_KEYS[2], PUBLIC_KEYS[0], PUBLIC_KEYS[1]].map(bytes::<33>)
+        );
+    }
+
+    // The first one is BIP327's first nonce generation vector, the others come from the `musig2` crate.
+    #[rstest]
+    #[case(
+        true,
+        Some("0101010101010101010101010101010101010101010101010101010101010101"),
+        Some("0808080808080808080808080808080808080808080808080808080808080808"),
+        "b114e502beaa4e301dd08a50264172c84e41650e6cb726b410c0694d59effb6495b5caf28d045b973d63e3c99a44b807bde375fd6cb39e46dc4a511708d0e9d2"
+    )]
+    #[case(
+        false,
+        None,
+        None,
+        "1e05e872bf84e99c49724fb9ed1a9f6d1d5e6cf0fbf4757cafbe05147bfa3959c3bbac9b617f615792c0f790d8ea03b378be2441525bb2b4d9d6aac39844b5b3"
+    )]
+    #[case(
+        false,
+        Some(""),
+        None,
+        "9c21c13742c05a0996d09b3b797df73e50b032fefc5927d72b9263d718be82e7a8f511ae442c825f54ee76ec622bdbfabb78a235bf5acd952fcb79e0b1ad3299"
+    )]
+    fn test_nonce_gen(
+        #[case] with_aggregate_key: bool,
+        #[case] message: Option<&str>,
+        #[case] extra_input: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        let secret_key = [0x02; 32];
+        let aggregate_key = [0x07; 32];
+        let message = message.map(|message| hex::decode(message).unwrap());
+        let extra_input = extra_input.map(|extra| hex::decode(extra).unwrap());
+        let inputs = NonceInputs {
+            secret_key: Some(&secret_key),
+            aggregate_key: with_aggregate_key.then_some(&aggregate_key),
+            message: message.as_deref(),
+            extra_input: extra_input.as_deref(),
+        };
+        let public_key = public_key(&secret_key);
+
+        let (secret_nonce, public_nonce) =
+            nonce_gen_with_rand(&[0x0f; 32], &public_key, &inputs).unwrap();
+        let secret_nonce = secret_nonce.to_bytes();
+        assert_eq!(hex::encode(&secret_nonce[..64]), expected);
+        assert_eq!(secret_nonce[64..], public_key);
+        assert_eq!(
+            SecretNonce::from_bytes(&secret_nonce)
+                .unwrap()
+                .public_nonce(),
+            public_nonce
+        );
+    }
+
+    // Three signer sessions, checked against the `musig2` crate :
+    // untweaked, BIP86 tweaked, and plain tweaked then taproot tweaked with a merkle root.
+    #[rstest]
+    #[case(
+        None,
+        None,
+        "c22250527200815667e505acc0512a3b23ebeba9a912a05a07222a53c8799338",
+        "0265ddee70bd601307a539265c85fb03e7e5420b8396a4f18d788aab2b9ec0e4df035135db3d2e1d9dc4394d67bac1ec590ca7bd189ce6f2ae8843dd88029d6961ff",
+        ["aa895e1d2de5e0921a57be5bc8de1184084e3a401e80c758226953bef49ba7e6", "7ad2f25b2863572b509f15265a5555b7727b330c73f56e2596739315c082183a", "ab81a238e9f7e36ed5c29e9c07d0e636020fa3ca66490a91504cf6f4896776e2"],
+        "5aa53397272addf2d563ca80ea59893af8530e56e730d4bc4ff0acecb12b983fd0ddf2b140411b2c40b9721e2b044d72c22a343049769fd349577f3c6e4ef5c1"
+    )]
+    #[case(
+        None,
+        Some(None),
+        "149f60f254dcc150c1eb29342f5dc5c37462738d10867af18df7861f338254c3",
+        "020be26d1d53126f6d3e58f7353ab29546a7bc6cf702a38f2db612dddfd513a7a403f89ab96491683f44f313eaa4b08fa22ed295a99a5335db56f2840ec508755408",
+        ["5ed3039bfce95d64791eecab8fc739b029070fc592a5746b2fe5cbefc8de5450", "29f5f2979fc7793b473f8fdeb1a435965d39b49f42a6c5420f4bd27102c60824", "ee80f3bed0fd5f3fad405e3f1d91f7dd2ca2f7b89ab16451fc87370089082c6f"],
+        "48ebd5c0aa945180e039a20a0b8081e9e1fe9b192d3ec9e4378a4315725810dc32aa9b6f59590b499c38680f85d64bdd69c35242a07bf1e0af5a5ead29f14354"
+    )]
+    #[case(
+        Some([0x11; 32]),
+        Some(Some([0x42; 32])),
+        "12574fc77f550eb014accf0051a3fbfddd678190c6b0bc25942c885ac175b622",
+        "026ebff88fc53c86454c24f5f31dcc86ee423f64a947d6f9c4f5900254ff9018d4035f19e0b67e4eedccf61664c4c9b874f5827bf8f3bae878d16f362fcfe614e739",
+        ["dad889bfb75fba3751f25e3892faca4d409ed1b51881e7b8577fbead2ab42707", "932cc6636b6f81fe595a5e41ae2aa202d34094d0579dd5a1acf734741dd6029c", "d201b60cd28454bbe095e8df381e893cee3e51c93b96eb2610b7a405cda22e50"],
+        "36e97c075e51d9884c51a487079b945989470687728b25d20840309e989ae9497bfd6bf03cf827663cdf7a3e79a8be9a56b3b01ce353ea1735669a80a3329b23"
+    )]
+    fn test_session(
+        #[case] plain_tweak: Option<[u8; 32]>,
+        #[case] taproot_tweak: Option<Option<[u8; 32]>>,
+        #[case] output_key: &str,
+        #[case] aggregate_nonce: &str,
+        #[case] partial_signatures: [&str; 3],
+        #[case] signature: &str,
+    ) -> color_eyre::Result<()> {
+        let secret_keys: Vec<[u8; 32]> = SECRET_KEYS.iter().map(|key| bytes(key)).collect();
+        let public_keys: Vec<[u8; 33]> = secret_keys.iter().map(public_key).collect();
+        let message: [u8; 32] = bytes(MESSAGE);
+
+        let mut context = KeyAggContext::new(&public_keys)?;
+        if let Some(tweak) = plain_tweak {
+            context.apply_tweak(&tweak, false)?;
+        }
+        if let Some(merkle_root) = taproot_tweak {
+            context.apply_taproot_tweak(merkle_root.as_ref())?;
+        }
+        let x_only_key = context.x_only_key();
+        assert_eq!(hex::encode(x_only_key), output_key);
+
+        let mut nonces = vec![];
+        for (index, secret_key) in secret_keys.iter().enumerate() {
+            let inputs = NonceInputs {
+                secret_key: Some(secret_key),
+                aggregate_key: Some(&x_only_key),
+                message: Some(&message),
+                extra_input: None,
+            };
+            nonces.push(nonce_gen_with_rand(
+                &[0xa0 + index as u8; 32],
+                &public_keys[index],
+                &inputs,
+            )?);
+        }
+        let public_nonces: Vec<_> = nonces
+            .iter()
+            .map(|(_, public_nonce)| *public_nonce)
+            .collect();
+        let aggregate = nonce_agg(&public_nonces)?;
+        assert_eq!(hex::encode(aggregate), aggregate_nonce);
+
+        let session = Session::new(&context, &aggregate, &message)?;
+        let mut signatures = vec![];
+        for (index, (secret_nonce, public_nonce)) in nonces.into_iter().enumerate() {
+            let partial = session.sign(secret_nonce, &secret_keys[index])?;
+            assert_eq!(hex::encode(partial), partial_signatures[index]);
+            assert!(session.verify_partial(&partial, &public_nonce, &public_keys[index]));
+            // Checked against the nonce of another signer.
+            assert!(!session.verify_partial(
+                &partial,
+                &public_nonces[(index + 1) % 3],
+                &public_keys[index]
+            ));
+            signatures.push(partial);
+        }
+
+        let aggregated = session.aggregate(&signatures)?;
+        assert_eq!(hex::encode(aggregated), signature);
+        assert!(schnorr::verify(&x_only_key, &message, &aggregated));
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_sign_with_wrong_key() -> color_eyre::Result<()> {
+        let secret_keys: Vec<[u8; 32]> = SECRET_KEYS.iter().map(|key| bytes(key)).collect();
+        let public_keys: Vec<[u8; 33]> = secret_keys.iter().map(public_key).collect();
+        let context = KeyAggContext::new(&public_keys[..2])?;
+
+        let (first_nonce, first_public) = nonce_gen(&public_keys[0], &NonceInputs::default())?;
+        let (second_nonce, second_public) = nonce_gen(&public_keys[2], &NonceInputs::default())?;
+        let aggregate = nonce_agg(&[first_public, second_public])?;
+        let session = Session::new(&context, &aggregate, b"message")?;
+
+        assert!(matches!(
+            session.sign(first_nonce, &secret_keys[1]),
+            Err(MusigError::InvalidSecretNonce(_))
+        ));
+        assert_eq!(
+            session.sign(second_nonce, &secret_keys[2]).unwrap_err(),
+            MusigError::UnknownPublicKey
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some([0x42; 32]))]
+    fn test_taproot_key_path_spend(
+        #[case] merkle_root: Option<[u8; 32]>,
+    ) -> color_eyre::Result<()> {
+        let secret_keys: Vec<[u8; 32]> = SECRET_KEYS.iter().map(|key| bytes(key)).collect();
+        let mut public_keys: Vec<[u8; 33]> = secret_keys.iter().map(public_key).collect();
+        key_sort(&mut public_keys);
+        let mut context = KeyAggContext::new(&public_keys)?;
+        context.apply_taproot_tweak(merkle_root.as_ref())?;
+
+        let mut script_pubkey = vec![0x51, 0x20];
+        script_pubkey.extend_from_slice(&context.x_only_key());
+        let prevouts = vec![TxOut {
+            value: 100_000,
+            script_pubkey: script_pubkey.clone(),
+        }];
+        let mut tx = Transaction {
+            version: 2,
+            inputs: vec![TxIn::new(OutPoint::default(), 0xffff_fffd)],
+            outputs: vec![TxOut {
+                value: 99_000,
+                script_pubkey,
+            }],
+            lock_time: 0,
+        };
+        let sighash = ExecutionContext::taproot(tx.clone(), 0, prevouts.clone(), None)?
+            .signature_hash(&[], SIGHASH_DEFAULT as u32, u32::MAX)?;
+
+        let nonces = secret_keys
+            .iter()
+            .map(|secret_key| nonce_gen(&public_key(secret_key), &NonceInputs::default()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let public_nonces: Vec<_> = nonces
+            .iter()
+            .map(|(_, public_nonce)| *public_nonce)
+            .collect();
+        let session = Session::new(&context, &nonce_agg(&public_nonces)?, &sighash)?;
+        let partial_signatures = nonces
+            .into_iter()
+            .zip(&secret_keys)
+            .map(|((secret_nonce, _), secret_key)| session.sign(secret_nonce, secret_key))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        tx.inputs[0].witness = vec![session.aggregate(&partial_signatures)?.to_vec()];
+        verify_input(&tx, 0, &prevouts)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::errors::AddressError;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    /// Base58 version byte of P2PKH addresses.
+    pub fn p2pkh_version(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            _ => 0x6f,
+        }
+    }
+
+    /// Base58 version byte of P2SH addresses.
+    pub fn p2sh_version(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            _ => 0xc4,
+        }
+    }
+
+    /// Version byte of WIF encoded private keys.
+    pub fn wif_version(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x80,
+            _ => 0xef,
+        }
+    }
+
+    /// Human readable part of segwit addresses.
+    pub fn bech32_hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mainnet" | "main" | "bitcoin" => Ok(Network::Mainnet),
+            "testnet" | "test" | "testnet3" | "testnet4" => Ok(Network::Testnet),
+            "signet" => Ok(Network::Signet),
+            "regtest" => Ok(Network::Regtest),
+            _ => Err(AddressError::UnknownNetwork(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Signet => "signet",
+            Network::Regtest => "regtest",
+        };
+        write!(f, "{}", name)
+    }
+}
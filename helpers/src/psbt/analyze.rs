@@ -0,0 +1,268 @@
+// Reports what every input still needs and which role has to act next, in the shape of
+// Bitcoin Core's `analyzepsbt`.
+
+use crate::errors::PsbtError;
+use crate::hashes::hash160;
+use crate::psbt::sign::{spend_info, SpendInfo};
+use crate::psbt::Psbt;
+use bee_vm::script::classify::{classify, ScriptType};
+use serde_json::{json, Map, Value};
+
+const SATOSHIS_PER_BTC: f64 = 100_000_000.0;
+
+/// PSBT roles in the order they act.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Updater,
+    Signer,
+    Finalizer,
+    Extractor,
+}
+
+impl Role {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Updater => "updater",
+            Role::Signer => "signer",
+            Role::Finalizer => "finalizer",
+            Role::Extractor => "extractor",
+        }
+    }
+}
+
+/// Missing data of an input and the role that can provide it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InputAnalysis {
+    pub has_utxo: bool,
+    pub is_final: bool,
+    /// Hash160 of the keys whose public key is not known.
+    pub missing_pubkeys: Vec<[u8; 20]>,
+    /// Hash160 (x-only key for taproot) of the keys that still have to sign.
+    pub missing_signatures: Vec<Vec<u8>>,
+    pub missing_redeem_script: Option<Vec<u8>>,
+    pub missing_witness_script: Option<Vec<u8>>,
+}
+
+impl InputAnalysis {
+    pub fn next(&self) -> Role {
+        if self.is_final {
+            Role::Extractor
+        } else if !self.has_utxo
+            || self.missing_pubkeys.len() > self.missing_signatures.len()
+            || self.missing_redeem_script.is_some()
+            || self.missing_witness_script.is_some()
+        {
+            Role::Updater
+        } else if !self.missing_signatures.is_empty() {
+            Role::Signer
+        } else {
+            Role::Finalizer
+        }
+    }
+}
+
+pub fn analyze_input(psbt: &Psbt, index: usize) -> InputAnalysis {
+    let input = &psbt.inputs[index];
+    let mut analysis = InputAnalysis {
+        has_utxo: psbt.utxo(index).is_ok(),
+        is_final: input.is_final(),
+        ..Default::default()
+    };
+    if analysis.is_final || !analysis.has_utxo {
+        return analysis;
+    }
+
+    let info = match spend_info(psbt, index) {
+        Ok(info) => info,
+        Err(PsbtError::ScriptMismatch(..)) => {
+            // The script hash the missing (or wrong) script must match.
+            let script_pubkey = psbt.utxo(index).unwrap().script_pubkey;
+            match classify(&script_pubkey) {
+                ScriptType::P2SH { script_hash } => match &input.redeem_script {
+                    Some(redeem_script) if hash160(redeem_script) == script_hash => {
+                        if let ScriptType::P2WSH { script_hash } = classify(redeem_script) {
+                            analysis.missing_witness_script = Some(script_hash.to_vec());
+                        }
+                    }
+                    _ => analysis.missing_redeem_script = Some(script_hash.to_vec()),
+                },
+                ScriptType::P2WSH { script_hash } => {
+                    analysis.missing_witness_script = Some(script_hash.to_vec())
+                }
+                _ => {}
+            }
+            return analysis;
+        }
+        Err(_) => return analysis,
+    };
+
+    match info {
+        SpendInfo::Ecdsa { script_code, .. } => match classify(&script_code) {
+            ScriptType::P2PK { pubkey } if !input.partial_sigs.contains_key(&pubkey) => {
+                analysis.missing_signatures.push(hash160(&pubkey).to_vec());
+            }
+            ScriptType::P2PKH { pubkey_hash } => {
+                let matches = |public_key: &&Vec<u8>| hash160(public_key) == pubkey_hash;
+                if !input.partial_sigs.keys().any(|key| matches(&key)) {
+                    if !input.bip32_derivation.keys().any(|key| matches(&key)) {
+                        analysis.missing_pubkeys.push(pubkey_hash);
+                    }
+                    analysis.missing_signatures.push(pubkey_hash.to_vec());
+                }
+            }
+            ScriptType::Multisig { required, pubkeys } => {
+                let signed = pubkeys
+                    .iter()
+                    .filter(|public_key| input.partial_sigs.contains_key(*public_key))
+                    .count();
+                if signed < required {
+                    analysis.missing_signatures = pubkeys
+                        .iter()
+                        .filter(|public_key| !input.partial_sigs.contains_key(*public_key))
+                        .map(|public_key| hash160(public_key).to_vec())
+                        .collect();
+                }
+            }
+            _ => {}
+        },
+        SpendInfo::Taproot { output_key } => {
+            if input.tap_key_sig.is_none() && input.tap_script_sigs.is_empty() {
+                let key = input.tap_internal_key.unwrap_or(output_key);
+                analysis.missing_signatures.push(key.to_vec());
+            }
+        }
+    }
+    analysis
+}
+
+/// JSON report of the PSBT, like Core's `analyzepsbt`.
+pub fn analyze(psbt: &Psbt) -> Value {
+    let analyses: Vec<InputAnalysis> = (0..psbt.inputs.len())
+        .map(|index| analyze_input(psbt, index))
+        .collect();
+
+    let inputs: Vec<Value> = analyses
+        .iter()
+        .map(|analysis| {
+            let mut value = json!({
+                "has_utxo": analysis.has_utxo,
+                "is_final": analysis.is_final,
+            });
+            let mut missing = Map::new();
+            if !analysis.missing_pubkeys.is_empty() {
+                missing.insert(
+                    "pubkeys".to_string(),
+                    analysis.missing_pubkeys.iter().map(hex::encode).collect(),
+                );
+            }
+            if !analysis.missing_signatures.is_empty() {
+                missing.insert(
+                    "signatures".to_string(),
+                    analysis
+                        .missing_signatures
+                        .iter()
+                        .map(hex::encode)
+                        .collect(),
+                );
+            }
+            if let Some(hash) = &analysis.missing_redeem_script {
+                missing.insert("redeemscript".to_string(), hex::encode(hash).into());
+            }
+            if let Some(hash) = &analysis.missing_witness_script {
+                missing.insert("witnessscript".to_string(), hex::encode(hash).into());
+            }
+            if !missing.is_empty() {
+                value["missing"] = Value::Object(missing);
+            }
+            value["next"] = analysis.next().name().into();
+            value
+        })
+        .collect();
+
+    let mut report = json!({ "inputs": inputs });
+    if let Ok(spent_outputs) = psbt.spent_outputs() {
+        let spent: u64 = spent_outputs.iter().map(|output| output.value).sum();
+        let sent: u64 = psbt.outputs.iter().map(|output| output.amount).sum();
+        match spent.checked_sub(sent) {
+            Some(fee) => report["fee"] = (fee as f64 / SATOSHIS_PER_BTC).into(),
+            None => report["error"] = "Outputs are worth more than the inputs".into(),
+        }
+    }
+    let next = analyses
+        .iter()
+        .map(InputAnalysis::next)
+        .min()
+        .unwrap_or(Role::Extractor);
+    report["next"] = next.name().into();
+    report
+}
+
+#[cfg(test)]
+mod test_analyze {
+    use crate::keys::PrivateKey;
+    use crate::network::Network;
+    use crate::psbt::analyze::{analyze, Role};
+    use crate::psbt::finalize::finalize;
+    use crate::psbt::sign::sign;
+    use crate::psbt::{Input, Output, Psbt};
+    use bee_vm::transaction::TxOut;
+    use rstest::rstest;
+
+    // BIP174 P2SH-P2WSH 2-of-2 input with one of its two signatures.
+    const P2SH_P2WSH_PARTIAL_SIG: &str = "70736274ff0100550200000001279a2323a5dfb51fc45f220fa58b0fc13e1e3342792a85d7e36cd6333b5cbc390000000000ffffffff01a05aea0b000000001976a914ffe9c0061097cc3b636f2cb0460fa4fc427d2b4588ac0000000000010120955eea0b0000000017a9146345200f68d189e1adc0df1c4d16ea8f14c0dbeb87220203b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4646304302200424b58effaaa694e1559ea5c93bbfd4a89064224055cdf070b6771469442d07021f5c8eb0fea6516d60b8acb33ad64ede60e8785bfb3aa94b99bdf86151db9a9a010104220020771fd18ad459666dd49f3d564e3dbc42f4c84774e360ada16816a8ed488d5681010547522103b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd462103de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083bd52ae220603b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4610b4a6ba67000000800000008004000080220603de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083bd10b4a6ba670000008000000080050000800000";
+
+    #[rstest]
+    fn test_missing_signature() -> color_eyre::Result<()> {
+        let report = analyze(&P2SH_P2WSH_PARTIAL_SIG.parse()?);
+        assert_eq!(report["inputs"][0]["has_utxo"], true);
+        // Hash160 of 03de55d1..., the key without a signature.
+        assert_eq!(
+            report["inputs"][0]["missing"]["signatures"],
+            serde_json::json!(["38c8ab6e1dc92b031458c197af2ffcbeb83acb4e"])
+        );
+        assert_eq!(report["next"], "signer");
+        // 1.99909013 BTC spent, 1.99908 BTC sent.
+        assert_eq!(report["fee"], 0.00001013);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_roles() -> color_eyre::Result<()> {
+        let alice = PrivateKey::from_seed(b"alice", Network::Testnet);
+        let p2wsh_script = [vec![0x21], alice.public_key().to_bytes(), vec![0xac]].concat();
+        let p2wsh = [
+            vec![0x00, 0x20],
+            crate::hashes::sha256(&p2wsh_script).to_vec(),
+        ]
+        .concat();
+
+        let mut psbt = Psbt::new(2, 2)?;
+        psbt.inputs.push(Input::default());
+        psbt.outputs.push(Output {
+            amount: 1_000,
+            script_pubkey: vec![0x6a],
+            ..Default::default()
+        });
+        assert_eq!(analyze(&psbt)["next"], Role::Updater.name());
+
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 2_000,
+            script_pubkey: p2wsh,
+        });
+        let report = analyze(&psbt);
+        assert_eq!(
+            report["inputs"][0]["missing"]["witnessscript"],
+            hex::encode(crate::hashes::sha256(&p2wsh_script))
+        );
+        assert_eq!(report["next"], Role::Updater.name());
+
+        psbt.inputs[0].witness_script = Some(p2wsh_script);
+        assert_eq!(analyze(&psbt)["next"], Role::Signer.name());
+        sign(&mut psbt, &[alice])?;
+        assert_eq!(analyze(&psbt)["next"], Role::Finalizer.name());
+        finalize(&mut psbt)?;
+        assert_eq!(analyze(&psbt)["next"], Role::Extractor.name());
+        assert_eq!(analyze(&psbt)["fee"], 0.00001);
+        Ok(())
+    }
+}
@@ -0,0 +1,442 @@
+// Finalizer and extractor roles.
+//
+// The finalizer turns the partial signatures of an input into its scriptSig and witness for
+// the templates it knows (P2PK, P2PKH, multisig, their P2SH / P2WSH / P2SH-P2WSH wrappings,
+// P2WPKH, P2SH-P2WPKH and taproot key path / single key leaves). The result is run through
+// bee-vm's `verify_input` before it is stored, so a finalized input is known to be valid.
+
+use crate::errors::PsbtError;
+use crate::hashes::hash160;
+use crate::psbt::sign::{spend_info, SpendInfo};
+use crate::psbt::Psbt;
+use bee_vm::opcodes::opcode::Opcode;
+use bee_vm::script::classify::{classify, ScriptType};
+use bee_vm::script::decode::{decode, Instruction};
+use bee_vm::script::encode::push_data;
+use bee_vm::transaction::sighash::tap_leaf_hash;
+use bee_vm::transaction::verify::verify_input;
+use bee_vm::transaction::{Transaction, TxOut};
+
+/// BIP371 PSBT_IN_TAP_BIP32_DERIVATION, kept with the unknown fields.
+const IN_TAP_BIP32_DERIVATION: u8 = 0x16;
+
+/// Finalizes every input, stopping at the first one that can not be finalized.
+pub fn finalize(psbt: &mut Psbt) -> Result<(), PsbtError> {
+    (0..psbt.inputs.len()).try_for_each(|index| finalize_input(psbt, index))
+}
+
+/// Builds the final scriptSig / witness of an input and checks it with bee-vm.
+///
+/// On success everything but the UTXO and the unknown fields is removed from the input.
+pub fn finalize_input(psbt: &mut Psbt, index: usize) -> Result<(), PsbtError> {
+    let input = psbt
+        .inputs
+        .get(index)
+        .ok_or(PsbtError::InputIndexOutOfRange(index))?;
+    if input.is_final() {
+        return Ok(());
+    }
+    let cannot = |reason: &str| PsbtError::CanNotFinalize(index, reason.to_string());
+
+    let (script_sig, witness) = match spend_info(psbt, index)? {
+        SpendInfo::Ecdsa { script_code, .. } => {
+            let items = satisfy(&script_code, &input.partial_sigs).map_err(|e| cannot(&e))?;
+            let script_pubkey = psbt.utxo(index)?.script_pubkey;
+            let redeem_script = input.redeem_script.clone().unwrap_or_default();
+            let program = match classify(&script_pubkey) {
+                ScriptType::P2SH { .. } => classify(&redeem_script),
+                script_type => script_type,
+            };
+
+            let witness_script = |mut items: Vec<Vec<u8>>| {
+                items.push(input.witness_script.clone().unwrap_or_default());
+                items
+            };
+            let (mut script_sig, witness) = match program {
+                ScriptType::P2WPKH { .. } => (vec![], items),
+                ScriptType::P2WSH { .. } => (vec![], witness_script(items)),
+                _ => (pushes(&items).map_err(|e| cannot(&e))?, vec![]),
+            };
+            if !redeem_script.is_empty() {
+                push_data(&mut script_sig, &redeem_script).map_err(|e| cannot(&e.to_string()))?;
+            }
+            (script_sig, witness)
+        }
+        SpendInfo::Taproot { .. } => (
+            vec![],
+            taproot_witness(psbt, index).map_err(|e| cannot(&e))?,
+        ),
+    };
+
+    // bee-vm has to accept the input before anything is stored.
+    let mut tx = psbt.unsigned_tx()?;
+    tx.inputs[index].script_sig = script_sig.clone();
+    tx.inputs[index].witness = witness.clone();
+    verify_input(&tx, index, &verification_prevouts(psbt, index)?).map_err(|e| {
+        PsbtError::VerifyFailed {
+            input: index,
+            reason: e.to_string(),
+        }
+    })?;
+
+    let input = &mut psbt.inputs[index];
+    input.final_script_sig = (!script_sig.is_empty()).then_some(script_sig);
+    input.final_script_witness = (!witness.is_empty()).then_some(witness);
+    input.partial_sigs.clear();
+    input.sighash_type = None;
+    input.redeem_script = None;
+    input.witness_script = None;
+    input.bip32_derivation.clear();
+    input.tap_key_sig = None;
+    input.tap_script_sigs.clear();
+    input.tap_leaf_scripts.clear();
+    input.tap_internal_key = None;
+    input.tap_merkle_root = None;
+    input
+        .unknown
+        .retain(|key, _| key[0] != IN_TAP_BIP32_DERIVATION);
+    Ok(())
+}
+
+/// Extractor role : the network transaction, once every input passes bee-vm verification.
+pub fn extract(psbt: &Psbt) -> Result<Transaction, PsbtError> {
+    let mut tx = psbt.unsigned_tx()?;
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        if !input.is_final() {
+            return Err(PsbtError::NotFinalized(index));
+        }
+        tx.inputs[index].script_sig = input.final_script_sig.clone().unwrap_or_default();
+        tx.inputs[index].witness = input.final_script_witness.clone().unwrap_or_default();
+    }
+
+    let prevouts = psbt.spent_outputs()?;
+    for index in 0..tx.inputs.len() {
+        verify_input(&tx, index, &prevouts).map_err(|e| PsbtError::VerifyFailed {
+            input: index,
+            reason: e.to_string(),
+        })?;
+    }
+    Ok(tx)
+}
+
+/// Stack items (bottom first) satisfying a script with the available signatures.
+fn satisfy(
+    script: &[u8],
+    partial_sigs: &std::collections::BTreeMap<Vec<u8>, Vec<u8>>,
+) -> Result<Vec<Vec<u8>>, String> {
+    match classify(script) {
+        ScriptType::P2PK { pubkey } => partial_sigs
+            .get(&pubkey)
+            .map(|signature| vec![signature.clone()])
+            .ok_or("missing signature".to_string()),
+        ScriptType::P2PKH { pubkey_hash } => partial_sigs
+            .iter()
+            .find(|(public_key, _)| hash160(public_key) == pubkey_hash)
+            .map(|(public_key, signature)| vec![signature.clone(), public_key.clone()])
+            .ok_or("missing signature".to_string()),
+        ScriptType::Multisig { required, pubkeys } => {
+            // Signatures in the order of the keys, after the dummy element CHECKMULTISIG pops.
+            let signatures: Vec<Vec<u8>> = pubkeys
+                .iter()
+                .filter_map(|public_key| partial_sigs.get(public_key).cloned())
+                .take(required)
+                .collect();
+            if signatures.len() < required {
+                return Err(format!("{} of {} signatures", signatures.len(), required));
+            }
+            Ok([vec![vec![]], signatures].concat())
+        }
+        script_type => Err(format!("unsupported script type {}", script_type.name())),
+    }
+}
+
+/// Key path signature, or the script path of a `<x-only key> OP_CHECKSIG` leaf with the
+/// smallest control block.
+fn taproot_witness(psbt: &Psbt, index: usize) -> Result<Vec<Vec<u8>>, String> {
+    let input = &psbt.inputs[index];
+    if let Some(signature) = &input.tap_key_sig {
+        return Ok(vec![signature.clone()]);
+    }
+
+    input
+        .tap_leaf_scripts
+        .iter()
+        .filter_map(|(control_block, (script, leaf_version))| {
+            let x_only: [u8; 32] = match decode(script).ok()?.as_slice() {
+                [Instruction::Push { data, .. }, Instruction::Op(Opcode(0xac))] => {
+                    data.as_slice().try_into().ok()?
+                }
+                _ => return None,
+            };
+            let signature = input
+                .tap_script_sigs
+                .get(&(x_only, tap_leaf_hash(*leaf_version, script)))?;
+            Some(vec![
+                signature.clone(),
+                script.clone(),
+                control_block.clone(),
+            ])
+        })
+        .min_by_key(|witness| witness[2].len())
+        .ok_or("missing taproot signature".to_string())
+}
+
+/// Outputs handed to `verify_input`. Taproot signatures commit to every spent output, legacy
+/// and segwit v0 ones only read their own so the others may be unknown.
+fn verification_prevouts(psbt: &Psbt, index: usize) -> Result<Vec<TxOut>, PsbtError> {
+    match psbt.spent_outputs() {
+        Ok(prevouts) => Ok(prevouts),
+        Err(_) => {
+            let spent_output = psbt.utxo(index)?;
+            Ok((0..psbt.inputs.len())
+                .map(|i| match i == index {
+                    true => spent_output.clone(),
+                    false => TxOut {
+                        value: 0,
+                        script_pubkey: vec![],
+                    },
+                })
+                .collect())
+        }
+    }
+}
+
+fn pushes(items: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    let mut script = vec![];
+    for item in items {
+        push_data(&mut script, item).map_err(|e| e.to_string())?;
+    }
+    Ok(script)
+}
+
+#[cfg(test)]
+mod test_finalize {
+    use crate::errors::PsbtError;
+    use crate::hashes::{hash160, sha256};
+    use crate::keys::PrivateKey;
+    use crate::network::Network;
+    use crate::psbt::finalize::{extract, finalize, finalize_input};
+    use crate::psbt::sign::sign;
+    use crate::psbt::{Input, Output, Psbt};
+    use crate::schnorr::{taproot_output_key, tweak_public_key};
+    use bee_vm::transaction::sighash::{tap_branch_hash, tap_leaf_hash, TAPSCRIPT_LEAF_VERSION};
+    use bee_vm::transaction::{OutPoint, Transaction, TxOut};
+    use rstest::rstest;
+
+    fn key(seed: &str) -> PrivateKey {
+        PrivateKey::from_seed(seed.as_bytes(), Network::Testnet)
+    }
+
+    fn multisig(keys: &[&PrivateKey]) -> Vec<u8> {
+        let mut script = vec![0x50 + 2];
+        for key in keys {
+            script.push(33);
+            script.extend_from_slice(&key.public_key().compressed_bytes());
+        }
+        script.extend_from_slice(&[0x50 + keys.len() as u8, 0xae]);
+        script
+    }
+
+    fn p2sh(script: &[u8]) -> Vec<u8> {
+        [vec![0xa9, 0x14], hash160(script).to_vec(), vec![0x87]].concat()
+    }
+
+    fn p2wsh(script: &[u8]) -> Vec<u8> {
+        [vec![0x00, 0x20], sha256(script).to_vec()].concat()
+    }
+
+    /// A PSBT spending `utxos` to a single P2WPKH output, with UTXOs only (no scripts).
+    fn psbt_spending(utxos: &[TxOut]) -> Psbt {
+        let mut psbt = Psbt::new(2, 2).unwrap();
+        psbt.inputs = utxos
+            .iter()
+            .enumerate()
+            .map(|(vout, utxo)| Input {
+                previous_output: OutPoint {
+                    txid: [7; 32],
+                    vout: vout as u32,
+                },
+                witness_utxo: Some(utxo.clone()),
+                ..Default::default()
+            })
+            .collect();
+        psbt.outputs = vec![Output {
+            amount: 90_000,
+            script_pubkey: [vec![0x00, 0x14], [1; 20].to_vec()].concat(),
+            ..Default::default()
+        }];
+        psbt
+    }
+
+    #[rstest]
+    fn test_segwit_and_p2sh_inputs() -> color_eyre::Result<()> {
+        let (alice, bob, carol) = (key("alice"), key("bob"), key("carol"));
+        let two_of_three = multisig(&[&alice, &bob, &carol]);
+        let p2wpkh = [vec![0x00, 0x14], alice.public_key().hash160().to_vec()].concat();
+
+        let mut psbt = psbt_spending(&[
+            TxOut {
+                value: 50_000,
+                script_pubkey: p2wpkh.clone(),
+            },
+            TxOut {
+                value: 30_000,
+                script_pubkey: p2sh(&p2wpkh),
+            },
+            TxOut {
+                value: 20_000,
+                script_pubkey: p2wsh(&two_of_three),
+            },
+            TxOut {
+                value: 10_000,
+                script_pubkey: p2sh(&p2wsh(&two_of_three)),
+            },
+        ]);
+        psbt.inputs[1].redeem_script = Some(p2wpkh);
+        psbt.inputs[2].witness_script = Some(two_of_three.clone());
+        psbt.inputs[3].redeem_script = Some(p2wsh(&two_of_three));
+        psbt.inputs[3].witness_script = Some(two_of_three);
+
+        // Alice alone can not finalize the multisig inputs.
+        assert_eq!(sign(&mut psbt, &[alice])?, 4);
+        assert!(matches!(
+            finalize_input(&mut psbt, 2),
+            Err(PsbtError::CanNotFinalize(2, _))
+        ));
+
+        assert_eq!(sign(&mut psbt, &[carol])?, 2);
+        finalize(&mut psbt)?;
+        assert!(psbt
+            .inputs
+            .iter()
+            .all(|input| input.partial_sigs.is_empty()));
+        assert!(psbt.inputs[0].final_script_sig.is_none());
+        assert!(psbt.inputs[3].final_script_sig.is_some());
+        assert_eq!(
+            psbt.inputs[3].final_script_witness.as_ref().unwrap().len(),
+            4
+        );
+
+        let tx = extract(&psbt)?;
+        assert_eq!(Transaction::deserialize(&tx.serialize())?, tx);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_legacy_inputs() -> color_eyre::Result<()> {
+        let (alice, bob) = (key("alice"), key("bob"));
+        let p2pkh = [
+            vec![0x76, 0xa9, 0x14],
+            alice.public_key().hash160().to_vec(),
+            vec![0x88, 0xac],
+        ]
+        .concat();
+        let two_of_two = multisig(&[&alice, &bob]);
+
+        // Legacy inputs need the whole previous transaction.
+        let previous = Transaction {
+            version: 2,
+            inputs: vec![],
+            outputs: vec![
+                TxOut {
+                    value: 60_000,
+                    script_pubkey: p2pkh,
+                },
+                TxOut {
+                    value: 40_000,
+                    script_pubkey: p2sh(&two_of_two),
+                },
+            ],
+            lock_time: 0,
+        };
+        let mut psbt = psbt_spending(&previous.outputs);
+        for (vout, input) in psbt.inputs.iter_mut().enumerate() {
+            input.previous_output = OutPoint {
+                txid: previous.txid(),
+                vout: vout as u32,
+            };
+            input.witness_utxo = None;
+            input.non_witness_utxo = Some(previous.clone());
+        }
+        psbt.inputs[1].redeem_script = Some(two_of_two);
+
+        sign(&mut psbt, &[alice, bob])?;
+        finalize(&mut psbt)?;
+        let tx = extract(&psbt)?;
+        assert!(!tx.has_witness());
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_taproot_inputs() -> color_eyre::Result<()> {
+        let (alice, bob) = (key("alice"), key("bob"));
+
+        // Key path of a BIP86 output.
+        let key_path_output = taproot_output_key(&alice.public_key().x_only(), None)?;
+
+        // Script path of `<bob> OP_CHECKSIG`, next to an unrelated leaf.
+        let leaf = [vec![0x20], bob.public_key().x_only().to_vec(), vec![0xac]].concat();
+        let sibling = tap_leaf_hash(TAPSCRIPT_LEAF_VERSION, &[0x51]);
+        let merkle_root = tap_branch_hash(&tap_leaf_hash(TAPSCRIPT_LEAF_VERSION, &leaf), &sibling);
+        let internal_key = key("carol").public_key().x_only();
+        // The control block carries the parity of the output key.
+        let (output_key, parity) = tweak_public_key(&internal_key, Some(&merkle_root))?;
+        let control_block = [
+            vec![TAPSCRIPT_LEAF_VERSION | parity.to_u8()],
+            internal_key.to_vec(),
+            sibling.to_vec(),
+        ]
+        .concat();
+
+        let p2tr = |key: [u8; 32]| [vec![0x51, 0x20], key.to_vec()].concat();
+        let mut psbt = psbt_spending(&[
+            TxOut {
+                value: 50_000,
+                script_pubkey: p2tr(key_path_output),
+            },
+            TxOut {
+                value: 50_000,
+                script_pubkey: p2tr(output_key),
+            },
+        ]);
+        psbt.inputs[1].tap_internal_key = Some(internal_key);
+        psbt.inputs[1].tap_merkle_root = Some(merkle_root);
+        psbt.inputs[1]
+            .tap_leaf_scripts
+            .insert(control_block, (leaf, TAPSCRIPT_LEAF_VERSION));
+
+        // Alice signs the BIP86 key path, Bob the leaf of the second input.
+        assert_eq!(sign(&mut psbt, &[alice, bob])?, 2);
+        assert!(psbt.inputs[0].tap_key_sig.is_some());
+        assert_eq!(psbt.inputs[1].tap_script_sigs.len(), 1);
+
+        finalize(&mut psbt)?;
+        assert_eq!(
+            psbt.inputs[1].final_script_witness.as_ref().unwrap().len(),
+            3
+        );
+        extract(&psbt)?;
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_bad_signature_is_not_finalized() -> color_eyre::Result<()> {
+        let alice = key("alice");
+        let p2wpkh = [vec![0x00, 0x14], alice.public_key().hash160().to_vec()].concat();
+        let mut psbt = psbt_spending(&[TxOut {
+            value: 50_000,
+            script_pubkey: p2wpkh,
+        }]);
+        sign(&mut psbt, &[alice])?;
+
+        // Signature of another amount.
+        psbt.inputs[0].witness_utxo.as_mut().unwrap().value += 1;
+        assert!(matches!(
+            finalize(&mut psbt),
+            Err(PsbtError::VerifyFailed { input: 0, .. })
+        ));
+        assert!(!psbt.inputs[0].is_final());
+        assert_eq!(extract(&psbt), Err(PsbtError::NotFinalized(0)));
+        Ok(())
+    }
+}
@@ -0,0 +1,385 @@
+// ==================================================
+// PSBT (BIP174 version 0 / BIP370 version 2)
+// ==================================================
+//
+// A PSBT is a transaction under construction plus everything each participant needs to sign
+// it : the outputs being spent, redeem / witness scripts, key origins and the signatures
+// collected so far. The roles are :
+// - creator   : `Psbt::new` / `Psbt::from_unsigned_tx`
+// - updater   : fills in UTXOs, scripts and key origins (`Input` fields)
+// - signer    : `sign::sign` adds partial signatures with local keys
+// - finalizer : `finalize::finalize` builds scriptSig / witness and checks them with bee-vm
+// - extractor : `finalize::extract` returns the network transaction
+//
+// Version 0 stores the unsigned transaction in the global map, version 2 stores the outpoint,
+// sequence, amount and script of every input / output in their own maps. Both are read into
+// the same model, the version only matters when serializing.
+
+pub mod analyze;
+pub mod finalize;
+mod serialize;
+pub mod sign;
+
+use crate::bip32::DerivationPath;
+use crate::errors::PsbtError;
+use bee_vm::transaction::{OutPoint, Transaction, TxIn, TxOut, SEQUENCE_FINAL};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Locktimes below this value are block heights, above it are unix timestamps.
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+/// Master key fingerprint and derivation path of a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySource {
+    pub fingerprint: [u8; 4],
+    pub path: DerivationPath,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Psbt {
+    /// 0 (BIP174) or 2 (BIP370).
+    pub version: u32,
+    pub tx_version: i32,
+    /// Locktime of a version 0 PSBT, or the one used when no input requires a locktime.
+    pub fallback_locktime: Option<u32>,
+    /// BIP370 flags : inputs modifiable (bit 0), outputs modifiable (bit 1), SIGHASH_SINGLE (bit 2).
+    pub tx_modifiable: Option<u8>,
+    /// Serialized extended public keys and their origin.
+    pub xpubs: BTreeMap<Vec<u8>, KeySource>,
+    pub inputs: Vec<Input>,
+    pub outputs: Vec<Output>,
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Input {
+    pub previous_output: OutPoint,
+    pub sequence: Option<u32>,
+    pub required_time_locktime: Option<u32>,
+    pub required_height_locktime: Option<u32>,
+    /// Full transaction holding the spent output (needed for legacy inputs).
+    pub non_witness_utxo: Option<Transaction>,
+    /// Spent output alone (enough for segwit inputs).
+    pub witness_utxo: Option<TxOut>,
+    /// ECDSA signatures (with sighash byte) by public key.
+    pub partial_sigs: BTreeMap<Vec<u8>, Vec<u8>>,
+    pub sighash_type: Option<u32>,
+    pub redeem_script: Option<Vec<u8>>,
+    pub witness_script: Option<Vec<u8>>,
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>,
+    pub final_script_sig: Option<Vec<u8>>,
+    pub final_script_witness: Option<Vec<Vec<u8>>>,
+    pub tap_key_sig: Option<Vec<u8>>,
+    /// Schnorr signatures by (x-only key, leaf hash).
+    pub tap_script_sigs: BTreeMap<([u8; 32], [u8; 32]), Vec<u8>>,
+    /// Leaf script and leaf version by control block.
+    pub tap_leaf_scripts: BTreeMap<Vec<u8>, (Vec<u8>, u8)>,
+    pub tap_internal_key: Option<[u8; 32]>,
+    pub tap_merkle_root: Option<[u8; 32]>,
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Output {
+    pub amount: u64,
+    pub script_pubkey: Vec<u8>,
+    pub redeem_script: Option<Vec<u8>>,
+    pub witness_script: Option<Vec<u8>>,
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>,
+    pub tap_internal_key: Option<[u8; 32]>,
+    /// Serialized BIP371 tap tree, kept as it is.
+    pub tap_tree: Option<Vec<u8>>,
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Psbt {
+    /// An empty PSBT of the given version (0 or 2) for a transaction of `tx_version`.
+    pub fn new(version: u32, tx_version: i32) -> Result<Psbt, PsbtError> {
+        if version != 0 && version != 2 {
+            return Err(PsbtError::UnsupportedVersion(version));
+        }
+        Ok(Psbt {
+            version,
+            tx_version,
+            ..Default::default()
+        })
+    }
+
+    /// Creator role : wraps a transaction without scriptSigs and witnesses.
+    pub fn from_unsigned_tx(tx: &Transaction, version: u32) -> Result<Psbt, PsbtError> {
+        if tx
+            .inputs
+            .iter()
+            .any(|input| !input.script_sig.is_empty() || !input.witness.is_empty())
+        {
+            return Err(PsbtError::SignedUnsignedTx);
+        }
+        let mut psbt = Psbt::new(version, tx.version)?;
+        psbt.fallback_locktime = Some(tx.lock_time);
+        psbt.inputs = tx
+            .inputs
+            .iter()
+            .map(|input| Input {
+                previous_output: input.previous_output,
+                sequence: Some(input.sequence),
+                ..Default::default()
+            })
+            .collect();
+        psbt.outputs = tx
+            .outputs
+            .iter()
+            .map(|output| Output {
+                amount: output.value,
+                script_pubkey: output.script_pubkey.clone(),
+                ..Default::default()
+            })
+            .collect();
+        Ok(psbt)
+    }
+
+    /// Transaction being signed, without any scriptSig or witness.
+    pub fn unsigned_tx(&self) -> Result<Transaction, PsbtError> {
+        Ok(self.build_tx(self.lock_time()?))
+    }
+
+    fn build_tx(&self, lock_time: u32) -> Transaction {
+        Transaction {
+            version: self.tx_version,
+            inputs: self
+                .inputs
+                .iter()
+                .map(|input| {
+                    TxIn::new(
+                        input.previous_output,
+                        input.sequence.unwrap_or(SEQUENCE_FINAL),
+                    )
+                })
+                .collect(),
+            outputs: self
+                .outputs
+                .iter()
+                .map(|output| TxOut {
+                    value: output.amount,
+                    script_pubkey: output.script_pubkey.clone(),
+                })
+                .collect(),
+            lock_time,
+        }
+    }
+
+    /// BIP370 locktime : the largest required height (or time) when inputs require one,
+    /// heights being preferred when every input accepts them, the fallback locktime otherwise.
+    pub fn lock_time(&self) -> Result<u32, PsbtError> {
+        if self.version == 0 {
+            return Ok(self.fallback_locktime.unwrap_or(0));
+        }
+        let requiring: Vec<&Input> = self
+            .inputs
+            .iter()
+            .filter(|input| {
+                input.required_height_locktime.is_some() || input.required_time_locktime.is_some()
+            })
+            .collect();
+        if requiring.is_empty() {
+            return Ok(self.fallback_locktime.unwrap_or(0));
+        }
+
+        if requiring
+            .iter()
+            .all(|input| input.required_height_locktime.is_some())
+        {
+            return Ok(requiring
+                .iter()
+                .filter_map(|input| input.required_height_locktime)
+                .max()
+                .unwrap_or(0));
+        }
+        if requiring
+            .iter()
+            .all(|input| input.required_time_locktime.is_some())
+        {
+            return Ok(requiring
+                .iter()
+                .filter_map(|input| input.required_time_locktime)
+                .max()
+                .unwrap_or(0));
+        }
+        Err(PsbtError::LocktimeConflict)
+    }
+
+    /// Outputs spent by every input, when all of them are known.
+    pub fn spent_outputs(&self) -> Result<Vec<TxOut>, PsbtError> {
+        (0..self.inputs.len())
+            .map(|index| self.utxo(index))
+            .collect()
+    }
+
+    /// Output spent by an input, from its witness UTXO or its non witness UTXO.
+    pub fn utxo(&self, index: usize) -> Result<TxOut, PsbtError> {
+        let input = self
+            .inputs
+            .get(index)
+            .ok_or(PsbtError::InputIndexOutOfRange(index))?;
+        if let Some(tx) = &input.non_witness_utxo {
+            if tx.txid() != input.previous_output.txid {
+                return Err(PsbtError::UtxoMismatch(index));
+            }
+            return tx
+                .outputs
+                .get(input.previous_output.vout as usize)
+                .cloned()
+                .ok_or(PsbtError::UtxoMismatch(index));
+        }
+        input
+            .witness_utxo
+            .clone()
+            .ok_or(PsbtError::MissingUtxo(index))
+    }
+
+    /// Converts between version 0 and version 2, the unsigned transaction stays the same.
+    pub fn convert(&self, version: u32) -> Result<Psbt, PsbtError> {
+        if version != 0 && version != 2 {
+            return Err(PsbtError::UnsupportedVersion(version));
+        }
+        let mut psbt = self.clone();
+        if version == 0 {
+            // Version 0 has no per input locktime requirements, the computed locktime is kept.
+            psbt.fallback_locktime = Some(self.lock_time()?);
+            psbt.tx_modifiable = None;
+            for input in psbt.inputs.iter_mut() {
+                input.required_height_locktime = None;
+                input.required_time_locktime = None;
+                input.sequence = Some(input.sequence.unwrap_or(SEQUENCE_FINAL));
+            }
+        }
+        if version == 2 {
+            // Unknown fields of a version 0 PSBT using version 2 types would not parse anymore.
+            psbt.unknown
+                .retain(|key, _| !(0x02..=0x06).contains(&key[0]));
+            for input in psbt.inputs.iter_mut() {
+                input
+                    .unknown
+                    .retain(|key, _| !(0x0e..=0x12).contains(&key[0]));
+            }
+            for output in psbt.outputs.iter_mut() {
+                output
+                    .unknown
+                    .retain(|key, _| !(0x03..=0x04).contains(&key[0]));
+            }
+        }
+        psbt.version = version;
+        Ok(psbt)
+    }
+}
+
+impl Input {
+    pub fn is_final(&self) -> bool {
+        self.final_script_sig.is_some() || self.final_script_witness.is_some()
+    }
+}
+
+impl fmt::Display for KeySource {
+    /// Descriptor key origin form : `d34db33f/84'/0'/0'`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.to_string();
+        write!(
+            f,
+            "{}{}",
+            hex::encode(self.fingerprint),
+            path.strip_prefix('m').unwrap_or(&path)
+        )
+    }
+}
+
+impl FromStr for KeySource {
+    type Err = PsbtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || PsbtError::InvalidValue(0x06, format!("key origin {}", s));
+        let (fingerprint, path) = s.split_once('/').unwrap_or((s, ""));
+        let fingerprint = hex::decode(fingerprint)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(invalid)?;
+        let path = format!("m/{}", path)
+            .trim_end_matches('/')
+            .parse::<DerivationPath>()
+            .map_err(|_| invalid())?;
+        Ok(KeySource { fingerprint, path })
+    }
+}
+
+/// Parses a descriptor style key with origin, `[d34db33f/84'/0'/0'/0/0]02abcd...`.
+pub fn parse_key_with_origin(key: &str) -> Result<(Vec<u8>, KeySource), PsbtError> {
+    let invalid = || PsbtError::InvalidValue(0x06, format!("key with origin {}", key));
+    let (origin, public_key) = key
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .ok_or_else(invalid)?;
+    let public_key = hex::decode(public_key).map_err(|_| invalid())?;
+    Ok((public_key, origin.parse()?))
+}
+
+#[cfg(test)]
+mod test_psbt {
+    use crate::errors::PsbtError;
+    use crate::psbt::{parse_key_with_origin, Input, Psbt};
+    use bee_vm::transaction::Transaction;
+    use rstest::rstest;
+
+    const LOCKTIME: u32 = 1_700_000_000;
+
+    // BIP143 native P2WPKH example, unsigned.
+    const UNSIGNED: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+    #[rstest]
+    #[case(0)]
+    #[case(2)]
+    fn test_unsigned_tx_round_trip(#[case] version: u32) -> color_eyre::Result<()> {
+        let tx = Transaction::from_hex(UNSIGNED)?;
+        let psbt = Psbt::from_unsigned_tx(&tx, version)?;
+        assert_eq!(psbt.unsigned_tx()?, tx);
+        assert_eq!(psbt.convert(2 - version)?.unsigned_tx()?, tx);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(vec![(Some(100), None), (Some(200), Some(LOCKTIME))], Ok(200))]
+    #[case(vec![(None, Some(LOCKTIME)), (Some(200), Some(LOCKTIME + 1))], Ok(LOCKTIME + 1))]
+    #[case(vec![(None, Some(LOCKTIME)), (Some(200), None)], Err(PsbtError::LocktimeConflict))]
+    #[case(vec![(None, None)], Ok(17))]
+    fn test_lock_time(
+        #[case] requirements: Vec<(Option<u32>, Option<u32>)>,
+        #[case] expected: Result<u32, PsbtError>,
+    ) -> color_eyre::Result<()> {
+        let mut psbt = Psbt::new(2, 2)?;
+        psbt.fallback_locktime = Some(17);
+        psbt.inputs = requirements
+            .into_iter()
+            .map(|(height, time)| Input {
+                required_height_locktime: height,
+                required_time_locktime: time,
+                ..Default::default()
+            })
+            .collect();
+        assert_eq!(psbt.lock_time(), expected);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_key_origin() -> color_eyre::Result<()> {
+        let (public_key, origin) = parse_key_with_origin(
+            "[d34db33f/84'/0'/0'/0/1]025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357",
+        )?;
+        assert_eq!(public_key.len(), 33);
+        assert_eq!(origin.to_string(), "d34db33f/84'/0'/0'/0/1");
+        assert_eq!(
+            parse_key_with_origin("[d34db33f]02ab")?.1.to_string(),
+            "d34db33f"
+        );
+        assert!(parse_key_with_origin("d34db33f/0]02ab").is_err());
+        Ok(())
+    }
+}
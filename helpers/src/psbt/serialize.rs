@@ -0,0 +1,807 @@
+// PSBT binary format : magic | global map | one map per input | one map per output.
+// A map is a list of <compact size key length | key type | key data | compact size value>
+// pairs ended by a 0x00 byte. Fields are written in key order, so a parsed PSBT serializes
+// back to the same bytes.
+
+use crate::base64;
+use crate::bip32::{ChildNumber, DerivationPath};
+use crate::errors::PsbtError;
+use crate::keys::PublicKey;
+use crate::psbt::{Input, KeySource, Output, Psbt, LOCKTIME_THRESHOLD};
+use bee_vm::errors::TransactionError;
+use bee_vm::transaction::encode::{write_bytes, write_compact_size, Reader};
+use bee_vm::transaction::{OutPoint, Transaction, TxOut};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+const MAGIC: [u8; 5] = [0x70, 0x73, 0x62, 0x74, 0xff];
+
+// Global types
+const GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const GLOBAL_XPUB: u8 = 0x01;
+const GLOBAL_TX_VERSION: u8 = 0x02;
+const GLOBAL_FALLBACK_LOCKTIME: u8 = 0x03;
+const GLOBAL_INPUT_COUNT: u8 = 0x04;
+const GLOBAL_OUTPUT_COUNT: u8 = 0x05;
+const GLOBAL_TX_MODIFIABLE: u8 = 0x06;
+const GLOBAL_VERSION: u8 = 0xfb;
+
+// Input types
+const IN_NON_WITNESS_UTXO: u8 = 0x00;
+const IN_WITNESS_UTXO: u8 = 0x01;
+const IN_PARTIAL_SIG: u8 = 0x02;
+const IN_SIGHASH_TYPE: u8 = 0x03;
+const IN_REDEEM_SCRIPT: u8 = 0x04;
+const IN_WITNESS_SCRIPT: u8 = 0x05;
+const IN_BIP32_DERIVATION: u8 = 0x06;
+const IN_FINAL_SCRIPTSIG: u8 = 0x07;
+const IN_FINAL_SCRIPTWITNESS: u8 = 0x08;
+const IN_PREVIOUS_TXID: u8 = 0x0e;
+const IN_OUTPUT_INDEX: u8 = 0x0f;
+const IN_SEQUENCE: u8 = 0x10;
+const IN_REQUIRED_TIME_LOCKTIME: u8 = 0x11;
+const IN_REQUIRED_HEIGHT_LOCKTIME: u8 = 0x12;
+const IN_TAP_KEY_SIG: u8 = 0x13;
+const IN_TAP_SCRIPT_SIG: u8 = 0x14;
+const IN_TAP_LEAF_SCRIPT: u8 = 0x15;
+const IN_TAP_INTERNAL_KEY: u8 = 0x17;
+const IN_TAP_MERKLE_ROOT: u8 = 0x18;
+
+// Output types
+const OUT_REDEEM_SCRIPT: u8 = 0x00;
+const OUT_WITNESS_SCRIPT: u8 = 0x01;
+const OUT_BIP32_DERIVATION: u8 = 0x02;
+const OUT_AMOUNT: u8 = 0x03;
+const OUT_SCRIPT: u8 = 0x04;
+const OUT_TAP_INTERNAL_KEY: u8 = 0x05;
+const OUT_TAP_TREE: u8 = 0x06;
+
+type Map = BTreeMap<Vec<u8>, Vec<u8>>;
+
+impl Psbt {
+    pub fn deserialize(bytes: &[u8]) -> Result<Psbt, PsbtError> {
+        let rest = bytes.strip_prefix(&MAGIC).ok_or(PsbtError::InvalidMagic)?;
+        let mut reader = Reader::new(rest);
+
+        let global = read_map(&mut reader)?;
+        let version = match global.get([GLOBAL_VERSION].as_slice()) {
+            Some(value) => read_u32(GLOBAL_VERSION, value)?,
+            None => 0,
+        };
+        let mut psbt = Psbt::new(version, 0)?;
+
+        let mut unsigned_tx = None;
+        let mut input_count = None;
+        let mut output_count = None;
+        for (key, value) in global {
+            let (key_type, key_data) = (key[0], &key[1..]);
+            match key_type {
+                GLOBAL_UNSIGNED_TX => {
+                    expect_v0(key_type, version)?;
+                    expect_no_key_data(key_type, key_data)?;
+                    let tx = Transaction::deserialize(&value)?;
+                    if tx
+                        .inputs
+                        .iter()
+                        .any(|input| !input.script_sig.is_empty() || !input.witness.is_empty())
+                    {
+                        return Err(PsbtError::SignedUnsignedTx);
+                    }
+                    unsigned_tx = Some(tx);
+                }
+                GLOBAL_XPUB => {
+                    if key_data.len() != 78 {
+                        return Err(PsbtError::InvalidKey(
+                            key_type,
+                            format!("expected a 78 bytes extended key, found {}", key_data.len()),
+                        ));
+                    }
+                    psbt.xpubs
+                        .insert(key_data.to_vec(), read_key_source(key_type, &value)?);
+                }
+                // Version 2 only types are unknown fields of a version 0 PSBT (BIP174 predates them).
+                GLOBAL_TX_VERSION if version == 2 => {
+                    expect_no_key_data(key_type, key_data)?;
+                    psbt.tx_version = read_u32(key_type, &value)? as i32;
+                }
+                GLOBAL_FALLBACK_LOCKTIME if version == 2 => {
+                    expect_no_key_data(key_type, key_data)?;
+                    psbt.fallback_locktime = Some(read_u32(key_type, &value)?);
+                }
+                GLOBAL_INPUT_COUNT | GLOBAL_OUTPUT_COUNT if version == 2 => {
+                    expect_no_key_data(key_type, key_data)?;
+                    let count = read_compact_size(key_type, &value)?;
+                    if key_type == GLOBAL_INPUT_COUNT {
+                        input_count = Some(count);
+                    } else {
+                        output_count = Some(count);
+                    }
+                }
+                GLOBAL_TX_MODIFIABLE if version == 2 => {
+                    expect_no_key_data(key_type, key_data)?;
+                    match value.as_slice() {
+                        [flags] => psbt.tx_modifiable = Some(*flags),
+                        _ => return Err(invalid_length(key_type, 1, value.len())),
+                    }
+                }
+                GLOBAL_VERSION => expect_no_key_data(key_type, key_data)?,
+                _ => {
+                    psbt.unknown.insert(key, value);
+                }
+            }
+        }
+
+        match &unsigned_tx {
+            Some(tx) => {
+                psbt.tx_version = tx.version;
+                psbt.fallback_locktime = Some(tx.lock_time);
+            }
+            None if version == 0 => {
+                return Err(PsbtError::MissingField(
+                    "PSBT_GLOBAL_UNSIGNED_TX".to_string(),
+                ))
+            }
+            None => {}
+        }
+        let input_count = match &unsigned_tx {
+            Some(tx) => tx.inputs.len() as u64,
+            None => input_count.ok_or(PsbtError::MissingField(
+                "PSBT_GLOBAL_INPUT_COUNT".to_string(),
+            ))?,
+        };
+        let output_count = match &unsigned_tx {
+            Some(tx) => tx.outputs.len() as u64,
+            None => output_count.ok_or(PsbtError::MissingField(
+                "PSBT_GLOBAL_OUTPUT_COUNT".to_string(),
+            ))?,
+        };
+
+        for index in 0..input_count as usize {
+            let mut input = parse_input(read_map(&mut reader)?, version)?;
+            if let Some(tx) = &unsigned_tx {
+                input.previous_output = tx.inputs[index].previous_output;
+                input.sequence = Some(tx.inputs[index].sequence);
+            }
+            psbt.inputs.push(input);
+        }
+        for index in 0..output_count as usize {
+            let mut output = parse_output(read_map(&mut reader)?, version)?;
+            if let Some(tx) = &unsigned_tx {
+                output.amount = tx.outputs[index].value;
+                output.script_pubkey = tx.outputs[index].script_pubkey.clone();
+            }
+            psbt.outputs.push(output);
+        }
+
+        if reader.remaining() != 0 {
+            return Err(TransactionError::TrailingBytes(reader.remaining()).into());
+        }
+        Ok(psbt)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buffer = MAGIC.to_vec();
+
+        let mut global = self.unknown.clone();
+        if self.version == 0 {
+            let tx = self.build_tx(self.fallback_locktime.unwrap_or(0));
+            global.insert(vec![GLOBAL_UNSIGNED_TX], tx.serialize_legacy());
+        } else {
+            global.insert(
+                vec![GLOBAL_TX_VERSION],
+                self.tx_version.to_le_bytes().to_vec(),
+            );
+            if let Some(lock_time) = self.fallback_locktime {
+                global.insert(
+                    vec![GLOBAL_FALLBACK_LOCKTIME],
+                    lock_time.to_le_bytes().to_vec(),
+                );
+            }
+            global.insert(
+                vec![GLOBAL_INPUT_COUNT],
+                compact_size(self.inputs.len() as u64),
+            );
+            global.insert(
+                vec![GLOBAL_OUTPUT_COUNT],
+                compact_size(self.outputs.len() as u64),
+            );
+            if let Some(flags) = self.tx_modifiable {
+                global.insert(vec![GLOBAL_TX_MODIFIABLE], vec![flags]);
+            }
+            global.insert(vec![GLOBAL_VERSION], self.version.to_le_bytes().to_vec());
+        }
+        for (xpub, source) in &self.xpubs {
+            global.insert(key(GLOBAL_XPUB, xpub), key_source_bytes(source));
+        }
+        write_map(&mut buffer, &global);
+
+        for input in &self.inputs {
+            write_map(&mut buffer, &input_map(input, self.version));
+        }
+        for output in &self.outputs {
+            write_map(&mut buffer, &output_map(output, self.version));
+        }
+        buffer
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.serialize())
+    }
+
+    pub fn from_base64(psbt: &str) -> Result<Psbt, PsbtError> {
+        Psbt::deserialize(&base64::decode(psbt.trim())?)
+    }
+}
+
+impl FromStr for Psbt {
+    type Err = PsbtError;
+
+    /// Reads a PSBT in base64 (the usual RPC form) or in hex.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match hex::decode(s) {
+            Ok(bytes) if bytes.starts_with(&MAGIC) => Psbt::deserialize(&bytes),
+            _ => Psbt::from_base64(s),
+        }
+    }
+}
+
+impl fmt::Display for Psbt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_base64())
+    }
+}
+
+fn parse_input(map: Map, version: u32) -> Result<Input, PsbtError> {
+    let mut input = Input::default();
+    let mut previous_txid = None;
+    let mut output_index = None;
+    for (key, value) in map {
+        let (key_type, key_data) = (key[0], &key[1..]);
+        match key_type {
+            IN_NON_WITNESS_UTXO => {
+                expect_no_key_data(key_type, key_data)?;
+                input.non_witness_utxo = Some(Transaction::deserialize(&value)?);
+            }
+            IN_WITNESS_UTXO => {
+                expect_no_key_data(key_type, key_data)?;
+                let mut reader = Reader::new(&value);
+                let output = TxOut {
+                    value: reader.read_u64()?,
+                    script_pubkey: reader.read_bytes()?,
+                };
+                if reader.remaining() != 0 {
+                    return Err(TransactionError::TrailingBytes(reader.remaining()).into());
+                }
+                input.witness_utxo = Some(output);
+            }
+            IN_PARTIAL_SIG => {
+                check_public_key(key_type, key_data)?;
+                input.partial_sigs.insert(key_data.to_vec(), value);
+            }
+            IN_SIGHASH_TYPE => {
+                expect_no_key_data(key_type, key_data)?;
+                input.sighash_type = Some(read_u32(key_type, &value)?);
+            }
+            IN_REDEEM_SCRIPT => {
+                expect_no_key_data(key_type, key_data)?;
+                input.redeem_script = Some(value);
+            }
+            IN_WITNESS_SCRIPT => {
+                expect_no_key_data(key_type, key_data)?;
+                input.witness_script = Some(value);
+            }
+            IN_BIP32_DERIVATION => {
+                check_public_key(key_type, key_data)?;
+                input
+                    .bip32_derivation
+                    .insert(key_data.to_vec(), read_key_source(key_type, &value)?);
+            }
+            IN_FINAL_SCRIPTSIG => {
+                expect_no_key_data(key_type, key_data)?;
+                input.final_script_sig = Some(value);
+            }
+            IN_FINAL_SCRIPTWITNESS => {
+                expect_no_key_data(key_type, key_data)?;
+                let mut reader = Reader::new(&value);
+                let items = reader.read_compact_size()?;
+                let witness = (0..items)
+                    .map(|_| reader.read_bytes())
+                    .collect::<Result<Vec<_>, _>>()?;
+                if reader.remaining() != 0 {
+                    return Err(TransactionError::TrailingBytes(reader.remaining()).into());
+                }
+                input.final_script_witness = Some(witness);
+            }
+            IN_PREVIOUS_TXID if version == 2 => {
+                expect_no_key_data(key_type, key_data)?;
+                previous_txid = Some(read_array::<32>(key_type, &value)?);
+            }
+            IN_OUTPUT_INDEX if version == 2 => {
+                expect_no_key_data(key_type, key_data)?;
+                output_index = Some(read_u32(key_type, &value)?);
+            }
+            IN_SEQUENCE if version == 2 => {
+                expect_no_key_data(key_type, key_data)?;
+                input.sequence = Some(read_u32(key_type, &value)?);
+            }
+            IN_REQUIRED_TIME_LOCKTIME if version == 2 => {
+                expect_no_key_data(key_type, key_data)?;
+                let lock_time = read_u32(key_type, &value)?;
+                if lock_time < LOCKTIME_THRESHOLD {
+                    return Err(PsbtError::InvalidValue(
+                        key_type,
+                        format!("{} is not a timestamp", lock_time),
+                    ));
+                }
+                input.required_time_locktime = Some(lock_time);
+            }
+            IN_REQUIRED_HEIGHT_LOCKTIME if version == 2 => {
+                expect_no_key_data(key_type, key_data)?;
+                let lock_time = read_u32(key_type, &value)?;
+                if lock_time == 0 || lock_time >= LOCKTIME_THRESHOLD {
+                    return Err(PsbtError::InvalidValue(
+                        key_type,
+                        format!("{} is not a block height", lock_time),
+                    ));
+                }
+                input.required_height_locktime = Some(lock_time);
+            }
+            IN_TAP_KEY_SIG => {
+                expect_no_key_data(key_type, key_data)?;
+                check_schnorr_signature(key_type, &value)?;
+                input.tap_key_sig = Some(value);
+            }
+            IN_TAP_SCRIPT_SIG => {
+                if key_data.len() != 64 {
+                    return Err(PsbtError::InvalidKey(
+                        key_type,
+                        "expected an x-only key and a leaf hash".to_string(),
+                    ));
+                }
+                let x_only = read_x_only(key_type, &key_data[..32])?;
+                check_schnorr_signature(key_type, &value)?;
+                input
+                    .tap_script_sigs
+                    .insert((x_only, key_data[32..].try_into().unwrap()), value);
+            }
+            IN_TAP_LEAF_SCRIPT => {
+                if key_data.len() < 33
+                    || (key_data.len() - 33) % 32 != 0
+                    || key_data.len() > 33 + 128 * 32
+                {
+                    return Err(PsbtError::InvalidKey(
+                        key_type,
+                        format!("invalid control block length {}", key_data.len()),
+                    ));
+                }
+                let (leaf_version, script) = value
+                    .split_last()
+                    .ok_or(PsbtError::InvalidValue(key_type, "empty leaf".to_string()))?;
+                input
+                    .tap_leaf_scripts
+                    .insert(key_data.to_vec(), (script.to_vec(), *leaf_version));
+            }
+            IN_TAP_INTERNAL_KEY => {
+                expect_no_key_data(key_type, key_data)?;
+                input.tap_internal_key = Some(read_x_only(key_type, &value)?);
+            }
+            IN_TAP_MERKLE_ROOT => {
+                expect_no_key_data(key_type, key_data)?;
+                input.tap_merkle_root = Some(read_array::<32>(key_type, &value)?);
+            }
+            _ => {
+                input.unknown.insert(key, value);
+            }
+        }
+    }
+
+    if version == 2 {
+        input.previous_output = OutPoint {
+            txid: previous_txid
+                .ok_or(PsbtError::MissingField("PSBT_IN_PREVIOUS_TXID".to_string()))?,
+            vout: output_index
+                .ok_or(PsbtError::MissingField("PSBT_IN_OUTPUT_INDEX".to_string()))?,
+        };
+    }
+    Ok(input)
+}
+
+fn parse_output(map: Map, version: u32) -> Result<Output, PsbtError> {
+    let mut output = Output::default();
+    let mut amount = None;
+    let mut script = None;
+    for (key, value) in map {
+        let (key_type, key_data) = (key[0], &key[1..]);
+        match key_type {
+            OUT_REDEEM_SCRIPT => {
+                expect_no_key_data(key_type, key_data)?;
+                output.redeem_script = Some(value);
+            }
+            OUT_WITNESS_SCRIPT => {
+                expect_no_key_data(key_type, key_data)?;
+                output.witness_script = Some(value);
+            }
+            OUT_BIP32_DERIVATION => {
+                check_public_key(key_type, key_data)?;
+                output
+                    .bip32_derivation
+                    .insert(key_data.to_vec(), read_key_source(key_type, &value)?);
+            }
+            OUT_AMOUNT if version == 2 => {
+                expect_no_key_data(key_type, key_data)?;
+                amount = Some(u64::from_le_bytes(read_array::<8>(key_type, &value)?));
+            }
+            OUT_SCRIPT if version == 2 => {
+                expect_no_key_data(key_type, key_data)?;
+                script = Some(value);
+            }
+            OUT_TAP_INTERNAL_KEY => {
+                expect_no_key_data(key_type, key_data)?;
+                output.tap_internal_key = Some(read_x_only(key_type, &value)?);
+            }
+            OUT_TAP_TREE => {
+                expect_no_key_data(key_type, key_data)?;
+                output.tap_tree = Some(value);
+            }
+            _ => {
+                output.unknown.insert(key, value);
+            }
+        }
+    }
+
+    if version == 2 {
+        output.amount = amount.ok_or(PsbtError::MissingField("PSBT_OUT_AMOUNT".to_string()))?;
+        output.script_pubkey =
+            script.ok_or(PsbtError::MissingField("PSBT_OUT_SCRIPT".to_string()))?;
+    }
+    Ok(output)
+}
+
+fn input_map(input: &Input, version: u32) -> Map {
+    let mut map = input.unknown.clone();
+    if let Some(tx) = &input.non_witness_utxo {
+        map.insert(vec![IN_NON_WITNESS_UTXO], tx.serialize());
+    }
+    if let Some(output) = &input.witness_utxo {
+        let mut value = vec![];
+        output.serialize(&mut value);
+        map.insert(vec![IN_WITNESS_UTXO], value);
+    }
+    for (public_key, signature) in &input.partial_sigs {
+        map.insert(key(IN_PARTIAL_SIG, public_key), signature.clone());
+    }
+    if let Some(sighash_type) = input.sighash_type {
+        map.insert(vec![IN_SIGHASH_TYPE], sighash_type.to_le_bytes().to_vec());
+    }
+    if let Some(script) = &input.redeem_script {
+        map.insert(vec![IN_REDEEM_SCRIPT], script.clone());
+    }
+    if let Some(script) = &input.witness_script {
+        map.insert(vec![IN_WITNESS_SCRIPT], script.clone());
+    }
+    for (public_key, source) in &input.bip32_derivation {
+        map.insert(
+            key(IN_BIP32_DERIVATION, public_key),
+            key_source_bytes(source),
+        );
+    }
+    if let Some(script_sig) = &input.final_script_sig {
+        map.insert(vec![IN_FINAL_SCRIPTSIG], script_sig.clone());
+    }
+    if let Some(witness) = &input.final_script_witness {
+        let mut value = compact_size(witness.len() as u64);
+        for item in witness {
+            write_bytes(&mut value, item);
+        }
+        map.insert(vec![IN_FINAL_SCRIPTWITNESS], value);
+    }
+    if version == 2 {
+        map.insert(vec![IN_PREVIOUS_TXID], input.previous_output.txid.to_vec());
+        map.insert(
+            vec![IN_OUTPUT_INDEX],
+            input.previous_output.vout.to_le_bytes().to_vec(),
+        );
+        if let Some(sequence) = input.sequence {
+            map.insert(vec![IN_SEQUENCE], sequence.to_le_bytes().to_vec());
+        }
+        if let Some(lock_time) = input.required_time_locktime {
+            map.insert(
+                vec![IN_REQUIRED_TIME_LOCKTIME],
+                lock_time.to_le_bytes().to_vec(),
+            );
+        }
+        if let Some(lock_time) = input.required_height_locktime {
+            map.insert(
+                vec![IN_REQUIRED_HEIGHT_LOCKTIME],
+                lock_time.to_le_bytes().to_vec(),
+            );
+        }
+    }
+    if let Some(signature) = &input.tap_key_sig {
+        map.insert(vec![IN_TAP_KEY_SIG], signature.clone());
+    }
+    for ((x_only, leaf_hash), signature) in &input.tap_script_sigs {
+        let mut key_data = x_only.to_vec();
+        key_data.extend_from_slice(leaf_hash);
+        map.insert(key(IN_TAP_SCRIPT_SIG, &key_data), signature.clone());
+    }
+    for (control_block, (script, leaf_version)) in &input.tap_leaf_scripts {
+        let mut value = script.clone();
+        value.push(*leaf_version);
+        map.insert(key(IN_TAP_LEAF_SCRIPT, control_block), value);
+    }
+    if let Some(internal_key) = &input.tap_internal_key {
+        map.insert(vec![IN_TAP_INTERNAL_KEY], internal_key.to_vec());
+    }
+    if let Some(merkle_root) = &input.tap_merkle_root {
+        map.insert(vec![IN_TAP_MERKLE_ROOT], merkle_root.to_vec());
+    }
+    map
+}
+
+fn output_map(output: &Output, version: u32) -> Map {
+    let mut map = output.unknown.clone();
+    if let Some(script) = &output.redeem_script {
+        map.insert(vec![OUT_REDEEM_SCRIPT], script.clone());
+    }
+    if let Some(script) = &output.witness_script {
+        map.insert(vec![OUT_WITNESS_SCRIPT], script.clone());
+    }
+    for (public_key, source) in &output.bip32_derivation {
+        map.insert(
+            key(OUT_BIP32_DERIVATION, public_key),
+            key_source_bytes(source),
+        );
+    }
+    if version == 2 {
+        map.insert(vec![OUT_AMOUNT], output.amount.to_le_bytes().to_vec());
+        map.insert(vec![OUT_SCRIPT], output.script_pubkey.clone());
+    }
+    if let Some(internal_key) = &output.tap_internal_key {
+        map.insert(vec![OUT_TAP_INTERNAL_KEY], internal_key.to_vec());
+    }
+    if let Some(tree) = &output.tap_tree {
+        map.insert(vec![OUT_TAP_TREE], tree.clone());
+    }
+    map
+}
+
+/// Reads key-value pairs up to the 0x00 separator, a key may only appear once.
+fn read_map(reader: &mut Reader) -> Result<Map, PsbtError> {
+    let mut map = Map::new();
+    loop {
+        let key = reader.read_bytes()?;
+        if key.is_empty() {
+            return Ok(map);
+        }
+        let value = reader.read_bytes()?;
+        if map.contains_key(&key) {
+            return Err(PsbtError::DuplicateKey(hex::encode(&key)));
+        }
+        map.insert(key, value);
+    }
+}
+
+fn write_map(buffer: &mut Vec<u8>, map: &Map) {
+    for (key, value) in map {
+        write_bytes(buffer, key);
+        write_bytes(buffer, value);
+    }
+    buffer.push(0x00);
+}
+
+fn key(key_type: u8, key_data: &[u8]) -> Vec<u8> {
+    let mut key = vec![key_type];
+    key.extend_from_slice(key_data);
+    key
+}
+
+fn compact_size(value: u64) -> Vec<u8> {
+    let mut buffer = vec![];
+    write_compact_size(&mut buffer, value);
+    buffer
+}
+
+fn key_source_bytes(source: &KeySource) -> Vec<u8> {
+    let mut bytes = source.fingerprint.to_vec();
+    for child in &source.path.0 {
+        bytes.extend_from_slice(&child.0.to_le_bytes());
+    }
+    bytes
+}
+
+fn read_key_source(key_type: u8, value: &[u8]) -> Result<KeySource, PsbtError> {
+    if value.len() < 4 || !(value.len() - 4).is_multiple_of(4) {
+        return Err(PsbtError::InvalidValue(
+            key_type,
+            format!("invalid key origin length {}", value.len()),
+        ));
+    }
+    Ok(KeySource {
+        fingerprint: value[..4].try_into().unwrap(),
+        path: DerivationPath(
+            value[4..]
+                .chunks(4)
+                .map(|index| ChildNumber(u32::from_le_bytes(index.try_into().unwrap())))
+                .collect(),
+        ),
+    })
+}
+
+fn expect_no_key_data(key_type: u8, key_data: &[u8]) -> Result<(), PsbtError> {
+    match key_data.is_empty() {
+        true => Ok(()),
+        false => Err(PsbtError::InvalidKey(
+            key_type,
+            format!("unexpected key data {}", hex::encode(key_data)),
+        )),
+    }
+}
+
+fn expect_v0(key_type: u8, version: u32) -> Result<(), PsbtError> {
+    match version {
+        0 => Ok(()),
+        _ => Err(PsbtError::UnexpectedField(key_type, version)),
+    }
+}
+
+fn invalid_length(key_type: u8, expected: usize, found: usize) -> PsbtError {
+    PsbtError::InvalidValue(
+        key_type,
+        format!("expected {} bytes, found {}", expected, found),
+    )
+}
+
+fn read_array<const N: usize>(key_type: u8, value: &[u8]) -> Result<[u8; N], PsbtError> {
+    value
+        .try_into()
+        .map_err(|_| invalid_length(key_type, N, value.len()))
+}
+
+fn read_u32(key_type: u8, value: &[u8]) -> Result<u32, PsbtError> {
+    Ok(u32::from_le_bytes(read_array(key_type, value)?))
+}
+
+fn read_compact_size(key_type: u8, value: &[u8]) -> Result<u64, PsbtError> {
+    let mut reader = Reader::new(value);
+    let count = reader.read_compact_size()?;
+    match reader.remaining() {
+        0 => Ok(count),
+        _ => Err(PsbtError::InvalidValue(
+            key_type,
+            "trailing bytes after count".to_string(),
+        )),
+    }
+}
+
+fn check_public_key(key_type: u8, key_data: &[u8]) -> Result<(), PsbtError> {
+    PublicKey::from_slice(key_data)
+        .map(|_| ())
+        .map_err(|e| PsbtError::InvalidKey(key_type, e.to_string()))
+}
+
+fn read_x_only(key_type: u8, bytes: &[u8]) -> Result<[u8; 32], PsbtError> {
+    let x_only = read_array::<32>(key_type, bytes)?;
+    k256::schnorr::VerifyingKey::from_bytes(&x_only)
+        .map_err(|_| PsbtError::InvalidValue(key_type, "invalid x-only key".to_string()))?;
+    Ok(x_only)
+}
+
+fn check_schnorr_signature(key_type: u8, signature: &[u8]) -> Result<(), PsbtError> {
+    match signature.len() {
+        64 | 65 => Ok(()),
+        length => Err(PsbtError::InvalidValue(
+            key_type,
+            format!("invalid schnorr signature length {}", length),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test_serialize {
+    use crate::errors::PsbtError;
+    use crate::psbt::Psbt;
+    use rstest::rstest;
+
+    // BIP174 test vectors.
+    const P2PKH_FINAL_AND_P2SH_P2WPKH: &str = "70736274ff0100a00200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40000000000feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f4620b553fa095e721b9ee0efe9fa039cca459788ac000000000001076a47304402204759661797c01b036b25928948686218347d89864b719e1f7fcf57d1e511658702205309eabf56aa4d8891ffd111fdf1336f3a29da866d7f8486d75546ceedaf93190121035cdc61fc7ba971c0b501a646a2a83b102cb43881217ca682dc86e2d73fa882920001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01fb82308000000";
+    const P2PKH_SIGHASH: &str = "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000001030401000000000000";
+    const WITH_OUTPUT_FIELDS: &str = "70736274ff0100a00200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40000000000feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f4620b553fa095e721b9ee0efe9fa039cca459788ac00000000000100df0200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf6000000006a473044022070b2245123e6bf474d60c5b50c043d4c691a5d2435f09a34a7662a9dc251790a022001329ca9dacf280bdf30740ec0390422422c81cb45839457aeb76fc12edd95b3012102657d118d3357b8e0f4c2cd46db7b39f6d9c38d9a70abcb9b2de5dc8dbfe4ce31feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e13000001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01fb8230800220202ead596687ca806043edc3de116cdf29d5e9257c196cd055cf698c8d02bf24e9910b4a6ba670000008000000080020000800022020394f62be9df19952c5587768aeb7698061ad2c4a25c894f47d8c162b4d7213d0510b4a6ba6700000080010000800200008000";
+    const P2SH_P2WSH_PARTIAL_SIG: &str = "70736274ff0100550200000001279a2323a5dfb51fc45f220fa58b0fc13e1e3342792a85d7e36cd6333b5cbc390000000000ffffffff01a05aea0b000000001976a914ffe9c0061097cc3b636f2cb0460fa4fc427d2b4588ac0000000000010120955eea0b0000000017a9146345200f68d189e1adc0df1c4d16ea8f14c0dbeb87220203b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4646304302200424b58effaaa694e1559ea5c93bbfd4a89064224055cdf070b6771469442d07021f5c8eb0fea6516d60b8acb33ad64ede60e8785bfb3aa94b99bdf86151db9a9a010104220020771fd18ad459666dd49f3d564e3dbc42f4c84774e360ada16816a8ed488d5681010547522103b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd462103de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083bd52ae220603b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4610b4a6ba67000000800000008004000080220603de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083bd10b4a6ba670000008000000080050000800000";
+    const UNKNOWN_FIELD: &str = "70736274ff01003f0200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000ffffffff010000000000000000036a010000000000000a0f0102030405060708090f0102030405060708090a0b0c0d0e0f0000";
+    // BIP371 : taproot key path with key origin, and script path with leaves and signatures.
+    const TAPROOT_KEY_PATH: &str = "70736274ff010052020000000127744ababf3027fe0d6cf23a96eee2efb188ef52301954585883e69b6624b2420000000000ffffffff0148e6052a01000000160014768e1eeb4cf420866033f80aceff0f9720744969000000000001012b00f2052a010000002251205a2c2cf5b52cf31f83ad2e8da63ff03183ecd8f609c7510ae8a48e03910a07572116fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa2321900772b2da75600008001000080000000800100000000000000011720fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa232002202036b772a6db74d8753c98a827958de6c78ab3312109f37d3e0304484242ece73d818772b2da7540000800100008000000080000000000000000000";
+
+    #[rstest]
+    #[case(P2PKH_FINAL_AND_P2SH_P2WPKH)]
+    #[case(P2PKH_SIGHASH)]
+    #[case(WITH_OUTPUT_FIELDS)]
+    #[case(P2SH_P2WSH_PARTIAL_SIG)]
+    #[case(UNKNOWN_FIELD)]
+    #[case(TAPROOT_KEY_PATH)]
+    fn test_round_trip(#[case] psbt_hex: &str) -> color_eyre::Result<()> {
+        let psbt: Psbt = psbt_hex.parse()?;
+        assert_eq!(hex::encode(psbt.serialize()), psbt_hex);
+        assert_eq!(psbt.to_string().parse::<Psbt>()?, psbt);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(P2PKH_FINAL_AND_P2SH_P2WPKH)]
+    #[case(P2PKH_SIGHASH)]
+    #[case(WITH_OUTPUT_FIELDS)]
+    #[case(P2SH_P2WSH_PARTIAL_SIG)]
+    #[case(TAPROOT_KEY_PATH)]
+    fn test_convert(#[case] psbt_hex: &str) -> color_eyre::Result<()> {
+        // Version 2 holds the same transaction and converts back to the same bytes.
+        let psbt: Psbt = psbt_hex.parse()?;
+        let v2 = Psbt::deserialize(&psbt.convert(2)?.serialize())?;
+        assert_eq!(v2.version, 2);
+        assert_eq!(v2.unsigned_tx()?, psbt.unsigned_tx()?);
+        assert_eq!(hex::encode(v2.convert(0)?.serialize()), psbt_hex);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_fields() -> color_eyre::Result<()> {
+        let psbt: Psbt = P2SH_P2WSH_PARTIAL_SIG.parse()?;
+        let input = &psbt.inputs[0];
+        assert_eq!(input.partial_sigs.len(), 1);
+        assert_eq!(input.bip32_derivation.len(), 2);
+        assert_eq!(
+            input.bip32_derivation.values().next().unwrap().to_string(),
+            "b4a6ba67/0'/0'/4'"
+        );
+        assert_eq!(psbt.utxo(0)?.value, 199_909_013);
+
+        let psbt: Psbt = UNKNOWN_FIELD.parse()?;
+        assert_eq!(psbt.inputs[0].unknown.len(), 1);
+
+        let psbt: Psbt = P2PKH_SIGHASH.parse()?;
+        assert_eq!(psbt.inputs[0].sighash_type, Some(1));
+        assert_eq!(psbt.utxo(0)?.value, 200_000_000);
+        Ok(())
+    }
+
+    #[rstest]
+    // Raw transaction instead of a PSBT.
+    #[case("0200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf6000000006a473044022070b2245123e6bf474d60c5b50c043d4c691a5d2435f09a34a7662a9dc251790a022001329ca9dacf280bdf30740ec0390422422c81cb45839457aeb76fc12edd95b3012102657d118d3357b8e0f4c2cd46db7b39f6d9c38d9a70abcb9b2de5dc8dbfe4ce31feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300", PsbtError::InvalidMagic)]
+    // Unsigned transaction with a scriptSig.
+    #[case("70736274ff0100fd0a010200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be4000000006a47304402204759661797c01b036b25928948686218347d89864b719e1f7fcf57d1e511658702205309eabf56aa4d8891ffd111fdf1336f3a29da866d7f8486d75546ceedaf93190121035cdc61fc7ba971c0b501a646a2a83b102cb43881217ca682dc86e2d73fa88292feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f4620b553fa095e721b9ee0efe9fa039cca459788ac00000000000001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01fb82308000000", PsbtError::SignedUnsignedTx)]
+    // Empty global map.
+    #[case("70736274ff000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000000", PsbtError::MissingField("PSBT_GLOBAL_UNSIGNED_TX".to_string()))]
+    // Two non witness UTXOs for the same input.
+    #[case("70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000001003f0200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000ffffffff010000000000000000036a010000000000000000", PsbtError::DuplicateKey("00".to_string()))]
+    // Version 2 PSBT carrying a version 0 unsigned transaction.
+    #[case("70736274ff01003f0200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000ffffffff010000000000000000036a01000000000001fb0402000000000000", PsbtError::UnexpectedField(0x00, 2))]
+    fn test_invalid(#[case] psbt_hex: &str, #[case] error: PsbtError) {
+        assert_eq!(
+            Psbt::deserialize(&hex::decode(psbt_hex).unwrap()),
+            Err(error)
+        );
+    }
+
+    #[rstest]
+    fn test_invalid_non_witness_utxo() {
+        // BIP174 : the non witness UTXO is not a valid transaction.
+        let psbt_hex = "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000000";
+        assert!(matches!(
+            Psbt::deserialize(&hex::decode(psbt_hex).unwrap()),
+            Err(PsbtError::Transaction(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_invalid_base64() {
+        assert!(matches!(
+            Psbt::from_base64("cHNidA=="),
+            Err(PsbtError::InvalidMagic)
+        ));
+        assert!(matches!(
+            Psbt::from_base64("cHNidP8*"),
+            Err(PsbtError::InvalidBase64(_))
+        ));
+    }
+}
@@ -0,0 +1,221 @@
+// Signer role : adds partial signatures for every input a local key can sign.
+//
+// The script a key signs for is resolved from the UTXO and the redeem / witness scripts the
+// updater added. ECDSA keys sign when the script pushes their public key or its hash160,
+// taproot keys sign the key path when they tweak to the output key and every leaf that
+// pushes their x-only key.
+
+use crate::bip32::Xpriv;
+use crate::errors::PsbtError;
+use crate::hashes::{hash160, sha256};
+use crate::keys::PrivateKey;
+use crate::psbt::Psbt;
+use crate::schnorr::{self, tweak_public_key, tweak_secret_key};
+use bee_vm::opcodes::crypto_ops::SIGHASH_ALL;
+use bee_vm::script::classify::{classify, ScriptType};
+use bee_vm::script::decode::{decode, Instruction};
+use bee_vm::transaction::context::ExecutionContext;
+use bee_vm::transaction::sighash::{tap_leaf_hash, SigVersion, SIGHASH_DEFAULT};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+
+/// What the signature of an input commits to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpendInfo {
+    /// Legacy or segwit v0 spend and the script code hashed by OP_CHECKSIG.
+    Ecdsa {
+        sig_version: SigVersion,
+        script_code: Vec<u8>,
+    },
+    /// Segwit v1 spend of a taproot output key.
+    Taproot { output_key: [u8; 32] },
+}
+
+/// Resolves the script an input executes, checking the redeem / witness scripts against the
+/// hashes committed to by the UTXO.
+pub fn spend_info(psbt: &Psbt, index: usize) -> Result<SpendInfo, PsbtError> {
+    let input = &psbt.inputs[index];
+    let script_pubkey = psbt.utxo(index)?.script_pubkey;
+
+    let (script, nested) = match classify(&script_pubkey) {
+        ScriptType::P2SH { script_hash } => {
+            let redeem_script = input.redeem_script.clone().ok_or_else(|| {
+                PsbtError::ScriptMismatch(index, "missing redeem script".to_string())
+            })?;
+            if hash160(&redeem_script) != script_hash {
+                return Err(PsbtError::ScriptMismatch(
+                    index,
+                    "redeem script hash".to_string(),
+                ));
+            }
+            (redeem_script, true)
+        }
+        _ => (script_pubkey, false),
+    };
+
+    Ok(match classify(&script) {
+        ScriptType::P2WPKH { pubkey_hash } => SpendInfo::Ecdsa {
+            sig_version: SigVersion::WitnessV0,
+            script_code: p2pkh_script(&pubkey_hash),
+        },
+        ScriptType::P2WSH { script_hash } => {
+            let witness_script = input.witness_script.clone().ok_or_else(|| {
+                PsbtError::ScriptMismatch(index, "missing witness script".to_string())
+            })?;
+            if sha256(&witness_script) != script_hash {
+                return Err(PsbtError::ScriptMismatch(
+                    index,
+                    "witness script hash".to_string(),
+                ));
+            }
+            SpendInfo::Ecdsa {
+                sig_version: SigVersion::WitnessV0,
+                script_code: witness_script,
+            }
+        }
+        // Taproot outputs can not be wrapped in P2SH.
+        ScriptType::P2TR { output_key } if !nested => SpendInfo::Taproot { output_key },
+        _ => SpendInfo::Ecdsa {
+            sig_version: SigVersion::Base,
+            script_code: script,
+        },
+    })
+}
+
+/// Signs every input it can with `keys`, returns the number of signatures added.
+///
+/// Inputs default to SIGHASH_ALL (ECDSA) and SIGHASH_DEFAULT (taproot) without a sighash type.
+pub fn sign(psbt: &mut Psbt, keys: &[PrivateKey]) -> Result<usize, PsbtError> {
+    let tx = psbt.unsigned_tx()?;
+    let mut signatures = 0;
+
+    for index in 0..psbt.inputs.len() {
+        if psbt.inputs[index].is_final() {
+            continue;
+        }
+        match spend_info(psbt, index)? {
+            SpendInfo::Ecdsa {
+                sig_version,
+                script_code,
+            } => {
+                let sighash_type = psbt.inputs[index]
+                    .sighash_type
+                    .unwrap_or(SIGHASH_ALL as u32);
+                let context =
+                    ExecutionContext::new(tx.clone(), index, psbt.utxo(index)?, sig_version)?;
+                for key in keys {
+                    let public_key = key.public_key().to_bytes();
+                    if !pushes(&script_code, &[&public_key, &hash160(&public_key)]) {
+                        continue;
+                    }
+                    let sighash = context.signature_hash(&script_code, sighash_type, u32::MAX)?;
+                    let signature: k256::ecdsa::Signature = key
+                        .signing_key
+                        .sign_prehash(&sighash)
+                        .map_err(|e| PsbtError::Signing(e.to_string()))?;
+                    let mut signature = signature.to_der().as_bytes().to_vec();
+                    signature.push(sighash_type as u8);
+                    psbt.inputs[index]
+                        .partial_sigs
+                        .insert(public_key, signature);
+                    signatures += 1;
+                }
+            }
+            SpendInfo::Taproot { output_key } => {
+                let sighash_type = psbt.inputs[index]
+                    .sighash_type
+                    .unwrap_or(SIGHASH_DEFAULT as u32);
+                let prevouts = psbt.spent_outputs()?;
+                let input = &psbt.inputs[index];
+                let merkle_root = input.tap_merkle_root;
+                let leaves: Vec<(Vec<u8>, u8)> = input.tap_leaf_scripts.values().cloned().collect();
+
+                for key in keys {
+                    // Key path : the tweaked key must be the output key.
+                    let x_only = key.public_key().x_only();
+                    if tweak_public_key(&x_only, merkle_root.as_ref())?.0 == output_key {
+                        let context =
+                            ExecutionContext::taproot(tx.clone(), index, prevouts.clone(), None)?;
+                        let sighash = context.signature_hash(&[], sighash_type, u32::MAX)?;
+                        let tweaked = tweak_secret_key(&key.to_bytes(), merkle_root.as_ref())?;
+                        psbt.inputs[index].tap_key_sig =
+                            Some(schnorr_sign(&tweaked, &sighash, sighash_type)?);
+                        signatures += 1;
+                    }
+
+                    // Script path : leaves pushing the untweaked x-only key.
+                    for (script, leaf_version) in &leaves {
+                        if !pushes(script, &[&x_only]) {
+                            continue;
+                        }
+                        let leaf_hash = tap_leaf_hash(*leaf_version, script);
+                        let context = ExecutionContext::taproot(
+                            tx.clone(),
+                            index,
+                            prevouts.clone(),
+                            Some(leaf_hash),
+                        )?;
+                        let sighash = context.signature_hash(&[], sighash_type, u32::MAX)?;
+                        psbt.inputs[index].tap_script_sigs.insert(
+                            (x_only, leaf_hash),
+                            schnorr_sign(&key.to_bytes(), &sighash, sighash_type)?,
+                        );
+                        signatures += 1;
+                    }
+                }
+            }
+        }
+    }
+    Ok(signatures)
+}
+
+/// Keys of an extended master private key used by the PSBT, found through the BIP32
+/// derivations whose fingerprint is the master fingerprint.
+pub fn derive_keys(psbt: &Psbt, master: &Xpriv) -> Result<Vec<PrivateKey>, PsbtError> {
+    let fingerprint = master.fingerprint();
+    let mut keys = vec![];
+    for (public_key, source) in psbt
+        .inputs
+        .iter()
+        .flat_map(|input| input.bip32_derivation.iter())
+    {
+        if source.fingerprint != fingerprint {
+            continue;
+        }
+        let key = master.derive_path(&source.path)?.to_private_key();
+        // Skips origins that do not lead to the listed key (wrong master with the same fingerprint).
+        if key.public_key().compressed_bytes().as_slice() == public_key.as_slice() {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
+fn schnorr_sign(
+    secret_key: &[u8; 32],
+    sighash: &[u8; 32],
+    sighash_type: u32,
+) -> Result<Vec<u8>, PsbtError> {
+    let mut signature = schnorr::sign(secret_key, sighash, None)?.to_vec();
+    if sighash_type != SIGHASH_DEFAULT as u32 {
+        signature.push(sighash_type as u8);
+    }
+    Ok(signature)
+}
+
+/// Whether a script pushes one of `items` (undecodable scripts push nothing).
+fn pushes(script: &[u8], items: &[&[u8]]) -> bool {
+    decode(script).is_ok_and(|instructions| {
+        instructions.iter().any(|instruction| match instruction {
+            Instruction::Push { data, .. } => items.contains(&data.as_slice()),
+            Instruction::Op(_) => false,
+        })
+    })
+}
+
+/// BIP143 script code of a P2WPKH output : the matching P2PKH script.
+pub(crate) fn p2pkh_script(pubkey_hash: &[u8; 20]) -> Vec<u8> {
+    let mut script = vec![0x76, 0xa9, 0x14];
+    script.extend_from_slice(pubkey_hash);
+    script.extend_from_slice(&[0x88, 0xac]);
+    script
+}
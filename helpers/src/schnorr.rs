@@ -0,0 +1,406 @@
+// BIP340 Schnorr signatures and BIP341 key tweaking.
+//
+// Keys are x-only : 32 bytes holding the x coordinate of the point with an even y. A secret key
+// whose point has an odd y signs as its negation, so verifiers only need the x coordinate.
+// The taproot output key commits to an internal key and the merkle root of an optional script
+// tree, the parity of its y is kept since the control blocks of script path spends carry it.
+
+use crate::errors::KeyError;
+use crate::hashes::tagged_hash;
+use crate::keys::PublicKey;
+use k256::elliptic_curve::rand_core::{OsRng, RngCore};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::schnorr::{Signature, SigningKey, VerifyingKey};
+use k256::{ProjectivePoint, Scalar};
+use std::fmt;
+
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// Parity of the y coordinate of a point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    Even,
+    Odd,
+}
+
+impl Parity {
+    /// Bit stored in the first byte of a control block.
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Parity::Even => 0,
+            Parity::Odd => 1,
+        }
+    }
+}
+
+impl fmt::Display for Parity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Parity::Even => write!(f, "even"),
+            Parity::Odd => write!(f, "odd"),
+        }
+    }
+}
+
+/// X-only form of a 32 byte x-only, 33 byte compressed or 65 byte uncompressed public key,
+/// with the parity of its y (x-only keys are even).
+pub fn x_only_public_key(public_key: &[u8]) -> Result<([u8; 32], Parity), KeyError> {
+    if let Ok(x_only) = <[u8; 32]>::try_from(public_key) {
+        VerifyingKey::from_bytes(&x_only)
+            .map_err(|_| KeyError::InvalidPublicKey("x is not on the curve".to_string()))?;
+        return Ok((x_only, Parity::Even));
+    }
+    let compressed = PublicKey::from_slice(public_key)?.compressed_bytes();
+    let parity = match compressed[0] {
+        0x03 => Parity::Odd,
+        _ => Parity::Even,
+    };
+    Ok((compressed[1..].try_into().unwrap(), parity))
+}
+
+/// Signs `message` (any length, usually a 32 byte hash) with the BIP340 nonce derivation.
+///
+/// Without auxiliary randomness 32 random bytes are used, as BIP340 recommends.
+pub fn sign(
+    secret_key: &[u8; 32],
+    message: &[u8],
+    aux_rand: Option<&[u8; 32]>,
+) -> Result<[u8; SIGNATURE_SIZE], KeyError> {
+    let signing_key = SigningKey::from_bytes(secret_key)
+        .map_err(|e| KeyError::InvalidPrivateKey(e.to_string()))?;
+    let aux_rand = match aux_rand {
+        Some(aux_rand) => *aux_rand,
+        None => {
+            let mut aux_rand = [0u8; 32];
+            OsRng.fill_bytes(&mut aux_rand);
+            aux_rand
+        }
+    };
+    let signature = signing_key
+        .sign_raw(message, &aux_rand)
+        .map_err(|e| KeyError::InvalidPrivateKey(e.to_string()))?;
+    Ok(signature.to_bytes())
+}
+
+/// Whether `signature` is a valid BIP340 signature of `message` for the x-only `public_key`.
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let (Ok(public_key), Ok(signature)) = (
+        VerifyingKey::from_bytes(public_key),
+        Signature::try_from(signature),
+    ) else {
+        return false;
+    };
+    public_key.verify_raw(message, &signature).is_ok()
+}
+
+/// BIP341 tweak : `hash_TapTweak(internal_key || merkle_root)`, without the merkle root for
+/// key path only outputs.
+pub fn tap_tweak_hash(internal_key: &[u8; 32], merkle_root: Option<&[u8; 32]>) -> [u8; 32] {
+    let mut data = internal_key.to_vec();
+    if let Some(root) = merkle_root {
+        data.extend_from_slice(root);
+    }
+    tagged_hash("TapTweak", &data)
+}
+
+fn tweak_scalar(
+    internal_key: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<Scalar, KeyError> {
+    let tweak: Option<Scalar> =
+        Scalar::from_repr(tap_tweak_hash(internal_key, merkle_root).into()).into();
+    tweak.ok_or(KeyError::InvalidTweak)
+}
+
+/// BIP341 output key `Q = P + int(tweak) * G` and the parity of its y.
+pub fn tweak_public_key(
+    internal_key: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<([u8; 32], Parity), KeyError> {
+    let internal = VerifyingKey::from_bytes(internal_key)
+        .map_err(|e| KeyError::InvalidPublicKey(e.to_string()))?;
+    let tweak = tweak_scalar(internal_key, merkle_root)?;
+
+    let output = ProjectivePoint::from(*internal.as_affine()) + ProjectivePoint::GENERATOR * tweak;
+    let encoded = output.to_affine().to_encoded_point(true);
+    let x = encoded.x().ok_or(KeyError::InvalidTweak)?;
+    let parity = match encoded.tag() {
+        k256::elliptic_curve::sec1::Tag::CompressedOddY => Parity::Odd,
+        _ => Parity::Even,
+    };
+    Ok(((*x).into(), parity))
+}
+
+/// BIP341 output key of an internal key.
+///
+/// Without a merkle root this is the BIP86 key path only output.
+pub fn taproot_output_key(
+    internal_key: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<[u8; 32], KeyError> {
+    Ok(tweak_public_key(internal_key, merkle_root)?.0)
+}
+
+/// Secret key of the output key returned by [`tweak_public_key`] for the same merkle root.
+pub fn tweak_secret_key(
+    secret_key: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<[u8; 32], KeyError> {
+    // The BIP340 key already negates the secret when its point has an odd y.
+    let internal = SigningKey::from_bytes(secret_key)
+        .map_err(|e| KeyError::InvalidPrivateKey(e.to_string()))?;
+    let tweak = tweak_scalar(&internal.verifying_key().to_bytes().into(), merkle_root)?;
+
+    let tweaked = *internal.as_nonzero_scalar().as_ref() + tweak;
+    if tweaked.is_zero().into() {
+        return Err(KeyError::InvalidTweak);
+    }
+    Ok(tweaked.to_bytes().into())
+}
+
+#[cfg(test)]
+mod test_schnorr {
+    use crate::keys::PrivateKey;
+    use crate::network::Network;
+    use crate::schnorr::{
+        sign, taproot_output_key, tweak_public_key, tweak_secret_key, verify, x_only_public_key,
+        Parity,
+    };
+    use rstest::rstest;
+
+    fn bytes32(hex: &str) -> [u8; 32] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    // BIP340 test vectors 0 to 3 and 15 to 18 (messages that are not 32 bytes long).
+    #[rstest]
+    #[case(
+        "0000000000000000000000000000000000000000000000000000000000000003",
+        "F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0"
+    )]
+    #[case(
+        "B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF",
+        "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+        "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A"
+    )]
+    #[case(
+        "C90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B14E5C9",
+        "DD308AFEC5777E13121FA72B9CC1B7CC0139715309B086C960E18FD969774EB8",
+        "C87AA53824B4D7AE2EB035A2B5BBBCCC080E76CDC6D1692C4B0B62D798E6D906",
+        "7E2D58D8B3BCDF1ABADEC7829054F90DDA9805AAB56C77333024B9D0A508B75C",
+        "5831AAEED7B44BB74E5EAB94BA9D4294C49BCF2A60728D8B4C200F50DD313C1BAB745879A5AD954A72C45A91C3A51D3C7ADEA98D82F8481E0E1E03674A6F3FB7"
+    )]
+    #[case(
+        "0B432B2677937381AEF05BB02A66ECD012773062CF3FA2549E44F58ED2401710",
+        "25D1DFF95105F5253C4022F628A996AD3A0D95FBF21D468A1B33F8C160D8F517",
+        "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
+        "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
+        "7EB0509757E246F19449885651611CB965ECC1A187DD51B64FDA1EDC9637D5EC97582B9CB13DB3933705B32BA982AF5AF25FD78881EBB32771FC5922EFC66EA3"
+    )]
+    #[case(
+        "0340034003400340034003400340034003400340034003400340034003400340",
+        "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "",
+        "71535DB165ECD9FBBC046E5FFAEA61186BB6AD436732FCCC25291A55895464CF6069CE26BF03466228F19A3A62DB8A649F2D560FAC652827D1AF0574E427AB63"
+    )]
+    #[case(
+        "0340034003400340034003400340034003400340034003400340034003400340",
+        "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "11",
+        "08A20A0AFEF64124649232E0693C583AB1B9934AE63B4C3511F3AE1134C6A303EA3173BFEA6683BD101FA5AA5DBC1996FE7CACFC5A577D33EC14564CEC2BACBF"
+    )]
+    #[case(
+        "0340034003400340034003400340034003400340034003400340034003400340",
+        "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0102030405060708090A0B0C0D0E0F1011",
+        "5130F39A4059B43BC7CAC09A19ECE52B5D8699D1A71E3C52DA9AFDB6B50AC370C4A482B77BF960F8681540E25B6771ECE1E5A37FD80E5A51897C5566A97EA5A5"
+    )]
+    fn test_bip340_sign(
+        #[case] secret_key: &str,
+        #[case] public_key: &str,
+        #[case] aux_rand: &str,
+        #[case] message: &str,
+        #[case] signature: &str,
+    ) -> color_eyre::Result<()> {
+        let secret_key = bytes32(secret_key);
+        let public_key = hex::decode(public_key)?;
+        let message = hex::decode(message)?;
+
+        let key = PrivateKey::from_slice(&secret_key, Network::Mainnet)?;
+        assert_eq!(key.public_key().x_only().as_slice(), public_key);
+        let signed = sign(&secret_key, &message, Some(&bytes32(aux_rand)))?;
+        assert_eq!(hex::encode_upper(signed), signature);
+        assert!(verify(&public_key, &message, &signed));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(
+        "0340034003400340034003400340034003400340034003400340034003400340",
+        &[0x99; 100],
+        "403B12B0D8555A344175EA7EC746566303321E5DBFA8BE6F091635163ECA79A8585ED3E3170807E7C03B720FC54C7B23897FCBA0E9D0B4A06894CFD249F22367"
+    )]
+    fn test_bip340_long_message(
+        #[case] secret_key: &str,
+        #[case] message: &[u8],
+        #[case] signature: &str,
+    ) -> color_eyre::Result<()> {
+        let signed = sign(&bytes32(secret_key), message, Some(&[0; 32]))?;
+        assert_eq!(hex::encode_upper(signed), signature);
+        Ok(())
+    }
+
+    // BIP340 test vectors 4 to 14.
+    #[rstest]
+    #[case("D69C3509BB99E412E68B0FE8544E72837DFA30746D8BE2AA65975F29D22DC7B9", "4DF3C3F68FCC83B27E9D42C90431A72499F17875C81A599B566C9889B9696703", "00000000000000000000003B78CE563F89A0ED9414F5AA28AD0D96D6795F9C6376AFB1548AF603B3EB45C9F8207DEE1060CB71C04E80F593060B07D28308D7F4", true)]
+    // Public key not on the curve.
+    #[case("EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false)]
+    // has_even_y(R) is false.
+    #[case("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "FFF97BD5755EEEA420453A14355235D382F6472F8568A18B2F057A14602975563CC27944640AC607CD107AE10923D9EF7A73C643E166BE5EBEAFA34B1AC553E2", false)]
+    // Negated message.
+    #[case("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "1FA62E331EDBC21C394792D2AB1100A7B432B013DF3F6FF4F99FCB33E0E1515F28890B3EDB6E7189B630448B515CE4F8622A954CFE545735AAEA5134FCCDB2BD", false)]
+    // Negated s.
+    #[case("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769961764B3AA9B2FFCB6EF947B6887A226E8D7C93E00C5ED0C1834FF0D0C2E6DA6", false)]
+    // sG - eP is the point at infinity.
+    #[case("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "0000000000000000000000000000000000000000000000000000000000000000123DDA8328AF9C23A94C1FEECFD123BA4FB73476F0D594DCB65C6425BD186051", false)]
+    #[case("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "00000000000000000000000000000000000000000000000000000000000000017615FBAF5AE28864013C099742DEADB4DBA87F11AC6754F93780D5A1837CF197", false)]
+    // r is not the x coordinate of a point.
+    #[case("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "4A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false)]
+    // r is the field size.
+    #[case("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false)]
+    // s is the curve order.
+    #[case("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141", false)]
+    // Public key exceeds the field size.
+    #[case("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30", "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false)]
+    fn test_bip340_verify(
+        #[case] public_key: &str,
+        #[case] message: &str,
+        #[case] signature: &str,
+        #[case] valid: bool,
+    ) -> color_eyre::Result<()> {
+        assert_eq!(
+            verify(
+                &hex::decode(public_key)?,
+                &hex::decode(message)?,
+                &hex::decode(signature)?
+            ),
+            valid
+        );
+        Ok(())
+    }
+
+    // BIP341 wallet test vectors (scriptPubKey) and BIP86's first receiving key.
+    #[rstest]
+    #[case(
+        "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d",
+        None,
+        "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+        Parity::Odd
+    )]
+    #[case(
+        "187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27",
+        Some("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"),
+        "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+        Parity::Odd
+    )]
+    #[case(
+        "93478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820",
+        Some("c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b"),
+        "e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+        Parity::Even
+    )]
+    #[case(
+        "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115",
+        None,
+        "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+        Parity::Odd
+    )]
+    fn test_tweak_public_key(
+        #[case] internal_key: &str,
+        #[case] merkle_root: Option<&str>,
+        #[case] output_key: &str,
+        #[case] parity: Parity,
+    ) -> color_eyre::Result<()> {
+        let merkle_root = merkle_root.map(bytes32);
+        let (tweaked, tweaked_parity) =
+            tweak_public_key(&bytes32(internal_key), merkle_root.as_ref())?;
+        assert_eq!(hex::encode(tweaked), output_key);
+        assert_eq!(tweaked_parity, parity);
+        Ok(())
+    }
+
+    // BIP341 key path spending vector.
+    #[rstest]
+    fn test_tweak_secret_key() -> color_eyre::Result<()> {
+        let secret_key =
+            bytes32("6b973d88838f27366ed61c9ad6367663045cb456e28335c109e30717ae0c6baa");
+        assert_eq!(
+            hex::encode(tweak_secret_key(&secret_key, None)?),
+            "2405b971772ad26915c8dcdf10f238753a9b837e5f8e6a86fd7c0cce5b7296d9"
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some([0x42; 32]))]
+    fn test_tweaked_keys_match(#[case] merkle_root: Option<[u8; 32]>) -> color_eyre::Result<()> {
+        // Seeds with both parities of the internal key.
+        for seed in [b"alice".as_slice(), b"bob".as_slice(), b"carol".as_slice()] {
+            let key = PrivateKey::from_seed(seed, Network::Regtest);
+            let output_key = taproot_output_key(&key.public_key().x_only(), merkle_root.as_ref())?;
+            let secret_key = tweak_secret_key(&key.to_bytes(), merkle_root.as_ref())?;
+            let tweaked = PrivateKey::from_slice(&secret_key, Network::Regtest)?;
+            assert_eq!(tweaked.public_key().x_only(), output_key);
+
+            let signature = sign(&secret_key, b"key path", None)?;
+            assert!(verify(&output_key, b"key path", &signature));
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        Parity::Even
+    )]
+    #[case(
+        "03f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+        Parity::Odd
+    )]
+    #[case(
+        "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+        Parity::Even
+    )]
+    #[case(
+        "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        Parity::Even
+    )]
+    fn test_x_only_public_key(
+        #[case] public_key: &str,
+        #[case] parity: Parity,
+    ) -> color_eyre::Result<()> {
+        let (x_only, key_parity) = x_only_public_key(&hex::decode(public_key)?)?;
+        let prefix = if public_key.len() == 64 { 0 } else { 2 };
+        assert_eq!(hex::encode(x_only), public_key[prefix..prefix + 64]);
+        assert_eq!(key_parity, parity);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34")]
+    #[case("02eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34")]
+    #[case("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f817")]
+    fn test_invalid_x_only_public_key(#[case] public_key: &str) {
+        assert!(x_only_public_key(&hex::decode(public_key).unwrap()).is_err());
+    }
+}
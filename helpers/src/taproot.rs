@@ -0,0 +1,490 @@
+// Taproot script trees (BIP341).
+//
+// A P2TR output commits to an internal key and, optionally, to a tree of leaf scripts. Spending a
+// leaf reveals the script and a control block : the leaf version with the output key parity, the
+// internal key and the merkle path from the leaf to the root. Weighted leaves are placed with a
+// Huffman tree so the likely ones get the shortest paths (the smallest witnesses).
+
+use crate::address::Address;
+use crate::errors::TaprootError;
+use crate::network::Network;
+use crate::schnorr::{tweak_public_key, Parity};
+use bee_vm::transaction::sighash::{tap_branch_hash, tap_leaf_hash, TAPSCRIPT_LEAF_VERSION};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Merkle paths are limited to 128 nodes by the control block size.
+pub const MAX_DEPTH: usize = 128;
+
+/// BIP341 "nothing up my sleeve" point, an internal key nobody knows the secret of, for outputs
+/// that can only be spent through their scripts.
+pub const UNSPENDABLE_KEY: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// A script tree, branches are hashed in sorted order so swapping children keeps the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TapTree {
+    Leaf { script: Vec<u8>, leaf_version: u8 },
+    Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+    /// A tapscript leaf (version 0xc0).
+    pub fn leaf(script: Vec<u8>) -> TapTree {
+        TapTree::Leaf {
+            script,
+            leaf_version: TAPSCRIPT_LEAF_VERSION,
+        }
+    }
+
+    pub fn branch(left: TapTree, right: TapTree) -> TapTree {
+        TapTree::Branch(Box::new(left), Box::new(right))
+    }
+
+    /// Huffman tree of weighted subtrees : the two lightest nodes are merged until one is left.
+    /// Ties go to the node added first, so the tree only depends on the order of the leaves.
+    pub fn huffman(leaves: Vec<(u64, TapTree)>) -> Option<TapTree> {
+        let mut nodes = vec![];
+        let mut heap = BinaryHeap::new();
+        for (weight, tree) in leaves {
+            heap.push(Reverse((weight, nodes.len())));
+            nodes.push(Some(tree));
+        }
+        loop {
+            let Reverse((weight, first)) = heap.pop()?;
+            let Some(Reverse((other_weight, second))) = heap.pop() else {
+                return nodes[first].take();
+            };
+            let branch = TapTree::branch(
+                nodes[first].take().expect("node merged twice"),
+                nodes[second].take().expect("node merged twice"),
+            );
+            heap.push(Reverse((weight + other_weight, nodes.len())));
+            nodes.push(Some(branch));
+        }
+    }
+
+    /// Merkle root of the tree (the leaf hash for a single leaf).
+    pub fn merkle_root(&self) -> [u8; 32] {
+        match self {
+            TapTree::Leaf {
+                script,
+                leaf_version,
+            } => tap_leaf_hash(*leaf_version, script),
+            TapTree::Branch(left, right) => {
+                tap_branch_hash(&left.merkle_root(), &right.merkle_root())
+            }
+        }
+    }
+
+    /// Appends the leaves, left to right, with their merkle paths (from the leaf up).
+    fn collect_leaves(&self, leaves: &mut Vec<TapLeafInfo>) -> [u8; 32] {
+        match self {
+            TapTree::Leaf {
+                script,
+                leaf_version,
+            } => {
+                let leaf_hash = tap_leaf_hash(*leaf_version, script);
+                leaves.push(TapLeafInfo {
+                    script: script.clone(),
+                    leaf_version: *leaf_version,
+                    leaf_hash,
+                    merkle_path: vec![],
+                });
+                leaf_hash
+            }
+            TapTree::Branch(left, right) => {
+                let first = leaves.len();
+                let left_hash = left.collect_leaves(leaves);
+                let middle = leaves.len();
+                let right_hash = right.collect_leaves(leaves);
+                for leaf in &mut leaves[first..middle] {
+                    leaf.merkle_path.push(right_hash);
+                }
+                for leaf in &mut leaves[middle..] {
+                    leaf.merkle_path.push(left_hash);
+                }
+                tap_branch_hash(&left_hash, &right_hash)
+            }
+        }
+    }
+}
+
+/// A leaf of a finalized tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapLeafInfo {
+    pub script: Vec<u8>,
+    pub leaf_version: u8,
+    pub leaf_hash: [u8; 32],
+    /// Sibling hashes from the leaf up to the root.
+    pub merkle_path: Vec<[u8; 32]>,
+}
+
+/// Everything needed to spend a P2TR output, by its key path or any of its leaves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaprootSpendInfo {
+    pub internal_key: [u8; 32],
+    pub merkle_root: Option<[u8; 32]>,
+    pub output_key: [u8; 32],
+    pub parity: Parity,
+    pub leaves: Vec<TapLeafInfo>,
+}
+
+impl TaprootSpendInfo {
+    /// Spend info of an internal key and an optional tree, leaves are listed left to right.
+    pub fn new(
+        internal_key: &[u8; 32],
+        tree: Option<&TapTree>,
+    ) -> Result<TaprootSpendInfo, TaprootError> {
+        let mut leaves = vec![];
+        let merkle_root = tree.map(|tree| tree.collect_leaves(&mut leaves));
+        if let Some(leaf) = leaves
+            .iter()
+            .find(|leaf| leaf.merkle_path.len() > MAX_DEPTH)
+        {
+            return Err(TaprootError::TreeTooDeep(leaf.merkle_path.len()));
+        }
+        if let Some(leaf) = leaves
+            .iter()
+            .find(|leaf| leaf.leaf_version & 1 == 1 || leaf.leaf_version == 0x50)
+        {
+            return Err(TaprootError::InvalidLeafVersion(leaf.leaf_version));
+        }
+        let (output_key, parity) = tweak_public_key(internal_key, merkle_root.as_ref())?;
+        Ok(TaprootSpendInfo {
+            internal_key: *internal_key,
+            merkle_root,
+            output_key,
+            parity,
+            leaves,
+        })
+    }
+
+    /// `OP_1 <output key>`.
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        let mut script = vec![0x51, 0x20];
+        script.extend_from_slice(&self.output_key);
+        script
+    }
+
+    pub fn address(&self, network: Network) -> Address {
+        Address::p2tr(&self.output_key, network)
+    }
+
+    /// First leaf with this script and leaf version.
+    pub fn find_leaf(&self, script: &[u8], leaf_version: u8) -> Option<usize> {
+        self.leaves
+            .iter()
+            .position(|leaf| leaf.script == script && leaf.leaf_version == leaf_version)
+    }
+
+    /// `leaf version | parity || internal key || merkle path`.
+    pub fn control_block(&self, leaf_index: usize) -> Result<Vec<u8>, TaprootError> {
+        let leaf = self
+            .leaves
+            .get(leaf_index)
+            .ok_or(TaprootError::LeafIndexOutOfRange(leaf_index))?;
+        let mut control_block = vec![leaf.leaf_version | self.parity.to_u8()];
+        control_block.extend_from_slice(&self.internal_key);
+        for node in &leaf.merkle_path {
+            control_block.extend_from_slice(node);
+        }
+        Ok(control_block)
+    }
+
+    /// Script path witness : the script inputs, then the leaf script and its control block.
+    pub fn script_path_witness(
+        &self,
+        leaf_index: usize,
+        stack: Vec<Vec<u8>>,
+    ) -> Result<Vec<Vec<u8>>, TaprootError> {
+        let control_block = self.control_block(leaf_index)?;
+        let mut witness = stack;
+        witness.push(self.leaves[leaf_index].script.clone());
+        witness.push(control_block);
+        Ok(witness)
+    }
+}
+
+/// Collects weighted leaves and places them in a Huffman tree.
+#[derive(Debug, Clone, Default)]
+pub struct TaprootBuilder {
+    leaves: Vec<(u64, TapTree)>,
+}
+
+impl TaprootBuilder {
+    pub fn new() -> TaprootBuilder {
+        TaprootBuilder::default()
+    }
+
+    /// Adds a tapscript leaf, `weight` is how likely it is to be spent relative to the others.
+    pub fn add_leaf(self, weight: u32, script: Vec<u8>) -> TaprootBuilder {
+        self.add_leaf_with_version(weight, script, TAPSCRIPT_LEAF_VERSION)
+    }
+
+    pub fn add_leaf_with_version(
+        mut self,
+        weight: u32,
+        script: Vec<u8>,
+        leaf_version: u8,
+    ) -> TaprootBuilder {
+        self.leaves.push((
+            weight as u64,
+            TapTree::Leaf {
+                script,
+                leaf_version,
+            },
+        ));
+        self
+    }
+
+    /// The Huffman tree of the leaves, `None` without leaves.
+    pub fn tree(&self) -> Option<TapTree> {
+        TapTree::huffman(self.leaves.clone())
+    }
+
+    /// Spend info of the tree, with the leaves in the order they were added.
+    pub fn finalize(&self, internal_key: &[u8; 32]) -> Result<TaprootSpendInfo, TaprootError> {
+        let mut info = TaprootSpendInfo::new(internal_key, self.tree().as_ref())?;
+        let position = |leaf: &TapLeafInfo| {
+            self.leaves.iter().position(|(_, tree)| {
+                matches!(tree, TapTree::Leaf { script, leaf_version }
+                    if *script == leaf.script && *leaf_version == leaf.leaf_version)
+            })
+        };
+        info.leaves.sort_by_key(position);
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod test_taproot {
+    use crate::errors::TaprootError;
+    use crate::hashes::sha256;
+    use crate::keys::PrivateKey;
+    use crate::network::Network;
+    use crate::schnorr;
+    use crate::taproot::{TapTree, TaprootBuilder, TaprootSpendInfo, UNSPENDABLE_KEY};
+    use bee_vm::errors::VerifyError;
+    use bee_vm::transaction::context::ExecutionContext;
+    use bee_vm::transaction::sighash::SIGHASH_DEFAULT;
+    use bee_vm::transaction::verify::verify_input;
+    use bee_vm::transaction::{OutPoint, Transaction, TxIn, TxOut};
+    use rstest::rstest;
+
+    fn bytes32(hex: &str) -> [u8; 32] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    // BIP341 wallet test vectors with a single leaf.
+    #[rstest]
+    #[case(
+        "187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27",
+        "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac",
+        "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21",
+        "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+        "c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"
+    )]
+    #[case(
+        "93478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820",
+        "20b617298552a72ade070667e86ca63b8f5789a9fe8731ef91202a91c9f3459007ac",
+        "c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b",
+        "e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+        "c093478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820"
+    )]
+    fn test_single_leaf(
+        #[case] internal_key: &str,
+        #[case] script: &str,
+        #[case] merkle_root: &str,
+        #[case] output_key: &str,
+        #[case] control_block: &str,
+    ) -> color_eyre::Result<()> {
+        let tree = TapTree::leaf(hex::decode(script)?);
+        let info = TaprootSpendInfo::new(&bytes32(internal_key), Some(&tree))?;
+        assert_eq!(hex::encode(info.merkle_root.unwrap()), merkle_root);
+        assert_eq!(hex::encode(info.leaves[0].leaf_hash), merkle_root);
+        assert_eq!(hex::encode(info.output_key), output_key);
+        assert_eq!(hex::encode(info.control_block(0)?), control_block);
+        Ok(())
+    }
+
+    // Checked against rust-bitcoin's TaprootBuilder.
+    #[rstest]
+    fn test_branch_with_leaf_versions() -> color_eyre::Result<()> {
+        let tree = TapTree::branch(
+            TapTree::leaf(hex::decode(
+                "20387671353e273264c495656e27e39ba899ea8fee3bb69fb2a680e22093447d48ac",
+            )?),
+            TapTree::Leaf {
+                script: hex::decode("06424950333431")?,
+                leaf_version: 0xfa,
+            },
+        );
+        let internal_key =
+            bytes32("ee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf3786592");
+        let info = TaprootSpendInfo::new(&internal_key, Some(&tree))?;
+        assert_eq!(
+            hex::encode(info.merkle_root.unwrap()),
+            "6c2dc106ab816b73f9d07e3cd1ef2c8c1256f519748e0813e4edd2405d277bef"
+        );
+        assert_eq!(
+            hex::encode(info.output_key),
+            "712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5"
+        );
+        assert_eq!(
+            hex::encode(info.control_block(0)?),
+            "c0ee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf3786592f224a923cd0021ab202ab139cc56802ddb92dcfc172b9212261a539df79a112a"
+        );
+        assert_eq!(
+            hex::encode(info.control_block(1)?),
+            "faee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf37865928ad69ec7cf41c2a4001fd1f738bf1e505ce2277acdcaa63fe4765192497f47a7"
+        );
+        Ok(())
+    }
+
+    // Checked against rust-bitcoin's `TaprootBuilder::with_huffman_tree`.
+    #[rstest]
+    fn test_huffman_builder() -> color_eyre::Result<()> {
+        let internal_key =
+            bytes32("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d");
+        let info = TaprootBuilder::new()
+            .add_leaf(10, vec![0x51])
+            .add_leaf(5, vec![0x52])
+            .add_leaf(3, vec![0x53])
+            .add_leaf(1, vec![0x54])
+            .finalize(&internal_key)?;
+
+        assert_eq!(
+            hex::encode(info.merkle_root.unwrap()),
+            "0af1dec028ec6503db9a2c0d93829ec399d2b5aa3bda59f9302d24d41ed8a7f7"
+        );
+        assert_eq!(
+            hex::encode(info.output_key),
+            "444a4c40cb319eb3f2899b508e103d1f399aba9d246de31db43e1b9f1c79b46a"
+        );
+        let control_blocks = [
+            "c1d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d7448420524d03671ed33561027f399631fd2638df63ae0483e503fcce610033e",
+            "c1d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d961070ebeeb6007c20b0af10c2ba10030d0efcef3b31d0e8f56173dd7b247fdaa85b2107f791b26a84e7586c28cec7cb61202ed3d01944d832500f363782d675",
+            "c1d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d65479d810e8dac590c7bfc09abfc02d09613c9cdfbb7755df654cf49ae7e5e2bc276fef1386890619b80e10a4a328572d97493add269df1a15a7f89f8ae8ec09a85b2107f791b26a84e7586c28cec7cb61202ed3d01944d832500f363782d675",
+            "c1d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961da8199db85e1f94b911a63ffece012bb8afc92131e59a614341db4ed2312a3c48c276fef1386890619b80e10a4a328572d97493add269df1a15a7f89f8ae8ec09a85b2107f791b26a84e7586c28cec7cb61202ed3d01944d832500f363782d675",
+        ];
+        for (index, control_block) in control_blocks.iter().enumerate() {
+            assert_eq!(info.leaves[index].script, [0x51 + index as u8]);
+            assert_eq!(hex::encode(info.control_block(index)?), *control_block);
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_huffman_edge_cases() {
+        assert_eq!(TapTree::huffman(vec![]), None);
+        assert_eq!(
+            TapTree::huffman(vec![(1, TapTree::leaf(vec![0x51]))]),
+            Some(TapTree::leaf(vec![0x51]))
+        );
+        // Equal weights give a balanced tree.
+        let leaves = (0..4).map(|i| (1, TapTree::leaf(vec![0x51 + i]))).collect();
+        let info =
+            TaprootSpendInfo::new(&UNSPENDABLE_KEY, TapTree::huffman(leaves).as_ref()).unwrap();
+        assert!(info.leaves.iter().all(|leaf| leaf.merkle_path.len() == 2));
+    }
+
+    #[rstest]
+    fn test_invalid_trees() {
+        let mut tree = TapTree::leaf(vec![0x51]);
+        for _ in 0..129 {
+            tree = TapTree::branch(tree, TapTree::leaf(vec![0x52]));
+        }
+        assert_eq!(
+            TaprootSpendInfo::new(&UNSPENDABLE_KEY, Some(&tree)),
+            Err(TaprootError::TreeTooDeep(129))
+        );
+
+        let tree = TapTree::Leaf {
+            script: vec![0x51],
+            leaf_version: 0xc1,
+        };
+        assert_eq!(
+            TaprootSpendInfo::new(&UNSPENDABLE_KEY, Some(&tree)),
+            Err(TaprootError::InvalidLeafVersion(0xc1))
+        );
+        let info = TaprootSpendInfo::new(&UNSPENDABLE_KEY, None).unwrap();
+        assert_eq!(
+            info.control_block(0),
+            Err(TaprootError::LeafIndexOutOfRange(0))
+        );
+    }
+
+    // Every leaf of a tree is spent through bee-vm's script path verification.
+    #[rstest]
+    fn test_script_path_spends() -> color_eyre::Result<()> {
+        let alice = PrivateKey::from_seed(b"alice", Network::Regtest);
+        let bob = PrivateKey::from_seed(b"bob", Network::Regtest);
+        let preimage = b"taproot preimage".to_vec();
+
+        let mut checksig = vec![0x20];
+        checksig.extend_from_slice(&alice.public_key().x_only());
+        checksig.push(0xac);
+        let mut hashlock = vec![0xa8, 0x20];
+        hashlock.extend_from_slice(&sha256(&preimage));
+        hashlock.push(0x87);
+        let mut checksig_verify = vec![0x20];
+        checksig_verify.extend_from_slice(&bob.public_key().x_only());
+        checksig_verify.extend_from_slice(&[0xad, 0x51]);
+
+        let info = TaprootBuilder::new()
+            .add_leaf(5, checksig)
+            .add_leaf(1, hashlock)
+            .add_leaf(2, checksig_verify)
+            .add_leaf(1, vec![0x52, 0x87])
+            .finalize(&UNSPENDABLE_KEY)?;
+        let prevouts = vec![TxOut {
+            value: 50_000,
+            script_pubkey: info.script_pubkey(),
+        }];
+        let unsigned = Transaction {
+            version: 2,
+            inputs: vec![TxIn::new(OutPoint::default(), 0xffff_fffd)],
+            outputs: vec![TxOut {
+                value: 49_000,
+                script_pubkey: info.script_pubkey(),
+            }],
+            lock_time: 0,
+        };
+
+        let sign = |key: &PrivateKey, leaf: usize| -> color_eyre::Result<Vec<u8>> {
+            let context = ExecutionContext::taproot(
+                unsigned.clone(),
+                0,
+                prevouts.clone(),
+                Some(info.leaves[leaf].leaf_hash),
+            )?;
+            let sighash = context.signature_hash(&[], SIGHASH_DEFAULT as u32, u32::MAX)?;
+            Ok(schnorr::sign(&key.to_bytes(), &sighash, None)?.to_vec())
+        };
+        let stacks = [
+            vec![sign(&alice, 0)?],
+            vec![preimage],
+            vec![sign(&bob, 2)?],
+            vec![vec![0x02]],
+        ];
+
+        for (leaf, stack) in stacks.into_iter().enumerate() {
+            let mut tx = unsigned.clone();
+            tx.inputs[0].witness = info.script_path_witness(leaf, stack.clone())?;
+            verify_input(&tx, 0, &prevouts)?;
+
+            // The control block of another leaf does not commit to this script.
+            let other = (leaf + 1) % info.leaves.len();
+            let last = tx.inputs[0].witness.len() - 1;
+            tx.inputs[0].witness[last] = info.control_block(other)?;
+            assert_eq!(
+                verify_input(&tx, 0, &prevouts),
+                Err(VerifyError::WitnessProgramMismatch)
+            );
+        }
+        Ok(())
+    }
+}
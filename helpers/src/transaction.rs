@@ -0,0 +1,268 @@
+use crate::address::Address;
+use crate::errors::TxError;
+use crate::network::Network;
+use bee_vm::script::asm::{to_asm, to_asm_with_sighash};
+use bee_vm::script::classify::classify;
+use bee_vm::transaction::{display_hash, OutPoint, Transaction, TxIn, TxOut};
+use serde_json::{json, Value};
+
+const SATOSHIS_PER_BTC: f64 = 100_000_000.0;
+
+/// Parses an input given as `<txid>:<vout>[:<sequence>]`, the txid in its display (RPC) order.
+pub fn parse_input(input: &str, default_sequence: u32) -> Result<TxIn, TxError> {
+    let invalid = || TxError::InvalidInput(input.to_string());
+    let mut parts = input.split(':');
+    let txid = parts.next().ok_or_else(invalid)?;
+    let vout = parts
+        .next()
+        .and_then(|vout| vout.parse::<u32>().ok())
+        .ok_or_else(invalid)?;
+    let sequence = match parts.next() {
+        Some(sequence) => parse_u32(sequence).ok_or_else(invalid)?,
+        None => default_sequence,
+    };
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+
+    Ok(TxIn::new(
+        OutPoint::from_display_txid(txid, vout)?,
+        sequence,
+    ))
+}
+
+/// Parses an output given as `<address | scriptPubKey hex>:<satoshis>`.
+pub fn parse_output(output: &str, network: Network) -> Result<TxOut, TxError> {
+    let (destination, amount) = output
+        .rsplit_once(':')
+        .ok_or(TxError::InvalidOutput(output.to_string()))?;
+    let value = amount
+        .parse::<u64>()
+        .map_err(|_| TxError::InvalidOutput(output.to_string()))?;
+
+    // Addresses first, a scriptPubKey in hex otherwise (OP_RETURN outputs, bare scripts ...).
+    let script_pubkey = match Address::parse(destination, network) {
+        Ok(address) => address.script_pubkey(),
+        Err(address_error) => hex::decode(destination).map_err(|_| address_error)?,
+    };
+    Ok(TxOut {
+        value,
+        script_pubkey,
+    })
+}
+
+/// Builds an unsigned transaction, inputs without their own sequence get `default_sequence`.
+pub fn create(
+    version: i32,
+    inputs: &[String],
+    outputs: &[String],
+    lock_time: u32,
+    default_sequence: u32,
+    network: Network,
+) -> Result<Transaction, TxError> {
+    Ok(Transaction {
+        version,
+        inputs: inputs
+            .iter()
+            .map(|input| parse_input(input, default_sequence))
+            .collect::<Result<_, _>>()?,
+        outputs: outputs
+            .iter()
+            .map(|output| parse_output(output, network))
+            .collect::<Result<_, _>>()?,
+        lock_time,
+    })
+}
+
+/// Replaces the scriptSig of an input.
+pub fn set_script_sig(
+    tx: &mut Transaction,
+    index: usize,
+    script_sig: Vec<u8>,
+) -> Result<(), TxError> {
+    input_mut(tx, index)?.script_sig = script_sig;
+    Ok(())
+}
+
+/// Appends items to the witness of an input, the transaction switches to the segwit format.
+pub fn add_witness(tx: &mut Transaction, index: usize, items: Vec<Vec<u8>>) -> Result<(), TxError> {
+    input_mut(tx, index)?.witness.extend(items);
+    Ok(())
+}
+
+/// JSON breakdown of a transaction, in the shape of Bitcoin Core's `decoderawtransaction`.
+pub fn to_json(tx: &Transaction, network: Network) -> Value {
+    let inputs: Vec<Value> = tx
+        .inputs
+        .iter()
+        .map(|input| {
+            let mut value = json!({
+                "txid": input.previous_output.display_txid(),
+                "vout": input.previous_output.vout,
+                "scriptSig": {
+                    "asm": to_asm_with_sighash(&input.script_sig),
+                    "hex": hex::encode(&input.script_sig),
+                },
+            });
+            if !input.witness.is_empty() {
+                value["txinwitness"] = input.witness.iter().map(hex::encode).collect();
+            }
+            value["sequence"] = input.sequence.into();
+            value
+        })
+        .collect();
+
+    let outputs: Vec<Value> = tx
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(n, output)| {
+            let mut script_pubkey = json!({
+                "asm": to_asm(&output.script_pubkey),
+                "hex": hex::encode(&output.script_pubkey),
+                "type": classify(&output.script_pubkey).name(),
+            });
+            if let Ok(address) = Address::from_script_pubkey(&output.script_pubkey, network) {
+                script_pubkey["address"] = address.to_string().into();
+            }
+            json!({
+                "value": output.value as f64 / SATOSHIS_PER_BTC,
+                "n": n,
+                "scriptPubKey": script_pubkey,
+            })
+        })
+        .collect();
+
+    json!({
+        "txid": display_hash(&tx.txid()),
+        "hash": display_hash(&tx.wtxid()),
+        "version": tx.version,
+        "size": tx.size(),
+        "vsize": tx.vsize(),
+        "weight": tx.weight(),
+        "locktime": tx.lock_time,
+        "vin": inputs,
+        "vout": outputs,
+    })
+}
+
+fn input_mut(tx: &mut Transaction, index: usize) -> Result<&mut TxIn, TxError> {
+    let inputs = tx.inputs.len();
+    tx.inputs
+        .get_mut(index)
+        .ok_or(TxError::InputIndexOutOfRange { index, inputs })
+}
+
+/// Decimal or `0x` prefixed hex number.
+pub fn parse_u32(value: &str) -> Option<u32> {
+    match value.strip_prefix("0x") {
+        Some(hex_value) => u32::from_str_radix(hex_value, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod test_transaction {
+    use crate::errors::TxError;
+    use crate::network::Network;
+    use crate::transaction::{add_witness, create, parse_input, parse_output, to_json};
+    use bee_vm::transaction::{Transaction, SEQUENCE_FINAL};
+    use rstest::rstest;
+
+    // BIP143 native P2WPKH example, unsigned and signed.
+    const UNSIGNED: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+    const SIGNED: &str = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+    const SIGNATURE: &str = "304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01";
+    const PUBLIC_KEY: &str = "025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357";
+
+    #[rstest]
+    fn test_create_matches_bip143() -> color_eyre::Result<()> {
+        let inputs = [
+            "9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff:0:0xffffffee"
+                .to_string(),
+            "8ac60eb9575db5b2d987e29f301b5b819ea83a5c6579d282d189cc04b8e151ef:1".to_string(),
+        ];
+        let outputs = [
+            "1Cu32FVupVCgHkMMRJdYJugxwo2Aprgk7H:112340000".to_string(),
+            "76a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac:223450000".to_string(),
+        ];
+        let tx = create(1, &inputs, &outputs, 17, SEQUENCE_FINAL, Network::Mainnet)?;
+        assert_eq!(tx.to_hex(), UNSIGNED);
+        assert_eq!(tx.inputs[1].sequence, SEQUENCE_FINAL);
+        Ok(())
+    }
+
+    #[rstest]
+    fn test_add_witness_switches_format() -> color_eyre::Result<()> {
+        let signed = Transaction::from_hex(SIGNED)?;
+        let mut tx = Transaction::from_hex(UNSIGNED)?;
+        tx.inputs[0].script_sig = signed.inputs[0].script_sig.clone();
+        let txid = tx.txid();
+        add_witness(
+            &mut tx,
+            1,
+            vec![hex::decode(SIGNATURE)?, hex::decode(PUBLIC_KEY)?],
+        )?;
+        assert_eq!(tx.to_hex(), SIGNED);
+        // Witnesses are not committed to by the txid.
+        assert_eq!(tx.txid(), txid);
+
+        assert_eq!(
+            add_witness(&mut tx, 2, vec![]),
+            Err(TxError::InputIndexOutOfRange {
+                index: 2,
+                inputs: 2
+            })
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff")]
+    #[case("9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff:x")]
+    #[case("9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff:0:1:2")]
+    fn test_invalid_input(#[case] input: &str) {
+        assert_eq!(
+            parse_input(input, SEQUENCE_FINAL),
+            Err(TxError::InvalidInput(input.to_string()))
+        );
+    }
+
+    #[rstest]
+    fn test_output_network() {
+        // Mainnet address used on testnet.
+        assert!(matches!(
+            parse_output("1Cu32FVupVCgHkMMRJdYJugxwo2Aprgk7H:1", Network::Testnet),
+            Err(TxError::Address(_))
+        ));
+        assert!(parse_output("6a0568656c6c6f:0", Network::Testnet).is_ok());
+    }
+
+    #[rstest]
+    fn test_json() -> color_eyre::Result<()> {
+        let json = to_json(&Transaction::from_hex(SIGNED)?, Network::Mainnet);
+        assert_eq!(
+            json["txid"],
+            "e8151a2af31c368a35053ddd4bdb285a8595c769a3ad83e0fa02314a602d4609"
+        );
+        assert_eq!(
+            json["hash"],
+            "c36c38370907df2324d9ce9d149d191192f338b37665a82e78e76a12c909b762"
+        );
+        assert_eq!(json["size"], 343);
+        assert_eq!(json["vsize"], 261);
+        assert_eq!(json["weight"], 1042);
+        assert_eq!(json["locktime"], 17);
+        assert_eq!(json["vin"][0]["sequence"], 0xffffffee_u32);
+        assert!(json["vin"][0].get("txinwitness").is_none());
+        assert_eq!(json["vin"][1]["txinwitness"][0], SIGNATURE);
+        assert_eq!(json["vin"][1]["txinwitness"][1], PUBLIC_KEY);
+        assert_eq!(json["vout"][0]["value"], 1.1234);
+        assert_eq!(json["vout"][0]["scriptPubKey"]["type"], "pubkeyhash");
+        assert_eq!(
+            json["vout"][0]["scriptPubKey"]["address"],
+            "1Cu32FVupVCgHkMMRJdYJugxwo2Aprgk7H"
+        );
+        Ok(())
+    }
+}